@@ -4,14 +4,14 @@ use std::net::SocketAddr;
 use std::process::exit;
 
 use axum::extract::Path;
-use axum::routing::{delete, patch, post};
+use axum::routing::{delete, get, patch, post};
 use axum::{Json, Router};
 use log::*;
 
 use commons_error::{err_closure_fwd, err_fwd, log_error, log_info};
 use commons_pg::sql_transaction_async::init_db_pool_async;
 use commons_services::read_cek_and_store;
-use commons_services::token_lib::SecurityToken;
+use commons_services::token_lib::{SecurityToken, SessionToken};
 use commons_services::x_request_id::XRequestID;
 use dkconfig::conf_reader::{read_config, read_doka_env};
 use dkconfig::properties::{get_prop_pg_connect_string, get_prop_value, set_prop_values};
@@ -19,17 +19,20 @@ use dkconfig::property_name::{
     COMMON_EDIBLE_KEY_PROPERTY, LOG_CONFIG_FILE_PROPERTY, SERVER_PORT_PROPERTY,
 };
 use dkdto::{
-    CreateCustomerReply, CreateCustomerRequest, LoginReply, LoginRequest, SimpleMessage, WebType,
+    CreateCustomerReply, CreateCustomerRequest, GetUserReply, LoginReply, LoginRequest,
+    SimpleMessage, WebType,
 };
 
 use crate::customer::CustomerDelegate;
 use crate::login::LoginDelegate;
+use crate::user::UserDelegate;
 
 mod customer;
 mod dk_password;
 mod login;
 mod schema_cs;
 mod schema_fs;
+mod user;
 
 /// 0️ Login into the system with the provided credentials
 ///
@@ -91,6 +94,16 @@ pub async fn delete_customer(
     delegate.delete_customer(&customer_code).await
 }
 
+///
+/// 🌟 Find the users of the customer of the session
+/// **NORM
+///
+/// #[get("/user")]
+pub async fn get_users(session_token: SessionToken) -> WebType<GetUserReply> {
+    let delegate = UserDelegate::new(session_token, XRequestID::from_value(None));
+    delegate.get_users().await
+}
+
 /// Accept parameters from the commande line
 /// * --doka-env [optional] : the path to the .doka-config.json file (or from the DOKA_ENV environment variable)
 /// * --cluster-profile : the name of the cluster profile
@@ -174,6 +187,7 @@ async fn main() {
     let base_url = format!("/{}", PROJECT_CODE);
     let key_routes = Router::new()
         .route("/login", post(login))
+        .route("/user", get(get_users))
         .route("/customer", post(create_customer))
         .route("/customer/:customer_code", delete(delete_customer))
         .route(
@@ -23,6 +23,20 @@ CREATE INDEX document_id_idx ON document USING btree (id);
 CREATE INDEX document_language_idx ON document USING btree (lang);
 
 
+-- document_type definition
+
+-- Drop table
+
+-- DROP TABLE document_type;
+
+CREATE TABLE document_type (
+	id bigserial NOT NULL,
+	"name" varchar(50) NOT NULL,
+	CONSTRAINT document_type_name_uk UNIQUE (name),
+	CONSTRAINT document_type_pk PRIMARY KEY (id)
+);
+
+
 -- item definition
 
 -- Drop table
@@ -35,15 +49,38 @@ CREATE TABLE item (
 	created_gmt timestamp(0) NOT NULL,
 	last_modified_gmt timestamp(0) NOT NULL,
 	file_ref varchar(50) NULL,
-	CONSTRAINT item_pk PRIMARY KEY (id)
+	document_type_id int8 NULL,
+	trashed_gmt timestamp(0) NULL,
+	legal_hold bool NOT NULL DEFAULT false,
+	"version" int8 NOT NULL DEFAULT 1,
+	checked_out_session_id int8 NULL,
+	checked_out_user_id int8 NULL,
+	checkout_expiry_gmt timestamp(0) NULL,
+	CONSTRAINT item_pk PRIMARY KEY (id),
+	CONSTRAINT fk_item_document_type_id FOREIGN KEY (document_type_id) REFERENCES document_type(id)
 );
+CREATE INDEX item_document_type_idx ON item USING btree (document_type_id);
 CREATE INDEX item_created_idx ON item USING btree (created_gmt);
 CREATE UNIQUE INDEX item_file_ref_idx ON item USING btree (file_ref);
 CREATE INDEX item_last_modified_idx ON item USING btree (last_modified_gmt);
+CREATE INDEX item_trashed_idx ON item USING btree (trashed_gmt);
 CREATE INDEX item_name_btree_idx ON item USING btree (public.unaccent_lower((name)::text) COLLATE "C");
 CREATE INDEX item_name_gin_idx ON item USING gin (public.unaccent_lower((name)::text) public.gin_trgm_ops);
 
 
+-- customer_setting definition
+
+-- Drop table
+
+-- DROP TABLE customer_setting;
+
+CREATE TABLE customer_setting (
+	setting_key varchar(50) NOT NULL,
+	setting_value varchar(255) NOT NULL,
+	CONSTRAINT customer_setting_pk PRIMARY KEY (setting_key)
+);
+
+
 -- preview definition
 
 -- Drop table
@@ -75,12 +112,56 @@ CREATE TABLE tag_definition (
 	"type" varchar(25) NOT NULL,
 	string_tag_length int4 NULL,
 	default_value varchar(255) NULL,
+	multi_valued bool NOT NULL DEFAULT false,
+	min_value varchar(50) NULL,
+	max_value varchar(50) NULL,
+	pattern varchar(255) NULL,
+	required bool NOT NULL DEFAULT false,
 	CONSTRAINT length_limit CHECK (((string_tag_length >= 0) AND (string_tag_length <= 10000000))),
 	CONSTRAINT tag_name_uk UNIQUE (name),
 	CONSTRAINT tag_pk PRIMARY KEY (id)
 );
 
 
+-- document_type_tag definition
+
+-- Drop table
+
+-- DROP TABLE document_type_tag;
+
+CREATE TABLE document_type_tag (
+	id bigserial NOT NULL,
+	document_type_id int8 NOT NULL,
+	tag_id int8 NOT NULL,
+	required bool NOT NULL DEFAULT false,
+	default_value varchar(255) NULL,
+	sort_order int4 NOT NULL,
+	CONSTRAINT document_type_tag_pk PRIMARY KEY (id),
+	CONSTRAINT document_type_tag_uk UNIQUE (document_type_id, tag_id),
+	CONSTRAINT fk_document_type_tag_type_id FOREIGN KEY (document_type_id) REFERENCES document_type(id),
+	CONSTRAINT fk_document_type_tag_tag_id FOREIGN KEY (tag_id) REFERENCES tag_definition(id)
+);
+CREATE INDEX document_type_tag_sort_idx ON document_type_tag USING btree (document_type_id, sort_order);
+
+
+-- tag_enum_value definition
+
+-- Drop table
+
+-- DROP TABLE tag_enum_value;
+
+CREATE TABLE tag_enum_value (
+	id bigserial NOT NULL,
+	tag_id int8 NOT NULL,
+	value varchar(255) NOT NULL,
+	sort_order int4 NOT NULL,
+	CONSTRAINT tag_enum_value_pk PRIMARY KEY (id),
+	CONSTRAINT tag_enum_value_uk UNIQUE (tag_id, value),
+	CONSTRAINT fk_tag_enum_value_tag_id FOREIGN KEY (tag_id) REFERENCES tag_definition(id)
+);
+CREATE INDEX tag_enum_value_tag_sort_idx ON tag_enum_value USING btree (tag_id, sort_order);
+
+
 -- tag_value definition
 
 -- Drop table
@@ -97,6 +178,7 @@ CREATE TABLE tag_value (
 	value_date date NULL,
 	value_datetime timestamp(0) NULL,
 	value_boolean bool NULL,
+	modified_gmt timestamp(0) NULL,
 	CONSTRAINT tag_value_pk PRIMARY KEY (id),
 	CONSTRAINT fk_tag_value_item_id FOREIGN KEY (item_id) REFERENCES item(id)
 );
@@ -107,16 +189,260 @@ CREATE INDEX tag_value_integer_idx ON tag_value USING btree (value_integer);
 CREATE INDEX tag_value_str_like_gin_idx ON tag_value USING gin (public.unaccent_lower((value_string)::text) public.gin_trgm_ops);
 CREATE INDEX tag_value_str_sort_btree_idx ON tag_value USING btree (public.unaccent_lower((value_string)::text) COLLATE "C");
 
-CREATE UNIQUE INDEX tag_value_tag_item_udx ON tag_value  USING btree (tag_id, item_id);
+CREATE INDEX tag_value_tag_item_idx ON tag_value  USING btree (tag_id, item_id);
+
+
+-- tag_value_history definition
+
+-- Drop table
+
+-- DROP TABLE tag_value_history;
 
+CREATE TABLE tag_value_history (
+	id bigserial NOT NULL,
+	tag_value_id int8 NOT NULL,
+	item_id int8 NOT NULL,
+	tag_id int8 NOT NULL,
+	operation varchar(10) NOT NULL,
+	old_value varchar(2000) NULL,
+	new_value varchar(2000) NULL,
+	user_id int8 NOT NULL,
+	changed_gmt timestamp(0) NOT NULL,
+	CONSTRAINT tag_value_history_pk PRIMARY KEY (id)
+);
+CREATE INDEX tag_value_history_item_idx ON tag_value_history USING btree (item_id, changed_gmt);
+
+
+-- item_version definition
+
+-- Drop table
+
+-- DROP TABLE item_version;
+
+CREATE TABLE item_version (
+	id bigserial NOT NULL,
+	item_id int8 NOT NULL,
+	version_no int4 NOT NULL,
+	file_ref varchar(50) NOT NULL,
+	"comment" varchar(500) NULL,
+	user_id int8 NULL,
+	created_gmt timestamp(0) NOT NULL,
+	CONSTRAINT item_version_pk PRIMARY KEY (id),
+	CONSTRAINT item_version_no_uk UNIQUE (item_id, version_no),
+	CONSTRAINT item_version_file_ref_uk UNIQUE (file_ref),
+	CONSTRAINT fk_item_version_item_id FOREIGN KEY (item_id) REFERENCES item(id)
+);
+
+
+-- retention_rule definition
+
+-- Drop table
+
+-- DROP TABLE retention_rule;
 
-CREATE OR REPLACE PROCEDURE insert_document(file_ref character varying, part_no integer, doc_text character varying, tsv character varying, lang character varying)
+CREATE TABLE retention_rule (
+	id bigserial NOT NULL,
+	"name" varchar(50) NOT NULL,
+	filters varchar(2000) NOT NULL,
+	start_from varchar(25) NOT NULL,
+	period varchar(20) NOT NULL,
+	CONSTRAINT retention_rule_name_uk UNIQUE (name),
+	CONSTRAINT retention_rule_pk PRIMARY KEY (id)
+);
+
+
+-- destruction_certificate definition
+
+-- Drop table
+
+-- DROP TABLE destruction_certificate;
+
+CREATE TABLE destruction_certificate (
+	id bigserial NOT NULL,
+	item_id int8 NOT NULL,
+	item_name varchar(255) NOT NULL,
+	file_ref varchar(50) NULL,
+	rule_name varchar(50) NOT NULL,
+	expired_gmt timestamp(0) NOT NULL,
+	destroyed_gmt timestamp(0) NOT NULL,
+	user_id int8 NOT NULL,
+	CONSTRAINT destruction_certificate_pk PRIMARY KEY (id)
+);
+CREATE INDEX destruction_certificate_destroyed_idx ON destruction_certificate USING btree (destroyed_gmt);
+
+
+-- item_comment definition
+
+-- Drop table
+
+-- DROP TABLE item_comment;
+
+CREATE TABLE item_comment (
+	id bigserial NOT NULL,
+	item_id int8 NOT NULL,
+	parent_id int8 NULL,
+	user_id int8 NOT NULL,
+	comment_text text NOT NULL,
+	tsv tsvector NOT NULL,
+	lang varchar(20) NOT NULL,
+	page_no int4 NULL,
+	text_offset int4 NULL,
+	created_gmt timestamp(0) NOT NULL,
+	edited_gmt timestamp(0) NULL,
+	deleted_gmt timestamp(0) NULL,
+	CONSTRAINT item_comment_pk PRIMARY KEY (id),
+	CONSTRAINT fk_item_comment_item_id FOREIGN KEY (item_id) REFERENCES item(id),
+	CONSTRAINT fk_item_comment_parent_id FOREIGN KEY (parent_id) REFERENCES item_comment(id)
+);
+CREATE INDEX item_comment_item_idx ON item_comment USING btree (item_id, created_gmt);
+CREATE INDEX item_comment_ftsv_idx ON item_comment USING gin (tsv);
+
+
+-- item_comment_mention definition
+
+-- Drop table
+
+-- DROP TABLE item_comment_mention;
+
+CREATE TABLE item_comment_mention (
+	comment_id int8 NOT NULL,
+	user_id int8 NOT NULL,
+	CONSTRAINT item_comment_mention_pk PRIMARY KEY (comment_id, user_id),
+	CONSTRAINT fk_item_comment_mention_comment_id FOREIGN KEY (comment_id) REFERENCES item_comment(id)
+);
+CREATE INDEX item_comment_mention_user_idx ON item_comment_mention USING btree (user_id);
+
+
+-- fulltext_stop_word definition
+
+-- Drop table
+
+-- DROP TABLE fulltext_stop_word;
+
+CREATE TABLE fulltext_stop_word (
+	id bigserial NOT NULL,
+	word varchar(100) NOT NULL,
+	CONSTRAINT fulltext_stop_word_pk PRIMARY KEY (id),
+	CONSTRAINT fulltext_stop_word_uk UNIQUE (word)
+);
+
+
+-- fulltext_synonym definition
+
+-- Drop table
+
+-- DROP TABLE fulltext_synonym;
+
+CREATE TABLE fulltext_synonym (
+	id bigserial NOT NULL,
+	group_no int4 NOT NULL,
+	word varchar(100) NOT NULL,
+	CONSTRAINT fulltext_synonym_pk PRIMARY KEY (id)
+);
+CREATE INDEX fulltext_synonym_group_idx ON fulltext_synonym USING btree (group_no);
+
+
+-- fulltext_term_stat definition
+
+-- Drop table
+
+-- DROP TABLE fulltext_term_stat;
+
+CREATE TABLE fulltext_term_stat (
+	lexeme varchar(100) NOT NULL,
+	doc_count int4 NOT NULL,
+	CONSTRAINT fulltext_term_stat_pk PRIMARY KEY (lexeme)
+);
+
+
+-- document_keyword definition
+
+-- Drop table
+
+-- DROP TABLE document_keyword;
+
+CREATE TABLE document_keyword (
+	id bigserial NOT NULL,
+	file_ref varchar(50) NOT NULL,
+	lexeme varchar(100) NOT NULL,
+	term_count int4 NOT NULL,
+	CONSTRAINT document_keyword_pk PRIMARY KEY (id),
+	CONSTRAINT document_keyword_uk UNIQUE (file_ref, lexeme)
+);
+
+
+-- classification_rule definition
+
+-- Drop table
+
+-- DROP TABLE classification_rule;
+
+CREATE TABLE classification_rule (
+	id bigserial NOT NULL,
+	"name" varchar(50) NOT NULL,
+	"condition" varchar(2000) NOT NULL,
+	actions varchar(2000) NOT NULL,
+	CONSTRAINT classification_rule_name_uk UNIQUE (name),
+	CONSTRAINT classification_rule_pk PRIMARY KEY (id)
+);
+
+
+-- file_classification definition
+
+-- Drop table
+
+-- DROP TABLE file_classification;
+
+CREATE TABLE file_classification (
+	id bigserial NOT NULL,
+	file_ref varchar(50) NOT NULL,
+	rule_name varchar(50) NOT NULL,
+	tag_name varchar(25) NOT NULL,
+	value varchar(255) NOT NULL,
+	CONSTRAINT file_classification_pk PRIMARY KEY (id)
+);
+CREATE INDEX file_classification_file_ref_idx ON file_classification USING btree (file_ref);
+
+CREATE OR REPLACE PROCEDURE insert_documents(parts character varying)
  LANGUAGE sql
 AS $procedure$
    INSERT INTO {customer_schema}.document  ( FILE_REF,  PART_NO, DOC_TEXT, TSV, LANG )
-        VALUES ( FILE_REF, PART_NO, DOC_TEXT,
+        SELECT P.FILE_REF, P.PART_NO, P.DOC_TEXT,
+				P.TSV :: TSVECTOR
+				,  P.LANG
+        FROM jsonb_to_recordset( insert_documents.PARTS :: jsonb )
+            AS P ( FILE_REF character varying, PART_NO integer, DOC_TEXT character varying, TSV character varying, LANG character varying );
+$procedure$
+;
+
+CREATE OR REPLACE PROCEDURE update_document_tsv(document_id bigint, tsv character varying)
+ LANGUAGE sql
+AS $procedure$
+   UPDATE {customer_schema}.document
+        SET TSV = update_document_tsv.TSV :: TSVECTOR
+        WHERE ID = update_document_tsv.DOCUMENT_ID;
+$procedure$
+;
+
+CREATE OR REPLACE PROCEDURE insert_item_comment(item_id bigint, parent_id bigint, user_id bigint, comment_text character varying, tsv character varying, lang character varying, page_no integer, text_offset integer, created_gmt timestamp)
+ LANGUAGE sql
+AS $procedure$
+   INSERT INTO {customer_schema}.item_comment ( ITEM_ID, PARENT_ID, USER_ID, COMMENT_TEXT, TSV, LANG, PAGE_NO, TEXT_OFFSET, CREATED_GMT )
+        VALUES ( ITEM_ID, PARENT_ID, USER_ID, COMMENT_TEXT,
 				TSV :: TSVECTOR
-				,  LANG );
+				, LANG, PAGE_NO, TEXT_OFFSET, CREATED_GMT );
+$procedure$
+;
+
+CREATE OR REPLACE PROCEDURE update_item_comment(comment_id bigint, comment_text character varying, tsv character varying, lang character varying, edited_gmt timestamp)
+ LANGUAGE sql
+AS $procedure$
+   UPDATE {customer_schema}.item_comment
+        SET COMMENT_TEXT = update_item_comment.COMMENT_TEXT,
+            TSV = update_item_comment.TSV :: TSVECTOR,
+            LANG = update_item_comment.LANG,
+            EDITED_GMT = update_item_comment.EDITED_GMT
+        WHERE ID = update_item_comment.COMMENT_ID;
 $procedure$
 ;
 
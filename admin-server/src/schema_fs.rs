@@ -16,6 +16,7 @@ CREATE TABLE file_reference (
 	is_encrypted bool NOT NULL,
 	is_fulltext_parsed bool NULL,
 	is_preview_generated bool NULL,
+	item_info varchar(50) NULL,
 	CONSTRAINT file_reference_pk PRIMARY KEY (id),
 	CONSTRAINT file_reference_uk UNIQUE (file_ref)
 );
@@ -53,4 +54,28 @@ CREATE TABLE file_metadata (
 );
 CREATE UNIQUE INDEX ref_meta_udx ON file_metadata USING btree (file_reference_id, meta_key);
 
+CREATE TABLE reindex_job (
+	id bigserial NOT NULL,
+	pattern varchar(50) NOT NULL,
+	filters text NULL,
+	status varchar(10) NOT NULL,
+	file_count int8 NOT NULL,
+	done_count int8 NOT NULL,
+	failed_count int8 NOT NULL,
+	last_file_id int8 NOT NULL,
+	last_file_ref varchar(50) NULL,
+	start_gmt timestamp NOT NULL,
+	end_gmt timestamp NULL,
+	CONSTRAINT reindex_job_pk PRIMARY KEY (id)
+);
+
+CREATE TABLE reindex_job_file (
+	id bigserial NOT NULL,
+	job_id int8 NOT NULL,
+	file_ref varchar(50) NOT NULL,
+	CONSTRAINT reindex_job_file_pk PRIMARY KEY (id),
+	CONSTRAINT reindex_job_file_job_fk FOREIGN KEY (job_id) REFERENCES reindex_job(id)
+);
+CREATE UNIQUE INDEX reindex_job_file_udx ON reindex_job_file (job_id, file_ref);
+
     "#;
@@ -0,0 +1,143 @@
+use std::collections::HashMap;
+
+use anyhow::anyhow;
+use axum::http::StatusCode;
+use log::*;
+use serde::de::DeserializeOwned;
+
+use commons_error::*;
+use commons_pg::sql_transaction::CellValue;
+use commons_pg::sql_transaction_async::{
+    SQLConnectionAsync, SQLQueryBlockAsync, SQLTransactionAsync,
+};
+use commons_services::session_lib::valid_sid_get_session;
+use commons_services::token_lib::SessionToken;
+use commons_services::try_or_return;
+use commons_services::x_request_id::{Follower, XRequestID};
+use dkdto::error_codes::INTERNAL_DATABASE_ERROR;
+use dkdto::{ErrorSet, GetUserReply, UserElement, WebType, WebTypeBuilder};
+use doka_cli::request_client::TokenType;
+
+pub(crate) struct UserDelegate {
+    pub session_token: SessionToken,
+    pub follower: Follower,
+}
+
+impl UserDelegate {
+    pub fn new(session_token: SessionToken, x_request_id: XRequestID) -> Self {
+        Self {
+            session_token,
+            follower: Follower {
+                x_request_id: x_request_id.new_if_null(),
+                token_type: TokenType::None,
+            },
+        }
+    }
+
+    ///
+    /// 🌟 Find all the users of the customer of the session
+    ///
+    pub async fn get_users(mut self) -> WebType<GetUserReply> {
+        log_info!("🚀 Start get_users api, follower=[{}]", &self.follower);
+
+        let entry_session = try_or_return!(
+            valid_sid_get_session(&self.session_token, &mut self.follower).await,
+            Self::web_type_error()
+        );
+
+        let Ok(mut cnx) = SQLConnectionAsync::from_pool().await.map_err(err_fwd!(
+            "💣 New Db connection failed, follower=[{}]",
+            &self.follower
+        )) else {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        };
+
+        let Ok(mut trans) = cnx.begin().await.map_err(err_fwd!(
+            "💣 Transaction issue, follower=[{}]",
+            &self.follower
+        )) else {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        };
+
+        let Ok(users) = self
+            .search_users(&mut trans, entry_session.customer_id)
+            .await
+            .map_err(err_fwd!(
+                "💣 Cannot find the users, customer_id=[{}], follower=[{}]",
+                entry_session.customer_id,
+                &self.follower
+            ))
+        else {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        };
+
+        if trans
+            .commit()
+            .await
+            .map_err(err_fwd!("💣 Commit failed, follower=[{}]", &self.follower))
+            .is_err()
+        {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        }
+
+        log_info!(
+            "😎 Found the users, count=[{}], follower=[{}]",
+            users.len(),
+            &self.follower
+        );
+        log_info!("🏁 End get_users api, follower=[{}]", &self.follower);
+
+        WebType::from_item(StatusCode::OK.as_u16(), GetUserReply { users })
+    }
+
+    async fn search_users(
+        &self,
+        trans: &mut SQLTransactionAsync<'_>,
+        customer_id: i64,
+    ) -> anyhow::Result<Vec<UserElement>> {
+        let mut params = HashMap::new();
+        params.insert(
+            "p_customer_id".to_owned(),
+            CellValue::from_raw_int(customer_id),
+        );
+
+        let query = SQLQueryBlockAsync {
+            sql_query: r"SELECT id, login, full_name FROM dokaadmin.appuser
+                        WHERE customer_id = :p_customer_id
+                        ORDER BY id "
+                .to_string(),
+            start: 0,
+            length: None,
+            params,
+        };
+
+        let mut sql_result = query.execute(trans).await.map_err(err_fwd!(
+            "💣 Query failed, [{}], follower=[{}]",
+            &query.sql_query,
+            &self.follower
+        ))?;
+
+        let mut users = vec![];
+        while sql_result.next() {
+            users.push(UserElement {
+                user_id: sql_result.get_int("id").ok_or(anyhow!("Wrong id"))?,
+                login: sql_result
+                    .get_string("login")
+                    .ok_or(anyhow!("Wrong login name"))?,
+                // The full name is optional for a user
+                full_name: sql_result.get_string("full_name").unwrap_or_default(),
+            });
+        }
+        Ok(users)
+    }
+
+    fn web_type_error<T>() -> impl Fn(&ErrorSet<'static>) -> WebType<T>
+    where
+        T: DeserializeOwned,
+    {
+        |e| {
+            log_error!("💣 Error after try {:?}", e);
+            WebType::from_errorset(e)
+        }
+    }
+}
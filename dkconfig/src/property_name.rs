@@ -11,7 +11,15 @@ pub const SESSION_MANAGER_PORT_PROPERTY: &str = "sm.port";
 pub const KEY_MANAGER_HOSTNAME_PROPERTY: &str = "km.host";
 pub const KEY_MANAGER_PORT_PROPERTY: &str = "km.port";
 
+pub const ADMIN_SERVER_HOSTNAME_PROPERTY: &str = "as.host";
+pub const ADMIN_SERVER_PORT_PROPERTY: &str = "as.port";
+
 pub const DOCUMENT_SERVER_HOSTNAME_PROPERTY: &str = "ds.host";
 pub const DOCUMENT_SERVER_PORT_PROPERTY: &str = "ds.port";
+pub const FILE_SERVER_HOSTNAME_PROPERTY: &str = "fs.host";
+pub const FILE_SERVER_PORT_PROPERTY: &str = "fs.port";
 pub const TIKA_SERVER_HOSTNAME_PROPERTY: &str = "tks.host";
-pub const TIKA_SERVER_PORT_PROPERTY: &str = "tks.port";
\ No newline at end of file
+pub const TIKA_SERVER_PORT_PROPERTY: &str = "tks.port";
+
+pub const LANGUAGE_DETECTOR_PROPERTY: &str = "ft.lang_detector";
+pub const SCHEDULER_PERIOD_PROPERTY: &str = "scheduler.period_minutes";
@@ -132,6 +132,26 @@ pub static INCORRECT_LENGTH_TAG_NAME: Lazy<ErrorSet> = Lazy::new(|| ErrorSet {
     err_message: "Tag name too long",
     http_error_code: StatusCode::BAD_REQUEST.as_u16(),
 });
+pub static INCORRECT_ENUM_ALLOWED_VALUES: Lazy<ErrorSet> = Lazy::new(|| ErrorSet {
+    err_message: "Incorrect list of allowed values",
+    http_error_code: StatusCode::BAD_REQUEST.as_u16(),
+});
+pub static INCORRECT_DEFAULT_ENUM_VALUE: Lazy<ErrorSet> = Lazy::new(|| ErrorSet {
+    err_message: "Incorrect default enum value",
+    http_error_code: StatusCode::BAD_REQUEST.as_u16(),
+});
+pub static NOT_AN_ENUM_TAG: Lazy<ErrorSet> = Lazy::new(|| ErrorSet {
+    err_message: "The tag is not an enum tag",
+    http_error_code: StatusCode::BAD_REQUEST.as_u16(),
+});
+pub static INCORRECT_TAG_CONSTRAINTS: Lazy<ErrorSet> = Lazy::new(|| ErrorSet {
+    err_message: "Incorrect tag constraints",
+    http_error_code: StatusCode::BAD_REQUEST.as_u16(),
+});
+pub static MISSING_TAG: Lazy<ErrorSet> = Lazy::new(|| ErrorSet {
+    err_message: "Missing tag",
+    http_error_code: StatusCode::NOT_FOUND.as_u16(),
+});
 
 /// Items
 pub static MISSING_ITEM: Lazy<ErrorSet> = Lazy::new(|| ErrorSet {
@@ -146,6 +166,142 @@ pub static MISSING_TAG_FOR_ITEM: Lazy<ErrorSet> = Lazy::new(|| ErrorSet {
     err_message: "Missing or Incorrect tag definition",
     http_error_code: StatusCode::BAD_REQUEST.as_u16(),
 });
+pub static INCORRECT_FILTER: Lazy<ErrorSet> = Lazy::new(|| ErrorSet {
+    err_message: "Incorrect search filter",
+    http_error_code: StatusCode::BAD_REQUEST.as_u16(),
+});
+pub static ENUM_VALUE_NOT_ALLOWED: Lazy<ErrorSet> = Lazy::new(|| ErrorSet {
+    err_message: "Value not allowed for the enum tag",
+    http_error_code: StatusCode::BAD_REQUEST.as_u16(),
+});
+pub static TAG_CONSTRAINT_VIOLATION: Lazy<ErrorSet> = Lazy::new(|| ErrorSet {
+    err_message: "Tag constraint violation",
+    http_error_code: StatusCode::BAD_REQUEST.as_u16(),
+});
+pub static INCORRECT_CSV: Lazy<ErrorSet> = Lazy::new(|| ErrorSet {
+    err_message: "Incorrect csv content",
+    http_error_code: StatusCode::BAD_REQUEST.as_u16(),
+});
+pub static INCORRECT_AS_OF_DATE: Lazy<ErrorSet> = Lazy::new(|| ErrorSet {
+    err_message: "Incorrect \"as of\" datetime, expected an ISO 8601 datetime",
+    http_error_code: StatusCode::BAD_REQUEST.as_u16(),
+});
+pub static ITEM_NOT_IN_TRASH: Lazy<ErrorSet> = Lazy::new(|| ErrorSet {
+    err_message: "The item is not in the trash",
+    http_error_code: StatusCode::BAD_REQUEST.as_u16(),
+});
+pub static INCORRECT_RETENTION_PERIOD: Lazy<ErrorSet> = Lazy::new(|| ErrorSet {
+    err_message: "Incorrect trash retention period, expected a positive number of days",
+    http_error_code: StatusCode::BAD_REQUEST.as_u16(),
+});
+pub static FILE_RELEASE_FAILED: Lazy<ErrorSet> = Lazy::new(|| ErrorSet {
+    err_message: "The file server could not release the file of the item",
+    http_error_code: StatusCode::INTERNAL_SERVER_ERROR.as_u16(),
+});
+pub static ITEM_CHECKED_OUT: Lazy<ErrorSet> = Lazy::new(|| ErrorSet {
+    err_message: "The item is checked out by another session",
+    http_error_code: StatusCode::CONFLICT.as_u16(),
+});
+pub static ITEM_VERSION_MISMATCH: Lazy<ErrorSet> = Lazy::new(|| ErrorSet {
+    err_message: "The item has been changed since the version given in If-Match",
+    http_error_code: StatusCode::PRECONDITION_FAILED.as_u16(),
+});
+pub static INCORRECT_IF_MATCH: Lazy<ErrorSet> = Lazy::new(|| ErrorSet {
+    err_message: "Incorrect If-Match header, expected an item version",
+    http_error_code: StatusCode::BAD_REQUEST.as_u16(),
+});
+pub static INCORRECT_CHECKOUT_DURATION: Lazy<ErrorSet> = Lazy::new(|| ErrorSet {
+    err_message: "Incorrect check-out duration, expected 1 to 1440 minutes",
+    http_error_code: StatusCode::BAD_REQUEST.as_u16(),
+});
+pub static MISSING_ITEM_VERSION: Lazy<ErrorSet> = Lazy::new(|| ErrorSet {
+    err_message: "Missing item version",
+    http_error_code: StatusCode::NOT_FOUND.as_u16(),
+});
+pub static FILE_REF_ALREADY_USED: Lazy<ErrorSet> = Lazy::new(|| ErrorSet {
+    err_message: "The file reference is already used by an item",
+    http_error_code: StatusCode::CONFLICT.as_u16(),
+});
+pub static INCORRECT_FILE_REF: Lazy<ErrorSet> = Lazy::new(|| ErrorSet {
+    err_message: "Incorrect file reference",
+    http_error_code: StatusCode::BAD_REQUEST.as_u16(),
+});
+pub static MISSING_DOCUMENT_TEXT: Lazy<ErrorSet> = Lazy::new(|| ErrorSet {
+    err_message: "There is no extracted text for the file",
+    http_error_code: StatusCode::NOT_FOUND.as_u16(),
+});
+pub static MISSING_COMMENT: Lazy<ErrorSet> = Lazy::new(|| ErrorSet {
+    err_message: "Missing comment",
+    http_error_code: StatusCode::NOT_FOUND.as_u16(),
+});
+pub static INCORRECT_COMMENT: Lazy<ErrorSet> = Lazy::new(|| ErrorSet {
+    err_message: "Incorrect comment, expected a text of 1 to 4000 characters and a positive anchor",
+    http_error_code: StatusCode::BAD_REQUEST.as_u16(),
+});
+pub static NOT_COMMENT_AUTHOR: Lazy<ErrorSet> = Lazy::new(|| ErrorSet {
+    err_message: "Only the author can change or delete the comment",
+    http_error_code: StatusCode::FORBIDDEN.as_u16(),
+});
+pub static UNKNOWN_MENTIONED_USER: Lazy<ErrorSet> = Lazy::new(|| ErrorSet {
+    err_message: "The mentioned user is not a user of the customer",
+    http_error_code: StatusCode::BAD_REQUEST.as_u16(),
+});
+pub static INCORRECT_FULLTEXT_QUERY: Lazy<ErrorSet> = Lazy::new(|| ErrorSet {
+    err_message: "Incorrect full text query, expected at least one word to search",
+    http_error_code: StatusCode::BAD_REQUEST.as_u16(),
+});
+pub static INCORRECT_FULLTEXT_DICTIONARY: Lazy<ErrorSet> = Lazy::new(|| {
+    ErrorSet {
+    err_message: "Incorrect full text dictionary, expected single stop words and synonym groups of at least 2 distinct words",
+    http_error_code: StatusCode::BAD_REQUEST.as_u16(),
+}
+});
+
+/// Document types
+pub static INCORRECT_DOCUMENT_TYPE: Lazy<ErrorSet> = Lazy::new(|| ErrorSet {
+    err_message: "Incorrect document type definition",
+    http_error_code: StatusCode::BAD_REQUEST.as_u16(),
+});
+pub static DOCUMENT_TYPE_NAME_ALREADY_TAKEN: Lazy<ErrorSet> = Lazy::new(|| ErrorSet {
+    err_message: "Document type name already taken",
+    http_error_code: StatusCode::CONFLICT.as_u16(),
+});
+pub static MISSING_DOCUMENT_TYPE: Lazy<ErrorSet> = Lazy::new(|| ErrorSet {
+    err_message: "Missing document type",
+    http_error_code: StatusCode::NOT_FOUND.as_u16(),
+});
+
+/// Retention
+pub static INCORRECT_RETENTION_RULE: Lazy<ErrorSet> = Lazy::new(|| ErrorSet {
+    err_message: "Incorrect retention rule definition",
+    http_error_code: StatusCode::BAD_REQUEST.as_u16(),
+});
+pub static RETENTION_RULE_NAME_ALREADY_TAKEN: Lazy<ErrorSet> = Lazy::new(|| ErrorSet {
+    err_message: "Retention rule name already taken",
+    http_error_code: StatusCode::CONFLICT.as_u16(),
+});
+pub static MISSING_RETENTION_RULE: Lazy<ErrorSet> = Lazy::new(|| ErrorSet {
+    err_message: "Missing retention rule",
+    http_error_code: StatusCode::NOT_FOUND.as_u16(),
+});
+pub static ITEM_ON_LEGAL_HOLD: Lazy<ErrorSet> = Lazy::new(|| ErrorSet {
+    err_message: "The item is on legal hold and cannot be deleted",
+    http_error_code: StatusCode::CONFLICT.as_u16(),
+});
+
+/// Classification
+pub static INCORRECT_CLASSIFICATION_RULE: Lazy<ErrorSet> = Lazy::new(|| ErrorSet {
+    err_message: "Incorrect classification rule definition",
+    http_error_code: StatusCode::BAD_REQUEST.as_u16(),
+});
+pub static CLASSIFICATION_RULE_NAME_ALREADY_TAKEN: Lazy<ErrorSet> = Lazy::new(|| ErrorSet {
+    err_message: "Classification rule name already taken",
+    http_error_code: StatusCode::CONFLICT.as_u16(),
+});
+pub static MISSING_CLASSIFICATION_RULE: Lazy<ErrorSet> = Lazy::new(|| ErrorSet {
+    err_message: "Missing classification rule",
+    http_error_code: StatusCode::NOT_FOUND.as_u16(),
+});
 
 /// Customer
 pub static CUSTOMER_NAME_ALREADY_TAKEN: Lazy<ErrorSet> = Lazy::new(|| ErrorSet {
@@ -175,6 +331,20 @@ pub static FILE_INFO_NOT_FOUND: Lazy<ErrorSet> = Lazy::new(|| ErrorSet {
     http_error_code: StatusCode::NOT_FOUND.as_u16(),
 });
 
+/// Reindex
+pub static INCORRECT_REINDEX_PATTERN: Lazy<ErrorSet> = Lazy::new(|| ErrorSet {
+    err_message: "Incorrect file pattern, only letters, digits, '-' and '*' are allowed",
+    http_error_code: StatusCode::BAD_REQUEST.as_u16(),
+});
+pub static REINDEX_JOB_NOT_FOUND: Lazy<ErrorSet> = Lazy::new(|| ErrorSet {
+    err_message: "Reindex job not found",
+    http_error_code: StatusCode::NOT_FOUND.as_u16(),
+});
+pub static REINDEX_JOB_RUNNING: Lazy<ErrorSet> = Lazy::new(|| ErrorSet {
+    err_message: "The reindex job is running",
+    http_error_code: StatusCode::CONFLICT.as_u16(),
+});
+
 pub static HTTP_CLIENT_ERROR: Lazy<ErrorSet> = Lazy::new(|| ErrorSet {
     err_message: "Http Client Error",
     http_error_code: StatusCode::BAD_REQUEST.as_u16(),
@@ -244,6 +244,19 @@ pub struct LoginReply {
     pub customer_code: String,
 }
 
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct UserElement {
+    pub user_id: i64,
+    pub login: String,
+    pub full_name: String,
+}
+
+/// The users of the customer of the session
+#[derive(Serialize, Deserialize, Debug)]
+pub struct GetUserReply {
+    pub users: Vec<UserElement>,
+}
+
 ///
 /// Document Server
 ///
@@ -257,6 +270,7 @@ pub enum EnumTagValue {
     SimpleDate(Option<String>),
     DateTime(Option<String>), // "1970-03-23T23:04:10.236Z"
     Link(Option<String>),
+    Enumeration(Option<String>),
 }
 
 impl EnumTagValue {
@@ -269,6 +283,7 @@ impl EnumTagValue {
             EnumTagValue::SimpleDate(v) => v.clone().unwrap_or("".to_string()).to_string(),
             EnumTagValue::DateTime(v) => v.clone().unwrap_or("".to_string()).to_string(),
             EnumTagValue::Link(v) => v.clone().unwrap_or("".to_string()).to_string(),
+            EnumTagValue::Enumeration(v) => v.clone().unwrap_or("".to_string()).to_string(),
         }
     }
 
@@ -296,6 +311,7 @@ impl EnumTagValue {
                 Err(e) => Err(format!("Bad datetime value: {}", e.to_string())),
             },
             TAG_TYPE_LINK => Ok(Self::Link(Some(tag_value.to_owned()))),
+            TAG_TYPE_ENUM => Ok(Self::Enumeration(Some(tag_value.to_owned()))),
             _ => Err(format!("Bad type: {}", tag_type)),
         }
     }
@@ -306,6 +322,7 @@ pub struct AddItemRequest {
     pub name: String,
     pub file_ref: Option<String>, // file reference to be associated to the item
     pub properties: Option<Vec<AddTagValue>>,
+    pub document_type: Option<String>, // name of the document type, its missing tags get their default value
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -314,7 +331,9 @@ pub struct AddItemTagRequest {
     pub properties: Vec<AddTagValue>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+/// For a multi-valued tag, each AddTagValue adds one value to the list of the item,
+/// so several values are given by repeating the tag.
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct AddTagValue {
     pub tag_id: Option<i64>, // TODO, not used for now, check if it's usefull or not
     pub tag_name: Option<String>,
@@ -324,6 +343,66 @@ pub struct AddTagValue {
 #[derive(Debug)]
 pub struct DeleteTagsRequest(pub Vec<String>);
 
+/// Tag changes applied to all the items matching the filter.
+/// The deleted tags lose all their values, the deleted values are removed one by one.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct BulkTagRequest {
+    pub filters: String,
+    pub properties: Option<Vec<AddTagValue>>, // Tags added or changed on the items
+    pub deleted_tags: Option<Vec<String>>,
+    pub deleted_values: Option<Vec<DeletedTagValue>>,
+    pub dry_run: Option<bool>, // Only count the items, default false
+}
+
+/// One value of a tag, in its text form
+#[derive(Serialize, Deserialize, Debug)]
+pub struct DeletedTagValue {
+    pub tag_name: String,
+    pub value: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct BulkTagReply {
+    pub item_count: u64,
+    pub dry_run: bool,
+}
+
+/// The first line of the csv holds the column names, one column is the item key ([match_by]),
+/// the other columns are tag names. An empty cell leaves the tag unchanged.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ImportCsvRequest {
+    pub csv: String,
+    pub match_by: Option<String>, // "name" (default) or "file_ref"
+    pub dry_run: Option<bool>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CsvLineError {
+    pub line: u64,
+    pub message: String,
+}
+
+/// No item is changed when there is an error
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ImportCsvReply {
+    pub row_count: u64,
+    pub item_count: u64,
+    pub errors: Vec<CsvLineError>,
+    pub dry_run: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ExportCsvReply {
+    pub item_count: u64,
+    pub csv: String,
+}
+
+/// The file references of the items matching a filter
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ItemFileRefsReply {
+    pub file_refs: Vec<String>,
+}
+
 // #[derive(Serialize, Deserialize, Debug)]
 // pub struct FilterCondition {
 //     pub tag: String,
@@ -360,8 +439,20 @@ pub struct ItemElement {
     pub created: String,
     pub last_modified: Option<String>,
     pub properties: Option<Vec<TagValueElement>>,
+    pub document_type: Option<String>,
+    #[serde(default)]
+    pub legal_hold: bool,
+    /// Bumped on each change of the item, to be sent back in the If-Match header
+    #[serde(default)]
+    pub version: i64,
+    /// Only set while the item is checked out
+    #[serde(default)]
+    pub checked_out_by: Option<i64>,
+    #[serde(default)]
+    pub checkout_expiry: Option<String>,
 }
 
+/// A multi-valued tag is returned as one TagValueElement per value, all with the same tag_id.
 #[derive(Serialize, Deserialize, Debug)]
 pub struct TagValueElement {
     pub tag_value_id: i64,
@@ -371,6 +462,143 @@ pub struct TagValueElement {
     pub value: EnumTagValue,
 }
 
+/// One change of a tag value on an item, the values are in their text form.
+/// The operation is "insert", "update" or "delete".
+#[derive(Serialize, Deserialize, Debug)]
+pub struct TagHistoryElement {
+    pub tag_value_id: i64,
+    pub tag_id: i64,
+    pub tag_name: String,
+    pub tag_type: String,
+    pub operation: String,
+    pub old_value: Option<String>,
+    pub new_value: Option<String>,
+    pub user_id: i64,
+    pub changed: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct GetItemHistoryReply {
+    pub item_id: i64,
+    pub history: Vec<TagHistoryElement>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TrashElement {
+    pub item_id: i64,
+    pub name: String,
+    pub file_ref: Option<String>,
+    pub document_type: Option<String>,
+    pub trashed: String,
+    pub purge_after: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct GetTrashReply {
+    pub retention_days: i32,
+    pub items: Vec<TrashElement>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct TrashRetentionRequest {
+    pub retention_days: i32,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct TrashRetentionReply {
+    pub retention_days: i32,
+}
+
+/// Without duration, the item is checked out for 60 minutes
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CheckOutRequest {
+    pub duration_minutes: Option<i32>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CheckOutReply {
+    pub item_id: i64,
+    pub user_id: i64,
+    pub expiry: String,
+    pub version: i64,
+}
+
+/// The current version is the one whose file is the file of the item.
+/// The author is unknown for the first version of the items created before the versions.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ItemVersionElement {
+    pub version_no: i32,
+    pub file_ref: String,
+    pub comment: Option<String>,
+    pub user_id: Option<i64>,
+    pub created: String,
+    pub current: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct GetItemVersionReply {
+    pub item_id: i64,
+    pub versions: Vec<ItemVersionElement>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct AddItemVersionRequest {
+    pub file_ref: String,
+    pub comment: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct AddItemVersionReply {
+    pub item_id: i64,
+    pub version_no: i32,
+}
+
+/// A comment can be anchored to a page and/or a text offset of the file of the item.
+/// The text of a deleted comment is empty, it stays in the list to keep the thread of its replies.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ItemCommentElement {
+    pub comment_id: i64,
+    pub parent_id: Option<i64>,
+    pub user_id: i64,
+    pub text: String,
+    pub mentions: Vec<i64>,
+    pub page: Option<i32>,
+    pub text_offset: Option<i32>,
+    pub created: String,
+    pub edited: Option<String>,
+    pub deleted: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct GetItemCommentReply {
+    pub item_id: i64,
+    pub comments: Vec<ItemCommentElement>,
+}
+
+/// The mentions are the user ids of other users of the customer
+#[derive(Serialize, Deserialize, Debug)]
+pub struct AddItemCommentRequest {
+    pub text: String,
+    pub parent_id: Option<i64>,
+    #[serde(default)]
+    pub mentions: Vec<i64>,
+    pub page: Option<i32>,
+    pub text_offset: Option<i32>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct AddItemCommentReply {
+    pub item_id: i64,
+    pub comment_id: i64,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct UpdateItemCommentRequest {
+    pub text: String,
+    #[serde(default)]
+    pub mentions: Vec<i64>,
+}
+
 // Tag
 
 const TAG_TYPE_TEXT: &str = "text";
@@ -380,6 +608,7 @@ const TAG_TYPE_DOUBLE: &str = "decimal";
 const TAG_TYPE_DATE: &str = "date";
 const TAG_TYPE_DATETIME: &str = "datetime";
 const TAG_TYPE_LINK: &str = "link";
+const TAG_TYPE_ENUM: &str = "enum";
 
 #[derive(Debug, PartialEq, Eq, Hash)]
 pub enum TagType {
@@ -390,6 +619,7 @@ pub enum TagType {
     Date,
     DateTime,
     Link,
+    Enum,
 }
 
 impl TagType {
@@ -402,6 +632,7 @@ impl TagType {
             TagType::Date => TAG_TYPE_DATE,
             TagType::DateTime => TAG_TYPE_DATETIME,
             TagType::Link => TAG_TYPE_LINK,
+            TagType::Enum => TAG_TYPE_ENUM,
         }
     }
 }
@@ -418,6 +649,7 @@ impl FromStr for TagType {
             TAG_TYPE_DATE => Ok(TagType::Date),
             TAG_TYPE_DATETIME => Ok(TagType::DateTime),
             TAG_TYPE_LINK => Ok(TagType::Link),
+            TAG_TYPE_ENUM => Ok(TagType::Enum),
             _ => Err(()),
         }
     }
@@ -429,12 +661,27 @@ impl fmt::Display for TagType {
     }
 }
 
+///
+/// Constraints on the values of a tag, checked each time a value is set on an item
+///
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct TagConstraints {
+    pub min_value: Option<String>, // Only for integer, double and date
+    pub max_value: Option<String>, // Only for integer, double and date
+    pub pattern: Option<String>,   // Regex the whole value must match, only for string
+    pub max_length: Option<i32>,   // Only for string, default 2000
+    pub required: Option<bool>,    // Default false
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct AddTagRequest {
     pub name: String,
-    pub tag_type: String, // string, bool, integer, double, date, datetime
+    pub tag_type: String, // string, bool, integer, double, date, datetime, enum
 
     pub default_value: Option<String>,
+    pub allowed_values: Option<Vec<String>>, // Ordered list of values, only for the enum type
+    pub multi_valued: Option<bool>,          // Default false
+    pub constraints: Option<TagConstraints>,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -451,17 +698,268 @@ pub struct GetTagReply {
 pub struct TagElement {
     pub tag_id: i64,
     pub name: String,
-    pub tag_type: String, // string, bool, integer, double, date, datetime
+    pub tag_type: String, // string, bool, integer, double, date, datetime, enum
+    pub default_value: Option<String>,
+    pub allowed_values: Option<Vec<String>>,
+    pub multi_valued: bool,
+    pub constraints: TagConstraints,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EnumValueRename {
+    pub old_value: String,
+    pub new_value: String,
+}
+
+/// The new ordered list of allowed values replaces the existing one.
+/// The renamed values are propagated to the tag values of the items.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct UpdateEnumValuesRequest {
+    pub allowed_values: Vec<String>,
+    pub renames: Option<Vec<EnumValueRename>>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct GetEnumValuesReply {
+    pub tag_id: i64,
+    pub name: String,
+    pub allowed_values: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct TagStatsReply {
+    pub tag_id: i64,
+    pub name: String,
+    pub tag_type: String,
+    pub item_count: i64,           // Items holding at least one value of the tag
+    pub distinct_value_count: i64, //
+    pub min_value: Option<String>, // In the same format as the tag values
+    pub max_value: Option<String>,
+    pub last_used: Option<String>, // Last time a value was set, ISO8601
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct TagValueCount {
+    pub value: String,
+    pub item_count: i64,
+}
+
+/// The distinct values of a tag, the most used first
+#[derive(Serialize, Deserialize, Debug)]
+pub struct GetTagValuesReply {
+    pub tag_id: i64,
+    pub name: String,
+    pub values: Vec<TagValueCount>,
+}
+
+// Document types
+
+/// A tag of a document type, its default value overrides the default value of the tag definition
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct DocumentTypeTag {
+    pub tag_name: String,
+    pub required: Option<bool>, // Default false
+    pub default_value: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct AddDocumentTypeRequest {
+    pub name: String,
+    pub tags: Vec<DocumentTypeTag>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct AddDocumentTypeReply {
+    pub document_type_id: i64,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct DocumentTypeTagElement {
+    pub tag_id: i64,
+    pub tag_name: String,
+    pub tag_type: String,
+    pub required: bool,
     pub default_value: Option<String>,
 }
 
+#[derive(Serialize, Deserialize, Debug)]
+pub struct DocumentTypeElement {
+    pub document_type_id: i64,
+    pub name: String,
+    pub tags: Vec<DocumentTypeTagElement>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct GetDocumentTypeReply {
+    pub document_types: Vec<DocumentTypeElement>,
+}
+
+// Retention
+
+/// The items matching the filter are destroyed once the period has passed since [start_from].
+/// [start_from] is "@created" or the name of a date or datetime tag,
+/// the period is a number of years, months or days, ex: "10 years", "6m", "30d".
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RetentionRuleElement {
+    pub rule_id: i64,
+    pub name: String,
+    pub filters: String,
+    pub start_from: String,
+    pub period: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct AddRetentionRuleRequest {
+    pub name: String,
+    pub filters: String,
+    pub start_from: String,
+    pub period: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct AddRetentionRuleReply {
+    pub rule_id: i64,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct GetRetentionRuleReply {
+    pub rules: Vec<RetentionRuleElement>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct LegalHoldRequest {
+    pub legal_hold: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ApplyRetentionRequest {
+    pub dry_run: Option<bool>, // Only report the expired items, default true
+}
+
+/// An item whose retention period is over, the items on legal hold are reported but never destroyed
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ExpiredItemElement {
+    pub item_id: i64,
+    pub name: String,
+    pub file_ref: Option<String>,
+    pub rule_name: String,
+    pub expired: String,
+    pub legal_hold: bool,
+    pub destroyed: bool,
+    pub error: Option<String>, // Reason why the item could not be destroyed
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ApplyRetentionReply {
+    pub dry_run: bool,
+    pub items: Vec<ExpiredItemElement>,
+    pub destroyed_count: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct DestructionCertificateElement {
+    pub certificate_id: i64,
+    pub item_id: i64,
+    pub item_name: String,
+    pub file_ref: Option<String>,
+    pub rule_name: String,
+    pub expired: String,
+    pub destroyed: String,
+    pub user_id: i64,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct GetDestructionCertificateReply {
+    pub certificates: Vec<DestructionCertificateElement>,
+}
+
+// Classification
+
+/// Value given to a tag of the items of the file
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ClassificationAction {
+    pub tag_name: String,
+    pub value: String,
+}
+
+/// When a new file matches the condition, the actions are applied to its items.
+/// The condition is a filter on the attributes of the file : "mime", "file_name", "text"
+/// and the metadata keys with "_" instead of the special characters, ex: "meta_author" for "meta:author",
+/// ex: (mime == "application/pdf" AND text LIKE "%invoice%" AND meta_author == "X")
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ClassificationRuleElement {
+    pub rule_id: i64,
+    pub name: String,
+    pub condition: String,
+    pub actions: Vec<ClassificationAction>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct AddClassificationRuleRequest {
+    pub name: String,
+    pub condition: String,
+    pub actions: Vec<ClassificationAction>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct AddClassificationRuleReply {
+    pub rule_id: i64,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct GetClassificationRuleReply {
+    pub rules: Vec<ClassificationRuleElement>,
+}
+
+/// Attributes of a new file, sent by the file server once the text of the file is indexed
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ClassifyFileRequest {
+    pub file_ref: String,
+    pub file_name: String,
+    pub media_type: String,
+    pub metadata: HashMap<String, String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ClassifyFileReply {
+    pub file_ref: String,
+    pub rule_names: Vec<String>, // Rules matching the file
+    pub item_count: u64,         // Items of the file already tagged
+}
+
+/// Test a condition on the most recent documents, [limit] documents by default 100
+#[derive(Serialize, Deserialize, Debug)]
+pub struct DryRunClassificationRequest {
+    pub condition: String,
+    pub limit: Option<u32>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ClassifiedItemElement {
+    pub item_id: i64,
+    pub name: String,
+    pub file_ref: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct DryRunClassificationReply {
+    pub checked_count: u32,
+    pub items: Vec<ClassifiedItemElement>,
+}
+
 // Full text
 
+/// First line of the NDJSON body of the full text indexing
 #[derive(Serialize, Deserialize, Debug)]
-pub struct FullTextRequest {
+pub struct FullTextStreamHeader {
     pub file_name: String,
     pub file_ref: String,
-    pub raw_text: String,
+}
+
+/// Next lines of the NDJSON body of the full text indexing, the chunks do not split the words
+#[derive(Serialize, Deserialize, Debug)]
+pub struct FullTextChunk {
+    pub text: String,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -474,6 +972,67 @@ pub struct DeleteFullTextRequest {
     pub file_ref: String,
 }
 
+/// The text extracted from a file, its parts are grouped by language
+#[derive(Serialize, Deserialize, Debug)]
+pub struct DocumentTextReply {
+    pub file_ref: String,
+    pub part_count: u32,
+    pub languages: Vec<String>,
+    pub text: String,
+}
+
+/// An item matching the full text query, the snippets show the matched words between <mark> tags
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct FullTextHitElement {
+    pub item_id: i64,
+    pub name: String,
+    pub file_ref: Option<String>, // None when only a comment of the item matches
+    pub snippets: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct FullTextSearchReply {
+    pub hits: Vec<FullTextHitElement>,
+}
+
+/// The stop words are left out of the index, the words of a synonym group match each other at search time
+#[derive(Serialize, Deserialize, Debug)]
+pub struct FullTextDictionaryReply {
+    pub stop_words: Vec<String>,
+    pub synonyms: Vec<Vec<String>>,
+}
+
+/// The new stop words and synonym groups replace the existing ones.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct UpdateFullTextDictionaryRequest {
+    pub stop_words: Vec<String>,
+    pub synonyms: Vec<Vec<String>>,
+}
+
+/// The reindex job started on the file server when the stop words change the index of documents
+#[derive(Serialize, Deserialize, Debug)]
+pub struct UpdateFullTextDictionaryReply {
+    pub reindex_job_id: Option<i64>,
+}
+
+/// A value of an enumerated or text tag matching the keywords of the document of an item
+/// The score is between 0 and 1, 1 for the best keyword of the document
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SuggestedTag {
+    pub tag_id: i64,
+    pub tag_name: String,
+    pub tag_type: String,
+    pub value: String,
+    pub score: f64,
+}
+
+/// The suggested tags of an item, the best first
+#[derive(Serialize, Deserialize, Debug)]
+pub struct SuggestedTagsReply {
+    pub item_id: i64,
+    pub tags: Vec<SuggestedTag>,
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct UploadReply {
     pub file_ref: String,
@@ -514,6 +1073,14 @@ pub struct GetFileInfoReply {
     pub is_preview_generated: Option<bool>,
 }
 
+/// The metadata of a file, as extracted along with its text
+#[derive(Serialize, Deserialize, Debug)]
+pub struct FileMetadataReply {
+    pub file_ref: String,
+    pub media_type: Option<String>,
+    pub metadata: HashMap<String, String>,
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct ListOfFileInfoReply {
     pub list_of_files: Vec<GetFileInfoReply>,
@@ -534,6 +1101,30 @@ pub struct UploadInfoReply {
     pub uploaded_count: i64,    // Number of block simply loaded
 }
 
+/// The files to reindex, a file reference or a pattern like in the file list ("5c0e*"),
+/// all the files of the customer when None
+/// With [filters], only the files of the items matching the search filter,
+/// the document server gives their file references when the job starts
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ReindexRequest {
+    pub pattern: Option<String>,
+    pub filters: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ReindexJobReply {
+    pub job_id: i64,
+    pub pattern: String,
+    pub filters: Option<String>,
+    pub status: String, // RUNNING, STOPPED, DONE
+    pub file_count: i64,
+    pub done_count: i64,
+    pub failed_count: i64,
+    pub last_file_ref: Option<String>, // Last file processed, the job resumes after it
+    pub start_date_time: DateTime<Utc>,
+    pub end_date_time: Option<DateTime<Utc>>,
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct GetFileInfoShortReply {
     pub file_ref: String,
@@ -0,0 +1,1280 @@
+use std::collections::{HashMap, HashSet};
+
+use anyhow::anyhow;
+use axum::http::StatusCode;
+use axum::Json;
+use log::*;
+use serde::de::DeserializeOwned;
+
+use commons_error::*;
+use commons_pg::sql_transaction::{CellValue, SQLDataSet};
+use commons_pg::sql_transaction_async::{
+    SQLChangeAsync, SQLConnectionAsync, SQLQueryBlockAsync, SQLTransactionAsync,
+};
+use commons_services::key_lib::fetch_customer_key;
+use commons_services::session_lib::valid_sid_get_session;
+use commons_services::token_lib::SessionToken;
+use commons_services::try_or_return;
+use commons_services::x_request_id::{Follower, XRequestID};
+use dkconfig::properties::get_prop_value;
+use dkconfig::property_name::{FILE_SERVER_HOSTNAME_PROPERTY, FILE_SERVER_PORT_PROPERTY};
+use dkdto::error_codes::{
+    CLASSIFICATION_RULE_NAME_ALREADY_TAKEN, INCORRECT_CLASSIFICATION_RULE, INTERNAL_DATABASE_ERROR,
+    INTERNAL_TECHNICAL_ERROR, MISSING_CLASSIFICATION_RULE,
+};
+use dkdto::{
+    AddClassificationRuleReply, AddClassificationRuleRequest, AddTagValue, ClassificationAction,
+    ClassificationRuleElement, ClassifiedItemElement, ClassifyFileReply, ClassifyFileRequest,
+    DryRunClassificationReply, DryRunClassificationRequest, EnumTagValue, ErrorSet,
+    GetClassificationRuleReply, SimpleMessage, TagType, WebType, WebTypeBuilder,
+};
+use doka_cli::async_request_client::FileServerClientAsync;
+use doka_cli::request_client::TokenType;
+
+use crate::filter::{
+    analyse_expression, extract_all_attributes, matches_attributes, FilterExpressionAST,
+};
+use crate::fulltext::{assemble_parts, FullTextDelegate};
+use crate::item::ItemDelegate;
+use crate::item_lock::ItemLockDelegate;
+use crate::tag::TagDelegate;
+
+/// Attributes of the file in the conditions, the other attributes are the metadata of the file
+const MIME_ATTRIBUTE: &str = "mime";
+const FILE_NAME_ATTRIBUTE: &str = "file_name";
+const TEXT_ATTRIBUTE: &str = "text";
+
+/// Size limits of the columns of the classification tables
+const MAX_CONDITION_LENGTH: usize = 2_000;
+const MAX_ACTIONS_LENGTH: usize = 2_000;
+const MAX_ACTION_VALUE_LENGTH: usize = 255;
+
+/// Number of the most recent documents tested by a dry run
+const DRY_RUN_DEFAULT_LIMIT: u32 = 100;
+const DRY_RUN_MAX_LIMIT: u32 = 1_000;
+
+///
+/// Classification rules, the new files matching the condition of a rule get the tags of its actions
+/// The file server applies the rules once the text of a file is indexed,
+/// the items created afterwards on the file get the tags at their creation.
+///
+pub(crate) struct ClassificationDelegate {
+    pub session_token: SessionToken,
+    pub follower: Follower,
+}
+
+impl ClassificationDelegate {
+    pub fn new(session_token: SessionToken, x_request_id: XRequestID) -> Self {
+        Self {
+            session_token,
+            follower: Follower {
+                x_request_id: x_request_id.new_if_null(),
+                token_type: TokenType::None,
+            },
+        }
+    }
+
+    ///
+    /// 🌟 Find all the classification rules
+    ///
+    pub async fn get_all_classification_rule(mut self) -> WebType<GetClassificationRuleReply> {
+        log_info!(
+            "🚀 Start get_all_classification_rule api, follower=[{}]",
+            &self.follower
+        );
+
+        let entry_session = try_or_return!(
+            valid_sid_get_session(&self.session_token, &mut self.follower).await,
+            Self::web_type_error()
+        );
+
+        let customer_code = entry_session.customer_code.as_str();
+
+        let Ok(mut cnx) = SQLConnectionAsync::from_pool().await.map_err(err_fwd!(
+            "💣 New Db connection failed, follower=[{}]",
+            &self.follower
+        )) else {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        };
+
+        let Ok(mut trans) = cnx.begin().await.map_err(err_fwd!(
+            "💣 Transaction issue, follower=[{}]",
+            &self.follower
+        )) else {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        };
+
+        let Ok(rules) = self
+            .search_classification_rules(&mut trans, None, customer_code)
+            .await
+            .map_err(err_fwd!(
+                "💣 Cannot find the classification rules, follower=[{}]",
+                &self.follower
+            ))
+        else {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        };
+
+        if trans
+            .commit()
+            .await
+            .map_err(err_fwd!("💣 Commit failed, follower=[{}]", &self.follower))
+            .is_err()
+        {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        }
+
+        log_info!(
+            "🏁 End get_all_classification_rule api, follower=[{}]",
+            &self.follower
+        );
+
+        WebType::from_item(
+            StatusCode::OK.as_u16(),
+            GetClassificationRuleReply { rules },
+        )
+    }
+
+    ///
+    /// 🌟 Create a new classification rule
+    ///
+    pub async fn add_classification_rule(
+        mut self,
+        add_rule_request: Json<AddClassificationRuleRequest>,
+    ) -> WebType<AddClassificationRuleReply> {
+        log_info!(
+            "🚀 Start add_classification_rule api, add_rule_request=[{:?}], follower=[{}]",
+            &add_rule_request,
+            &self.follower
+        );
+
+        let entry_session = try_or_return!(
+            valid_sid_get_session(&self.session_token, &mut self.follower).await,
+            Self::web_type_error()
+        );
+
+        let customer_code = entry_session.customer_code.as_str();
+
+        let name = add_rule_request.name.trim();
+        let condition = add_rule_request.condition.trim();
+        let Ok(actions) = serde_json::to_string(&add_rule_request.actions).map_err(err_fwd!(
+            "💣 Cannot serialize the actions, follower=[{}]",
+            &self.follower
+        )) else {
+            return WebType::from_errorset(&INTERNAL_TECHNICAL_ERROR);
+        };
+
+        if name.is_empty()
+            || name.len() > 50
+            || condition.len() > MAX_CONDITION_LENGTH
+            || add_rule_request.actions.is_empty()
+            || actions.len() > MAX_ACTIONS_LENGTH
+        {
+            log_error!(
+                "💣 Incorrect classification rule, name=[{}], action count=[{}], follower=[{}]",
+                name,
+                add_rule_request.actions.len(),
+                &self.follower
+            );
+            return WebType::from_errorset(&INCORRECT_CLASSIFICATION_RULE);
+        }
+
+        // The condition must be valid now, not only when a file is classified
+        if let Err(e) = analyse_expression(condition) {
+            let message = e.human_error_message();
+            log_error!(
+                "💣 Incorrect classification condition, condition=[{}], error=[{}], follower=[{}]",
+                condition,
+                &message,
+                &self.follower
+            );
+            return WebType::from_simple(
+                StatusCode::BAD_REQUEST.as_u16(),
+                SimpleMessage { message },
+            );
+        }
+
+        let Ok(mut cnx) = SQLConnectionAsync::from_pool().await.map_err(err_fwd!(
+            "💣 New Db connection failed, follower=[{}]",
+            &self.follower
+        )) else {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        };
+
+        let Ok(mut trans) = cnx.begin().await.map_err(err_fwd!(
+            "💣 Transaction issue, follower=[{}]",
+            &self.follower
+        )) else {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        };
+
+        let Ok(existing_rules) = self
+            .search_classification_rules(&mut trans, Some(name), customer_code)
+            .await
+            .map_err(err_fwd!(
+                "💣 Cannot read the classification rules, follower=[{}]",
+                &self.follower
+            ))
+        else {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        };
+
+        if !existing_rules.is_empty() {
+            log_error!(
+                "💣 The classification rule already exists, name=[{}], follower=[{}]",
+                name,
+                &self.follower
+            );
+            return WebType::from_errorset(&CLASSIFICATION_RULE_NAME_ALREADY_TAKEN);
+        }
+
+        for action in &add_rule_request.actions {
+            if let Err(message) = self.check_action(&mut trans, action, customer_code).await {
+                log_error!(
+                    "💣 Incorrect classification action, action=[{:?}], follower=[{}]",
+                    action,
+                    &self.follower
+                );
+                return WebType::from_simple(
+                    StatusCode::BAD_REQUEST.as_u16(),
+                    SimpleMessage { message },
+                );
+            }
+        }
+
+        let sql_query = format!(
+            r"INSERT INTO cs_{}.classification_rule (name, condition, actions)
+                    VALUES (:p_name, :p_condition, :p_actions)",
+            customer_code
+        );
+
+        let mut params = HashMap::new();
+        params.insert("p_name".to_string(), CellValue::from_raw_str(name));
+        params.insert(
+            "p_condition".to_string(),
+            CellValue::from_raw_str(condition),
+        );
+        params.insert("p_actions".to_string(), CellValue::from_raw_string(actions));
+
+        let sql_insert = SQLChangeAsync {
+            sql_query,
+            params,
+            sequence_name: format!("cs_{}.classification_rule_id_seq", customer_code),
+        };
+
+        let Ok(rule_id) = sql_insert.insert(&mut trans).await.map_err(err_fwd!(
+            "💣 Insertion of a new classification rule failed, follower=[{}]",
+            &self.follower
+        )) else {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        };
+
+        if trans
+            .commit()
+            .await
+            .map_err(err_fwd!("💣 Commit failed, follower=[{}]", &self.follower))
+            .is_err()
+        {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        }
+
+        log_info!(
+            "😎 The classification rule has been created, rule_id=[{}], follower=[{}]",
+            rule_id,
+            &self.follower
+        );
+        log_info!(
+            "🏁 End add_classification_rule api, follower=[{}]",
+            &self.follower
+        );
+
+        WebType::from_item(
+            StatusCode::OK.as_u16(),
+            AddClassificationRuleReply { rule_id },
+        )
+    }
+
+    ///
+    /// 🌟 Delete a classification rule, the tags it has already set are kept
+    ///
+    pub async fn delete_classification_rule(mut self, rule_id: i64) -> WebType<SimpleMessage> {
+        log_info!(
+            "🚀 Start delete_classification_rule api, rule_id=[{}], follower=[{}]",
+            rule_id,
+            &self.follower
+        );
+
+        let entry_session = try_or_return!(
+            valid_sid_get_session(&self.session_token, &mut self.follower).await,
+            Self::web_type_error()
+        );
+
+        let customer_code = entry_session.customer_code.as_str();
+
+        let Ok(mut cnx) = SQLConnectionAsync::from_pool().await.map_err(err_fwd!(
+            "💣 New Db connection failed, follower=[{}]",
+            &self.follower
+        )) else {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        };
+
+        let Ok(mut trans) = cnx.begin().await.map_err(err_fwd!(
+            "💣 Transaction issue, follower=[{}]",
+            &self.follower
+        )) else {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        };
+
+        let Ok(rules) = self
+            .search_classification_rules(&mut trans, None, customer_code)
+            .await
+            .map_err(err_fwd!(
+                "💣 Cannot read the classification rules, follower=[{}]",
+                &self.follower
+            ))
+        else {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        };
+
+        if !rules.iter().any(|rule| rule.rule_id == rule_id) {
+            log_error!(
+                "💣 Missing classification rule, rule_id=[{}], follower=[{}]",
+                rule_id,
+                &self.follower
+            );
+            return WebType::from_errorset(&MISSING_CLASSIFICATION_RULE);
+        }
+
+        let mut params = HashMap::new();
+        params.insert("p_rule_id".to_string(), CellValue::from_raw_int(rule_id));
+
+        let sql_delete = SQLChangeAsync {
+            sql_query: format!(
+                "DELETE FROM cs_{}.classification_rule WHERE id = :p_rule_id",
+                customer_code
+            ),
+            params,
+            sequence_name: "".to_string(),
+        };
+
+        if sql_delete
+            .delete(&mut trans)
+            .await
+            .map_err(err_fwd!(
+                "💣 Cannot delete the classification rule, follower=[{}]",
+                &self.follower
+            ))
+            .is_err()
+        {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        }
+
+        if trans
+            .commit()
+            .await
+            .map_err(err_fwd!("💣 Commit failed, follower=[{}]", &self.follower))
+            .is_err()
+        {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        }
+
+        log_info!(
+            "🏁 End delete_classification_rule api, follower=[{}]",
+            &self.follower
+        );
+
+        WebType::from_item(
+            StatusCode::OK.as_u16(),
+            SimpleMessage {
+                message: "Ok".to_string(),
+            },
+        )
+    }
+
+    ///
+    /// 🌟 Apply the classification rules to a new file, called from the file server once the text of the file is indexed
+    ///     The tags are set on the items of the file and kept for the items created later on the file
+    ///
+    pub async fn classify_file(
+        mut self,
+        classify_request: Json<ClassifyFileRequest>,
+    ) -> WebType<ClassifyFileReply> {
+        log_info!(
+            "🚀 Start classify_file api, file_ref=[{}], follower=[{}]",
+            &classify_request.file_ref,
+            &self.follower
+        );
+
+        let entry_session = try_or_return!(
+            valid_sid_get_session(&self.session_token, &mut self.follower).await,
+            Self::web_type_error()
+        );
+
+        let customer_code = entry_session.customer_code.as_str();
+        let file_ref = classify_request.file_ref.as_str();
+
+        let Ok(customer_key) = fetch_customer_key(customer_code, &self.follower)
+            .await
+            .map_err(err_fwd!(
+                "💣 Cannot get the customer key, follower=[{}]",
+                &self.follower
+            ))
+        else {
+            return WebType::from_errorset(&INTERNAL_TECHNICAL_ERROR);
+        };
+
+        let Ok(mut cnx) = SQLConnectionAsync::from_pool().await.map_err(err_fwd!(
+            "💣 New Db connection failed, follower=[{}]",
+            &self.follower
+        )) else {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        };
+
+        let Ok(mut trans) = cnx.begin().await.map_err(err_fwd!(
+            "💣 Transaction issue, follower=[{}]",
+            &self.follower
+        )) else {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        };
+
+        let Ok(rules) = self
+            .search_classification_rules(&mut trans, None, customer_code)
+            .await
+            .map_err(err_fwd!(
+                "💣 Cannot read the classification rules, follower=[{}]",
+                &self.follower
+            ))
+        else {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        };
+
+        let conditions = self.parse_conditions(&rules);
+
+        // The text is only read when a condition needs it
+        let text = if conditions
+            .iter()
+            .any(|(_, attributes)| attributes.contains(TEXT_ATTRIBUTE))
+        {
+            Some(try_or_return!(
+                self.read_file_text(&mut trans, file_ref, &customer_key, customer_code)
+                    .await,
+                Self::web_type_error()
+            ))
+        } else {
+            None
+        };
+
+        let attributes = file_attributes(
+            &classify_request.file_name,
+            &classify_request.media_type,
+            &classify_request.metadata,
+            text,
+        );
+
+        let matching_rules: Vec<&ClassificationRuleElement> = rules
+            .iter()
+            .zip(conditions.iter())
+            .filter(|(_, (condition, _))| {
+                condition
+                    .as_ref()
+                    .is_some_and(|c| matches_attributes(c, &attributes))
+            })
+            .map(|(rule, _)| rule)
+            .collect();
+
+        log_info!(
+            "Classification rules matching the file, rule_names=[{:?}], follower=[{}]",
+            matching_rules.iter().map(|r| &r.name).collect::<Vec<_>>(),
+            &self.follower
+        );
+
+        // | The former classification of the file is replaced
+        if self
+            .delete_file_classification(&mut trans, file_ref, customer_code)
+            .await
+            .map_err(err_fwd!(
+                "💣 Cannot delete the classification of the file, file_ref=[{}], follower=[{}]",
+                file_ref,
+                &self.follower
+            ))
+            .is_err()
+        {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        }
+
+        for rule in &matching_rules {
+            for action in &rule.actions {
+                if self
+                    .insert_file_classification(
+                        &mut trans,
+                        file_ref,
+                        &rule.name,
+                        action,
+                        customer_code,
+                    )
+                    .await
+                    .map_err(err_fwd!(
+                        "💣 Cannot insert the classification of the file, file_ref=[{}], follower=[{}]",
+                        file_ref,
+                        &self.follower
+                    ))
+                    .is_err()
+                {
+                    return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+                }
+            }
+        }
+
+        // | Tag the items already created on the file
+        let Ok(properties) = self
+            .search_file_classification(&mut trans, file_ref, customer_code)
+            .await
+            .map_err(err_fwd!(
+                "💣 Cannot read the classification of the file, file_ref=[{}], follower=[{}]",
+                file_ref,
+                &self.follower
+            ))
+        else {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        };
+
+        let mut item_count = 0;
+        if !properties.is_empty() {
+            let Ok(item_ids) = self
+                .search_file_items(&mut trans, file_ref, customer_code)
+                .await
+                .map_err(err_fwd!(
+                    "💣 Cannot find the items of the file, file_ref=[{}], follower=[{}]",
+                    file_ref,
+                    &self.follower
+                ))
+            else {
+                return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+            };
+
+            let item_delegate = self.item_delegate();
+            let item_lock_delegate = self.item_lock_delegate();
+            for item_id in &item_ids {
+                let Ok(item_tags) = item_delegate
+                    .find_item_properties(&mut trans, *item_id, customer_code)
+                    .await
+                    .map_err(err_fwd!(
+                        "💣 Cannot read the tags of the item, item_id=[{}], follower=[{}]",
+                        item_id,
+                        &self.follower
+                    ))
+                else {
+                    return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+                };
+
+                // The tags already set on the item win, like in add_item
+                let item_properties: Vec<AddTagValue> = properties
+                    .iter()
+                    .filter(|tag| {
+                        !item_tags
+                            .iter()
+                            .any(|t| tag.tag_name.as_ref() == Some(&t.tag_name))
+                    })
+                    .cloned()
+                    .collect();
+                if item_properties.is_empty() {
+                    continue;
+                }
+
+                if let Err(e) = item_lock_delegate
+                    .prepare_write(&mut trans, *item_id, entry_session.id, None, customer_code)
+                    .await
+                {
+                    return WebType::from_errorset(e);
+                }
+                if let Err(e) = item_delegate
+                    .update_tags_on_item(
+                        &mut trans,
+                        *item_id,
+                        entry_session.user_id,
+                        customer_code,
+                        &item_properties,
+                    )
+                    .await
+                {
+                    return WebType::from_errorset(e);
+                }
+                item_count += 1;
+            }
+        }
+
+        if trans
+            .commit()
+            .await
+            .map_err(err_fwd!("💣 Commit failed, follower=[{}]", &self.follower))
+            .is_err()
+        {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        }
+
+        log_info!(
+            "😎 The file is classified, file_ref=[{}], item_count=[{}], follower=[{}]",
+            file_ref,
+            item_count,
+            &self.follower
+        );
+        log_info!("🏁 End classify_file api, follower=[{}]", &self.follower);
+
+        WebType::from_item(
+            StatusCode::OK.as_u16(),
+            ClassifyFileReply {
+                file_ref: file_ref.to_owned(),
+                rule_names: matching_rules.iter().map(|r| r.name.clone()).collect(),
+                item_count,
+            },
+        )
+    }
+
+    ///
+    /// 🌟 Test a condition on the most recent documents, nothing is tagged
+    ///     The file name of a document is the name of its item
+    ///
+    pub async fn dry_run_classification(
+        mut self,
+        dry_run_request: Json<DryRunClassificationRequest>,
+    ) -> WebType<DryRunClassificationReply> {
+        log_info!(
+            "🚀 Start dry_run_classification api, dry_run_request=[{:?}], follower=[{}]",
+            &dry_run_request,
+            &self.follower
+        );
+
+        let entry_session = try_or_return!(
+            valid_sid_get_session(&self.session_token, &mut self.follower).await,
+            Self::web_type_error()
+        );
+
+        let customer_code = entry_session.customer_code.as_str();
+
+        let condition = match analyse_expression(dry_run_request.condition.trim()) {
+            Ok(condition) => condition,
+            Err(e) => {
+                let message = e.human_error_message();
+                log_error!(
+                    "💣 Incorrect classification condition, error=[{}], follower=[{}]",
+                    &message,
+                    &self.follower
+                );
+                return WebType::from_simple(
+                    StatusCode::BAD_REQUEST.as_u16(),
+                    SimpleMessage { message },
+                );
+            }
+        };
+
+        let Ok(attribute_names) = condition_attributes(&condition) else {
+            return WebType::from_errorset(&INCORRECT_CLASSIFICATION_RULE);
+        };
+        let with_text = attribute_names.contains(TEXT_ATTRIBUTE);
+        // The media type and the metadata are only known by the file server
+        let with_metadata = attribute_names
+            .iter()
+            .any(|a| a != TEXT_ATTRIBUTE && a != FILE_NAME_ATTRIBUTE);
+
+        let limit = dry_run_request
+            .limit
+            .unwrap_or(DRY_RUN_DEFAULT_LIMIT)
+            .clamp(1, DRY_RUN_MAX_LIMIT);
+
+        let Ok(customer_key) = fetch_customer_key(customer_code, &self.follower)
+            .await
+            .map_err(err_fwd!(
+                "💣 Cannot get the customer key, follower=[{}]",
+                &self.follower
+            ))
+        else {
+            return WebType::from_errorset(&INTERNAL_TECHNICAL_ERROR);
+        };
+
+        let file_server_client = if with_metadata {
+            let Ok(client) = Self::find_file_server_client().map_err(err_fwd!(
+                "💣 Cannot read the file server information, follower=[{}]",
+                &self.follower
+            )) else {
+                return WebType::from_errorset(&INTERNAL_TECHNICAL_ERROR);
+            };
+            Some(client)
+        } else {
+            None
+        };
+
+        let Ok(mut cnx) = SQLConnectionAsync::from_pool().await.map_err(err_fwd!(
+            "💣 New Db connection failed, follower=[{}]",
+            &self.follower
+        )) else {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        };
+
+        let Ok(mut trans) = cnx.begin().await.map_err(err_fwd!(
+            "💣 Transaction issue, follower=[{}]",
+            &self.follower
+        )) else {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        };
+
+        let Ok(documents) = self
+            .search_recent_documents(&mut trans, limit, customer_code)
+            .await
+            .map_err(err_fwd!(
+                "💣 Cannot find the documents, follower=[{}]",
+                &self.follower
+            ))
+        else {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        };
+
+        let mut checked_count = 0;
+        let mut items = vec![];
+        for document in documents {
+            let (media_type, metadata) = match &file_server_client {
+                None => (String::new(), HashMap::new()),
+                Some(client) => match client
+                    .metadata(&document.file_ref, &self.session_token.0)
+                    .await
+                {
+                    Ok(reply) => (reply.media_type.unwrap_or_default(), reply.metadata),
+                    Err(e) => {
+                        log_warn!(
+                            "⛔ The document is skipped, its metadata cannot be read, file_ref=[{}], error=[{}], follower=[{}]",
+                            &document.file_ref,
+                            &e.message,
+                            &self.follower
+                        );
+                        continue;
+                    }
+                },
+            };
+
+            let text = if with_text {
+                Some(try_or_return!(
+                    self.read_file_text(
+                        &mut trans,
+                        &document.file_ref,
+                        &customer_key,
+                        customer_code
+                    )
+                    .await,
+                    Self::web_type_error()
+                ))
+            } else {
+                None
+            };
+
+            let attributes = file_attributes(&document.name, &media_type, &metadata, text);
+            checked_count += 1;
+            if matches_attributes(&condition, &attributes) {
+                items.push(document);
+            }
+        }
+
+        if trans
+            .commit()
+            .await
+            .map_err(err_fwd!("💣 Commit failed, follower=[{}]", &self.follower))
+            .is_err()
+        {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        }
+
+        log_info!(
+            "😎 Dry run done, checked_count=[{}], matching count=[{}], follower=[{}]",
+            checked_count,
+            items.len(),
+            &self.follower
+        );
+        log_info!(
+            "🏁 End dry_run_classification api, follower=[{}]",
+            &self.follower
+        );
+
+        WebType::from_item(
+            StatusCode::OK.as_u16(),
+            DryRunClassificationReply {
+                checked_count,
+                items,
+            },
+        )
+    }
+
+    /// The tags given to the file by the classification rules, the tags deleted since then are ignored
+    pub(crate) async fn search_file_classification(
+        &self,
+        trans: &mut SQLTransactionAsync<'_>,
+        file_ref: &str,
+        customer_code: &str,
+    ) -> anyhow::Result<Vec<AddTagValue>> {
+        let mut params = HashMap::new();
+        params.insert("p_file_ref".to_owned(), CellValue::from_raw_str(file_ref));
+
+        let query = SQLQueryBlockAsync {
+            sql_query: format!(
+                r"SELECT fc.tag_name, t.type, fc.value
+                    FROM cs_{0}.file_classification fc
+                    JOIN cs_{0}.tag_definition t ON t.name = fc.tag_name
+                    WHERE fc.file_ref = :p_file_ref
+                    ORDER BY fc.id ",
+                customer_code
+            ),
+            start: 0,
+            length: None,
+            params,
+        };
+
+        let mut sql_result: SQLDataSet = query
+            .execute(trans)
+            .await
+            .map_err(err_fwd!("Query failed, [{}]", &query.sql_query))?;
+
+        let mut properties = vec![];
+        while sql_result.next() {
+            let tag_name = sql_result
+                .get_string("tag_name")
+                .ok_or(anyhow!("Wrong tag name"))?;
+            let tag_type = sql_result.get_string("type").ok_or(anyhow!("Wrong type"))?;
+            let value = sql_result.get_string("value").unwrap_or_default();
+            match EnumTagValue::from_string(&value, &tag_type) {
+                Ok(value) => properties.push(AddTagValue {
+                    tag_id: None,
+                    tag_name: Some(tag_name),
+                    value,
+                }),
+                Err(message) => {
+                    log_warn!(
+                        "⛔ The classification tag is skipped, tag_name=[{}], message=[{}], follower=[{}]",
+                        &tag_name,
+                        &message,
+                        &self.follower
+                    );
+                }
+            }
+        }
+        Ok(properties)
+    }
+
+    /// The conditions of the rules with their attributes, None for the conditions that cannot be parsed
+    fn parse_conditions(
+        &self,
+        rules: &[ClassificationRuleElement],
+    ) -> Vec<(Option<Box<FilterExpressionAST>>, HashSet<String>)> {
+        rules
+            .iter()
+            .map(|rule| {
+                let parsed = analyse_expression(&rule.condition)
+                    .ok()
+                    .and_then(|c| condition_attributes(&c).ok().map(|a| (c, a)));
+                match parsed {
+                    Some((condition, attributes)) => (Some(condition), attributes),
+                    None => {
+                        log_warn!(
+                            "⛔ The classification rule is skipped, incorrect condition, rule_name=[{}], follower=[{}]",
+                            &rule.name,
+                            &self.follower
+                        );
+                        (None, HashSet::new())
+                    }
+                }
+            })
+            .collect()
+    }
+
+    /// The tag of the action must exist and accept the value
+    async fn check_action(
+        &self,
+        trans: &mut SQLTransactionAsync<'_>,
+        action: &ClassificationAction,
+        customer_code: &str,
+    ) -> Result<(), String> {
+        let Ok(tag) = self
+            .tag_delegate()
+            .search_tag_by_name(trans, &action.tag_name, customer_code)
+            .await
+        else {
+            return Err(format!("Unknown tag [{}]", &action.tag_name));
+        };
+
+        if action.value.len() > MAX_ACTION_VALUE_LENGTH {
+            return Err(format!(
+                "The value of the tag [{}] is too long",
+                &action.tag_name
+            ));
+        }
+
+        EnumTagValue::from_string(&action.value, &tag.tag_type).map_err(|message| {
+            format!(
+                "Incorrect value [{}] for the tag [{}], {}",
+                &action.value, &action.tag_name, message
+            )
+        })?;
+
+        if tag.tag_type == TagType::Enum.as_str()
+            && !tag
+                .allowed_values
+                .unwrap_or_default()
+                .contains(&action.value)
+        {
+            return Err(format!(
+                "The value [{}] is not allowed for the tag [{}]",
+                &action.value, &action.tag_name
+            ));
+        }
+        Ok(())
+    }
+
+    /// The text of the file, empty when the file has no text
+    async fn read_file_text(
+        &self,
+        trans: &mut SQLTransactionAsync<'_>,
+        file_ref: &str,
+        customer_key: &str,
+        customer_code: &str,
+    ) -> Result<String, &'static ErrorSet<'static>> {
+        let parts = self
+            .fulltext_delegate()
+            .read_document_parts(trans, file_ref, customer_key, customer_code)
+            .await?;
+        Ok(assemble_parts(file_ref, parts).text)
+    }
+
+    async fn search_classification_rules(
+        &self,
+        trans: &mut SQLTransactionAsync<'_>,
+        name: Option<&str>,
+        customer_code: &str,
+    ) -> anyhow::Result<Vec<ClassificationRuleElement>> {
+        let mut params = HashMap::new();
+        params.insert("p_name".to_owned(), CellValue::from_opt_str(name));
+
+        let sql_query = format!(
+            r"SELECT id, name, condition, actions FROM cs_{}.classification_rule
+                    WHERE ( name = :p_name OR :p_name IS NULL )
+                    ORDER BY id ",
+            customer_code
+        );
+
+        let query = SQLQueryBlockAsync {
+            sql_query,
+            start: 0,
+            length: None,
+            params,
+        };
+
+        let mut sql_result: SQLDataSet = query
+            .execute(trans)
+            .await
+            .map_err(err_fwd!("Query failed, [{}]", &query.sql_query))?;
+
+        let mut rules = vec![];
+        while sql_result.next() {
+            let actions = sql_result.get_string("actions").unwrap_or_default();
+            rules.push(ClassificationRuleElement {
+                rule_id: sql_result.get_int("id").ok_or(anyhow!("Wrong id"))?,
+                name: sql_result.get_string("name").unwrap_or_default(),
+                condition: sql_result.get_string("condition").unwrap_or_default(),
+                actions: serde_json::from_str(&actions)
+                    .map_err(err_fwd!("Wrong actions, [{}]", &actions))?,
+            });
+        }
+        Ok(rules)
+    }
+
+    /// The items on the file, but the ones in the trash
+    async fn search_file_items(
+        &self,
+        trans: &mut SQLTransactionAsync<'_>,
+        file_ref: &str,
+        customer_code: &str,
+    ) -> anyhow::Result<Vec<i64>> {
+        let mut params = HashMap::new();
+        params.insert("p_file_ref".to_owned(), CellValue::from_raw_str(file_ref));
+
+        let query = SQLQueryBlockAsync {
+            sql_query: format!(
+                r"SELECT id FROM cs_{}.item
+                    WHERE file_ref = :p_file_ref AND trashed_gmt IS NULL
+                    ORDER BY id ",
+                customer_code
+            ),
+            start: 0,
+            length: None,
+            params,
+        };
+
+        let mut sql_result: SQLDataSet = query
+            .execute(trans)
+            .await
+            .map_err(err_fwd!("Query failed, [{}]", &query.sql_query))?;
+
+        let mut item_ids = vec![];
+        while sql_result.next() {
+            item_ids.push(sql_result.get_int("id").ok_or(anyhow!("Wrong id"))?);
+        }
+        Ok(item_ids)
+    }
+
+    /// The most recent items with a file, but the ones in the trash
+    async fn search_recent_documents(
+        &self,
+        trans: &mut SQLTransactionAsync<'_>,
+        limit: u32,
+        customer_code: &str,
+    ) -> anyhow::Result<Vec<ClassifiedItemElement>> {
+        let query = SQLQueryBlockAsync {
+            sql_query: format!(
+                r"SELECT id, name, file_ref FROM cs_{}.item
+                    WHERE file_ref IS NOT NULL AND trashed_gmt IS NULL
+                    ORDER BY id DESC ",
+                customer_code
+            ),
+            start: 0,
+            length: Some(limit),
+            params: HashMap::new(),
+        };
+
+        let mut sql_result: SQLDataSet = query
+            .execute(trans)
+            .await
+            .map_err(err_fwd!("Query failed, [{}]", &query.sql_query))?;
+
+        let mut documents = vec![];
+        while sql_result.next() {
+            documents.push(ClassifiedItemElement {
+                item_id: sql_result.get_int("id").ok_or(anyhow!("Wrong id"))?,
+                name: sql_result.get_string("name").unwrap_or_default(),
+                file_ref: sql_result
+                    .get_string("file_ref")
+                    .ok_or(anyhow!("Wrong file ref"))?,
+            });
+        }
+        Ok(documents)
+    }
+
+    async fn delete_file_classification(
+        &self,
+        trans: &mut SQLTransactionAsync<'_>,
+        file_ref: &str,
+        customer_code: &str,
+    ) -> anyhow::Result<()> {
+        let mut params = HashMap::new();
+        params.insert("p_file_ref".to_string(), CellValue::from_raw_str(file_ref));
+
+        let sql_delete = SQLChangeAsync {
+            sql_query: format!(
+                "DELETE FROM cs_{}.file_classification WHERE file_ref = :p_file_ref",
+                customer_code
+            ),
+            params,
+            sequence_name: "".to_string(),
+        };
+
+        sql_delete.delete(trans).await
+    }
+
+    async fn insert_file_classification(
+        &self,
+        trans: &mut SQLTransactionAsync<'_>,
+        file_ref: &str,
+        rule_name: &str,
+        action: &ClassificationAction,
+        customer_code: &str,
+    ) -> anyhow::Result<()> {
+        let mut params = HashMap::new();
+        params.insert("p_file_ref".to_string(), CellValue::from_raw_str(file_ref));
+        params.insert(
+            "p_rule_name".to_string(),
+            CellValue::from_raw_str(rule_name),
+        );
+        params.insert(
+            "p_tag_name".to_string(),
+            CellValue::from_raw_str(&action.tag_name),
+        );
+        params.insert(
+            "p_value".to_string(),
+            CellValue::from_raw_str(&action.value),
+        );
+
+        let sql_insert = SQLChangeAsync {
+            sql_query: format!(
+                r"INSERT INTO cs_{}.file_classification (file_ref, rule_name, tag_name, value)
+                    VALUES (:p_file_ref, :p_rule_name, :p_tag_name, :p_value)",
+                customer_code
+            ),
+            params,
+            sequence_name: format!("cs_{}.file_classification_id_seq", customer_code),
+        };
+
+        let _ = sql_insert.insert(trans).await?;
+        Ok(())
+    }
+
+    fn find_file_server_client() -> anyhow::Result<FileServerClientAsync> {
+        let file_server_host = get_prop_value(FILE_SERVER_HOSTNAME_PROPERTY)?;
+        let file_server_port = get_prop_value(FILE_SERVER_PORT_PROPERTY)?.parse::<u16>()?;
+        Ok(FileServerClientAsync::new(
+            &file_server_host,
+            file_server_port,
+        ))
+    }
+
+    fn fulltext_delegate(&self) -> FullTextDelegate {
+        FullTextDelegate::new(
+            self.session_token.clone(),
+            self.follower.x_request_id.clone(),
+        )
+    }
+
+    fn item_delegate(&self) -> ItemDelegate {
+        ItemDelegate::new(
+            self.session_token.clone(),
+            self.follower.x_request_id.clone(),
+        )
+    }
+
+    fn item_lock_delegate(&self) -> ItemLockDelegate {
+        ItemLockDelegate::new(
+            self.session_token.clone(),
+            self.follower.x_request_id.clone(),
+        )
+    }
+
+    fn tag_delegate(&self) -> TagDelegate {
+        TagDelegate::new(
+            self.session_token.clone(),
+            self.follower.x_request_id.clone(),
+        )
+    }
+
+    fn web_type_error<T>() -> impl Fn(&ErrorSet<'static>) -> WebType<T>
+    where
+        T: DeserializeOwned,
+    {
+        |e| {
+            log_error!("💣 Error after try {:?}", e);
+            WebType::from_errorset(e)
+        }
+    }
+}
+
+/// The attributes of the condition, in lowercase like the attributes of the file
+fn condition_attributes(condition: &FilterExpressionAST) -> anyhow::Result<HashSet<String>> {
+    let attributes = extract_all_attributes(condition).map_err(|e| anyhow!("{}", e))?;
+    Ok(attributes.iter().map(|a| a.to_lowercase()).collect())
+}
+
+/// The attributes of a file for the conditions : its metadata, "mime", "file_name" and "text".
+/// The text is not an attribute when it is not read.
+fn file_attributes(
+    file_name: &str,
+    media_type: &str,
+    metadata: &HashMap<String, String>,
+    text: Option<String>,
+) -> HashMap<String, String> {
+    let mut attributes: HashMap<String, String> = metadata
+        .iter()
+        .map(|(key, value)| (attribute_name(key), value.clone()))
+        .collect();
+    attributes.insert(MIME_ATTRIBUTE.to_owned(), media_type.to_owned());
+    attributes.insert(FILE_NAME_ATTRIBUTE.to_owned(), file_name.to_owned());
+    match text {
+        Some(text) => {
+            attributes.insert(TEXT_ATTRIBUTE.to_owned(), text);
+        }
+        None => {
+            attributes.remove(TEXT_ATTRIBUTE);
+        }
+    }
+    attributes
+}
+
+/// Attribute name of a metadata key, the filter attributes are made of letters, digits and "_"
+/// ex: "meta:author" -> "meta_author"
+fn attribute_name(meta_key: &str) -> String {
+    meta_key
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_lowercase()
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use crate::classification::{attribute_name, file_attributes};
+    use crate::filter::{analyse_expression, matches_attributes};
+
+    #[test]
+    fn attribute_name_test() {
+        assert_eq!("meta_author", attribute_name("meta:author"));
+        assert_eq!("content_type", attribute_name("Content-Type"));
+        assert_eq!("dc_title", attribute_name("dc:title"));
+        assert_eq!("x_tika_pdf_version", attribute_name("X-TIKA:pdf version"));
+    }
+
+    #[test]
+    fn file_attributes_test() {
+        let mut metadata = HashMap::new();
+        metadata.insert("meta:author".to_owned(), "Accounting".to_owned());
+        metadata.insert("File-Name".to_owned(), "from the metadata".to_owned());
+        metadata.insert("text".to_owned(), "from the metadata".to_owned());
+
+        let attributes = file_attributes(
+            "invoice_042.pdf",
+            "application/pdf",
+            &metadata,
+            Some("Invoice n° 42".to_owned()),
+        );
+        assert_eq!("Accounting", attributes["meta_author"]);
+        assert_eq!("application/pdf", attributes["mime"]);
+        assert_eq!("invoice_042.pdf", attributes["file_name"]);
+        assert_eq!("Invoice n° 42", attributes["text"]);
+
+        // The text is not read
+        let attributes = file_attributes("invoice_042.pdf", "application/pdf", &metadata, None);
+        assert!(!attributes.contains_key("text"));
+    }
+
+    #[test]
+    fn invoice_rule_test() {
+        let condition = analyse_expression(
+            r#"(mime == "application/pdf" AND text LIKE "%invoice%" AND meta_author == "Accounting")"#,
+        )
+        .unwrap();
+
+        let mut metadata = HashMap::new();
+        metadata.insert("meta:author".to_owned(), "Accounting".to_owned());
+
+        let invoice = file_attributes(
+            "2024_05.pdf",
+            "application/pdf",
+            &metadata,
+            Some("INVOICE\nAmount due : 120 EUR".to_owned()),
+        );
+        assert!(matches_attributes(&condition, &invoice));
+
+        let receipt = file_attributes(
+            "2024_05.pdf",
+            "application/pdf",
+            &metadata,
+            Some("RECEIPT\nAmount paid : 120 EUR".to_owned()),
+        );
+        assert!(!matches_attributes(&condition, &receipt));
+
+        let image = file_attributes(
+            "2024_05.png",
+            "image/png",
+            &metadata,
+            Some("INVOICE".to_owned()),
+        );
+        assert!(!matches_attributes(&condition, &image));
+    }
+}
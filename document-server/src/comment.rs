@@ -0,0 +1,1177 @@
+use std::collections::{HashMap, HashSet};
+use std::time::SystemTime;
+
+use anyhow::anyhow;
+use axum::http::StatusCode;
+use axum::Json;
+use chrono::{DateTime, Utc};
+use log::{error, info};
+use serde::de::DeserializeOwned;
+
+use commons_error::*;
+use commons_pg::sql_transaction::{date_time_to_iso, CellValue, SQLDataSet};
+use commons_pg::sql_transaction_async::{
+    SQLChangeAsync, SQLConnectionAsync, SQLQueryBlockAsync, SQLTransactionAsync,
+};
+use commons_services::key_lib::fetch_customer_key;
+use commons_services::session_lib::valid_sid_get_session;
+use commons_services::token_lib::SessionToken;
+use commons_services::try_or_return;
+use commons_services::x_request_id::{Follower, XRequestID};
+use dkconfig::properties::get_prop_value;
+use dkconfig::property_name::{ADMIN_SERVER_HOSTNAME_PROPERTY, ADMIN_SERVER_PORT_PROPERTY};
+use dkcrypto::dk_crypto::CypherMode::CC20;
+use dkcrypto::dk_crypto::DkEncrypt;
+use dkdto::error_codes::{
+    INCORRECT_COMMENT, INTERNAL_DATABASE_ERROR, INTERNAL_TECHNICAL_ERROR, MISSING_COMMENT,
+    MISSING_ITEM, NOT_COMMENT_AUTHOR, UNKNOWN_MENTIONED_USER,
+};
+use dkdto::{
+    AddItemCommentReply, AddItemCommentRequest, ErrorSet, GetItemCommentReply, ItemCommentElement,
+    SimpleMessage, UpdateItemCommentRequest, WebType, WebTypeBuilder,
+};
+use doka_cli::async_request_client::AdminServerClientAsync;
+use doka_cli::request_client::TokenType;
+
+use crate::fulltext::{EncryptedText, FullTextDelegate};
+
+/// Max number of characters of a comment
+const MAX_COMMENT_LENGTH: usize = 4_000;
+
+/// The author and the state of a comment
+struct CommentOwner {
+    user_id: i64,
+    deleted: bool,
+}
+
+///
+/// Comments on the items
+/// The text of the comments is encrypted with the customer key and indexed like the document parts,
+/// so the comments can be found by the full text search.
+/// A deleted comment keeps its place in the thread, without text, as long as it has replies.
+///
+pub(crate) struct CommentDelegate {
+    pub session_token: SessionToken,
+    pub follower: Follower,
+}
+
+impl CommentDelegate {
+    pub fn new(session_token: SessionToken, x_request_id: XRequestID) -> Self {
+        Self {
+            session_token,
+            follower: Follower {
+                x_request_id: x_request_id.new_if_null(),
+                token_type: TokenType::None,
+            },
+        }
+    }
+
+    ///
+    /// 🌟 Find all the comments of an item, oldest first
+    ///
+    pub async fn get_item_comments(mut self, item_id: i64) -> WebType<GetItemCommentReply> {
+        log_info!(
+            "🚀 Start get_item_comments api, item_id=[{}], follower=[{}]",
+            item_id,
+            &self.follower
+        );
+
+        let entry_session = try_or_return!(
+            valid_sid_get_session(&self.session_token, &mut self.follower).await,
+            Self::web_type_error()
+        );
+
+        let customer_code = entry_session.customer_code.as_str();
+
+        let Ok(customer_key) = fetch_customer_key(customer_code, &self.follower)
+            .await
+            .map_err(err_fwd!(
+                "💣 Cannot get the customer key, follower=[{}]",
+                &self.follower
+            ))
+        else {
+            return WebType::from_errorset(&INTERNAL_TECHNICAL_ERROR);
+        };
+
+        let Ok(mut cnx) = SQLConnectionAsync::from_pool().await.map_err(err_fwd!(
+            "💣 New Db connection failed, follower=[{}]",
+            &self.follower
+        )) else {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        };
+
+        let Ok(mut trans) = cnx.begin().await.map_err(err_fwd!(
+            "💣 Transaction issue, follower=[{}]",
+            &self.follower
+        )) else {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        };
+
+        if let Err(e) = self.check_item(&mut trans, item_id, customer_code).await {
+            return WebType::from_errorset(e);
+        }
+
+        let Ok(comments) = self
+            .search_comments(&mut trans, item_id, &customer_key, customer_code)
+            .await
+            .map_err(err_fwd!(
+                "💣 Cannot find the comments, item_id=[{}], follower=[{}]",
+                item_id,
+                &self.follower
+            ))
+        else {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        };
+
+        if trans
+            .commit()
+            .await
+            .map_err(err_fwd!("💣 Commit failed, follower=[{}]", &self.follower))
+            .is_err()
+        {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        }
+
+        log_info!(
+            "🏁 End get_item_comments api, follower=[{}]",
+            &self.follower
+        );
+
+        WebType::from_item(
+            StatusCode::OK.as_u16(),
+            GetItemCommentReply {
+                item_id,
+                comments: visible_comments(comments),
+            },
+        )
+    }
+
+    ///
+    /// 🌟 Comment an item, or reply to a comment of the item
+    ///
+    pub async fn add_item_comment(
+        mut self,
+        item_id: i64,
+        add_comment_request: Json<AddItemCommentRequest>,
+    ) -> WebType<AddItemCommentReply> {
+        log_info!(
+            "🚀 Start add_item_comment api, item_id=[{}], parent_id=[{:?}], follower=[{}]",
+            item_id,
+            add_comment_request.parent_id,
+            &self.follower
+        );
+
+        let entry_session = try_or_return!(
+            valid_sid_get_session(&self.session_token, &mut self.follower).await,
+            Self::web_type_error()
+        );
+
+        let customer_code = entry_session.customer_code.as_str();
+
+        let text = add_comment_request.text.trim();
+        if !is_valid_comment(
+            text,
+            add_comment_request.page,
+            add_comment_request.text_offset,
+        ) {
+            log_error!(
+                "💣 Incorrect comment, page=[{:?}], text_offset=[{:?}], follower=[{}]",
+                add_comment_request.page,
+                add_comment_request.text_offset,
+                &self.follower
+            );
+            return WebType::from_errorset(&INCORRECT_COMMENT);
+        }
+
+        let mentions = normalize_mentions(&add_comment_request.mentions);
+        if let Err(e) = self.check_mentions(&mentions, entry_session.user_id).await {
+            return WebType::from_errorset(e);
+        }
+
+        let Ok(customer_key) = fetch_customer_key(customer_code, &self.follower)
+            .await
+            .map_err(err_fwd!(
+                "💣 Cannot get the customer key, follower=[{}]",
+                &self.follower
+            ))
+        else {
+            return WebType::from_errorset(&INTERNAL_TECHNICAL_ERROR);
+        };
+
+        let Ok(lang) = self
+            .fulltext_delegate()
+            .detect_language(text)
+            .await
+            .map_err(err_fwd!(
+                "💣 Cannot detect the language of the comment, follower=[{}]",
+                &self.follower
+            ))
+        else {
+            return WebType::from_errorset(&INTERNAL_TECHNICAL_ERROR);
+        };
+
+        let Ok(mut cnx) = SQLConnectionAsync::from_pool().await.map_err(err_fwd!(
+            "💣 New Db connection failed, follower=[{}]",
+            &self.follower
+        )) else {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        };
+
+        let Ok(mut trans) = cnx.begin().await.map_err(err_fwd!(
+            "💣 Transaction issue, follower=[{}]",
+            &self.follower
+        )) else {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        };
+
+        if let Err(e) = self.check_item(&mut trans, item_id, customer_code).await {
+            return WebType::from_errorset(e);
+        }
+
+        // A reply is made to a living comment of the same item
+        if let Some(parent_id) = add_comment_request.parent_id {
+            match self
+                .find_comment_owner(&mut trans, item_id, parent_id, customer_code)
+                .await
+            {
+                Ok(Some(parent)) if !parent.deleted => {}
+                Ok(_) => {
+                    log_error!(
+                        "💣 Missing parent comment, item_id=[{}], parent_id=[{}], follower=[{}]",
+                        item_id,
+                        parent_id,
+                        &self.follower
+                    );
+                    return WebType::from_errorset(&MISSING_COMMENT);
+                }
+                Err(_) => return WebType::from_errorset(&INTERNAL_DATABASE_ERROR),
+            }
+        }
+
+        let Ok(encrypted_text) = self
+            .fulltext_delegate()
+            .encrypt_text(&mut trans, text, &lang, &customer_key)
+            .await
+        else {
+            return WebType::from_errorset(&INTERNAL_TECHNICAL_ERROR);
+        };
+
+        let Ok(comment_id) = self
+            .insert_comment(
+                &mut trans,
+                item_id,
+                entry_session.user_id,
+                &add_comment_request,
+                &encrypted_text,
+                customer_code,
+            )
+            .await
+        else {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        };
+
+        if self
+            .insert_mentions(&mut trans, comment_id, &mentions, customer_code)
+            .await
+            .is_err()
+        {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        }
+
+        if trans
+            .commit()
+            .await
+            .map_err(err_fwd!("💣 Commit failed, follower=[{}]", &self.follower))
+            .is_err()
+        {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        }
+
+        log_info!(
+            "😎 Added the comment, item_id=[{}], comment_id=[{}], mentions=[{:?}], follower=[{}]",
+            item_id,
+            comment_id,
+            &mentions,
+            &self.follower
+        );
+        log_info!("🏁 End add_item_comment api, follower=[{}]", &self.follower);
+
+        WebType::from_item(
+            StatusCode::OK.as_u16(),
+            AddItemCommentReply {
+                item_id,
+                comment_id,
+            },
+        )
+    }
+
+    ///
+    /// 🌟 Change the text and the mentions of a comment, only for its author
+    ///
+    pub async fn update_item_comment(
+        mut self,
+        item_id: i64,
+        comment_id: i64,
+        update_comment_request: Json<UpdateItemCommentRequest>,
+    ) -> WebType<SimpleMessage> {
+        log_info!(
+            "🚀 Start update_item_comment api, item_id=[{}], comment_id=[{}], follower=[{}]",
+            item_id,
+            comment_id,
+            &self.follower
+        );
+
+        let entry_session = try_or_return!(
+            valid_sid_get_session(&self.session_token, &mut self.follower).await,
+            Self::web_type_error()
+        );
+
+        let customer_code = entry_session.customer_code.as_str();
+
+        let text = update_comment_request.text.trim();
+        if !is_valid_comment(text, None, None) {
+            log_error!(
+                "💣 Incorrect comment, comment_id=[{}], follower=[{}]",
+                comment_id,
+                &self.follower
+            );
+            return WebType::from_errorset(&INCORRECT_COMMENT);
+        }
+
+        let mentions = normalize_mentions(&update_comment_request.mentions);
+        if let Err(e) = self.check_mentions(&mentions, entry_session.user_id).await {
+            return WebType::from_errorset(e);
+        }
+
+        let Ok(customer_key) = fetch_customer_key(customer_code, &self.follower)
+            .await
+            .map_err(err_fwd!(
+                "💣 Cannot get the customer key, follower=[{}]",
+                &self.follower
+            ))
+        else {
+            return WebType::from_errorset(&INTERNAL_TECHNICAL_ERROR);
+        };
+
+        let Ok(lang) = self
+            .fulltext_delegate()
+            .detect_language(text)
+            .await
+            .map_err(err_fwd!(
+                "💣 Cannot detect the language of the comment, follower=[{}]",
+                &self.follower
+            ))
+        else {
+            return WebType::from_errorset(&INTERNAL_TECHNICAL_ERROR);
+        };
+
+        let Ok(mut cnx) = SQLConnectionAsync::from_pool().await.map_err(err_fwd!(
+            "💣 New Db connection failed, follower=[{}]",
+            &self.follower
+        )) else {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        };
+
+        let Ok(mut trans) = cnx.begin().await.map_err(err_fwd!(
+            "💣 Transaction issue, follower=[{}]",
+            &self.follower
+        )) else {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        };
+
+        if let Err(e) = self
+            .check_author(
+                &mut trans,
+                item_id,
+                comment_id,
+                entry_session.user_id,
+                customer_code,
+            )
+            .await
+        {
+            return WebType::from_errorset(e);
+        }
+
+        let Ok(encrypted_text) = self
+            .fulltext_delegate()
+            .encrypt_text(&mut trans, text, &lang, &customer_key)
+            .await
+        else {
+            return WebType::from_errorset(&INTERNAL_TECHNICAL_ERROR);
+        };
+
+        if self
+            .update_comment(&mut trans, comment_id, &encrypted_text, customer_code)
+            .await
+            .is_err()
+        {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        }
+
+        if self
+            .delete_mentions(&mut trans, comment_id, customer_code)
+            .await
+            .is_err()
+        {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        }
+
+        if self
+            .insert_mentions(&mut trans, comment_id, &mentions, customer_code)
+            .await
+            .is_err()
+        {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        }
+
+        if trans
+            .commit()
+            .await
+            .map_err(err_fwd!("💣 Commit failed, follower=[{}]", &self.follower))
+            .is_err()
+        {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        }
+
+        log_info!(
+            "😎 Changed the comment, item_id=[{}], comment_id=[{}], follower=[{}]",
+            item_id,
+            comment_id,
+            &self.follower
+        );
+        log_info!(
+            "🏁 End update_item_comment api, follower=[{}]",
+            &self.follower
+        );
+
+        WebType::from_item(
+            StatusCode::OK.as_u16(),
+            SimpleMessage {
+                message: "Ok".to_string(),
+            },
+        )
+    }
+
+    ///
+    /// 🌟 Delete a comment, only for its author
+    ///     The text and the mentions are removed, the replies stay in place
+    ///
+    pub async fn delete_item_comment(
+        mut self,
+        item_id: i64,
+        comment_id: i64,
+    ) -> WebType<SimpleMessage> {
+        log_info!(
+            "🚀 Start delete_item_comment api, item_id=[{}], comment_id=[{}], follower=[{}]",
+            item_id,
+            comment_id,
+            &self.follower
+        );
+
+        let entry_session = try_or_return!(
+            valid_sid_get_session(&self.session_token, &mut self.follower).await,
+            Self::web_type_error()
+        );
+
+        let customer_code = entry_session.customer_code.as_str();
+
+        let Ok(mut cnx) = SQLConnectionAsync::from_pool().await.map_err(err_fwd!(
+            "💣 New Db connection failed, follower=[{}]",
+            &self.follower
+        )) else {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        };
+
+        let Ok(mut trans) = cnx.begin().await.map_err(err_fwd!(
+            "💣 Transaction issue, follower=[{}]",
+            &self.follower
+        )) else {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        };
+
+        if let Err(e) = self
+            .check_author(
+                &mut trans,
+                item_id,
+                comment_id,
+                entry_session.user_id,
+                customer_code,
+            )
+            .await
+        {
+            return WebType::from_errorset(e);
+        }
+
+        if self
+            .delete_mentions(&mut trans, comment_id, customer_code)
+            .await
+            .is_err()
+        {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        }
+
+        if self
+            .mark_deleted(&mut trans, comment_id, customer_code)
+            .await
+            .is_err()
+        {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        }
+
+        if trans
+            .commit()
+            .await
+            .map_err(err_fwd!("💣 Commit failed, follower=[{}]", &self.follower))
+            .is_err()
+        {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        }
+
+        log_info!(
+            "😎 Deleted the comment, item_id=[{}], comment_id=[{}], follower=[{}]",
+            item_id,
+            comment_id,
+            &self.follower
+        );
+        log_info!(
+            "🏁 End delete_item_comment api, follower=[{}]",
+            &self.follower
+        );
+
+        WebType::from_item(
+            StatusCode::OK.as_u16(),
+            SimpleMessage {
+                message: "Ok".to_string(),
+            },
+        )
+    }
+
+    /// Ensure the mentioned users are other users of the customer
+    async fn check_mentions(
+        &self,
+        mentions: &[i64],
+        author_id: i64,
+    ) -> Result<(), &'static ErrorSet<'static>> {
+        if mentions.is_empty() {
+            return Ok(());
+        }
+
+        let Ok(admin_server_client) = Self::find_admin_server_client().map_err(err_fwd!(
+            "💣 Cannot read the admin server information, follower=[{}]",
+            &self.follower
+        )) else {
+            return Err(&INTERNAL_TECHNICAL_ERROR);
+        };
+
+        let user_ids: Vec<i64> = match admin_server_client.get_users(&self.session_token.0).await {
+            Ok(reply) => reply.users.iter().map(|u| u.user_id).collect(),
+            Err(e) => {
+                log_error!(
+                    "💣 The admin server failed to give the users, error=[{:?}], follower=[{}]",
+                    e,
+                    &self.follower
+                );
+                return Err(&INTERNAL_TECHNICAL_ERROR);
+            }
+        };
+
+        if let Some(user_id) = unknown_mention(mentions, &user_ids, author_id) {
+            log_error!(
+                "💣 Unknown mentioned user, user_id=[{}], follower=[{}]",
+                user_id,
+                &self.follower
+            );
+            return Err(&UNKNOWN_MENTIONED_USER);
+        }
+        Ok(())
+    }
+
+    fn find_admin_server_client() -> anyhow::Result<AdminServerClientAsync> {
+        let admin_server_host = get_prop_value(ADMIN_SERVER_HOSTNAME_PROPERTY)?;
+        let admin_server_port = get_prop_value(ADMIN_SERVER_PORT_PROPERTY)?.parse::<u16>()?;
+        Ok(AdminServerClientAsync::new(
+            &admin_server_host,
+            admin_server_port,
+        ))
+    }
+
+    /// Ensure the item exists out of the trash
+    async fn check_item(
+        &self,
+        trans: &mut SQLTransactionAsync<'_>,
+        item_id: i64,
+        customer_code: &str,
+    ) -> Result<(), &'static ErrorSet<'static>> {
+        let mut params = HashMap::new();
+        params.insert("p_item_id".to_string(), CellValue::from_raw_int(item_id));
+
+        let query = SQLQueryBlockAsync {
+            sql_query: format!(
+                r"SELECT id FROM cs_{}.item
+                    WHERE id = :p_item_id AND trashed_gmt IS NULL",
+                customer_code
+            ),
+            start: 0,
+            length: Some(1),
+            params,
+        };
+
+        let Ok(mut sql_result) = query.execute(trans).await.map_err(err_fwd!(
+            "💣 Query failed, [{}], follower=[{}]",
+            &query.sql_query,
+            &self.follower
+        )) else {
+            return Err(&INTERNAL_DATABASE_ERROR);
+        };
+
+        if !sql_result.next() {
+            log_error!(
+                "💣 Missing item, item_id=[{}], follower=[{}]",
+                item_id,
+                &self.follower
+            );
+            return Err(&MISSING_ITEM);
+        }
+        Ok(())
+    }
+
+    /// Ensure the comment is a living comment of the item written by the user
+    async fn check_author(
+        &self,
+        trans: &mut SQLTransactionAsync<'_>,
+        item_id: i64,
+        comment_id: i64,
+        user_id: i64,
+        customer_code: &str,
+    ) -> Result<(), &'static ErrorSet<'static>> {
+        self.check_item(trans, item_id, customer_code).await?;
+
+        let Ok(owner) = self
+            .find_comment_owner(trans, item_id, comment_id, customer_code)
+            .await
+        else {
+            return Err(&INTERNAL_DATABASE_ERROR);
+        };
+
+        match owner {
+            Some(owner) if owner.deleted => {
+                log_error!(
+                    "💣 The comment is deleted, comment_id=[{}], follower=[{}]",
+                    comment_id,
+                    &self.follower
+                );
+                Err(&MISSING_COMMENT)
+            }
+            Some(owner) if owner.user_id != user_id => {
+                log_error!(
+                    "💣 The user is not the author of the comment, comment_id=[{}], user_id=[{}], follower=[{}]",
+                    comment_id,
+                    user_id,
+                    &self.follower
+                );
+                Err(&NOT_COMMENT_AUTHOR)
+            }
+            Some(_) => Ok(()),
+            None => {
+                log_error!(
+                    "💣 Missing comment, item_id=[{}], comment_id=[{}], follower=[{}]",
+                    item_id,
+                    comment_id,
+                    &self.follower
+                );
+                Err(&MISSING_COMMENT)
+            }
+        }
+    }
+
+    async fn find_comment_owner(
+        &self,
+        trans: &mut SQLTransactionAsync<'_>,
+        item_id: i64,
+        comment_id: i64,
+        customer_code: &str,
+    ) -> anyhow::Result<Option<CommentOwner>> {
+        let mut params = HashMap::new();
+        params.insert("p_item_id".to_string(), CellValue::from_raw_int(item_id));
+        params.insert(
+            "p_comment_id".to_string(),
+            CellValue::from_raw_int(comment_id),
+        );
+
+        let query = SQLQueryBlockAsync {
+            sql_query: format!(
+                r"SELECT user_id, deleted_gmt IS NOT NULL AS deleted
+                    FROM cs_{}.item_comment
+                    WHERE id = :p_comment_id AND item_id = :p_item_id",
+                customer_code
+            ),
+            start: 0,
+            length: Some(1),
+            params,
+        };
+
+        let mut sql_result: SQLDataSet = query.execute(trans).await.map_err(err_fwd!(
+            "💣 Query failed, [{}], follower=[{}]",
+            &query.sql_query,
+            &self.follower
+        ))?;
+
+        if !sql_result.next() {
+            return Ok(None);
+        }
+
+        Ok(Some(CommentOwner {
+            user_id: sql_result
+                .get_int("user_id")
+                .ok_or(anyhow!("Wrong user id"))?,
+            deleted: sql_result
+                .get_bool("deleted")
+                .ok_or(anyhow!("Wrong deleted flag"))?,
+        }))
+    }
+
+    /// All the comments of the item with their clear text, oldest first
+    async fn search_comments(
+        &self,
+        trans: &mut SQLTransactionAsync<'_>,
+        item_id: i64,
+        customer_key: &str,
+        customer_code: &str,
+    ) -> anyhow::Result<Vec<ItemCommentElement>> {
+        let mut mentions = self.search_mentions(trans, item_id, customer_code).await?;
+
+        let mut params = HashMap::new();
+        params.insert("p_item_id".to_string(), CellValue::from_raw_int(item_id));
+
+        let query = SQLQueryBlockAsync {
+            sql_query: format!(
+                r"SELECT id, parent_id, user_id, comment_text, page_no, text_offset,
+                    created_gmt, edited_gmt, deleted_gmt IS NOT NULL AS deleted
+                    FROM cs_{}.item_comment
+                    WHERE item_id = :p_item_id
+                    ORDER BY id ",
+                customer_code
+            ),
+            start: 0,
+            length: None,
+            params,
+        };
+
+        let mut sql_result: SQLDataSet = query
+            .execute(trans)
+            .await
+            .map_err(err_fwd!("Query failed, [{}]", &query.sql_query))?;
+
+        let mut comments = vec![];
+        while sql_result.next() {
+            let comment_id = sql_result.get_int("id").ok_or(anyhow!("Wrong id"))?;
+            let deleted = sql_result
+                .get_bool("deleted")
+                .ok_or(anyhow!("Wrong deleted flag"))?;
+            let text = if deleted {
+                "".to_string()
+            } else {
+                let encrypted_text = sql_result
+                    .get_string("comment_text")
+                    .ok_or(anyhow!("Wrong comment text"))?;
+                DkEncrypt::new(CC20)
+                    .decrypt_str(&encrypted_text, customer_key)
+                    .map_err(err_fwd!(
+                        "Cannot decrypt the comment, comment_id=[{}], follower=[{}]",
+                        comment_id,
+                        &self.follower
+                    ))?
+            };
+            let created_gmt: DateTime<Utc> = sql_result
+                .get_timestamp_as_datetime("created_gmt")
+                .ok_or(anyhow!("Wrong created gmt"))?;
+
+            comments.push(ItemCommentElement {
+                comment_id,
+                parent_id: sql_result.get_int("parent_id"),
+                user_id: sql_result
+                    .get_int("user_id")
+                    .ok_or(anyhow!("Wrong user id"))?,
+                text,
+                mentions: mentions.remove(&comment_id).unwrap_or_default(),
+                page: sql_result.get_int_32("page_no"),
+                text_offset: sql_result.get_int_32("text_offset"),
+                created: date_time_to_iso(&created_gmt),
+                edited: sql_result
+                    .get_timestamp_as_datetime("edited_gmt")
+                    .map(|d| date_time_to_iso(&d)),
+                deleted,
+            });
+        }
+        Ok(comments)
+    }
+
+    /// Mentioned users of the comments of the item, by comment id
+    async fn search_mentions(
+        &self,
+        trans: &mut SQLTransactionAsync<'_>,
+        item_id: i64,
+        customer_code: &str,
+    ) -> anyhow::Result<HashMap<i64, Vec<i64>>> {
+        let mut params = HashMap::new();
+        params.insert("p_item_id".to_string(), CellValue::from_raw_int(item_id));
+
+        let query = SQLQueryBlockAsync {
+            sql_query: format!(
+                r"SELECT m.comment_id, m.user_id
+                    FROM cs_{0}.item_comment_mention m
+                    INNER JOIN cs_{0}.item_comment c ON c.id = m.comment_id
+                    WHERE c.item_id = :p_item_id
+                    ORDER BY m.comment_id, m.user_id ",
+                customer_code
+            ),
+            start: 0,
+            length: None,
+            params,
+        };
+
+        let mut sql_result: SQLDataSet = query
+            .execute(trans)
+            .await
+            .map_err(err_fwd!("Query failed, [{}]", &query.sql_query))?;
+
+        let mut mentions: HashMap<i64, Vec<i64>> = HashMap::new();
+        while sql_result.next() {
+            let comment_id = sql_result
+                .get_int("comment_id")
+                .ok_or(anyhow!("Wrong comment id"))?;
+            let user_id = sql_result
+                .get_int("user_id")
+                .ok_or(anyhow!("Wrong user id"))?;
+            mentions.entry(comment_id).or_default().push(user_id);
+        }
+        Ok(mentions)
+    }
+
+    async fn insert_comment(
+        &self,
+        trans: &mut SQLTransactionAsync<'_>,
+        item_id: i64,
+        user_id: i64,
+        add_comment_request: &AddItemCommentRequest,
+        encrypted_text: &EncryptedText,
+        customer_code: &str,
+    ) -> anyhow::Result<i64> {
+        // Use a stored proc to hide the TSVECTOR type from Rust
+        let sql_query = format!(
+            r"CALL cs_{}.insert_item_comment( :p_item_id, :p_parent_id, :p_user_id, :p_text, :p_tsv, :p_lang, :p_page, :p_offset, :p_created )",
+            customer_code
+        );
+
+        let mut params = HashMap::new();
+        params.insert("p_item_id".to_string(), CellValue::from_raw_int(item_id));
+        params.insert(
+            "p_parent_id".to_string(),
+            CellValue::Int(add_comment_request.parent_id),
+        );
+        params.insert("p_user_id".to_string(), CellValue::from_raw_int(user_id));
+        params.insert(
+            "p_text".to_string(),
+            CellValue::from_raw_str(&encrypted_text.text),
+        );
+        params.insert(
+            "p_tsv".to_string(),
+            CellValue::from_raw_str(&encrypted_text.tsv),
+        );
+        params.insert(
+            "p_lang".to_string(),
+            CellValue::from_raw_str(&encrypted_text.lang),
+        );
+        params.insert(
+            "p_page".to_string(),
+            CellValue::Int32(add_comment_request.page),
+        );
+        params.insert(
+            "p_offset".to_string(),
+            CellValue::Int32(add_comment_request.text_offset),
+        );
+        params.insert(
+            "p_created".to_string(),
+            CellValue::from_raw_systemtime(SystemTime::now()),
+        );
+
+        let sql_insert = SQLChangeAsync {
+            sql_query,
+            params,
+            sequence_name: format!("cs_{}.item_comment_id_seq", customer_code),
+        };
+
+        sql_insert.insert(trans).await.map_err(err_fwd!(
+            "💣 Cannot insert the comment, item_id=[{}], follower=[{}]",
+            item_id,
+            &self.follower
+        ))
+    }
+
+    async fn update_comment(
+        &self,
+        trans: &mut SQLTransactionAsync<'_>,
+        comment_id: i64,
+        encrypted_text: &EncryptedText,
+        customer_code: &str,
+    ) -> anyhow::Result<()> {
+        // Use a stored proc to hide the TSVECTOR type from Rust
+        let sql_query = format!(
+            r"CALL cs_{}.update_item_comment( :p_comment_id, :p_text, :p_tsv, :p_lang, :p_edited )",
+            customer_code
+        );
+
+        let mut params = HashMap::new();
+        params.insert(
+            "p_comment_id".to_string(),
+            CellValue::from_raw_int(comment_id),
+        );
+        params.insert(
+            "p_text".to_string(),
+            CellValue::from_raw_str(&encrypted_text.text),
+        );
+        params.insert(
+            "p_tsv".to_string(),
+            CellValue::from_raw_str(&encrypted_text.tsv),
+        );
+        params.insert(
+            "p_lang".to_string(),
+            CellValue::from_raw_str(&encrypted_text.lang),
+        );
+        params.insert(
+            "p_edited".to_string(),
+            CellValue::from_raw_systemtime(SystemTime::now()),
+        );
+
+        let sql_update = SQLChangeAsync {
+            sql_query,
+            params,
+            sequence_name: "".to_string(),
+        };
+
+        sql_update.update(trans).await.map_err(err_fwd!(
+            "💣 Cannot change the comment, comment_id=[{}], follower=[{}]",
+            comment_id,
+            &self.follower
+        ))
+    }
+
+    /// Remove the text of the comment, it leaves the full text index as well
+    async fn mark_deleted(
+        &self,
+        trans: &mut SQLTransactionAsync<'_>,
+        comment_id: i64,
+        customer_code: &str,
+    ) -> anyhow::Result<()> {
+        let mut params = HashMap::new();
+        params.insert(
+            "p_comment_id".to_string(),
+            CellValue::from_raw_int(comment_id),
+        );
+        params.insert(
+            "p_deleted".to_string(),
+            CellValue::from_raw_systemtime(SystemTime::now()),
+        );
+
+        let sql_update = SQLChangeAsync {
+            sql_query: format!(
+                r"UPDATE cs_{}.item_comment
+                    SET comment_text = '', tsv = ''::tsvector, deleted_gmt = :p_deleted
+                    WHERE id = :p_comment_id",
+                customer_code
+            ),
+            params,
+            sequence_name: "".to_string(),
+        };
+
+        sql_update.update(trans).await.map_err(err_fwd!(
+            "💣 Cannot delete the comment, comment_id=[{}], follower=[{}]",
+            comment_id,
+            &self.follower
+        ))
+    }
+
+    async fn insert_mentions(
+        &self,
+        trans: &mut SQLTransactionAsync<'_>,
+        comment_id: i64,
+        mentions: &[i64],
+        customer_code: &str,
+    ) -> anyhow::Result<()> {
+        for user_id in mentions {
+            let mut params = HashMap::new();
+            params.insert(
+                "p_comment_id".to_string(),
+                CellValue::from_raw_int(comment_id),
+            );
+            params.insert("p_user_id".to_string(), CellValue::from_raw_int(*user_id));
+
+            let sql_insert = SQLChangeAsync {
+                sql_query: format!(
+                    "INSERT INTO cs_{}.item_comment_mention (comment_id, user_id) VALUES (:p_comment_id, :p_user_id)",
+                    customer_code
+                ),
+                params,
+                sequence_name: "".to_string(),
+            };
+
+            sql_insert.insert_no_pk(trans).await.map_err(err_fwd!(
+                "💣 Cannot insert the mention, comment_id=[{}], user_id=[{}], follower=[{}]",
+                comment_id,
+                user_id,
+                &self.follower
+            ))?;
+        }
+        Ok(())
+    }
+
+    async fn delete_mentions(
+        &self,
+        trans: &mut SQLTransactionAsync<'_>,
+        comment_id: i64,
+        customer_code: &str,
+    ) -> anyhow::Result<()> {
+        let mut params = HashMap::new();
+        params.insert(
+            "p_comment_id".to_string(),
+            CellValue::from_raw_int(comment_id),
+        );
+
+        let sql_delete = SQLChangeAsync {
+            sql_query: format!(
+                "DELETE FROM cs_{}.item_comment_mention WHERE comment_id = :p_comment_id",
+                customer_code
+            ),
+            params,
+            sequence_name: "".to_string(),
+        };
+
+        sql_delete.delete(trans).await.map_err(err_fwd!(
+            "💣 Cannot delete the mentions, comment_id=[{}], follower=[{}]",
+            comment_id,
+            &self.follower
+        ))
+    }
+
+    fn fulltext_delegate(&self) -> FullTextDelegate {
+        FullTextDelegate::new(
+            self.session_token.clone(),
+            self.follower.x_request_id.clone(),
+        )
+    }
+
+    fn web_type_error<T>() -> impl Fn(&ErrorSet<'static>) -> WebType<T>
+    where
+        T: DeserializeOwned,
+    {
+        |e| {
+            log_error!("💣 Error after try {:?}", e);
+            WebType::from_errorset(e)
+        }
+    }
+}
+
+/// A comment has a text and its anchor, if any, is a page from 1 and an offset from 0
+fn is_valid_comment(text: &str, page: Option<i32>, text_offset: Option<i32>) -> bool {
+    !text.is_empty()
+        && text.chars().count() <= MAX_COMMENT_LENGTH
+        && !matches!(page, Some(p) if p < 1)
+        && !matches!(text_offset, Some(o) if o < 0)
+}
+
+/// Each mentioned user once, in order
+fn normalize_mentions(mentions: &[i64]) -> Vec<i64> {
+    let mut mentions = mentions.to_vec();
+    mentions.sort_unstable();
+    mentions.dedup();
+    mentions
+}
+
+/// The first mention that is not another user of the customer
+fn unknown_mention(mentions: &[i64], user_ids: &[i64], author_id: i64) -> Option<i64> {
+    mentions
+        .iter()
+        .find(|m| **m == author_id || !user_ids.contains(m))
+        .copied()
+}
+
+/// Drop the deleted comments, except the ones still holding replies.
+/// The comments are ordered by id, a reply always comes after its parent.
+fn visible_comments(comments: Vec<ItemCommentElement>) -> Vec<ItemCommentElement> {
+    let mut parents_to_keep: HashSet<i64> = HashSet::new();
+    let mut visible = vec![];
+    for comment in comments.into_iter().rev() {
+        if comment.deleted && !parents_to_keep.contains(&comment.comment_id) {
+            continue;
+        }
+        if let Some(parent_id) = comment.parent_id {
+            parents_to_keep.insert(parent_id);
+        }
+        visible.push(comment);
+    }
+    visible.reverse();
+    visible
+}
+
+#[cfg(test)]
+mod test {
+    use dkdto::ItemCommentElement;
+
+    use crate::comment::{is_valid_comment, normalize_mentions, unknown_mention, visible_comments};
+
+    fn comment(comment_id: i64, parent_id: Option<i64>, deleted: bool) -> ItemCommentElement {
+        ItemCommentElement {
+            comment_id,
+            parent_id,
+            user_id: 1,
+            text: if deleted { "" } else { "Hello" }.to_string(),
+            mentions: vec![],
+            page: None,
+            text_offset: None,
+            created: "2023-01-01T00:00:00+00:00".to_string(),
+            edited: None,
+            deleted,
+        }
+    }
+
+    #[test]
+    fn is_valid_comment_test() {
+        assert!(is_valid_comment("Hello", None, None));
+        assert!(is_valid_comment("Hello", Some(1), Some(0)));
+        assert!(!is_valid_comment("", None, None));
+        assert!(!is_valid_comment("Hello", Some(0), None));
+        assert!(!is_valid_comment("Hello", None, Some(-1)));
+        assert!(!is_valid_comment(&"a".repeat(4_001), None, None));
+    }
+
+    #[test]
+    fn normalize_mentions_test() {
+        assert_eq!(vec![2, 5], normalize_mentions(&[5, 2, 5]));
+        assert!(normalize_mentions(&[]).is_empty());
+    }
+
+    #[test]
+    fn unknown_mention_test() {
+        let users = [1, 2, 3];
+        assert_eq!(None, unknown_mention(&[2, 3], &users, 1));
+        assert_eq!(Some(1), unknown_mention(&[1, 2], &users, 1));
+        assert_eq!(Some(9), unknown_mention(&[2, 9], &users, 1));
+    }
+
+    #[test]
+    fn visible_comments_test() {
+        let comments = vec![
+            comment(1, None, true),
+            comment(2, Some(1), false),
+            comment(3, None, true),
+            comment(4, None, false),
+            comment(5, Some(4), true),
+            comment(6, Some(5), true),
+        ];
+        let ids: Vec<i64> = visible_comments(comments)
+            .iter()
+            .map(|c| c.comment_id)
+            .collect();
+        assert_eq!(vec![1, 2, 4], ids);
+    }
+}
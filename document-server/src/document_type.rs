@@ -0,0 +1,691 @@
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use anyhow::anyhow;
+use axum::http::StatusCode;
+use axum::Json;
+use log::{error, info};
+use serde::de::DeserializeOwned;
+
+use commons_error::*;
+use commons_pg::sql_transaction::{CellValue, SQLDataSet};
+use commons_pg::sql_transaction_async::{
+    SQLChangeAsync, SQLConnectionAsync, SQLQueryBlockAsync, SQLTransactionAsync,
+};
+use commons_services::session_lib::valid_sid_get_session;
+use commons_services::token_lib::SessionToken;
+use commons_services::try_or_return;
+use commons_services::x_request_id::{Follower, XRequestID};
+use dkdto::error_codes::{
+    DOCUMENT_TYPE_NAME_ALREADY_TAKEN, INCORRECT_DOCUMENT_TYPE, INTERNAL_DATABASE_ERROR,
+    MISSING_TAG, STILL_IN_USE,
+};
+use dkdto::{
+    AddDocumentTypeReply, AddDocumentTypeRequest, DocumentTypeElement, DocumentTypeTagElement,
+    EnumTagValue, ErrorSet, GetDocumentTypeReply, SimpleMessage, TagElement, TagType, WebType,
+    WebTypeBuilder,
+};
+use doka_cli::request_client::TokenType;
+
+use crate::TagDelegate;
+
+pub(crate) struct DocumentTypeDelegate {
+    pub session_token: SessionToken,
+    pub follower: Follower,
+}
+
+impl DocumentTypeDelegate {
+    pub fn new(session_token: SessionToken, x_request_id: XRequestID) -> Self {
+        Self {
+            session_token,
+            follower: Follower {
+                x_request_id: x_request_id.new_if_null(),
+                token_type: TokenType::None,
+            },
+        }
+    }
+
+    ///
+    /// 🌟 Find all the existing document types by pages
+    ///
+    pub async fn get_all_document_type(
+        mut self,
+        start_page: Option<u32>,
+        page_size: Option<u32>,
+    ) -> WebType<GetDocumentTypeReply> {
+        log_info!(
+            "🚀 Start get_all_document_type api, follower=[{}]",
+            &self.follower
+        );
+
+        let entry_session = try_or_return!(
+            valid_sid_get_session(&self.session_token, &mut self.follower).await,
+            Self::web_type_error()
+        );
+
+        let customer_code = entry_session.customer_code.as_str();
+
+        // Open Db connection
+        let Ok(mut cnx) = SQLConnectionAsync::from_pool().await.map_err(err_fwd!(
+            "💣 New Db connection failed, follower=[{}]",
+            &self.follower
+        )) else {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        };
+
+        let Ok(mut trans) = cnx.begin().await.map_err(err_fwd!(
+            "💣 Transaction issue, follower=[{}]",
+            &self.follower
+        )) else {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        };
+
+        let Ok(document_types) = self
+            .search_document_types(&mut trans, None, start_page, page_size, customer_code)
+            .await
+            .map_err(err_fwd!(
+                "💣 Cannot find the document types, follower=[{}]",
+                &self.follower
+            ))
+        else {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        };
+
+        if trans
+            .commit()
+            .await
+            .map_err(err_fwd!("💣 Commit failed, follower=[{}]", &self.follower))
+            .is_err()
+        {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        }
+
+        log_info!(
+            "🏁 End get_all_document_type api, follower=[{}]",
+            &self.follower
+        );
+
+        WebType::from_item(
+            StatusCode::OK.as_u16(),
+            GetDocumentTypeReply { document_types },
+        )
+    }
+
+    ///
+    /// 🌟 Create a new document type
+    ///
+    pub async fn add_document_type(
+        mut self,
+        add_document_type_request: Json<AddDocumentTypeRequest>,
+    ) -> WebType<AddDocumentTypeReply> {
+        log_info!(
+            "🚀 Start add_document_type api, add_document_type_request=[{:?}], follower=[{}]",
+            &add_document_type_request,
+            &self.follower
+        );
+
+        let entry_session = try_or_return!(
+            valid_sid_get_session(&self.session_token, &mut self.follower).await,
+            Self::web_type_error()
+        );
+
+        let customer_code = entry_session.customer_code.as_str();
+
+        log_info!(
+            "😎 We found the session, customer code=[{}], follower=[{}]",
+            customer_code,
+            &self.follower
+        );
+
+        if let Err(e) = Self::check_input_values(&add_document_type_request) {
+            log_error!(
+                "💣 Document type definition is not correct, err message=[{}], follower=[{}]",
+                e.err_message,
+                &self.follower
+            );
+            return WebType::from_errorset(e);
+        }
+
+        // Open Db connection
+        let Ok(mut cnx) = SQLConnectionAsync::from_pool().await.map_err(err_fwd!(
+            "💣 New Db connection failed, follower=[{}]",
+            &self.follower
+        )) else {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        };
+
+        let Ok(mut trans) = cnx.begin().await.map_err(err_fwd!(
+            "💣 Transaction issue, follower=[{}]",
+            &self.follower
+        )) else {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        };
+
+        let Ok(o_existing) = self
+            .search_document_type_by_name(
+                &mut trans,
+                &add_document_type_request.name,
+                customer_code,
+            )
+            .await
+            .map_err(err_fwd!(
+                "💣 Cannot read the document type, follower=[{}]",
+                &self.follower
+            ))
+        else {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        };
+
+        if o_existing.is_some() {
+            log_error!(
+                "💣 The document type already exists, name=[{}], follower=[{}]",
+                &add_document_type_request.name,
+                &self.follower
+            );
+            return WebType::from_errorset(&DOCUMENT_TYPE_NAME_ALREADY_TAKEN);
+        }
+
+        // Every tag of the type must be defined, with a default value suitable for it
+        let tag_delegate = TagDelegate::new(
+            self.session_token.clone(),
+            self.follower.x_request_id.clone(),
+        );
+        let mut tag_ids = vec![];
+        for type_tag in &add_document_type_request.tags {
+            let Ok(tag) = tag_delegate
+                .search_tag_by_name(&mut trans, &type_tag.tag_name, customer_code)
+                .await
+            else {
+                log_error!(
+                    "💣 Unknown tag in the document type, tag_name=[{}], follower=[{}]",
+                    &type_tag.tag_name,
+                    &self.follower
+                );
+                return WebType::from_errorset(&MISSING_TAG);
+            };
+
+            if let Some(default_value) = &type_tag.default_value {
+                if !Self::is_valid_default_value(&tag, default_value) {
+                    log_error!(
+                        "💣 Incorrect default value, tag_name=[{}], default_value=[{}], follower=[{}]",
+                        &type_tag.tag_name,
+                        default_value,
+                        &self.follower
+                    );
+                    return WebType::from_errorset(&INCORRECT_DOCUMENT_TYPE);
+                }
+            }
+            tag_ids.push(tag.tag_id);
+        }
+
+        let Ok(document_type_id) = self
+            .insert_document_type(
+                &mut trans,
+                &add_document_type_request,
+                &tag_ids,
+                customer_code,
+            )
+            .await
+            .map_err(err_fwd!(
+                "💣 Insertion of a new document type failed, follower=[{}]",
+                &self.follower
+            ))
+        else {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        };
+
+        if trans
+            .commit()
+            .await
+            .map_err(err_fwd!("💣 Commit failed, follower=[{}]", &self.follower))
+            .is_err()
+        {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        }
+
+        log_info!(
+            "😎 The document type has been created, document_type_id=[{}], follower=[{}]",
+            document_type_id,
+            &self.follower
+        );
+        log_info!(
+            "🏁 End add_document_type api, follower=[{}]",
+            &self.follower
+        );
+
+        WebType::from_item(
+            StatusCode::OK.as_u16(),
+            AddDocumentTypeReply { document_type_id },
+        )
+    }
+
+    ///
+    /// 🌟 Delete a document type, only if no item refers to it
+    ///
+    pub async fn delete_document_type(mut self, document_type_id: i64) -> WebType<SimpleMessage> {
+        log_info!(
+            "🚀 Start delete_document_type api, document_type_id=[{}], follower=[{}]",
+            document_type_id,
+            &self.follower
+        );
+
+        let entry_session = try_or_return!(
+            valid_sid_get_session(&self.session_token, &mut self.follower).await,
+            Self::web_type_error()
+        );
+
+        let customer_code = entry_session.customer_code.as_str();
+
+        // Open Db connection
+        let Ok(mut cnx) = SQLConnectionAsync::from_pool().await.map_err(err_fwd!(
+            "💣 New Db connection failed, follower=[{}]",
+            &self.follower
+        )) else {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        };
+
+        let Ok(mut trans) = cnx.begin().await.map_err(err_fwd!(
+            "💣 Transaction issue, follower=[{}]",
+            &self.follower
+        )) else {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        };
+
+        let Ok(in_use) = self
+            .is_document_type_used(&mut trans, document_type_id, customer_code)
+            .await
+            .map_err(err_fwd!(
+                "💣 Cannot check the document type usage, follower=[{}]",
+                &self.follower
+            ))
+        else {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        };
+
+        if in_use {
+            log_error!(
+                "💣 The document type is still in use, document_type_id=[{}], follower=[{}]",
+                document_type_id,
+                &self.follower
+            );
+            return WebType::from_errorset(&STILL_IN_USE);
+        }
+
+        let mut params = HashMap::new();
+        params.insert(
+            "p_document_type_id".to_string(),
+            CellValue::from_raw_int(document_type_id),
+        );
+
+        for table in ["document_type_tag", "document_type"] {
+            let column = if table == "document_type" {
+                "id"
+            } else {
+                "document_type_id"
+            };
+            let sql_delete = SQLChangeAsync {
+                sql_query: format!(
+                    r"DELETE FROM cs_{}.{} WHERE {} = :p_document_type_id",
+                    customer_code, table, column
+                ),
+                params: params.clone(),
+                sequence_name: "".to_string(),
+            };
+
+            if sql_delete
+                .delete(&mut trans)
+                .await
+                .map_err(err_fwd!(
+                    "💣 Document type delete failed, table=[{}], document_type_id=[{}], follower=[{}]",
+                    table,
+                    document_type_id,
+                    &self.follower
+                ))
+                .is_err()
+            {
+                return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+            }
+        }
+
+        if trans
+            .commit()
+            .await
+            .map_err(err_fwd!("💣 Commit failed, follower=[{}]", &self.follower))
+            .is_err()
+        {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        }
+
+        log_info!(
+            "🏁 End delete_document_type api, follower=[{}]",
+            &self.follower
+        );
+
+        WebType::from_item(
+            StatusCode::OK.as_u16(),
+            SimpleMessage {
+                message: "Ok".to_string(),
+            },
+        )
+    }
+
+    /// Find the document type with its tags
+    pub(crate) async fn search_document_type_by_name(
+        &self,
+        trans: &mut SQLTransactionAsync<'_>,
+        name: &str,
+        customer_code: &str,
+    ) -> anyhow::Result<Option<DocumentTypeElement>> {
+        let document_types = self
+            .search_document_types(trans, Some(name), None, None, customer_code)
+            .await
+            .map_err(tr_fwd!())?;
+        Ok(document_types.into_iter().next())
+    }
+
+    /// Search the document types by name
+    /// If no name provided, return all the existing types
+    async fn search_document_types(
+        &self,
+        trans: &mut SQLTransactionAsync<'_>,
+        name: Option<&str>,
+        start_page: Option<u32>,
+        page_size: Option<u32>,
+        customer_code: &str,
+    ) -> anyhow::Result<Vec<DocumentTypeElement>> {
+        let mut params = HashMap::new();
+        params.insert("p_name".to_owned(), CellValue::from_opt_str(name));
+
+        let sql_query = format!(
+            r"SELECT id, name FROM cs_{}.document_type
+                    WHERE ( name = :p_name OR :p_name IS NULL )
+                    ORDER BY name ",
+            customer_code
+        );
+
+        let query = SQLQueryBlockAsync {
+            sql_query,
+            start: start_page.unwrap_or(0) * page_size.unwrap_or(0),
+            length: page_size,
+            params,
+        };
+
+        let mut sql_result: SQLDataSet = query.execute(trans).await.map_err(err_fwd!(
+            "Query failed, sql=[{}], follower=[{}]",
+            &query.sql_query,
+            &self.follower
+        ))?;
+
+        let mut document_types = vec![];
+        while sql_result.next() {
+            let id: i64 = sql_result.get_int("id").ok_or(anyhow!("Wrong id"))?;
+            let name: String = sql_result.get_string("name").ok_or(anyhow!("Wrong name"))?;
+            document_types.push(DocumentTypeElement {
+                document_type_id: id,
+                name,
+                tags: vec![],
+            });
+        }
+
+        for document_type in document_types.iter_mut() {
+            document_type.tags = self
+                .search_document_type_tags(trans, document_type.document_type_id, customer_code)
+                .await
+                .map_err(tr_fwd!())?;
+        }
+
+        Ok(document_types)
+    }
+
+    /// Find the tags of a document type, in their definition order
+    /// The default value of the type falls back on the default value of the tag definition
+    async fn search_document_type_tags(
+        &self,
+        trans: &mut SQLTransactionAsync<'_>,
+        document_type_id: i64,
+        customer_code: &str,
+    ) -> anyhow::Result<Vec<DocumentTypeTagElement>> {
+        let mut params = HashMap::new();
+        params.insert(
+            "p_document_type_id".to_owned(),
+            CellValue::from_raw_int(document_type_id),
+        );
+
+        let sql_query = format!(
+            r"SELECT td.id, td.name, td.type, dtt.required,
+                    COALESCE(dtt.default_value, td.default_value) AS default_value
+                    FROM cs_{0}.document_type_tag dtt
+                    INNER JOIN cs_{0}.tag_definition td ON td.id = dtt.tag_id
+                    WHERE dtt.document_type_id = :p_document_type_id
+                    ORDER BY dtt.sort_order ",
+            customer_code
+        );
+
+        let query = SQLQueryBlockAsync {
+            sql_query,
+            start: 0,
+            length: None,
+            params,
+        };
+
+        let mut sql_result: SQLDataSet = query.execute(trans).await.map_err(err_fwd!(
+            "Query failed, sql=[{}], follower=[{}]",
+            &query.sql_query,
+            &self.follower
+        ))?;
+
+        let mut tags = vec![];
+        while sql_result.next() {
+            let tag_id: i64 = sql_result.get_int("id").ok_or(anyhow!("Wrong id"))?;
+            let tag_name: String = sql_result.get_string("name").ok_or(anyhow!("Wrong name"))?;
+            let tag_type = sql_result
+                .get_string("type")
+                .ok_or(anyhow!("Wrong tag_type"))?;
+            let required = sql_result.get_bool("required").unwrap_or(false);
+            let default_value = sql_result.get_string("default_value");
+
+            tags.push(DocumentTypeTagElement {
+                tag_id,
+                tag_name,
+                tag_type,
+                required,
+                default_value,
+            });
+        }
+
+        Ok(tags)
+    }
+
+    /// Insert the document type and its tags, the order of the tags is kept
+    async fn insert_document_type(
+        &self,
+        trans: &mut SQLTransactionAsync<'_>,
+        add_document_type_request: &AddDocumentTypeRequest,
+        tag_ids: &[i64],
+        customer_code: &str,
+    ) -> anyhow::Result<i64> {
+        let mut params = HashMap::new();
+        params.insert(
+            "p_name".to_string(),
+            CellValue::from_raw_string(add_document_type_request.name.clone()),
+        );
+
+        let sql_insert = SQLChangeAsync {
+            sql_query: format!(
+                r"INSERT INTO cs_{}.document_type(name) VALUES (:p_name)",
+                customer_code
+            ),
+            params,
+            sequence_name: format!("cs_{}.document_type_id_seq", customer_code),
+        };
+
+        let document_type_id = sql_insert.insert(trans).await.map_err(err_fwd!(
+            "💣 Insertion of a new document type failed, follower=[{}]",
+            &self.follower
+        ))?;
+
+        for (i, (type_tag, tag_id)) in add_document_type_request
+            .tags
+            .iter()
+            .zip(tag_ids)
+            .enumerate()
+        {
+            let mut params = HashMap::new();
+            params.insert(
+                "p_document_type_id".to_string(),
+                CellValue::from_raw_int(document_type_id),
+            );
+            params.insert("p_tag_id".to_string(), CellValue::from_raw_int(*tag_id));
+            params.insert(
+                "p_required".to_string(),
+                CellValue::Bool(Some(type_tag.required.unwrap_or(false))),
+            );
+            params.insert(
+                "p_default_value".to_string(),
+                CellValue::from_opt_str(type_tag.default_value.as_deref()),
+            );
+            params.insert("p_sort_order".to_string(), CellValue::Int32(Some(i as i32)));
+
+            let sql_insert = SQLChangeAsync {
+                sql_query: format!(
+                    r"INSERT INTO cs_{}.document_type_tag(document_type_id, tag_id, required, default_value, sort_order)
+                        VALUES (:p_document_type_id, :p_tag_id, :p_required, :p_default_value, :p_sort_order)",
+                    customer_code
+                ),
+                params,
+                sequence_name: format!("cs_{}.document_type_tag_id_seq", customer_code),
+            };
+
+            let _ = sql_insert.insert(trans).await.map_err(err_fwd!(
+                "💣 Insertion of a document type tag failed, tag_id=[{}], follower=[{}]",
+                tag_id,
+                &self.follower
+            ))?;
+        }
+
+        Ok(document_type_id)
+    }
+
+    async fn is_document_type_used(
+        &self,
+        trans: &mut SQLTransactionAsync<'_>,
+        document_type_id: i64,
+        customer_code: &str,
+    ) -> anyhow::Result<bool> {
+        let mut params = HashMap::new();
+        params.insert(
+            "p_document_type_id".to_owned(),
+            CellValue::from_raw_int(document_type_id),
+        );
+
+        let query = SQLQueryBlockAsync {
+            sql_query: format!(
+                r"SELECT 1 FROM cs_{}.item WHERE document_type_id = :p_document_type_id",
+                customer_code
+            ),
+            start: 0,
+            length: Some(1),
+            params,
+        };
+
+        let dataset = query.execute(trans).await.map_err(tr_fwd!())?;
+        Ok(dataset.len() > 0)
+    }
+
+    /// The default value must be of the tag type and respect its constraints
+    fn is_valid_default_value(tag: &TagElement, default_value: &str) -> bool {
+        let Ok(tag_type) = TagType::from_str(tag.tag_type.to_lowercase().as_str()) else {
+            return false;
+        };
+        let Ok(value) = EnumTagValue::from_string(default_value, tag_type.as_str()) else {
+            return false;
+        };
+        if let Some(allowed_values) = &tag.allowed_values {
+            if !allowed_values.iter().any(|v| v == default_value) {
+                return false;
+            }
+        }
+        TagDelegate::check_value_constraints(&tag_type, &tag.constraints, &value).is_ok()
+    }
+
+    ///
+    /// The name must be printable, the list of tags non empty and without duplicates
+    ///
+    fn check_input_values(
+        add_document_type_request: &AddDocumentTypeRequest,
+    ) -> Result<(), &'static ErrorSet<'static>> {
+        const MAX_NAME_LENGTH: usize = 50;
+        let name = &add_document_type_request.name;
+        if name.trim().is_empty()
+            || name.chars().count() > MAX_NAME_LENGTH
+            || name.chars().any(|c| c.is_control())
+        {
+            return Err(&INCORRECT_DOCUMENT_TYPE);
+        }
+
+        let tags = &add_document_type_request.tags;
+        if tags.is_empty() {
+            return Err(&INCORRECT_DOCUMENT_TYPE);
+        }
+        for (i, tag) in tags.iter().enumerate() {
+            if tags[..i].iter().any(|t| t.tag_name == tag.tag_name) {
+                return Err(&INCORRECT_DOCUMENT_TYPE);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn web_type_error<T>() -> impl Fn(&ErrorSet<'static>) -> WebType<T>
+    where
+        T: DeserializeOwned,
+    {
+        |e| {
+            log_error!("💣 Error after try {:?}", e);
+            WebType::from_errorset(e)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use dkdto::{AddDocumentTypeRequest, DocumentTypeTag};
+
+    use crate::document_type::DocumentTypeDelegate;
+
+    #[test]
+    fn check_input_values_test() {
+        let request = |name: &str, tags: &[&str]| AddDocumentTypeRequest {
+            name: name.to_string(),
+            tags: tags
+                .iter()
+                .map(|t| DocumentTypeTag {
+                    tag_name: t.to_string(),
+                    required: None,
+                    default_value: None,
+                })
+                .collect(),
+        };
+
+        assert!(DocumentTypeDelegate::check_input_values(&request(
+            "Invoice",
+            &["amount", "due_date"]
+        ))
+        .is_ok());
+        assert!(
+            DocumentTypeDelegate::check_input_values(&request("Pay slip", &["employee"])).is_ok()
+        );
+
+        assert!(DocumentTypeDelegate::check_input_values(&request("", &["amount"])).is_err());
+        assert!(DocumentTypeDelegate::check_input_values(&request("Invoice", &[])).is_err());
+        assert!(DocumentTypeDelegate::check_input_values(&request(
+            "Invoice",
+            &["amount", "amount"]
+        ))
+        .is_err());
+        assert!(
+            DocumentTypeDelegate::check_input_values(&request(&"x".repeat(51), &["amount"]))
+                .is_err()
+        );
+    }
+}
@@ -4,12 +4,14 @@ use crate::filter::filter_lexer::{lex3, FilterError, FilterErrorCode};
 use crate::filter::filter_normalizer::normalize_lexeme;
 use crate::parser_log;
 use chrono::format::Numeric::Second;
+use chrono::{DateTime, NaiveDate};
 use commons_error::*;
 use dkdto::{ClearTextReply, TagElement, TagType};
 use log::*;
-use std::cmp::PartialEq;
+use std::cmp::{Ordering, PartialEq};
 use std::collections::{HashMap, HashSet};
 use std::fmt;
+use std::str::FromStr;
 
 mod filter_ast;
 mod filter_lexer;
@@ -210,6 +212,7 @@ pub(crate) fn extract_boolean_filter(
     Ok(content)
 }
 
+/// Sql condition on a single tag value (alias "tv") of the given type
 fn generate_tag_value_filter(
     filter_condition: &FilterCondition,
     tag_type: &TagType,
@@ -224,47 +227,206 @@ fn generate_tag_value_filter(
         ComparisonOperator::LIKE => "LIKE",
     };
 
+    let value_mismatch = || GenerationError::ValueMismatch(filter_condition.attribute.clone());
+
+    if filter_condition.operator == ComparisonOperator::LIKE
+        && !matches!(tag_type, TagType::Text | TagType::Link | TagType::Enum)
+    {
+        return Err(value_mismatch());
+    }
+
+    let raw_value = match &filter_condition.value {
+        FilterValue::ValueInt(i) => i.to_string(),
+        FilterValue::ValueString(s) => s.clone(),
+        FilterValue::ValueBool(b) => b.to_string(),
+    };
+
     let tag_value_filter = match tag_type {
-        TagType::Text => {
+        TagType::Text | TagType::Link | TagType::Enum => {
             //unaccent_lower((tv.value_string)::text) LIKE unaccent_lower('ab%')
             format!(
-                "unaccent_lower((tv.value_string)::text) {0} unaccent_lower('{1}')",
-                &sql_op, &filter_condition.value
+                "unaccent_lower((tv.value_string)::text) {0} unaccent_lower({1})",
+                &sql_op,
+                sql_string_literal(&raw_value)
             )
         }
         TagType::Bool => {
             // science == true
-            format!("tv.value_boolean {0} {1}", &sql_op, &filter_condition.value)
+            let b = raw_value
+                .to_lowercase()
+                .parse::<bool>()
+                .map_err(|_| value_mismatch())?;
+            format!("tv.value_boolean {0} {1}", &sql_op, b)
         }
         TagType::Int => {
-            format!("tv.value_integer {0} {1}", &sql_op, &filter_condition.value)
+            let i = raw_value.parse::<i64>().map_err(|_| value_mismatch())?;
+            format!("tv.value_integer {0} {1}", &sql_op, i)
         }
         TagType::Double => {
-            format!("tv.value_double {0} {1}", &sql_op, &filter_condition.value)
+            let d = raw_value.parse::<f64>().map_err(|_| value_mismatch())?;
+            format!("tv.value_double {0} {1}", &sql_op, d)
         }
         TagType::Date => {
-            todo!();
+            let d =
+                NaiveDate::parse_from_str(&raw_value, "%Y-%m-%d").map_err(|_| value_mismatch())?;
+            format!(
+                "tv.value_date {0} DATE '{1}'",
+                &sql_op,
+                d.format("%Y-%m-%d")
+            )
         }
         TagType::DateTime => {
-            todo!();
-        }
-        TagType::Link => {
-            todo!();
+            let dt = DateTime::parse_from_rfc3339(&raw_value).map_err(|_| value_mismatch())?;
+            format!(
+                "tv.value_datetime {0} TIMESTAMP '{1}'",
+                &sql_op,
+                dt.naive_utc().format("%Y-%m-%d %H:%M:%S")
+            )
         }
     };
 
     Ok(tag_value_filter)
 }
 
+fn sql_string_literal(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "''"))
+}
+
+/// All the tag names used in the filter conditions
+pub(crate) fn extract_all_attributes(
+    filter_expression_ast: &FilterExpressionAST,
+) -> Result<HashSet<String>, GenerationError> {
+    let filter_conditions = vectorize_conditions(filter_expression_ast)?;
+    Ok(filter_conditions
+        .into_iter()
+        .map(|fc| fc.attribute)
+        .collect())
+}
+
+/// Build the sql condition on the items (alias "it") from the filter AST.
+/// Each condition is an EXISTS on the tag values of the item,
+/// so a condition on a multi-valued tag holds as soon as any of its values matches.
+pub(crate) fn generate_item_condition(
+    filter_expression_ast: &FilterExpressionAST,
+    tags: &HashMap<String, TagElement>,
+    customer_code: &str,
+) -> Result<String, GenerationError> {
+    let mut content: String = String::from("");
+    match filter_expression_ast {
+        FilterExpressionAST::Condition(filter_condition) => {
+            let tag = tags
+                .get(&filter_condition.attribute)
+                .ok_or(GenerationError::TagUnknown(
+                    filter_condition.attribute.clone(),
+                ))?;
+            let tag_type = TagType::from_str(&tag.tag_type)
+                .map_err(|_| GenerationError::TagTypeUnknown(tag.tag_type.clone()))?;
+            let tag_value_filter = generate_tag_value_filter(filter_condition, &tag_type)?;
+            let s = format!(
+                " EXISTS ( SELECT 1 FROM cs_{0}.tag_value tv WHERE tv.item_id = it.id AND tv.tag_id = {1} AND {2} ) ",
+                customer_code, tag.tag_id, tag_value_filter
+            );
+            content.push_str(&s);
+        }
+        FilterExpressionAST::Logical { operator, leaves } => {
+            content.push_str("(");
+
+            for (i, l) in leaves.iter().enumerate() {
+                let leaf = generate_item_condition(l, tags, customer_code)?;
+                content.push_str(&leaf);
+                if i < leaves.len() - 1 {
+                    content.push_str(&format!(" {:?} ", &operator));
+                }
+            }
+            content.push_str(")");
+        }
+    }
+    Ok(content)
+}
+
+/// Evaluate the filter on named attributes, for instance the attributes of a file ("mime", "text", ...)
+/// As in the sql condition, the text comparisons ignore the case
+/// and a condition on a missing attribute is false.
+pub(crate) fn matches_attributes(
+    filter_expression_ast: &FilterExpressionAST,
+    attributes: &HashMap<String, String>,
+) -> bool {
+    match filter_expression_ast {
+        FilterExpressionAST::Condition(filter_condition) => attributes
+            .get(&filter_condition.attribute.to_lowercase())
+            .map_or(false, |value| matches_condition(filter_condition, value)),
+        FilterExpressionAST::Logical { operator, leaves } => match operator {
+            LogicalOperator::AND => leaves.iter().all(|l| matches_attributes(l, attributes)),
+            LogicalOperator::OR => leaves.iter().any(|l| matches_attributes(l, attributes)),
+        },
+    }
+}
+
+fn matches_condition(filter_condition: &FilterCondition, value: &str) -> bool {
+    let ordering = match &filter_condition.value {
+        FilterValue::ValueInt(i) => match value.trim().parse::<i64>() {
+            Ok(v) => v.cmp(&(*i as i64)),
+            Err(_) => return false,
+        },
+        FilterValue::ValueBool(b) => match value.trim().to_lowercase().parse::<bool>() {
+            Ok(v) => v.cmp(b),
+            Err(_) => return false,
+        },
+        FilterValue::ValueString(s) => {
+            if filter_condition.operator == ComparisonOperator::LIKE {
+                return like_match(&value.to_lowercase(), &s.to_lowercase());
+            }
+            value.to_lowercase().cmp(&s.to_lowercase())
+        }
+    };
+
+    match filter_condition.operator {
+        ComparisonOperator::EQ | ComparisonOperator::LIKE => ordering == Ordering::Equal,
+        ComparisonOperator::NEQ => ordering != Ordering::Equal,
+        ComparisonOperator::GT => ordering == Ordering::Greater,
+        ComparisonOperator::GTE => ordering != Ordering::Less,
+        ComparisonOperator::LT => ordering == Ordering::Less,
+        ComparisonOperator::LTE => ordering != Ordering::Greater,
+    }
+}
+
+/// Sql LIKE, "%" matches any sequence of characters and "_" a single character
+fn like_match(value: &str, pattern: &str) -> bool {
+    let value: Vec<char> = value.chars().collect();
+    let pattern: Vec<char> = pattern.chars().collect();
+
+    let (mut v, mut p) = (0, 0);
+    // Position after the last "%" in the pattern and the position in the value where it stopped
+    let mut last_percent: Option<(usize, usize)> = None;
+    while v < value.len() {
+        if p < pattern.len() && pattern[p] == '%' {
+            p += 1;
+            last_percent = Some((p, v));
+        } else if p < pattern.len() && (pattern[p] == '_' || pattern[p] == value[v]) {
+            p += 1;
+            v += 1;
+        } else if let Some((after_percent, percent_end)) = last_percent {
+            // The last "%" takes one more character
+            p = after_percent;
+            v = percent_end + 1;
+            last_percent = Some((after_percent, v));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '%')
+}
+
 enum SearchSqlGenerationMode {
     Live,
     Persisted,
 }
 
 #[derive(Debug)]
-enum GenerationError {
+pub(crate) enum GenerationError {
     TagUnknown(String),
     TagTypeUnknown(String),
+    ValueMismatch(String),
 }
 
 impl fmt::Display for GenerationError {
@@ -326,12 +488,15 @@ mod tests {
 
     use crate::filter::filter_ast::{parse_tokens, to_canonical_form};
     use crate::filter::{
-        analyse_expression, extract_all_conditions, extract_boolean_filter, to_sql_form,
-        ComparisonOperator, FilterExpressionAST,
+        analyse_expression, extract_all_conditions, extract_boolean_filter,
+        generate_item_condition, like_match, matches_attributes, to_sql_form, ComparisonOperator,
+        FilterExpressionAST,
     };
     use crate::parser_log;
     use commons_error::*;
+    use dkdto::TagElement;
     use log::*;
+    use std::collections::HashMap;
     use std::sync::Once;
 
     static INIT_LOGGER: Once = Once::new();
@@ -387,6 +552,89 @@ mod tests {
         assert_eq!(EXPECTED, &boolean_filter);
     }
 
+    fn tag_element(tag_id: i64, name: &str, tag_type: &str, multi_valued: bool) -> TagElement {
+        TagElement {
+            tag_id,
+            name: name.to_owned(),
+            tag_type: tag_type.to_owned(),
+            default_value: None,
+            allowed_values: None,
+            multi_valued,
+            constraints: Default::default(),
+        }
+    }
+
+    #[test]
+    pub fn generate_item_condition_1() {
+        let input1 = "(keyword == \"rust\" AND score >= 50)";
+        let tree1 = analyse_expression(input1).unwrap();
+        let mut tags = HashMap::new();
+        tags.insert(
+            "keyword".to_owned(),
+            tag_element(1, "keyword", "text", true),
+        );
+        tags.insert("score".to_owned(), tag_element(2, "score", "int", false));
+
+        let condition = generate_item_condition(tree1.as_ref(), &tags, "abc").unwrap();
+
+        // A multi-valued tag matches when any of its values matches, hence the EXISTS
+        const EXPECTED : &str = "( EXISTS ( SELECT 1 FROM cs_abc.tag_value tv WHERE tv.item_id = it.id AND tv.tag_id = 1 AND unaccent_lower((tv.value_string)::text) = unaccent_lower('rust') )  AND  EXISTS ( SELECT 1 FROM cs_abc.tag_value tv WHERE tv.item_id = it.id AND tv.tag_id = 2 AND tv.value_integer >= 50 ) )";
+        assert_eq!(EXPECTED, &condition);
+    }
+
+    #[test]
+    pub fn generate_item_condition_fail() {
+        let mut tags = HashMap::new();
+        tags.insert("score".to_owned(), tag_element(2, "score", "int", false));
+
+        let tree1 = analyse_expression("(score == \"high\")").unwrap();
+        assert!(generate_item_condition(tree1.as_ref(), &tags, "abc").is_err());
+
+        let tree2 = analyse_expression("(unknown == 12)").unwrap();
+        assert!(generate_item_condition(tree2.as_ref(), &tags, "abc").is_err());
+    }
+
+    #[test]
+    pub fn like_match_test() {
+        assert!(like_match("invoice 2024", "invoice%"));
+        assert!(like_match("the invoice of may", "%invoice%"));
+        assert!(like_match("invoice", "%invoice"));
+        assert!(like_match("invoice", "inv_ice"));
+        assert!(like_match("aab", "%ab"));
+        assert!(like_match("", "%"));
+        assert!(!like_match("invoices", "invoice"));
+        assert!(!like_match("an invoice", "invoice%"));
+        assert!(!like_match("invoice", "inv_ce"));
+    }
+
+    #[test]
+    pub fn matches_attributes_test() {
+        let mut attributes = HashMap::new();
+        attributes.insert("mime".to_owned(), "application/pdf".to_owned());
+        attributes.insert("text".to_owned(), "Invoice n° 12, amount 30".to_owned());
+        attributes.insert("meta_author".to_owned(), "Denis".to_owned());
+        attributes.insert("page_count".to_owned(), "3".to_owned());
+
+        let matches = |expression: &str| {
+            let tree = analyse_expression(expression).unwrap();
+            matches_attributes(tree.as_ref(), &attributes)
+        };
+
+        assert!(matches(
+            "(mime == \"application/pdf\" AND text LIKE \"%invoice%\" AND meta_author == \"denis\")"
+        ));
+        assert!(matches("(page_count >= 3 AND page_count < 10)"));
+        assert!(matches(
+            "(mime == \"image/png\" OR meta_author LIKE \"D%\")"
+        ));
+        assert!(!matches(
+            "(mime == \"application/pdf\" AND text LIKE \"%receipt%\")"
+        ));
+        assert!(!matches("(page_count > 3)"));
+        // A missing attribute never matches
+        assert!(!matches("(meta_title != \"Invoice\")"));
+    }
+
     // Failure case
 
     #[test]
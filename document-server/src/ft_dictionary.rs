@@ -0,0 +1,793 @@
+use std::collections::{HashMap, HashSet};
+
+use anyhow::anyhow;
+use axum::http::StatusCode;
+use axum::Json;
+use log::*;
+use serde::de::DeserializeOwned;
+
+use commons_error::*;
+use commons_pg::sql_transaction::{CellValue, SQLDataSet};
+use commons_pg::sql_transaction_async::{
+    SQLChangeAsync, SQLConnectionAsync, SQLQueryBlockAsync, SQLTransactionAsync,
+};
+use commons_services::key_lib::fetch_customer_key;
+use commons_services::session_lib::valid_sid_get_session;
+use commons_services::token_lib::SessionToken;
+use commons_services::try_or_return;
+use commons_services::x_request_id::{Follower, XRequestID};
+use dkconfig::properties::get_prop_value;
+use dkconfig::property_name::{FILE_SERVER_HOSTNAME_PROPERTY, FILE_SERVER_PORT_PROPERTY};
+use dkdto::error_codes::{
+    INCORRECT_FULLTEXT_DICTIONARY, INTERNAL_DATABASE_ERROR, INTERNAL_TECHNICAL_ERROR,
+};
+use dkdto::{
+    ErrorSet, FullTextDictionaryReply, ReindexRequest, UpdateFullTextDictionaryReply,
+    UpdateFullTextDictionaryRequest, WebType, WebTypeBuilder,
+};
+use doka_cli::async_request_client::FileServerClientAsync;
+use doka_cli::request_client::TokenType;
+
+use crate::ft_tokenizer::{encrypt_tsquery, remove_stop_lexemes, tsquery_lexemes};
+
+/// The reindex job of the file server is started on all the files of the customer
+const ALL_FILES_PATTERN: &str = "*";
+/// Max length of a stop word or a synonym
+const MAX_DICTIONARY_WORD_LENGTH: usize = 100;
+
+/// The dictionary of the customer, normalized like the tsvectors of a language
+#[derive(Default)]
+pub(crate) struct LanguageDictionary {
+    pub stop_lexemes: HashSet<String>,
+    /// lexeme -> the clear tsqueries of its synonyms
+    pub synonyms: HashMap<String, Vec<String>>,
+}
+
+pub(crate) struct FullTextDictionaryDelegate {
+    pub session_token: SessionToken,
+    pub follower: Follower,
+}
+
+impl FullTextDictionaryDelegate {
+    pub fn new(session_token: SessionToken, x_request_id: XRequestID) -> Self {
+        Self {
+            session_token,
+            follower: Follower {
+                x_request_id: x_request_id.new_if_null(),
+                token_type: TokenType::None,
+            },
+        }
+    }
+
+    ///
+    /// 🌟 Get the stop words and the synonym groups of the customer
+    ///
+    pub async fn get_dictionary(mut self) -> WebType<FullTextDictionaryReply> {
+        log_info!("🚀 Start get_dictionary api, follower=[{}]", &self.follower);
+
+        let entry_session = try_or_return!(
+            valid_sid_get_session(&self.session_token, &mut self.follower).await,
+            Self::web_type_error()
+        );
+
+        let customer_code = entry_session.customer_code.as_str();
+
+        let Ok(mut cnx) = SQLConnectionAsync::from_pool().await.map_err(err_fwd!(
+            "💣 New Db connection failed, follower=[{}]",
+            &self.follower
+        )) else {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        };
+
+        let Ok(mut trans) = cnx.begin().await.map_err(err_fwd!(
+            "💣 Transaction issue, follower=[{}]",
+            &self.follower
+        )) else {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        };
+
+        let Ok(stop_words) = self
+            .search_stop_words(&mut trans, customer_code)
+            .await
+            .map_err(err_fwd!(
+                "💣 Cannot read the stop words, follower=[{}]",
+                &self.follower
+            ))
+        else {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        };
+
+        let Ok(synonyms) = self
+            .search_synonyms(&mut trans, customer_code)
+            .await
+            .map_err(err_fwd!(
+                "💣 Cannot read the synonyms, follower=[{}]",
+                &self.follower
+            ))
+        else {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        };
+
+        if trans
+            .commit()
+            .await
+            .map_err(err_fwd!("💣 Commit failed, follower=[{}]", &self.follower))
+            .is_err()
+        {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        }
+
+        log_info!("🏁 End get_dictionary api, follower=[{}]", &self.follower);
+
+        WebType::from_item(
+            StatusCode::OK.as_u16(),
+            FullTextDictionaryReply {
+                stop_words,
+                synonyms,
+            },
+        )
+    }
+
+    ///
+    /// 🌟 Replace the stop words and the synonym groups of the customer
+    ///     When documents are concerned by the changed stop words, a reindex job is started
+    ///     on the file server, their tsvectors are computed again in the background
+    ///     The synonyms are only used at search time
+    ///
+    pub async fn update_dictionary(
+        mut self,
+        update_request: Json<UpdateFullTextDictionaryRequest>,
+    ) -> WebType<UpdateFullTextDictionaryReply> {
+        log_info!(
+            "🚀 Start update_dictionary api, update_request=[{:?}], follower=[{}]",
+            &update_request,
+            &self.follower
+        );
+
+        let entry_session = try_or_return!(
+            valid_sid_get_session(&self.session_token, &mut self.follower).await,
+            Self::web_type_error()
+        );
+
+        let customer_code = entry_session.customer_code.as_str();
+
+        if let Err(e) = Self::check_dictionary(&update_request) {
+            log_error!(
+                "💣 The dictionary is not correct, err message=[{}], follower=[{}]",
+                e.err_message,
+                &self.follower
+            );
+            return WebType::from_errorset(e);
+        }
+
+        let Ok(customer_key) = fetch_customer_key(customer_code, &self.follower)
+            .await
+            .map_err(err_fwd!(
+                "💣 Cannot get the customer key, follower=[{}]",
+                &self.follower
+            ))
+        else {
+            return WebType::from_errorset(&INTERNAL_TECHNICAL_ERROR);
+        };
+
+        let Ok(mut cnx) = SQLConnectionAsync::from_pool().await.map_err(err_fwd!(
+            "💣 New Db connection failed, follower=[{}]",
+            &self.follower
+        )) else {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        };
+
+        let Ok(mut trans) = cnx.begin().await.map_err(err_fwd!(
+            "💣 Transaction issue, follower=[{}]",
+            &self.follower
+        )) else {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        };
+
+        let Ok(former_stop_words) = self
+            .search_stop_words(&mut trans, customer_code)
+            .await
+            .map_err(err_fwd!(
+                "💣 Cannot read the stop words, follower=[{}]",
+                &self.follower
+            ))
+        else {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        };
+
+        // | Replace the dictionary
+        if self
+            .replace_dictionary(&mut trans, &update_request, customer_code)
+            .await
+            .map_err(err_fwd!(
+                "💣 Cannot replace the dictionary, follower=[{}]",
+                &self.follower
+            ))
+            .is_err()
+        {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        }
+
+        // | Index the concerned document parts again, with the new stop words
+        let stop_words: Vec<String> = update_request
+            .stop_words
+            .iter()
+            .map(|w| w.trim().to_string())
+            .collect();
+        let added_stop_words: Vec<String> = stop_words
+            .iter()
+            .filter(|w| !former_stop_words.contains(w))
+            .cloned()
+            .collect();
+        let has_removed_stop_words = former_stop_words.iter().any(|w| !stop_words.contains(w));
+
+        let Ok(has_concerned_parts) = self
+            .has_concerned_parts(
+                &mut trans,
+                &added_stop_words,
+                has_removed_stop_words,
+                &customer_key,
+                customer_code,
+            )
+            .await
+            .map_err(err_fwd!(
+                "💣 Cannot find the concerned document parts, follower=[{}]",
+                &self.follower
+            ))
+        else {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        };
+
+        if trans
+            .commit()
+            .await
+            .map_err(err_fwd!("💣 Commit failed, follower=[{}]", &self.follower))
+            .is_err()
+        {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        }
+
+        // | The file server indexes the documents again, with the new stop words
+        let reindex_job_id = if has_concerned_parts {
+            self.start_reindex_job().await
+        } else {
+            None
+        };
+
+        log_info!(
+            "😎 Replaced the dictionary, reindex_job_id=[{:?}], follower=[{}]",
+            reindex_job_id,
+            &self.follower
+        );
+        log_info!(
+            "🏁 End update_dictionary api, follower=[{}]",
+            &self.follower
+        );
+
+        WebType::from_item(
+            StatusCode::OK.as_u16(),
+            UpdateFullTextDictionaryReply { reindex_job_id },
+        )
+    }
+
+    /// The dictionary of the customer for a language of the documents
+    pub(crate) async fn load_language_dictionary(
+        &self,
+        trans: &mut SQLTransactionAsync<'_>,
+        lang: &str,
+        customer_code: &str,
+    ) -> anyhow::Result<LanguageDictionary> {
+        let stop_query = SQLQueryBlockAsync {
+            sql_query: format!(
+                r"SELECT DISTINCT (unnest(to_tsvector('{}', unaccent_lower(word)))).lexeme AS lexeme
+                    FROM cs_{}.fulltext_stop_word ",
+                lang, customer_code
+            ),
+            start: 0,
+            length: None,
+            params: HashMap::new(),
+        };
+
+        let mut sql_result: SQLDataSet = stop_query.execute(trans).await.map_err(err_fwd!(
+            "💣 Query failed, [{}], follower=[{}]",
+            &stop_query.sql_query,
+            &self.follower
+        ))?;
+
+        let mut stop_lexemes = HashSet::new();
+        while sql_result.next() {
+            stop_lexemes.insert(
+                sql_result
+                    .get_string("lexeme")
+                    .ok_or(anyhow!("Wrong lexeme"))?,
+            );
+        }
+
+        let synonym_query = SQLQueryBlockAsync {
+            sql_query: format!(
+                r"SELECT group_no, CAST( phraseto_tsquery('{}', unaccent_lower(word)) as VARCHAR ) as tsq
+                    FROM cs_{}.fulltext_synonym
+                    ORDER BY group_no, id ",
+                lang, customer_code
+            ),
+            start: 0,
+            length: None,
+            params: HashMap::new(),
+        };
+
+        let mut sql_result: SQLDataSet = synonym_query.execute(trans).await.map_err(err_fwd!(
+            "💣 Query failed, [{}], follower=[{}]",
+            &synonym_query.sql_query,
+            &self.follower
+        ))?;
+
+        let mut groups: Vec<Vec<String>> = vec![];
+        let mut current_group_no = None;
+        while sql_result.next() {
+            let group_no = sql_result
+                .get_int_32("group_no")
+                .ok_or(anyhow!("Wrong group no"))?;
+            let tsquery = sql_result.get_string("tsq").unwrap_or_default();
+            if current_group_no != Some(group_no) {
+                groups.push(vec![]);
+                current_group_no = Some(group_no);
+            }
+            if let Some(group) = groups.last_mut() {
+                group.push(remove_stop_lexemes(&tsquery, &stop_lexemes));
+            }
+        }
+
+        Ok(LanguageDictionary {
+            stop_lexemes,
+            synonyms: synonym_alternatives(&groups),
+        })
+    }
+
+    /// Tell if some document parts must be indexed again
+    /// All the parts when a stop word is removed, since its lexeme is not in the tsvectors anymore,
+    /// otherwise only the parts holding the added stop words
+    async fn has_concerned_parts(
+        &self,
+        trans: &mut SQLTransactionAsync<'_>,
+        added_stop_words: &[String],
+        has_removed_stop_words: bool,
+        customer_key: &str,
+        customer_code: &str,
+    ) -> anyhow::Result<bool> {
+        if added_stop_words.is_empty() && !has_removed_stop_words {
+            return Ok(false);
+        }
+
+        for lang in self.document_languages(trans, customer_code).await? {
+            if has_removed_stop_words {
+                return Ok(true);
+            }
+            let lexemes = self
+                .select_stop_lexemes(trans, &lang, added_stop_words)
+                .await?;
+            if lexemes.is_empty() {
+                continue;
+            }
+            let clear_tsquery = lexemes
+                .iter()
+                .map(|l| format!("'{}'", l.replace('\'', "''")))
+                .collect::<Vec<String>>()
+                .join(" | ");
+            let tsquery = encrypt_tsquery(&clear_tsquery, customer_key);
+            if self
+                .has_matching_part(trans, &lang, &tsquery, customer_code)
+                .await?
+            {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    /// Start the reindex job of all the files of the customer on the file server
+    /// A failure is only logged, the job can be started with "doka-cli file reindex"
+    async fn start_reindex_job(&self) -> Option<i64> {
+        let Ok(file_server_client) = Self::find_file_server_client().map_err(err_fwd!(
+            "💣 Cannot read the file server information, follower=[{}]",
+            &self.follower
+        )) else {
+            return None;
+        };
+
+        let request = ReindexRequest {
+            pattern: Some(ALL_FILES_PATTERN.to_string()),
+            filters: None,
+        };
+        match file_server_client
+            .start_reindex(&request, &self.session_token.0)
+            .await
+        {
+            Ok(job) => Some(job.job_id),
+            Err(e) => {
+                log_warn!(
+                    "⛔ The reindex job is not started, error=[{:?}], follower=[{}]",
+                    e,
+                    &self.follower
+                );
+                None
+            }
+        }
+    }
+
+    /// The lexemes of the words for the language
+    async fn select_stop_lexemes(
+        &self,
+        trans: &mut SQLTransactionAsync<'_>,
+        lang: &str,
+        words: &[String],
+    ) -> anyhow::Result<Vec<String>> {
+        let mut params = HashMap::new();
+        params.insert(
+            "p_words".to_string(),
+            CellValue::from_raw_string(words.join(" ")),
+        );
+
+        let query = SQLQueryBlockAsync {
+            sql_query: format!(
+                r"SELECT DISTINCT (unnest(to_tsvector('{}', unaccent_lower(:p_words)))).lexeme AS lexeme ",
+                lang
+            ),
+            start: 0,
+            length: None,
+            params,
+        };
+
+        let mut sql_result: SQLDataSet = query.execute(trans).await.map_err(err_fwd!(
+            "💣 Query failed, [{}], follower=[{}]",
+            &query.sql_query,
+            &self.follower
+        ))?;
+
+        let mut lexemes = vec![];
+        while sql_result.next() {
+            lexemes.push(
+                sql_result
+                    .get_string("lexeme")
+                    .ok_or(anyhow!("Wrong lexeme"))?,
+            );
+        }
+        Ok(lexemes)
+    }
+
+    /// Tell if a part of the language matches the encrypted [tsquery]
+    async fn has_matching_part(
+        &self,
+        trans: &mut SQLTransactionAsync<'_>,
+        lang: &str,
+        tsquery: &str,
+        customer_code: &str,
+    ) -> anyhow::Result<bool> {
+        let mut params = HashMap::new();
+        params.insert("p_lang".to_string(), CellValue::from_raw_str(lang));
+        params.insert("p_tsquery".to_string(), CellValue::from_raw_str(tsquery));
+
+        let query = SQLQueryBlockAsync {
+            sql_query: format!(
+                r"SELECT id FROM cs_{}.document
+                    WHERE lang = :p_lang AND tsv @@ CAST(:p_tsquery AS tsquery) ",
+                customer_code
+            ),
+            start: 0,
+            length: Some(1),
+            params,
+        };
+
+        let mut sql_result: SQLDataSet = query.execute(trans).await.map_err(err_fwd!(
+            "💣 Query failed, [{}], follower=[{}]",
+            &query.sql_query,
+            &self.follower
+        ))?;
+
+        Ok(sql_result.next())
+    }
+
+    /// The languages of the indexed documents
+    async fn document_languages(
+        &self,
+        trans: &mut SQLTransactionAsync<'_>,
+        customer_code: &str,
+    ) -> anyhow::Result<Vec<String>> {
+        let query = SQLQueryBlockAsync {
+            sql_query: format!(
+                r"SELECT DISTINCT lang FROM cs_{}.document ORDER BY lang ",
+                customer_code
+            ),
+            start: 0,
+            length: None,
+            params: HashMap::new(),
+        };
+
+        let mut sql_result: SQLDataSet = query.execute(trans).await.map_err(err_fwd!(
+            "💣 Query failed, [{}], follower=[{}]",
+            &query.sql_query,
+            &self.follower
+        ))?;
+
+        let mut languages = vec![];
+        while sql_result.next() {
+            languages.push(sql_result.get_string("lang").ok_or(anyhow!("Wrong lang"))?);
+        }
+        Ok(languages)
+    }
+
+    async fn search_stop_words(
+        &self,
+        trans: &mut SQLTransactionAsync<'_>,
+        customer_code: &str,
+    ) -> anyhow::Result<Vec<String>> {
+        let query = SQLQueryBlockAsync {
+            sql_query: format!(
+                r"SELECT word FROM cs_{}.fulltext_stop_word ORDER BY id ",
+                customer_code
+            ),
+            start: 0,
+            length: None,
+            params: HashMap::new(),
+        };
+
+        let mut sql_result: SQLDataSet = query.execute(trans).await.map_err(err_fwd!(
+            "💣 Query failed, [{}], follower=[{}]",
+            &query.sql_query,
+            &self.follower
+        ))?;
+
+        let mut words = vec![];
+        while sql_result.next() {
+            words.push(sql_result.get_string("word").ok_or(anyhow!("Wrong word"))?);
+        }
+        Ok(words)
+    }
+
+    async fn search_synonyms(
+        &self,
+        trans: &mut SQLTransactionAsync<'_>,
+        customer_code: &str,
+    ) -> anyhow::Result<Vec<Vec<String>>> {
+        let query = SQLQueryBlockAsync {
+            sql_query: format!(
+                r"SELECT group_no, word FROM cs_{}.fulltext_synonym ORDER BY group_no, id ",
+                customer_code
+            ),
+            start: 0,
+            length: None,
+            params: HashMap::new(),
+        };
+
+        let mut sql_result: SQLDataSet = query.execute(trans).await.map_err(err_fwd!(
+            "💣 Query failed, [{}], follower=[{}]",
+            &query.sql_query,
+            &self.follower
+        ))?;
+
+        let mut groups: Vec<Vec<String>> = vec![];
+        let mut current_group_no = None;
+        while sql_result.next() {
+            let group_no = sql_result
+                .get_int_32("group_no")
+                .ok_or(anyhow!("Wrong group no"))?;
+            let word = sql_result.get_string("word").ok_or(anyhow!("Wrong word"))?;
+            if current_group_no != Some(group_no) {
+                groups.push(vec![]);
+                current_group_no = Some(group_no);
+            }
+            if let Some(group) = groups.last_mut() {
+                group.push(word);
+            }
+        }
+        Ok(groups)
+    }
+
+    /// Delete the former stop words and synonyms, then insert the new ones
+    async fn replace_dictionary(
+        &self,
+        trans: &mut SQLTransactionAsync<'_>,
+        update_request: &UpdateFullTextDictionaryRequest,
+        customer_code: &str,
+    ) -> anyhow::Result<()> {
+        for table in ["fulltext_stop_word", "fulltext_synonym"] {
+            let sql_delete = SQLChangeAsync {
+                sql_query: format!(r"DELETE FROM cs_{}.{}", customer_code, table),
+                params: HashMap::new(),
+                sequence_name: "".to_string(),
+            };
+            sql_delete.delete(trans).await.map_err(tr_fwd!())?;
+        }
+
+        for word in update_request.stop_words.iter() {
+            let mut params = HashMap::new();
+            params.insert("p_word".to_string(), CellValue::from_raw_str(word.trim()));
+
+            let sql_insert = SQLChangeAsync {
+                sql_query: format!(
+                    r"INSERT INTO cs_{}.fulltext_stop_word(word) VALUES (:p_word)",
+                    customer_code
+                ),
+                params,
+                sequence_name: format!("cs_{}.fulltext_stop_word_id_seq", customer_code),
+            };
+
+            let _ = sql_insert.insert(trans).await.map_err(err_fwd!(
+                "💣 Insertion of a stop word failed, word=[{}], follower=[{}]",
+                word,
+                &self.follower
+            ))?;
+        }
+
+        for (group_no, group) in update_request.synonyms.iter().enumerate() {
+            for word in group {
+                let mut params = HashMap::new();
+                params.insert(
+                    "p_group_no".to_string(),
+                    CellValue::from_raw_int_32(group_no as i32),
+                );
+                params.insert("p_word".to_string(), CellValue::from_raw_str(word.trim()));
+
+                let sql_insert = SQLChangeAsync {
+                    sql_query: format!(
+                        r"INSERT INTO cs_{}.fulltext_synonym(group_no, word) VALUES (:p_group_no, :p_word)",
+                        customer_code
+                    ),
+                    params,
+                    sequence_name: format!("cs_{}.fulltext_synonym_id_seq", customer_code),
+                };
+
+                let _ = sql_insert.insert(trans).await.map_err(err_fwd!(
+                    "💣 Insertion of a synonym failed, word=[{}], follower=[{}]",
+                    word,
+                    &self.follower
+                ))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// A stop word is a single word, a synonym group holds at least 2 distinct words or expressions
+    fn check_dictionary(
+        update_request: &UpdateFullTextDictionaryRequest,
+    ) -> Result<(), &'static ErrorSet<'static>> {
+        let is_valid_word = |word: &str| {
+            !word.is_empty()
+                && word.len() <= MAX_DICTIONARY_WORD_LENGTH
+                && !word.chars().any(|c| c.is_control())
+        };
+
+        let mut stop_words = HashSet::new();
+        for word in update_request.stop_words.iter().map(|w| w.trim()) {
+            if !is_valid_word(word)
+                || word.contains(char::is_whitespace)
+                || !stop_words.insert(word.to_lowercase())
+            {
+                return Err(&INCORRECT_FULLTEXT_DICTIONARY);
+            }
+        }
+
+        for group in update_request.synonyms.iter() {
+            let mut words = HashSet::new();
+            for word in group.iter().map(|w| w.trim()) {
+                if !is_valid_word(word) || !words.insert(word.to_lowercase()) {
+                    return Err(&INCORRECT_FULLTEXT_DICTIONARY);
+                }
+            }
+            if words.len() < 2 {
+                return Err(&INCORRECT_FULLTEXT_DICTIONARY);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn find_file_server_client() -> anyhow::Result<FileServerClientAsync> {
+        let file_server_host = get_prop_value(FILE_SERVER_HOSTNAME_PROPERTY)?;
+        let file_server_port = get_prop_value(FILE_SERVER_PORT_PROPERTY)?.parse::<u16>()?;
+        Ok(FileServerClientAsync::new(
+            &file_server_host,
+            file_server_port,
+        ))
+    }
+
+    fn web_type_error<T>() -> impl Fn(&ErrorSet<'static>) -> WebType<T>
+    where
+        T: DeserializeOwned,
+    {
+        |e| {
+            log_error!("💣 Error after try {:?}", e);
+            WebType::from_errorset(e)
+        }
+    }
+}
+
+/// For each single lexeme of a synonym group, the clear tsqueries of all the synonyms of the group
+/// The expressions of several words are only alternatives, they are not searched in the query
+fn synonym_alternatives(groups: &[Vec<String>]) -> HashMap<String, Vec<String>> {
+    let mut synonyms: HashMap<String, Vec<String>> = HashMap::new();
+    for group in groups {
+        let alternatives: Vec<String> = group.iter().filter(|q| !q.is_empty()).cloned().collect();
+        for tsquery in alternatives.iter() {
+            let lexemes = tsquery_lexemes(tsquery);
+            if lexemes.len() != 1 {
+                continue;
+            }
+            let entry = synonyms.entry(lexemes[0].clone()).or_default();
+            for alternative in alternatives.iter() {
+                if !entry.contains(alternative) {
+                    entry.push(alternative.clone());
+                }
+            }
+        }
+    }
+    synonyms
+}
+
+#[cfg(test)]
+mod test {
+    use dkdto::UpdateFullTextDictionaryRequest;
+
+    use crate::ft_dictionary::{synonym_alternatives, FullTextDictionaryDelegate};
+
+    fn words(w: &[&str]) -> Vec<String> {
+        w.iter().map(|x| x.to_string()).collect()
+    }
+
+    #[test]
+    fn check_dictionary_test() {
+        let request = |stop_words: &[&str], synonyms: &[&[&str]]| UpdateFullTextDictionaryRequest {
+            stop_words: words(stop_words),
+            synonyms: synonyms.iter().map(|g| words(g)).collect(),
+        };
+
+        assert!(
+            FullTextDictionaryDelegate::check_dictionary(&request(&["art", "sa"], &[])).is_ok()
+        );
+        assert!(FullTextDictionaryDelegate::check_dictionary(&request(
+            &[],
+            &[&["LLC", "limited liability company"], &["invoice", "bill"]]
+        ))
+        .is_ok());
+
+        assert!(FullTextDictionaryDelegate::check_dictionary(&request(&["art", ""], &[])).is_err());
+        assert!(
+            FullTextDictionaryDelegate::check_dictionary(&request(&["two words"], &[])).is_err()
+        );
+        assert!(
+            FullTextDictionaryDelegate::check_dictionary(&request(&["Art", "art"], &[])).is_err()
+        );
+        assert!(
+            FullTextDictionaryDelegate::check_dictionary(&request(&[], &[&["invoice"]])).is_err()
+        );
+        assert!(FullTextDictionaryDelegate::check_dictionary(&request(
+            &[],
+            &[&["invoice", "Invoice "]]
+        ))
+        .is_err());
+        assert!(FullTextDictionaryDelegate::check_dictionary(&request(
+            &["x".repeat(101).as_str()],
+            &[]
+        ))
+        .is_err());
+    }
+
+    #[test]
+    fn synonym_alternatives_test() {
+        let groups = vec![
+            words(&["'llc'", "'limit' <-> 'liabil' <-> 'compani'"]),
+            words(&["'invoic'", "'bill'", ""]),
+        ];
+        let synonyms = synonym_alternatives(&groups);
+
+        assert_eq!(3, synonyms.len());
+        assert_eq!(
+            &words(&["'llc'", "'limit' <-> 'liabil' <-> 'compani'"]),
+            synonyms.get("llc").unwrap()
+        );
+        assert_eq!(
+            &words(&["'invoic'", "'bill'"]),
+            synonyms.get("bill").unwrap()
+        );
+        assert!(!synonyms.contains_key("limit"));
+    }
+}
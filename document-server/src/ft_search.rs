@@ -0,0 +1,800 @@
+use std::collections::{HashMap, HashSet};
+
+use anyhow::anyhow;
+use axum::http::StatusCode;
+use log::*;
+use serde::de::DeserializeOwned;
+
+use commons_error::*;
+use commons_pg::sql_transaction::{CellValue, SQLDataSet};
+use commons_pg::sql_transaction_async::{
+    SQLConnectionAsync, SQLQueryBlockAsync, SQLTransactionAsync,
+};
+use commons_services::key_lib::fetch_customer_key;
+use commons_services::session_lib::valid_sid_get_session;
+use commons_services::token_lib::SessionToken;
+use commons_services::try_or_return;
+use commons_services::x_request_id::{Follower, XRequestID};
+use dkcrypto::dk_crypto::CypherMode::CC20;
+use dkcrypto::dk_crypto::DkEncrypt;
+use dkdto::error_codes::{
+    INCORRECT_FULLTEXT_QUERY, INTERNAL_DATABASE_ERROR, INTERNAL_TECHNICAL_ERROR,
+};
+use dkdto::{ErrorSet, FullTextHitElement, FullTextSearchReply, WebType, WebTypeBuilder};
+use doka_cli::request_client::TokenType;
+
+use crate::ft_dictionary::{FullTextDictionaryDelegate, LanguageDictionary};
+use crate::ft_tokenizer::{
+    encrypt_tsquery, expand_synonyms, near_tsquery, remove_stop_lexemes, tsquery_lexemes,
+    tsvector_lexemes, FTTokenizer,
+};
+use crate::fulltext::FullTextDelegate;
+
+/// Number of words around a matched word in a snippet
+const SNIPPET_CONTEXT_WORDS: usize = 8;
+/// Max number of snippets for a hit
+const MAX_SNIPPETS: usize = 3;
+const HIT_START_MARK: &str = "<mark>";
+const HIT_END_MARK: &str = "</mark>";
+/// Max number of words between the two words of NEAR/N
+const MAX_NEAR_DISTANCE: u32 = 20;
+/// Number of hits of a page, each hit decrypts its text for the snippets
+const DEFAULT_HIT_PAGE_SIZE: u32 = 20;
+const MAX_HIT_PAGE_SIZE: u32 = 100;
+
+/// A term of the full text query, all the terms must match
+#[derive(Debug, PartialEq)]
+enum QueryTerm {
+    /// Words found anywhere in the text, ex: income year
+    Words(String),
+    /// Words found in sequence, ex: "net income"
+    Phrase(String),
+    /// Two words at most N words apart in any order, ex: income NEAR/5 year
+    Near(String, String, u32),
+}
+
+#[derive(Debug)]
+enum QueryToken {
+    Word(String),
+    Phrase(String),
+    Near(u32),
+}
+
+/// The query normalized for one language of the documents
+struct LanguageQuery {
+    lang: String,
+    lexemes: HashSet<String>,
+    tsquery: String,
+}
+
+pub(crate) struct FullTextSearchDelegate {
+    pub session_token: SessionToken,
+    pub follower: Follower,
+}
+
+impl FullTextSearchDelegate {
+    pub fn new(session_token: SessionToken, x_request_id: XRequestID) -> Self {
+        Self {
+            session_token,
+            follower: Follower {
+                x_request_id: x_request_id.new_if_null(),
+                token_type: TokenType::None,
+            },
+        }
+    }
+
+    ///
+    /// 🌟 Find the items whose file or one of the comments contains all the words of the query,
+    ///     at page [start_page], 20 hits per page by default and 100 at most
+    ///     Each hit comes with a few snippets of its text, the matched words are marked
+    ///
+    pub async fn search_fulltext(
+        mut self,
+        query: &str,
+        start_page: Option<u32>,
+        page_size: Option<u32>,
+    ) -> WebType<FullTextSearchReply> {
+        log_info!(
+            "🚀 Start search_fulltext api, query=[{}], start_page=[{:?}], page_size=[{:?}], follower=[{}]",
+            query,
+            start_page,
+            page_size,
+            &self.follower
+        );
+
+        let entry_session = try_or_return!(
+            valid_sid_get_session(&self.session_token, &mut self.follower).await,
+            Self::web_type_error()
+        );
+
+        let page_size = page_size
+            .unwrap_or(DEFAULT_HIT_PAGE_SIZE)
+            .clamp(1, MAX_HIT_PAGE_SIZE);
+
+        let Some(terms) = parse_fulltext_query(query) else {
+            log_error!(
+                "💣 No word to search or wrong syntax in the query, query=[{}], follower=[{}]",
+                query,
+                &self.follower
+            );
+            return WebType::from_errorset(&INCORRECT_FULLTEXT_QUERY);
+        };
+
+        let customer_code = entry_session.customer_code.as_str();
+
+        let Ok(customer_key) = fetch_customer_key(customer_code, &self.follower)
+            .await
+            .map_err(err_fwd!(
+                "💣 Cannot get the customer key, follower=[{}]",
+                &self.follower
+            ))
+        else {
+            return WebType::from_errorset(&INTERNAL_TECHNICAL_ERROR);
+        };
+
+        let Ok(mut cnx) = SQLConnectionAsync::from_pool().await.map_err(err_fwd!(
+            "💣 New Db connection failed, follower=[{}]",
+            &self.follower
+        )) else {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        };
+
+        let Ok(mut trans) = cnx.begin().await.map_err(err_fwd!(
+            "💣 Transaction issue, follower=[{}]",
+            &self.follower
+        )) else {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        };
+
+        let Ok(queries) = self
+            .build_language_queries(&mut trans, &terms, &customer_key, customer_code)
+            .await
+            .map_err(err_fwd!(
+                "💣 Cannot build the queries, follower=[{}]",
+                &self.follower
+            ))
+        else {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        };
+
+        let Ok(mut hits) = self
+            .search_hits(&mut trans, &queries, start_page, page_size, customer_code)
+            .await
+            .map_err(err_fwd!(
+                "💣 Cannot find the hits, follower=[{}]",
+                &self.follower
+            ))
+        else {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        };
+
+        // Snippets only for the page we return
+        for hit in hits.iter_mut() {
+            hit.snippets = try_or_return!(
+                self.build_hit_snippets(
+                    &mut trans,
+                    hit.item_id,
+                    hit.file_ref.as_deref(),
+                    &queries,
+                    &customer_key,
+                    customer_code
+                )
+                .await,
+                Self::web_type_error()
+            );
+        }
+
+        if trans
+            .commit()
+            .await
+            .map_err(err_fwd!("💣 Commit failed, follower=[{}]", &self.follower))
+            .is_err()
+        {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        }
+
+        log_info!(
+            "😎 Found the hits, count=[{}], follower=[{}]",
+            hits.len(),
+            &self.follower
+        );
+        log_info!("🏁 End search_fulltext api, follower=[{}]", &self.follower);
+
+        WebType::from_item(StatusCode::OK.as_u16(), FullTextSearchReply { hits })
+    }
+
+    /// Normalize the terms of the query for every language of the documents
+    /// The languages where all the words are stop words are left out
+    /// The stop words of the customer are removed and the synonyms are alternatives of the words
+    async fn build_language_queries(
+        &self,
+        trans: &mut SQLTransactionAsync<'_>,
+        terms: &[QueryTerm],
+        customer_key: &str,
+        customer_code: &str,
+    ) -> anyhow::Result<Vec<LanguageQuery>> {
+        let mut queries = vec![];
+        for lang in self.search_languages(trans, customer_code).await? {
+            let dictionary = self
+                .dictionary_delegate()
+                .load_language_dictionary(trans, &lang, customer_code)
+                .await?;
+            let mut term_queries = vec![];
+            for term in terms {
+                let term_query = match term {
+                    QueryTerm::Words(words) => {
+                        self.select_tsquery(trans, &lang, "plainto_tsquery", words, &dictionary)
+                            .await?
+                    }
+                    QueryTerm::Phrase(words) => {
+                        self.select_tsquery(trans, &lang, "phraseto_tsquery", words, &dictionary)
+                            .await?
+                    }
+                    QueryTerm::Near(left, right, distance) => {
+                        let left = self
+                            .select_tsquery(trans, &lang, "plainto_tsquery", left, &dictionary)
+                            .await?;
+                        let right = self
+                            .select_tsquery(trans, &lang, "plainto_tsquery", right, &dictionary)
+                            .await?;
+                        // A stop word is no anchor for the distance, keep the other word
+                        match (left.is_empty(), right.is_empty()) {
+                            (false, false) => near_tsquery(&left, &right, *distance),
+                            (true, _) => right,
+                            (false, true) => left,
+                        }
+                    }
+                };
+                if !term_query.is_empty() {
+                    term_queries.push(format!("( {} )", term_query));
+                }
+            }
+
+            if term_queries.is_empty() {
+                continue;
+            }
+            let clear_tsquery = term_queries.join(" & ");
+            queries.push(LanguageQuery {
+                lexemes: tsquery_lexemes(&clear_tsquery).into_iter().collect(),
+                tsquery: encrypt_tsquery(&clear_tsquery, customer_key),
+                lang,
+            });
+        }
+        Ok(queries)
+    }
+
+    /// Clear tsquery of the words, normalized like the tsvector of the documents
+    /// It's empty when all the words are stop words
+    async fn select_tsquery(
+        &self,
+        trans: &mut SQLTransactionAsync<'_>,
+        lang: &str,
+        ts_function: &str,
+        words: &str,
+        dictionary: &LanguageDictionary,
+    ) -> anyhow::Result<String> {
+        let mut params = HashMap::new();
+        params.insert(
+            "p_words".to_string(),
+            CellValue::from_raw_string(words.to_string()),
+        );
+
+        let query = SQLQueryBlockAsync {
+            sql_query: format!(
+                r"SELECT CAST( {}('{}', unaccent_lower(:p_words)) as VARCHAR ) as tsq",
+                ts_function, lang
+            ),
+            start: 0,
+            length: None,
+            params,
+        };
+
+        let mut sql_result: SQLDataSet = query.execute(trans).await.map_err(err_fwd!(
+            "💣 Query failed, [{}], follower=[{}]",
+            &query.sql_query,
+            &self.follower
+        ))?;
+
+        if !sql_result.next() {
+            return Err(anyhow!("Impossible to compute the tsquery"));
+        }
+        let tsquery = sql_result.get_string("tsq").unwrap_or_default();
+        let tsquery = remove_stop_lexemes(&tsquery, &dictionary.stop_lexemes);
+        Ok(expand_synonyms(&tsquery, &dictionary.synonyms))
+    }
+
+    /// The languages of the indexed documents and comments
+    async fn search_languages(
+        &self,
+        trans: &mut SQLTransactionAsync<'_>,
+        customer_code: &str,
+    ) -> anyhow::Result<Vec<String>> {
+        let query = SQLQueryBlockAsync {
+            sql_query: format!(
+                r"SELECT lang FROM cs_{0}.document
+                    UNION
+                    SELECT lang FROM cs_{0}.item_comment WHERE deleted_gmt IS NULL
+                    ORDER BY lang ",
+                customer_code
+            ),
+            start: 0,
+            length: None,
+            params: HashMap::new(),
+        };
+
+        let mut sql_result: SQLDataSet = query.execute(trans).await.map_err(err_fwd!(
+            "💣 Query failed, [{}], follower=[{}]",
+            &query.sql_query,
+            &self.follower
+        ))?;
+
+        let mut languages = vec![];
+        while sql_result.next() {
+            languages.push(sql_result.get_string("lang").ok_or(anyhow!("Wrong lang"))?);
+        }
+        Ok(languages)
+    }
+
+    /// The items out of the trash matching the query, best rank first
+    /// A document part or a comment matches when it holds all the lexemes of the query of its language
+    async fn search_hits(
+        &self,
+        trans: &mut SQLTransactionAsync<'_>,
+        queries: &[LanguageQuery],
+        start_page: Option<u32>,
+        page_size: u32,
+        customer_code: &str,
+    ) -> anyhow::Result<Vec<FullTextHitElement>> {
+        if queries.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let mut params = HashMap::new();
+        let mut sub_queries = vec![];
+        for (i, q) in queries.iter().enumerate() {
+            // The trailing underscore keeps p_query_1_ from being a prefix of p_query_10_
+            let p_lang = format!("p_lang_{}_", i);
+            let p_query = format!("p_query_{}_", i);
+            sub_queries.push(format!(
+                r"SELECT it.id AS item_id, ts_rank(d.tsv, CAST(:{1} AS tsquery)) AS rank
+                    FROM cs_{2}.document d
+                    INNER JOIN cs_{2}.item it ON it.file_ref = d.file_ref
+                    WHERE d.lang = :{0} AND d.tsv @@ CAST(:{1} AS tsquery)",
+                p_lang, p_query, customer_code
+            ));
+            sub_queries.push(format!(
+                r"SELECT c.item_id, ts_rank(c.tsv, CAST(:{1} AS tsquery)) AS rank
+                    FROM cs_{2}.item_comment c
+                    WHERE c.deleted_gmt IS NULL
+                    AND c.lang = :{0} AND c.tsv @@ CAST(:{1} AS tsquery)",
+                p_lang, p_query, customer_code
+            ));
+            params.insert(p_lang, CellValue::from_raw_string(q.lang.clone()));
+            params.insert(p_query, CellValue::from_raw_string(q.tsquery.clone()));
+        }
+
+        let query = SQLQueryBlockAsync {
+            sql_query: format!(
+                r"SELECT it.id, it.name, it.file_ref
+                    FROM ( {} ) hit
+                    INNER JOIN cs_{}.item it ON it.id = hit.item_id
+                    WHERE it.trashed_gmt IS NULL
+                    GROUP BY it.id, it.name, it.file_ref
+                    ORDER BY MAX(hit.rank) DESC, it.id DESC ",
+                sub_queries.join(" UNION ALL "),
+                customer_code
+            ),
+            start: start_page.unwrap_or(0) * page_size,
+            length: Some(page_size),
+            params,
+        };
+
+        let mut sql_result: SQLDataSet = query.execute(trans).await.map_err(err_fwd!(
+            "💣 Query failed, [{}], follower=[{}]",
+            &query.sql_query,
+            &self.follower
+        ))?;
+
+        let mut hits = vec![];
+        while sql_result.next() {
+            hits.push(FullTextHitElement {
+                item_id: sql_result.get_int("id").ok_or(anyhow!("Wrong id"))?,
+                name: sql_result.get_string("name").ok_or(anyhow!("Wrong name"))?,
+                file_ref: sql_result.get_string("file_ref"),
+                snippets: vec![],
+            });
+        }
+        Ok(hits)
+    }
+
+    /// Snippets of the decrypted parts of the file, then of the comments of the item,
+    /// the words are matched on their lexeme
+    async fn build_hit_snippets(
+        &self,
+        trans: &mut SQLTransactionAsync<'_>,
+        item_id: i64,
+        file_ref: Option<&str>,
+        queries: &[LanguageQuery],
+        customer_key: &str,
+        customer_code: &str,
+    ) -> Result<Vec<String>, &'static ErrorSet<'static>> {
+        let mut texts = vec![];
+        if let Some(file_ref) = file_ref {
+            let parts = self
+                .fulltext_delegate()
+                .read_document_parts(trans, file_ref, customer_key, customer_code)
+                .await?;
+            texts.extend(parts.into_iter().map(|part| (part.lang, part.doc_text)));
+        }
+
+        let Ok(comments) = self
+            .search_comment_texts(trans, item_id, customer_key, customer_code)
+            .await
+            .map_err(err_fwd!(
+                "💣 Cannot read the comments, item_id=[{}], follower=[{}]",
+                item_id,
+                &self.follower
+            ))
+        else {
+            return Err(&INTERNAL_DATABASE_ERROR);
+        };
+        texts.extend(comments);
+
+        let mut snippets = vec![];
+        for (lang, text) in texts {
+            if snippets.len() >= MAX_SNIPPETS {
+                break;
+            }
+            let Some(query) = queries.iter().find(|q| q.lang == lang) else {
+                continue;
+            };
+
+            // The parts are cleaned up by the tokenizer, the comments are the text as typed
+            let words: Vec<&str> = text.split_whitespace().collect();
+
+            let Ok(word_lexemes) = self
+                .select_word_lexemes(trans, &words, &lang)
+                .await
+                .map_err(err_fwd!(
+                    "💣 Cannot find the lexemes of the words, item_id=[{}], follower=[{}]",
+                    item_id,
+                    &self.follower
+                ))
+            else {
+                return Err(&INTERNAL_DATABASE_ERROR);
+            };
+
+            let hits: Vec<bool> = words
+                .iter()
+                .map(|w| {
+                    word_lexemes
+                        .get(*w)
+                        .is_some_and(|lexemes| lexemes.iter().any(|l| query.lexemes.contains(l)))
+                })
+                .collect();
+
+            snippets.append(&mut build_snippets(
+                &words,
+                &hits,
+                SNIPPET_CONTEXT_WORDS,
+                MAX_SNIPPETS - snippets.len(),
+            ));
+        }
+        Ok(snippets)
+    }
+
+    /// The language and the decrypted text of the comments of the item, not deleted
+    async fn search_comment_texts(
+        &self,
+        trans: &mut SQLTransactionAsync<'_>,
+        item_id: i64,
+        customer_key: &str,
+        customer_code: &str,
+    ) -> anyhow::Result<Vec<(String, String)>> {
+        let mut params = HashMap::new();
+        params.insert("p_item_id".to_string(), CellValue::from_raw_int(item_id));
+
+        let query = SQLQueryBlockAsync {
+            sql_query: format!(
+                r"SELECT lang, comment_text FROM cs_{}.item_comment
+                    WHERE item_id = :p_item_id AND deleted_gmt IS NULL
+                    ORDER BY id ",
+                customer_code
+            ),
+            start: 0,
+            length: None,
+            params,
+        };
+
+        let mut sql_result: SQLDataSet = query.execute(trans).await.map_err(err_fwd!(
+            "💣 Query failed, [{}], follower=[{}]",
+            &query.sql_query,
+            &self.follower
+        ))?;
+
+        let mut comments = vec![];
+        while sql_result.next() {
+            let lang = sql_result.get_string("lang").ok_or(anyhow!("Wrong lang"))?;
+            let encrypted_text = sql_result
+                .get_string("comment_text")
+                .ok_or(anyhow!("Wrong comment text"))?;
+            let text = DkEncrypt::new(CC20)
+                .decrypt_str(&encrypted_text, customer_key)
+                .map_err(err_fwd!(
+                    "Cannot decrypt the comment, item_id=[{}], follower=[{}]",
+                    item_id,
+                    &self.follower
+                ))?;
+            comments.push((lang, text));
+        }
+        Ok(comments)
+    }
+
+    /// The lexemes of each distinct word, with the same normalisation as the tsvector of the part
+    async fn select_word_lexemes(
+        &self,
+        trans: &mut SQLTransactionAsync<'_>,
+        words: &[&str],
+        lang: &str,
+    ) -> anyhow::Result<HashMap<String, Vec<String>>> {
+        let distinct_words: HashSet<&str> = words.iter().copied().collect();
+        if distinct_words.is_empty() {
+            return Ok(HashMap::new());
+        }
+
+        let mut params = HashMap::new();
+        params.insert(
+            "p_words".to_string(),
+            CellValue::from_raw_string(distinct_words.into_iter().collect::<Vec<&str>>().join(" ")),
+        );
+
+        let query = SQLQueryBlockAsync {
+            sql_query: format!(
+                r"SELECT w AS word, CAST( to_tsvector('{}', unaccent_lower(w)) as VARCHAR ) as tsv
+                    FROM unnest( string_to_array(:p_words, ' ') ) AS w ",
+                lang
+            ),
+            start: 0,
+            length: None,
+            params,
+        };
+
+        let mut sql_result: SQLDataSet = query.execute(trans).await.map_err(err_fwd!(
+            "💣 Query failed, [{}], follower=[{}]",
+            &query.sql_query,
+            &self.follower
+        ))?;
+
+        let mut word_lexemes = HashMap::new();
+        while sql_result.next() {
+            let word = sql_result.get_string("word").ok_or(anyhow!("Wrong word"))?;
+            let tsv = sql_result.get_string("tsv").unwrap_or_default();
+            word_lexemes.insert(word, tsvector_lexemes(&tsv));
+        }
+        Ok(word_lexemes)
+    }
+
+    fn dictionary_delegate(&self) -> FullTextDictionaryDelegate {
+        FullTextDictionaryDelegate::new(
+            self.session_token.clone(),
+            self.follower.x_request_id.clone(),
+        )
+    }
+
+    fn fulltext_delegate(&self) -> FullTextDelegate {
+        FullTextDelegate::new(
+            self.session_token.clone(),
+            self.follower.x_request_id.clone(),
+        )
+    }
+
+    fn web_type_error<T>() -> impl Fn(&ErrorSet<'static>) -> WebType<T>
+    where
+        T: DeserializeOwned,
+    {
+        |e| {
+            log_error!("💣 Error after try {:?}", e);
+            WebType::from_errorset(e)
+        }
+    }
+}
+
+/// Split the query into words, quoted phrases and NEAR/N operators
+/// None if a phrase is not closed or the distance is not a number
+fn lex_fulltext_query(query: &str) -> Option<Vec<QueryToken>> {
+    let mut tokens = vec![];
+    let mut rest = query.trim_start();
+    while !rest.is_empty() {
+        if let Some(quoted) = rest.strip_prefix('"') {
+            let end = quoted.find('"')?;
+            tokens.push(QueryToken::Phrase(quoted[..end].to_string()));
+            rest = &quoted[end + 1..];
+        } else {
+            let end = rest
+                .find(|c: char| c.is_whitespace() || c == '"')
+                .unwrap_or(rest.len());
+            let word = &rest[..end];
+            match word.strip_prefix("NEAR/") {
+                Some(distance) => tokens.push(QueryToken::Near(distance.parse().ok()?)),
+                None => tokens.push(QueryToken::Word(word.to_string())),
+            }
+            rest = &rest[end..];
+        }
+        rest = rest.trim_start();
+    }
+    Some(tokens)
+}
+
+/// Same clean up as the indexing of the raw text, the short words are kept for the phrases
+fn clean_words(text: &str) -> String {
+    FTTokenizer::new(text).next_n_words(text.len()).join(" ")
+}
+
+/// The terms of the query, ex: income "net income" year NEAR/5 growth
+/// None when the syntax is wrong or there is no word left to search
+fn parse_fulltext_query(query: &str) -> Option<Vec<QueryTerm>> {
+    let tokens = lex_fulltext_query(query)?;
+    let mut words = vec![];
+    let mut terms = vec![];
+
+    let mut i = 0;
+    while i < tokens.len() {
+        match (&tokens[i], tokens.get(i + 1), tokens.get(i + 2)) {
+            (
+                QueryToken::Word(left),
+                Some(QueryToken::Near(distance)),
+                Some(QueryToken::Word(right)),
+            ) => {
+                if !(1..=MAX_NEAR_DISTANCE).contains(distance) {
+                    return None;
+                }
+                let (left, right) = (clean_words(left), clean_words(right));
+                if left.is_empty() || right.is_empty() {
+                    words.push(left);
+                    words.push(right);
+                } else {
+                    terms.push(QueryTerm::Near(left, right, *distance));
+                }
+                i += 3;
+            }
+            (QueryToken::Word(word), _, _) => {
+                words.push(clean_words(word));
+                i += 1;
+            }
+            (QueryToken::Phrase(phrase), _, _) => {
+                let phrase = clean_words(phrase);
+                if !phrase.is_empty() {
+                    terms.push(QueryTerm::Phrase(phrase));
+                }
+                i += 1;
+            }
+            (QueryToken::Near(_), _, _) => return None,
+        }
+    }
+
+    words.retain(|w| !w.is_empty());
+    if !words.is_empty() {
+        terms.insert(0, QueryTerm::Words(words.join(" ")));
+    }
+
+    if terms.is_empty() {
+        None
+    } else {
+        Some(terms)
+    }
+}
+
+/// Cut snippets of words around the hits, overlapping windows are merged
+/// The matched words are marked and "..." shows the text goes on
+fn build_snippets(
+    words: &[&str],
+    hits: &[bool],
+    context: usize,
+    max_snippets: usize,
+) -> Vec<String> {
+    let mut windows: Vec<(usize, usize)> = vec![];
+    for (i, _) in hits.iter().enumerate().filter(|(_, hit)| **hit) {
+        let start = i.saturating_sub(context);
+        let end = (i + context).min(words.len() - 1);
+        match windows.last_mut() {
+            Some(last) if start <= last.1 + 1 => last.1 = end,
+            _ => windows.push((start, end)),
+        }
+    }
+
+    windows
+        .into_iter()
+        .take(max_snippets)
+        .map(|(start, end)| {
+            let mut snippet = words[start..=end]
+                .iter()
+                .zip(&hits[start..=end])
+                .map(|(w, hit)| {
+                    if *hit {
+                        format!("{}{}{}", HIT_START_MARK, w, HIT_END_MARK)
+                    } else {
+                        w.to_string()
+                    }
+                })
+                .collect::<Vec<String>>()
+                .join(" ");
+            if start > 0 {
+                snippet.insert_str(0, "... ");
+            }
+            if end < words.len() - 1 {
+                snippet.push_str(" ...");
+            }
+            snippet
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use crate::ft_search::{build_snippets, parse_fulltext_query, QueryTerm};
+
+    #[test]
+    fn parse_fulltext_query_test() {
+        let terms = parse_fulltext_query(r#"growth "net income" report NEAR/5 yearly"#).unwrap();
+        assert_eq!(
+            vec![
+                QueryTerm::Words("growth".to_string()),
+                QueryTerm::Phrase("net income".to_string()),
+                QueryTerm::Near("report".to_string(), "yearly".to_string(), 5),
+            ],
+            terms
+        );
+
+        let terms = parse_fulltext_query(r#""quarterly net income""#).unwrap();
+        assert_eq!(
+            vec![QueryTerm::Phrase("quarterly net income".to_string())],
+            terms
+        );
+    }
+
+    #[test]
+    fn parse_fulltext_query_error_test() {
+        assert!(parse_fulltext_query("").is_none());
+        // No word left once cleaned up, the stop words are removed later for each language
+        assert!(parse_fulltext_query("¥ ╫").is_none());
+        assert!(parse_fulltext_query(r#""net income"#).is_none());
+        assert!(parse_fulltext_query("report NEAR/x yearly").is_none());
+        assert!(parse_fulltext_query("report NEAR/0 yearly").is_none());
+        assert!(parse_fulltext_query("report NEAR/50 yearly").is_none());
+        assert!(parse_fulltext_query("NEAR/5 yearly").is_none());
+    }
+
+    #[test]
+    fn build_snippets_test() {
+        let words = vec!["the", "net", "income", "of", "the", "year", "grew", "again"];
+        let hits = vec![false, false, true, false, false, false, false, false];
+        let snippets = build_snippets(&words, &hits, 1, 3);
+        assert_eq!(vec!["... net <mark>income</mark> of ..."], snippets);
+    }
+
+    #[test]
+    fn build_snippets_merge_test() {
+        let words = vec!["a", "b", "c", "d", "e", "f", "g", "h", "i", "j"];
+        let hits = vec![
+            true, false, false, true, false, false, false, false, false, true,
+        ];
+        let snippets = build_snippets(&words, &hits, 1, 3);
+        assert_eq!(
+            vec![
+                "<mark>a</mark> b c <mark>d</mark> e ...",
+                "... i <mark>j</mark>"
+            ],
+            snippets
+        );
+    }
+
+    #[test]
+    fn build_snippets_max_test() {
+        let words = vec!["a", "b", "c", "d", "e", "f", "g"];
+        let hits = vec![true, false, false, true, false, false, true];
+        assert_eq!(1, build_snippets(&words, &hits, 0, 1).len());
+        assert_eq!(3, build_snippets(&words, &hits, 0, 5).len());
+        assert!(build_snippets(&words, &[false; 7], 2, 3).is_empty());
+        assert!(build_snippets(&[], &[], 2, 3).is_empty());
+    }
+}
@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 use anyhow::anyhow;
 use chrono::Utc;
@@ -37,6 +37,7 @@ fn concat(phrase: &[WordType]) -> String {
 enum CharType {
     SEPARATOR,
     LEXEME,
+    CJK,
     // UNKNOWN,
 }
 
@@ -46,11 +47,10 @@ enum PatternStatus {
     DATE,
     NUMBER,
     EMAIL,
+    CJK,
     //UNKNOWN,
 }
 
-const MIN_WORD_LEN: usize = 4;
-
 pub(crate) struct FTTokenizer<'a> {
     graphemes: Graphemes<'a>,
     _raw_size: usize,
@@ -71,6 +71,7 @@ impl<'a> FTTokenizer<'a> {
     fn char_type(g: &str) -> CharType {
         match g {
             "/" | "," | "." | "-" | "@" => CharType::LEXEME,
+            _ if Self::is_cjk(g) => CharType::CJK,
             _ => {
                 let w = g.unicode_words().collect::<Vec<&str>>();
                 if w.is_empty() {
@@ -87,14 +88,56 @@ impl<'a> FTTokenizer<'a> {
         w
     }
 
+    ///
+    /// Han, Kana and Hangul, written without space between the words
+    ///
+    fn is_cjk(g: &str) -> bool {
+        g.chars().next().is_some_and(|c| {
+            matches!(c as u32,
+                0x1100..=0x11FF // Hangul Jamo
+                | 0x3040..=0x30FF // Hiragana, Katakana
+                | 0x3130..=0x318F // Hangul compatibility Jamo
+                | 0x31F0..=0x31FF // Katakana extension
+                | 0x3400..=0x4DBF // Han extension A
+                | 0x4E00..=0x9FFF // Han
+                | 0xAC00..=0xD7AF // Hangul syllables
+                | 0xF900..=0xFAFF // Han compatibility
+                | 0xFF66..=0xFF9F // Half width Katakana
+                | 0x20000..=0x2FFFF // Han extensions B to F
+            )
+        })
+    }
+
+    ///
+    /// A CJK run becomes overlapping bigrams, ex: 東京都 => 東京 京都
+    ///
+    fn terminate_cjk_run(&mut self, run: &mut Vec<&str>) {
+        if run.len() == 1 {
+            self.words.push(run[0].to_string());
+        } else {
+            for bigram in run.windows(2) {
+                log_debug!("Added bigram [{}]", bigram.concat());
+                self.words.push(bigram.concat());
+            }
+        }
+        self.pattern_status = PatternStatus::NORMAL;
+        run.clear();
+    }
+
     fn terminate_word(&mut self, word: &mut Vec<&str>) {
+        if let PatternStatus::CJK = self.pattern_status {
+            self.terminate_cjk_run(word);
+            return;
+        }
+
         let s: String = word.concat();
         let w = match self.pattern_status {
             PatternStatus::NORMAL => Self::clear_word(&s),
             _ => s,
         };
 
-        if word.len() >= MIN_WORD_LEN {
+        // The short words are kept, the phrases and NEAR distances count them
+        if !w.is_empty() {
             log_debug!("Added word [{}]", &w);
             self.words.push(w);
         }
@@ -145,7 +188,18 @@ impl<'a> FTTokenizer<'a> {
                         break;
                     }
                 }
+                CharType::CJK => {
+                    // A CJK run never sticks to the word before
+                    if !matches!(self.pattern_status, PatternStatus::CJK) {
+                        self.terminate_word(&mut word);
+                        self.pattern_status = PatternStatus::CJK;
+                    }
+                    word.push(g);
+                }
                 CharType::LEXEME => {
+                    if let PatternStatus::CJK = self.pattern_status {
+                        self.terminate_word(&mut word);
+                    }
                     match self.pattern_status {
                         PatternStatus::NORMAL => {
                             if word.is_empty() && Self::is_digit(g) {
@@ -203,6 +257,10 @@ impl<'a> FTTokenizer<'a> {
                         }
                         PatternStatus::EMAIL => {
                             word.push(g);
+                        }
+                        // The CJK run was terminated above
+                        PatternStatus::CJK => {
+                            word.push(g);
                         } // PatternStatus::UNKNOWN => {
                           //     word.push(g);
                           // }
@@ -410,14 +468,251 @@ pub fn encrypt_tsvector(tsvector: &str, customer_key: &str) -> anyhow::Result<St
     Ok(complete_phrase)
 }
 
+///
+/// Number of positions of each lexeme of an encrypted tsvector, ex: 'xxx':1,4 'yyy':2
+///
+pub(crate) fn tsvector_term_counts(tsvector: &str) -> HashMap<String, u32> {
+    let mut counts = HashMap::new();
+    for entry in tsvector.split(' ').filter(|e| !e.is_empty()) {
+        let (lexeme, positions) = entry.rsplit_once(':').unwrap_or((entry, ""));
+        let count = positions
+            .split(',')
+            .filter(|p| !p.is_empty())
+            .count()
+            .max(1) as u32;
+        *counts
+            .entry(lexeme.trim_matches('\'').to_string())
+            .or_insert(0) += count;
+    }
+    counts
+}
+
+///
+/// The lexemes of a clear tsvector, in order of appearance
+///
+pub(crate) fn tsvector_lexemes(tsvector: &str) -> Vec<String> {
+    let (_, words) = parse_vector(tsvector);
+    let mut orders: Vec<&u64> = words.keys().collect();
+    orders.sort();
+    orders
+        .iter()
+        .filter_map(|o| words.get(o).cloned())
+        .collect()
+}
+
+#[derive(Debug, PartialEq)]
+enum QueryPart {
+    Operator(String),
+    Lexeme(String),
+}
+
+///
+/// Split a clear tsquery, ex: 'net' <-> 'incom' & 'year', into its lexemes and the text around
+///
+fn parse_query(tsquery: &str) -> Vec<QueryPart> {
+    let mut parts = vec![];
+    let mut operator = String::new();
+    let mut chars = tsquery.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if c != '\'' {
+            operator.push(c);
+            continue;
+        }
+        if !operator.is_empty() {
+            parts.push(QueryPart::Operator(operator.clone()));
+            operator.clear();
+        }
+        let mut lexeme = String::new();
+        loop {
+            match chars.next() {
+                None => break,
+                Some('\'') => {
+                    // A doubled quote is a quote inside the lexeme
+                    if chars.peek() == Some(&'\'') {
+                        chars.next();
+                        lexeme.push('\'');
+                    } else {
+                        break;
+                    }
+                }
+                Some(l) => lexeme.push(l),
+            }
+        }
+        parts.push(QueryPart::Lexeme(lexeme));
+    }
+
+    if !operator.is_empty() {
+        parts.push(QueryPart::Operator(operator));
+    }
+    parts
+}
+
+///
+/// The lexemes of a clear tsquery, in order of appearance
+///
+pub(crate) fn tsquery_lexemes(tsquery: &str) -> Vec<String> {
+    parse_query(tsquery)
+        .into_iter()
+        .filter_map(|part| match part {
+            QueryPart::Lexeme(l) => Some(l),
+            QueryPart::Operator(_) => None,
+        })
+        .collect()
+}
+
+///
+/// Hash the lexemes of a clear tsquery like in the encrypted tsvector
+/// The operators are kept, so the positional ones <-> and <N> still work on the encrypted data
+///
+pub(crate) fn encrypt_tsquery(tsquery: &str, customer_key: &str) -> String {
+    parse_query(tsquery)
+        .into_iter()
+        .map(|part| match part {
+            QueryPart::Lexeme(l) => format!("'{}'", DkEncrypt::hmac_word(&l, customer_key)),
+            QueryPart::Operator(o) => o,
+        })
+        .collect()
+}
+
+fn quote_lexeme(lexeme: &str) -> String {
+    format!("'{}'", lexeme.replace('\'', "''"))
+}
+
+/// Link between two lexemes of a tsquery
+#[derive(Debug, Clone, Copy)]
+enum QueryLink {
+    And,
+    Follow(u32),
+}
+
+impl QueryLink {
+    fn parse(operator: &str) -> Option<Self> {
+        match operator.trim() {
+            "&" => Some(QueryLink::And),
+            "<->" => Some(QueryLink::Follow(1)),
+            o => o
+                .strip_prefix('<')
+                .and_then(|o| o.strip_suffix('>'))
+                .and_then(|d| d.parse::<u32>().ok())
+                .map(QueryLink::Follow),
+        }
+    }
+
+    /// The link that remains when the lexeme between the two links is removed
+    fn merge(self, next: QueryLink) -> Self {
+        match (self, next) {
+            (QueryLink::Follow(d1), QueryLink::Follow(d2)) => QueryLink::Follow(d1 + d2),
+            _ => QueryLink::And,
+        }
+    }
+
+    fn as_operator(&self) -> String {
+        match self {
+            QueryLink::And => " & ".to_string(),
+            QueryLink::Follow(1) => " <-> ".to_string(),
+            QueryLink::Follow(d) => format!(" <{}> ", d),
+        }
+    }
+}
+
+///
+/// Remove the stop lexemes from a clear tsquery made by plainto_tsquery or phraseto_tsquery
+/// The distances of the phrases are kept, like the positions in the tsvector after ts_delete
+/// It's empty when all the lexemes are stop lexemes
+///
+pub(crate) fn remove_stop_lexemes(tsquery: &str, stop_lexemes: &HashSet<String>) -> String {
+    let parts = parse_query(tsquery);
+    if !parts
+        .iter()
+        .any(|p| matches!(p, QueryPart::Lexeme(l) if stop_lexemes.contains(l)))
+    {
+        return tsquery.to_string();
+    }
+
+    let mut query = String::new();
+    let mut link: Option<QueryLink> = None;
+    for part in parts {
+        match part {
+            QueryPart::Operator(o) => {
+                // Only the flat queries of the ts functions are expected
+                let Some(next) = QueryLink::parse(&o) else {
+                    return tsquery.to_string();
+                };
+                link = Some(link.map_or(next, |l| l.merge(next)));
+            }
+            QueryPart::Lexeme(l) if stop_lexemes.contains(&l) => {}
+            QueryPart::Lexeme(l) => {
+                if let Some(link) = link.take() {
+                    if !query.is_empty() {
+                        query.push_str(&link.as_operator());
+                    }
+                }
+                query.push_str(&quote_lexeme(&l));
+            }
+        }
+    }
+    query
+}
+
+///
+/// Replace each lexeme of a clear tsquery having synonyms by the alternative of all of them
+/// [synonyms] : lexeme -> the clear tsqueries of its synonyms
+///
+pub(crate) fn expand_synonyms(tsquery: &str, synonyms: &HashMap<String, Vec<String>>) -> String {
+    if synonyms.is_empty() {
+        return tsquery.to_string();
+    }
+    parse_query(tsquery)
+        .into_iter()
+        .map(|part| match part {
+            QueryPart::Lexeme(l) => {
+                let mut alternatives = vec![quote_lexeme(&l)];
+                for synonym in synonyms.get(&l).into_iter().flatten() {
+                    if !synonym.is_empty() && !alternatives.contains(synonym) {
+                        alternatives.push(synonym.clone());
+                    }
+                }
+                if alternatives.len() == 1 {
+                    alternatives.remove(0)
+                } else {
+                    format!("( {} )", alternatives.join(" | "))
+                }
+            }
+            QueryPart::Operator(o) => o,
+        })
+        .collect()
+}
+
+///
+/// Tsquery matching the two queries at most [distance] words apart, in any order
+///
+pub(crate) fn near_tsquery(left: &str, right: &str, distance: u32) -> String {
+    let mut alternatives = vec![];
+    for d in 1..=distance {
+        alternatives.push(format!("( {} ) <{}> ( {} )", left, d, right));
+        alternatives.push(format!("( {} ) <{}> ( {} )", right, d, left));
+    }
+    alternatives.join(" | ")
+}
+
 #[cfg(test)]
 mod file_server_test {
-    use std::collections::HashMap;
+    use std::collections::{HashMap, HashSet};
 
     use chrono::Utc;
 
+    use commons_pg::sql_transaction::CellValue;
+    use commons_pg::sql_transaction_async::{
+        SQLConnectionAsync, SQLQueryBlockAsync, SQLTransactionAsync,
+    };
+
     use crate::char_lib::has_not_printable_char;
-    use crate::ft_tokenizer::{encrypt_tsvector, FTTokenizer};
+    use crate::ft_tokenizer::{
+        encrypt_tsquery, encrypt_tsvector, expand_synonyms, near_tsquery, remove_stop_lexemes,
+        tsquery_lexemes, tsvector_lexemes, tsvector_term_counts, FTTokenizer,
+    };
+    use crate::test_db::TEST_DB_URL;
 
     const KEY: &str = "fqYVyce-Nh0HwpPQ7ZGZLog5s7PBLnwFMAW2OMnNPUs";
 
@@ -470,6 +765,203 @@ mod file_server_test {
         Ok(())
     }
 
+    #[test]
+    fn tsvector_term_counts_test() {
+        let counts = tsvector_term_counts("'aGVsbG8':1,4,9 'd29ybGQ':2 'Zm9v'");
+        assert_eq!(3, counts.len());
+        assert_eq!(3, counts["aGVsbG8"]);
+        assert_eq!(1, counts["d29ybGQ"]);
+        assert_eq!(1, counts["Zm9v"]);
+        assert!(tsvector_term_counts("").is_empty());
+    }
+
+    #[test]
+    fn tsvector_lexemes_test() {
+        let lexemes = tsvector_lexemes("'incom':2,5 'net':1 'l''été':3");
+        assert_eq!(vec!["incom", "net", "l'été"], lexemes);
+        assert!(tsvector_lexemes("").is_empty());
+    }
+
+    #[test]
+    fn tsquery_lexemes_test() {
+        let lexemes = tsquery_lexemes("'net' <-> 'incom' & ( 'l''été' | 'year' )");
+        assert_eq!(vec!["net", "incom", "l'été", "year"], lexemes);
+        assert!(tsquery_lexemes("").is_empty());
+    }
+
+    #[test]
+    fn encrypt_tsquery_test() {
+        let tsquery = encrypt_tsquery("'net' <-> 'incom' & !'loss'", KEY);
+        let lexemes = tsquery_lexemes(&tsquery);
+        assert_eq!(3, lexemes.len());
+        assert_eq!(
+            format!("'{}' <-> '{}' & !'{}'", lexemes[0], lexemes[1], lexemes[2]),
+            tsquery
+        );
+        assert!(!tsquery.contains("incom"));
+    }
+
+    #[test]
+    fn near_tsquery_test() {
+        assert_eq!(
+            "( 'net' ) <1> ( 'incom' ) | ( 'incom' ) <1> ( 'net' ) | ( 'net' ) <2> ( 'incom' ) | ( 'incom' ) <2> ( 'net' )",
+            near_tsquery("'net'", "'incom'", 2)
+        );
+    }
+
+    #[test]
+    fn remove_stop_lexemes_test() {
+        let stop: HashSet<String> = ["art", "sa"].iter().map(|s| s.to_string()).collect();
+        assert_eq!(
+            "'limit' & 'compani'",
+            remove_stop_lexemes("'limit' & 'art' & 'compani'", &stop)
+        );
+        assert_eq!(
+            "'net' <3> 'incom'",
+            remove_stop_lexemes("'net' <-> 'art' <2> 'incom'", &stop)
+        );
+        assert_eq!(
+            "'incom' & 'year'",
+            remove_stop_lexemes("'sa' <-> 'incom' <-> 'art' & 'year'", &stop)
+        );
+        assert_eq!("", remove_stop_lexemes("'art' & 'sa'", &stop));
+        assert_eq!(
+            "'net' <-> 'incom'",
+            remove_stop_lexemes("'net' <-> 'incom'", &stop)
+        );
+    }
+
+    #[test]
+    fn expand_synonyms_test() {
+        let mut synonyms = HashMap::new();
+        synonyms.insert(
+            "llc".to_string(),
+            vec!["'llc'".to_string(), "'limit' <-> 'compani'".to_string()],
+        );
+        assert_eq!(
+            "'net' & ( 'llc' | 'limit' <-> 'compani' )",
+            expand_synonyms("'net' & 'llc'", &synonyms)
+        );
+        assert_eq!(
+            "'net' <-> 'incom'",
+            expand_synonyms("'net' <-> 'incom'", &synonyms)
+        );
+        assert_eq!("'l''été'", expand_synonyms("'l''été'", &synonyms));
+    }
+
+    /// Clear tsvector or tsquery computed by the database, [ts_call] uses :p_text
+    async fn select_ts(trans: &mut SQLTransactionAsync<'_>, ts_call: &str, text: &str) -> String {
+        let mut params = HashMap::new();
+        params.insert("p_text".to_string(), CellValue::from_raw_str(text));
+        let mut data = SQLQueryBlockAsync {
+            sql_query: format!("SELECT CAST( {} AS VARCHAR ) AS ts", ts_call),
+            start: 0,
+            length: None,
+            params,
+        }
+        .execute(trans)
+        .await
+        .unwrap();
+        assert!(data.next());
+        data.get_string("ts").unwrap()
+    }
+
+    /// The database matches the encrypted tsvector with the encrypted tsquery
+    async fn is_match(trans: &mut SQLTransactionAsync<'_>, tsvector: &str, tsquery: &str) -> bool {
+        let mut params = HashMap::new();
+        params.insert("p_tsv".to_string(), CellValue::from_raw_str(tsvector));
+        params.insert("p_tsq".to_string(), CellValue::from_raw_str(tsquery));
+        let mut data = SQLQueryBlockAsync {
+            sql_query: "SELECT CAST(:p_tsv AS tsvector) @@ CAST(:p_tsq AS tsquery) AS found"
+                .to_string(),
+            start: 0,
+            length: None,
+            params,
+        }
+        .execute(trans)
+        .await
+        .unwrap();
+        assert!(data.next());
+        data.get_bool("found").unwrap()
+    }
+
+    const TO_TSVECTOR: &str = "to_tsvector('english', unaccent_lower(:p_text))";
+    const PHRASETO_TSQUERY: &str = "phraseto_tsquery('english', unaccent_lower(:p_text))";
+
+    /// Words of the text, as the tokenizer gives them to the database
+    fn tokenize(text: &str) -> String {
+        FTTokenizer::new(text).next_n_words(text.len()).join(" ")
+    }
+
+    /// The phrases and the NEAR distances match on the encrypted tsvector of the tokenized text
+    /// Needs the database of the tests : cargo test -- --ignored
+    #[tokio::test]
+    #[ignore]
+    async fn phrase_match_survives_encryption() -> anyhow::Result<()> {
+        let text = tokenize("The net income of the year, grew by 5 % in Q3.");
+        // The short words keep their position
+        assert_eq!("The net income of the year grew by 5 in Q3", text);
+
+        let mut cnx = SQLConnectionAsync::new(TEST_DB_URL).await?;
+        let mut trans = cnx.begin().await?;
+
+        let clear_tsv = select_ts(&mut trans, TO_TSVECTOR, &text).await;
+        let tsv = encrypt_tsvector(&clear_tsv, KEY)?;
+
+        let tsq = select_ts(&mut trans, PHRASETO_TSQUERY, &tokenize("net income")).await;
+        assert_eq!("'net' <-> 'incom'", tsq);
+        assert!(is_match(&mut trans, &tsv, &encrypt_tsquery(&tsq, KEY)).await);
+
+        let tsq = select_ts(&mut trans, PHRASETO_TSQUERY, &tokenize("income net")).await;
+        assert!(!is_match(&mut trans, &tsv, &encrypt_tsquery(&tsq, KEY)).await);
+
+        // The stop words of the phrase count in the distance
+        let tsq = select_ts(
+            &mut trans,
+            PHRASETO_TSQUERY,
+            &tokenize("income of the year"),
+        )
+        .await;
+        assert_eq!("'incom' <3> 'year'", tsq);
+        assert!(is_match(&mut trans, &tsv, &encrypt_tsquery(&tsq, KEY)).await);
+        let tsq = select_ts(&mut trans, PHRASETO_TSQUERY, &tokenize("income of year")).await;
+        assert!(!is_match(&mut trans, &tsv, &encrypt_tsquery(&tsq, KEY)).await);
+
+        // "net" and "year" are 4 words apart, in any order
+        let near = near_tsquery("'year'", "'net'", 4);
+        assert!(is_match(&mut trans, &tsv, &encrypt_tsquery(&near, KEY)).await);
+        let near = near_tsquery("'year'", "'net'", 3);
+        assert!(!is_match(&mut trans, &tsv, &encrypt_tsquery(&near, KEY)).await);
+
+        // Another key breaks the match
+        let other_key_phrase = encrypt_tsquery(
+            "'net' <-> 'incom'",
+            "Rm9vYmFyRm9vYmFyRm9vYmFyRm9vYmFyRm9vYmFyMTI",
+        );
+        assert!(!is_match(&mut trans, &tsv, &other_key_phrase).await);
+
+        trans.rollback().await;
+        Ok(())
+    }
+
+    #[test]
+    fn tokenize_cjk() {
+        let mut tkn = FTTokenizer::new("東京都に住む");
+        assert_eq!(
+            vec!["東京", "京都", "都に", "に住", "住む"],
+            tkn.next_n_words(100)
+        );
+
+        let mut tkn = FTTokenizer::new("Tokyo 東京 office, Rust東京。京");
+        assert_eq!(
+            vec!["Tokyo", "東京", "office", "Rust", "東京", "京"],
+            tkn.next_n_words(100)
+        );
+
+        let mut tkn = FTTokenizer::new("서울 시청에서");
+        assert_eq!(vec!["서울", "시청", "청에", "에서"], tkn.next_n_words(100));
+    }
+
     #[test]
     fn tokenize_garbage() {
         let garbage_1 = "On [ne] sera jamais l'élite de la nation";
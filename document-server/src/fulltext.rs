@@ -1,12 +1,15 @@
 use std::collections::HashMap;
 
+use axum::body::Body;
 use axum::http::StatusCode;
 use axum::Json;
+use futures::StreamExt;
 use log::*;
 use serde::de::DeserializeOwned;
+use serde_json::{json, Value};
 
 use commons_error::*;
-use commons_pg::sql_transaction::CellValue;
+use commons_pg::sql_transaction::{CellValue, SQLDataSet};
 use commons_pg::sql_transaction_async::{
     SQLChangeAsync, SQLConnectionAsync, SQLQueryBlockAsync, SQLTransactionAsync,
 };
@@ -16,19 +19,50 @@ use commons_services::token_lib::SessionToken;
 use commons_services::try_or_return;
 use commons_services::x_request_id::{Follower, XRequestID};
 use dkconfig::properties::get_prop_value;
-use dkconfig::property_name::{TIKA_SERVER_HOSTNAME_PROPERTY, TIKA_SERVER_PORT_PROPERTY};
+use dkconfig::property_name::{
+    LANGUAGE_DETECTOR_PROPERTY, TIKA_SERVER_HOSTNAME_PROPERTY, TIKA_SERVER_PORT_PROPERTY,
+};
 use dkcrypto::dk_crypto::CypherMode::CC20;
 use dkcrypto::dk_crypto::DkEncrypt;
-use dkdto::error_codes::{INTERNAL_DATABASE_ERROR, INTERNAL_TECHNICAL_ERROR};
+use dkdto::error_codes::{
+    INTERNAL_DATABASE_ERROR, INTERNAL_TECHNICAL_ERROR, MISSING_DOCUMENT_TEXT, MISSING_ITEM,
+};
 use dkdto::{
-    DeleteFullTextRequest, ErrorSet, FullTextReply, FullTextRequest, SimpleMessage, WebType,
-    WebTypeBuilder,
+    DeleteFullTextRequest, DocumentTextReply, ErrorSet, FullTextChunk, FullTextReply,
+    FullTextStreamHeader, SimpleMessage, WebType, WebTypeBuilder,
 };
 use doka_cli::async_request_client::TikaServerClientAsync;
 use doka_cli::request_client::TokenType;
 
-use crate::ft_tokenizer::{encrypt_tsvector, FTTokenizer};
+use crate::ft_tokenizer::{encrypt_tsvector, tsvector_term_counts, FTTokenizer};
+use crate::lang_detector::{detect_code, NGRAM_DETECTOR};
 use crate::language::{lang_name_from_code_2, map_code};
+use crate::tag_suggestion::tf_idf;
+
+/// Number of words for the language detection
+const FINESSE_LANGUAGE_BLOCK: usize = 1_000;
+/// Size of the words of a part, a part is written as soon as its language reaches it
+const MAX_LANGUAGE_BUFFER_BLOCK: usize = 200_000;
+/// A line of the indexing body holds a chunk of text, much smaller than this
+const MAX_NDJSON_LINE: usize = 4 * 1_048_576;
+/// Number of parts written by a single statement
+pub(crate) const PART_BATCH_SIZE: usize = 16;
+/// Number of the lexemes with the best TF-IDF kept as the keywords of a document
+const KEYWORD_COUNT: usize = 100;
+
+/// A part of the text of a document, for one language
+pub(crate) struct DocumentPart {
+    pub part_no: i32,
+    pub doc_text: String,
+    pub lang: String,
+}
+
+/// A text and its tsvector, both encrypted with the customer key
+pub(crate) struct EncryptedText {
+    pub text: String,
+    pub tsv: String,
+    pub lang: String,
+}
 
 pub(crate) struct FullTextDelegate {
     pub session_token: SessionToken,
@@ -97,6 +131,24 @@ impl FullTextDelegate {
     async fn delete_document(&self, file_ref: &str, customer_code: &str) -> anyhow::Result<()> {
         let mut cnx = SQLConnectionAsync::from_pool().await.map_err(tr_fwd!())?;
         let mut trans = cnx.begin().await.map_err(tr_fwd!())?;
+        self.delete_document_parts(&mut trans, file_ref, customer_code)
+            .await?;
+        trans
+            .commit()
+            .await
+            .map_err(err_fwd!("💣 Commit failed, follower=[{}]", &self.follower))?;
+        Ok(())
+    }
+
+    /// Delete the parts of the document and its keywords within the transaction
+    pub(crate) async fn delete_document_parts(
+        &self,
+        trans: &mut SQLTransactionAsync<'_>,
+        file_ref: &str,
+        customer_code: &str,
+    ) -> anyhow::Result<()> {
+        self.delete_document_keywords(trans, file_ref, customer_code)
+            .await?;
 
         let sql_delete = format!(
             r"DELETE FROM cs_{0}.document WHERE file_ref = :p_file_ref",
@@ -112,26 +164,21 @@ impl FullTextDelegate {
             sequence_name: "".to_string(),
         };
 
-        let _id = query.delete(&mut trans).await.map_err(err_fwd!(
+        let _id = query.delete(trans).await.map_err(err_fwd!(
             "💣 Query failed, [{}], , follower=[{}]",
             &query.sql_query,
             &self.follower
         ))?;
-        trans
-            .commit()
-            .await
-            .map_err(err_fwd!("💣 Commit failed, follower=[{}]", &self.follower))?;
         Ok(())
     }
 
-    /// 🌟 Parse the raw text data and create the document parts
-    /// Service called from the file-server
-    pub async fn fulltext_indexing(
-        mut self,
-        raw_text_request: Json<FullTextRequest>,
-    ) -> WebType<FullTextReply> {
+    ///
+    /// 🌟 Clear text extracted from the current file of an item
+    ///
+    pub async fn get_item_text(mut self, item_id: i64) -> WebType<DocumentTextReply> {
         log_info!(
-            "🚀 Start fulltext_indexing api, follower=[{}]",
+            "🚀 Start get_item_text api, item_id=[{}], follower=[{}]",
+            item_id,
             &self.follower
         );
 
@@ -142,7 +189,6 @@ impl FullTextDelegate {
 
         let customer_code = entry_session.customer_code.as_str();
 
-        // Get the crypto key
         let Ok(customer_key) = fetch_customer_key(customer_code, &self.follower)
             .await
             .map_err(err_fwd!(
@@ -153,7 +199,6 @@ impl FullTextDelegate {
             return WebType::from_errorset(&INTERNAL_TECHNICAL_ERROR);
         };
 
-        // Open Db connection
         let Ok(mut cnx) = SQLConnectionAsync::from_pool().await.map_err(err_fwd!(
             "💣 New Db connection failed, follower=[{}]",
             &self.follower
@@ -168,23 +213,79 @@ impl FullTextDelegate {
             return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
         };
 
-        // Generate the FT index and create an entry in the "document" table
-        let Ok(part_count) = self
-            .indexing(
-                &mut trans,
-                &raw_text_request,
-                &entry_session.customer_code,
-                &customer_key,
-            )
+        let file_ref = try_or_return!(
+            self.find_item_file_ref(&mut trans, item_id, customer_code)
+                .await,
+            Self::web_type_error()
+        );
+
+        let reply = try_or_return!(
+            self.read_document_text(&mut trans, &file_ref, &customer_key, customer_code)
+                .await,
+            Self::web_type_error()
+        );
+
+        if trans
+            .commit()
+            .await
+            .map_err(err_fwd!("💣 Commit failed, follower=[{}]", &self.follower))
+            .is_err()
+        {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        }
+
+        log_info!("🏁 End get_item_text api, follower=[{}]", &self.follower);
+
+        WebType::from_item(StatusCode::OK.as_u16(), reply)
+    }
+
+    ///
+    /// 🌟 Clear text extracted from a file
+    ///
+    pub async fn get_file_text(mut self, file_ref: &str) -> WebType<DocumentTextReply> {
+        log_info!(
+            "🚀 Start get_file_text api, file_ref=[{}], follower=[{}]",
+            file_ref,
+            &self.follower
+        );
+
+        let entry_session = try_or_return!(
+            valid_sid_get_session(&self.session_token, &mut self.follower).await,
+            Self::web_type_error()
+        );
+
+        let customer_code = entry_session.customer_code.as_str();
+
+        let Ok(customer_key) = fetch_customer_key(customer_code, &self.follower)
             .await
             .map_err(err_fwd!(
-                "💣 Indexing process failed, follower=[{}]",
+                "💣 Cannot get the customer key, follower=[{}]",
                 &self.follower
             ))
         else {
             return WebType::from_errorset(&INTERNAL_TECHNICAL_ERROR);
         };
 
+        let Ok(mut cnx) = SQLConnectionAsync::from_pool().await.map_err(err_fwd!(
+            "💣 New Db connection failed, follower=[{}]",
+            &self.follower
+        )) else {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        };
+
+        let Ok(mut trans) = cnx.begin().await.map_err(err_fwd!(
+            "💣 Transaction issue, follower=[{}]",
+            &self.follower
+        )) else {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        };
+
+        let reply = try_or_return!(
+            self.read_document_text(&mut trans, file_ref, &customer_key, customer_code)
+                .await,
+            Self::web_type_error()
+        );
+
         if trans
             .commit()
             .await
@@ -194,272 +295,1334 @@ impl FullTextDelegate {
             return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
         }
 
-        log_info!("😎 Generated the indexes and the document part entries, number of parts=[{}], follower=[{}]", part_count, &self.follower);
-        log_info!(
-            "🏁 End fulltext_indexing api, follower=[{}]",
-            &self.follower
-        );
+        log_info!("🏁 End get_file_text api, follower=[{}]", &self.follower);
 
-        WebType::from_item(StatusCode::OK.as_u16(), FullTextReply { part_count })
+        WebType::from_item(StatusCode::OK.as_u16(), reply)
     }
 
-    async fn indexing(
+    /// Delete the keywords of the document, and remove it from the document frequencies
+    /// The documents indexed before the keywords have no keyword and are not counted
+    async fn delete_document_keywords(
         &self,
-        mut trans: &mut SQLTransactionAsync<'_>,
-        raw_text_request: &FullTextRequest,
+        trans: &mut SQLTransactionAsync<'_>,
+        file_ref: &str,
         customer_code: &str,
-        customer_key: &str,
-    ) -> anyhow::Result<u32> {
-        // Limit the number of languages. Build it the static way.
-        const FINESSE_LANGUAGE_BLOCK: usize = 1_000; // nb of chars for the language detection
-        const MAX_LANGUAGE_BUFFER_BLOCK: usize = 200_000; // TODO  > 200_000;
-
-        let mut language_buffer_block: HashMap<String, Vec<String>> = HashMap::new(); // { "french", Vec<PureWord> }
-        let tika_server_host = get_prop_value(TIKA_SERVER_HOSTNAME_PROPERTY).map_err(tr_fwd!())?;
-        let tika_server_port = get_prop_value(TIKA_SERVER_PORT_PROPERTY)
-            .map_err(tr_fwd!())?
-            .parse::<u16>()
-            .map_err(tr_fwd!())?;
-
-        let tsc = TikaServerClientAsync::new(&tika_server_host, tika_server_port);
-        // Clean up the raw text
-        let mut ftt = FTTokenizer::new(&raw_text_request.raw_text);
+    ) -> anyhow::Result<()> {
+        let mut params = HashMap::new();
+        params.insert("p_file_ref".to_string(), CellValue::from_raw_str(file_ref));
+        let sql_update = SQLChangeAsync {
+            sql_query: format!(
+                r"UPDATE cs_{0}.fulltext_term_stat SET doc_count = doc_count - 1
+                    WHERE lexeme IN ( SELECT DISTINCT (unnest(tsv)).lexeme FROM cs_{0}.document
+                                        WHERE file_ref = :p_file_ref )
+                    AND EXISTS ( SELECT 1 FROM cs_{0}.document_keyword WHERE file_ref = :p_file_ref )",
+                customer_code
+            ),
+            params,
+            sequence_name: "".to_string(),
+        };
+        sql_update.update(trans).await.map_err(err_fwd!(
+            "💣 Cannot change the document frequencies, follower=[{}]",
+            &self.follower
+        ))?;
 
-        log_info!(
-            "Parsing the words from the raw data, file_ref=[{}], follower=[{}]",
-            &raw_text_request.file_ref,
+        let mut params = HashMap::new();
+        params.insert("p_file_ref".to_string(), CellValue::from_raw_str(file_ref));
+        let sql_delete = SQLChangeAsync {
+            sql_query: format!(
+                r"DELETE FROM cs_{}.document_keyword WHERE file_ref = :p_file_ref",
+                customer_code
+            ),
+            params,
+            sequence_name: "".to_string(),
+        };
+        sql_delete.delete(trans).await.map_err(err_fwd!(
+            "💣 Cannot delete the keywords, follower=[{}]",
             &self.follower
-        );
-        loop {
-            // Parse the words from the raw data until FINESSE_NBCHAR => pure word block (PWB)
-            let mut pure_word_block = ftt.next_n_words(FINESSE_LANGUAGE_BLOCK);
-            if pure_word_block.is_empty() {
-                break;
-            }
+        ))?;
+        Ok(())
+    }
 
-            // language detection on the language of the pure word block
-            let meta_data = tsc
-                .read_meta(&pure_word_block.join(" "))
-                .await
-                .map_err(err_fwd!(
-                    "Cannot read meta information, follower=[{}]",
-                    &self.follower
-                ))?;
-            let lang_code = map_code(&meta_data.language);
-
-            let language_words = match language_buffer_block.get_mut(lang_code) {
-                None => {
-                    log_debug!(
-                        "Init the language map for language=[{}], follower=[{}]",
-                        lang_code,
-                        &self.follower
-                    );
-                    language_buffer_block.insert(lang_code.to_string(), vec![]);
-                    language_buffer_block.get_mut(lang_code).unwrap()
-                }
-                Some(lw) => lw,
-            };
+    /// The file of an item out of the trash
+    pub(crate) async fn find_item_file_ref(
+        &self,
+        trans: &mut SQLTransactionAsync<'_>,
+        item_id: i64,
+        customer_code: &str,
+    ) -> Result<String, &'static ErrorSet<'static>> {
+        let mut params = HashMap::new();
+        params.insert("p_item_id".to_string(), CellValue::from_raw_int(item_id));
 
-            log_debug!(
-                "Add words for language, nb words=[{}], language=[{}({})], follower=[{}]",
-                pure_word_block.len(),
-                lang_code,
-                &meta_data.language,
-                &self.follower
-            );
-            language_words.append(&mut pure_word_block);
-        }
+        let query = SQLQueryBlockAsync {
+            sql_query: format!(
+                r"SELECT file_ref FROM cs_{}.item
+                    WHERE id = :p_item_id AND trashed_gmt IS NULL",
+                customer_code
+            ),
+            start: 0,
+            length: Some(1),
+            params,
+        };
 
-        log_info!(
-            "Init part counter, file_ref=[{}], follower=[{}]",
-            &raw_text_request.file_ref,
+        let Ok(mut sql_result) = query.execute(trans).await.map_err(err_fwd!(
+            "💣 Query failed, [{}], follower=[{}]",
+            &query.sql_query,
             &self.follower
-        );
-        let mut part_no = 0;
-        for (l, words) in &language_buffer_block {
-            log_debug!(
-                "For language=[{}] : len=[{}] : words={:?}, follower=[{}]",
-                l,
-                words.len(),
-                words,
+        )) else {
+            return Err(&INTERNAL_DATABASE_ERROR);
+        };
+
+        if !sql_result.next() {
+            log_error!(
+                "💣 Missing item, item_id=[{}], follower=[{}]",
+                item_id,
                 &self.follower
             );
+            return Err(&MISSING_ITEM);
+        }
 
-            let mut word_text: Vec<String> = vec![];
-            let mut word_text_size = 0;
-            let last_word_index = words.len() - 1;
-            for i in 0..=last_word_index {
-                let w = words.get(i).ok_or(anyhow::anyhow!("No word to read"))?;
-                word_text.push(w.clone());
-                word_text_size += w.len();
-
-                if word_text_size >= MAX_LANGUAGE_BUFFER_BLOCK || i == last_word_index {
-                    log_info!(
-                        "Create a new part, file_ref=[{}], part_no=[{}]",
-                        &raw_text_request.file_ref,
-                        part_no
-                    );
-                    let _id = self
-                        .insert_document_part(
-                            &mut trans,
-                            &raw_text_request.file_ref,
-                            part_no,
-                            &word_text.join(" "),
-                            lang_name_from_code_2(l),
-                            customer_code,
-                            customer_key,
-                        )
-                        .await
-                        .map_err(err_fwd!(
-                            "Cannot insert the part no [{}], follower=[{}]",
-                            part_no,
-                            &self.follower
-                        ))?;
-
-                    log_info!(
-                        "Create a new part Done, file_ref=[{}], part_no=[{}], follower=[{}]",
-                        &raw_text_request.file_ref,
-                        part_no,
-                        &self.follower
-                    );
-                    part_no += 1;
-                    word_text.clear();
-                    word_text_size = 0;
-                }
+        match sql_result.get_string("file_ref") {
+            Some(file_ref) => Ok(file_ref),
+            None => {
+                log_error!(
+                    "💣 The item has no file, item_id=[{}], follower=[{}]",
+                    item_id,
+                    &self.follower
+                );
+                Err(&MISSING_DOCUMENT_TEXT)
             }
         }
-
-        Ok(part_no)
     }
 
-    ///
-    async fn insert_document_part(
+    /// Decrypt the parts of the document and put them back together
+    async fn read_document_text(
         &self,
-        mut trans: &mut SQLTransactionAsync<'_>,
+        trans: &mut SQLTransactionAsync<'_>,
         file_ref: &str,
-        part_no: u32,
-        words_text: &str,
-        lang: &str,
-        customer_code: &str,
         customer_key: &str,
-    ) -> anyhow::Result<i64> {
-        log_info!(
-            "Insert document, file_ref=[{}], part_no=[{}], follower=[{}]",
-            file_ref,
-            part_no,
-            &self.follower
-        );
+        customer_code: &str,
+    ) -> Result<DocumentTextReply, &'static ErrorSet<'static>> {
+        let clear_parts = self
+            .read_document_parts(trans, file_ref, customer_key, customer_code)
+            .await?;
 
-        let words_encrypted = DkEncrypt::new(CC20)
-            .encrypt_str(words_text, customer_key)
-            .map_err(err_fwd!(
-                "Cannot encrypt the words, follower=[{}]",
+        if clear_parts.is_empty() {
+            log_error!(
+                "💣 No document part for the file, file_ref=[{}], follower=[{}]",
+                file_ref,
                 &self.follower
-            ))?;
+            );
+            return Err(&MISSING_DOCUMENT_TEXT);
+        }
 
-        let tsv = self
-            .select_tsvector(&mut trans, Some(lang), words_text)
+        Ok(assemble_parts(file_ref, clear_parts))
+    }
+
+    /// The decrypted parts of the document, in part_no order
+    pub(crate) async fn read_document_parts(
+        &self,
+        trans: &mut SQLTransactionAsync<'_>,
+        file_ref: &str,
+        customer_key: &str,
+        customer_code: &str,
+    ) -> Result<Vec<DocumentPart>, &'static ErrorSet<'static>> {
+        let Ok(parts) = self
+            .search_document_parts(trans, file_ref, customer_code)
             .await
             .map_err(err_fwd!(
-                "Cannot build the tsvector, follower=[{}]",
+                "💣 Cannot find the document parts, file_ref=[{}], follower=[{}]",
+                file_ref,
                 &self.follower
-            ))?;
-
-        // Encrypt the words of the tsvector, it's actually a Sha256 hash for each single word
-        let tsv_encrypted = encrypt_tsvector(&tsv, customer_key).map_err(err_fwd!(
-            "Cannot encrypt the vector, follower=[{}]",
-            &self.follower
-        ))?;
-        log_info!("Encrypted tsvector length: [{}]", tsv_encrypted.len());
-
-        // dbg!(&tsv_encrypted);
+            ))
+        else {
+            return Err(&INTERNAL_DATABASE_ERROR);
+        };
 
-        // Use a stored proc to hide the TSVECTOR type from Rust
-        let sql_query = format!(
-            r"CALL cs_{}.insert_document( :p_file_ref, :p_part_no, :p_doc_text, :p_tsv, :p_lang )",
-            customer_code
-        );
+        let mut clear_parts = vec![];
+        for part in parts {
+            let Ok(text) = DkEncrypt::new(CC20)
+                .decrypt_str(&part.doc_text, customer_key)
+                .map_err(err_fwd!(
+                    "💣 Cannot decrypt the part, file_ref=[{}], part_no=[{}], follower=[{}]",
+                    file_ref,
+                    part.part_no,
+                    &self.follower
+                ))
+            else {
+                return Err(&INTERNAL_TECHNICAL_ERROR);
+            };
+            clear_parts.push(DocumentPart {
+                doc_text: text,
+                ..part
+            });
+        }
 
-        // Still very important to name the sequence to get the id back after the insert
-        let sequence_name = format!("cs_{}.document_id_seq", customer_code);
+        Ok(clear_parts)
+    }
 
+    /// The parts of the document, still encrypted
+    async fn search_document_parts(
+        &self,
+        trans: &mut SQLTransactionAsync<'_>,
+        file_ref: &str,
+        customer_code: &str,
+    ) -> anyhow::Result<Vec<DocumentPart>> {
         let mut params = HashMap::new();
-        params.insert(
-            "p_file_ref".to_string(),
-            CellValue::from_raw_string(file_ref.to_string()),
-        );
-        params.insert(
-            "p_part_no".to_string(),
-            CellValue::from_raw_int_32(part_no as i32),
-        );
-        params.insert(
-            "p_doc_text".to_string(),
-            CellValue::from_raw_string(words_encrypted),
-        );
-        params.insert(
-            "p_tsv".to_string(),
-            CellValue::from_raw_string(tsv_encrypted),
-        );
-        params.insert(
-            "p_lang".to_string(),
-            CellValue::from_raw_string(lang.to_string()),
-        );
+        params.insert("p_file_ref".to_string(), CellValue::from_raw_str(file_ref));
 
-        let sql_insert = SQLChangeAsync {
-            sql_query,
+        let query = SQLQueryBlockAsync {
+            sql_query: format!(
+                r"SELECT part_no, doc_text, lang FROM cs_{}.document
+                    WHERE file_ref = :p_file_ref
+                    ORDER BY part_no ",
+                customer_code
+            ),
+            start: 0,
+            length: None,
             params,
-            sequence_name,
         };
 
-        let document_id = sql_insert
-            .insert(&mut trans)
+        let mut sql_result: SQLDataSet = query
+            .execute(trans)
             .await
-            .map_err(err_fwd!("Insertion failed, follower=[{}]", &self.follower))?;
+            .map_err(err_fwd!("Query failed, [{}]", &query.sql_query))?;
 
-        Ok(document_id)
+        let mut parts = vec![];
+        while sql_result.next() {
+            parts.push(DocumentPart {
+                part_no: sql_result
+                    .get_int_32("part_no")
+                    .ok_or(anyhow::anyhow!("Wrong part no"))?,
+                doc_text: sql_result
+                    .get_string("doc_text")
+                    .ok_or(anyhow::anyhow!("Wrong doc text"))?,
+                lang: sql_result
+                    .get_string("lang")
+                    .ok_or(anyhow::anyhow!("Wrong lang"))?,
+            });
+        }
+        Ok(parts)
     }
 
+    /// 🌟 Parse the raw text data and create the document parts
+    /// Service called from the file-server, the former parts of the file are replaced
     ///
-    async fn select_tsvector(
-        &self,
-        mut trans: &mut SQLTransactionAsync<'_>,
-        lang: Option<&str>,
-        text: &str,
-    ) -> anyhow::Result<String> {
-        let sql_query = match lang {
-            None => r"SELECT CAST( to_tsvector(unaccent_lower(:p_doc_text)) as VARCHAR ) as tsv"
-                .to_string(),
-            Some(lg) => {
-                format!(
-                    r"SELECT CAST( to_tsvector('{}',  unaccent_lower(:p_doc_text)) as VARCHAR ) as tsv",
-                    lg
-                )
-            }
-        };
+    /// The body is a NDJSON stream, a FullTextStreamHeader line followed by FullTextChunk lines.
+    /// The parts are written as soon as they are full, so the memory does not depend on the size of the text
+    pub async fn fulltext_indexing(mut self, body: Body) -> WebType<FullTextReply> {
+        log_info!(
+            "🚀 Start fulltext_indexing api, follower=[{}]",
+            &self.follower
+        );
 
-        let mut params = HashMap::new();
-        params.insert(
-            "p_doc_text".to_string(),
-            CellValue::from_raw_string(text.to_string()),
+        let entry_session = try_or_return!(
+            valid_sid_get_session(&self.session_token, &mut self.follower).await,
+            Self::web_type_error()
         );
-        let sql_block = SQLQueryBlockAsync {
-            sql_query: sql_query.to_string(),
-            start: 0,
-            length: None,
-            params,
-        };
 
-        let mut data = sql_block
-            .execute(&mut trans)
-            .await
-            .map_err(err_fwd!("Error compute tsvector"))?;
+        let customer_code = entry_session.customer_code.as_str();
 
-        let tsv = if data.next() {
-            data.get_string("tsv").unwrap_or("ERROR".to_string())
-        } else {
-            return Err(anyhow::anyhow!("Impossible to compute the tsvector"));
+        // Get the crypto key
+        let Ok(customer_key) = fetch_customer_key(customer_code, &self.follower)
+            .await
+            .map_err(err_fwd!(
+                "💣 Cannot get the customer key, follower=[{}]",
+                &self.follower
+            ))
+        else {
+            return WebType::from_errorset(&INTERNAL_TECHNICAL_ERROR);
+        };
+
+        // Open Db connection
+        let Ok(mut cnx) = SQLConnectionAsync::from_pool().await.map_err(err_fwd!(
+            "💣 New Db connection failed, follower=[{}]",
+            &self.follower
+        )) else {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        };
+
+        let Ok(mut trans) = cnx.begin().await.map_err(err_fwd!(
+            "💣 Transaction issue, follower=[{}]",
+            &self.follower
+        )) else {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        };
+
+        // Generate the FT index and create an entry in the "document" table
+        let mut text_indexing = TextIndexing::new(customer_code, &customer_key);
+        let Ok(part_count) = self
+            .indexing(&mut trans, body, &mut text_indexing)
+            .await
+            .map_err(err_fwd!(
+                "💣 Indexing process failed, follower=[{}]",
+                &self.follower
+            ))
+        else {
+            return WebType::from_errorset(&INTERNAL_TECHNICAL_ERROR);
+        };
+
+        if trans
+            .commit()
+            .await
+            .map_err(err_fwd!("💣 Commit failed, follower=[{}]", &self.follower))
+            .is_err()
+        {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        }
+
+        log_info!("😎 Generated the indexes and the document part entries, number of parts=[{}], follower=[{}]", part_count, &self.follower);
+        log_info!(
+            "🏁 End fulltext_indexing api, follower=[{}]",
+            &self.follower
+        );
+
+        WebType::from_item(StatusCode::OK.as_u16(), FullTextReply { part_count })
+    }
+
+    /// Read the lines of the body as they come and index their text
+    async fn indexing(
+        &self,
+        trans: &mut SQLTransactionAsync<'_>,
+        body: Body,
+        text_indexing: &mut TextIndexing,
+    ) -> anyhow::Result<u32> {
+        let tika_server_host = get_prop_value(TIKA_SERVER_HOSTNAME_PROPERTY).map_err(tr_fwd!())?;
+        let tika_server_port = get_prop_value(TIKA_SERVER_PORT_PROPERTY)
+            .map_err(tr_fwd!())?
+            .parse::<u16>()
+            .map_err(tr_fwd!())?;
+
+        let tsc = TikaServerClientAsync::new(&tika_server_host, tika_server_port);
+
+        let mut lines = NdjsonLines::default();
+        let mut stream = body.into_data_stream();
+        while let Some(bytes) = stream.next().await {
+            let bytes = bytes.map_err(|e| anyhow::anyhow!("Cannot read the body, e=[{}]", e))?;
+            for line in lines.push(&bytes)? {
+                self.read_indexing_line(trans, &tsc, text_indexing, &line)
+                    .await?;
+            }
+        }
+        if let Some(line) = lines.finish() {
+            self.read_indexing_line(trans, &tsc, text_indexing, &line)
+                .await?;
+        }
+
+        if text_indexing.file_ref.is_empty() {
+            return Err(anyhow::anyhow!("The indexing body has no header"));
+        }
+
+        // The last words are indexed whatever their number
+        if let Some(block) = text_indexing.take_language_block(true) {
+            self.add_language_block(trans, &tsc, text_indexing, block)
+                .await?;
+        }
+        let mut languages: Vec<String> = text_indexing.language_words.keys().cloned().collect();
+        languages.sort();
+        for lang_code in languages {
+            self.add_language_part(trans, text_indexing, &lang_code)
+                .await?;
+        }
+        self.insert_document_parts(trans, text_indexing).await?;
+        self.insert_document_keywords(trans, text_indexing).await?;
+        log_debug!(
+            "Parts written, number of parts=[{}], number of batches=[{}], follower=[{}]",
+            text_indexing.part_count,
+            text_indexing.batch_count,
+            &self.follower
+        );
+
+        Ok(text_indexing.part_count)
+    }
+
+    /// The first line is the header of the file, the next ones are the chunks of text
+    async fn read_indexing_line(
+        &self,
+        trans: &mut SQLTransactionAsync<'_>,
+        tsc: &TikaServerClientAsync,
+        text_indexing: &mut TextIndexing,
+        line: &[u8],
+    ) -> anyhow::Result<()> {
+        if text_indexing.file_ref.is_empty() {
+            let header: FullTextStreamHeader = serde_json::from_slice(line).map_err(tr_fwd!())?;
+            log_info!(
+                "Parsing the words from the raw data, file_ref=[{}], file_name=[{}], follower=[{}]",
+                &header.file_ref,
+                &header.file_name,
+                &self.follower
+            );
+            // A reindexed file replaces its former parts
+            self.delete_document_parts(trans, &header.file_ref, &text_indexing.customer_code)
+                .await?;
+            text_indexing.file_ref = header.file_ref;
+            return Ok(());
+        }
+
+        let chunk: FullTextChunk = serde_json::from_slice(line).map_err(tr_fwd!())?;
+        let mut ftt = FTTokenizer::new(&chunk.text);
+        loop {
+            let mut words = ftt.next_n_words(FINESSE_LANGUAGE_BLOCK);
+            if words.is_empty() {
+                break;
+            }
+            text_indexing.push_words(&mut words);
+            while let Some(block) = text_indexing.take_language_block(false) {
+                self.add_language_block(trans, tsc, text_indexing, block)
+                    .await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Detect the language of the block, its words go to the buffer of the language
+    /// and a part is written when the buffer is full
+    async fn add_language_block(
+        &self,
+        trans: &mut SQLTransactionAsync<'_>,
+        tsc: &TikaServerClientAsync,
+        text_indexing: &mut TextIndexing,
+        block: Vec<String>,
+    ) -> anyhow::Result<()> {
+        let lang_code = self.detect_language_code(tsc, &block.join(" ")).await?;
+        log_debug!(
+            "Add words for language, nb words=[{}], language=[{}], follower=[{}]",
+            block.len(),
+            &lang_code,
+            &self.follower
+        );
+        if text_indexing.add_words(&lang_code, block) {
+            self.add_language_part(trans, text_indexing, &lang_code)
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// Make a new part with the words of the buffer of the language,
+    /// the parts are written as soon as a batch is full
+    async fn add_language_part(
+        &self,
+        trans: &mut SQLTransactionAsync<'_>,
+        text_indexing: &mut TextIndexing,
+        lang_code: &str,
+    ) -> anyhow::Result<()> {
+        let Some(part_no) = text_indexing.take_part(lang_code) else {
+            return Ok(());
+        };
+        log_info!(
+            "Create a new part, file_ref=[{}], part_no=[{}], follower=[{}]",
+            &text_indexing.file_ref,
+            part_no,
+            &self.follower
+        );
+        if text_indexing.is_batch_full() {
+            self.insert_document_parts(trans, text_indexing).await?;
+        }
+        Ok(())
+    }
+
+    /// Write the pending parts with 2 statements, one for the tsvectors and one for the inserts
+    async fn insert_document_parts(
+        &self,
+        trans: &mut SQLTransactionAsync<'_>,
+        text_indexing: &mut TextIndexing,
+    ) -> anyhow::Result<()> {
+        let parts = text_indexing.take_pending_parts();
+        if parts.is_empty() {
+            return Ok(());
+        }
+        log_info!(
+            "Insert document parts, file_ref=[{}], first part_no=[{}], number of parts=[{}], follower=[{}]",
+            &text_indexing.file_ref,
+            parts[0].part_no,
+            parts.len(),
+            &self.follower
+        );
+
+        let tsvectors = self
+            .select_part_tsvectors(trans, &parts, &text_indexing.customer_code)
+            .await
+            .map_err(err_fwd!(
+                "Cannot build the tsvectors of the parts, follower=[{}]",
+                &self.follower
+            ))?;
+
+        let mut encrypted_parts = Vec::with_capacity(parts.len());
+        for (part, tsv) in parts.iter().zip(tsvectors.iter()) {
+            let encrypted_text = self.encrypt_with_tsvector(
+                &part.doc_text,
+                tsv,
+                &part.lang,
+                &text_indexing.customer_key,
+            )?;
+            text_indexing.add_term_counts(&encrypted_text.tsv);
+            encrypted_parts.push((part.part_no, encrypted_text));
+        }
+
+        // Use a stored proc to hide the TSVECTOR type from Rust
+        let sql_query = format!(
+            r"CALL cs_{}.insert_documents( :p_parts )",
+            &text_indexing.customer_code
+        );
+
+        let mut params = HashMap::new();
+        params.insert(
+            "p_parts".to_string(),
+            CellValue::from_raw_string(encrypted_parts_json(
+                &text_indexing.file_ref,
+                &encrypted_parts,
+            )),
+        );
+
+        let sql_insert = SQLChangeAsync {
+            sql_query,
+            params,
+            sequence_name: "".to_string(),
+        };
+
+        sql_insert
+            .update(trans)
+            .await
+            .map_err(err_fwd!("Insertion failed, follower=[{}]", &self.follower))?;
+        text_indexing.batch_count += 1;
+
+        Ok(())
+    }
+
+    ///
+    /// Count the document in the document frequency of each of its lexemes,
+    /// and keep the lexemes with the best TF-IDF against the documents of the customer as its keywords
+    /// The lexemes are the encrypted ones, like in the tsvectors
+    ///
+    async fn insert_document_keywords(
+        &self,
+        trans: &mut SQLTransactionAsync<'_>,
+        text_indexing: &TextIndexing,
+    ) -> anyhow::Result<()> {
+        if text_indexing.term_counts.is_empty() {
+            return Ok(());
+        }
+        let customer_code = &text_indexing.customer_code;
+
+        let lexemes: Vec<&String> = text_indexing.term_counts.keys().collect();
+        let mut params = HashMap::new();
+        params.insert(
+            "p_lexemes".to_string(),
+            CellValue::from_raw_string(json!(lexemes).to_string()),
+        );
+        let sql_insert = SQLChangeAsync {
+            sql_query: format!(
+                r"INSERT INTO cs_{0}.fulltext_term_stat AS s ( lexeme, doc_count )
+                    SELECT l, 1 FROM jsonb_array_elements_text( CAST( :p_lexemes AS jsonb ) ) AS l
+                    ON CONFLICT ( lexeme ) DO UPDATE SET doc_count = s.doc_count + 1",
+                customer_code
+            ),
+            params,
+            sequence_name: "".to_string(),
+        };
+        sql_insert.insert_no_pk(trans).await.map_err(err_fwd!(
+            "💣 Cannot count the document frequencies, follower=[{}]",
+            &self.follower
+        ))?;
+
+        let (doc_counts, total_count) = self
+            .search_document_frequencies(trans, &lexemes, customer_code)
+            .await?;
+
+        let keywords: Vec<Value> = text_indexing
+            .top_keywords(&doc_counts, total_count, KEYWORD_COUNT)
+            .into_iter()
+            .map(|(lexeme, term_count)| json!({ "lexeme": lexeme, "term_count": term_count }))
+            .collect();
+        let mut params = HashMap::new();
+        params.insert(
+            "p_file_ref".to_string(),
+            CellValue::from_raw_str(&text_indexing.file_ref),
+        );
+        params.insert(
+            "p_keywords".to_string(),
+            CellValue::from_raw_string(Value::Array(keywords).to_string()),
+        );
+        let sql_insert = SQLChangeAsync {
+            sql_query: format!(
+                r"INSERT INTO cs_{}.document_keyword ( file_ref, lexeme, term_count )
+                    SELECT :p_file_ref, k.lexeme, k.term_count
+                    FROM jsonb_to_recordset( CAST( :p_keywords AS jsonb ) ) AS k( lexeme varchar, term_count int4 )",
+                customer_code
+            ),
+            params,
+            sequence_name: "".to_string(),
+        };
+        sql_insert.insert_no_pk(trans).await.map_err(err_fwd!(
+            "💣 Cannot insert the keywords, follower=[{}]",
+            &self.follower
+        ))?;
+
+        Ok(())
+    }
+
+    /// The document frequency of each lexeme, and the number of documents with keywords,
+    /// the document being indexed included
+    async fn search_document_frequencies(
+        &self,
+        trans: &mut SQLTransactionAsync<'_>,
+        lexemes: &[&String],
+        customer_code: &str,
+    ) -> anyhow::Result<(HashMap<String, i64>, i64)> {
+        let mut params = HashMap::new();
+        params.insert(
+            "p_lexemes".to_string(),
+            CellValue::from_raw_string(json!(lexemes).to_string()),
+        );
+        let query = SQLQueryBlockAsync {
+            sql_query: format!(
+                r"SELECT s.lexeme, s.doc_count,
+                        ( SELECT COUNT(DISTINCT file_ref) FROM cs_{0}.document_keyword ) + 1 AS total_count
+                    FROM cs_{0}.fulltext_term_stat s
+                    WHERE s.lexeme IN ( SELECT jsonb_array_elements_text( CAST( :p_lexemes AS jsonb ) ) )",
+                customer_code
+            ),
+            start: 0,
+            length: None,
+            params,
+        };
+
+        let mut sql_result = query.execute(trans).await.map_err(err_fwd!(
+            "💣 Cannot read the document frequencies, follower=[{}]",
+            &self.follower
+        ))?;
+
+        let mut doc_counts = HashMap::new();
+        let mut total_count = 1;
+        while sql_result.next() {
+            let lexeme = sql_result
+                .get_string("lexeme")
+                .ok_or(anyhow::anyhow!("Wrong lexeme"))?;
+            let doc_count = sql_result.get_int_32("doc_count").unwrap_or(1) as i64;
+            total_count = sql_result.get_int("total_count").unwrap_or(1);
+            doc_counts.insert(lexeme, doc_count);
+        }
+        Ok((doc_counts, total_count))
+    }
+
+    /// Clear tsvectors of the parts, in the order of the parts, with a single statement
+    /// The lexemes of the stop words of the customer are deleted, the positions of the others are kept
+    async fn select_part_tsvectors(
+        &self,
+        mut trans: &mut SQLTransactionAsync<'_>,
+        parts: &[DocumentPart],
+        customer_code: &str,
+    ) -> anyhow::Result<Vec<String>> {
+        // The parts are sent as a json array, unnested into rows by jsonb_to_recordset
+        let sql_query = format!(
+            r"SELECT p.part_no, CAST( ts_delete( to_tsvector( CAST(p.lang AS regconfig), unaccent_lower(p.doc_text) ),
+                    ARRAY( SELECT (unnest(to_tsvector( CAST(p.lang AS regconfig), unaccent_lower(sw.word) ))).lexeme
+                        FROM cs_{}.fulltext_stop_word sw ) ) as VARCHAR ) as tsv
+                FROM jsonb_to_recordset( CAST( :p_parts AS jsonb ) ) AS p( part_no int4, doc_text text, lang text )
+                ORDER BY p.part_no",
+            customer_code
+        );
+
+        let mut params = HashMap::new();
+        params.insert(
+            "p_parts".to_string(),
+            CellValue::from_raw_string(clear_parts_json(parts)),
+        );
+        let sql_block = SQLQueryBlockAsync {
+            sql_query,
+            start: 0,
+            length: None,
+            params,
+        };
+
+        let mut data = sql_block
+            .execute(&mut trans)
+            .await
+            .map_err(err_fwd!("Error compute tsvectors"))?;
+
+        let mut tsvectors = Vec::with_capacity(parts.len());
+        while data.next() {
+            tsvectors.push(data.get_string("tsv").unwrap_or("ERROR".to_string()));
+        }
+        if tsvectors.len() != parts.len() {
+            return Err(anyhow::anyhow!(
+                "Impossible to compute the tsvectors, expected=[{}], found=[{}]",
+                parts.len(),
+                tsvectors.len()
+            ));
+        }
+
+        Ok(tsvectors)
+    }
+
+    /// Language name of a text, as expected by the tsvector functions
+    pub(crate) async fn detect_language(&self, text: &str) -> anyhow::Result<String> {
+        let tika_server_host = get_prop_value(TIKA_SERVER_HOSTNAME_PROPERTY).map_err(tr_fwd!())?;
+        let tika_server_port = get_prop_value(TIKA_SERVER_PORT_PROPERTY)
+            .map_err(tr_fwd!())?
+            .parse::<u16>()
+            .map_err(tr_fwd!())?;
+
+        let tsc = TikaServerClientAsync::new(&tika_server_host, tika_server_port);
+        let lang_code = self.detect_language_code(&tsc, text).await?;
+        Ok(lang_name_from_code_2(&lang_code).to_string())
+    }
+
+    /// Language code-2 of a text, from the in-process detector when the property selects it
+    /// Tika is the fallback when the detector cannot decide
+    async fn detect_language_code(
+        &self,
+        tsc: &TikaServerClientAsync,
+        text: &str,
+    ) -> anyhow::Result<String> {
+        let use_ngram = get_prop_value(LANGUAGE_DETECTOR_PROPERTY)
+            .map(|detector| detector == NGRAM_DETECTOR)
+            .unwrap_or(false);
+
+        if use_ngram {
+            if let Some(lang_code) = detect_code(text) {
+                return Ok(map_code(lang_code).to_string());
+            }
+            log_debug!(
+                "Language not detected in process, fallback to Tika, follower=[{}]",
+                &self.follower
+            );
+        }
+
+        let meta_data = tsc.read_meta(text).await.map_err(err_fwd!(
+            "Cannot read meta information, follower=[{}]",
+            &self.follower
+        ))?;
+        Ok(map_code(&meta_data.language).to_string())
+    }
+
+    /// Encrypt the text and its tsvector with the customer key
+    pub(crate) async fn encrypt_text(
+        &self,
+        trans: &mut SQLTransactionAsync<'_>,
+        text: &str,
+        lang: &str,
+        customer_key: &str,
+    ) -> anyhow::Result<EncryptedText> {
+        let tsv = self
+            .select_tsvector(trans, Some(lang), text)
+            .await
+            .map_err(err_fwd!(
+                "Cannot build the tsvector, follower=[{}]",
+                &self.follower
+            ))?;
+
+        self.encrypt_with_tsvector(text, &tsv, lang, customer_key)
+    }
+
+    /// Encrypt the text and its clear tsvector
+    fn encrypt_with_tsvector(
+        &self,
+        text: &str,
+        tsv: &str,
+        lang: &str,
+        customer_key: &str,
+    ) -> anyhow::Result<EncryptedText> {
+        let text_encrypted = DkEncrypt::new(CC20)
+            .encrypt_str(text, customer_key)
+            .map_err(err_fwd!(
+                "Cannot encrypt the words, follower=[{}]",
+                &self.follower
+            ))?;
+
+        // Encrypt the words of the tsvector, it's actually a Sha256 hash for each single word
+        let tsv_encrypted = encrypt_tsvector(tsv, customer_key).map_err(err_fwd!(
+            "Cannot encrypt the vector, follower=[{}]",
+            &self.follower
+        ))?;
+        log_info!("Encrypted tsvector length: [{}]", tsv_encrypted.len());
+
+        Ok(EncryptedText {
+            text: text_encrypted,
+            tsv: tsv_encrypted,
+            lang: lang.to_string(),
+        })
+    }
+
+    /// Clear tsvector of the text
+    async fn select_tsvector(
+        &self,
+        mut trans: &mut SQLTransactionAsync<'_>,
+        lang: Option<&str>,
+        text: &str,
+    ) -> anyhow::Result<String> {
+        let sql_query = match lang {
+            None => r"SELECT CAST( to_tsvector(unaccent_lower(:p_doc_text)) as VARCHAR ) as tsv"
+                .to_string(),
+            Some(lg) => {
+                format!(
+                    r"SELECT CAST( to_tsvector('{}',  unaccent_lower(:p_doc_text)) as VARCHAR ) as tsv",
+                    lg
+                )
+            }
+        };
+
+        let mut params = HashMap::new();
+        params.insert(
+            "p_doc_text".to_string(),
+            CellValue::from_raw_string(text.to_string()),
+        );
+        let sql_block = SQLQueryBlockAsync {
+            sql_query: sql_query.to_string(),
+            start: 0,
+            length: None,
+            params,
+        };
+
+        let mut data = sql_block
+            .execute(&mut trans)
+            .await
+            .map_err(err_fwd!("Error compute tsvector"))?;
+
+        let tsv = if data.next() {
+            data.get_string("tsv").unwrap_or("ERROR".to_string())
+        } else {
+            return Err(anyhow::anyhow!("Impossible to compute the tsvector"));
         };
 
         Ok(tsv)
     }
 }
+
+/// The words of a text being indexed, waiting for their language or for their part to be full
+/// The full parts wait for their batch to be written
+pub(crate) struct TextIndexing {
+    file_ref: String, // Empty until the header is read
+    customer_code: String,
+    customer_key: String,
+    pending_words: Vec<String>,
+    language_words: HashMap<String, (Vec<String>, usize)>, // { "fr", ( words, size ) }
+    pending_parts: Vec<DocumentPart>,
+    term_counts: HashMap<String, u32>, // { encrypted lexeme, number of positions }
+    part_count: u32,
+    batch_count: u32,
+}
+
+impl TextIndexing {
+    pub(crate) fn new(customer_code: &str, customer_key: &str) -> Self {
+        Self {
+            file_ref: String::new(),
+            customer_code: customer_code.to_string(),
+            customer_key: customer_key.to_string(),
+            pending_words: vec![],
+            language_words: HashMap::new(),
+            pending_parts: vec![],
+            term_counts: HashMap::new(),
+            part_count: 0,
+            batch_count: 0,
+        }
+    }
+
+    /// Append the words of the text, they wait for the language detection
+    pub(crate) fn push_words(&mut self, words: &mut Vec<String>) {
+        self.pending_words.append(words);
+    }
+
+    /// The next block of words for the language detection,
+    /// a smaller block is only taken at the end of the text
+    pub(crate) fn take_language_block(&mut self, end_of_text: bool) -> Option<Vec<String>> {
+        if self.pending_words.len() >= FINESSE_LANGUAGE_BLOCK {
+            let rest = self.pending_words.split_off(FINESSE_LANGUAGE_BLOCK);
+            Some(std::mem::replace(&mut self.pending_words, rest))
+        } else if end_of_text && !self.pending_words.is_empty() {
+            Some(std::mem::take(&mut self.pending_words))
+        } else {
+            None
+        }
+    }
+
+    /// Add the words to the buffer of the language, true when the buffer is full
+    pub(crate) fn add_words(&mut self, lang_code: &str, mut words: Vec<String>) -> bool {
+        let (language_words, size) = self
+            .language_words
+            .entry(lang_code.to_string())
+            .or_insert((vec![], 0));
+        *size += words.iter().map(|w| w.len()).sum::<usize>();
+        language_words.append(&mut words);
+        *size >= MAX_LANGUAGE_BUFFER_BLOCK
+    }
+
+    /// Make a new pending part with the buffer of the language, its part number if any
+    pub(crate) fn take_part(&mut self, lang_code: &str) -> Option<u32> {
+        let (words, _) = self.language_words.remove(lang_code)?;
+        let part_no = self.part_count;
+        self.pending_parts.push(DocumentPart {
+            part_no: part_no as i32,
+            doc_text: words.join(" "),
+            lang: lang_name_from_code_2(lang_code).to_string(),
+        });
+        self.part_count += 1;
+        Some(part_no)
+    }
+
+    pub(crate) fn is_batch_full(&self) -> bool {
+        self.pending_parts.len() >= PART_BATCH_SIZE
+    }
+
+    /// The pending parts, as they are written by a batch
+    pub(crate) fn take_pending_parts(&mut self) -> Vec<DocumentPart> {
+        std::mem::take(&mut self.pending_parts)
+    }
+
+    /// Add the lexemes of the encrypted tsvector of a part to the term counts of the document
+    fn add_term_counts(&mut self, tsv: &str) {
+        for (lexeme, count) in tsvector_term_counts(tsv) {
+            *self.term_counts.entry(lexeme).or_insert(0) += count;
+        }
+    }
+
+    /// The lexemes of the document with the best TF-IDF, the best first
+    /// A lexeme missing from the document frequencies is only in this document
+    fn top_keywords(
+        &self,
+        doc_counts: &HashMap<String, i64>,
+        total_count: i64,
+        n: usize,
+    ) -> Vec<(&str, u32)> {
+        let mut terms: Vec<(&str, u32, f64)> = self
+            .term_counts
+            .iter()
+            .map(|(lexeme, count)| {
+                let doc_count = doc_counts.get(lexeme).copied().unwrap_or(1);
+                (
+                    lexeme.as_str(),
+                    *count,
+                    tf_idf(*count as i32, doc_count, total_count),
+                )
+            })
+            .collect();
+        terms.sort_by(|a, b| b.2.total_cmp(&a.2).then(a.0.cmp(b.0)));
+        terms.truncate(n);
+        terms
+            .into_iter()
+            .map(|(lexeme, count, _)| (lexeme, count))
+            .collect()
+    }
+}
+
+/// Cut the bytes of a NDJSON body into lines, whatever the way the body is chunked
+#[derive(Default)]
+struct NdjsonLines {
+    buffer: Vec<u8>,
+}
+
+impl NdjsonLines {
+    /// The complete lines after the bytes, the end of the last line waits for the next bytes
+    fn push(&mut self, bytes: &[u8]) -> anyhow::Result<Vec<Vec<u8>>> {
+        let mut lines = vec![];
+        for b in bytes {
+            if *b == b'\n' {
+                let line = std::mem::take(&mut self.buffer);
+                if !line.is_empty() {
+                    lines.push(line);
+                }
+            } else {
+                self.buffer.push(*b);
+            }
+        }
+        if self.buffer.len() > MAX_NDJSON_LINE {
+            return Err(anyhow::anyhow!(
+                "The line is too long, size=[{}]",
+                self.buffer.len()
+            ));
+        }
+        Ok(lines)
+    }
+
+    /// The last line, when the body does not end with a new line
+    fn finish(self) -> Option<Vec<u8>> {
+        if self.buffer.is_empty() {
+            None
+        } else {
+            Some(self.buffer)
+        }
+    }
+}
+
+/// The clear parts, for the computation of their tsvectors
+pub(crate) fn clear_parts_json(parts: &[DocumentPart]) -> String {
+    let rows: Vec<Value> = parts
+        .iter()
+        .map(|p| json!({ "part_no": p.part_no, "doc_text": p.doc_text, "lang": p.lang }))
+        .collect();
+    Value::Array(rows).to_string()
+}
+
+/// The encrypted parts, for the stored proc of the bulk insert
+fn encrypted_parts_json(file_ref: &str, parts: &[(i32, EncryptedText)]) -> String {
+    let rows: Vec<Value> = parts
+        .iter()
+        .map(|(part_no, p)| {
+            json!({ "file_ref": file_ref, "part_no": part_no, "doc_text": p.text, "tsv": p.tsv, "lang": p.lang })
+        })
+        .collect();
+    Value::Array(rows).to_string()
+}
+
+/// Put the parts back together in order, each part starts on a new line
+pub(crate) fn assemble_parts(file_ref: &str, mut parts: Vec<DocumentPart>) -> DocumentTextReply {
+    parts.sort_by_key(|p| p.part_no);
+    let mut languages: Vec<String> = vec![];
+    for part in &parts {
+        if !languages.contains(&part.lang) {
+            languages.push(part.lang.clone());
+        }
+    }
+    DocumentTextReply {
+        file_ref: file_ref.to_string(),
+        part_count: parts.len() as u32,
+        languages,
+        text: parts
+            .into_iter()
+            .map(|p| p.doc_text)
+            .collect::<Vec<String>>()
+            .join("\n"),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+    use std::time::Instant;
+
+    use serde_json::Value;
+
+    use commons_pg::sql_transaction_async::{
+        SQLChangeAsync, SQLConnectionAsync, SQLQueryBlockAsync, SQLTransactionAsync,
+    };
+    use commons_services::token_lib::SessionToken;
+    use commons_services::x_request_id::XRequestID;
+
+    use crate::ft_tokenizer::FTTokenizer;
+    use crate::fulltext::{
+        assemble_parts, clear_parts_json, DocumentPart, FullTextDelegate, NdjsonLines,
+        TextIndexing, FINESSE_LANGUAGE_BLOCK, MAX_LANGUAGE_BUFFER_BLOCK, PART_BATCH_SIZE,
+    };
+    use crate::test_db::{create_customer_schema, TEST_DB_URL};
+
+    const KEY: &str = "fqYVyce-Nh0HwpPQ7ZGZLog5s7PBLnwFMAW2OMnNPUs";
+
+    /// Size of the text of the benchmark, like a big report
+    const BENCH_TEXT_SIZE: usize = 10 * 1024 * 1024;
+
+    /// Languages of the benchmark, with their sentences in lang-corpus/test
+    const BENCH_LANGUAGES: [&str; 7] = ["en", "fr", "de", "es", "it", "nl", "pt"];
+
+    /// Create the customer schema of the tests, with 'chat' as a stop word of the customer
+    async fn create_parts_schema(trans: &mut SQLTransactionAsync<'_>) -> anyhow::Result<()> {
+        create_customer_schema(trans, "partstest").await?;
+        SQLChangeAsync {
+            sql_query: "INSERT INTO cs_partstest.fulltext_stop_word ( word ) VALUES ( 'chat' )"
+                .to_string(),
+            params: Default::default(),
+            sequence_name: "".to_string(),
+        }
+        .insert_no_pk(trans)
+        .await?;
+        Ok(())
+    }
+
+    fn part(part_no: i32, doc_text: &str, lang: &str) -> DocumentPart {
+        DocumentPart {
+            part_no,
+            doc_text: doc_text.to_string(),
+            lang: lang.to_string(),
+        }
+    }
+
+    #[test]
+    fn assemble_parts_test() {
+        let reply = assemble_parts(
+            "abc",
+            vec![
+                part(2, "third", "english"),
+                part(0, "first", "french"),
+                part(1, "second", "english"),
+            ],
+        );
+        assert_eq!("abc", reply.file_ref);
+        assert_eq!(3, reply.part_count);
+        assert_eq!(vec!["french", "english"], reply.languages);
+        assert_eq!("first\nsecond\nthird", reply.text);
+    }
+
+    #[test]
+    fn ndjson_lines_test() {
+        let mut lines = NdjsonLines::default();
+        assert!(lines.push(b"{\"a\":").unwrap().is_empty());
+        let found = lines.push(b"1}\n\n{\"b\":2}\n{\"c\"").unwrap();
+        assert_eq!(vec![b"{\"a\":1}".to_vec(), b"{\"b\":2}".to_vec()], found);
+        assert!(lines.push(b":3}").unwrap().is_empty());
+        assert_eq!(Some(b"{\"c\":3}".to_vec()), lines.finish());
+    }
+
+    #[test]
+    fn take_language_block_test() {
+        let mut text_indexing = TextIndexing::new("abc", "key");
+        text_indexing.pending_words = (0..FINESSE_LANGUAGE_BLOCK + 10)
+            .map(|i| i.to_string())
+            .collect();
+        let block = text_indexing.take_language_block(false).unwrap();
+        assert_eq!(FINESSE_LANGUAGE_BLOCK, block.len());
+        assert_eq!("0", block[0]);
+        assert!(text_indexing.take_language_block(false).is_none());
+        let block = text_indexing.take_language_block(true).unwrap();
+        assert_eq!(10, block.len());
+        assert!(text_indexing.take_language_block(true).is_none());
+    }
+
+    #[test]
+    fn add_words_test() {
+        let mut text_indexing = TextIndexing::new("abc", "key");
+        let word = "x".repeat(MAX_LANGUAGE_BUFFER_BLOCK / 2);
+        assert!(!text_indexing.add_words("fr", vec![word.clone()]));
+        assert!(!text_indexing.add_words("en", vec![word.clone()]));
+        assert!(text_indexing.add_words("fr", vec![word]));
+    }
+
+    #[test]
+    fn take_part_test() {
+        let mut text_indexing = TextIndexing::new("abc", "key");
+        assert_eq!(None, text_indexing.take_part("fr"));
+        for i in 0..PART_BATCH_SIZE {
+            text_indexing.add_words("fr", vec!["le".to_string(), "chat".to_string()]);
+            assert!(!text_indexing.is_batch_full());
+            assert_eq!(Some(i as u32), text_indexing.take_part("fr"));
+        }
+        assert!(text_indexing.is_batch_full());
+        let parts = text_indexing.take_pending_parts();
+        assert_eq!(PART_BATCH_SIZE, parts.len());
+        assert_eq!("le chat", parts[0].doc_text);
+        assert_eq!("french", parts[0].lang);
+        assert!(!text_indexing.is_batch_full());
+    }
+
+    #[test]
+    fn top_keywords_test() {
+        let mut text_indexing = TextIndexing::new("abc", "key");
+        text_indexing.add_term_counts("'aa':1,3 'bb':2 'cc':4,5,6");
+        text_indexing.add_term_counts("'bb':1 'dd':2");
+        // Same document frequency, the most frequent first
+        let doc_counts = HashMap::new();
+        assert_eq!(
+            vec![("cc", 3), ("aa", 2), ("bb", 2)],
+            text_indexing.top_keywords(&doc_counts, 100, 3)
+        );
+        assert_eq!(4, text_indexing.top_keywords(&doc_counts, 100, 10).len());
+
+        // "cc" is in all the documents, the rare "aa" wins
+        let doc_counts = HashMap::from([("cc".to_string(), 100), ("bb".to_string(), 50)]);
+        assert_eq!(
+            vec![("aa", 2), ("dd", 1)],
+            text_indexing.top_keywords(&doc_counts, 100, 2)
+        );
+    }
+
+    #[test]
+    fn clear_parts_json_test() {
+        let json = clear_parts_json(&[part(0, "l'été", "french"), part(1, "a \"b\"", "english")]);
+        let rows: Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(2, rows.as_array().unwrap().len());
+        assert_eq!(0, rows[0]["part_no"]);
+        assert_eq!("l'été", rows[0]["doc_text"]);
+        assert_eq!("a \"b\"", rows[1]["doc_text"]);
+        assert_eq!("english", rows[1]["lang"]);
+    }
+
+    /// Write the parts of a document against a real schema, the schema is rolled back at the end
+    /// A batch is 2 statements (tsvectors + insert), so n parts take ceil(n/16) batches
+    /// Needs the database of the tests : cargo test -- --ignored
+    #[tokio::test]
+    #[ignore]
+    async fn insert_document_parts_test() -> anyhow::Result<()> {
+        const PART_COUNT: usize = 2 * PART_BATCH_SIZE + 5;
+
+        let mut cnx = SQLConnectionAsync::new(TEST_DB_URL).await?;
+        let mut trans = cnx.begin().await?;
+        create_parts_schema(&mut trans).await?;
+
+        let delegate = FullTextDelegate::new(
+            SessionToken("test".to_string()),
+            XRequestID::from_value(None),
+        );
+        let mut text_indexing = TextIndexing::new("partstest", KEY);
+        text_indexing.file_ref = "file-ref-parts".to_string();
+        for i in 0..PART_COUNT {
+            let lang_code = if i % 2 == 0 { "fr" } else { "en" };
+            text_indexing.add_words(
+                lang_code,
+                vec![format!("part{}", i), "le".to_string(), "chat".to_string()],
+            );
+            delegate
+                .add_language_part(&mut trans, &mut text_indexing, lang_code)
+                .await?;
+        }
+        delegate
+            .insert_document_parts(&mut trans, &mut text_indexing)
+            .await?;
+
+        assert_eq!(PART_COUNT as u32, text_indexing.part_count);
+        assert_eq!(
+            PART_COUNT.div_ceil(PART_BATCH_SIZE) as u32,
+            text_indexing.batch_count
+        );
+
+        let mut data = SQLQueryBlockAsync {
+            sql_query: r"SELECT part_no, lang, CAST( tsv as VARCHAR ) as tsv FROM cs_partstest.document
+                    WHERE file_ref = 'file-ref-parts' ORDER BY id"
+                .to_string(),
+            start: 0,
+            length: None,
+            params: Default::default(),
+        }
+        .execute(&mut trans)
+        .await?;
+        trans.rollback().await;
+
+        let mut part_nos = vec![];
+        while data.next() {
+            let part_no = data.get_int_32("part_no").unwrap();
+            let lang = data.get_string("lang").unwrap();
+            let lexeme_count = data.get_string("tsv").unwrap().split(' ').count();
+            // The lexemes are encrypted, the stop word of the customer is gone in both languages
+            // and "le" is a stop word in french only
+            if part_no % 2 == 0 {
+                assert_eq!(("french", 1), (lang.as_str(), lexeme_count));
+            } else {
+                assert_eq!(("english", 2), (lang.as_str(), lexeme_count));
+            }
+            part_nos.push(part_no);
+        }
+        assert_eq!((0..PART_COUNT as i32).collect::<Vec<i32>>(), part_nos);
+        Ok(())
+    }
+
+    /// Blocks of words of about [size] bytes in all the languages of the benchmark,
+    /// each block holds the words of one language, like the blocks of the indexing
+    fn multi_language_blocks(size: usize) -> anyhow::Result<Vec<(&'static str, Vec<String>)>> {
+        let mut language_words = vec![];
+        for lang_code in BENCH_LANGUAGES {
+            let path = format!(
+                "{}/lang-corpus/test/{}.txt",
+                env!("CARGO_MANIFEST_DIR"),
+                lang_code
+            );
+            let text = std::fs::read_to_string(path)?;
+            let mut ftt = FTTokenizer::new(&text);
+            let words = ftt.next_n_words(usize::MAX);
+            language_words.push((lang_code, words));
+        }
+
+        let mut blocks = vec![];
+        let mut text_size = 0;
+        for (lang_code, words) in language_words.iter().cycle() {
+            if text_size >= size {
+                break;
+            }
+            let block: Vec<String> = words
+                .iter()
+                .cycle()
+                .take(FINESSE_LANGUAGE_BLOCK)
+                .cloned()
+                .collect();
+            text_size += block.iter().map(|w| w.len() + 1).sum::<usize>();
+            blocks.push((*lang_code, block));
+        }
+        Ok(blocks)
+    }
+
+    /// Write the blocks of words as the indexing does, with the parts written
+    /// every [PART_BATCH_SIZE] parts, or one by one with [per_part]
+    async fn write_blocks(
+        trans: &mut SQLTransactionAsync<'_>,
+        file_ref: &str,
+        blocks: &[(&str, Vec<String>)],
+        per_part: bool,
+    ) -> anyhow::Result<TextIndexing> {
+        let delegate = FullTextDelegate::new(
+            SessionToken("test".to_string()),
+            XRequestID::from_value(None),
+        );
+        let mut text_indexing = TextIndexing::new("partstest", KEY);
+        text_indexing.file_ref = file_ref.to_string();
+        for (lang_code, block) in blocks {
+            if text_indexing.add_words(lang_code, block.clone()) {
+                delegate
+                    .add_language_part(trans, &mut text_indexing, lang_code)
+                    .await?;
+                if per_part {
+                    delegate
+                        .insert_document_parts(trans, &mut text_indexing)
+                        .await?;
+                }
+            }
+        }
+        for lang_code in BENCH_LANGUAGES {
+            delegate
+                .add_language_part(trans, &mut text_indexing, lang_code)
+                .await?;
+            if per_part {
+                delegate
+                    .insert_document_parts(trans, &mut text_indexing)
+                    .await?;
+            }
+        }
+        delegate
+            .insert_document_parts(trans, &mut text_indexing)
+            .await?;
+        Ok(text_indexing)
+    }
+
+    /// Benchmark of the parts of a 10 MB text in 7 languages : one tsvector query and one CALL
+    /// for every part, like before the batches, against the batches of [PART_BATCH_SIZE] parts
+    /// Measured with a release build on a local database, 4 runs of 47 parts :
+    ///     per part : 94 statements, 4_570 to 5_147 ms
+    ///     batched  :  6 statements, 3_994 to 5_586 ms
+    /// Without network between the server and the database, the time goes to the tsvectors
+    /// and the encryption, the gain is in the noise. The batches save 88 round trips,
+    /// so the gain grows with the latency of the database.
+    /// Needs the database of the tests : cargo test --release -- --ignored --nocapture
+    #[tokio::test]
+    #[ignore]
+    async fn insert_document_parts_perf() -> anyhow::Result<()> {
+        let blocks = multi_language_blocks(BENCH_TEXT_SIZE)?;
+
+        let mut cnx = SQLConnectionAsync::new(TEST_DB_URL).await?;
+        let mut trans = cnx.begin().await?;
+        create_parts_schema(&mut trans).await?;
+
+        let start = Instant::now();
+        let per_part = write_blocks(&mut trans, "file-ref-per-part", &blocks, true).await?;
+        let per_part_time = start.elapsed().as_millis();
+
+        let start = Instant::now();
+        let batched = write_blocks(&mut trans, "file-ref-batched", &blocks, false).await?;
+        let batched_time = start.elapsed().as_millis();
+        trans.rollback().await;
+
+        println!(
+            "{} parts, per part : {} statements, {} ms",
+            per_part.part_count,
+            2 * per_part.batch_count,
+            per_part_time
+        );
+        println!(
+            "{} parts, batched : {} statements, {} ms",
+            batched.part_count,
+            2 * batched.batch_count,
+            batched_time
+        );
+
+        assert_eq!(per_part.part_count, batched.part_count);
+        assert_eq!(per_part.part_count, per_part.batch_count);
+        assert_eq!(
+            batched.part_count.div_ceil(PART_BATCH_SIZE as u32),
+            batched.batch_count
+        );
+        Ok(())
+    }
+}
@@ -22,19 +22,70 @@ use commons_services::token_lib::SessionToken;
 use commons_services::try_or_return;
 use commons_services::x_request_id::{Follower, XRequestID};
 use dkdto::error_codes::{
-    BAD_TAG_FOR_ITEM, INCORRECT_TAG_TYPE, INTERNAL_DATABASE_ERROR, MISSING_ITEM,
-    MISSING_TAG_FOR_ITEM,
+    BAD_TAG_FOR_ITEM, ENUM_VALUE_NOT_ALLOWED, INCORRECT_AS_OF_DATE, INCORRECT_CSV,
+    INCORRECT_FILTER, INCORRECT_TAG_TYPE, INTERNAL_DATABASE_ERROR, INTERNAL_TECHNICAL_ERROR,
+    INVALID_REQUEST, MISSING_DOCUMENT_TYPE, MISSING_ITEM, MISSING_TAG_FOR_ITEM,
+    TAG_CONSTRAINT_VIOLATION,
 };
 use dkdto::{
     AddItemReply, AddItemRequest, AddItemTagReply, AddItemTagRequest, AddTagRequest, AddTagValue,
-    EnumTagValue, ErrorSet, GetItemReply, ItemElement, SimpleMessage, TagType, TagValueElement,
-    WebTypeBuilder,
+    BulkTagReply, BulkTagRequest, CsvLineError, DeletedTagValue, DocumentTypeElement, EnumTagValue,
+    ErrorSet, ExportCsvReply, GetItemHistoryReply, GetItemReply, ImportCsvReply, ImportCsvRequest,
+    ItemElement, ItemFileRefsReply, SimpleMessage, TagElement, TagHistoryElement, TagType,
+    TagValueElement, WebTypeBuilder,
 };
 use doka_cli::request_client::TokenType;
 
-use crate::filter::{analyse_expression, to_sql_form, FilterExpressionAST};
+use crate::classification::ClassificationDelegate;
+use crate::document_type::DocumentTypeDelegate;
+use crate::filter::{
+    analyse_expression, extract_all_attributes, generate_item_condition, FilterExpressionAST,
+};
+use crate::item_csv::{
+    duplicated_keys, read_csv_sheet, row_properties, write_csv_sheet, CsvColumn, CsvSheet,
+    KEY_COLUMNS,
+};
+use crate::item_lock::{parse_if_match, ItemLockDelegate};
+use crate::item_version::{ItemVersionDelegate, NewVersion};
 use crate::{TagDelegate, WebType};
 
+/// Number of items changed in a single transaction by a bulk tag operation
+const BULK_TAG_BATCH_SIZE: usize = 500;
+
+/// Text form of the value of the tag value row "tv"
+const TAG_VALUE_AS_TEXT: &str = r#"COALESCE(tv.value_string,
+                                    CAST(tv.value_integer AS VARCHAR),
+                                    CAST(tv.value_double AS VARCHAR),
+                                    CAST(tv.value_date AS VARCHAR),
+                                    to_char(tv.value_datetime, 'YYYY-MM-DD"T"HH24:MI:SS"Z"'),
+                                    CAST(tv.value_boolean AS VARCHAR))"#;
+
+/// Kind of change kept in the tag value history
+enum TagChange {
+    Insert,
+    Update,
+    Delete,
+}
+
+impl TagChange {
+    fn as_str(&self) -> &'static str {
+        match self {
+            TagChange::Insert => "insert",
+            TagChange::Update => "update",
+            TagChange::Delete => "delete",
+        }
+    }
+
+    /// Old and new values as SQL expressions, the old value of an update is given by :p_old_value
+    fn value_expressions(&self) -> (&'static str, &'static str) {
+        match self {
+            TagChange::Insert => ("NULL", TAG_VALUE_AS_TEXT),
+            TagChange::Update => (":p_old_value", TAG_VALUE_AS_TEXT),
+            TagChange::Delete => (TAG_VALUE_AS_TEXT, "NULL"),
+        }
+    }
+}
+
 pub(crate) struct ItemDelegate {
     pub session_token: SessionToken,
     pub follower: Follower,
@@ -61,9 +112,10 @@ impl ItemDelegate {
         filters: Option<String>,
     ) -> WebType<GetItemReply> {
         log_info!(
-            "🚀 Start get_all_item api, start_page=[{:?}], page_size=[{:?}], follower=[{}]",
+            "🚀 Start search_item api, start_page=[{:?}], page_size=[{:?}], filters=[{:?}], follower=[{}]",
             start_page,
             page_size,
+            &filters,
             &self.follower
         );
 
@@ -72,29 +124,7 @@ impl ItemDelegate {
             Self::web_type_error()
         );
 
-        let filter_tokens: Box<FilterExpressionAST> =
-            match analyse_expression(&filters.unwrap_or("()".to_owned())) {
-                Ok(v) => v,
-                Err(_) => {
-                    // TODO
-                    panic!("Cannot lex the expression");
-                }
-            };
-
-        let s = to_sql_form(&filter_tokens.deref()).unwrap(); // TODO
-        log_info!("sql = {}", &s);
-
-        // let r_lexemes = filter_lexer::lex3(&filters.0);
-        // // Normalise !!!
-        //
-        // let Ok(lexeme) = r_lexemes else {
-        //     panic!("Cannot lex the expression");
-        // };
-        //
-        // let filter_tokens: Box<FilterExpressionAST> = parse_tokens(&lexeme).unwrap(); // TODO * 2
-
-        // Verify the the attributes are existing tag in doka and the type complies with the filter condition
-        // ...
+        let customer_code = entry_session.customer_code.as_str();
 
         log_info!("😎 We fetched the session, follower=[{}]", &self.follower);
 
@@ -113,17 +143,26 @@ impl ItemDelegate {
             return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
         };
 
+        let sql_condition = try_or_return!(
+            self.build_filter_condition(&mut trans, filters.as_deref(), customer_code)
+                .await,
+            Self::web_type_error()
+        );
+
         let Ok(items) = self
             .search_item_with_filter(
                 &mut trans,
-                &filter_tokens.deref(),
+                &sql_condition,
                 start_page,
                 page_size,
-                &entry_session.customer_code,
+                customer_code,
             )
             .await
         else {
-            log_error!("💣 Cannot find item by id, follower=[{}]", &self.follower);
+            log_error!(
+                "💣 Cannot find item by filter, follower=[{}]",
+                &self.follower
+            );
             return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
         };
 
@@ -142,25 +181,22 @@ impl ItemDelegate {
             return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
         }
 
-        log_info!("🏁 End get_all_item, follower=[{}]", &self.follower);
+        log_info!("🏁 End search_item, follower=[{}]", &self.follower);
 
-        WebType::from_errorset(&INTERNAL_DATABASE_ERROR)
+        WebType::from_item(StatusCode::OK.as_u16(), GetItemReply { items })
     }
 
-    /// Deprecated - replace it with search_item
-    /// 🌟 Find all the items at page [start_page]
     ///
-    pub async fn get_all_item(
+    /// 🌟 Add, change and delete tags on all the items matching the filter
+    ///     The items are processed in batches, each batch in its own transaction
+    ///
+    pub async fn bulk_tag(
         mut self,
-        start_page: Option<u32>,
-        page_size: Option<u32>,
-    ) -> WebType<GetItemReply> {
-        // Already done in the delegate constructor : self.follower.x_request_id = self.follower.x_request_id.new_if_null();
-
+        bulk_tag_request: Json<BulkTagRequest>,
+    ) -> WebType<BulkTagReply> {
         log_info!(
-            "🚀 Start get_all_item api, start_page=[{:?}], page_size=[{:?}], follower=[{}]",
-            start_page,
-            page_size,
+            "🚀 Start bulk_tag api, bulk_tag_request=[{:?}], follower=[{}]",
+            &bulk_tag_request,
             &self.follower
         );
 
@@ -169,30 +205,31 @@ impl ItemDelegate {
             Self::web_type_error()
         );
 
-        self.follower.token_type = TokenType::Sid(self.session_token.0.clone());
+        let customer_code = entry_session.customer_code.as_str();
 
-        // // Check if the token is valid
-        // if !self.session_token.is_valid() {
-        //     log_error!(
-        //         "💣 Invalid session token=[{:?}], follower=[{}]",
-        //         &self.session_token,
-        //         &self.follower
-        //     );
-        //     return WebType::from_errorset(&INVALID_TOKEN);
-        // }
-        //
-        // // Read the session information
-        // let Ok(entry_session) = fetch_entry_session(&self.follower.token_type.value())
-        //     .await
-        //     .map_err(err_fwd!(
-        //         "💣 Session Manager failed, follower=[{}]",
-        //         &self.follower
-        //     ))
-        // else {
-        //     return WebType::from_errorset(&INTERNAL_TECHNICAL_ERROR);
-        // };
+        // A bulk change on all the items is too dangerous, the filter is mandatory
+        let filters = bulk_tag_request.filters.trim();
+        if filters.is_empty() || filters == "()" {
+            log_error!("💣 The filter is mandatory, follower=[{}]", &self.follower);
+            return WebType::from_errorset(&INCORRECT_FILTER);
+        }
 
-        log_info!("😎 We fetched the session, follower=[{}]", &self.follower);
+        let no_properties = vec![];
+        let properties = bulk_tag_request
+            .properties
+            .as_ref()
+            .unwrap_or(&no_properties);
+        let no_deleted_tags = vec![];
+        let deleted_tags = bulk_tag_request
+            .deleted_tags
+            .as_ref()
+            .unwrap_or(&no_deleted_tags);
+        let no_deleted_values = vec![];
+        let deleted_values = bulk_tag_request
+            .deleted_values
+            .as_ref()
+            .unwrap_or(&no_deleted_values);
+        let dry_run = bulk_tag_request.dry_run.unwrap_or(false);
 
         // Open Db connection
         let Ok(mut cnx) = SQLConnectionAsync::from_pool().await.map_err(err_fwd!(
@@ -202,6 +239,7 @@ impl ItemDelegate {
             return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
         };
 
+        // | Find the items and check the changes once for all
         let Ok(mut trans) = cnx.begin().await.map_err(err_fwd!(
             "💣 Transaction issue, follower=[{}]",
             &self.follower
@@ -209,26 +247,32 @@ impl ItemDelegate {
             return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
         };
 
-        let Ok(items) = self
-            .search_item_by_id(
-                &mut trans,
-                None,
-                start_page,
-                page_size,
-                &entry_session.customer_code,
-            )
+        let sql_condition = try_or_return!(
+            self.build_filter_condition(&mut trans, Some(filters), customer_code)
+                .await,
+            Self::web_type_error()
+        );
+
+        let violations = try_or_return!(
+            self.check_tag_constraints(&mut trans, properties, false, customer_code)
+                .await,
+            Self::web_type_error()
+        );
+        if !violations.is_empty() {
+            return Self::constraint_violation_error(&violations);
+        }
+
+        let Ok(item_ids) = self
+            .find_item_ids(&mut trans, &sql_condition, customer_code)
             .await
+            .map_err(err_fwd!(
+                "💣 Cannot find the items, follower=[{}]",
+                &self.follower
+            ))
         else {
-            log_error!("💣 Cannot find item by id, follower=[{}]", &self.follower);
             return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
         };
 
-        log_info!(
-            "😎 We found the items, item count=[{}], follower=[{}]",
-            items.len(),
-            &self.follower
-        );
-
         if trans
             .commit()
             .await
@@ -238,124 +282,953 @@ impl ItemDelegate {
             return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
         }
 
-        log_info!("🏁 End get_all_item, follower=[{}]", &self.follower);
+        log_info!(
+            "😎 We found the items, item count=[{}], dry_run=[{}], follower=[{}]",
+            item_ids.len(),
+            dry_run,
+            &self.follower
+        );
 
-        WebType::from_item(StatusCode::OK.as_u16(), GetItemReply { items })
-    }
+        if dry_run {
+            log_info!("🏁 End bulk_tag, follower=[{}]", &self.follower);
+            return WebType::from_item(
+                StatusCode::OK.as_u16(),
+                BulkTagReply {
+                    item_count: item_ids.len() as u64,
+                    dry_run,
+                },
+            );
+        }
 
-    /// Search items from the filter given
-    /// If no item id provided, return all existing items
-    /// TODO Merge the main query with the property query in order to reduce the number of SQL queries
-    async fn search_item_with_filter(
-        &self,
-        mut trans: &mut SQLTransactionAsync<'_>,
-        filters: &FilterExpressionAST,
-        start_page: Option<u32>,
-        page_size: Option<u32>,
-        customer_code: &str,
-    ) -> anyhow::Result<Vec<ItemElement>> {
-        let sql_condition = match to_sql_form(&filters) {
-            Ok(sq) => sq,
-            Err(e) => {
-                log_error!("Syntax error in {:?}", e);
-                return Err(anyhow!("Impossible to parse the filters")); // TODO find a way to inform the client about the detail of the error
-            }
-        };
+        // | Apply the changes, batch by batch
+        let mut processed: usize = 0;
+        for batch in item_ids.chunks(BULK_TAG_BATCH_SIZE) {
+            let Ok(mut trans) = cnx.begin().await.map_err(err_fwd!(
+                "💣 Transaction issue, follower=[{}]",
+                &self.follower
+            )) else {
+                return Self::bulk_error(&INTERNAL_DATABASE_ERROR, processed);
+            };
 
-        let params = HashMap::new();
+            for item_id in batch {
+                if let Err(e) = self
+                    .item_lock_delegate()
+                    .prepare_write(&mut trans, *item_id, entry_session.id, None, customer_code)
+                    .await
+                {
+                    return Self::bulk_error(e, processed);
+                }
+                let required_before = match self
+                    .find_required_tags_on_item(&mut trans, *item_id, customer_code)
+                    .await
+                {
+                    Ok(required_before) => required_before,
+                    Err(e) => return Self::bulk_error(e, processed),
+                };
+                if !properties.is_empty() {
+                    if let Err(e) = self
+                        .update_tags_on_item(
+                            &mut trans,
+                            *item_id,
+                            entry_session.user_id,
+                            customer_code,
+                            properties,
+                        )
+                        .await
+                    {
+                        return Self::bulk_error(e, processed);
+                    }
+                }
+                if let Err(e) = self
+                    .delete_tags_on_item(
+                        &mut trans,
+                        *item_id,
+                        deleted_tags,
+                        entry_session.user_id,
+                        customer_code,
+                    )
+                    .await
+                {
+                    return Self::bulk_error(e, processed);
+                }
+                if let Err(e) = self
+                    .delete_tag_text_values_on_item(
+                        &mut trans,
+                        *item_id,
+                        deleted_values,
+                        entry_session.user_id,
+                        customer_code,
+                    )
+                    .await
+                {
+                    return Self::bulk_error(e, processed);
+                }
+                // The batch is rolled back when an item loses the last value of a required tag
+                match self
+                    .check_required_values_left(
+                        &mut trans,
+                        *item_id,
+                        &required_before,
+                        customer_code,
+                    )
+                    .await
+                {
+                    Ok(violations) if !violations.is_empty() => {
+                        log_error!(
+                            "💣 Required tag removed, item_id=[{}], violations=[{:?}], follower=[{}]",
+                            item_id,
+                            &violations,
+                            &self.follower
+                        );
+                        return Self::bulk_error(&TAG_CONSTRAINT_VIOLATION, processed);
+                    }
+                    Ok(_) => {}
+                    Err(e) => return Self::bulk_error(e, processed),
+                }
+            }
 
-        let sql_query = format!(
-            r"SELECT id, name, file_ref, created_gmt, last_modified_gmt
-                    FROM cs_{0}.item INNER JOIN   cs_{0}.property prop
-                    WHERE  ({1})
-                    ORDER BY prop.col1 ",
-            customer_code, sql_condition
-        );
+            if trans
+                .commit()
+                .await
+                .map_err(err_fwd!("💣 Commit failed, follower=[{}]", &self.follower))
+                .is_err()
+            {
+                return Self::bulk_error(&INTERNAL_DATABASE_ERROR, processed);
+            }
 
-        let query = SQLQueryBlockAsync {
-            sql_query,
-            start: start_page.unwrap_or(0) * page_size.unwrap_or(0),
-            length: page_size,
-            params,
-        };
+            processed += batch.len();
+            log_info!(
+                "😎 We processed a batch of items, processed=[{}/{}], follower=[{}]",
+                processed,
+                item_ids.len(),
+                &self.follower
+            );
+        }
 
-        let mut _sql_result: SQLDataSet = query
-            .execute(&mut trans)
-            .await
-            .map_err(err_fwd!("Query failed, [{}]", &query.sql_query))?;
+        log_info!("🏁 End bulk_tag, follower=[{}]", &self.follower);
 
-        Ok(vec![])
+        WebType::from_item(
+            StatusCode::OK.as_u16(),
+            BulkTagReply {
+                item_count: processed as u64,
+                dry_run,
+            },
+        )
     }
 
-    /// ! Deprecated - user search_with_filter instead
-    /// Search items by id
-    /// If no item id provided, return all existing items
-    /// TODO Merge the main query with the property query in order to reduce the number of SQL queries
-    async fn search_item_by_id(
-        &self,
-        mut trans: &mut SQLTransactionAsync<'_>,
-        item_id: Option<i64>,
-        start_page: Option<u32>,
-        page_size: Option<u32>,
-        customer_code: &str,
-    ) -> anyhow::Result<Vec<ItemElement>> {
-        let p_item_id = CellValue::Int(item_id);
+    /// The batches already committed are kept, tell how many items were processed
+    fn bulk_error(e: &ErrorSet<'static>, processed: usize) -> WebType<BulkTagReply> {
+        log_error!(
+            "💣 Bulk tag failed, error=[{}], processed=[{}]",
+            e.err_message,
+            processed
+        );
+        WebType::from_simple(
+            e.http_error_code,
+            SimpleMessage {
+                message: format!("{}, processed items=[{}]", e.err_message, processed),
+            },
+        )
+    }
 
-        let mut params = HashMap::new();
-        params.insert("p_item_id".to_owned(), p_item_id);
+    ///
+    /// 🌟 Set the tag values of the items from a csv sheet
+    ///     All the rows are validated first, nothing is changed if one of them is wrong
+    ///
+    pub async fn import_csv(
+        mut self,
+        import_request: Json<ImportCsvRequest>,
+    ) -> WebType<ImportCsvReply> {
+        log_info!(
+            "🚀 Start import_csv api, match_by=[{:?}], dry_run=[{:?}], follower=[{}]",
+            &import_request.match_by,
+            &import_request.dry_run,
+            &self.follower
+        );
 
-        let sql_query = format!(
-            r"SELECT id, name, file_ref, created_gmt, last_modified_gmt
-                    FROM cs_{}.item
-                    WHERE ( id = :p_item_id OR  :p_item_id IS NULL )
-                    ORDER BY name ",
-            customer_code
+        let entry_session = try_or_return!(
+            valid_sid_get_session(&self.session_token, &mut self.follower).await,
+            Self::web_type_error()
         );
 
-        let query = SQLQueryBlockAsync {
-            sql_query,
-            start: start_page.unwrap_or(0) * page_size.unwrap_or(0),
-            length: page_size,
-            params,
+        let customer_code = entry_session.customer_code.as_str();
+        let dry_run = import_request.dry_run.unwrap_or(false);
+
+        let key_column = import_request.match_by.as_deref().unwrap_or("name");
+        if !KEY_COLUMNS.contains(&key_column) {
+            log_error!(
+                "💣 Items can only be matched by name or file_ref, match_by=[{}], follower=[{}]",
+                key_column,
+                &self.follower
+            );
+            return WebType::from_errorset(&INCORRECT_CSV);
+        }
+
+        let sheet = match read_csv_sheet(&import_request.csv, key_column) {
+            Ok(sheet) => sheet,
+            Err(e) => {
+                log_error!(
+                    "💣 Cannot read the csv, error=[{}], follower=[{}]",
+                    e,
+                    &self.follower
+                );
+                return WebType::from_simple(
+                    INCORRECT_CSV.http_error_code,
+                    SimpleMessage {
+                        message: format!("{}: {}", INCORRECT_CSV.err_message, e),
+                    },
+                );
+            }
         };
 
-        let mut sql_result: SQLDataSet = query
-            .execute(&mut trans)
-            .await
-            .map_err(err_fwd!("Query failed, [{}]", &query.sql_query))?;
+        log_info!(
+            "😎 We read the csv, tag count=[{}], row count=[{}], follower=[{}]",
+            sheet.tag_names.len(),
+            sheet.rows.len(),
+            &self.follower
+        );
 
-        let mut items = vec![];
-        while sql_result.next() {
-            let id: i64 = sql_result.get_int("id").ok_or(anyhow!("Wring id"))?;
-            let name: String = sql_result.get_string("name").unwrap_or("".to_owned());
-            let o_file_ref: Option<String> = sql_result.get_string("file_ref"); // .unwrap_or("".to_owned());
-            let created_gmt = sql_result
-                .get_timestamp_as_datetime("created_gmt")
-                .ok_or(anyhow::anyhow!("Wrong created gmt"))
-                .map_err(tr_fwd!())?;
+        // Open Db connection
+        let Ok(mut cnx) = SQLConnectionAsync::from_pool().await.map_err(err_fwd!(
+            "💣 New Db connection failed, follower=[{}]",
+            &self.follower
+        )) else {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        };
 
-            // Optional
-            let last_modified_gmt = sql_result
-                .get_timestamp_as_datetime("last_modified_gmt")
-                .as_ref()
-                .map(|x| date_time_to_iso(x));
+        let Ok(mut trans) = cnx.begin().await.map_err(err_fwd!(
+            "💣 Transaction issue, follower=[{}]",
+            &self.follower
+        )) else {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        };
 
-            let props = self
-                .find_item_properties(trans, id, customer_code)
-                .await
-                .map_err(tr_fwd!())?;
+        let columns = self
+            .read_csv_columns(&mut trans, &sheet, customer_code)
+            .await;
 
-            let item = ItemElement {
-                item_id: id,
-                name,
-                file_ref: o_file_ref,
-                created: date_time_to_iso(&created_gmt),
-                last_modified: last_modified_gmt,
-                properties: Some(props),
+        // | Validate all the rows
+        let duplicates = duplicated_keys(&sheet);
+        let mut errors = vec![];
+        let mut changes = vec![];
+        for row in &sheet.rows {
+            let Ok(item_ids) = self
+                .find_item_ids_by_key(&mut trans, key_column, &row.key, customer_code)
+                .await
+                .map_err(err_fwd!(
+                    "💣 Cannot find the item, key=[{}], follower=[{}]",
+                    &row.key,
+                    &self.follower
+                ))
+            else {
+                return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
             };
 
-            let _ = &items.push(item);
-        }
+            let mut row_errors = vec![];
+            if duplicates.contains(row.key.as_str()) {
+                row_errors.push(format!("{} [{}] is on several rows", key_column, &row.key));
+            }
+            match item_ids.len() {
+                0 => row_errors.push(format!("No item with {} [{}]", key_column, &row.key)),
+                1 => {}
+                n => row_errors.push(format!("{} items with {} [{}]", n, key_column, &row.key)),
+            }
+
+            let (properties, property_errors) = row_properties(&columns, &row.cells);
+            row_errors.extend(property_errors);
+
+            let violations = try_or_return!(
+                self.check_tag_constraints(&mut trans, &properties, false, customer_code)
+                    .await,
+                Self::web_type_error()
+            );
+            row_errors.extend(violations);
+
+            if !row_errors.is_empty() {
+                errors.extend(row_errors.into_iter().map(|message| CsvLineError {
+                    line: row.line,
+                    message,
+                }));
+            } else if !properties.is_empty() {
+                changes.push((row.line, item_ids[0], properties));
+            }
+        }
+
+        let reply = ImportCsvReply {
+            row_count: sheet.rows.len() as u64,
+            item_count: changes.len() as u64,
+            errors,
+            dry_run,
+        };
+
+        if !reply.errors.is_empty() || dry_run {
+            log_info!(
+                "🏁 End import_csv without change, error count=[{}], follower=[{}]",
+                reply.errors.len(),
+                &self.follower
+            );
+            return WebType::from_item(StatusCode::OK.as_u16(), reply);
+        }
+
+        // | Change the tag values, the missing tags are defined on the fly
+        for (line, item_id, properties) in &changes {
+            let r_change = match self
+                .item_lock_delegate()
+                .prepare_write(&mut trans, *item_id, entry_session.id, None, customer_code)
+                .await
+            {
+                Ok(()) => {
+                    self.update_tags_on_item(
+                        &mut trans,
+                        *item_id,
+                        entry_session.user_id,
+                        customer_code,
+                        properties,
+                    )
+                    .await
+                }
+                Err(e) => Err(e),
+            };
+            if let Err(e) = r_change {
+                log_error!(
+                    "💣 Cannot change the tags, line=[{}], item_id=[{}], follower=[{}]",
+                    line,
+                    item_id,
+                    &self.follower
+                );
+                return WebType::from_simple(
+                    e.http_error_code,
+                    SimpleMessage {
+                        message: format!("{}, line=[{}]", e.err_message, line),
+                    },
+                );
+            }
+        }
+
+        if trans
+            .commit()
+            .await
+            .map_err(err_fwd!("💣 Commit failed, follower=[{}]", &self.follower))
+            .is_err()
+        {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        }
+
+        log_info!(
+            "🏁 End import_csv, item_count=[{}], follower=[{}]",
+            reply.item_count,
+            &self.follower
+        );
+
+        WebType::from_item(StatusCode::OK.as_u16(), reply)
+    }
+
+    ///
+    /// 🌟 Write the items matching the filter as csv, one column per tag
+    ///
+    pub async fn export_csv(mut self, filters: Option<String>) -> WebType<ExportCsvReply> {
+        log_info!(
+            "🚀 Start export_csv api, filters=[{:?}], follower=[{}]",
+            &filters,
+            &self.follower
+        );
+
+        let entry_session = try_or_return!(
+            valid_sid_get_session(&self.session_token, &mut self.follower).await,
+            Self::web_type_error()
+        );
+
+        let customer_code = entry_session.customer_code.as_str();
+
+        // Open Db connection
+        let Ok(mut cnx) = SQLConnectionAsync::from_pool().await.map_err(err_fwd!(
+            "💣 New Db connection failed, follower=[{}]",
+            &self.follower
+        )) else {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        };
+
+        let Ok(mut trans) = cnx.begin().await.map_err(err_fwd!(
+            "💣 Transaction issue, follower=[{}]",
+            &self.follower
+        )) else {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        };
+
+        let sql_condition = try_or_return!(
+            self.build_filter_condition(&mut trans, filters.as_deref(), customer_code)
+                .await,
+            Self::web_type_error()
+        );
+
+        let Ok(items) = self
+            .search_item_with_filter(&mut trans, &sql_condition, None, None, customer_code)
+            .await
+            .map_err(err_fwd!(
+                "💣 Cannot search the items, follower=[{}]",
+                &self.follower
+            ))
+        else {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        };
+
+        if trans
+            .commit()
+            .await
+            .map_err(err_fwd!("💣 Commit failed, follower=[{}]", &self.follower))
+            .is_err()
+        {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        }
+
+        let Ok(csv) = write_csv_sheet(&items).map_err(err_fwd!(
+            "💣 Cannot write the csv, follower=[{}]",
+            &self.follower
+        )) else {
+            return WebType::from_errorset(&INTERNAL_TECHNICAL_ERROR);
+        };
+
+        log_info!(
+            "🏁 End export_csv, item_count=[{}], follower=[{}]",
+            items.len(),
+            &self.follower
+        );
+
+        WebType::from_item(
+            StatusCode::OK.as_u16(),
+            ExportCsvReply {
+                item_count: items.len() as u64,
+                csv,
+            },
+        )
+    }
+
+    ///
+    /// 🌟 Find the file references of the items matching the filter
+    ///     The file server reindexes the files of a search with them
+    ///
+    pub async fn search_file_refs(mut self, filters: Option<String>) -> WebType<ItemFileRefsReply> {
+        log_info!(
+            "🚀 Start search_file_refs api, filters=[{:?}], follower=[{}]",
+            &filters,
+            &self.follower
+        );
+
+        let entry_session = try_or_return!(
+            valid_sid_get_session(&self.session_token, &mut self.follower).await,
+            Self::web_type_error()
+        );
+
+        let customer_code = entry_session.customer_code.as_str();
+
+        // All the files are found with the pattern of the file server, the filter is mandatory
+        let filters = filters.unwrap_or_default();
+        if filters.trim().is_empty() || filters.trim() == "()" {
+            log_error!("💣 The filter is mandatory, follower=[{}]", &self.follower);
+            return WebType::from_errorset(&INCORRECT_FILTER);
+        }
+
+        // Open Db connection
+        let Ok(mut cnx) = SQLConnectionAsync::from_pool().await.map_err(err_fwd!(
+            "💣 New Db connection failed, follower=[{}]",
+            &self.follower
+        )) else {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        };
+
+        let Ok(mut trans) = cnx.begin().await.map_err(err_fwd!(
+            "💣 Transaction issue, follower=[{}]",
+            &self.follower
+        )) else {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        };
+
+        let sql_condition = try_or_return!(
+            self.build_filter_condition(&mut trans, Some(&filters), customer_code)
+                .await,
+            Self::web_type_error()
+        );
+
+        let Ok(file_refs) = self
+            .find_file_refs(&mut trans, &sql_condition, customer_code)
+            .await
+            .map_err(err_fwd!(
+                "💣 Cannot find the file references, follower=[{}]",
+                &self.follower
+            ))
+        else {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        };
+
+        if trans
+            .commit()
+            .await
+            .map_err(err_fwd!("💣 Commit failed, follower=[{}]", &self.follower))
+            .is_err()
+        {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        }
+
+        log_info!(
+            "🏁 End search_file_refs, file_count=[{}], follower=[{}]",
+            file_refs.len(),
+            &self.follower
+        );
+
+        WebType::from_item(StatusCode::OK.as_u16(), ItemFileRefsReply { file_refs })
+    }
+
+    /// Tag of each column, from its definition or with the type inferred from the values
+    async fn read_csv_columns(
+        &self,
+        trans: &mut SQLTransactionAsync<'_>,
+        sheet: &CsvSheet,
+        customer_code: &str,
+    ) -> Vec<CsvColumn> {
+        let session_token = self.session_token.clone();
+        let x_request_id = self.follower.x_request_id.clone();
+        let tag_delegate = TagDelegate::new(session_token, x_request_id);
+
+        let mut columns = vec![];
+        for (index, tag_name) in sheet.tag_names.iter().enumerate() {
+            let column = match tag_delegate
+                .search_tag_by_name(trans, tag_name, customer_code)
+                .await
+            {
+                Ok(tag) => CsvColumn::from_tag(tag),
+                Err(_) => {
+                    let values: Vec<&str> = sheet
+                        .rows
+                        .iter()
+                        .map(|row| row.cells[index].as_str())
+                        .collect();
+                    CsvColumn::new_tag(tag_name, &values)
+                }
+            };
+            log_debug!(
+                "Csv column, tag_name=[{}], tag_type=[{}], follower=[{}]",
+                &column.tag_name,
+                &column.tag_type,
+                &self.follower
+            );
+            columns.push(column);
+        }
+        columns
+    }
+
+    /// Find the ids of the items with the given name or file_ref
+    async fn find_item_ids_by_key(
+        &self,
+        trans: &mut SQLTransactionAsync<'_>,
+        key_column: &str,
+        key: &str,
+        customer_code: &str,
+    ) -> anyhow::Result<Vec<i64>> {
+        let sql_query = format!(
+            r"SELECT it.id FROM cs_{0}.item it
+                    WHERE it.{1} = :p_key
+                    AND it.trashed_gmt IS NULL
+                    ORDER BY it.id ",
+            customer_code, key_column
+        );
+
+        let mut params = HashMap::new();
+        params.insert("p_key".to_string(), CellValue::from_raw_str(key));
+
+        let query = SQLQueryBlockAsync {
+            sql_query,
+            start: 0,
+            length: None,
+            params,
+        };
+
+        let mut sql_result: SQLDataSet = query
+            .execute(trans)
+            .await
+            .map_err(err_fwd!("Query failed, [{}]", &query.sql_query))?;
+
+        let mut item_ids = vec![];
+        while sql_result.next() {
+            item_ids.push(sql_result.get_int("id").ok_or(anyhow!("Wrong id"))?);
+        }
+        Ok(item_ids)
+    }
+
+    /// Find the ids of all the items matching the sql condition
+    async fn find_item_ids(
+        &self,
+        trans: &mut SQLTransactionAsync<'_>,
+        sql_condition: &str,
+        customer_code: &str,
+    ) -> anyhow::Result<Vec<i64>> {
+        let sql_query = format!(
+            r"SELECT it.id FROM cs_{0}.item it
+                    WHERE ({1})
+                    AND it.trashed_gmt IS NULL
+                    ORDER BY it.id ",
+            customer_code, sql_condition
+        );
+
+        let query = SQLQueryBlockAsync {
+            sql_query,
+            start: 0,
+            length: None,
+            params: HashMap::new(),
+        };
+
+        let mut sql_result: SQLDataSet = query
+            .execute(trans)
+            .await
+            .map_err(err_fwd!("Query failed, [{}]", &query.sql_query))?;
+
+        let mut item_ids = vec![];
+        while sql_result.next() {
+            item_ids.push(sql_result.get_int("id").ok_or(anyhow!("Wrong id"))?);
+        }
+        Ok(item_ids)
+    }
+
+    /// Find the file references of the items matching the sql condition
+    async fn find_file_refs(
+        &self,
+        trans: &mut SQLTransactionAsync<'_>,
+        sql_condition: &str,
+        customer_code: &str,
+    ) -> anyhow::Result<Vec<String>> {
+        let sql_query = format!(
+            r"SELECT DISTINCT it.file_ref FROM cs_{0}.item it
+                    WHERE ({1})
+                    AND it.trashed_gmt IS NULL
+                    AND it.file_ref IS NOT NULL
+                    ORDER BY it.file_ref ",
+            customer_code, sql_condition
+        );
+
+        let query = SQLQueryBlockAsync {
+            sql_query,
+            start: 0,
+            length: None,
+            params: HashMap::new(),
+        };
+
+        let mut sql_result: SQLDataSet = query
+            .execute(trans)
+            .await
+            .map_err(err_fwd!("Query failed, [{}]", &query.sql_query))?;
+
+        let mut file_refs = vec![];
+        while sql_result.next() {
+            file_refs.push(
+                sql_result
+                    .get_string("file_ref")
+                    .ok_or(anyhow!("Wrong file_ref"))?,
+            );
+        }
+        Ok(file_refs)
+    }
+
+    /// Translate the filter expression into a sql condition on the items (alias "it")
+    /// An empty filter matches all the items
+    pub(crate) async fn build_filter_condition(
+        &self,
+        trans: &mut SQLTransactionAsync<'_>,
+        filters: Option<&str>,
+        customer_code: &str,
+    ) -> Result<String, &'static ErrorSet<'static>> {
+        let filters = filters.unwrap_or("").trim();
+        if filters.is_empty() || filters == "()" {
+            return Ok("TRUE".to_owned());
+        }
+
+        let filter_tokens: Box<FilterExpressionAST> = match analyse_expression(filters) {
+            Ok(v) => v,
+            Err(e) => {
+                log_error!(
+                    "💣 Cannot analyse the filter, error=[{}], follower=[{}]",
+                    e.human_error_message(),
+                    &self.follower
+                );
+                return Err(&INCORRECT_FILTER);
+            }
+        };
+
+        let Ok(attributes) = extract_all_attributes(&filter_tokens) else {
+            return Err(&INCORRECT_FILTER);
+        };
+
+        // Verify the attributes are existing tags in doka
+        let session_token = self.session_token.clone();
+        let x_request_id = self.follower.x_request_id.clone();
+        let tag_delegate = TagDelegate::new(session_token, x_request_id);
+
+        let mut tags = HashMap::new();
+        for attribute in attributes {
+            let Ok(tag) = tag_delegate
+                .search_tag_by_name(trans, &attribute, customer_code)
+                .await
+            else {
+                log_error!(
+                    "💣 Unknown tag in the filter, tag_name=[{}], follower=[{}]",
+                    &attribute,
+                    &self.follower
+                );
+                return Err(&INCORRECT_FILTER);
+            };
+            tags.insert(attribute, tag);
+        }
+
+        match generate_item_condition(&filter_tokens, &tags, customer_code) {
+            Ok(sql_condition) => {
+                log_debug!(
+                    "Filter condition=[{}], follower=[{}]",
+                    &sql_condition,
+                    &self.follower
+                );
+                Ok(sql_condition)
+            }
+            Err(e) => {
+                log_error!(
+                    "💣 Cannot generate the filter condition, error=[{}], follower=[{}]",
+                    e,
+                    &self.follower
+                );
+                Err(&INCORRECT_FILTER)
+            }
+        }
+    }
+
+    /// Deprecated - replace it with search_item
+    /// 🌟 Find all the items at page [start_page]
+    ///
+    pub async fn get_all_item(
+        mut self,
+        start_page: Option<u32>,
+        page_size: Option<u32>,
+    ) -> WebType<GetItemReply> {
+        // Already done in the delegate constructor : self.follower.x_request_id = self.follower.x_request_id.new_if_null();
+
+        log_info!(
+            "🚀 Start get_all_item api, start_page=[{:?}], page_size=[{:?}], follower=[{}]",
+            start_page,
+            page_size,
+            &self.follower
+        );
+
+        let entry_session = try_or_return!(
+            valid_sid_get_session(&self.session_token, &mut self.follower).await,
+            Self::web_type_error()
+        );
+
+        self.follower.token_type = TokenType::Sid(self.session_token.0.clone());
+
+        // // Check if the token is valid
+        // if !self.session_token.is_valid() {
+        //     log_error!(
+        //         "💣 Invalid session token=[{:?}], follower=[{}]",
+        //         &self.session_token,
+        //         &self.follower
+        //     );
+        //     return WebType::from_errorset(&INVALID_TOKEN);
+        // }
+        //
+        // // Read the session information
+        // let Ok(entry_session) = fetch_entry_session(&self.follower.token_type.value())
+        //     .await
+        //     .map_err(err_fwd!(
+        //         "💣 Session Manager failed, follower=[{}]",
+        //         &self.follower
+        //     ))
+        // else {
+        //     return WebType::from_errorset(&INTERNAL_TECHNICAL_ERROR);
+        // };
+
+        log_info!("😎 We fetched the session, follower=[{}]", &self.follower);
+
+        // Open Db connection
+        let Ok(mut cnx) = SQLConnectionAsync::from_pool().await.map_err(err_fwd!(
+            "💣 New Db connection failed, follower=[{}]",
+            &self.follower
+        )) else {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        };
+
+        let Ok(mut trans) = cnx.begin().await.map_err(err_fwd!(
+            "💣 Transaction issue, follower=[{}]",
+            &self.follower
+        )) else {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        };
+
+        let Ok(items) = self
+            .search_item_by_id(
+                &mut trans,
+                None,
+                start_page,
+                page_size,
+                &entry_session.customer_code,
+            )
+            .await
+        else {
+            log_error!("💣 Cannot find item by id, follower=[{}]", &self.follower);
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        };
+
+        log_info!(
+            "😎 We found the items, item count=[{}], follower=[{}]",
+            items.len(),
+            &self.follower
+        );
+
+        if trans
+            .commit()
+            .await
+            .map_err(err_fwd!("💣 Commit failed, follower=[{}]", &self.follower))
+            .is_err()
+        {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        }
+
+        log_info!("🏁 End get_all_item, follower=[{}]", &self.follower);
+
+        WebType::from_item(StatusCode::OK.as_u16(), GetItemReply { items })
+    }
+
+    /// Search items from the sql condition built from the filters
+    /// TODO Merge the main query with the property query in order to reduce the number of SQL queries
+    async fn search_item_with_filter(
+        &self,
+        mut trans: &mut SQLTransactionAsync<'_>,
+        sql_condition: &str,
+        start_page: Option<u32>,
+        page_size: Option<u32>,
+        customer_code: &str,
+    ) -> anyhow::Result<Vec<ItemElement>> {
+        let params = HashMap::new();
+
+        let sql_query = format!(
+            r"SELECT it.id, it.name, it.file_ref, it.created_gmt, it.last_modified_gmt,
+                    it.legal_hold, it.version, it.checked_out_user_id, it.checkout_expiry_gmt,
+                    dt.name AS document_type
+                    FROM cs_{0}.item it
+                    LEFT JOIN cs_{0}.document_type dt ON dt.id = it.document_type_id
+                    WHERE ({1})
+                    AND it.trashed_gmt IS NULL
+                    ORDER BY it.name ",
+            customer_code, sql_condition
+        );
+
+        let query = SQLQueryBlockAsync {
+            sql_query,
+            start: start_page.unwrap_or(0) * page_size.unwrap_or(0),
+            length: page_size,
+            params,
+        };
+
+        let sql_result: SQLDataSet = query
+            .execute(&mut trans)
+            .await
+            .map_err(err_fwd!("Query failed, [{}]", &query.sql_query))?;
+
+        self.read_items(trans, sql_result, customer_code).await
+    }
+
+    /// ! Deprecated - user search_with_filter instead
+    /// Search items by id
+    /// If no item id provided, return all the items out of the trash
+    /// TODO Merge the main query with the property query in order to reduce the number of SQL queries
+    async fn search_item_by_id(
+        &self,
+        mut trans: &mut SQLTransactionAsync<'_>,
+        item_id: Option<i64>,
+        start_page: Option<u32>,
+        page_size: Option<u32>,
+        customer_code: &str,
+    ) -> anyhow::Result<Vec<ItemElement>> {
+        let p_item_id = CellValue::Int(item_id);
+
+        let mut params = HashMap::new();
+        params.insert("p_item_id".to_owned(), p_item_id);
+
+        let sql_query = format!(
+            r"SELECT it.id, it.name, it.file_ref, it.created_gmt, it.last_modified_gmt,
+                    it.legal_hold, it.version, it.checked_out_user_id, it.checkout_expiry_gmt,
+                    dt.name AS document_type
+                    FROM cs_{0}.item it
+                    LEFT JOIN cs_{0}.document_type dt ON dt.id = it.document_type_id
+                    WHERE ( it.id = :p_item_id OR  :p_item_id IS NULL )
+                    AND it.trashed_gmt IS NULL
+                    ORDER BY it.name ",
+            customer_code
+        );
+
+        let query = SQLQueryBlockAsync {
+            sql_query,
+            start: start_page.unwrap_or(0) * page_size.unwrap_or(0),
+            length: page_size,
+            params,
+        };
+
+        let sql_result: SQLDataSet = query
+            .execute(&mut trans)
+            .await
+            .map_err(err_fwd!("Query failed, [{}]", &query.sql_query))?;
+
+        self.read_items(trans, sql_result, customer_code).await
+    }
+
+    /// Build the items from the rows, with their properties
+    async fn read_items(
+        &self,
+        trans: &mut SQLTransactionAsync<'_>,
+        mut sql_result: SQLDataSet,
+        customer_code: &str,
+    ) -> anyhow::Result<Vec<ItemElement>> {
+        let mut items = vec![];
+        while sql_result.next() {
+            let id: i64 = sql_result.get_int("id").ok_or(anyhow!("Wring id"))?;
+            let name: String = sql_result.get_string("name").unwrap_or("".to_owned());
+            let o_file_ref: Option<String> = sql_result.get_string("file_ref"); // .unwrap_or("".to_owned());
+            let created_gmt = sql_result
+                .get_timestamp_as_datetime("created_gmt")
+                .ok_or(anyhow::anyhow!("Wrong created gmt"))
+                .map_err(tr_fwd!())?;
+
+            // Optional
+            let last_modified_gmt = sql_result
+                .get_timestamp_as_datetime("last_modified_gmt")
+                .as_ref()
+                .map(|x| date_time_to_iso(x));
+            let document_type = sql_result.get_string("document_type");
+            let legal_hold = sql_result.get_bool("legal_hold").unwrap_or(false);
+            let version = sql_result.get_int("version").unwrap_or(1);
+            // An expired check-out is the same as no check-out
+            let checkout_expiry_gmt = sql_result
+                .get_timestamp_as_datetime("checkout_expiry_gmt")
+                .filter(|expiry| *expiry > Utc::now());
+            let checked_out_by =
+                checkout_expiry_gmt.and_then(|_| sql_result.get_int("checked_out_user_id"));
+
+            let props = self
+                .find_item_properties(trans, id, customer_code)
+                .await
+                .map_err(tr_fwd!())?;
+
+            let item = ItemElement {
+                item_id: id,
+                name,
+                file_ref: o_file_ref,
+                created: date_time_to_iso(&created_gmt),
+                last_modified: last_modified_gmt,
+                properties: Some(props),
+                document_type,
+                legal_hold,
+                version,
+                checked_out_by,
+                checkout_expiry: checkout_expiry_gmt.as_ref().map(date_time_to_iso),
+            };
+
+            let _ = &items.push(item);
+        }
 
         Ok(items)
     }
@@ -363,7 +1236,7 @@ impl ItemDelegate {
     ///
     ///
     ///
-    async fn find_item_properties(
+    pub(crate) async fn find_item_properties(
         &self,
         trans: &mut SQLTransactionAsync<'_>,
         item_id: i64,
@@ -381,7 +1254,8 @@ impl ItemDelegate {
                 tv.value_date, tv.value_datetime, tv.value_boolean
                 FROM cs_{}.tag_value tv
                 INNER JOIN cs_{}.tag_definition td ON td.id = tv.tag_id
-                WHERE tv.item_id = :p_item_id ",
+                WHERE tv.item_id = :p_item_id
+                ORDER BY td.name, tv.id ",
             customer_code, customer_code
         );
 
@@ -430,6 +1304,10 @@ impl ItemDelegate {
                     let value_string = sql_result.get_string("value_string");
                     EnumTagValue::Link(value_string)
                 }
+                TagType::Enum => {
+                    let value_string = sql_result.get_string("value_string");
+                    EnumTagValue::Enumeration(value_string)
+                }
                 TagType::Bool => {
                     let value_boolean = sql_result.get_bool("value_boolean");
                     EnumTagValue::Boolean(value_boolean)
@@ -472,12 +1350,13 @@ impl ItemDelegate {
     ///
     /// 🌟 Find an item from its item id
     ///
-    pub async fn get_item(mut self, item_id: i64) -> WebType<GetItemReply> {
+    pub async fn get_item(mut self, item_id: i64, as_of: Option<String>) -> WebType<GetItemReply> {
         // Done in the delegate constructor : self.follower.x_request_id = self.follower.x_request_id.new_if_null();
 
         log_info!(
-            "🚀 Start get_item api, item_id=[{}], follower=[{}]",
+            "🚀 Start get_item api, item_id=[{}], as_of=[{:?}], follower=[{}]",
             item_id,
+            &as_of,
             &self.follower
         );
 
@@ -527,7 +1406,20 @@ impl ItemDelegate {
             return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
         };
 
-        let Ok(items) = self
+        let o_as_of = match as_of.as_deref().map(iso_to_datetime).transpose() {
+            Ok(o_as_of) => o_as_of,
+            Err(e) => {
+                log_error!(
+                    "💣 Incorrect as of datetime, as_of=[{:?}], error=[{}], follower=[{}]",
+                    &as_of,
+                    e,
+                    &self.follower
+                );
+                return WebType::from_errorset(&INCORRECT_AS_OF_DATE);
+            }
+        };
+
+        let Ok(mut items) = self
             .search_item_by_id(
                 &mut trans,
                 Some(item_id),
@@ -560,6 +1452,54 @@ impl ItemDelegate {
             &self.follower
         );
 
+        // | Bring the tag values back to the "as of" time
+        if let Some(as_of) = o_as_of {
+            let item = &mut items[0];
+            if iso_to_datetime(&item.created).map_or(false, |created| created > as_of) {
+                log_error!(
+                    "💣 The item did not exist yet, item_id=[{}], as_of=[{}], follower=[{}]",
+                    item_id,
+                    date_time_to_iso(&as_of),
+                    &self.follower
+                );
+                return WebType::from_errorset(&MISSING_ITEM);
+            }
+
+            let Ok(changes) = self
+                .search_tag_history(
+                    &mut trans,
+                    item_id,
+                    Some(SystemTime::from(as_of)),
+                    &entry_session.customer_code,
+                )
+                .await
+                .map_err(err_fwd!(
+                    "💣 Cannot read the tag value history, follower=[{}]",
+                    &self.follower
+                ))
+            else {
+                return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+            };
+
+            let properties = item.properties.get_or_insert_with(Vec::new);
+            if let Err(e) = Self::rewind_properties(properties, item_id, &changes) {
+                log_error!(
+                    "💣 Cannot rebuild the tag values, item_id=[{}], error=[{}], follower=[{}]",
+                    item_id,
+                    e,
+                    &self.follower
+                );
+                return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+            }
+
+            log_info!(
+                "😎 We rebuilt the tag values, item_id=[{}], undone changes=[{}], follower=[{}]",
+                item_id,
+                changes.len(),
+                &self.follower
+            );
+        }
+
         if trans
             .commit()
             .await
@@ -573,21 +1513,263 @@ impl ItemDelegate {
         WebType::from_item(StatusCode::OK.as_u16(), GetItemReply { items })
     }
 
+    ///
+    /// 🌟 Get the changes of the tag values of an item, oldest first
+    ///
+    pub async fn get_item_history(mut self, item_id: i64) -> WebType<GetItemHistoryReply> {
+        log_info!(
+            "🚀 Start get_item_history api, item_id=[{}], follower=[{}]",
+            item_id,
+            &self.follower
+        );
+
+        let entry_session = try_or_return!(
+            valid_sid_get_session(&self.session_token, &mut self.follower).await,
+            Self::web_type_error()
+        );
+
+        let customer_code = entry_session.customer_code.as_str();
+
+        // Open Db connection
+        let Ok(mut cnx) = SQLConnectionAsync::from_pool().await.map_err(err_fwd!(
+            "💣 New Db connection failed, follower=[{}]",
+            &self.follower
+        )) else {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        };
+
+        let Ok(mut trans) = cnx.begin().await.map_err(err_fwd!(
+            "💣 Transaction issue, follower=[{}]",
+            &self.follower
+        )) else {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        };
+
+        let Ok(items) = self
+            .search_item_by_id(&mut trans, Some(item_id), None, None, customer_code)
+            .await
+            .map_err(err_fwd!(
+                "💣 Cannot search item by id, follower=[{}]",
+                &self.follower
+            ))
+        else {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        };
+
+        if items.is_empty() {
+            log_error!(
+                "💣 Missing item=[{:?}], follower=[{}]",
+                item_id,
+                &self.follower
+            );
+            return WebType::from_errorset(&MISSING_ITEM);
+        }
+
+        let Ok(history) = self
+            .search_tag_history(&mut trans, item_id, None, customer_code)
+            .await
+            .map_err(err_fwd!(
+                "💣 Cannot read the tag value history, follower=[{}]",
+                &self.follower
+            ))
+        else {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        };
+
+        log_info!(
+            "😎 We read the tag value history, item_id=[{}], change count=[{}], follower=[{}]",
+            item_id,
+            history.len(),
+            &self.follower
+        );
+
+        if trans
+            .commit()
+            .await
+            .map_err(err_fwd!("💣 Commit failed, follower=[{}]", &self.follower))
+            .is_err()
+        {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        }
+
+        log_info!("🏁 End get_item_history, follower=[{}]", &self.follower);
+        WebType::from_item(
+            StatusCode::OK.as_u16(),
+            GetItemHistoryReply { item_id, history },
+        )
+    }
+
+    /// Changes of the tag values of the item, oldest first, only the ones after [o_after] if provided
+    async fn search_tag_history(
+        &self,
+        trans: &mut SQLTransactionAsync<'_>,
+        item_id: i64,
+        o_after: Option<SystemTime>,
+        customer_code: &str,
+    ) -> anyhow::Result<Vec<TagHistoryElement>> {
+        let after_condition = match o_after {
+            Some(_) => "AND h.changed_gmt > :p_after",
+            None => "",
+        };
+
+        let sql_query = format!(
+            r"SELECT h.id, h.tag_value_id, h.tag_id, td.name, td.type, h.operation,
+                    h.old_value, h.new_value, h.user_id, h.changed_gmt
+                FROM cs_{0}.tag_value_history h
+                INNER JOIN cs_{0}.tag_definition td ON td.id = h.tag_id
+                WHERE h.item_id = :p_item_id {1}
+                ORDER BY h.id",
+            customer_code, after_condition
+        );
+
+        let mut params = HashMap::new();
+        params.insert("p_item_id".to_owned(), CellValue::from_raw_int(item_id));
+        if let Some(after) = o_after {
+            params.insert("p_after".to_owned(), CellValue::from_raw_systemtime(after));
+        }
+
+        let query = SQLQueryBlockAsync {
+            sql_query,
+            start: 0,
+            length: None,
+            params,
+        };
+
+        let mut sql_result = query
+            .execute(trans)
+            .await
+            .map_err(err_fwd!("Query failed, [{}]", &query.sql_query))?;
+
+        let mut history = vec![];
+        while sql_result.next() {
+            let changed = sql_result
+                .get_timestamp_as_datetime("changed_gmt")
+                .ok_or(anyhow!("Wrong changed_gmt"))?;
+            history.push(TagHistoryElement {
+                tag_value_id: sql_result
+                    .get_int("tag_value_id")
+                    .ok_or(anyhow!("Wrong tag_value_id"))?,
+                tag_id: sql_result
+                    .get_int("tag_id")
+                    .ok_or(anyhow!("Wrong tag_id"))?,
+                tag_name: sql_result.get_string("name").ok_or(anyhow!("Wrong name"))?,
+                tag_type: sql_result.get_string("type").ok_or(anyhow!("Wrong type"))?,
+                operation: sql_result
+                    .get_string("operation")
+                    .ok_or(anyhow!("Wrong operation"))?,
+                old_value: sql_result.get_string("old_value"),
+                new_value: sql_result.get_string("new_value"),
+                user_id: sql_result
+                    .get_int("user_id")
+                    .ok_or(anyhow!("Wrong user_id"))?,
+                changed: date_time_to_iso(&changed),
+            });
+        }
+
+        Ok(history)
+    }
+
+    ///
+    /// Undo the changes, newest first, to get the tag values the item held before them
+    /// The changes made before the history existed cannot be undone
+    ///
+    fn rewind_properties(
+        properties: &mut Vec<TagValueElement>,
+        item_id: i64,
+        changes: &[TagHistoryElement],
+    ) -> Result<(), String> {
+        for change in changes.iter().rev() {
+            match change.operation.as_str() {
+                "insert" => properties.retain(|p| p.tag_value_id != change.tag_value_id),
+                "update" | "delete" => {
+                    let value = match &change.old_value {
+                        Some(old_value) => EnumTagValue::from_string(old_value, &change.tag_type)?,
+                        None => Self::empty_tag_value(&change.tag_type)?,
+                    };
+                    match properties
+                        .iter_mut()
+                        .find(|p| p.tag_value_id == change.tag_value_id)
+                    {
+                        Some(property) => property.value = value,
+                        None => properties.push(TagValueElement {
+                            tag_value_id: change.tag_value_id,
+                            item_id,
+                            tag_id: change.tag_id,
+                            tag_name: change.tag_name.clone(),
+                            value,
+                        }),
+                    }
+                }
+                operation => return Err(format!("Unknown operation: {}", operation)),
+            }
+        }
+
+        // Same order as the tag values read from the database
+        properties.sort_by(|a, b| {
+            a.tag_name
+                .cmp(&b.tag_name)
+                .then(a.tag_value_id.cmp(&b.tag_value_id))
+        });
+        Ok(())
+    }
+
+    /// Value without content for the tag type
+    fn empty_tag_value(tag_type: &str) -> Result<EnumTagValue, String> {
+        let tt = TagType::from_str(tag_type.to_lowercase().as_str())
+            .map_err(|_| format!("Bad type: {}", tag_type))?;
+        Ok(match tt {
+            TagType::Text => EnumTagValue::Text(None),
+            TagType::Bool => EnumTagValue::Boolean(None),
+            TagType::Int => EnumTagValue::Integer(None),
+            TagType::Double => EnumTagValue::Double(None),
+            TagType::Date => EnumTagValue::SimpleDate(None),
+            TagType::DateTime => EnumTagValue::DateTime(None),
+            TagType::Link => EnumTagValue::Link(None),
+            TagType::Enum => EnumTagValue::Enumeration(None),
+        })
+    }
+
     ///
     /// 🌟 Delegate for delete_item_tag
     ///
     pub async fn delete_item_tag(
         mut self,
         item_id: i64,
-        tag_names: Vec<String>,
+        if_match: Option<&str>,
+        tag_names: Option<&str>,
+        value_ids: Option<&str>,
     ) -> WebType<SimpleMessage> {
         log_info!(
-            "🚀 Start delete_item_tag api, item_id=[{}], tag_names=[{:?}], follower=[{}]",
+            "🚀 Start delete_item_tag api, item_id=[{}], tag_names=[{:?}], value_ids=[{:?}], follower=[{}]",
             item_id,
-            &tag_names,
+            tag_names,
+            value_ids,
             &self.follower
         );
 
+        let tag_names: Vec<String> = tag_names
+            .unwrap_or_default()
+            .split(',')
+            .map(|name| name.trim().to_string())
+            .filter(|name| !name.is_empty())
+            .collect();
+
+        let Ok(value_ids) = value_ids
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|id| !id.is_empty())
+            .map(|id| id.parse::<i64>())
+            .collect::<Result<Vec<i64>, _>>()
+            .map_err(err_fwd!(
+                "💣 Wrong tag value id, value_ids=[{:?}], follower=[{}]",
+                value_ids,
+                &self.follower
+            ))
+        else {
+            return WebType::from_errorset(&INVALID_REQUEST);
+        };
+
         let entry_session = try_or_return!(
             valid_sid_get_session(&self.session_token, &mut self.follower).await,
             Self::web_type_error()
@@ -628,24 +1810,61 @@ impl ItemDelegate {
         //     return WebType::from_errorset(&INVALID_REQUEST);
         // };
 
-        //let item_id = add_item_tag_request.item_id;
-        for tag_name in tag_names {
-            if let Err(e) = self
-                .delete_item_tag_value(&mut trans, item_id, &tag_name, customer_code)
-                .await
-            {
-                log_error!(
-                    "💣 Delete item tag value error, error=[{:?}], follower=[{}]",
-                    e,
-                    &self.follower
-                );
-                return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
-            };
-            log_info!(
-                "😎 We deleted the tag, tag_name=[{}], follower=[{}]",
-                &tag_name,
-                &self.follower
-            );
+        let if_match = try_or_return!(parse_if_match(if_match), Self::web_type_error());
+        if let Err(e) = self
+            .item_lock_delegate()
+            .prepare_write(
+                &mut trans,
+                item_id,
+                entry_session.id,
+                if_match,
+                customer_code,
+            )
+            .await
+        {
+            return WebType::from_errorset(e);
+        }
+
+        let required_before = try_or_return!(
+            self.find_required_tags_on_item(&mut trans, item_id, customer_code)
+                .await,
+            Self::web_type_error()
+        );
+
+        if let Err(e) = self
+            .delete_tags_on_item(
+                &mut trans,
+                item_id,
+                &tag_names,
+                entry_session.user_id,
+                customer_code,
+            )
+            .await
+        {
+            return WebType::from_errorset(e);
+        }
+
+        if let Err(e) = self
+            .delete_tag_values_on_item(
+                &mut trans,
+                item_id,
+                &value_ids,
+                entry_session.user_id,
+                customer_code,
+            )
+            .await
+        {
+            return WebType::from_errorset(e);
+        }
+
+        // A required tag cannot lose its last value, the deletions are rolled back
+        let violations = try_or_return!(
+            self.check_required_values_left(&mut trans, item_id, &required_before, customer_code)
+                .await,
+            Self::web_type_error()
+        );
+        if !violations.is_empty() {
+            return Self::constraint_violation_error(&violations);
         }
 
         if trans
@@ -728,6 +1947,7 @@ impl ItemDelegate {
     pub async fn update_item_tag(
         mut self,
         item_id: i64,
+        if_match: Option<&str>,
         add_item_tag_request: Json<AddItemTagRequest>,
     ) -> WebType<AddItemTagReply> {
         log_info!(
@@ -771,11 +1991,43 @@ impl ItemDelegate {
             return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
         };
 
+        // The item must not be checked out by another session, nor changed since the If-Match version
+        let if_match = try_or_return!(parse_if_match(if_match), Self::web_type_error());
+        if let Err(e) = self
+            .item_lock_delegate()
+            .prepare_write(
+                &mut trans,
+                item_id,
+                entry_session.id,
+                if_match,
+                customer_code,
+            )
+            .await
+        {
+            return WebType::from_errorset(e);
+        }
+
+        // Check the values against the tag constraints
+        let violations = try_or_return!(
+            self.check_tag_constraints(
+                &mut trans,
+                &add_item_tag_request.properties,
+                false,
+                customer_code
+            )
+            .await,
+            Self::web_type_error()
+        );
+        if !violations.is_empty() {
+            return Self::constraint_violation_error(&violations);
+        }
+
         // Add the tags
         let r_add_tags = self
             .update_tags_on_item(
                 &mut trans,
                 item_id,
+                entry_session.user_id,
                 customer_code,
                 &add_item_tag_request.properties,
             )
@@ -844,6 +2096,31 @@ impl ItemDelegate {
             return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
         };
 
+        // Complete the properties with the default values of the document type
+        let mut properties = add_item_request.properties.clone().unwrap_or_default();
+        let mut violations = vec![];
+        let mut document_type_id = None;
+        if let Some(document_type_name) = &add_item_request.document_type {
+            let document_type = try_or_return!(
+                self.find_document_type(&mut trans, document_type_name, customer_code)
+                    .await,
+                Self::web_type_error()
+            );
+            violations = Self::apply_document_type(&document_type, &mut properties);
+            document_type_id = Some(document_type.document_type_id);
+        }
+
+        // Check the values against the tag constraints, the required tags must all be provided
+        let constraint_violations = try_or_return!(
+            self.check_tag_constraints(&mut trans, &properties, true, customer_code)
+                .await,
+            Self::web_type_error()
+        );
+        violations.extend(constraint_violations);
+        if !violations.is_empty() {
+            return Self::constraint_violation_error(&violations);
+        }
+
         let o_file_ref = add_item_request.file_ref.clone();
         let Ok(item_id) = self
             .create_item(
@@ -851,6 +2128,7 @@ impl ItemDelegate {
                 &add_item_request.name,
                 customer_code,
                 o_file_ref,
+                document_type_id,
             )
             .await
             .map_err(err_fwd!(
@@ -867,10 +2145,42 @@ impl ItemDelegate {
             &self.follower
         );
 
+        // | The file of the item is its first version
+        if let Some(file_ref) = add_item_request.file_ref.as_deref() {
+            let item_version_delegate = ItemVersionDelegate::new(
+                self.session_token.clone(),
+                self.follower.x_request_id.clone(),
+            );
+            if item_version_delegate
+                .insert_version(
+                    &mut trans,
+                    item_id,
+                    &NewVersion {
+                        version_no: 1,
+                        file_ref,
+                        comment: None,
+                        user_id: Some(entry_session.user_id),
+                        created: SystemTime::now(),
+                    },
+                    customer_code,
+                )
+                .await
+                .is_err()
+            {
+                return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+            }
+        }
+
         // | Insert all the properties
-        if let Some(properties) = &add_item_request.properties {
+        if !properties.is_empty() {
             if let Err(e) = self
-                .update_tags_on_item(&mut trans, item_id, customer_code, properties)
+                .update_tags_on_item(
+                    &mut trans,
+                    item_id,
+                    entry_session.user_id,
+                    customer_code,
+                    &properties,
+                )
                 .await
             {
                 return WebType::from_errorset(e);
@@ -883,6 +2193,42 @@ impl ItemDelegate {
             &self.follower
         );
 
+        // | Add the tags of the classification rules matched by the file, the given tags win
+        if let Some(file_ref) = &add_item_request.file_ref {
+            let Ok(classification_tags) = self
+                .classification_delegate()
+                .search_file_classification(&mut trans, file_ref, customer_code)
+                .await
+                .map_err(err_fwd!(
+                    "💣 Cannot read the classification of the file, file_ref=[{}], follower=[{}]",
+                    file_ref,
+                    &self.follower
+                ))
+            else {
+                return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+            };
+
+            let classification_tags: Vec<AddTagValue> = classification_tags
+                .into_iter()
+                .filter(|tag| !properties.iter().any(|p| p.tag_name == tag.tag_name))
+                .collect();
+
+            if !classification_tags.is_empty() {
+                if let Err(e) = self
+                    .update_tags_on_item(
+                        &mut trans,
+                        item_id,
+                        entry_session.user_id,
+                        customer_code,
+                        &classification_tags,
+                    )
+                    .await
+                {
+                    return WebType::from_errorset(e);
+                }
+            }
+        }
+
         if trans
             .commit()
             .await
@@ -909,11 +2255,109 @@ impl ItemDelegate {
         )
     }
 
+    /// Remove all the values of the tags from an item
+    async fn delete_tags_on_item(
+        &self,
+        trans: &mut SQLTransactionAsync<'_>,
+        item_id: i64,
+        tag_names: &[String],
+        user_id: i64,
+        customer_code: &str,
+    ) -> Result<(), &ErrorSet<'static>> {
+        for tag_name in tag_names {
+            if let Err(e) = self
+                .delete_item_tag_value(trans, item_id, tag_name, user_id, customer_code)
+                .await
+            {
+                log_error!(
+                    "💣 Delete item tag value error, error=[{:?}], follower=[{}]",
+                    e,
+                    &self.follower
+                );
+                return Err(&INTERNAL_DATABASE_ERROR);
+            };
+            log_info!(
+                "😎 We deleted the tag, tag_name=[{}], follower=[{}]",
+                tag_name,
+                &self.follower
+            );
+        }
+        Ok(())
+    }
+
+    /// Remove single tag values from an item, by their tag value id
+    async fn delete_tag_values_on_item(
+        &self,
+        trans: &mut SQLTransactionAsync<'_>,
+        item_id: i64,
+        value_ids: &[i64],
+        user_id: i64,
+        customer_code: &str,
+    ) -> Result<(), &ErrorSet<'static>> {
+        for value_id in value_ids {
+            if let Err(e) = self
+                .delete_item_tag_value_by_id(trans, item_id, *value_id, user_id, customer_code)
+                .await
+            {
+                log_error!(
+                    "💣 Delete item tag value error, error=[{:?}], follower=[{}]",
+                    e,
+                    &self.follower
+                );
+                return Err(&INTERNAL_DATABASE_ERROR);
+            };
+            log_info!(
+                "😎 We deleted the tag value, value_id=[{}], follower=[{}]",
+                value_id,
+                &self.follower
+            );
+        }
+        Ok(())
+    }
+
+    /// Remove single tag values from an item, by their text form
+    async fn delete_tag_text_values_on_item(
+        &self,
+        trans: &mut SQLTransactionAsync<'_>,
+        item_id: i64,
+        deleted_values: &[DeletedTagValue],
+        user_id: i64,
+        customer_code: &str,
+    ) -> Result<(), &ErrorSet<'static>> {
+        for deleted_value in deleted_values {
+            if let Err(e) = self
+                .delete_item_tag_single_value(
+                    trans,
+                    item_id,
+                    &deleted_value.tag_name,
+                    &deleted_value.value,
+                    user_id,
+                    customer_code,
+                )
+                .await
+            {
+                log_error!(
+                    "💣 Delete item tag value error, error=[{:?}], follower=[{}]",
+                    e,
+                    &self.follower
+                );
+                return Err(&INTERNAL_DATABASE_ERROR);
+            };
+            log_info!(
+                "😎 We deleted the tag value, tag_name=[{}], follower=[{}]",
+                &deleted_value.tag_name,
+                &self.follower
+            );
+        }
+        Ok(())
+    }
+
     /// Add tags on an item
-    async fn update_tags_on_item(
+    pub(crate) async fn update_tags_on_item(
         &self,
         mut trans: &mut SQLTransactionAsync<'_>,
         item_id: i64,
+        user_id: i64,
         customer_code: &str,
         properties: &Vec<AddTagValue>,
     ) -> Result<(), &ErrorSet<'static>> {
@@ -965,6 +2409,60 @@ impl ItemDelegate {
                 }
             };
 
+            // The value of an enum tag must be one of its allowed values
+            if let EnumTagValue::Enumeration(Some(value)) = &tag.value {
+                self.check_enum_value(&mut trans, tag_id, value, customer_code)
+                    .await?;
+            }
+
+            let tag_definition = self
+                .find_tag_definition(&mut trans, tag_id, customer_code)
+                .await?;
+
+            // A multi-valued tag receives one more value, unless the item already holds it
+            if tag_definition.multi_valued {
+                if Self::enum_tag_value_to_tag_type(&tag) != tag_definition.tag_type {
+                    log_error!("💣 Trying to add a value with a different type : final type=[{:?}], original type=[{}], item id=[{}], tag_id=[{}], follower=[{}]"
+                        , tag.value, &tag_definition.tag_type, item_id, tag_id, &self.follower);
+                    return Err(&INCORRECT_TAG_TYPE);
+                }
+
+                let add_tag_value = AddTagValue {
+                    tag_id: Some(tag_id),
+                    tag_name: tag.tag_name.clone(),
+                    value: tag.value.clone(),
+                };
+
+                match self
+                    .is_value_on_item(&mut trans, item_id, &add_tag_value, customer_code)
+                    .await
+                {
+                    Ok(true) => {
+                        log_info!(
+                            "The item already holds the value, tag name=[{:?}], follower=[{}]",
+                            tag.value,
+                            &self.follower
+                        );
+                    }
+                    Ok(false) => {
+                        if self.create_item_property(&mut trans, &add_tag_value, item_id, user_id, customer_code).await
+                            .map_err(err_fwd!("💣 Insertion of a new tag value failed, tag value=[{:?}], follower=[{}]", tag, &self.follower)).is_err() {
+                            return Err(&INTERNAL_DATABASE_ERROR);
+                        }
+                        log_info!(
+                            "😎 We added the value to the multi-valued tag, tag name=[{:?}], follower=[{}]",
+                            tag.value,
+                            &self.follower
+                        );
+                    }
+                    Err(e) => {
+                        log_error!("💣 Error while reading the tag values, item id=[{}], tag_id=[{}], message=[{}], follower=[{}]", item_id, tag_id, e.to_string(), &self.follower);
+                        return Err(&INTERNAL_DATABASE_ERROR);
+                    }
+                }
+                continue;
+            }
+
             // Verify if the tag exists on the item
             match self
                 .is_tags_on_item(&mut trans, item_id, tag_id, customer_code)
@@ -980,7 +2478,7 @@ impl ItemDelegate {
                                 value: tag.value.clone(),
                             };
 
-                            if self.create_item_property(&mut trans, &add_tag_value, item_id, customer_code).await
+                            if self.create_item_property(&mut trans, &add_tag_value, item_id, user_id, customer_code).await
                                 .map_err(err_fwd!("💣 Insertion of a new tag value failed, tag value=[{:?}], follower=[{}]", tag, &self.follower)).is_err() {
                                 return Err(&INTERNAL_DATABASE_ERROR);
                             }
@@ -998,36 +2496,368 @@ impl ItemDelegate {
                                 &self.follower
                             );
 
-                            if Self::enum_tag_value_to_tag_type(&tag) != tag_type {
-                                log_error!("💣 Trying to change the tag value to different type : final type=[{:?}], original type=[{}], item id=[{}], tag_id=[{}], follower=[{}]"
-                                    , tag.value, &tag_type, item_id, tag_id, &self.follower);
-                                return Err(&INCORRECT_TAG_TYPE);
-                            }
+                            if Self::enum_tag_value_to_tag_type(&tag) != tag_type {
+                                log_error!("💣 Trying to change the tag value to different type : final type=[{:?}], original type=[{}], item id=[{}], tag_id=[{}], follower=[{}]"
+                                    , tag.value, &tag_type, item_id, tag_id, &self.follower);
+                                return Err(&INCORRECT_TAG_TYPE);
+                            }
+
+                            let add_tag_value = AddTagValue {
+                                tag_id: Some(tag_id),
+                                tag_name: tag.tag_name.clone(),
+                                value: tag.value.clone(),
+                            };
+
+                            if self.change_item_tag_value(&mut trans, &add_tag_value, tag_value_id, user_id, customer_code).await
+                                .map_err(err_fwd!("💣 Change of tag value failed, tag value=[{:?}], follower=[{}]", tag, &self.follower)).is_err() {
+                                return Err(&INTERNAL_DATABASE_ERROR);
+                            }
+                            log_info!("😎 We changed the tag value of the item, tag name=[{:?}], follower=[{}]", tag.value, &self.follower);
+                        }
+                        (_, _) => {
+                            log_error!("💣 Error while checking the existing the tag value, item id=[{}], tag_id=[{}], follower=[{}]", item_id, tag_id, &self.follower);
+                            return Err(&INTERNAL_DATABASE_ERROR);
+                        }
+                    }
+                }
+                Err(e) => {
+                    log_error!("💣 Error while reading the tag value, item id=[{}], tag_id=[{}], message=[{}], follower=[{}]", item_id, tag_id, e.to_string(), &self.follower);
+                    return Err(&INTERNAL_DATABASE_ERROR);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Find the document type by its name
+    async fn find_document_type(
+        &self,
+        trans: &mut SQLTransactionAsync<'_>,
+        document_type_name: &str,
+        customer_code: &str,
+    ) -> Result<DocumentTypeElement, &ErrorSet<'static>> {
+        let session_token = self.session_token.clone();
+        let x_request_id = self.follower.x_request_id.clone();
+        let document_type_delegate = DocumentTypeDelegate::new(session_token, x_request_id);
+
+        let Ok(o_document_type) = document_type_delegate
+            .search_document_type_by_name(trans, document_type_name, customer_code)
+            .await
+            .map_err(err_fwd!(
+                "💣 Cannot read the document type, name=[{}], follower=[{}]",
+                document_type_name,
+                &self.follower
+            ))
+        else {
+            return Err(&INTERNAL_DATABASE_ERROR);
+        };
+
+        let Some(document_type) = o_document_type else {
+            log_error!(
+                "💣 Unknown document type, name=[{}], follower=[{}]",
+                document_type_name,
+                &self.follower
+            );
+            return Err(&MISSING_DOCUMENT_TYPE);
+        };
+
+        Ok(document_type)
+    }
+
+    ///
+    /// Add the default value of the tags of the document type missing from the properties
+    /// Return the violations for the required tags without value nor default
+    ///
+    fn apply_document_type(
+        document_type: &DocumentTypeElement,
+        properties: &mut Vec<AddTagValue>,
+    ) -> Vec<String> {
+        let mut violations = vec![];
+        for type_tag in &document_type.tags {
+            let provided = properties.iter().any(|p| {
+                p.tag_id == Some(type_tag.tag_id)
+                    || p.tag_name.as_deref() == Some(type_tag.tag_name.as_str())
+            });
+            if provided {
+                continue;
+            }
+
+            match &type_tag.default_value {
+                Some(default_value) => {
+                    match EnumTagValue::from_string(default_value, &type_tag.tag_type) {
+                        Ok(value) => properties.push(AddTagValue {
+                            tag_id: Some(type_tag.tag_id),
+                            tag_name: None,
+                            value,
+                        }),
+                        Err(e) => violations.push(format!("{}: {}", &type_tag.tag_name, e)),
+                    }
+                }
+                None => {
+                    if type_tag.required {
+                        violations.push(format!(
+                            "{}: value is required by the document type [{}]",
+                            &type_tag.tag_name, &document_type.name
+                        ));
+                    }
+                }
+            }
+        }
+        violations
+    }
+
+    ///
+    /// Check the tag values against the constraints of their tag definition
+    /// Return the violations, one per field, as "<tag name>: <reason>"
+    /// The tags not defined yet have no constraint, they will be created on the fly
+    ///
+    async fn check_tag_constraints(
+        &self,
+        trans: &mut SQLTransactionAsync<'_>,
+        properties: &[AddTagValue],
+        check_required: bool,
+        customer_code: &str,
+    ) -> Result<Vec<String>, &ErrorSet<'static>> {
+        let session_token = self.session_token.clone();
+        let x_request_id = self.follower.x_request_id.clone();
+        let tag_delegate = TagDelegate::new(session_token, x_request_id);
+
+        let mut violations = vec![];
+        for prop in properties {
+            let o_tag_definition = match (prop.tag_id, &prop.tag_name) {
+                (Some(tag_id), None) => Some(
+                    self.find_tag_definition(trans, tag_id, customer_code)
+                        .await?,
+                ),
+                (_, Some(tag_name)) => {
+                    let Ok(o_tag) = tag_delegate
+                        .find_tag_by_name(trans, tag_name, customer_code)
+                        .await
+                        .map_err(err_fwd!(
+                            "💣 Cannot read the tag definition, tag_name=[{}], follower=[{}]",
+                            tag_name,
+                            &self.follower
+                        ))
+                    else {
+                        return Err(&INTERNAL_DATABASE_ERROR);
+                    };
+                    o_tag
+                }
+                (None, None) => None,
+            };
+
+            let Some(tag_definition) = o_tag_definition else {
+                continue;
+            };
+
+            let Ok(tag_type) = TagType::from_str(tag_definition.tag_type.to_lowercase().as_str())
+            else {
+                log_error!(
+                    "💣 Unknown tag type, tag_type=[{}], follower=[{}]",
+                    &tag_definition.tag_type,
+                    &self.follower
+                );
+                return Err(&INTERNAL_DATABASE_ERROR);
+            };
+
+            if let Err(reason) = TagDelegate::check_value_constraints(
+                &tag_type,
+                &tag_definition.constraints,
+                &prop.value,
+            ) {
+                violations.push(format!("{}: {}", &tag_definition.name, reason));
+            }
+        }
+
+        if check_required {
+            let Ok(tags) = tag_delegate
+                .search_tag_by_id(trans, None, None, None, customer_code)
+                .await
+                .map_err(err_fwd!(
+                    "💣 Cannot read the tag definitions, follower=[{}]",
+                    &self.follower
+                ))
+            else {
+                return Err(&INTERNAL_DATABASE_ERROR);
+            };
+
+            for tag in tags
+                .iter()
+                .filter(|t| t.constraints.required.unwrap_or(false))
+            {
+                let provided = properties.iter().any(|p| {
+                    p.tag_id == Some(tag.tag_id) || p.tag_name.as_deref() == Some(tag.name.as_str())
+                });
+                if !provided {
+                    violations.push(format!("{}: value is required", &tag.name));
+                }
+            }
+        }
+
+        if !violations.is_empty() {
+            log_error!(
+                "💣 Tag constraint violation, violations=[{:?}], follower=[{}]",
+                &violations,
+                &self.follower
+            );
+        }
+
+        Ok(violations)
+    }
+
+    /// Names of the required tags holding at least one value on the item
+    async fn find_required_tags_on_item(
+        &self,
+        trans: &mut SQLTransactionAsync<'_>,
+        item_id: i64,
+        customer_code: &str,
+    ) -> Result<Vec<String>, &ErrorSet<'static>> {
+        let sql_query = format!(
+            r"SELECT DISTINCT td.name FROM cs_{0}.tag_value tv
+                    INNER JOIN cs_{0}.tag_definition td ON td.id = tv.tag_id
+                    WHERE tv.item_id = :p_item_id
+                    AND td.required
+                    ORDER BY td.name ",
+            customer_code
+        );
+
+        let mut params = HashMap::new();
+        params.insert("p_item_id".to_string(), CellValue::from_raw_int(item_id));
+
+        let query = SQLQueryBlockAsync {
+            sql_query,
+            start: 0,
+            length: None,
+            params,
+        };
+
+        let Ok(mut sql_result) = query.execute(trans).await.map_err(err_fwd!(
+            "💣 Query failed, [{}], follower=[{}]",
+            &query.sql_query,
+            &self.follower
+        )) else {
+            return Err(&INTERNAL_DATABASE_ERROR);
+        };
+
+        let mut tag_names = vec![];
+        while sql_result.next() {
+            let Some(tag_name) = sql_result.get_string("name") else {
+                log_error!("💣 Wrong tag name, follower=[{}]", &self.follower);
+                return Err(&INTERNAL_DATABASE_ERROR);
+            };
+            tag_names.push(tag_name);
+        }
+        Ok(tag_names)
+    }
+
+    ///
+    /// A required tag that held a value before the deletions must still hold one
+    /// It covers the removal of a whole tag as well as the removal of its last value
+    ///
+    async fn check_required_values_left(
+        &self,
+        trans: &mut SQLTransactionAsync<'_>,
+        item_id: i64,
+        required_before: &[String],
+        customer_code: &str,
+    ) -> Result<Vec<String>, &ErrorSet<'static>> {
+        let required_after = self
+            .find_required_tags_on_item(trans, item_id, customer_code)
+            .await?;
+
+        Ok(required_before
+            .iter()
+            .filter(|tag_name| !required_after.contains(tag_name))
+            .map(|tag_name| format!("{}: value is required", tag_name))
+            .collect())
+    }
+
+    /// Build the error reply with the detail of each violation
+    fn constraint_violation_error<T>(violations: &[String]) -> WebType<T>
+    where
+        T: DeserializeOwned,
+    {
+        WebType::from_simple(
+            TAG_CONSTRAINT_VIOLATION.http_error_code,
+            SimpleMessage {
+                message: format!(
+                    "{}: [{}]",
+                    TAG_CONSTRAINT_VIOLATION.err_message,
+                    violations.join("], [")
+                ),
+            },
+        )
+    }
+
+    /// Read the definition of the tag
+    async fn find_tag_definition(
+        &self,
+        trans: &mut SQLTransactionAsync<'_>,
+        tag_id: i64,
+        customer_code: &str,
+    ) -> Result<TagElement, &ErrorSet<'static>> {
+        let session_token = self.session_token.clone();
+        let x_request_id = self.follower.x_request_id.clone();
+        let tag_delegate = TagDelegate::new(session_token, x_request_id);
+
+        let Ok(mut tags) = tag_delegate
+            .search_tag_by_id(trans, Some(tag_id), None, None, customer_code)
+            .await
+            .map_err(err_fwd!(
+                "💣 Cannot read the tag definition, tag_id=[{}], follower=[{}]",
+                tag_id,
+                &self.follower
+            ))
+        else {
+            return Err(&INTERNAL_DATABASE_ERROR);
+        };
+
+        if tags.is_empty() {
+            log_error!(
+                "💣 Missing tag definition, tag_id=[{}], follower=[{}]",
+                tag_id,
+                &self.follower
+            );
+            return Err(&MISSING_TAG_FOR_ITEM);
+        }
 
-                            let add_tag_value = AddTagValue {
-                                tag_id: Some(tag_id),
-                                tag_name: tag.tag_name.clone(),
-                                value: tag.value.clone(),
-                            };
+        Ok(tags.remove(0))
+    }
 
-                            if self.change_item_tag_value(&mut trans, &add_tag_value, tag_value_id, customer_code).await
-                                .map_err(err_fwd!("💣 Change of tag value failed, tag value=[{:?}], follower=[{}]", tag, &self.follower)).is_err() {
-                                return Err(&INTERNAL_DATABASE_ERROR);
-                            }
-                            log_info!("😎 We changed the tag value of the item, tag name=[{:?}], follower=[{}]", tag.value, &self.follower);
-                        }
-                        (_, _) => {
-                            log_error!("💣 Error while checking the existing the tag value, item id=[{}], tag_id=[{}], follower=[{}]", item_id, tag_id, &self.follower);
-                            return Err(&INTERNAL_DATABASE_ERROR);
-                        }
-                    }
-                }
-                Err(e) => {
-                    log_error!("💣 Error while reading the tag value, item id=[{}], tag_id=[{}], message=[{}], follower=[{}]", item_id, tag_id, e.to_string(), &self.follower);
-                    return Err(&INTERNAL_DATABASE_ERROR);
-                }
-            }
+    /// Ensure the value is in the allowed values of the enum tag
+    async fn check_enum_value(
+        &self,
+        trans: &mut SQLTransactionAsync<'_>,
+        tag_id: i64,
+        value: &str,
+        customer_code: &str,
+    ) -> Result<(), &ErrorSet<'static>> {
+        let session_token = self.session_token.clone();
+        let x_request_id = self.follower.x_request_id.clone();
+        let tag_delegate = TagDelegate::new(session_token, x_request_id);
+
+        let Ok(allowed_values) = tag_delegate
+            .search_enum_values(trans, tag_id, customer_code)
+            .await
+            .map_err(err_fwd!(
+                "💣 Cannot read the allowed values, tag_id=[{}], follower=[{}]",
+                tag_id,
+                &self.follower
+            ))
+        else {
+            return Err(&INTERNAL_DATABASE_ERROR);
+        };
+
+        if !allowed_values.iter().any(|v| v == value) {
+            log_error!(
+                "💣 Value not allowed for the enum tag, tag_id=[{}], value=[{}], follower=[{}]",
+                tag_id,
+                value,
+                &self.follower
+            );
+            return Err(&ENUM_VALUE_NOT_ALLOWED);
         }
+
         Ok(())
     }
 
@@ -1037,8 +2867,13 @@ impl ItemDelegate {
         mut trans: &mut SQLTransactionAsync<'_>,
         tag: &AddTagValue,
         tag_value_id: i64,
+        user_id: i64,
         customer_code: &str,
     ) -> anyhow::Result<()> {
+        let old_value = self
+            .find_tag_value_text(trans, tag_value_id, customer_code)
+            .await?;
+
         let sql_update = format!(
             r"UPDATE cs_{0}.tag_value
                                             SET
@@ -1047,7 +2882,8 @@ impl ItemDelegate {
                                                 value_integer = :p_value_integer,
                                                 value_double = :p_value_double,
                                                 value_date = :p_val_date,
-                                                value_datetime = :p_value_datetime
+                                                value_datetime = :p_value_datetime,
+                                                modified_gmt = :p_modified
                                             WHERE id = :p_tag_value_id
                                                  ",
             customer_code
@@ -1059,6 +2895,10 @@ impl ItemDelegate {
             "p_tag_value_id".to_string(),
             CellValue::from_raw_int(tag_value_id),
         );
+        params.insert(
+            "p_modified".to_string(),
+            CellValue::from_raw_systemtime(SystemTime::now()),
+        );
         params = self.build_params_for_insert_and_update(&tag, params);
         let query = SQLChangeAsync {
             sql_query: sql_update.to_string(),
@@ -1070,6 +2910,22 @@ impl ItemDelegate {
             &query.sql_query,
             &self.follower
         ))?;
+
+        let mut history_params = HashMap::new();
+        history_params.insert(
+            "p_tag_value_id".to_string(),
+            CellValue::from_raw_int(tag_value_id),
+        );
+        history_params.insert("p_old_value".to_string(), CellValue::String(old_value));
+        self.record_tag_history(
+            trans,
+            TagChange::Update,
+            "tv.id = :p_tag_value_id",
+            history_params,
+            user_id,
+            customer_code,
+        )
+        .await?;
         Ok(())
     }
 
@@ -1079,20 +2935,141 @@ impl ItemDelegate {
         mut trans: &mut SQLTransactionAsync<'_>,
         item_id: i64,
         tag_name: &str,
+        user_id: i64,
         customer_code: &str,
     ) -> anyhow::Result<()> {
+        let condition = format!(
+            r"tv.tag_id = (SELECT td.id FROM cs_{0}.tag_definition td WHERE td.name = :p_tag_name)
+                                            AND tv.item_id = :p_item_id",
+            customer_code
+        );
+
+        let mut params = HashMap::new();
+
+        params.insert("p_item_id".to_string(), CellValue::from_raw_int(item_id));
+        params.insert("p_tag_name".to_string(), CellValue::from_raw_str(tag_name));
+
+        self.record_tag_history(
+            trans,
+            TagChange::Delete,
+            &condition,
+            params.clone(),
+            user_id,
+            customer_code,
+        )
+        .await?;
+
         let sql_delete = format!(
-            r"DELETE FROM cs_{0}.tag_value
-                                            WHERE tag_id = (SELECT td.id FROM cs_{0}.tag_definition td WHERE td.name = :p_tag_name)
-                                            AND item_id = :p_item_id
+            r"DELETE FROM cs_{0}.tag_value tv
+                                            WHERE {1}
                                                  ",
-            customer_code
+            customer_code, condition
+        );
+
+        let query = SQLChangeAsync {
+            sql_query: sql_delete.to_string(),
+            params,
+            sequence_name: "".to_string(),
+        };
+
+        let _id = query.delete(&mut trans).await.map_err(err_fwd!(
+            "💣 Query failed, [{}], , follower=[{}]",
+            &query.sql_query,
+            &self.follower
+        ))?;
+        Ok(())
+    }
+
+    /// Delete one value of the tag for the given item, the value is compared in its text form
+    async fn delete_item_tag_single_value(
+        &self,
+        mut trans: &mut SQLTransactionAsync<'_>,
+        item_id: i64,
+        tag_name: &str,
+        value: &str,
+        user_id: i64,
+        customer_code: &str,
+    ) -> anyhow::Result<()> {
+        let condition = format!(
+            r"tv.tag_id = (SELECT td.id FROM cs_{0}.tag_definition td WHERE td.name = :p_tag_name)
+                                            AND tv.item_id = :p_item_id
+                                            AND {1} = :p_value",
+            customer_code, TAG_VALUE_AS_TEXT
         );
 
         let mut params = HashMap::new();
 
         params.insert("p_item_id".to_string(), CellValue::from_raw_int(item_id));
         params.insert("p_tag_name".to_string(), CellValue::from_raw_str(tag_name));
+        params.insert("p_value".to_string(), CellValue::from_raw_str(value));
+
+        self.record_tag_history(
+            trans,
+            TagChange::Delete,
+            &condition,
+            params.clone(),
+            user_id,
+            customer_code,
+        )
+        .await?;
+
+        let sql_delete = format!(
+            r"DELETE FROM cs_{0}.tag_value tv
+                                            WHERE {1}
+                                                 ",
+            customer_code, condition
+        );
+
+        let query = SQLChangeAsync {
+            sql_query: sql_delete.to_string(),
+            params,
+            sequence_name: "".to_string(),
+        };
+
+        let _id = query.delete(&mut trans).await.map_err(err_fwd!(
+            "💣 Query failed, [{}], , follower=[{}]",
+            &query.sql_query,
+            &self.follower
+        ))?;
+        Ok(())
+    }
+
+    /// Delete one value of a tag for the given item, from its tag value id
+    async fn delete_item_tag_value_by_id(
+        &self,
+        mut trans: &mut SQLTransactionAsync<'_>,
+        item_id: i64,
+        tag_value_id: i64,
+        user_id: i64,
+        customer_code: &str,
+    ) -> anyhow::Result<()> {
+        let condition = "tv.id = :p_tag_value_id AND tv.item_id = :p_item_id";
+
+        let mut params = HashMap::new();
+
+        params.insert("p_item_id".to_string(), CellValue::from_raw_int(item_id));
+        params.insert(
+            "p_tag_value_id".to_string(),
+            CellValue::from_raw_int(tag_value_id),
+        );
+
+        self.record_tag_history(
+            trans,
+            TagChange::Delete,
+            condition,
+            params.clone(),
+            user_id,
+            customer_code,
+        )
+        .await?;
+
+        let sql_delete = format!(
+            r"DELETE FROM cs_{0}.tag_value tv
+                                            WHERE {1}
+                                                 ",
+            customer_code, condition
+        );
+
         let query = SQLChangeAsync {
             sql_query: sql_delete.to_string(),
             params,
@@ -1107,6 +3084,53 @@ impl ItemDelegate {
         Ok(())
     }
 
+    /// find if the item already holds this exact value for the tag
+    async fn is_value_on_item(
+        &self,
+        trans: &mut SQLTransactionAsync<'_>,
+        item_id: i64,
+        tag: &AddTagValue,
+        customer_code: &str,
+    ) -> anyhow::Result<bool> {
+        let tag_id = tag.tag_id.ok_or(anyhow!(
+            "Tag id must be provided, follower=[{}]",
+            &self.follower
+        ))?;
+
+        let sql_query = format!(
+            r"SELECT 1 FROM cs_{}.tag_value tv
+                    WHERE tv.tag_id = :p_tag_id
+                    AND tv.item_id = :p_item_id
+                    AND tv.value_boolean IS NOT DISTINCT FROM :p_value_boolean
+                    AND tv.value_string IS NOT DISTINCT FROM :p_value_string
+                    AND tv.value_integer IS NOT DISTINCT FROM :p_value_integer
+                    AND tv.value_double IS NOT DISTINCT FROM :p_value_double
+                    AND tv.value_date IS NOT DISTINCT FROM :p_val_date
+                    AND tv.value_datetime IS NOT DISTINCT FROM :p_value_datetime",
+            customer_code
+        );
+
+        let mut params = HashMap::new();
+        params.insert("p_tag_id".to_string(), CellValue::from_raw_int(tag_id));
+        params.insert("p_item_id".to_string(), CellValue::from_raw_int(item_id));
+        params = self.build_params_for_insert_and_update(&tag, params);
+
+        let query = SQLQueryBlockAsync {
+            sql_query,
+            start: 0,
+            length: Some(1),
+            params,
+        };
+
+        let sql_result: SQLDataSet = query.execute(trans).await.map_err(err_fwd!(
+            "Query failed, [{}], , follower=[{}]",
+            &query.sql_query,
+            &self.follower
+        ))?;
+
+        Ok(sql_result.len() > 0)
+    }
+
     /// find if the tag is already assigned to the item
     async fn is_tags_on_item(
         &self,
@@ -1155,10 +3179,11 @@ impl ItemDelegate {
         item_name: &str,
         customer_code: &str,
         file_ref: Option<String>,
+        document_type_id: Option<i64>,
     ) -> anyhow::Result<i64> {
         let sql_query = format!(
-            r"INSERT INTO cs_{}.item(name, created_gmt, last_modified_gmt, file_ref)
-                                        VALUES (:p_name, :p_created, :p_last_modified, :p_file_ref)",
+            r"INSERT INTO cs_{}.item(name, created_gmt, last_modified_gmt, file_ref, document_type_id)
+                                        VALUES (:p_name, :p_created, :p_last_modified, :p_file_ref, :p_document_type_id)",
             customer_code
         );
 
@@ -1179,6 +3204,10 @@ impl ItemDelegate {
             CellValue::from_raw_systemtime(now.clone()),
         );
         params.insert("p_file_ref".to_string(), CellValue::String(file_ref));
+        params.insert(
+            "p_document_type_id".to_string(),
+            CellValue::Int(document_type_id),
+        );
 
         let sql_insert = SQLChangeAsync {
             sql_query,
@@ -1275,6 +3304,9 @@ impl ItemDelegate {
                     name: tag_name.clone(),
                     tag_type: Self::enum_tag_value_to_tag_type(&prop),
                     default_value: None,
+                    allowed_values: None,
+                    multi_valued: None,
+                    constraints: None,
                 };
 
                 if let Err(err) = tag_delegate.check_input_values(&add_tag_request) {
@@ -1308,6 +3340,7 @@ impl ItemDelegate {
             EnumTagValue::SimpleDate(_) => TagType::Date.as_str(),
             EnumTagValue::DateTime(_) => TagType::DateTime.as_str(),
             EnumTagValue::Link(_) => TagType::Link.as_str(),
+            EnumTagValue::Enumeration(_) => TagType::Enum.as_str(),
         }
         .to_string()
     }
@@ -1318,6 +3351,7 @@ impl ItemDelegate {
         trans: &mut SQLTransactionAsync<'_>,
         tag: &AddTagValue,
         item_id: i64,
+        user_id: i64,
         customer_code: &str,
     ) -> anyhow::Result<()> {
         let tag_id = tag.tag_id.ok_or(anyhow!(
@@ -1328,8 +3362,8 @@ impl ItemDelegate {
         // FIXME BUG: we named the variable :p_val_date because otherwise it conflict with :p_value_datetime
         //              the replacement expression should be ":variable:" to avoid this case
         let sql_query = format!(
-            r"INSERT INTO cs_{}.tag_value (tag_id, item_id, value_boolean, value_string, value_integer, value_double, value_date, value_datetime)
-                 VALUES (:p_tag_id, :p_item_id, :p_value_boolean, :p_value_string, :p_value_integer, :p_value_double, :p_val_date, :p_value_datetime) ",
+            r"INSERT INTO cs_{}.tag_value (tag_id, item_id, value_boolean, value_string, value_integer, value_double, value_date, value_datetime, modified_gmt)
+                 VALUES (:p_tag_id, :p_item_id, :p_value_boolean, :p_value_string, :p_value_integer, :p_value_double, :p_val_date, :p_value_datetime, :p_modified) ",
             customer_code
         );
 
@@ -1337,6 +3371,10 @@ impl ItemDelegate {
 
         params.insert("p_tag_id".to_string(), CellValue::from_raw_int(tag_id));
         params.insert("p_item_id".to_string(), CellValue::from_raw_int(item_id));
+        params.insert(
+            "p_modified".to_string(),
+            CellValue::from_raw_systemtime(SystemTime::now()),
+        );
 
         params = self.build_params_for_insert_and_update(&tag, params);
 
@@ -1352,10 +3390,110 @@ impl ItemDelegate {
             &self.follower
         );
 
-        let _ = sql_insert.insert(trans).await.map_err(err_fwd!(
+        let tag_value_id = sql_insert.insert(trans).await.map_err(err_fwd!(
             "Cannot insert the tag value, follower=[{}]",
             &self.follower
         ))?;
+
+        let mut history_params = HashMap::new();
+        history_params.insert(
+            "p_tag_value_id".to_string(),
+            CellValue::from_raw_int(tag_value_id),
+        );
+        self.record_tag_history(
+            trans,
+            TagChange::Insert,
+            "tv.id = :p_tag_value_id",
+            history_params,
+            user_id,
+            customer_code,
+        )
+        .await?;
+        Ok(())
+    }
+
+    /// Text form of the current value of the tag value
+    async fn find_tag_value_text(
+        &self,
+        trans: &mut SQLTransactionAsync<'_>,
+        tag_value_id: i64,
+        customer_code: &str,
+    ) -> anyhow::Result<Option<String>> {
+        let sql_query = format!(
+            r"SELECT {1} AS value_text FROM cs_{0}.tag_value tv
+                    WHERE tv.id = :p_tag_value_id",
+            customer_code, TAG_VALUE_AS_TEXT
+        );
+
+        let mut params = HashMap::new();
+        params.insert(
+            "p_tag_value_id".to_string(),
+            CellValue::from_raw_int(tag_value_id),
+        );
+
+        let query = SQLQueryBlockAsync {
+            sql_query,
+            start: 0,
+            length: Some(1),
+            params,
+        };
+
+        let mut sql_result: SQLDataSet = query.execute(trans).await.map_err(err_fwd!(
+            "Query failed, [{}], follower=[{}]",
+            &query.sql_query,
+            &self.follower
+        ))?;
+
+        let value_text = if sql_result.next() {
+            sql_result.get_string("value_text")
+        } else {
+            None
+        };
+        Ok(value_text)
+    }
+
+    ///
+    /// Keep a trace of the change of the tag values matching the condition
+    /// Must be called after an insert or an update, and before a delete
+    ///
+    async fn record_tag_history(
+        &self,
+        trans: &mut SQLTransactionAsync<'_>,
+        change: TagChange,
+        condition: &str,
+        mut params: HashMap<String, CellValue>,
+        user_id: i64,
+        customer_code: &str,
+    ) -> anyhow::Result<()> {
+        let (old_value, new_value) = change.value_expressions();
+        let sql_query = format!(
+            r"INSERT INTO cs_{0}.tag_value_history (tag_value_id, item_id, tag_id, operation, old_value, new_value, user_id, changed_gmt)
+                 SELECT tv.id, tv.item_id, tv.tag_id, :p_operation, {1}, {2}, :p_user_id, :p_changed
+                 FROM cs_{0}.tag_value tv
+                 WHERE {3}",
+            customer_code, old_value, new_value, condition
+        );
+
+        params.insert(
+            "p_operation".to_string(),
+            CellValue::from_raw_str(change.as_str()),
+        );
+        params.insert("p_user_id".to_string(), CellValue::from_raw_int(user_id));
+        params.insert(
+            "p_changed".to_string(),
+            CellValue::from_raw_systemtime(SystemTime::now()),
+        );
+
+        let sql_insert = SQLChangeAsync {
+            sql_query,
+            params,
+            sequence_name: "".to_string(),
+        };
+
+        let _ = sql_insert.update(trans).await.map_err(err_fwd!(
+            "Cannot record the tag value history, follower=[{}]",
+            &self.follower
+        ))?;
         Ok(())
     }
 
@@ -1415,13 +3553,27 @@ impl ItemDelegate {
                     CellValue::SystemTime(opt_st),
                 );
             }
-            EnumTagValue::Link(tv) => {
+            EnumTagValue::Link(tv) | EnumTagValue::Enumeration(tv) => {
                 params.insert("p_value_string".to_string(), CellValue::String(tv.clone()));
             }
         }
         params
     }
 
+    fn item_lock_delegate(&self) -> ItemLockDelegate {
+        ItemLockDelegate::new(
+            self.session_token.clone(),
+            self.follower.x_request_id.clone(),
+        )
+    }
+
+    fn classification_delegate(&self) -> ClassificationDelegate {
+        ClassificationDelegate::new(
+            self.session_token.clone(),
+            self.follower.x_request_id.clone(),
+        )
+    }
+
     fn web_type_error<T>() -> impl Fn(&ErrorSet<'static>) -> WebType<T>
     where
         T: DeserializeOwned,
@@ -1432,3 +3584,66 @@ impl ItemDelegate {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use dkdto::{EnumTagValue, TagHistoryElement, TagValueElement};
+
+    use crate::item::ItemDelegate;
+
+    fn change(
+        tag_value_id: i64,
+        operation: &str,
+        old_value: Option<&str>,
+        new_value: Option<&str>,
+    ) -> TagHistoryElement {
+        TagHistoryElement {
+            tag_value_id,
+            tag_id: 1,
+            tag_name: "amount".to_string(),
+            tag_type: "int".to_string(),
+            operation: operation.to_string(),
+            old_value: old_value.map(str::to_string),
+            new_value: new_value.map(str::to_string),
+            user_id: 7,
+            changed: "2023-01-01T00:00:00.000Z".to_string(),
+        }
+    }
+
+    fn amount(tag_value_id: i64, value: i64) -> TagValueElement {
+        TagValueElement {
+            tag_value_id,
+            item_id: 100,
+            tag_id: 1,
+            tag_name: "amount".to_string(),
+            value: EnumTagValue::Integer(Some(value)),
+        }
+    }
+
+    #[test]
+    fn rewind_properties_test() {
+        // amount 10 inserted as #1, changed to 20, deleted, inserted again as #2 with 30
+        let changes = vec![
+            change(1, "update", Some("10"), Some("20")),
+            change(1, "delete", Some("20"), None),
+            change(2, "insert", None, Some("30")),
+        ];
+
+        let mut properties = vec![amount(2, 30)];
+        ItemDelegate::rewind_properties(&mut properties, 100, &changes).unwrap();
+        assert_eq!(1, properties.len());
+        assert_eq!(1, properties[0].tag_value_id);
+        assert!(matches!(
+            properties[0].value,
+            EnumTagValue::Integer(Some(10))
+        ));
+
+        let mut properties = vec![amount(2, 30)];
+        ItemDelegate::rewind_properties(&mut properties, 100, &changes[2..]).unwrap();
+        assert!(properties.is_empty());
+
+        let mut properties = vec![amount(1, 20)];
+        let unknown = vec![change(1, "merge", None, None)];
+        assert!(ItemDelegate::rewind_properties(&mut properties, 100, &unknown).is_err());
+    }
+}
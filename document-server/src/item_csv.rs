@@ -0,0 +1,379 @@
+use std::collections::{BTreeSet, HashSet};
+
+use anyhow::anyhow;
+use chrono::{DateTime, NaiveDate};
+
+use dkdto::{AddTagValue, EnumTagValue, ItemElement, TagElement, TagType};
+
+/// Columns of the csv which are item attributes, not tags
+pub(crate) const KEY_COLUMNS: [&str; 2] = ["name", "file_ref"];
+
+/// Separator between the values of a multi-valued tag in a cell
+pub(crate) const MULTI_VALUE_SEPARATOR: char = '|';
+
+#[derive(Debug)]
+pub(crate) struct CsvRow {
+    pub line: u64,
+    pub key: String,
+    pub cells: Vec<String>,
+}
+
+/// Tag of a column, tag_id is None for a tag to be created
+#[derive(Debug)]
+pub(crate) struct CsvColumn {
+    pub tag_id: Option<i64>,
+    pub tag_name: String,
+    pub tag_type: String,
+    pub multi_valued: bool,
+    pub allowed_values: Option<Vec<String>>,
+}
+
+impl CsvColumn {
+    pub fn from_tag(tag: TagElement) -> Self {
+        Self {
+            tag_id: Some(tag.tag_id),
+            tag_name: tag.name,
+            tag_type: tag.tag_type,
+            multi_valued: tag.multi_valued,
+            allowed_values: tag.allowed_values,
+        }
+    }
+
+    pub fn new_tag(tag_name: &str, values: &[&str]) -> Self {
+        Self {
+            tag_id: None,
+            tag_name: tag_name.to_string(),
+            tag_type: infer_tag_type(values).as_str().to_string(),
+            multi_valued: false,
+            allowed_values: None,
+        }
+    }
+}
+
+/// Content of an imported csv, the cells of a row are in the order of the tag names
+#[derive(Debug)]
+pub(crate) struct CsvSheet {
+    pub tag_names: Vec<String>,
+    pub rows: Vec<CsvRow>,
+}
+
+///
+/// Read the csv, the [key_column] identifies the item of each row
+/// The other key column, if any, is ignored, all the remaining columns are tags
+///
+pub(crate) fn read_csv_sheet(csv_text: &str, key_column: &str) -> anyhow::Result<CsvSheet> {
+    let mut reader = csv::ReaderBuilder::new()
+        .has_headers(true)
+        .from_reader(csv_text.as_bytes());
+
+    let headers = reader.headers()?.clone();
+    let mut key_index = None;
+    let mut tag_columns = vec![];
+    let mut tag_names: Vec<String> = vec![];
+    for (index, header) in headers.iter().enumerate() {
+        let header = header.trim();
+        if header == key_column {
+            key_index = Some(index);
+        } else if KEY_COLUMNS.contains(&header) {
+            continue;
+        } else if header.is_empty() {
+            return Err(anyhow!("Column {} has no name", index + 1));
+        } else if tag_names.iter().any(|n| n == header) {
+            return Err(anyhow!("Column [{}] is duplicated", header));
+        } else {
+            tag_columns.push(index);
+            tag_names.push(header.to_string());
+        }
+    }
+
+    let Some(key_index) = key_index else {
+        return Err(anyhow!("Missing the [{}] column", key_column));
+    };
+
+    let mut rows = vec![];
+    for record in reader.records() {
+        let record = record?;
+        let line = record.position().map(|p| p.line()).unwrap_or(0);
+        let key = record.get(key_index).unwrap_or("").trim().to_string();
+        let cells = tag_columns
+            .iter()
+            .map(|i| record.get(*i).unwrap_or("").trim().to_string())
+            .collect();
+        rows.push(CsvRow { line, key, cells });
+    }
+
+    Ok(CsvSheet { tag_names, rows })
+}
+
+///
+/// Narrowest tag type able to hold all the values of a column
+/// The empty cells are not considered, a column without value is a text
+///
+pub(crate) fn infer_tag_type(values: &[&str]) -> TagType {
+    let values: Vec<&str> = values
+        .iter()
+        .flat_map(|v| v.split(MULTI_VALUE_SEPARATOR))
+        .map(str::trim)
+        .filter(|v| !v.is_empty())
+        .collect();
+
+    if values.is_empty() {
+        return TagType::Text;
+    }
+
+    if values.iter().all(|v| v.parse::<i64>().is_ok()) {
+        TagType::Int
+    } else if values.iter().all(|v| v.parse::<f64>().is_ok()) {
+        TagType::Double
+    } else if values
+        .iter()
+        .all(|v| NaiveDate::parse_from_str(v, "%Y-%m-%d").is_ok())
+    {
+        TagType::Date
+    } else if values
+        .iter()
+        .all(|v| DateTime::parse_from_rfc3339(v).is_ok())
+    {
+        TagType::DateTime
+    } else if values.iter().all(|v| *v == "true" || *v == "false") {
+        TagType::Bool
+    } else {
+        TagType::Text
+    }
+}
+
+///
+/// Tag values of the non empty cells of a row
+/// Return the errors as "<tag name>: <reason>"
+///
+pub(crate) fn row_properties(
+    columns: &[CsvColumn],
+    cells: &[String],
+) -> (Vec<AddTagValue>, Vec<String>) {
+    let mut properties = vec![];
+    let mut errors = vec![];
+    for (column, cell) in columns.iter().zip(cells) {
+        if cell.is_empty() {
+            continue;
+        }
+
+        let values: Vec<&str> = if column.multi_valued {
+            cell.split(MULTI_VALUE_SEPARATOR)
+                .map(str::trim)
+                .filter(|v| !v.is_empty())
+                .collect()
+        } else {
+            vec![cell.as_str()]
+        };
+
+        for value in values {
+            if let Some(allowed_values) = &column.allowed_values {
+                if !allowed_values.iter().any(|v| v == value) {
+                    errors.push(format!(
+                        "{}: value [{}] is not allowed",
+                        &column.tag_name, value
+                    ));
+                    continue;
+                }
+            }
+
+            match EnumTagValue::from_string(value, &column.tag_type) {
+                Ok(tag_value) => properties.push(AddTagValue {
+                    tag_id: column.tag_id,
+                    tag_name: match column.tag_id {
+                        Some(_) => None,
+                        None => Some(column.tag_name.clone()),
+                    },
+                    value: tag_value,
+                }),
+                Err(e) => errors.push(format!("{}: {}", &column.tag_name, e)),
+            }
+        }
+    }
+    (properties, errors)
+}
+
+/// Text form of the value, None for a value without content
+pub(crate) fn tag_value_as_text(value: &EnumTagValue) -> Option<String> {
+    match value {
+        EnumTagValue::Text(v)
+        | EnumTagValue::SimpleDate(v)
+        | EnumTagValue::DateTime(v)
+        | EnumTagValue::Link(v)
+        | EnumTagValue::Enumeration(v) => v.clone(),
+        EnumTagValue::Boolean(v) => v.map(|b| b.to_string()),
+        EnumTagValue::Integer(v) => v.map(|i| i.to_string()),
+        EnumTagValue::Double(v) => v.map(|d| d.to_string()),
+    }
+}
+
+///
+/// Write the items as csv, one row per item and one column per tag
+/// The values of a multi-valued tag are joined in a single cell
+///
+pub(crate) fn write_csv_sheet(items: &[ItemElement]) -> anyhow::Result<String> {
+    let tag_names: BTreeSet<&str> = items
+        .iter()
+        .flat_map(|item| item.properties.iter().flatten())
+        .map(|p| p.tag_name.as_str())
+        .collect();
+
+    let mut writer = csv::Writer::from_writer(vec![]);
+
+    let mut headers = KEY_COLUMNS.to_vec();
+    headers.extend(tag_names.iter());
+    writer.write_record(&headers)?;
+
+    for item in items {
+        let mut record = vec![item.name.clone(), item.file_ref.clone().unwrap_or_default()];
+        for tag_name in &tag_names {
+            let values: Vec<String> = item
+                .properties
+                .iter()
+                .flatten()
+                .filter(|p| p.tag_name == *tag_name)
+                .filter_map(|p| tag_value_as_text(&p.value))
+                .collect();
+            record.push(values.join(&MULTI_VALUE_SEPARATOR.to_string()));
+        }
+        writer.write_record(&record)?;
+    }
+
+    let bytes = writer.into_inner().map_err(|e| anyhow!("{}", e))?;
+    Ok(String::from_utf8(bytes)?)
+}
+
+/// Keys present on several rows of the sheet
+pub(crate) fn duplicated_keys(sheet: &CsvSheet) -> HashSet<&str> {
+    let mut seen = HashSet::new();
+    sheet
+        .rows
+        .iter()
+        .map(|row| row.key.as_str())
+        .filter(|key| !seen.insert(*key))
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use dkdto::{EnumTagValue, ItemElement, TagType, TagValueElement};
+
+    use crate::item_csv::{
+        duplicated_keys, infer_tag_type, read_csv_sheet, row_properties, tag_value_as_text,
+        write_csv_sheet, CsvColumn,
+    };
+
+    #[test]
+    fn read_csv_sheet_test() {
+        let csv_text = "file_ref,name,amount,client\n\
+                        f1,invoice 1,120,\"Dupont, Paris\"\n\
+                        f2,invoice 2,,Martin\n";
+
+        let sheet = read_csv_sheet(csv_text, "name").unwrap();
+        assert_eq!(vec!["amount", "client"], sheet.tag_names);
+        assert_eq!(2, sheet.rows.len());
+        assert_eq!("invoice 1", sheet.rows[0].key);
+        assert_eq!(vec!["120", "Dupont, Paris"], sheet.rows[0].cells);
+        assert_eq!(2, sheet.rows[0].line);
+        assert_eq!(vec!["", "Martin"], sheet.rows[1].cells);
+
+        let sheet = read_csv_sheet(csv_text, "file_ref").unwrap();
+        assert_eq!("f2", sheet.rows[1].key);
+
+        assert!(read_csv_sheet("name,amount,amount\na,1,2\n", "name").is_err());
+        assert!(read_csv_sheet("amount\n1\n", "name").is_err());
+    }
+
+    #[test]
+    fn infer_tag_type_test() {
+        assert_eq!(TagType::Int, infer_tag_type(&["12", "", "-3"]));
+        assert_eq!(TagType::Double, infer_tag_type(&["12", "3.5"]));
+        assert_eq!(TagType::Date, infer_tag_type(&["2023-01-31"]));
+        assert_eq!(TagType::DateTime, infer_tag_type(&["2023-01-31T10:00:00Z"]));
+        assert_eq!(TagType::Bool, infer_tag_type(&["true", "false"]));
+        assert_eq!(TagType::Text, infer_tag_type(&["12", "abc"]));
+        assert_eq!(TagType::Text, infer_tag_type(&["", ""]));
+        assert_eq!(TagType::Int, infer_tag_type(&["1|2", "3"]));
+    }
+
+    #[test]
+    fn row_properties_test() {
+        let columns = vec![
+            CsvColumn::new_tag("amount", &["120", "80"]),
+            CsvColumn {
+                tag_id: Some(5),
+                tag_name: "status".to_string(),
+                tag_type: "enum".to_string(),
+                multi_valued: true,
+                allowed_values: Some(vec!["open".to_string(), "paid".to_string()]),
+            },
+        ];
+
+        let cells = vec!["120".to_string(), "open|paid".to_string()];
+        let (properties, errors) = row_properties(&columns, &cells);
+        assert!(errors.is_empty());
+        assert_eq!(3, properties.len());
+        assert_eq!(Some("amount".to_string()), properties[0].tag_name);
+        assert!(matches!(
+            properties[0].value,
+            EnumTagValue::Integer(Some(120))
+        ));
+        assert_eq!(Some(5), properties[2].tag_id);
+        assert_eq!(None, properties[2].tag_name);
+
+        let cells = vec!["".to_string(), "closed".to_string()];
+        let (properties, errors) = row_properties(&columns, &cells);
+        assert!(properties.is_empty());
+        assert_eq!(vec!["status: value [closed] is not allowed"], errors);
+
+        let cells = vec!["abc".to_string(), "".to_string()];
+        let (_, errors) = row_properties(&columns, &cells);
+        assert_eq!(1, errors.len());
+        assert!(errors[0].starts_with("amount: Bad integer value"));
+    }
+
+    #[test]
+    fn write_csv_sheet_test() {
+        let property = |tag_value_id, tag_name: &str, value| TagValueElement {
+            tag_value_id,
+            item_id: 1,
+            tag_id: tag_value_id,
+            tag_name: tag_name.to_string(),
+            value,
+        };
+
+        let items = vec![ItemElement {
+            item_id: 1,
+            name: "invoice 1".to_string(),
+            file_ref: None,
+            created: "2023-01-01T00:00:00.000Z".to_string(),
+            last_modified: None,
+            properties: Some(vec![
+                property(1, "amount", EnumTagValue::Integer(Some(120))),
+                property(2, "keyword", EnumTagValue::Text(Some("a".to_string()))),
+                property(3, "keyword", EnumTagValue::Text(Some("b, c".to_string()))),
+            ]),
+            document_type: None,
+            legal_hold: false,
+            version: 1,
+            checked_out_by: None,
+            checkout_expiry: None,
+        }];
+
+        let csv_text = write_csv_sheet(&items).unwrap();
+        assert_eq!(
+            "name,file_ref,amount,keyword\ninvoice 1,,120,\"a|b, c\"\n",
+            csv_text
+        );
+
+        assert_eq!(None, tag_value_as_text(&EnumTagValue::Double(None)));
+    }
+
+    #[test]
+    fn duplicated_keys_test() {
+        let sheet = read_csv_sheet("name,amount\na,1\nb,2\na,3\n", "name").unwrap();
+        let duplicates = duplicated_keys(&sheet);
+        assert_eq!(1, duplicates.len());
+        assert!(duplicates.contains("a"));
+    }
+}
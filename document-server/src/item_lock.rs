@@ -0,0 +1,511 @@
+use std::collections::HashMap;
+use std::time::SystemTime;
+
+use anyhow::anyhow;
+use axum::http::StatusCode;
+use axum::Json;
+use chrono::{DateTime, Duration, Utc};
+use log::{error, info};
+use serde::de::DeserializeOwned;
+
+use commons_error::*;
+use commons_pg::sql_transaction::{date_time_to_iso, CellValue, SQLDataSet};
+use commons_pg::sql_transaction_async::{
+    SQLChangeAsync, SQLConnectionAsync, SQLQueryBlockAsync, SQLTransactionAsync,
+};
+use commons_services::session_lib::valid_sid_get_session;
+use commons_services::token_lib::SessionToken;
+use commons_services::try_or_return;
+use commons_services::x_request_id::{Follower, XRequestID};
+use dkdto::error_codes::{
+    INCORRECT_CHECKOUT_DURATION, INCORRECT_IF_MATCH, INTERNAL_DATABASE_ERROR, ITEM_CHECKED_OUT,
+    ITEM_VERSION_MISMATCH, MISSING_ITEM,
+};
+use dkdto::{CheckOutReply, CheckOutRequest, ErrorSet, SimpleMessage, WebType, WebTypeBuilder};
+use doka_cli::request_client::TokenType;
+
+/// Minutes an item stays checked out when the request does not tell
+const DEFAULT_CHECKOUT_MINUTES: i32 = 60;
+
+/// A check-out cannot last more than a day, a forgotten check-in must not block the item for long
+const MAX_CHECKOUT_MINUTES: i32 = 1440;
+
+/// Version and check-out of an item, read with a row lock
+struct ItemLock {
+    version: i64,
+    session_id: Option<i64>,
+    expiry_gmt: Option<DateTime<Utc>>,
+    trashed: bool,
+}
+
+///
+/// Concurrent changes of the items
+/// A session can check out an item, the other sessions cannot change it until the check-in
+/// or the end of the check-out. Each change bumps the version of the item,
+/// a writer sending the version it read in If-Match is refused if someone changed the item since.
+///
+pub(crate) struct ItemLockDelegate {
+    pub session_token: SessionToken,
+    pub follower: Follower,
+}
+
+impl ItemLockDelegate {
+    pub fn new(session_token: SessionToken, x_request_id: XRequestID) -> Self {
+        Self {
+            session_token,
+            follower: Follower {
+                x_request_id: x_request_id.new_if_null(),
+                token_type: TokenType::None,
+            },
+        }
+    }
+
+    ///
+    /// 🌟 Check out an item, only the current session can change it until the check-in
+    ///     Checking out again an item of the session extends the check-out
+    ///
+    pub async fn check_out(
+        mut self,
+        item_id: i64,
+        check_out_request: Json<CheckOutRequest>,
+    ) -> WebType<CheckOutReply> {
+        log_info!(
+            "🚀 Start check_out api, item_id=[{}], check_out_request=[{:?}], follower=[{}]",
+            item_id,
+            &check_out_request,
+            &self.follower
+        );
+
+        let entry_session = try_or_return!(
+            valid_sid_get_session(&self.session_token, &mut self.follower).await,
+            Self::web_type_error()
+        );
+
+        let customer_code = entry_session.customer_code.as_str();
+
+        let Some(minutes) = checkout_minutes(check_out_request.duration_minutes) else {
+            log_error!(
+                "💣 Incorrect check-out duration, duration_minutes=[{:?}], follower=[{}]",
+                check_out_request.duration_minutes,
+                &self.follower
+            );
+            return WebType::from_errorset(&INCORRECT_CHECKOUT_DURATION);
+        };
+
+        let Ok(mut cnx) = SQLConnectionAsync::from_pool().await.map_err(err_fwd!(
+            "💣 New Db connection failed, follower=[{}]",
+            &self.follower
+        )) else {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        };
+
+        let Ok(mut trans) = cnx.begin().await.map_err(err_fwd!(
+            "💣 Transaction issue, follower=[{}]",
+            &self.follower
+        )) else {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        };
+
+        let lock = match self
+            .find_item_lock(&mut trans, item_id, entry_session.id, customer_code)
+            .await
+        {
+            Ok(lock) => lock,
+            Err(e) => return WebType::from_errorset(e),
+        };
+
+        let expiry_gmt = Utc::now() + Duration::minutes(minutes as i64);
+
+        let mut params = HashMap::new();
+        params.insert(
+            "p_session_id".to_string(),
+            CellValue::from_raw_int(entry_session.id),
+        );
+        params.insert(
+            "p_user_id".to_string(),
+            CellValue::from_raw_int(entry_session.user_id),
+        );
+        params.insert(
+            "p_expiry".to_string(),
+            CellValue::from_raw_systemtime(SystemTime::from(expiry_gmt)),
+        );
+        params.insert("p_item_id".to_string(), CellValue::from_raw_int(item_id));
+
+        let sql_update = SQLChangeAsync {
+            sql_query: format!(
+                r"UPDATE cs_{}.item SET checked_out_session_id = :p_session_id,
+                    checked_out_user_id = :p_user_id, checkout_expiry_gmt = :p_expiry
+                    WHERE id = :p_item_id",
+                customer_code
+            ),
+            params,
+            sequence_name: "".to_string(),
+        };
+
+        if sql_update
+            .update(&mut trans)
+            .await
+            .map_err(err_fwd!(
+                "💣 Cannot check out the item, follower=[{}]",
+                &self.follower
+            ))
+            .is_err()
+        {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        }
+
+        if trans
+            .commit()
+            .await
+            .map_err(err_fwd!("💣 Commit failed, follower=[{}]", &self.follower))
+            .is_err()
+        {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        }
+
+        log_info!(
+            "😎 The item is checked out, item_id=[{}], expiry=[{}], follower=[{}]",
+            item_id,
+            expiry_gmt,
+            &self.follower
+        );
+        log_info!("🏁 End check_out api, follower=[{}]", &self.follower);
+
+        WebType::from_item(
+            StatusCode::OK.as_u16(),
+            CheckOutReply {
+                item_id,
+                user_id: entry_session.user_id,
+                expiry: date_time_to_iso(&expiry_gmt),
+                version: lock.version,
+            },
+        )
+    }
+
+    ///
+    /// 🌟 Check in an item, any session can change it again
+    ///     Checking in an item that is not checked out does nothing
+    ///
+    pub async fn check_in(mut self, item_id: i64) -> WebType<SimpleMessage> {
+        log_info!(
+            "🚀 Start check_in api, item_id=[{}], follower=[{}]",
+            item_id,
+            &self.follower
+        );
+
+        let entry_session = try_or_return!(
+            valid_sid_get_session(&self.session_token, &mut self.follower).await,
+            Self::web_type_error()
+        );
+
+        let customer_code = entry_session.customer_code.as_str();
+
+        let Ok(mut cnx) = SQLConnectionAsync::from_pool().await.map_err(err_fwd!(
+            "💣 New Db connection failed, follower=[{}]",
+            &self.follower
+        )) else {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        };
+
+        let Ok(mut trans) = cnx.begin().await.map_err(err_fwd!(
+            "💣 Transaction issue, follower=[{}]",
+            &self.follower
+        )) else {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        };
+
+        if let Err(e) = self
+            .find_item_lock(&mut trans, item_id, entry_session.id, customer_code)
+            .await
+        {
+            return WebType::from_errorset(e);
+        }
+
+        if self
+            .release_item(&mut trans, item_id, customer_code)
+            .await
+            .is_err()
+        {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        }
+
+        if trans
+            .commit()
+            .await
+            .map_err(err_fwd!("💣 Commit failed, follower=[{}]", &self.follower))
+            .is_err()
+        {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        }
+
+        log_info!("🏁 End check_in api, follower=[{}]", &self.follower);
+
+        WebType::from_item(
+            StatusCode::OK.as_u16(),
+            SimpleMessage {
+                message: "Ok".to_string(),
+            },
+        )
+    }
+
+    /// Ensure the session can change the item and bump its version.
+    /// The item row stays locked until the end of the transaction,
+    /// so two concurrent writers cannot both succeed with the same If-Match version.
+    pub(crate) async fn prepare_write(
+        &self,
+        trans: &mut SQLTransactionAsync<'_>,
+        item_id: i64,
+        session_id: i64,
+        if_match: Option<i64>,
+        customer_code: &str,
+    ) -> Result<(), &'static ErrorSet<'static>> {
+        let lock = self
+            .find_item_lock(trans, item_id, session_id, customer_code)
+            .await?;
+
+        if let Some(version) = if_match {
+            if version != lock.version {
+                log_error!(
+                    "💣 The item has been changed, item_id=[{}], if_match=[{}], version=[{}], follower=[{}]",
+                    item_id,
+                    version,
+                    lock.version,
+                    &self.follower
+                );
+                return Err(&ITEM_VERSION_MISMATCH);
+            }
+        }
+
+        let mut params = HashMap::new();
+        params.insert(
+            "p_now".to_string(),
+            CellValue::from_raw_systemtime(SystemTime::now()),
+        );
+        params.insert("p_item_id".to_string(), CellValue::from_raw_int(item_id));
+
+        let sql_update = SQLChangeAsync {
+            sql_query: format!(
+                r"UPDATE cs_{}.item SET version = version + 1, last_modified_gmt = :p_now
+                    WHERE id = :p_item_id",
+                customer_code
+            ),
+            params,
+            sequence_name: "".to_string(),
+        };
+
+        if sql_update
+            .update(trans)
+            .await
+            .map_err(err_fwd!(
+                "💣 Cannot change the version of the item, item_id=[{}], follower=[{}]",
+                item_id,
+                &self.follower
+            ))
+            .is_err()
+        {
+            return Err(&INTERNAL_DATABASE_ERROR);
+        }
+
+        Ok(())
+    }
+
+    /// Read the version and check-out of an item out of the trash, and lock its row.
+    /// Fail if another session holds the check-out.
+    async fn find_item_lock(
+        &self,
+        trans: &mut SQLTransactionAsync<'_>,
+        item_id: i64,
+        session_id: i64,
+        customer_code: &str,
+    ) -> Result<ItemLock, &'static ErrorSet<'static>> {
+        let lock = match self.search_item_lock(trans, item_id, customer_code).await {
+            Ok(Some(lock)) if !lock.trashed => lock,
+            Ok(_) => {
+                log_error!(
+                    "💣 Missing item, item_id=[{}], follower=[{}]",
+                    item_id,
+                    &self.follower
+                );
+                return Err(&MISSING_ITEM);
+            }
+            Err(e) => {
+                log_error!(
+                    "💣 Cannot read the item, item_id=[{}], error=[{}], follower=[{}]",
+                    item_id,
+                    e,
+                    &self.follower
+                );
+                return Err(&INTERNAL_DATABASE_ERROR);
+            }
+        };
+
+        if is_checked_out_by_other(lock.session_id, lock.expiry_gmt, session_id, Utc::now()) {
+            log_error!(
+                "💣 The item is checked out by another session, item_id=[{}], follower=[{}]",
+                item_id,
+                &self.follower
+            );
+            return Err(&ITEM_CHECKED_OUT);
+        }
+
+        Ok(lock)
+    }
+
+    async fn search_item_lock(
+        &self,
+        trans: &mut SQLTransactionAsync<'_>,
+        item_id: i64,
+        customer_code: &str,
+    ) -> anyhow::Result<Option<ItemLock>> {
+        let mut params = HashMap::new();
+        params.insert("p_item_id".to_string(), CellValue::from_raw_int(item_id));
+
+        // The query block appends an OFFSET, the row lock goes in a sub-select
+        let query = SQLQueryBlockAsync {
+            sql_query: format!(
+                r"SELECT version, checked_out_session_id, checkout_expiry_gmt, trashed_gmt
+                    FROM ( SELECT version, checked_out_session_id, checkout_expiry_gmt, trashed_gmt
+                        FROM cs_{}.item WHERE id = :p_item_id FOR UPDATE ) it ",
+                customer_code
+            ),
+            start: 0,
+            length: Some(1),
+            params,
+        };
+
+        let mut sql_result: SQLDataSet = query
+            .execute(trans)
+            .await
+            .map_err(err_fwd!("Query failed, [{}]", &query.sql_query))?;
+
+        if !sql_result.next() {
+            return Ok(None);
+        }
+
+        Ok(Some(ItemLock {
+            version: sql_result
+                .get_int("version")
+                .ok_or(anyhow!("Wrong version"))?,
+            session_id: sql_result.get_int("checked_out_session_id"),
+            expiry_gmt: sql_result.get_timestamp_as_datetime("checkout_expiry_gmt"),
+            trashed: sql_result
+                .get_timestamp_as_datetime("trashed_gmt")
+                .is_some(),
+        }))
+    }
+
+    async fn release_item(
+        &self,
+        trans: &mut SQLTransactionAsync<'_>,
+        item_id: i64,
+        customer_code: &str,
+    ) -> anyhow::Result<()> {
+        let mut params = HashMap::new();
+        params.insert("p_item_id".to_string(), CellValue::from_raw_int(item_id));
+
+        let sql_update = SQLChangeAsync {
+            sql_query: format!(
+                r"UPDATE cs_{}.item SET checked_out_session_id = NULL,
+                    checked_out_user_id = NULL, checkout_expiry_gmt = NULL
+                    WHERE id = :p_item_id",
+                customer_code
+            ),
+            params,
+            sequence_name: "".to_string(),
+        };
+
+        sql_update.update(trans).await.map_err(err_fwd!(
+            "💣 Cannot check in the item, item_id=[{}], follower=[{}]",
+            item_id,
+            &self.follower
+        ))
+    }
+
+    fn web_type_error<T>() -> impl Fn(&ErrorSet<'static>) -> WebType<T>
+    where
+        T: DeserializeOwned,
+    {
+        |e| {
+            log_error!("💣 Error after try {:?}", e);
+            WebType::from_errorset(e)
+        }
+    }
+}
+
+/// Read the item version of an If-Match header, ex: 3, "3" or W/"3"
+/// "*" matches any version
+pub(crate) fn parse_if_match(
+    if_match: Option<&str>,
+) -> Result<Option<i64>, &'static ErrorSet<'static>> {
+    let Some(if_match) = if_match.map(str::trim) else {
+        return Ok(None);
+    };
+    if if_match == "*" {
+        return Ok(None);
+    }
+    let version = if_match.strip_prefix("W/").unwrap_or(if_match);
+    version
+        .trim_matches('"')
+        .parse::<i64>()
+        .map(Some)
+        .map_err(|_| &*INCORRECT_IF_MATCH)
+}
+
+/// Duration of a check-out in minutes, None if out of bounds
+fn checkout_minutes(duration_minutes: Option<i32>) -> Option<i32> {
+    let minutes = duration_minutes.unwrap_or(DEFAULT_CHECKOUT_MINUTES);
+    (1..=MAX_CHECKOUT_MINUTES)
+        .contains(&minutes)
+        .then_some(minutes)
+}
+
+/// True if another session holds a check-out that is not over
+fn is_checked_out_by_other(
+    checked_out_session_id: Option<i64>,
+    expiry_gmt: Option<DateTime<Utc>>,
+    session_id: i64,
+    now: DateTime<Utc>,
+) -> bool {
+    match (checked_out_session_id, expiry_gmt) {
+        (Some(owner_id), Some(expiry_gmt)) => owner_id != session_id && expiry_gmt > now,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::{Duration, Utc};
+
+    use crate::item_lock::{checkout_minutes, is_checked_out_by_other, parse_if_match};
+
+    #[test]
+    fn parse_if_match_test() {
+        assert_eq!(Some(None), parse_if_match(None).ok());
+        assert_eq!(Some(None), parse_if_match(Some("*")).ok());
+        assert_eq!(Some(Some(3)), parse_if_match(Some("3")).ok());
+        assert_eq!(Some(Some(3)), parse_if_match(Some("\"3\"")).ok());
+        assert_eq!(Some(Some(12)), parse_if_match(Some(" W/\"12\" ")).ok());
+        assert!(parse_if_match(Some("abc")).is_err());
+        assert!(parse_if_match(Some("")).is_err());
+    }
+
+    #[test]
+    fn checkout_minutes_test() {
+        assert_eq!(Some(60), checkout_minutes(None));
+        assert_eq!(Some(1), checkout_minutes(Some(1)));
+        assert_eq!(Some(1440), checkout_minutes(Some(1440)));
+        assert_eq!(None, checkout_minutes(Some(0)));
+        assert_eq!(None, checkout_minutes(Some(1441)));
+    }
+
+    #[test]
+    fn is_checked_out_by_other_test() {
+        let now = Utc::now();
+        let later = now + Duration::minutes(10);
+        let earlier = now - Duration::minutes(10);
+        assert!(!is_checked_out_by_other(None, None, 1, now));
+        assert!(!is_checked_out_by_other(Some(1), Some(later), 1, now));
+        assert!(is_checked_out_by_other(Some(2), Some(later), 1, now));
+        assert!(!is_checked_out_by_other(Some(2), Some(earlier), 1, now));
+    }
+}
@@ -0,0 +1,740 @@
+use std::collections::HashMap;
+use std::time::SystemTime;
+
+use anyhow::anyhow;
+use axum::http::StatusCode;
+use axum::Json;
+use chrono::{DateTime, Utc};
+use log::{error, info};
+use serde::de::DeserializeOwned;
+
+use commons_error::*;
+use commons_pg::sql_transaction::{date_time_to_iso, CellValue, SQLDataSet};
+use commons_pg::sql_transaction_async::{
+    SQLChangeAsync, SQLConnectionAsync, SQLQueryBlockAsync, SQLTransactionAsync,
+};
+use commons_services::session_lib::valid_sid_get_session;
+use commons_services::token_lib::SessionToken;
+use commons_services::try_or_return;
+use commons_services::x_request_id::{Follower, XRequestID};
+use dkdto::error_codes::{
+    FILE_REF_ALREADY_USED, INCORRECT_FILE_REF, INTERNAL_DATABASE_ERROR, MISSING_ITEM,
+    MISSING_ITEM_VERSION,
+};
+use dkdto::{
+    AddItemVersionReply, AddItemVersionRequest, ErrorSet, GetItemVersionReply, ItemVersionElement,
+    SimpleMessage, WebType, WebTypeBuilder,
+};
+use doka_cli::request_client::TokenType;
+
+use crate::item_lock::{parse_if_match, ItemLockDelegate};
+
+/// A version to record
+pub(crate) struct NewVersion<'a> {
+    pub version_no: i32,
+    pub file_ref: &'a str,
+    pub comment: Option<&'a str>,
+    pub user_id: Option<i64>,
+    pub created: SystemTime,
+}
+
+/// The file of an item out of the trash
+struct ItemFile {
+    file_ref: Option<String>,
+    created_gmt: DateTime<Utc>,
+}
+
+///
+/// Versions of the file of an item
+/// The file of the item (item.file_ref) is the file of the current version,
+/// so the full text of an item is the one of its current version, the "document" rows
+/// of the other versions are kept to make the rollback immediate.
+/// The items created before the versions get their first version on their first change.
+///
+pub(crate) struct ItemVersionDelegate {
+    pub session_token: SessionToken,
+    pub follower: Follower,
+}
+
+impl ItemVersionDelegate {
+    pub fn new(session_token: SessionToken, x_request_id: XRequestID) -> Self {
+        Self {
+            session_token,
+            follower: Follower {
+                x_request_id: x_request_id.new_if_null(),
+                token_type: TokenType::None,
+            },
+        }
+    }
+
+    ///
+    /// 🌟 Find all the versions of an item, oldest first
+    ///
+    pub async fn get_item_versions(mut self, item_id: i64) -> WebType<GetItemVersionReply> {
+        log_info!(
+            "🚀 Start get_item_versions api, item_id=[{}], follower=[{}]",
+            item_id,
+            &self.follower
+        );
+
+        let entry_session = try_or_return!(
+            valid_sid_get_session(&self.session_token, &mut self.follower).await,
+            Self::web_type_error()
+        );
+
+        let customer_code = entry_session.customer_code.as_str();
+
+        let Ok(mut cnx) = SQLConnectionAsync::from_pool().await.map_err(err_fwd!(
+            "💣 New Db connection failed, follower=[{}]",
+            &self.follower
+        )) else {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        };
+
+        let Ok(mut trans) = cnx.begin().await.map_err(err_fwd!(
+            "💣 Transaction issue, follower=[{}]",
+            &self.follower
+        )) else {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        };
+
+        let item_file = try_or_return!(
+            self.find_item_file(&mut trans, item_id, customer_code)
+                .await,
+            Self::web_type_error()
+        );
+
+        let Ok(mut versions) = self
+            .search_versions(&mut trans, item_id, customer_code)
+            .await
+            .map_err(err_fwd!(
+                "💣 Cannot find the versions, item_id=[{}], follower=[{}]",
+                item_id,
+                &self.follower
+            ))
+        else {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        };
+
+        // The file of an item created before the versions is its first version
+        if versions.is_empty() {
+            if let Some(file_ref) = &item_file.file_ref {
+                versions.push(ItemVersionElement {
+                    version_no: 1,
+                    file_ref: file_ref.clone(),
+                    comment: None,
+                    user_id: None,
+                    created: date_time_to_iso(&item_file.created_gmt),
+                    current: false,
+                });
+            }
+        }
+        mark_current(&mut versions, item_file.file_ref.as_deref());
+
+        if trans
+            .commit()
+            .await
+            .map_err(err_fwd!("💣 Commit failed, follower=[{}]", &self.follower))
+            .is_err()
+        {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        }
+
+        log_info!(
+            "🏁 End get_item_versions api, follower=[{}]",
+            &self.follower
+        );
+
+        WebType::from_item(
+            StatusCode::OK.as_u16(),
+            GetItemVersionReply { item_id, versions },
+        )
+    }
+
+    ///
+    /// 🌟 Add a new version to an item, its file becomes the file of the item
+    ///     The file must be uploaded on the file server first
+    ///
+    pub async fn add_item_version(
+        mut self,
+        item_id: i64,
+        if_match: Option<&str>,
+        add_version_request: Json<AddItemVersionRequest>,
+    ) -> WebType<AddItemVersionReply> {
+        log_info!(
+            "🚀 Start add_item_version api, item_id=[{}], add_version_request=[{:?}], follower=[{}]",
+            item_id,
+            &add_version_request,
+            &self.follower
+        );
+
+        let entry_session = try_or_return!(
+            valid_sid_get_session(&self.session_token, &mut self.follower).await,
+            Self::web_type_error()
+        );
+
+        let customer_code = entry_session.customer_code.as_str();
+
+        let if_match = try_or_return!(parse_if_match(if_match), Self::web_type_error());
+
+        let file_ref = add_version_request.file_ref.trim();
+        if file_ref.is_empty() || file_ref.len() > 50 {
+            log_error!(
+                "💣 Incorrect file reference, file_ref=[{}], follower=[{}]",
+                file_ref,
+                &self.follower
+            );
+            return WebType::from_errorset(&INCORRECT_FILE_REF);
+        }
+
+        let Ok(mut cnx) = SQLConnectionAsync::from_pool().await.map_err(err_fwd!(
+            "💣 New Db connection failed, follower=[{}]",
+            &self.follower
+        )) else {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        };
+
+        let Ok(mut trans) = cnx.begin().await.map_err(err_fwd!(
+            "💣 Transaction issue, follower=[{}]",
+            &self.follower
+        )) else {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        };
+
+        if let Err(e) = self
+            .item_lock_delegate()
+            .prepare_write(
+                &mut trans,
+                item_id,
+                entry_session.id,
+                if_match,
+                customer_code,
+            )
+            .await
+        {
+            return WebType::from_errorset(e);
+        }
+
+        let Ok(used) = self
+            .is_file_ref_used(&mut trans, file_ref, customer_code)
+            .await
+            .map_err(err_fwd!(
+                "💣 Cannot check the file reference, follower=[{}]",
+                &self.follower
+            ))
+        else {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        };
+
+        if used {
+            log_error!(
+                "💣 The file reference is already used, file_ref=[{}], follower=[{}]",
+                file_ref,
+                &self.follower
+            );
+            return WebType::from_errorset(&FILE_REF_ALREADY_USED);
+        }
+
+        let versions = try_or_return!(
+            self.find_all_versions(&mut trans, item_id, customer_code)
+                .await,
+            Self::web_type_error()
+        );
+
+        let version_no = next_version_no(&versions);
+
+        if self
+            .insert_version(
+                &mut trans,
+                item_id,
+                &NewVersion {
+                    version_no,
+                    file_ref,
+                    comment: add_version_request.comment.as_deref(),
+                    user_id: Some(entry_session.user_id),
+                    created: SystemTime::now(),
+                },
+                customer_code,
+            )
+            .await
+            .is_err()
+        {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        }
+
+        if self
+            .set_item_file(&mut trans, item_id, file_ref, customer_code)
+            .await
+            .is_err()
+        {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        }
+
+        if trans
+            .commit()
+            .await
+            .map_err(err_fwd!("💣 Commit failed, follower=[{}]", &self.follower))
+            .is_err()
+        {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        }
+
+        log_info!(
+            "😎 Added the version, item_id=[{}], version_no=[{}], file_ref=[{}], follower=[{}]",
+            item_id,
+            version_no,
+            file_ref,
+            &self.follower
+        );
+        log_info!("🏁 End add_item_version api, follower=[{}]", &self.follower);
+
+        WebType::from_item(
+            StatusCode::OK.as_u16(),
+            AddItemVersionReply {
+                item_id,
+                version_no,
+            },
+        )
+    }
+
+    ///
+    /// 🌟 Make a previous version the current version of the item
+    ///     The versions after it are kept, a rollback can be undone
+    ///
+    pub async fn rollback_item_version(
+        mut self,
+        item_id: i64,
+        version_no: i32,
+        if_match: Option<&str>,
+    ) -> WebType<SimpleMessage> {
+        log_info!(
+            "🚀 Start rollback_item_version api, item_id=[{}], version_no=[{}], follower=[{}]",
+            item_id,
+            version_no,
+            &self.follower
+        );
+
+        let entry_session = try_or_return!(
+            valid_sid_get_session(&self.session_token, &mut self.follower).await,
+            Self::web_type_error()
+        );
+
+        let customer_code = entry_session.customer_code.as_str();
+
+        let if_match = try_or_return!(parse_if_match(if_match), Self::web_type_error());
+
+        let Ok(mut cnx) = SQLConnectionAsync::from_pool().await.map_err(err_fwd!(
+            "💣 New Db connection failed, follower=[{}]",
+            &self.follower
+        )) else {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        };
+
+        let Ok(mut trans) = cnx.begin().await.map_err(err_fwd!(
+            "💣 Transaction issue, follower=[{}]",
+            &self.follower
+        )) else {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        };
+
+        if let Err(e) = self
+            .item_lock_delegate()
+            .prepare_write(
+                &mut trans,
+                item_id,
+                entry_session.id,
+                if_match,
+                customer_code,
+            )
+            .await
+        {
+            return WebType::from_errorset(e);
+        }
+
+        let versions = try_or_return!(
+            self.find_all_versions(&mut trans, item_id, customer_code)
+                .await,
+            Self::web_type_error()
+        );
+
+        let Some(version) = versions.iter().find(|v| v.version_no == version_no) else {
+            log_error!(
+                "💣 Missing version, item_id=[{}], version_no=[{}], follower=[{}]",
+                item_id,
+                version_no,
+                &self.follower
+            );
+            return WebType::from_errorset(&MISSING_ITEM_VERSION);
+        };
+
+        if self
+            .set_item_file(&mut trans, item_id, &version.file_ref, customer_code)
+            .await
+            .is_err()
+        {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        }
+
+        if trans
+            .commit()
+            .await
+            .map_err(err_fwd!("💣 Commit failed, follower=[{}]", &self.follower))
+            .is_err()
+        {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        }
+
+        log_info!(
+            "😎 Rolled back the item, item_id=[{}], version_no=[{}], follower=[{}]",
+            item_id,
+            version_no,
+            &self.follower
+        );
+        log_info!(
+            "🏁 End rollback_item_version api, follower=[{}]",
+            &self.follower
+        );
+
+        WebType::from_item(
+            StatusCode::OK.as_u16(),
+            SimpleMessage {
+                message: "Ok".to_string(),
+            },
+        )
+    }
+
+    /// Record a version of the file of an item
+    pub(crate) async fn insert_version(
+        &self,
+        trans: &mut SQLTransactionAsync<'_>,
+        item_id: i64,
+        version: &NewVersion<'_>,
+        customer_code: &str,
+    ) -> anyhow::Result<i64> {
+        let sql_query = format!(
+            r"INSERT INTO cs_{}.item_version (item_id, version_no, file_ref, comment, user_id, created_gmt)
+                    VALUES (:p_item_id, :p_version_no, :p_file_ref, :p_comment, :p_user_id, :p_created)",
+            customer_code
+        );
+
+        let mut params = HashMap::new();
+        params.insert("p_item_id".to_string(), CellValue::from_raw_int(item_id));
+        params.insert(
+            "p_version_no".to_string(),
+            CellValue::from_raw_int_32(version.version_no),
+        );
+        params.insert(
+            "p_file_ref".to_string(),
+            CellValue::from_raw_str(version.file_ref),
+        );
+        params.insert(
+            "p_comment".to_string(),
+            CellValue::from_opt_str(version.comment),
+        );
+        params.insert("p_user_id".to_string(), CellValue::Int(version.user_id));
+        params.insert(
+            "p_created".to_string(),
+            CellValue::from_raw_systemtime(version.created),
+        );
+
+        let sql_insert = SQLChangeAsync {
+            sql_query,
+            params,
+            sequence_name: format!("cs_{}.item_version_id_seq", customer_code),
+        };
+
+        sql_insert.insert(trans).await.map_err(err_fwd!(
+            "💣 Cannot insert the item version, item_id=[{}], follower=[{}]",
+            item_id,
+            &self.follower
+        ))
+    }
+
+    /// Files of all the versions of an item
+    pub(crate) async fn find_version_file_refs(
+        &self,
+        trans: &mut SQLTransactionAsync<'_>,
+        item_id: i64,
+        customer_code: &str,
+    ) -> anyhow::Result<Vec<String>> {
+        let versions = self.search_versions(trans, item_id, customer_code).await?;
+        Ok(versions.into_iter().map(|v| v.file_ref).collect())
+    }
+
+    /// The recorded versions of the item, the file of an item created before the versions
+    /// is recorded as its first version on the way
+    async fn find_all_versions(
+        &self,
+        trans: &mut SQLTransactionAsync<'_>,
+        item_id: i64,
+        customer_code: &str,
+    ) -> Result<Vec<ItemVersionElement>, &'static ErrorSet<'static>> {
+        let item_file = self.find_item_file(trans, item_id, customer_code).await?;
+
+        let Ok(versions) = self
+            .search_versions(trans, item_id, customer_code)
+            .await
+            .map_err(err_fwd!(
+                "💣 Cannot find the versions, item_id=[{}], follower=[{}]",
+                item_id,
+                &self.follower
+            ))
+        else {
+            return Err(&INTERNAL_DATABASE_ERROR);
+        };
+
+        match (versions.is_empty(), item_file.file_ref) {
+            (true, Some(file_ref)) => {
+                let created = SystemTime::from(item_file.created_gmt);
+                if self
+                    .insert_version(
+                        trans,
+                        item_id,
+                        &NewVersion {
+                            version_no: 1,
+                            file_ref: &file_ref,
+                            comment: None,
+                            user_id: None,
+                            created,
+                        },
+                        customer_code,
+                    )
+                    .await
+                    .is_err()
+                {
+                    return Err(&INTERNAL_DATABASE_ERROR);
+                }
+                Ok(vec![ItemVersionElement {
+                    version_no: 1,
+                    file_ref,
+                    comment: None,
+                    user_id: None,
+                    created: date_time_to_iso(&item_file.created_gmt),
+                    current: true,
+                }])
+            }
+            _ => Ok(versions),
+        }
+    }
+
+    async fn search_versions(
+        &self,
+        trans: &mut SQLTransactionAsync<'_>,
+        item_id: i64,
+        customer_code: &str,
+    ) -> anyhow::Result<Vec<ItemVersionElement>> {
+        let mut params = HashMap::new();
+        params.insert("p_item_id".to_string(), CellValue::from_raw_int(item_id));
+
+        let query = SQLQueryBlockAsync {
+            sql_query: format!(
+                r"SELECT version_no, file_ref, comment, user_id, created_gmt
+                    FROM cs_{}.item_version
+                    WHERE item_id = :p_item_id
+                    ORDER BY version_no ",
+                customer_code
+            ),
+            start: 0,
+            length: None,
+            params,
+        };
+
+        let mut sql_result: SQLDataSet = query
+            .execute(trans)
+            .await
+            .map_err(err_fwd!("Query failed, [{}]", &query.sql_query))?;
+
+        let mut versions = vec![];
+        while sql_result.next() {
+            let created_gmt = sql_result
+                .get_timestamp_as_datetime("created_gmt")
+                .ok_or(anyhow!("Wrong created gmt"))?;
+            versions.push(ItemVersionElement {
+                version_no: sql_result
+                    .get_int_32("version_no")
+                    .ok_or(anyhow!("Wrong version no"))?,
+                file_ref: sql_result
+                    .get_string("file_ref")
+                    .ok_or(anyhow!("Wrong file ref"))?,
+                comment: sql_result.get_string("comment"),
+                user_id: sql_result.get_int("user_id"),
+                created: date_time_to_iso(&created_gmt),
+                current: false,
+            });
+        }
+        Ok(versions)
+    }
+
+    async fn find_item_file(
+        &self,
+        trans: &mut SQLTransactionAsync<'_>,
+        item_id: i64,
+        customer_code: &str,
+    ) -> Result<ItemFile, &'static ErrorSet<'static>> {
+        let mut params = HashMap::new();
+        params.insert("p_item_id".to_string(), CellValue::from_raw_int(item_id));
+
+        let query = SQLQueryBlockAsync {
+            sql_query: format!(
+                r"SELECT file_ref, created_gmt FROM cs_{}.item
+                    WHERE id = :p_item_id AND trashed_gmt IS NULL",
+                customer_code
+            ),
+            start: 0,
+            length: Some(1),
+            params,
+        };
+
+        let Ok(mut sql_result) = query.execute(trans).await.map_err(err_fwd!(
+            "💣 Query failed, [{}], follower=[{}]",
+            &query.sql_query,
+            &self.follower
+        )) else {
+            return Err(&INTERNAL_DATABASE_ERROR);
+        };
+
+        if !sql_result.next() {
+            log_error!(
+                "💣 Missing item, item_id=[{}], follower=[{}]",
+                item_id,
+                &self.follower
+            );
+            return Err(&MISSING_ITEM);
+        }
+
+        let Some(created_gmt) = sql_result.get_timestamp_as_datetime("created_gmt") else {
+            return Err(&INTERNAL_DATABASE_ERROR);
+        };
+
+        Ok(ItemFile {
+            file_ref: sql_result.get_string("file_ref"),
+            created_gmt,
+        })
+    }
+
+    /// True if the file is the file of an item or of a version
+    async fn is_file_ref_used(
+        &self,
+        trans: &mut SQLTransactionAsync<'_>,
+        file_ref: &str,
+        customer_code: &str,
+    ) -> anyhow::Result<bool> {
+        let mut params = HashMap::new();
+        params.insert("p_file_ref".to_string(), CellValue::from_raw_str(file_ref));
+
+        let query = SQLQueryBlockAsync {
+            sql_query: format!(
+                r"SELECT id FROM cs_{0}.item WHERE file_ref = :p_file_ref
+                    UNION ALL
+                    SELECT item_id FROM cs_{0}.item_version WHERE file_ref = :p_file_ref ",
+                customer_code
+            ),
+            start: 0,
+            length: Some(1),
+            params,
+        };
+
+        let mut sql_result: SQLDataSet = query
+            .execute(trans)
+            .await
+            .map_err(err_fwd!("Query failed, [{}]", &query.sql_query))?;
+
+        Ok(sql_result.next())
+    }
+
+    /// Make the file the current file of the item
+    async fn set_item_file(
+        &self,
+        trans: &mut SQLTransactionAsync<'_>,
+        item_id: i64,
+        file_ref: &str,
+        customer_code: &str,
+    ) -> anyhow::Result<()> {
+        let mut params = HashMap::new();
+        params.insert("p_file_ref".to_string(), CellValue::from_raw_str(file_ref));
+        params.insert("p_item_id".to_string(), CellValue::from_raw_int(item_id));
+
+        let sql_update = SQLChangeAsync {
+            sql_query: format!(
+                "UPDATE cs_{}.item SET file_ref = :p_file_ref WHERE id = :p_item_id",
+                customer_code
+            ),
+            params,
+            sequence_name: "".to_string(),
+        };
+
+        sql_update.update(trans).await.map_err(err_fwd!(
+            "💣 Cannot change the file of the item, item_id=[{}], follower=[{}]",
+            item_id,
+            &self.follower
+        ))
+    }
+
+    fn item_lock_delegate(&self) -> ItemLockDelegate {
+        ItemLockDelegate::new(
+            self.session_token.clone(),
+            self.follower.x_request_id.clone(),
+        )
+    }
+
+    fn web_type_error<T>() -> impl Fn(&ErrorSet<'static>) -> WebType<T>
+    where
+        T: DeserializeOwned,
+    {
+        |e| {
+            log_error!("💣 Error after try {:?}", e);
+            WebType::from_errorset(e)
+        }
+    }
+}
+
+/// Number of the version after the last one
+fn next_version_no(versions: &[ItemVersionElement]) -> i32 {
+    versions.iter().map(|v| v.version_no).max().unwrap_or(0) + 1
+}
+
+/// The current version is the one holding the file of the item
+fn mark_current(versions: &mut [ItemVersionElement], item_file_ref: Option<&str>) {
+    for version in versions.iter_mut() {
+        version.current = Some(version.file_ref.as_str()) == item_file_ref;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use dkdto::ItemVersionElement;
+
+    use crate::item_version::{mark_current, next_version_no};
+
+    fn version(version_no: i32, file_ref: &str) -> ItemVersionElement {
+        ItemVersionElement {
+            version_no,
+            file_ref: file_ref.to_string(),
+            comment: None,
+            user_id: Some(1),
+            created: "2023-01-01T00:00:00+00:00".to_string(),
+            current: false,
+        }
+    }
+
+    #[test]
+    fn next_version_no_test() {
+        assert_eq!(1, next_version_no(&[]));
+        assert_eq!(3, next_version_no(&[version(1, "a"), version(2, "b")]));
+    }
+
+    #[test]
+    fn mark_current_test() {
+        let mut versions = vec![version(1, "a"), version(2, "b"), version(3, "c")];
+        mark_current(&mut versions, Some("b"));
+        let current: Vec<i32> = versions
+            .iter()
+            .filter(|v| v.current)
+            .map(|v| v.version_no)
+            .collect();
+        assert_eq!(vec![2], current);
+
+        mark_current(&mut versions, None);
+        assert!(versions.iter().all(|v| !v.current));
+    }
+}
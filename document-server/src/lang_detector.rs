@@ -0,0 +1,329 @@
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+use crate::language::{
+    LanguageCode, ARABIC, DANISH, DUTCH, ENGLISH, FINNISH, FRENCH, GERMAN, GREEK, HUNGARIAN,
+    INDONESIAN, IRISH, ITALIAN, LITHUANIAN, NEPALI, NORWEGIAN, PORTUGUESE, ROMANIAN, RUSSIAN,
+    SPANISH, SWEDISH, TAMIL, TURKISH,
+};
+
+/// Value of the "ft.lang_detector" property to use the in-process detector, Tika is used otherwise
+pub(crate) const NGRAM_DETECTOR: &str = "ngram";
+
+/// Below this number of trigrams, the text is too short to be detected
+const MIN_TRIGRAMS: usize = 12;
+/// Minimum gap of the average log probability between the best and the second language
+const MIN_MARGIN: f64 = 0.05;
+/// Only the start of the text is read
+const MAX_SAMPLE_CHARS: usize = 3_000;
+
+/// Training texts of the languages written with the latin alphabet
+/// The other languages are found from their script
+static LATIN_CORPUS: [(LanguageCode, &str); 17] = [
+    (DANISH, include_str!("../lang-corpus/train/da.txt")),
+    (DUTCH, include_str!("../lang-corpus/train/nl.txt")),
+    (ENGLISH, include_str!("../lang-corpus/train/en.txt")),
+    (FINNISH, include_str!("../lang-corpus/train/fi.txt")),
+    (FRENCH, include_str!("../lang-corpus/train/fr.txt")),
+    (GERMAN, include_str!("../lang-corpus/train/de.txt")),
+    (HUNGARIAN, include_str!("../lang-corpus/train/hu.txt")),
+    (INDONESIAN, include_str!("../lang-corpus/train/id.txt")),
+    (IRISH, include_str!("../lang-corpus/train/ga.txt")),
+    (ITALIAN, include_str!("../lang-corpus/train/it.txt")),
+    (LITHUANIAN, include_str!("../lang-corpus/train/lt.txt")),
+    (NORWEGIAN, include_str!("../lang-corpus/train/nb.txt")),
+    (PORTUGUESE, include_str!("../lang-corpus/train/pt.txt")),
+    (ROMANIAN, include_str!("../lang-corpus/train/ro.txt")),
+    (SPANISH, include_str!("../lang-corpus/train/es.txt")),
+    (SWEDISH, include_str!("../lang-corpus/train/sv.txt")),
+    (TURKISH, include_str!("../lang-corpus/train/tr.txt")),
+];
+
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+enum Script {
+    Latin,
+    Arabic,
+    Greek,
+    Cyrillic,
+    Devanagari,
+    Tamil,
+    Han,
+    Kana,
+    Hangul,
+    Other,
+}
+
+/// Log probabilities of the trigrams of a language, with add-one smoothing
+struct TrigramProfile {
+    lang: LanguageCode,
+    log_probs: HashMap<String, f64>,
+    unknown_log_prob: f64,
+}
+
+impl TrigramProfile {
+    fn train(lang: LanguageCode, corpus: &str) -> Self {
+        let mut counts: HashMap<String, u32> = HashMap::new();
+        for trigram in trigrams(corpus) {
+            *counts.entry(trigram).or_insert(0) += 1;
+        }
+        let total = counts.values().sum::<u32>() as f64 + counts.len() as f64;
+        Self {
+            lang,
+            log_probs: counts
+                .into_iter()
+                .map(|(t, c)| (t, ((c + 1) as f64 / total).ln()))
+                .collect(),
+            unknown_log_prob: (1.0 / total).ln(),
+        }
+    }
+
+    fn score(&self, trigrams: &[String]) -> f64 {
+        trigrams
+            .iter()
+            .map(|t| *self.log_probs.get(t).unwrap_or(&self.unknown_log_prob))
+            .sum()
+    }
+}
+
+fn latin_profiles() -> &'static [TrigramProfile] {
+    static PROFILES: OnceLock<Vec<TrigramProfile>> = OnceLock::new();
+    PROFILES.get_or_init(|| {
+        LATIN_CORPUS
+            .iter()
+            .map(|(lang, corpus)| TrigramProfile::train(*lang, corpus))
+            .collect()
+    })
+}
+
+fn script_of(c: char) -> Script {
+    match c as u32 {
+        0x0041..=0x024F | 0x1E00..=0x1EFF => Script::Latin,
+        0x0370..=0x03FF | 0x1F00..=0x1FFF => Script::Greek,
+        0x0400..=0x052F => Script::Cyrillic,
+        0x0600..=0x06FF | 0x0750..=0x077F | 0xFB50..=0xFDFF | 0xFE70..=0xFEFF => Script::Arabic,
+        0x0900..=0x097F => Script::Devanagari,
+        0x0B80..=0x0BFF => Script::Tamil,
+        0x3400..=0x4DBF | 0x4E00..=0x9FFF | 0xF900..=0xFAFF | 0x20000..=0x2FFFF => Script::Han,
+        0x3040..=0x30FF | 0x31F0..=0x31FF | 0xFF66..=0xFF9F => Script::Kana,
+        0x1100..=0x11FF | 0x3130..=0x318F | 0xAC00..=0xD7AF => Script::Hangul,
+        _ => Script::Other,
+    }
+}
+
+/// Number of letters of the text for each script
+fn script_counts(text: &str) -> HashMap<Script, usize> {
+    let mut counts: HashMap<Script, usize> = HashMap::new();
+    for c in text.chars().filter(|c| c.is_alphabetic()) {
+        *counts.entry(script_of(c)).or_insert(0) += 1;
+    }
+    counts
+}
+
+/// The script of most of the letters of the text
+fn dominant_script(counts: &HashMap<Script, usize>) -> Option<Script> {
+    counts
+        .iter()
+        .max_by_key(|(_, count)| **count)
+        .map(|(script, _)| *script)
+}
+
+/// The trigrams of the lowercase words, each word is padded with a space on both sides
+fn trigrams(text: &str) -> Vec<String> {
+    let mut trigrams = vec![];
+    for word in text
+        .split(|c: char| !c.is_alphabetic())
+        .filter(|w| !w.is_empty())
+    {
+        let padded: Vec<char> = format!(" {} ", word.to_lowercase()).chars().collect();
+        for window in padded.windows(3) {
+            trigrams.push(window.iter().collect());
+        }
+    }
+    trigrams
+}
+
+/// Language of a text written in latin script, None when the text is too short
+/// or when two languages are too close to decide
+fn detect_latin(text: &str) -> Option<&'static str> {
+    let trigrams = trigrams(text);
+    if trigrams.len() < MIN_TRIGRAMS {
+        return None;
+    }
+
+    let mut scores: Vec<(f64, &'static str)> = latin_profiles()
+        .iter()
+        .map(|p| (p.score(&trigrams), p.lang.1))
+        .collect();
+    scores.sort_by(|a, b| b.0.total_cmp(&a.0));
+
+    let margin = (scores[0].0 - scores[1].0) / trigrams.len() as f64;
+    if margin < MIN_MARGIN {
+        None
+    } else {
+        Some(scores[0].1)
+    }
+}
+
+///
+/// Language code-2 of the text, like Tika would answer, to be mapped with map_code
+/// None when the detector cannot decide, the caller should fall back to Tika
+///
+pub(crate) fn detect_code(text: &str) -> Option<&'static str> {
+    let sample: String = text.chars().take(MAX_SAMPLE_CHARS).collect();
+    let counts = script_counts(&sample);
+    match dominant_script(&counts)? {
+        // Japanese mixes Han with Kana
+        Script::Han if counts.contains_key(&Script::Kana) => Some("ja"),
+        Script::Han => Some("zh"),
+        Script::Kana => Some("ja"),
+        Script::Hangul => Some("ko"),
+        Script::Latin => detect_latin(&sample),
+        Script::Arabic => Some(ARABIC.1),
+        Script::Greek => Some(GREEK.1),
+        Script::Cyrillic => Some(RUSSIAN.1),
+        Script::Devanagari => Some(NEPALI.1),
+        Script::Tamil => Some(TAMIL.1),
+        Script::Other => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+
+    use doka_cli::async_request_client::TikaServerClientAsync;
+
+    use crate::lang_detector::detect_code;
+    use crate::language::{map_code, LANGUAGES};
+
+    /// Test sentences of the bundled corpus, with their expected language code-2
+    fn test_corpus() -> Vec<(&'static str, String)> {
+        let mut sentences = vec![];
+        for lang in LANGUAGES {
+            let path = format!(
+                "{}/lang-corpus/test/{}.txt",
+                env!("CARGO_MANIFEST_DIR"),
+                lang.1
+            );
+            let text = fs::read_to_string(&path).unwrap();
+            for line in text.lines().filter(|l| !l.trim().is_empty()) {
+                sentences.push((lang.1, line.to_string()));
+            }
+        }
+        sentences
+    }
+
+    #[test]
+    fn detect_script_test() {
+        assert_eq!(
+            Some("ru"),
+            detect_code("Продажи выросли на двенадцать процентов")
+        );
+        assert_eq!(Some("el"), detect_code("Οι πωλήσεις αυξήθηκαν"));
+        assert_eq!(
+            Some("ja"),
+            detect_code("売上は前年比で十二パーセント増加しました")
+        );
+        assert_eq!(Some("zh"), detect_code("销售额比上年增长了百分之十二"));
+        assert_eq!(
+            Some("ko"),
+            detect_code("매출이 전년 대비 12퍼센트 증가했습니다")
+        );
+        assert_eq!(None, detect_code("המכירות עלו בשנים עשר אחוזים"));
+        assert_eq!(None, detect_code("1234 5678"));
+    }
+
+    #[test]
+    fn detect_short_text_test() {
+        assert_eq!(None, detect_code("Hello"));
+    }
+
+    #[test]
+    fn corpus_accuracy_test() {
+        let sentences = test_corpus();
+        let mut correct = 0;
+        let mut wrong = 0;
+        for (expected, sentence) in &sentences {
+            match detect_code(sentence) {
+                Some(code) if code == *expected => correct += 1,
+                Some(code) => {
+                    println!("Wrong [{}] for [{}] : {}", code, expected, sentence);
+                    wrong += 1;
+                }
+                None => println!("Undecided for [{}] : {}", expected, sentence),
+            }
+        }
+        println!(
+            "Sentences : {}, correct : {}, wrong : {}",
+            sentences.len(),
+            correct,
+            wrong
+        );
+        // See lang-corpus/README.md
+        assert!(correct * 100 >= sentences.len() * 95);
+        assert!(wrong * 100 <= sentences.len() * 2);
+    }
+
+    /// Write the Tika row of the accuracy table and the sentences Tika got wrong in the README
+    fn write_tika_results(row: &str, wrong_sentences: &[String]) {
+        const START: &str = "<!-- tika-wrong -->";
+        const END: &str = "<!-- /tika-wrong -->";
+        let path = format!("{}/lang-corpus/README.md", env!("CARGO_MANIFEST_DIR"));
+        let readme = fs::read_to_string(&path).unwrap();
+
+        let mut lines: Vec<String> = vec![];
+        let mut in_list = false;
+        for line in readme.lines() {
+            if line.starts_with("| Tika ") {
+                lines.push(row.to_string());
+            } else if line == START {
+                in_list = true;
+                lines.push(line.to_string());
+                if wrong_sentences.is_empty() {
+                    lines.push("* none".to_string());
+                }
+                lines.extend(wrong_sentences.iter().map(|s| format!("* {}", s)));
+            } else if line == END {
+                in_list = false;
+                lines.push(line.to_string());
+            } else if !in_list {
+                lines.push(line.to_string());
+            }
+        }
+        fs::write(&path, lines.join("\n") + "\n").unwrap();
+    }
+
+    /// Compare with the Tika path, needs a Tika server on localhost:40010
+    /// The results go to lang-corpus/README.md
+    #[ignore]
+    #[tokio::test]
+    async fn tika_accuracy_test() {
+        let tsc = TikaServerClientAsync::new("localhost", 40010);
+        let sentences = test_corpus();
+        let mut correct = 0;
+        let mut wrong_sentences = vec![];
+        for (expected, sentence) in &sentences {
+            let meta_data = tsc.read_meta(sentence).await.unwrap();
+            let code = map_code(&meta_data.language);
+            if code == *expected {
+                correct += 1;
+            } else {
+                wrong_sentences.push(format!(
+                    "`{}` read as `{}` ({}) : {}",
+                    expected, code, &meta_data.language, sentence
+                ));
+            }
+        }
+        let row = format!(
+            "| Tika           | {} ({:.1}%) | {}     | -            |",
+            correct,
+            correct as f64 * 100.0 / sentences.len() as f64,
+            sentences.len() - correct
+        );
+        println!("{}", &row);
+        for wrong in &wrong_sentences {
+            println!("{}", wrong);
+        }
+        write_tika_results(&row, &wrong_sentences);
+    }
+}
@@ -23,6 +23,10 @@ pub const SWEDISH: LanguageCode = ("swedish", "sv", "swe");
 pub const TAMIL: LanguageCode = ("tamil", "ta", "tam");
 pub const TURKISH: LanguageCode = ("turkish", "tr", "tur");
 
+/// No stemmer for the language (CJK, Hebrew, Swahili...), the words are only lowercased
+/// "xx" is not an iso code, it only stands for the languages out of the list
+pub const SIMPLE: LanguageCode = ("simple", "xx", "und");
+
 pub static LANGUAGES: [LanguageCode; 22] = [
     ARABIC, DANISH, DUTCH, ENGLISH, FINNISH, FRENCH, GERMAN, GREEK, HUNGARIAN, INDONESIAN, IRISH,
     ITALIAN, LITHUANIAN, NEPALI, NORWEGIAN, PORTUGUESE, ROMANIAN, RUSSIAN, SPANISH, SWEDISH, TAMIL,
@@ -50,32 +54,26 @@ pub static LANGUAGES: [LanguageCode; 22] = [
 // }
 
 pub(crate) fn lang_name_from_code_2(lang_code_2: &'_ str) -> &'_ str {
-    search_from_code_2(lang_code_2).0
+    search_from_code_2(lang_code_2).unwrap_or(SIMPLE).0
 }
 
 ///
 /// (private) Find the language Code from the code-2 iso
 ///
-fn search_from_code_2(lang_code_2: &'_ str) -> LanguageCode {
-    let mut found_lg = ENGLISH;
-    for lg in LANGUAGES {
-        if lg.1 == lang_code_2 {
-            found_lg = lg;
-            break;
-        }
-    }
-    found_lg
+fn search_from_code_2(lang_code_2: &'_ str) -> Option<LanguageCode> {
+    LANGUAGES.into_iter().find(|lg| lg.1 == lang_code_2)
 }
 
 ///
 /// From the lang code returned by Tika, we find a lang code that is relevant for PGSQL
 /// We also map some languages with substitution languages (ex . créole => français)
+/// The languages without a stemmer get the "simple" configuration
 ///
 pub(crate) fn map_code(lang_code_2: &'_ str) -> &'_ str {
     match lang_code_2 {
         // Créole haïtien
         "ht" => FRENCH.1,
-        _ => search_from_code_2(lang_code_2).1,
+        _ => search_from_code_2(lang_code_2).unwrap_or(SIMPLE).1,
     }
 }
 
@@ -109,7 +107,10 @@ mod test {
         assert_eq!("it", code);
 
         let code = map_code("sw");
-        assert_eq!("en", code);
+        assert_eq!("xx", code);
+
+        let code = map_code("zh");
+        assert_eq!("simple", lang_name_from_code_2(code));
     }
 
     // #[test]
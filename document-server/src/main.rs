@@ -1,7 +1,10 @@
 use std::net::SocketAddr;
 use std::process::exit;
 
+use axum::body::Body;
 use axum::extract::{Path, Query};
+use axum::http::header::IF_MATCH;
+use axum::http::HeaderMap;
 use axum::routing::{delete, get, post};
 use axum::{Json, Router};
 use log::{error, info};
@@ -18,22 +21,58 @@ use dkconfig::property_name::{
     COMMON_EDIBLE_KEY_PROPERTY, LOG_CONFIG_FILE_PROPERTY, SERVER_PORT_PROPERTY,
 };
 use dkdto::{
-    AddItemReply, AddItemRequest, AddItemTagReply, AddItemTagRequest, AddTagReply, AddTagRequest,
-    DeleteFullTextRequest, FullTextReply, FullTextRequest, GetItemReply, GetTagReply,
-    SimpleMessage, WebType, WebTypeBuilder,
+    AddClassificationRuleReply, AddClassificationRuleRequest, AddDocumentTypeReply,
+    AddDocumentTypeRequest, AddItemCommentReply, AddItemCommentRequest, AddItemReply,
+    AddItemRequest, AddItemTagReply, AddItemTagRequest, AddItemVersionReply, AddItemVersionRequest,
+    AddRetentionRuleReply, AddRetentionRuleRequest, AddTagReply, AddTagRequest,
+    ApplyRetentionReply, ApplyRetentionRequest, BulkTagReply, BulkTagRequest, CheckOutReply,
+    CheckOutRequest, ClassifyFileReply, ClassifyFileRequest, DeleteFullTextRequest,
+    DocumentTextReply, DryRunClassificationReply, DryRunClassificationRequest, ExportCsvReply,
+    FullTextDictionaryReply, FullTextReply, FullTextSearchReply, GetClassificationRuleReply,
+    GetDestructionCertificateReply, GetDocumentTypeReply, GetEnumValuesReply, GetItemCommentReply,
+    GetItemHistoryReply, GetItemReply, GetItemVersionReply, GetRetentionRuleReply, GetTagReply,
+    GetTagValuesReply, GetTrashReply, ImportCsvReply, ImportCsvRequest, ItemFileRefsReply,
+    LegalHoldRequest, SimpleMessage, SuggestedTagsReply, TagStatsReply, TrashRetentionReply,
+    TrashRetentionRequest, UpdateEnumValuesRequest, UpdateFullTextDictionaryReply,
+    UpdateFullTextDictionaryRequest, UpdateItemCommentRequest, WebType, WebTypeBuilder,
 };
 
+use crate::classification::ClassificationDelegate;
+use crate::comment::CommentDelegate;
+use crate::document_type::DocumentTypeDelegate;
+use crate::ft_dictionary::FullTextDictionaryDelegate;
+use crate::ft_search::FullTextSearchDelegate;
 use crate::fulltext::FullTextDelegate;
 use crate::item::ItemDelegate;
+use crate::item_lock::ItemLockDelegate;
+use crate::item_version::ItemVersionDelegate;
+use crate::retention::RetentionDelegate;
 use crate::tag::TagDelegate;
+use crate::tag_suggestion::TagSuggestionDelegate;
+use crate::trash::TrashDelegate;
 
 mod char_lib;
+mod classification;
+mod comment;
+mod document_type;
 mod filter;
+mod ft_dictionary;
+mod ft_search;
 mod ft_tokenizer;
 mod fulltext;
 mod item;
+mod item_csv;
+mod item_lock;
+mod item_version;
+mod lang_detector;
 mod language;
+mod retention;
+mod scheduler;
 mod tag;
+mod tag_suggestion;
+#[cfg(test)]
+mod test_db;
+mod trash;
 
 #[derive(Serialize, Deserialize)]
 pub struct PageQuery {
@@ -90,17 +129,337 @@ pub async fn search_item(
     // WebType::from_errorset(INTERNAL_DATABASE_ERROR)
 }
 
+#[derive(Serialize, Deserialize)]
+pub struct FullTextSearchQuery {
+    pub query: String,
+    pub start_page: Option<u32>,
+    pub page_size: Option<u32>,
+}
+
+///
+/// 🌟 Find the items whose file contains all the words of the query, at page [start_page]
+///     Each hit comes with a few snippets of its text, the matched words are marked
+///     The query accepts quoted phrases and the proximity of 2 words, ex: "net income" report NEAR/5 yearly
+/// **NORM
+///
+/// #[get("/search/fulltext?<query>&<start_page>&<page_size>")]
+pub async fn search_fulltext(
+    Query(fulltext_query): Query<FullTextSearchQuery>,
+    session_token: SessionToken,
+) -> WebType<FullTextSearchReply> {
+    let delegate = FullTextSearchDelegate::new(session_token, XRequestID::from_value(None));
+    delegate
+        .search_fulltext(
+            &fulltext_query.query,
+            fulltext_query.start_page,
+            fulltext_query.page_size,
+        )
+        .await
+}
+
+///
+/// 🌟 Get the stop words and the synonym groups used by the full text search
+/// **NORM
+///
+/// #[get("/search/fulltext/dictionary")]
+pub(crate) async fn get_fulltext_dictionary(
+    session_token: SessionToken,
+) -> WebType<FullTextDictionaryReply> {
+    let delegate = FullTextDictionaryDelegate::new(session_token, XRequestID::from_value(None));
+    delegate.get_dictionary().await
+}
+
+///
+/// 🌟 Replace the stop words and the synonym groups used by the full text search
+///     The documents holding the changed stop words are indexed again
+/// **NORM
+///
+/// #[post("/search/fulltext/dictionary", format = "application/json", data = "<update_request>")]
+pub(crate) async fn update_fulltext_dictionary(
+    session_token: SessionToken,
+    update_request: Json<UpdateFullTextDictionaryRequest>,
+) -> WebType<UpdateFullTextDictionaryReply> {
+    let delegate = FullTextDictionaryDelegate::new(session_token, XRequestID::from_value(None));
+    delegate.update_dictionary(update_request).await
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ItemAsOfQuery {
+    pub as_of: Option<String>,
+}
+
 ///
 /// 🌟  Find a item from its item id
+///     With "as_of", the tag values are the ones the item held at that time
 /// **NORM
 ///
-/// #[get("/item/<item_id>")]
+/// #[get("/item/<item_id>?<as_of>")]
 pub(crate) async fn get_item(
     Path(item_id): Path<i64>,
+    Query(item_as_of_query): Query<ItemAsOfQuery>,
     session_token: SessionToken,
 ) -> WebType<GetItemReply> {
     let delegate = ItemDelegate::new(session_token, XRequestID::from_value(None));
-    delegate.get_item(item_id).await
+    delegate.get_item(item_id, item_as_of_query.as_of).await
+}
+
+///
+/// 🌟 Move an item to the trash
+/// **NORM
+///
+/// #[delete("/item/<item_id>")]
+pub(crate) async fn trash_item(
+    Path(item_id): Path<i64>,
+    session_token: SessionToken,
+) -> WebType<SimpleMessage> {
+    let delegate = TrashDelegate::new(session_token, XRequestID::from_value(None));
+    delegate.trash_item(item_id).await
+}
+
+///
+/// 🌟 Find the items in the trash at page [start_page]
+/// **NORM
+///
+/// #[get("/trash?<start_page>&<page_size>")]
+pub(crate) async fn get_trash(
+    Query(page): Query<PageQuery>,
+    session_token: SessionToken,
+) -> WebType<GetTrashReply> {
+    let delegate = TrashDelegate::new(session_token, XRequestID::from_value(None));
+    delegate.get_trash(page.start_page, page.page_size).await
+}
+
+///
+/// 🌟 Bring an item back from the trash
+/// **NORM
+///
+/// #[post("/trash/<item_id>/restore")]
+pub(crate) async fn restore_item(
+    Path(item_id): Path<i64>,
+    session_token: SessionToken,
+) -> WebType<SimpleMessage> {
+    let delegate = TrashDelegate::new(session_token, XRequestID::from_value(None));
+    delegate.restore_item(item_id).await
+}
+
+///
+/// 🌟 Delete for good an item from the trash
+/// **NORM
+///
+/// #[delete("/trash/<item_id>")]
+pub(crate) async fn purge_item(
+    Path(item_id): Path<i64>,
+    session_token: SessionToken,
+) -> WebType<SimpleMessage> {
+    let delegate = TrashDelegate::new(session_token, XRequestID::from_value(None));
+    delegate.purge_item(item_id).await
+}
+
+///
+/// 🌟 Change the number of days the items stay in the trash
+/// **NORM
+///
+/// #[post("/trash/retention", format = "application/json", data = "<retention_request>")]
+pub(crate) async fn set_trash_retention(
+    session_token: SessionToken,
+    retention_request: Json<TrashRetentionRequest>,
+) -> WebType<TrashRetentionReply> {
+    let delegate = TrashDelegate::new(session_token, XRequestID::from_value(None));
+    delegate.set_trash_retention(retention_request).await
+}
+
+///
+/// 🌟 Find all the retention rules
+/// **NORM
+///
+/// #[get("/retention_rule")]
+pub(crate) async fn get_all_retention_rule(
+    session_token: SessionToken,
+) -> WebType<GetRetentionRuleReply> {
+    let delegate = RetentionDelegate::new(session_token, XRequestID::from_value(None));
+    delegate.get_all_retention_rule().await
+}
+
+///
+/// 🌟 Create a new retention rule
+/// **NORM
+///
+/// #[post("/retention_rule", format = "application/json", data = "<add_rule_request>")]
+pub(crate) async fn add_retention_rule(
+    session_token: SessionToken,
+    add_rule_request: Json<AddRetentionRuleRequest>,
+) -> WebType<AddRetentionRuleReply> {
+    let delegate = RetentionDelegate::new(session_token, XRequestID::from_value(None));
+    delegate.add_retention_rule(add_rule_request).await
+}
+
+///
+/// 🌟 Delete a retention rule
+/// **NORM
+///
+/// #[delete("/retention_rule/<rule_id>")]
+pub(crate) async fn delete_retention_rule(
+    Path(rule_id): Path<i64>,
+    session_token: SessionToken,
+) -> WebType<SimpleMessage> {
+    let delegate = RetentionDelegate::new(session_token, XRequestID::from_value(None));
+    delegate.delete_retention_rule(rule_id).await
+}
+
+///
+/// 🌟 Put an item on legal hold or release it
+/// **NORM
+///
+/// #[post("/item/<item_id>/legal_hold", format = "application/json", data = "<legal_hold_request>")]
+pub(crate) async fn set_legal_hold(
+    Path(item_id): Path<i64>,
+    session_token: SessionToken,
+    legal_hold_request: Json<LegalHoldRequest>,
+) -> WebType<SimpleMessage> {
+    let delegate = RetentionDelegate::new(session_token, XRequestID::from_value(None));
+    delegate.set_legal_hold(item_id, legal_hold_request).await
+}
+
+///
+/// 🌟 Report or destroy the items whose retention period is over
+/// **NORM
+///
+/// #[post("/retention/apply", format = "application/json", data = "<apply_request>")]
+pub(crate) async fn apply_retention(
+    session_token: SessionToken,
+    apply_request: Json<ApplyRetentionRequest>,
+) -> WebType<ApplyRetentionReply> {
+    let delegate = RetentionDelegate::new(session_token, XRequestID::from_value(None));
+    delegate.apply_retention(apply_request).await
+}
+
+///
+/// 🌟 Find the destruction certificates at page [start_page]
+/// **NORM
+///
+/// #[get("/retention/certificate?<start_page>&<page_size>")]
+pub(crate) async fn get_destruction_certificates(
+    Query(page): Query<PageQuery>,
+    session_token: SessionToken,
+) -> WebType<GetDestructionCertificateReply> {
+    let delegate = RetentionDelegate::new(session_token, XRequestID::from_value(None));
+    delegate
+        .get_destruction_certificates(page.start_page, page.page_size)
+        .await
+}
+
+///
+/// 🌟 Find all the classification rules
+/// **NORM
+///
+/// #[get("/classification_rule")]
+pub(crate) async fn get_all_classification_rule(
+    session_token: SessionToken,
+) -> WebType<GetClassificationRuleReply> {
+    let delegate = ClassificationDelegate::new(session_token, XRequestID::from_value(None));
+    delegate.get_all_classification_rule().await
+}
+
+///
+/// 🌟 Create a new classification rule
+/// **NORM
+///
+/// #[post("/classification_rule", format = "application/json", data = "<add_rule_request>")]
+pub(crate) async fn add_classification_rule(
+    session_token: SessionToken,
+    add_rule_request: Json<AddClassificationRuleRequest>,
+) -> WebType<AddClassificationRuleReply> {
+    let delegate = ClassificationDelegate::new(session_token, XRequestID::from_value(None));
+    delegate.add_classification_rule(add_rule_request).await
+}
+
+///
+/// 🌟 Delete a classification rule
+/// **NORM
+///
+/// #[delete("/classification_rule/<rule_id>")]
+pub(crate) async fn delete_classification_rule(
+    Path(rule_id): Path<i64>,
+    session_token: SessionToken,
+) -> WebType<SimpleMessage> {
+    let delegate = ClassificationDelegate::new(session_token, XRequestID::from_value(None));
+    delegate.delete_classification_rule(rule_id).await
+}
+
+///
+/// 🌟 Test a classification condition on the most recent documents
+/// **NORM
+///
+/// #[post("/classification_rule/dry_run", format = "application/json", data = "<dry_run_request>")]
+pub(crate) async fn dry_run_classification(
+    session_token: SessionToken,
+    dry_run_request: Json<DryRunClassificationRequest>,
+) -> WebType<DryRunClassificationReply> {
+    let delegate = ClassificationDelegate::new(session_token, XRequestID::from_value(None));
+    delegate.dry_run_classification(dry_run_request).await
+}
+
+///
+/// 🌟 Set the tag values of the items from a csv sheet
+///     The rows are matched to the items by name or file_ref
+/// **NORM
+///
+/// #[post("/item/import_csv", format = "application/json", data = "<import_request>")]
+pub(crate) async fn import_csv(
+    session_token: SessionToken,
+    import_request: Json<ImportCsvRequest>,
+) -> WebType<ImportCsvReply> {
+    let delegate = ItemDelegate::new(session_token, XRequestID::from_value(None));
+    delegate.import_csv(import_request).await
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ExportCsvQuery {
+    pub filters: Option<String>,
+}
+
+///
+/// 🌟 Write the items matching the filter as csv
+/// **NORM
+///
+/// #[get("/item/export_csv?<filters>")]
+pub(crate) async fn export_csv(
+    session_token: SessionToken,
+    Query(export_query): Query<ExportCsvQuery>,
+) -> WebType<ExportCsvReply> {
+    let delegate = ItemDelegate::new(session_token, XRequestID::from_value(None));
+    delegate.export_csv(export_query.filters).await
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct FileRefsQuery {
+    pub filters: Option<String>,
+}
+
+///
+/// 🌟 File references of the items matching the filter
+/// **NORM
+///
+/// #[get("/item/file_refs?<filters>")]
+pub(crate) async fn search_file_refs(
+    session_token: SessionToken,
+    Query(file_refs_query): Query<FileRefsQuery>,
+) -> WebType<ItemFileRefsReply> {
+    let delegate = ItemDelegate::new(session_token, XRequestID::from_value(None));
+    delegate.search_file_refs(file_refs_query.filters).await
+}
+
+///
+/// 🌟  Changes of the tag values of an item, oldest first
+/// **NORM
+///
+/// #[get("/item/<item_id>/history")]
+pub(crate) async fn get_item_history(
+    Path(item_id): Path<i64>,
+    session_token: SessionToken,
+) -> WebType<GetItemHistoryReply> {
+    let delegate = ItemDelegate::new(session_token, XRequestID::from_value(None));
+    delegate.get_item_history(item_id).await
 }
 
 ///
@@ -120,6 +479,7 @@ pub(crate) async fn add_item(
 ///
 /// 🌟 Update tags on an existing item
 ///     Tags can be already existing in the system.
+///     With an If-Match version, the item must not have changed since
 ///
 /// ```
 /// #[post(
@@ -131,33 +491,234 @@ pub(crate) async fn add_item(
 pub(crate) async fn update_item_tag(
     session_token: SessionToken,
     Path(item_id): Path<i64>,
+    headers: HeaderMap,
     add_item_tag_request: Json<AddItemTagRequest>,
 ) -> WebType<AddItemTagReply> {
     let delegate = ItemDelegate::new(session_token, XRequestID::from_value(None));
     delegate
-        .update_item_tag(item_id, add_item_tag_request)
+        .update_item_tag(item_id, if_match(&headers), add_item_tag_request)
         .await
 }
 
+///
+/// 🌟 Add, change and delete tags on all the items matching a filter
+///     With dry_run, only count the items
+/// **NORM
+///
+/// #[post("/item/bulk_tag", format = "application/json", data = "<bulk_tag_request>")]
+pub(crate) async fn bulk_tag(
+    session_token: SessionToken,
+    bulk_tag_request: Json<BulkTagRequest>,
+) -> WebType<BulkTagReply> {
+    let delegate = ItemDelegate::new(session_token, XRequestID::from_value(None));
+    delegate.bulk_tag(bulk_tag_request).await
+}
+
 #[derive(Serialize, Deserialize)]
 pub struct DeleteTagsQuery {
-    pub names: Vec<String>,
+    pub tag_names: Option<String>, // Comma separated, the tags lose all their values
+    pub value_ids: Option<String>, // Comma separated tag value ids, a single value is removed
 }
 
 ///
-/// 🌟 Update tags on an existing item
-///     Tags can be already existing in the system.
+/// 🌟 Delete tags or single tag values on an existing item
 ///
-///  DELETE /api/documents/{item_id}/tags?tag_names=tag1,tag2,tag3
+///  DELETE /api/documents/{item_id}/tags?tag_names=tag1,tag2&value_ids=12,13
 ///
-/// #[delete("/item/<item_id>/tags?<tag_names>")]
+/// #[delete("/item/<item_id>/tags?<tag_names>&<value_ids>")]
 pub(crate) async fn delete_item_tag(
     session_token: SessionToken,
     Path(item_id): Path<i64>,
-    Query(tag_names): Query<DeleteTagsQuery>,
+    headers: HeaderMap,
+    Query(delete_tags_query): Query<DeleteTagsQuery>,
 ) -> WebType<SimpleMessage> {
     let delegate = ItemDelegate::new(session_token, XRequestID::from_value(None));
-    delegate.delete_item_tag(item_id, tag_names.names).await
+    delegate
+        .delete_item_tag(
+            item_id,
+            if_match(&headers),
+            delete_tags_query.tag_names.as_deref(),
+            delete_tags_query.value_ids.as_deref(),
+        )
+        .await
+}
+
+///
+/// 🌟 Find all the versions of an item
+/// **NORM
+///
+/// #[get("/item/<item_id>/version")]
+pub(crate) async fn get_item_versions(
+    Path(item_id): Path<i64>,
+    session_token: SessionToken,
+) -> WebType<GetItemVersionReply> {
+    let delegate = ItemVersionDelegate::new(session_token, XRequestID::from_value(None));
+    delegate.get_item_versions(item_id).await
+}
+
+///
+/// 🌟 Add a new version to an item, with a file already uploaded on the file server
+///     With an If-Match version, the item must not have changed since
+/// **NORM
+///
+/// #[post("/item/<item_id>/version", format = "application/json", data = "<add_version_request>")]
+pub(crate) async fn add_item_version(
+    Path(item_id): Path<i64>,
+    session_token: SessionToken,
+    headers: HeaderMap,
+    add_version_request: Json<AddItemVersionRequest>,
+) -> WebType<AddItemVersionReply> {
+    let delegate = ItemVersionDelegate::new(session_token, XRequestID::from_value(None));
+    delegate
+        .add_item_version(item_id, if_match(&headers), add_version_request)
+        .await
+}
+
+///
+/// 🌟 Make a previous version the current version of the item
+/// **NORM
+///
+/// #[post("/item/<item_id>/version/<version_no>/rollback")]
+pub(crate) async fn rollback_item_version(
+    Path((item_id, version_no)): Path<(i64, i32)>,
+    session_token: SessionToken,
+    headers: HeaderMap,
+) -> WebType<SimpleMessage> {
+    let delegate = ItemVersionDelegate::new(session_token, XRequestID::from_value(None));
+    delegate
+        .rollback_item_version(item_id, version_no, if_match(&headers))
+        .await
+}
+
+///
+/// 🌟 Find all the comments of an item
+/// **NORM
+///
+/// #[get("/item/<item_id>/comment")]
+pub(crate) async fn get_item_comments(
+    Path(item_id): Path<i64>,
+    session_token: SessionToken,
+) -> WebType<GetItemCommentReply> {
+    let delegate = CommentDelegate::new(session_token, XRequestID::from_value(None));
+    delegate.get_item_comments(item_id).await
+}
+
+///
+/// 🌟 Comment an item or reply to one of its comments
+/// **NORM
+///
+/// #[post("/item/<item_id>/comment", format = "application/json", data = "<add_comment_request>")]
+pub(crate) async fn add_item_comment(
+    Path(item_id): Path<i64>,
+    session_token: SessionToken,
+    add_comment_request: Json<AddItemCommentRequest>,
+) -> WebType<AddItemCommentReply> {
+    let delegate = CommentDelegate::new(session_token, XRequestID::from_value(None));
+    delegate
+        .add_item_comment(item_id, add_comment_request)
+        .await
+}
+
+///
+/// 🌟 Change the text of a comment, only for its author
+/// **NORM
+///
+/// #[post("/item/<item_id>/comment/<comment_id>", format = "application/json", data = "<update_comment_request>")]
+pub(crate) async fn update_item_comment(
+    Path((item_id, comment_id)): Path<(i64, i64)>,
+    session_token: SessionToken,
+    update_comment_request: Json<UpdateItemCommentRequest>,
+) -> WebType<SimpleMessage> {
+    let delegate = CommentDelegate::new(session_token, XRequestID::from_value(None));
+    delegate
+        .update_item_comment(item_id, comment_id, update_comment_request)
+        .await
+}
+
+///
+/// 🌟 Delete a comment, only for its author
+/// **NORM
+///
+/// #[delete("/item/<item_id>/comment/<comment_id>")]
+pub(crate) async fn delete_item_comment(
+    Path((item_id, comment_id)): Path<(i64, i64)>,
+    session_token: SessionToken,
+) -> WebType<SimpleMessage> {
+    let delegate = CommentDelegate::new(session_token, XRequestID::from_value(None));
+    delegate.delete_item_comment(item_id, comment_id).await
+}
+
+///
+/// 🌟 Clear text extracted from the current file of an item
+/// **NORM
+///
+/// #[get("/item/<item_id>/text")]
+pub(crate) async fn get_item_text(
+    Path(item_id): Path<i64>,
+    session_token: SessionToken,
+) -> WebType<DocumentTextReply> {
+    let delegate = FullTextDelegate::new(session_token, XRequestID::from_value(None));
+    delegate.get_item_text(item_id).await
+}
+
+///
+/// 🌟 Tag values matching the keywords of the document of an item
+/// **NORM
+///
+/// #[get("/item/<item_id>/suggested-tags")]
+pub(crate) async fn get_suggested_tags(
+    Path(item_id): Path<i64>,
+    session_token: SessionToken,
+) -> WebType<SuggestedTagsReply> {
+    let delegate = TagSuggestionDelegate::new(session_token, XRequestID::from_value(None));
+    delegate.get_suggested_tags(item_id).await
+}
+
+///
+/// 🌟 Clear text extracted from a file
+/// **NORM
+///
+/// #[get("/file/<file_ref>/text")]
+pub(crate) async fn get_file_text(
+    Path(file_ref): Path<String>,
+    session_token: SessionToken,
+) -> WebType<DocumentTextReply> {
+    let delegate = FullTextDelegate::new(session_token, XRequestID::from_value(None));
+    delegate.get_file_text(&file_ref).await
+}
+
+/// The If-Match header, a value that is not text is kept to be refused later
+fn if_match(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get(IF_MATCH)
+        .map(|value| value.to_str().unwrap_or_default())
+}
+
+///
+/// 🌟 Check out an item, the other sessions cannot change it until the check-in
+/// **NORM
+///
+/// #[post("/item/<item_id>/checkout", format = "application/json", data = "<check_out_request>")]
+pub(crate) async fn check_out_item(
+    Path(item_id): Path<i64>,
+    session_token: SessionToken,
+    check_out_request: Json<CheckOutRequest>,
+) -> WebType<CheckOutReply> {
+    let delegate = ItemLockDelegate::new(session_token, XRequestID::from_value(None));
+    delegate.check_out(item_id, check_out_request).await
+}
+
+///
+/// 🌟 Check in an item
+/// **NORM
+///
+/// #[post("/item/<item_id>/checkin")]
+pub(crate) async fn check_in_item(
+    Path(item_id): Path<i64>,
+    session_token: SessionToken,
+) -> WebType<SimpleMessage> {
+    let delegate = ItemLockDelegate::new(session_token, XRequestID::from_value(None));
+    delegate.check_in(item_id).await
 }
 
 type Type = GetTagReply;
@@ -201,26 +762,145 @@ pub(crate) async fn add_tag(
     delegate.add_tag(add_tag_request).await
 }
 
+#[derive(Serialize, Deserialize)]
+pub struct EnumValuesQuery {
+    pub prefix: Option<String>,
+}
+
+///
+/// 🌟 Get the allowed values of an enum tag
+///     Used for the autocompletion of the filters
+/// **NORM
+///
+/// #[get("/tag/<tag_id>/enum?<prefix>")]
+pub(crate) async fn get_enum_values(
+    session_token: SessionToken,
+    Path(tag_id): Path<i64>,
+    Query(enum_values_query): Query<EnumValuesQuery>,
+) -> WebType<GetEnumValuesReply> {
+    let delegate = TagDelegate::new(session_token, XRequestID::from_value(None));
+    delegate
+        .get_enum_values(tag_id, enum_values_query.prefix)
+        .await
+}
+
+///
+/// 🌟 Usage statistics of a tag
+/// **NORM
+///
+/// #[get("/tag/<tag_id>/stats")]
+pub(crate) async fn get_tag_stats(
+    session_token: SessionToken,
+    Path(tag_id): Path<i64>,
+) -> WebType<TagStatsReply> {
+    let delegate = TagDelegate::new(session_token, XRequestID::from_value(None));
+    delegate.get_tag_stats(tag_id).await
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct TagValuesQuery {
+    pub prefix: Option<String>,
+    pub start_page: Option<u32>,
+    pub page_size: Option<u32>,
+}
+
+///
+/// 🌟 Distinct values of a tag with their item count
+///     Used for the value pickers
+/// **NORM
+///
+/// #[get("/tag/<tag_id>/values?<prefix>&<start_page>&<page_size>")]
+pub(crate) async fn get_tag_values(
+    session_token: SessionToken,
+    Path(tag_id): Path<i64>,
+    Query(tag_values_query): Query<TagValuesQuery>,
+) -> WebType<GetTagValuesReply> {
+    let delegate = TagDelegate::new(session_token, XRequestID::from_value(None));
+    delegate
+        .get_tag_values(
+            tag_id,
+            tag_values_query.prefix,
+            tag_values_query.start_page,
+            tag_values_query.page_size,
+        )
+        .await
+}
+
+///
+/// 🌟 Change the allowed values of an enum tag
+/// **NORM
+///
+/// #[post("/tag/<tag_id>/enum", format = "application/json", data = "<update_request>")]
+pub(crate) async fn update_enum_values(
+    session_token: SessionToken,
+    Path(tag_id): Path<i64>,
+    update_request: Json<UpdateEnumValuesRequest>,
+) -> WebType<SimpleMessage> {
+    let delegate = TagDelegate::new(session_token, XRequestID::from_value(None));
+    delegate.update_enum_values(tag_id, update_request).await
+}
+
+///
+/// 🌟 Find all the existing document types by pages
+/// **NORM
+///
+/// #[get("/document_type?<start_page>&<page_size>")]
+pub(crate) async fn get_all_document_type(
+    Query(page): Query<PageQuery>,
+    session_token: SessionToken,
+) -> WebType<GetDocumentTypeReply> {
+    let delegate = DocumentTypeDelegate::new(session_token, XRequestID::from_value(None));
+    delegate
+        .get_all_document_type(page.start_page, page.page_size)
+        .await
+}
+
+///
+/// 🌟 Create a new document type
+/// **NORM
+///
+/// #[post("/document_type", format = "application/json", data = "<add_document_type_request>")]
+pub(crate) async fn add_document_type(
+    session_token: SessionToken,
+    add_document_type_request: Json<AddDocumentTypeRequest>,
+) -> WebType<AddDocumentTypeReply> {
+    let delegate = DocumentTypeDelegate::new(session_token, XRequestID::from_value(None));
+    delegate.add_document_type(add_document_type_request).await
+}
+
+///
+/// 🌟 Delete a document type
+/// **NORM
+///
+/// #[delete("/document_type/<document_type_id>")]
+pub(crate) async fn delete_document_type(
+    session_token: SessionToken,
+    Path(document_type_id): Path<i64>,
+) -> WebType<SimpleMessage> {
+    let delegate = DocumentTypeDelegate::new(session_token, XRequestID::from_value(None));
+    delegate.delete_document_type(document_type_id).await
+}
+
 ///
 /// 🌟 Parse the raw text data and create the document parts
 /// Used from file-server
+/// The body is streamed as NDJSON : a FullTextStreamHeader line, then FullTextChunk lines
 /// **NORM
 ///
 /// ```
 /// #[post(
 ///    "/fulltext_indexing",
-///    format = "application/json",
-///    data = "<raw_text_request>"
+///    format = "application/x-ndjson",
+///    data = "<body>"
 /// )]
 /// ```
 pub(crate) async fn fulltext_indexing(
     session_token: SessionToken,
     x_request_id: XRequestID,
-    raw_text_request: Json<FullTextRequest>,
+    body: Body,
 ) -> WebType<FullTextReply> {
-    log_info!(">>> Hey!!!");
     let delegate = FullTextDelegate::new(session_token, x_request_id);
-    delegate.fulltext_indexing(raw_text_request).await
+    delegate.fulltext_indexing(body).await
 }
 
 /// 🌟 Delete the information linked to the document full text indexing information
@@ -242,6 +922,25 @@ pub(crate) async fn delete_text_indexing(
     delegate.delete_text_indexing(delete_text_request).await
 }
 
+/// 🌟 Apply the classification rules to a file whose text is indexed
+/// Used from file-server
+/// **NORM
+///
+/// ```
+/// #[post(
+///    "/classify_file",
+///    format = "application/json",
+///    data = "<classify_request>"
+/// )]
+pub(crate) async fn classify_file(
+    session_token: SessionToken,
+    x_request_id: XRequestID,
+    classify_request: Json<ClassifyFileRequest>,
+) -> WebType<ClassifyFileReply> {
+    let delegate = ClassificationDelegate::new(session_token, x_request_id);
+    delegate.classify_file(classify_request).await
+}
+
 #[tokio::main]
 async fn main() {
     const PROGRAM_NAME: &str = "Document Server";
@@ -316,6 +1015,8 @@ async fn main() {
 
     let _ = init_db_pool_async(&connect_string, db_pool_size).await;
 
+    scheduler::start_scheduler();
+
     log_info!("🚀 Start {} on port {}", PROGRAM_NAME, port);
 
     // Build our application with some routes
@@ -324,15 +1025,76 @@ async fn main() {
     let key_routes = Router::new()
         .route("/item", get(get_all_item))
         .route("/search", get(search_item))
+        .route("/search/fulltext", get(search_fulltext))
+        .route("/search/fulltext/dictionary", get(get_fulltext_dictionary))
+        .route(
+            "/search/fulltext/dictionary",
+            post(update_fulltext_dictionary),
+        )
         .route("/item/:item_id", get(get_item))
+        .route("/item/:item_id", delete(trash_item))
+        .route("/item/:item_id/history", get(get_item_history))
         .route("/item", post(add_item))
         .route("/item/:item_id/tags", post(update_item_tag))
         .route("/item/:item_id/tags", delete(delete_item_tag))
+        .route("/item/bulk_tag", post(bulk_tag))
+        .route("/item/import_csv", post(import_csv))
+        .route("/item/export_csv", get(export_csv))
+        .route("/item/file_refs", get(search_file_refs))
+        .route("/trash", get(get_trash))
+        .route("/trash/retention", post(set_trash_retention))
+        .route("/trash/:item_id/restore", post(restore_item))
+        .route("/trash/:item_id", delete(purge_item))
+        .route("/item/:item_id/legal_hold", post(set_legal_hold))
+        .route("/item/:item_id/checkout", post(check_out_item))
+        .route("/item/:item_id/checkin", post(check_in_item))
+        .route("/item/:item_id/version", get(get_item_versions))
+        .route("/item/:item_id/version", post(add_item_version))
+        .route(
+            "/item/:item_id/version/:version_no/rollback",
+            post(rollback_item_version),
+        )
+        .route("/item/:item_id/text", get(get_item_text))
+        .route("/item/:item_id/suggested-tags", get(get_suggested_tags))
+        .route("/file/:file_ref/text", get(get_file_text))
+        .route("/item/:item_id/comment", get(get_item_comments))
+        .route("/item/:item_id/comment", post(add_item_comment))
+        .route(
+            "/item/:item_id/comment/:comment_id",
+            post(update_item_comment),
+        )
+        .route(
+            "/item/:item_id/comment/:comment_id",
+            delete(delete_item_comment),
+        )
+        .route("/retention_rule", get(get_all_retention_rule))
+        .route("/retention_rule", post(add_retention_rule))
+        .route("/retention_rule/:rule_id", delete(delete_retention_rule))
+        .route("/retention/apply", post(apply_retention))
+        .route("/retention/certificate", get(get_destruction_certificates))
+        .route("/classification_rule", get(get_all_classification_rule))
+        .route("/classification_rule", post(add_classification_rule))
+        .route(
+            "/classification_rule/:rule_id",
+            delete(delete_classification_rule),
+        )
+        .route("/classification_rule/dry_run", post(dry_run_classification))
         .route("/tag", get(get_all_tag))
         .route("/tag", post(add_tag))
         .route("/tag/:tag_id", delete(delete_tag))
+        .route("/tag/:tag_id/enum", get(get_enum_values))
+        .route("/tag/:tag_id/enum", post(update_enum_values))
+        .route("/tag/:tag_id/stats", get(get_tag_stats))
+        .route("/tag/:tag_id/values", get(get_tag_values))
+        .route("/document_type", get(get_all_document_type))
+        .route("/document_type", post(add_document_type))
+        .route(
+            "/document_type/:document_type_id",
+            delete(delete_document_type),
+        )
         .route("/fulltext_indexing", post(fulltext_indexing))
-        .route("/delete_text_indexing", post(delete_text_indexing));
+        .route("/delete_text_indexing", post(delete_text_indexing))
+        .route("/classify_file", post(classify_file));
 
     let app = Router::new().nest(&base_url, key_routes);
 
@@ -0,0 +1,1173 @@
+use std::collections::{HashMap, HashSet};
+use std::time::SystemTime;
+
+use anyhow::anyhow;
+use axum::http::StatusCode;
+use axum::Json;
+use log::{error, info, warn};
+use serde::de::DeserializeOwned;
+
+use commons_error::*;
+use commons_pg::sql_transaction::{date_time_to_iso, CellValue, SQLDataSet};
+use commons_pg::sql_transaction_async::{
+    SQLChangeAsync, SQLConnectionAsync, SQLQueryBlockAsync, SQLTransactionAsync,
+};
+use commons_services::session_lib::valid_sid_get_session;
+use commons_services::token_lib::SessionToken;
+use commons_services::try_or_return;
+use commons_services::x_request_id::{Follower, XRequestID};
+use dkdto::error_codes::{
+    INCORRECT_RETENTION_RULE, INTERNAL_DATABASE_ERROR, MISSING_ITEM, MISSING_RETENTION_RULE,
+    RETENTION_RULE_NAME_ALREADY_TAKEN,
+};
+use dkdto::{
+    AddRetentionRuleReply, AddRetentionRuleRequest, ApplyRetentionReply, ApplyRetentionRequest,
+    DestructionCertificateElement, ErrorSet, ExpiredItemElement, GetDestructionCertificateReply,
+    GetRetentionRuleReply, LegalHoldRequest, RetentionRuleElement, SimpleMessage, TagType, WebType,
+    WebTypeBuilder,
+};
+use doka_cli::request_client::TokenType;
+
+use crate::item::ItemDelegate;
+use crate::tag::TagDelegate;
+use crate::trash::TrashDelegate;
+
+/// Retention start meaning the creation date of the item
+const START_FROM_CREATED: &str = "@created";
+
+/// An expired item, with the rule that made it expire
+struct ExpiredItem {
+    element: ExpiredItemElement,
+    expired_gmt: SystemTime,
+}
+
+///
+/// Retention rules and legal hold
+/// The retention is applied periodically by the scheduler of the document server,
+/// it can also be applied on demand with "doka-cli retention apply".
+///
+pub(crate) struct RetentionDelegate {
+    pub session_token: SessionToken,
+    pub follower: Follower,
+}
+
+impl RetentionDelegate {
+    pub fn new(session_token: SessionToken, x_request_id: XRequestID) -> Self {
+        Self {
+            session_token,
+            follower: Follower {
+                x_request_id: x_request_id.new_if_null(),
+                token_type: TokenType::None,
+            },
+        }
+    }
+
+    ///
+    /// 🌟 Find all the retention rules
+    ///
+    pub async fn get_all_retention_rule(mut self) -> WebType<GetRetentionRuleReply> {
+        log_info!(
+            "🚀 Start get_all_retention_rule api, follower=[{}]",
+            &self.follower
+        );
+
+        let entry_session = try_or_return!(
+            valid_sid_get_session(&self.session_token, &mut self.follower).await,
+            Self::web_type_error()
+        );
+
+        let customer_code = entry_session.customer_code.as_str();
+
+        let Ok(mut cnx) = SQLConnectionAsync::from_pool().await.map_err(err_fwd!(
+            "💣 New Db connection failed, follower=[{}]",
+            &self.follower
+        )) else {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        };
+
+        let Ok(mut trans) = cnx.begin().await.map_err(err_fwd!(
+            "💣 Transaction issue, follower=[{}]",
+            &self.follower
+        )) else {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        };
+
+        let Ok(rules) = self
+            .search_retention_rules(&mut trans, None, customer_code)
+            .await
+            .map_err(err_fwd!(
+                "💣 Cannot find the retention rules, follower=[{}]",
+                &self.follower
+            ))
+        else {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        };
+
+        if trans
+            .commit()
+            .await
+            .map_err(err_fwd!("💣 Commit failed, follower=[{}]", &self.follower))
+            .is_err()
+        {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        }
+
+        log_info!(
+            "🏁 End get_all_retention_rule api, follower=[{}]",
+            &self.follower
+        );
+
+        WebType::from_item(StatusCode::OK.as_u16(), GetRetentionRuleReply { rules })
+    }
+
+    ///
+    /// 🌟 Create a new retention rule
+    ///
+    pub async fn add_retention_rule(
+        mut self,
+        add_rule_request: Json<AddRetentionRuleRequest>,
+    ) -> WebType<AddRetentionRuleReply> {
+        log_info!(
+            "🚀 Start add_retention_rule api, add_rule_request=[{:?}], follower=[{}]",
+            &add_rule_request,
+            &self.follower
+        );
+
+        let entry_session = try_or_return!(
+            valid_sid_get_session(&self.session_token, &mut self.follower).await,
+            Self::web_type_error()
+        );
+
+        let customer_code = entry_session.customer_code.as_str();
+
+        let name = add_rule_request.name.trim();
+        if name.is_empty() || name.len() > 50 {
+            log_error!(
+                "💣 Incorrect retention rule name, name=[{}], follower=[{}]",
+                name,
+                &self.follower
+            );
+            return WebType::from_errorset(&INCORRECT_RETENTION_RULE);
+        }
+
+        let Some(period) = parse_period(&add_rule_request.period) else {
+            log_error!(
+                "💣 Incorrect retention period, period=[{}], follower=[{}]",
+                &add_rule_request.period,
+                &self.follower
+            );
+            return WebType::from_simple(
+                StatusCode::BAD_REQUEST.as_u16(),
+                SimpleMessage {
+                    message: format!(
+                        "Incorrect retention period [{}], expected a number of years, months or days, ex: \"10 years\"",
+                        &add_rule_request.period
+                    ),
+                },
+            );
+        };
+
+        let Ok(mut cnx) = SQLConnectionAsync::from_pool().await.map_err(err_fwd!(
+            "💣 New Db connection failed, follower=[{}]",
+            &self.follower
+        )) else {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        };
+
+        let Ok(mut trans) = cnx.begin().await.map_err(err_fwd!(
+            "💣 Transaction issue, follower=[{}]",
+            &self.follower
+        )) else {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        };
+
+        let Ok(existing_rules) = self
+            .search_retention_rules(&mut trans, Some(name), customer_code)
+            .await
+            .map_err(err_fwd!(
+                "💣 Cannot read the retention rules, follower=[{}]",
+                &self.follower
+            ))
+        else {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        };
+
+        if !existing_rules.is_empty() {
+            log_error!(
+                "💣 The retention rule already exists, name=[{}], follower=[{}]",
+                name,
+                &self.follower
+            );
+            return WebType::from_errorset(&RETENTION_RULE_NAME_ALREADY_TAKEN);
+        }
+
+        // The filter must be valid now, not only when the retention is applied
+        let item_delegate = ItemDelegate::new(
+            self.session_token.clone(),
+            self.follower.x_request_id,
+        );
+        if let Err(e) = item_delegate
+            .build_filter_condition(&mut trans, Some(&add_rule_request.filters), customer_code)
+            .await
+        {
+            return WebType::from_errorset(e);
+        }
+
+        let start_from = add_rule_request.start_from.trim();
+        if start_from != START_FROM_CREATED {
+            if let Err(message) = self
+                .check_date_tag(&mut trans, start_from, customer_code)
+                .await
+            {
+                log_error!(
+                    "💣 Incorrect retention start, start_from=[{}], follower=[{}]",
+                    start_from,
+                    &self.follower
+                );
+                return WebType::from_simple(
+                    StatusCode::BAD_REQUEST.as_u16(),
+                    SimpleMessage { message },
+                );
+            }
+        }
+
+        let sql_query = format!(
+            r"INSERT INTO cs_{}.retention_rule (name, filters, start_from, period)
+                    VALUES (:p_name, :p_filters, :p_start_from, :p_period)",
+            customer_code
+        );
+
+        let mut params = HashMap::new();
+        params.insert("p_name".to_string(), CellValue::from_raw_str(name));
+        params.insert(
+            "p_filters".to_string(),
+            CellValue::from_raw_str(add_rule_request.filters.trim()),
+        );
+        params.insert(
+            "p_start_from".to_string(),
+            CellValue::from_raw_str(start_from),
+        );
+        params.insert("p_period".to_string(), CellValue::from_raw_string(period));
+
+        let sql_insert = SQLChangeAsync {
+            sql_query,
+            params,
+            sequence_name: format!("cs_{}.retention_rule_id_seq", customer_code),
+        };
+
+        let Ok(rule_id) = sql_insert.insert(&mut trans).await.map_err(err_fwd!(
+            "💣 Insertion of a new retention rule failed, follower=[{}]",
+            &self.follower
+        )) else {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        };
+
+        if trans
+            .commit()
+            .await
+            .map_err(err_fwd!("💣 Commit failed, follower=[{}]", &self.follower))
+            .is_err()
+        {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        }
+
+        log_info!(
+            "😎 The retention rule has been created, rule_id=[{}], follower=[{}]",
+            rule_id,
+            &self.follower
+        );
+        log_info!(
+            "🏁 End add_retention_rule api, follower=[{}]",
+            &self.follower
+        );
+
+        WebType::from_item(StatusCode::OK.as_u16(), AddRetentionRuleReply { rule_id })
+    }
+
+    ///
+    /// 🌟 Delete a retention rule, the certificates of the items it destroyed are kept
+    ///
+    pub async fn delete_retention_rule(mut self, rule_id: i64) -> WebType<SimpleMessage> {
+        log_info!(
+            "🚀 Start delete_retention_rule api, rule_id=[{}], follower=[{}]",
+            rule_id,
+            &self.follower
+        );
+
+        let entry_session = try_or_return!(
+            valid_sid_get_session(&self.session_token, &mut self.follower).await,
+            Self::web_type_error()
+        );
+
+        let customer_code = entry_session.customer_code.as_str();
+
+        let Ok(mut cnx) = SQLConnectionAsync::from_pool().await.map_err(err_fwd!(
+            "💣 New Db connection failed, follower=[{}]",
+            &self.follower
+        )) else {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        };
+
+        let Ok(mut trans) = cnx.begin().await.map_err(err_fwd!(
+            "💣 Transaction issue, follower=[{}]",
+            &self.follower
+        )) else {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        };
+
+        let Ok(rules) = self
+            .search_retention_rules(&mut trans, None, customer_code)
+            .await
+            .map_err(err_fwd!(
+                "💣 Cannot read the retention rules, follower=[{}]",
+                &self.follower
+            ))
+        else {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        };
+
+        if !rules.iter().any(|rule| rule.rule_id == rule_id) {
+            log_error!(
+                "💣 Missing retention rule, rule_id=[{}], follower=[{}]",
+                rule_id,
+                &self.follower
+            );
+            return WebType::from_errorset(&MISSING_RETENTION_RULE);
+        }
+
+        let mut params = HashMap::new();
+        params.insert("p_rule_id".to_string(), CellValue::from_raw_int(rule_id));
+
+        let sql_delete = SQLChangeAsync {
+            sql_query: format!(
+                "DELETE FROM cs_{}.retention_rule WHERE id = :p_rule_id",
+                customer_code
+            ),
+            params,
+            sequence_name: "".to_string(),
+        };
+
+        if sql_delete
+            .delete(&mut trans)
+            .await
+            .map_err(err_fwd!(
+                "💣 Cannot delete the retention rule, follower=[{}]",
+                &self.follower
+            ))
+            .is_err()
+        {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        }
+
+        if trans
+            .commit()
+            .await
+            .map_err(err_fwd!("💣 Commit failed, follower=[{}]", &self.follower))
+            .is_err()
+        {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        }
+
+        log_info!(
+            "🏁 End delete_retention_rule api, follower=[{}]",
+            &self.follower
+        );
+
+        WebType::from_item(
+            StatusCode::OK.as_u16(),
+            SimpleMessage {
+                message: "Ok".to_string(),
+            },
+        )
+    }
+
+    ///
+    /// 🌟 Put an item on legal hold or release it
+    ///     An item on legal hold can be neither trashed, purged nor destroyed by a retention rule
+    ///
+    pub async fn set_legal_hold(
+        mut self,
+        item_id: i64,
+        legal_hold_request: Json<LegalHoldRequest>,
+    ) -> WebType<SimpleMessage> {
+        log_info!(
+            "🚀 Start set_legal_hold api, item_id=[{}], legal_hold_request=[{:?}], follower=[{}]",
+            item_id,
+            &legal_hold_request,
+            &self.follower
+        );
+
+        let entry_session = try_or_return!(
+            valid_sid_get_session(&self.session_token, &mut self.follower).await,
+            Self::web_type_error()
+        );
+
+        let customer_code = entry_session.customer_code.as_str();
+
+        let Ok(mut cnx) = SQLConnectionAsync::from_pool().await.map_err(err_fwd!(
+            "💣 New Db connection failed, follower=[{}]",
+            &self.follower
+        )) else {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        };
+
+        let Ok(mut trans) = cnx.begin().await.map_err(err_fwd!(
+            "💣 Transaction issue, follower=[{}]",
+            &self.follower
+        )) else {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        };
+
+        let Ok(exists) = self
+            .is_item_existing(&mut trans, item_id, customer_code)
+            .await
+            .map_err(err_fwd!(
+                "💣 Cannot read the item, follower=[{}]",
+                &self.follower
+            ))
+        else {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        };
+
+        if !exists {
+            log_error!(
+                "💣 Missing item, item_id=[{}], follower=[{}]",
+                item_id,
+                &self.follower
+            );
+            return WebType::from_errorset(&MISSING_ITEM);
+        }
+
+        let mut params = HashMap::new();
+        params.insert(
+            "p_legal_hold".to_string(),
+            CellValue::from_raw_bool(legal_hold_request.legal_hold),
+        );
+        params.insert("p_item_id".to_string(), CellValue::from_raw_int(item_id));
+
+        let sql_update = SQLChangeAsync {
+            sql_query: format!(
+                "UPDATE cs_{}.item SET legal_hold = :p_legal_hold WHERE id = :p_item_id",
+                customer_code
+            ),
+            params,
+            sequence_name: "".to_string(),
+        };
+
+        if sql_update
+            .update(&mut trans)
+            .await
+            .map_err(err_fwd!(
+                "💣 Cannot change the legal hold of the item, follower=[{}]",
+                &self.follower
+            ))
+            .is_err()
+        {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        }
+
+        if trans
+            .commit()
+            .await
+            .map_err(err_fwd!("💣 Commit failed, follower=[{}]", &self.follower))
+            .is_err()
+        {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        }
+
+        log_info!(
+            "😎 Legal hold changed, item_id=[{}], legal_hold=[{}], follower=[{}]",
+            item_id,
+            legal_hold_request.legal_hold,
+            &self.follower
+        );
+        log_info!("🏁 End set_legal_hold api, follower=[{}]", &self.follower);
+
+        WebType::from_item(
+            StatusCode::OK.as_u16(),
+            SimpleMessage {
+                message: "Ok".to_string(),
+            },
+        )
+    }
+
+    ///
+    /// 🌟 Find the items whose retention period is over and destroy them
+    ///     Each destroyed item gets a destruction certificate.
+    ///     With [dry_run], the default, the expired items are only reported.
+    ///
+    pub async fn apply_retention(
+        mut self,
+        apply_request: Json<ApplyRetentionRequest>,
+    ) -> WebType<ApplyRetentionReply> {
+        log_info!(
+            "🚀 Start apply_retention api, apply_request=[{:?}], follower=[{}]",
+            &apply_request,
+            &self.follower
+        );
+
+        let entry_session = try_or_return!(
+            valid_sid_get_session(&self.session_token, &mut self.follower).await,
+            Self::web_type_error()
+        );
+
+        let customer_code = entry_session.customer_code.as_str();
+        // Destroying the items cannot be undone, it must be asked for
+        let dry_run = apply_request.dry_run.unwrap_or(true);
+
+        let Ok(mut cnx) = SQLConnectionAsync::from_pool().await.map_err(err_fwd!(
+            "💣 New Db connection failed, follower=[{}]",
+            &self.follower
+        )) else {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        };
+
+        // | Find the expired items of all the rules
+        let mut expired_items = {
+            let Ok(mut trans) = cnx.begin().await.map_err(err_fwd!(
+                "💣 Transaction issue, follower=[{}]",
+                &self.follower
+            )) else {
+                return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+            };
+
+            let expired_items = match self.find_all_expired_items(&mut trans, customer_code).await {
+                Ok(expired_items) => expired_items,
+                Err(e) => return WebType::from_errorset(e),
+            };
+
+            if trans
+                .commit()
+                .await
+                .map_err(err_fwd!("💣 Commit failed, follower=[{}]", &self.follower))
+                .is_err()
+            {
+                return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+            }
+            expired_items
+        };
+
+        log_info!(
+            "😎 We found the expired items, item count=[{}], follower=[{}]",
+            expired_items.len(),
+            &self.follower
+        );
+
+        // | Destroy them, one transaction per item, a failure is reported on the item
+        let mut destroyed_count = 0;
+        if !dry_run {
+            let trash_delegate = TrashDelegate::new(
+                self.session_token.clone(),
+                self.follower.x_request_id,
+            );
+
+            for expired_item in expired_items.iter_mut().filter(|e| !e.element.legal_hold) {
+                match self
+                    .destroy_expired_item(
+                        &mut cnx,
+                        &trash_delegate,
+                        expired_item,
+                        entry_session.user_id,
+                        customer_code,
+                    )
+                    .await
+                {
+                    // Put on legal hold or removed since the search, the item is kept
+                    Ok(false) => {}
+                    Ok(true) => {
+                        log_info!(
+                            "😎 Destroyed the expired item, item_id=[{}], rule_name=[{}], follower=[{}]",
+                            expired_item.element.item_id,
+                            &expired_item.element.rule_name,
+                            &self.follower
+                        );
+                        expired_item.element.destroyed = true;
+                        destroyed_count += 1;
+                    }
+                    Err(e) => {
+                        log_warn!(
+                            "⛔ The expired item is not destroyed, item_id=[{}], follower=[{}]",
+                            expired_item.element.item_id,
+                            &self.follower
+                        );
+                        expired_item.element.error = Some(e.err_message.to_string());
+                    }
+                }
+            }
+        }
+
+        log_info!("🏁 End apply_retention api, follower=[{}]", &self.follower);
+
+        WebType::from_item(
+            StatusCode::OK.as_u16(),
+            ApplyRetentionReply {
+                dry_run,
+                items: expired_items.into_iter().map(|e| e.element).collect(),
+                destroyed_count,
+            },
+        )
+    }
+
+    /// Certify and purge an expired item in its own transaction
+    /// The item is kept if it was put on legal hold or removed since it was found,
+    /// the answer is false then
+    async fn destroy_expired_item(
+        &self,
+        cnx: &mut SQLConnectionAsync,
+        trash_delegate: &TrashDelegate,
+        expired_item: &ExpiredItem,
+        user_id: i64,
+        customer_code: &str,
+    ) -> Result<bool, &'static ErrorSet<'static>> {
+        let item = &expired_item.element;
+        let mut trans = cnx
+            .begin()
+            .await
+            .map_err(err_fwd!(
+                "💣 Transaction issue, follower=[{}]",
+                &self.follower
+            ))
+            .map_err(|_| &*INTERNAL_DATABASE_ERROR)?;
+
+        let legal_hold = self
+            .lock_item_legal_hold(&mut trans, item.item_id, customer_code)
+            .await
+            .map_err(|_| &*INTERNAL_DATABASE_ERROR)?;
+        if legal_hold != Some(false) {
+            log_warn!(
+                "⛔ The item is on legal hold or gone, it is not destroyed, item_id=[{}], legal_hold=[{:?}], follower=[{}]",
+                item.item_id,
+                legal_hold,
+                &self.follower
+            );
+            trans.rollback().await;
+            return Ok(false);
+        }
+
+        self.insert_certificate(&mut trans, expired_item, user_id, customer_code)
+            .await
+            .map_err(|_| &*INTERNAL_DATABASE_ERROR)?;
+
+        let file_refs = trash_delegate
+            .purge(
+                &mut trans,
+                item.item_id,
+                item.file_ref.as_deref(),
+                customer_code,
+            )
+            .await?;
+
+        trans
+            .commit()
+            .await
+            .map_err(err_fwd!("💣 Commit failed, follower=[{}]", &self.follower))
+            .map_err(|_| &*INTERNAL_DATABASE_ERROR)?;
+
+        trash_delegate.release_files(&file_refs).await;
+        Ok(true)
+    }
+
+    /// Lock the row of the item until the end of the transaction
+    /// Give its legal hold, None if there is no item
+    async fn lock_item_legal_hold(
+        &self,
+        trans: &mut SQLTransactionAsync<'_>,
+        item_id: i64,
+        customer_code: &str,
+    ) -> anyhow::Result<Option<bool>> {
+        let mut params = HashMap::new();
+        params.insert("p_item_id".to_string(), CellValue::from_raw_int(item_id));
+
+        // The query block appends an OFFSET, the row lock goes in a sub-select
+        let query = SQLQueryBlockAsync {
+            sql_query: format!(
+                r"SELECT legal_hold FROM ( SELECT legal_hold FROM cs_{}.item
+                    WHERE id = :p_item_id FOR UPDATE ) it ",
+                customer_code
+            ),
+            start: 0,
+            length: Some(1),
+            params,
+        };
+
+        let mut data_set = query.execute(trans).await.map_err(err_fwd!(
+            "💣 Query failed, [{}], follower=[{}]",
+            &query.sql_query,
+            &self.follower
+        ))?;
+
+        if !data_set.next() {
+            return Ok(None);
+        }
+        let legal_hold = data_set
+            .get_bool("legal_hold")
+            .ok_or(anyhow!("Wrong legal_hold"))?;
+        Ok(Some(legal_hold))
+    }
+
+    ///
+    /// 🌟 Find the destruction certificates, most recent first
+    ///
+    pub async fn get_destruction_certificates(
+        mut self,
+        start_page: Option<u32>,
+        page_size: Option<u32>,
+    ) -> WebType<GetDestructionCertificateReply> {
+        log_info!(
+            "🚀 Start get_destruction_certificates api, start_page=[{:?}], page_size=[{:?}], follower=[{}]",
+            start_page,
+            page_size,
+            &self.follower
+        );
+
+        let entry_session = try_or_return!(
+            valid_sid_get_session(&self.session_token, &mut self.follower).await,
+            Self::web_type_error()
+        );
+
+        let customer_code = entry_session.customer_code.as_str();
+
+        let Ok(mut cnx) = SQLConnectionAsync::from_pool().await.map_err(err_fwd!(
+            "💣 New Db connection failed, follower=[{}]",
+            &self.follower
+        )) else {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        };
+
+        let Ok(mut trans) = cnx.begin().await.map_err(err_fwd!(
+            "💣 Transaction issue, follower=[{}]",
+            &self.follower
+        )) else {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        };
+
+        let Ok(certificates) = self
+            .search_certificates(&mut trans, start_page, page_size, customer_code)
+            .await
+            .map_err(err_fwd!(
+                "💣 Cannot find the destruction certificates, follower=[{}]",
+                &self.follower
+            ))
+        else {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        };
+
+        if trans
+            .commit()
+            .await
+            .map_err(err_fwd!("💣 Commit failed, follower=[{}]", &self.follower))
+            .is_err()
+        {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        }
+
+        log_info!(
+            "🏁 End get_destruction_certificates api, follower=[{}]",
+            &self.follower
+        );
+
+        WebType::from_item(
+            StatusCode::OK.as_u16(),
+            GetDestructionCertificateReply { certificates },
+        )
+    }
+
+    /// Expired items of all the rules, an item matching several rules is reported once
+    async fn find_all_expired_items(
+        &self,
+        trans: &mut SQLTransactionAsync<'_>,
+        customer_code: &str,
+    ) -> Result<Vec<ExpiredItem>, &'static ErrorSet<'static>> {
+        let Ok(rules) = self
+            .search_retention_rules(trans, None, customer_code)
+            .await
+            .map_err(err_fwd!(
+                "💣 Cannot read the retention rules, follower=[{}]",
+                &self.follower
+            ))
+        else {
+            return Err(&INTERNAL_DATABASE_ERROR);
+        };
+
+        let item_delegate = ItemDelegate::new(
+            self.session_token.clone(),
+            self.follower.x_request_id,
+        );
+        let tag_delegate = TagDelegate::new(
+            self.session_token.clone(),
+            self.follower.x_request_id,
+        );
+
+        let mut expired_items = vec![];
+        let mut item_ids = HashSet::new();
+        for rule in &rules {
+            let sql_condition = item_delegate
+                .build_filter_condition(trans, Some(&rule.filters), customer_code)
+                .await?;
+
+            let start_expression = if rule.start_from == START_FROM_CREATED {
+                "it.created_gmt".to_string()
+            } else {
+                // The tags of the filter are checked by the filter condition, not this one
+                let Ok(tag) = tag_delegate
+                    .search_tag_by_name(trans, &rule.start_from, customer_code)
+                    .await
+                else {
+                    log_warn!(
+                        "⛔ The retention rule is skipped, the start tag is missing, rule_name=[{}], start_from=[{}], follower=[{}]",
+                        &rule.name,
+                        &rule.start_from,
+                        &self.follower
+                    );
+                    continue;
+                };
+                // The latest value of a multi-valued tag keeps the item longer
+                format!(
+                    r"(SELECT MAX(COALESCE(CAST(tv.value_date AS timestamp), tv.value_datetime))
+                        FROM cs_{}.tag_value tv WHERE tv.item_id = it.id AND tv.tag_id = {})",
+                    customer_code, tag.tag_id
+                )
+            };
+
+            let Ok(rule_items) = self
+                .search_expired_items(
+                    trans,
+                    rule,
+                    &sql_condition,
+                    &start_expression,
+                    customer_code,
+                )
+                .await
+                .map_err(err_fwd!(
+                    "💣 Cannot find the expired items, rule_name=[{}], follower=[{}]",
+                    &rule.name,
+                    &self.follower
+                ))
+            else {
+                return Err(&INTERNAL_DATABASE_ERROR);
+            };
+
+            for expired_item in rule_items {
+                if item_ids.insert(expired_item.element.item_id) {
+                    expired_items.push(expired_item);
+                }
+            }
+        }
+
+        Ok(expired_items)
+    }
+
+    async fn search_expired_items(
+        &self,
+        trans: &mut SQLTransactionAsync<'_>,
+        rule: &RetentionRuleElement,
+        sql_condition: &str,
+        start_expression: &str,
+        customer_code: &str,
+    ) -> anyhow::Result<Vec<ExpiredItem>> {
+        // The period is checked by parse_period when the rule is created
+        let sql_query = format!(
+            r"SELECT id, name, file_ref, legal_hold, expired_gmt FROM (
+                    SELECT it.id, it.name, it.file_ref, it.legal_hold,
+                        {1} + INTERVAL '{2}' AS expired_gmt
+                    FROM cs_{0}.item it
+                    WHERE ({3}) ) expiry
+                    WHERE expired_gmt < :p_now
+                    ORDER BY id ",
+            customer_code, start_expression, &rule.period, sql_condition
+        );
+
+        let mut params = HashMap::new();
+        params.insert(
+            "p_now".to_string(),
+            CellValue::from_raw_systemtime(SystemTime::now()),
+        );
+
+        let query = SQLQueryBlockAsync {
+            sql_query,
+            start: 0,
+            length: None,
+            params,
+        };
+
+        let mut sql_result: SQLDataSet = query
+            .execute(trans)
+            .await
+            .map_err(err_fwd!("Query failed, [{}]", &query.sql_query))?;
+
+        let mut expired_items = vec![];
+        while sql_result.next() {
+            let expired_gmt = sql_result
+                .get_timestamp_as_datetime("expired_gmt")
+                .ok_or(anyhow!("Wrong expired gmt"))?;
+            expired_items.push(ExpiredItem {
+                element: ExpiredItemElement {
+                    item_id: sql_result.get_int("id").ok_or(anyhow!("Wrong id"))?,
+                    name: sql_result.get_string("name").unwrap_or_default(),
+                    file_ref: sql_result.get_string("file_ref"),
+                    rule_name: rule.name.clone(),
+                    expired: date_time_to_iso(&expired_gmt),
+                    legal_hold: sql_result.get_bool("legal_hold").unwrap_or(false),
+                    destroyed: false,
+                    error: None,
+                },
+                expired_gmt: SystemTime::from(expired_gmt),
+            });
+        }
+        Ok(expired_items)
+    }
+
+    async fn insert_certificate(
+        &self,
+        trans: &mut SQLTransactionAsync<'_>,
+        expired_item: &ExpiredItem,
+        user_id: i64,
+        customer_code: &str,
+    ) -> anyhow::Result<i64> {
+        let item = &expired_item.element;
+        let sql_query = format!(
+            r"INSERT INTO cs_{}.destruction_certificate
+                    (item_id, item_name, file_ref, rule_name, expired_gmt, destroyed_gmt, user_id)
+                    VALUES (:p_item_id, :p_item_name, :p_file_ref, :p_rule_name, :p_expired, :p_destroyed, :p_user_id)",
+            customer_code
+        );
+
+        let mut params = HashMap::new();
+        params.insert(
+            "p_item_id".to_string(),
+            CellValue::from_raw_int(item.item_id),
+        );
+        params.insert(
+            "p_item_name".to_string(),
+            CellValue::from_raw_str(&item.name),
+        );
+        params.insert(
+            "p_file_ref".to_string(),
+            CellValue::String(item.file_ref.clone()),
+        );
+        params.insert(
+            "p_rule_name".to_string(),
+            CellValue::from_raw_str(&item.rule_name),
+        );
+        params.insert(
+            "p_expired".to_string(),
+            CellValue::from_raw_systemtime(expired_item.expired_gmt),
+        );
+        params.insert(
+            "p_destroyed".to_string(),
+            CellValue::from_raw_systemtime(SystemTime::now()),
+        );
+        params.insert("p_user_id".to_string(), CellValue::from_raw_int(user_id));
+
+        let sql_insert = SQLChangeAsync {
+            sql_query,
+            params,
+            sequence_name: format!("cs_{}.destruction_certificate_id_seq", customer_code),
+        };
+
+        sql_insert.insert(trans).await.map_err(err_fwd!(
+            "💣 Cannot write the destruction certificate, item_id=[{}], follower=[{}]",
+            item.item_id,
+            &self.follower
+        ))
+    }
+
+    async fn search_certificates(
+        &self,
+        trans: &mut SQLTransactionAsync<'_>,
+        start_page: Option<u32>,
+        page_size: Option<u32>,
+        customer_code: &str,
+    ) -> anyhow::Result<Vec<DestructionCertificateElement>> {
+        let sql_query = format!(
+            r"SELECT id, item_id, item_name, file_ref, rule_name, expired_gmt, destroyed_gmt, user_id
+                    FROM cs_{}.destruction_certificate
+                    ORDER BY destroyed_gmt DESC, id DESC ",
+            customer_code
+        );
+
+        let query = SQLQueryBlockAsync {
+            sql_query,
+            start: start_page.unwrap_or(0) * page_size.unwrap_or(0),
+            length: page_size,
+            params: HashMap::new(),
+        };
+
+        let mut sql_result: SQLDataSet = query
+            .execute(trans)
+            .await
+            .map_err(err_fwd!("Query failed, [{}]", &query.sql_query))?;
+
+        let mut certificates = vec![];
+        while sql_result.next() {
+            let expired_gmt = sql_result
+                .get_timestamp_as_datetime("expired_gmt")
+                .ok_or(anyhow!("Wrong expired gmt"))?;
+            let destroyed_gmt = sql_result
+                .get_timestamp_as_datetime("destroyed_gmt")
+                .ok_or(anyhow!("Wrong destroyed gmt"))?;
+            certificates.push(DestructionCertificateElement {
+                certificate_id: sql_result.get_int("id").ok_or(anyhow!("Wrong id"))?,
+                item_id: sql_result
+                    .get_int("item_id")
+                    .ok_or(anyhow!("Wrong item id"))?,
+                item_name: sql_result.get_string("item_name").unwrap_or_default(),
+                file_ref: sql_result.get_string("file_ref"),
+                rule_name: sql_result.get_string("rule_name").unwrap_or_default(),
+                expired: date_time_to_iso(&expired_gmt),
+                destroyed: date_time_to_iso(&destroyed_gmt),
+                user_id: sql_result
+                    .get_int("user_id")
+                    .ok_or(anyhow!("Wrong user id"))?,
+            });
+        }
+        Ok(certificates)
+    }
+
+    /// Search the retention rules by name
+    /// If no name provided, return all the existing rules
+    async fn search_retention_rules(
+        &self,
+        trans: &mut SQLTransactionAsync<'_>,
+        name: Option<&str>,
+        customer_code: &str,
+    ) -> anyhow::Result<Vec<RetentionRuleElement>> {
+        let mut params = HashMap::new();
+        params.insert("p_name".to_owned(), CellValue::from_opt_str(name));
+
+        let sql_query = format!(
+            r"SELECT id, name, filters, start_from, period FROM cs_{}.retention_rule
+                    WHERE ( name = :p_name OR :p_name IS NULL )
+                    ORDER BY id ",
+            customer_code
+        );
+
+        let query = SQLQueryBlockAsync {
+            sql_query,
+            start: 0,
+            length: None,
+            params,
+        };
+
+        let mut sql_result: SQLDataSet = query
+            .execute(trans)
+            .await
+            .map_err(err_fwd!("Query failed, [{}]", &query.sql_query))?;
+
+        let mut rules = vec![];
+        while sql_result.next() {
+            rules.push(RetentionRuleElement {
+                rule_id: sql_result.get_int("id").ok_or(anyhow!("Wrong id"))?,
+                name: sql_result.get_string("name").unwrap_or_default(),
+                filters: sql_result.get_string("filters").unwrap_or_default(),
+                start_from: sql_result.get_string("start_from").unwrap_or_default(),
+                period: sql_result.get_string("period").unwrap_or_default(),
+            });
+        }
+        Ok(rules)
+    }
+
+    /// Ensure the retention can start from the tag
+    async fn check_date_tag(
+        &self,
+        trans: &mut SQLTransactionAsync<'_>,
+        tag_name: &str,
+        customer_code: &str,
+    ) -> Result<(), String> {
+        let tag_delegate = TagDelegate::new(
+            self.session_token.clone(),
+            self.follower.x_request_id,
+        );
+        let Ok(tag) = tag_delegate
+            .search_tag_by_name(trans, tag_name, customer_code)
+            .await
+        else {
+            return Err(format!(
+                "Unknown tag [{}], the retention starts from \"{}\" or a date tag",
+                tag_name, START_FROM_CREATED
+            ));
+        };
+
+        if tag.tag_type != TagType::Date.as_str() && tag.tag_type != TagType::DateTime.as_str() {
+            return Err(format!(
+                "The tag [{}] is of type [{}], the retention starts from a date tag",
+                tag_name, &tag.tag_type
+            ));
+        }
+        Ok(())
+    }
+
+    async fn is_item_existing(
+        &self,
+        trans: &mut SQLTransactionAsync<'_>,
+        item_id: i64,
+        customer_code: &str,
+    ) -> anyhow::Result<bool> {
+        let mut params = HashMap::new();
+        params.insert("p_item_id".to_string(), CellValue::from_raw_int(item_id));
+
+        let query = SQLQueryBlockAsync {
+            sql_query: format!(
+                "SELECT 1 FROM cs_{}.item WHERE id = :p_item_id",
+                customer_code
+            ),
+            start: 0,
+            length: Some(1),
+            params,
+        };
+
+        let mut sql_result: SQLDataSet = query
+            .execute(trans)
+            .await
+            .map_err(err_fwd!("Query failed, [{}]", &query.sql_query))?;
+
+        Ok(sql_result.next())
+    }
+
+    fn web_type_error<T>() -> impl Fn(&ErrorSet<'static>) -> WebType<T>
+    where
+        T: DeserializeOwned,
+    {
+        |e| {
+            log_error!("💣 Error after try {:?}", e);
+            WebType::from_errorset(e)
+        }
+    }
+}
+
+/// Normalized form of a retention period, ex: "10y" or "10 year" gives "10 years"
+/// None if the period is not a positive number of years, months or days
+fn parse_period(period: &str) -> Option<String> {
+    let period = period.trim().to_lowercase();
+    let split = period
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(period.len());
+    let (count, unit) = period.split_at(split);
+
+    let count = count.parse::<u32>().ok().filter(|count| *count > 0)?;
+    let unit = match unit.trim() {
+        "y" | "year" | "years" => "years",
+        "m" | "month" | "months" => "months",
+        "d" | "day" | "days" => "days",
+        _ => return None,
+    };
+    Some(format!("{} {}", count, unit))
+}
+
+#[cfg(test)]
+mod test {
+    use crate::retention::parse_period;
+
+    #[test]
+    fn parse_period_test() {
+        assert_eq!(Some("10 years".to_string()), parse_period("10y"));
+        assert_eq!(Some("1 years".to_string()), parse_period("1 year"));
+        assert_eq!(Some("6 months".to_string()), parse_period(" 6 Months "));
+        assert_eq!(Some("30 days".to_string()), parse_period("30d"));
+        assert_eq!(None, parse_period("0y"));
+        assert_eq!(None, parse_period("y"));
+        assert_eq!(None, parse_period("10"));
+        assert_eq!(None, parse_period("10 weeks"));
+        assert_eq!(None, parse_period("10 years'; DROP TABLE item"));
+    }
+}
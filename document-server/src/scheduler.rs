@@ -0,0 +1,217 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use anyhow::anyhow;
+use axum::Json;
+use log::{error, info, warn};
+use rs_uuid::iso::uuid_v4;
+use tokio::time::{interval_at, Instant};
+
+use commons_error::*;
+use commons_pg::sql_transaction_async::{SQLConnectionAsync, SQLQueryBlockAsync};
+use commons_services::token_lib::SessionToken;
+use commons_services::x_request_id::{Follower, XRequestID};
+use dkconfig::properties::get_prop_value;
+use dkconfig::property_name::{
+    COMMON_EDIBLE_KEY_PROPERTY, SCHEDULER_PERIOD_PROPERTY, SESSION_MANAGER_HOSTNAME_PROPERTY,
+    SESSION_MANAGER_PORT_PROPERTY,
+};
+use dkcrypto::dk_crypto::CypherMode::CC20;
+use dkcrypto::dk_crypto::DkEncrypt;
+use dkdto::{ApplyRetentionRequest, OpenSessionRequest};
+use doka_cli::async_request_client::SessionManagerClientAsync;
+use doka_cli::request_client::TokenType;
+
+use crate::retention::RetentionDelegate;
+use crate::trash::TrashDelegate;
+
+/// Period of the scheduled tasks, when the property is not set
+const DEFAULT_PERIOD_MINUTES: u64 = 60;
+
+/// User name of the technical sessions, its user id is 0
+const SCHEDULER_USER_NAME: &str = "scheduler";
+
+///
+/// Start the periodic tasks of the document server.
+/// The tasks run for every customer, under a technical session opened for the customer.
+/// The session manager cannot close a session, so the session of a customer is kept
+/// and reused by the next runs.
+///
+pub(crate) fn start_scheduler() {
+    let period_minutes = get_prop_value(SCHEDULER_PERIOD_PROPERTY)
+        .ok()
+        .and_then(|p| p.parse::<u64>().ok())
+        .unwrap_or(DEFAULT_PERIOD_MINUTES);
+
+    log_info!("😎 Start the scheduler, period=[{} min]", period_minutes);
+
+    let period = Duration::from_secs(period_minutes * 60);
+    let _th = tokio::spawn(async move {
+        // The first run waits for one period, the other services may not be up yet
+        let mut interval = interval_at(Instant::now() + period, period);
+        let mut sessions: HashMap<String, SessionToken> = HashMap::new();
+        loop {
+            interval.tick().await;
+            run_scheduled_tasks(&mut sessions).await;
+        }
+    });
+}
+
+/// [sessions] : the technical session of each customer, by customer code
+async fn run_scheduled_tasks(sessions: &mut HashMap<String, SessionToken>) {
+    let follower = Follower {
+        x_request_id: XRequestID::new(),
+        token_type: TokenType::None,
+    };
+
+    log_info!("🚀 Start the scheduled tasks, follower=[{}]", &follower);
+
+    let Ok(customer_codes) = find_customer_codes(&follower).await else {
+        return;
+    };
+
+    sessions.retain(|customer_code, _| customer_codes.contains(customer_code));
+
+    for customer_code in customer_codes {
+        let session_token = match sessions.get(&customer_code) {
+            Some(session_token) => session_token.clone(),
+            None => {
+                let Ok(session_token) = open_technical_session(&customer_code, &follower).await
+                else {
+                    continue;
+                };
+                sessions.insert(customer_code.clone(), session_token.clone());
+                session_token
+            }
+        };
+
+        TrashDelegate::new(session_token.clone(), follower.x_request_id)
+            .purge_expired_items(&customer_code)
+            .await;
+
+        let (_, reply) = RetentionDelegate::new(session_token, follower.x_request_id)
+            .apply_retention(Json(ApplyRetentionRequest {
+                dry_run: Some(false),
+            }))
+            .await;
+
+        match reply {
+            Ok(reply) => {
+                log_info!(
+                    "😎 Retention applied, customer_code=[{}], expired=[{}], destroyed=[{}], follower=[{}]",
+                    &customer_code,
+                    reply.items.len(),
+                    reply.destroyed_count,
+                    &follower
+                );
+            }
+            Err(e) => {
+                log_warn!(
+                    "⛔ Retention failed, customer_code=[{}], message=[{}], follower=[{}]",
+                    &customer_code,
+                    &e.message,
+                    &follower
+                );
+                // The session may be the cause, the next run opens a new one
+                sessions.remove(&customer_code);
+            }
+        }
+    }
+
+    log_info!("🏁 End the scheduled tasks, follower=[{}]", &follower);
+}
+
+/// The customers are the "cs_" schemas of the document database
+async fn find_customer_codes(follower: &Follower) -> anyhow::Result<Vec<String>> {
+    let mut cnx = SQLConnectionAsync::from_pool().await.map_err(err_fwd!(
+        "💣 New Db connection failed, follower=[{}]",
+        follower
+    ))?;
+
+    let mut trans = cnx
+        .begin()
+        .await
+        .map_err(err_fwd!("💣 Transaction issue, follower=[{}]", follower))?;
+
+    let query = SQLQueryBlockAsync {
+        sql_query: r"SELECT substring(schema_name from 4) AS customer_code
+                    FROM information_schema.schemata
+                    WHERE schema_name LIKE 'cs\_%'
+                    ORDER BY schema_name"
+            .to_string(),
+        start: 0,
+        length: None,
+        params: HashMap::new(),
+    };
+
+    let mut sql_result = query.execute(&mut trans).await.map_err(err_fwd!(
+        "💣 Query failed, [{}], follower=[{}]",
+        &query.sql_query,
+        follower
+    ))?;
+
+    let mut customer_codes = vec![];
+    while sql_result.next() {
+        customer_codes.push(
+            sql_result
+                .get_string("customer_code")
+                .ok_or(anyhow!("Wrong customer code"))?,
+        );
+    }
+
+    trans
+        .commit()
+        .await
+        .map_err(err_fwd!("💣 Commit failed, follower=[{}]", follower))?;
+
+    Ok(customer_codes)
+}
+
+/// Open a session for the customer, as the login does for a user
+async fn open_technical_session(
+    customer_code: &str,
+    follower: &Follower,
+) -> anyhow::Result<SessionToken> {
+    let cek = get_prop_value(COMMON_EDIBLE_KEY_PROPERTY)
+        .map_err(err_fwd!("💣 Cannot read the cek, follower=[{}]", follower))?;
+
+    let session_id = DkEncrypt::new(CC20)
+        .encrypt_str(&uuid_v4(), &cek)
+        .map_err(err_fwd!(
+            "💣 Cannot encrypt the session id, follower=[{}]",
+            follower
+        ))?;
+
+    let sm_host = get_prop_value(SESSION_MANAGER_HOSTNAME_PROPERTY).map_err(err_fwd!(
+        "💣 Cannot read Session Manager hostname, follower=[{}]",
+        follower
+    ))?;
+    let sm_port: u16 = get_prop_value(SESSION_MANAGER_PORT_PROPERTY)?.parse()?;
+    let smc = SessionManagerClientAsync::new(&sm_host, sm_port);
+
+    let open_session_request = OpenSessionRequest {
+        customer_code: customer_code.to_string(),
+        user_name: SCHEDULER_USER_NAME.to_string(),
+        customer_id: 0,
+        user_id: 0,
+        session_id: session_id.clone(),
+    };
+
+    smc.open_session(
+        &open_session_request,
+        &session_id,
+        follower.x_request_id.value(),
+    )
+    .await
+    .map_err(|e| {
+        log_error!(
+            "💣 Session Manager failed, customer_code=[{}], message=[{}], follower=[{}]",
+            customer_code,
+            &e.message,
+            follower
+        );
+        anyhow!("{}", e.message)
+    })?;
+
+    Ok(SessionToken(session_id))
+}
@@ -2,12 +2,16 @@ use anyhow::anyhow;
 use axum::http::StatusCode;
 use axum::Json;
 use log::{debug, error, info};
+use regex::Regex;
 use serde::de::DeserializeOwned;
 use std::collections::HashMap;
 use std::str::FromStr;
+use std::time::SystemTime;
 
 use commons_error::*;
-use commons_pg::sql_transaction::{iso_to_datetime, iso_to_naivedate, CellValue, SQLDataSet};
+use commons_pg::sql_transaction::{
+    date_time_to_iso, iso_to_datetime, iso_to_naivedate, CellValue, SQLDataSet,
+};
 use commons_pg::sql_transaction_async::{
     SQLChangeAsync, SQLConnectionAsync, SQLQueryBlockAsync, SQLTransactionAsync,
 };
@@ -17,18 +21,33 @@ use commons_services::try_or_return;
 use commons_services::x_request_id::{Follower, XRequestID};
 use dkdto::error_codes::{
     INCORRECT_CHAR_TAG_NAME, INCORRECT_DEFAULT_BOOLEAN_VALUE, INCORRECT_DEFAULT_DATETIME_VALUE,
-    INCORRECT_DEFAULT_DATE_VALUE, INCORRECT_DEFAULT_DOUBLE_VALUE, INCORRECT_DEFAULT_INTEGER_VALUE,
-    INCORRECT_DEFAULT_LINK_LENGTH, INCORRECT_DEFAULT_STRING_LENGTH, INCORRECT_LENGTH_TAG_NAME,
-    INCORRECT_TAG_TYPE, INTERNAL_DATABASE_ERROR, STILL_IN_USE,
+    INCORRECT_DEFAULT_DATE_VALUE, INCORRECT_DEFAULT_DOUBLE_VALUE, INCORRECT_DEFAULT_ENUM_VALUE,
+    INCORRECT_DEFAULT_INTEGER_VALUE, INCORRECT_DEFAULT_LINK_LENGTH,
+    INCORRECT_DEFAULT_STRING_LENGTH, INCORRECT_ENUM_ALLOWED_VALUES, INCORRECT_LENGTH_TAG_NAME,
+    INCORRECT_TAG_CONSTRAINTS, INCORRECT_TAG_TYPE, INTERNAL_DATABASE_ERROR, MISSING_TAG,
+    NOT_AN_ENUM_TAG, STILL_IN_USE,
 };
 use dkdto::{
-    AddTagReply, AddTagRequest, ErrorSet, GetTagReply, SimpleMessage, TagElement, TagType, WebType,
-    WebTypeBuilder,
+    AddTagReply, AddTagRequest, EnumTagValue, ErrorSet, GetEnumValuesReply, GetTagReply,
+    GetTagValuesReply, SimpleMessage, TagConstraints, TagElement, TagStatsReply, TagType,
+    TagValueCount, UpdateEnumValuesRequest, WebType, WebTypeBuilder,
 };
 use doka_cli::request_client::TokenType;
 
 use crate::char_lib::has_not_printable_char;
 
+/// Max length of a text tag value, the default when the tag definition does not set one
+const MAX_STRING_LENGTH: usize = 2000;
+
+/// LIKE pattern matching the values starting with the prefix, the wildcards of the prefix are escaped
+fn like_prefix_pattern(prefix: &str) -> String {
+    let escaped = prefix
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_");
+    format!("{}%", escaped)
+}
+
 pub(crate) struct TagDelegate {
     pub session_token: SessionToken,
     pub follower: Follower,
@@ -143,7 +162,8 @@ impl TagDelegate {
         params.insert("p_tag_id".to_owned(), p_tag_id);
 
         let sql_query = format!(
-            r"SELECT id, name, type, string_tag_length, default_value
+            r"SELECT id, name, type, string_tag_length, default_value, multi_valued,
+                                    min_value, max_value, pattern, required
                                     FROM cs_{}.tag_definition
                                     WHERE ( id = :p_tag_id OR :p_tag_id IS NULL )
                                     ORDER BY name ",
@@ -173,6 +193,8 @@ impl TagDelegate {
             // optional
 
             let default_value = sql_result.get_string("default_value");
+            let multi_valued = sql_result.get_bool("multi_valued").unwrap_or(false);
+            let constraints = Self::read_tag_constraints(&sql_result, &tag_type);
 
             log_debug!(
                 "Found tag, tag id=[{}], tag_name=[{}], follower=[{}]",
@@ -187,27 +209,65 @@ impl TagDelegate {
                 tag_type,
 
                 default_value,
+                allowed_values: None,
+                multi_valued,
+                constraints,
             };
             let _ = &tags.push(item);
         }
 
+        // Complete the enum tags with their allowed values
+        for tag in tags.iter_mut() {
+            if tag.tag_type == TagType::Enum.as_str() {
+                let values = self
+                    .search_enum_values(trans, tag.tag_id, customer_code)
+                    .await
+                    .map_err(tr_fwd!())?;
+                tag.allowed_values = Some(values);
+            }
+        }
+
         Ok(tags)
     }
 
     /// Search items by name
     pub(crate) async fn search_tag_by_name(
         &self,
-        mut trans: &mut SQLTransactionAsync<'_>,
+        trans: &mut SQLTransactionAsync<'_>,
         tag_name: &str,
         customer_code: &str,
     ) -> anyhow::Result<TagElement> {
+        match self
+            .find_tag_by_name(trans, tag_name, customer_code)
+            .await?
+        {
+            Some(tag) => Ok(tag),
+            None => {
+                log_error!(
+                    "💣 Cannot find the tag, tag_name=[{}], follower=[{}]",
+                    tag_name,
+                    &self.follower
+                );
+                Err(anyhow!("Cannot find tag, tag_name=[{}]", tag_name))
+            }
+        }
+    }
+
+    /// Read the tag by name, None when it does not exist
+    pub(crate) async fn find_tag_by_name(
+        &self,
+        mut trans: &mut SQLTransactionAsync<'_>,
+        tag_name: &str,
+        customer_code: &str,
+    ) -> anyhow::Result<Option<TagElement>> {
         let p_tag_name = CellValue::from_raw_string(tag_name.to_string());
 
         let mut params = HashMap::new();
         params.insert("p_tag_name".to_owned(), p_tag_name);
 
         let sql_query = format!(
-            r"SELECT id, name, type, string_tag_length, default_value
+            r"SELECT id, name, type, string_tag_length, default_value, multi_valued,
+                                    min_value, max_value, pattern, required
                                     FROM cs_{}.tag_definition
                                     WHERE ( name = :p_tag_name )
                                     ORDER BY name ",
@@ -234,8 +294,9 @@ impl TagDelegate {
                 .get_string("type")
                 .ok_or(anyhow!("Wrong tag_type"))?;
             // optional
-            // let string_tag_length = sql_result.get_int_32("string_tag_length");
             let default_value = sql_result.get_string("default_value");
+            let multi_valued = sql_result.get_bool("multi_valued").unwrap_or(false);
+            let constraints = Self::read_tag_constraints(&sql_result, &tag_type);
 
             log_debug!(
                 "Found tag, tag id=[{}], tag_name=[{}], follower=[{}]",
@@ -244,20 +305,87 @@ impl TagDelegate {
                 &self.follower
             );
 
-            Ok(TagElement {
+            let allowed_values = if tag_type == TagType::Enum.as_str() {
+                Some(
+                    self.search_enum_values(trans, id, customer_code)
+                        .await
+                        .map_err(tr_fwd!())?,
+                )
+            } else {
+                None
+            };
+
+            Ok(Some(TagElement {
                 tag_id: id,
                 name,
                 tag_type,
                 default_value,
-            })
+                allowed_values,
+                multi_valued,
+                constraints,
+            }))
         } else {
-            log_error!(
-                "💣 Cannot find the tag, tag_name=[{}], follower=[{}]",
-                tag_name,
-                &self.follower
-            );
-            Err(anyhow!("Cannot find tag, tag_name=[{}]", tag_name))
+            Ok(None)
+        }
+    }
+
+    /// Build the constraints from the current row of a tag_definition query
+    /// The string_tag_length is the max length of the text tags only
+    fn read_tag_constraints(sql_result: &SQLDataSet, tag_type: &str) -> TagConstraints {
+        let max_length = if tag_type == TagType::Text.as_str() {
+            sql_result.get_int_32("string_tag_length")
+        } else {
+            None
+        };
+
+        TagConstraints {
+            min_value: sql_result.get_string("min_value"),
+            max_value: sql_result.get_string("max_value"),
+            pattern: sql_result.get_string("pattern"),
+            max_length,
+            required: Some(sql_result.get_bool("required").unwrap_or(false)),
+        }
+    }
+
+    /// Find the allowed values of an enum tag, in their definition order
+    pub(crate) async fn search_enum_values(
+        &self,
+        trans: &mut SQLTransactionAsync<'_>,
+        tag_id: i64,
+        customer_code: &str,
+    ) -> anyhow::Result<Vec<String>> {
+        let mut params = HashMap::new();
+        params.insert("p_tag_id".to_owned(), CellValue::from_raw_int(tag_id));
+
+        let sql_query = format!(
+            r"SELECT value FROM cs_{}.tag_enum_value
+                                    WHERE tag_id = :p_tag_id
+                                    ORDER BY sort_order ",
+            customer_code
+        );
+
+        let query = SQLQueryBlockAsync {
+            sql_query,
+            start: 0,
+            length: None,
+            params,
+        };
+
+        let mut sql_result: SQLDataSet = query.execute(trans).await.map_err(err_fwd!(
+            "Query failed, sql=[{}], follower=[{}]",
+            &query.sql_query,
+            &self.follower
+        ))?;
+
+        let mut values = vec![];
+        while sql_result.next() {
+            let value: String = sql_result
+                .get_string("value")
+                .ok_or(anyhow!("Wrong value"))?;
+            values.push(value);
         }
+
+        Ok(values)
     }
 
     ///
@@ -333,6 +461,21 @@ impl TagDelegate {
             &self.follower
         );
 
+        // Delete the allowed values of the tag, if any
+
+        if self
+            .delete_enum_values(&mut trans, tag_id, customer_code)
+            .await
+            .map_err(err_fwd!(
+                "💣 Enum values delete failed, tag_id=[{}], follower=[{}]",
+                tag_id,
+                &self.follower
+            ))
+            .is_err()
+        {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        }
+
         // Delete the tag definition
 
         let sql_query = format!(
@@ -390,7 +533,10 @@ impl TagDelegate {
         customer_code: &str,
     ) -> anyhow::Result<()> {
         let sql_query = format!(
-            r"SELECT 1 FROM cs_{}.tag_value
+            r"SELECT 1 FROM cs_{0}.tag_value
+	                                WHERE tag_id = :p_tag_id
+	                  UNION ALL
+	                  SELECT 1 FROM cs_{0}.document_type_tag
 	                                WHERE tag_id = :p_tag_id",
             customer_code
         );
@@ -516,14 +662,24 @@ impl TagDelegate {
         customer_code: &str,
     ) -> anyhow::Result<i64> {
         let sql_query = format!(
-            r"INSERT INTO cs_{}.tag_definition(name, string_tag_length, default_value, type)
-	            VALUES (:p_name, :p_string_tag_length , :p_default_value, :p_type)",
+            r"INSERT INTO cs_{}.tag_definition(name, string_tag_length, default_value, type, multi_valued,
+                                min_value, max_value, pattern, required)
+	            VALUES (:p_name, :p_string_tag_length , :p_default_value, :p_type, :p_multi_valued,
+	                    :p_min_value, :p_max_value, :p_pattern, :p_required)",
             customer_code
         );
 
         let sequence_name = format!("cs_{}.tag_definition_id_seq", customer_code);
 
-        let length = CellValue::Int32(Some(2000_i32)); // TODO Db column to be removed
+        let constraints = add_tag_request.constraints.clone().unwrap_or_default();
+        // Only the text tags have a max length
+        let length = if add_tag_request.tag_type.to_lowercase() == TagType::Text.as_str() {
+            CellValue::Int32(Some(
+                constraints.max_length.unwrap_or(MAX_STRING_LENGTH as i32),
+            ))
+        } else {
+            CellValue::Int32(None)
+        };
         let default_value = CellValue::from_opt_str(add_tag_request.default_value.as_deref());
         let mut params = HashMap::new();
         params.insert(
@@ -536,6 +692,26 @@ impl TagDelegate {
         );
         params.insert("p_string_tag_length".to_string(), length);
         params.insert("p_default_value".to_string(), default_value);
+        params.insert(
+            "p_multi_valued".to_string(),
+            CellValue::Bool(Some(add_tag_request.multi_valued.unwrap_or(false))),
+        );
+        params.insert(
+            "p_min_value".to_string(),
+            CellValue::from_opt_str(constraints.min_value.as_deref()),
+        );
+        params.insert(
+            "p_max_value".to_string(),
+            CellValue::from_opt_str(constraints.max_value.as_deref()),
+        );
+        params.insert(
+            "p_pattern".to_string(),
+            CellValue::from_opt_str(constraints.pattern.as_deref()),
+        );
+        params.insert(
+            "p_required".to_string(),
+            CellValue::Bool(Some(constraints.required.unwrap_or(false))),
+        );
 
         let sql_insert = SQLChangeAsync {
             sql_query,
@@ -548,127 +724,1274 @@ impl TagDelegate {
             &self.follower
         ))?;
 
+        if let Some(allowed_values) = &add_tag_request.allowed_values {
+            self.insert_enum_values(&mut trans, tag_id, allowed_values, customer_code)
+                .await
+                .map_err(tr_fwd!())?;
+        }
+
         Ok(tag_id)
     }
 
+    /// Store the allowed values of an enum tag, the order of the list is kept
+    async fn insert_enum_values(
+        &self,
+        trans: &mut SQLTransactionAsync<'_>,
+        tag_id: i64,
+        allowed_values: &[String],
+        customer_code: &str,
+    ) -> anyhow::Result<()> {
+        for (sort_order, value) in allowed_values.iter().enumerate() {
+            let sql_query = format!(
+                r"INSERT INTO cs_{}.tag_enum_value(tag_id, value, sort_order)
+                    VALUES (:p_tag_id, :p_value, :p_sort_order)",
+                customer_code
+            );
+
+            let sequence_name = format!("cs_{}.tag_enum_value_id_seq", customer_code);
+
+            let mut params = HashMap::new();
+            params.insert("p_tag_id".to_string(), CellValue::from_raw_int(tag_id));
+            params.insert("p_value".to_string(), CellValue::from_raw_str(value));
+            params.insert(
+                "p_sort_order".to_string(),
+                CellValue::from_raw_int_32(sort_order as i32),
+            );
+
+            let sql_insert = SQLChangeAsync {
+                sql_query,
+                params,
+                sequence_name,
+            };
+
+            let _ = sql_insert.insert(trans).await.map_err(err_fwd!(
+                "💣 Insertion of an enum value failed, value=[{}], follower=[{}]",
+                value,
+                &self.follower
+            ))?;
+        }
+        Ok(())
+    }
+
+    async fn delete_enum_values(
+        &self,
+        trans: &mut SQLTransactionAsync<'_>,
+        tag_id: i64,
+        customer_code: &str,
+    ) -> anyhow::Result<()> {
+        let sql_query = format!(
+            r"DELETE FROM cs_{}.tag_enum_value
+	                                WHERE tag_id = :p_tag_id",
+            customer_code
+        );
+
+        let mut params = HashMap::new();
+        params.insert("p_tag_id".to_string(), CellValue::from_raw_int(tag_id));
+
+        let sql_delete = SQLChangeAsync {
+            sql_query,
+            params,
+            sequence_name: "".to_string(),
+        };
+
+        let _ = sql_delete.delete(trans).await.map_err(tr_fwd!())?;
+        Ok(())
+    }
+
     ///
-    /// Return a None if the tag definition is correct
+    /// 🌟 Get the allowed values of an enum tag
+    ///     The optional prefix restricts the values for the autocompletion of the filters
     ///
-    pub(crate) fn check_input_values(
-        &self,
-        add_tag_request: &AddTagRequest,
-    ) -> Result<(), &ErrorSet<'static>> {
+    pub async fn get_enum_values(
+        mut self,
+        tag_id: i64,
+        prefix: Option<String>,
+    ) -> WebType<GetEnumValuesReply> {
         log_info!(
-            "Check the tag definition, add_tag_request=[{:?}], follower=[{}]",
-            add_tag_request,
+            "🚀 Start get_enum_values api, tag_id=[{}], prefix=[{:?}], follower=[{}]",
+            tag_id,
+            &prefix,
             &self.follower
         );
 
-        // Check the tag name
-        if has_not_printable_char(&add_tag_request.name) {
-            return Err(&INCORRECT_CHAR_TAG_NAME);
-        }
+        let entry_session = try_or_return!(
+            valid_sid_get_session(&self.session_token, &mut self.follower).await,
+            Self::web_type_error()
+        );
 
-        if add_tag_request.name.len() > 50 {
-            return Err(&INCORRECT_LENGTH_TAG_NAME);
-        }
+        let customer_code = entry_session.customer_code.as_str();
 
-        let tag_type = match TagType::from_str(add_tag_request.tag_type.to_lowercase().as_str()) {
-            Ok(v) => v,
-            Err(_) => {
-                return Err(&INCORRECT_TAG_TYPE);
-            }
+        // Open Db connection
+        let Ok(mut cnx) = SQLConnectionAsync::from_pool().await.map_err(err_fwd!(
+            "💣 New Db connection failed, follower=[{}]",
+            &self.follower
+        )) else {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
         };
 
-        // Check the input values ( ie tag_type, length limit, default_value type, etc )
-        match tag_type {
-            TagType::Text => {
-                // The string_length between 0 and 10_000_000
-                const MAX_STRING_LENGTH: usize = 2000;
-                if let Some(default_string) = &add_tag_request.default_value {
-                    if default_string.len() > MAX_STRING_LENGTH as usize {
-                        return Err(&INCORRECT_DEFAULT_STRING_LENGTH);
-                    }
-                }
-            }
-            TagType::Link => {
-                // A Link is like a string
-                const MAX_LINK_LENGTH: usize = 400;
-                if let Some(default_string) = &add_tag_request.default_value {
-                    if default_string.len() > MAX_LINK_LENGTH as usize {
-                        return Err(&INCORRECT_DEFAULT_LINK_LENGTH);
-                    }
-                }
-            }
-            TagType::Bool => {
-                if let Some(v) = &add_tag_request.default_value {
-                    if v != "true" && v != "false" {
-                        return Err(&INCORRECT_DEFAULT_BOOLEAN_VALUE);
-                    }
-                }
-            }
-            TagType::Int => {
-                if let Some(v) = &add_tag_request.default_value {
-                    if v.parse::<i64>().is_err() {
-                        return Err(&INCORRECT_DEFAULT_INTEGER_VALUE);
-                    }
-                }
-            }
-            TagType::Double => {
-                if let Some(d) = &add_tag_request.default_value {
-                    if d.parse::<f64>().is_err() {
-                        return Err(&INCORRECT_DEFAULT_DOUBLE_VALUE);
-                    }
-                }
-            }
-            TagType::Date => {
-                if let Some(d_str) = &add_tag_request.default_value {
-                    // Check if the default is a valid date  ISO8601 1977-04-22
-                    if iso_to_naivedate(d_str).is_err() {
-                        return Err(&INCORRECT_DEFAULT_DATE_VALUE);
-                    }
-                }
-            }
-            TagType::DateTime => {
-                if let Some(dt_str) = &add_tag_request.default_value {
-                    // Check if the default is a valid datetime ISO8601 "1977-04-22T06:00:00Z"
-                    if iso_to_datetime(dt_str).is_err() {
-                        return Err(&INCORRECT_DEFAULT_DATETIME_VALUE);
-                    }
-                }
-            }
+        let Ok(mut trans) = cnx.begin().await.map_err(err_fwd!(
+            "💣 Transaction issue, follower=[{}]",
+            &self.follower
+        )) else {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
         };
 
-        Ok(())
-    }
+        let tag = try_or_return!(
+            self.find_enum_tag(&mut trans, tag_id, customer_code).await,
+            Self::web_type_error()
+        );
 
-    fn web_type_error<T>() -> impl Fn(&ErrorSet<'static>) -> WebType<T>
-    where
-        T: DeserializeOwned,
-    {
-        |e| {
-            log_error!("💣 Error after try {:?}", e);
-            WebType::from_errorset(e)
+        if trans
+            .commit()
+            .await
+            .map_err(err_fwd!("💣 Commit failed, follower=[{}]", &self.follower))
+            .is_err()
+        {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
         }
-    }
-}
 
-#[cfg(test)]
-mod test {
-    use chrono::{DateTime, Datelike, Timelike, Utc};
+        let allowed_values = tag
+            .allowed_values
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|v| match &prefix {
+                None => true,
+                Some(p) => v.to_lowercase().starts_with(&p.to_lowercase()),
+            })
+            .collect();
 
-    use commons_pg::sql_transaction::{iso_to_datetime, iso_to_naivedate};
+        log_info!("🏁 End get_enum_values api, follower=[{}]", &self.follower);
 
-    #[test]
-    fn is_valid_datetime_test() {
-        assert!(iso_to_datetime("1977-04-22T06:12:04Z").is_ok());
-        assert!(iso_to_datetime("1977-04-22T23:12:04Z").is_ok());
-        assert!(iso_to_datetime("1977-04-22T23:12:04+01:00").is_ok());
-        assert!(iso_to_datetime("0000-12-04T22:12:04Z").is_ok());
+        WebType::from_item(
+            StatusCode::OK.as_u16(),
+            GetEnumValuesReply {
+                tag_id: tag.tag_id,
+                name: tag.name,
+                allowed_values,
+            },
+        )
+    }
 
-        assert!(iso_to_datetime("1977-04-22T26:12:04Z").is_err());
-        assert!(iso_to_datetime("1977-0422T22:12:04Z").is_err());
-        assert!(iso_to_datetime("1977-04T22:12:04Z").is_err());
+    ///
+    /// 🌟 Usage statistics of a tag
+    ///
+    pub async fn get_tag_stats(mut self, tag_id: i64) -> WebType<TagStatsReply> {
+        log_info!(
+            "🚀 Start get_tag_stats api, tag_id=[{}], follower=[{}]",
+            tag_id,
+            &self.follower
+        );
+
+        let entry_session = try_or_return!(
+            valid_sid_get_session(&self.session_token, &mut self.follower).await,
+            Self::web_type_error()
+        );
+
+        let customer_code = entry_session.customer_code.as_str();
+
+        // Open Db connection
+        let Ok(mut cnx) = SQLConnectionAsync::from_pool().await.map_err(err_fwd!(
+            "💣 New Db connection failed, follower=[{}]",
+            &self.follower
+        )) else {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        };
+
+        let Ok(mut trans) = cnx.begin().await.map_err(err_fwd!(
+            "💣 Transaction issue, follower=[{}]",
+            &self.follower
+        )) else {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        };
+
+        let tag = try_or_return!(
+            self.find_tag(&mut trans, tag_id, customer_code).await,
+            Self::web_type_error()
+        );
+
+        let Ok(tag_stats) = self
+            .compute_tag_stats(&mut trans, &tag, customer_code)
+            .await
+            .map_err(err_fwd!(
+                "💣 Cannot compute the tag statistics, tag_id=[{}], follower=[{}]",
+                tag_id,
+                &self.follower
+            ))
+        else {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        };
+
+        if trans
+            .commit()
+            .await
+            .map_err(err_fwd!("💣 Commit failed, follower=[{}]", &self.follower))
+            .is_err()
+        {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        }
+
+        log_info!("🏁 End get_tag_stats api, follower=[{}]", &self.follower);
+
+        WebType::from_item(StatusCode::OK.as_u16(), tag_stats)
+    }
+
+    ///
+    /// 🌟 Distinct values of a tag with their item count, possibly restricted to a prefix
+    ///     Used for the value pickers
+    ///
+    pub async fn get_tag_values(
+        mut self,
+        tag_id: i64,
+        prefix: Option<String>,
+        start_page: Option<u32>,
+        page_size: Option<u32>,
+    ) -> WebType<GetTagValuesReply> {
+        log_info!(
+            "🚀 Start get_tag_values api, tag_id=[{}], prefix=[{:?}], follower=[{}]",
+            tag_id,
+            &prefix,
+            &self.follower
+        );
+
+        let entry_session = try_or_return!(
+            valid_sid_get_session(&self.session_token, &mut self.follower).await,
+            Self::web_type_error()
+        );
+
+        let customer_code = entry_session.customer_code.as_str();
+
+        // Open Db connection
+        let Ok(mut cnx) = SQLConnectionAsync::from_pool().await.map_err(err_fwd!(
+            "💣 New Db connection failed, follower=[{}]",
+            &self.follower
+        )) else {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        };
+
+        let Ok(mut trans) = cnx.begin().await.map_err(err_fwd!(
+            "💣 Transaction issue, follower=[{}]",
+            &self.follower
+        )) else {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        };
+
+        let tag = try_or_return!(
+            self.find_tag(&mut trans, tag_id, customer_code).await,
+            Self::web_type_error()
+        );
+
+        let Ok(values) = self
+            .search_tag_values(
+                &mut trans,
+                &tag,
+                prefix.as_deref(),
+                start_page,
+                page_size,
+                customer_code,
+            )
+            .await
+            .map_err(err_fwd!(
+                "💣 Cannot read the tag values, tag_id=[{}], follower=[{}]",
+                tag_id,
+                &self.follower
+            ))
+        else {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        };
+
+        if trans
+            .commit()
+            .await
+            .map_err(err_fwd!("💣 Commit failed, follower=[{}]", &self.follower))
+            .is_err()
+        {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        }
+
+        log_info!("🏁 End get_tag_values api, follower=[{}]", &self.follower);
+
+        WebType::from_item(
+            StatusCode::OK.as_u16(),
+            GetTagValuesReply {
+                tag_id: tag.tag_id,
+                name: tag.name,
+                values,
+            },
+        )
+    }
+
+    async fn compute_tag_stats(
+        &self,
+        trans: &mut SQLTransactionAsync<'_>,
+        tag: &TagElement,
+        customer_code: &str,
+    ) -> anyhow::Result<TagStatsReply> {
+        let tag_type = TagType::from_str(tag.tag_type.to_lowercase().as_str())
+            .map_err(|_| anyhow!("Wrong tag_type, tag_type=[{}]", &tag.tag_type))?;
+        let column = Self::value_column(&tag_type);
+        // There is no min/max on booleans
+        let (min_fn, max_fn) = match tag_type {
+            TagType::Bool => ("bool_and", "bool_or"),
+            _ => ("MIN", "MAX"),
+        };
+
+        let sql_query = format!(
+            r"SELECT COUNT(DISTINCT tv.item_id) AS item_count,
+                    COUNT(DISTINCT {1}) AS distinct_value_count,
+                    {2} AS min_value,
+                    {3} AS max_value,
+                    MAX(tv.modified_gmt) AS last_used
+                    FROM cs_{0}.tag_value tv
+                    WHERE tv.tag_id = :p_tag_id ",
+            customer_code,
+            column,
+            Self::value_as_text(&tag_type, &format!("{}({})", min_fn, column)),
+            Self::value_as_text(&tag_type, &format!("{}({})", max_fn, column)),
+        );
+
+        let mut params = HashMap::new();
+        params.insert("p_tag_id".to_owned(), CellValue::from_raw_int(tag.tag_id));
+
+        let query = SQLQueryBlockAsync {
+            sql_query,
+            start: 0,
+            length: None,
+            params,
+        };
+
+        let mut sql_result: SQLDataSet = query.execute(trans).await.map_err(err_fwd!(
+            "Query failed, sql=[{}], follower=[{}]",
+            &query.sql_query,
+            &self.follower
+        ))?;
+
+        if !sql_result.next() {
+            return Err(anyhow!(
+                "No statistics for the tag, tag_id=[{}]",
+                tag.tag_id
+            ));
+        }
+
+        Ok(TagStatsReply {
+            tag_id: tag.tag_id,
+            name: tag.name.clone(),
+            tag_type: tag.tag_type.clone(),
+            item_count: sql_result.get_int("item_count").unwrap_or(0),
+            distinct_value_count: sql_result.get_int("distinct_value_count").unwrap_or(0),
+            min_value: sql_result.get_string("min_value"),
+            max_value: sql_result.get_string("max_value"),
+            last_used: sql_result
+                .get_timestamp_as_datetime("last_used")
+                .as_ref()
+                .map(date_time_to_iso),
+        })
+    }
+
+    /// Find the distinct values of the tag, the most used first
+    async fn search_tag_values(
+        &self,
+        trans: &mut SQLTransactionAsync<'_>,
+        tag: &TagElement,
+        prefix: Option<&str>,
+        start_page: Option<u32>,
+        page_size: Option<u32>,
+        customer_code: &str,
+    ) -> anyhow::Result<Vec<TagValueCount>> {
+        let tag_type = TagType::from_str(tag.tag_type.to_lowercase().as_str())
+            .map_err(|_| anyhow!("Wrong tag_type, tag_type=[{}]", &tag.tag_type))?;
+        let column = Self::value_column(&tag_type);
+        let value_text = Self::value_as_text(&tag_type, column);
+
+        let mut params = HashMap::new();
+        params.insert("p_tag_id".to_owned(), CellValue::from_raw_int(tag.tag_id));
+
+        // The string values are compared like the search filters do, in order to use the gin index
+        let prefix_condition = match prefix {
+            None => "".to_owned(),
+            Some(p) => {
+                params.insert(
+                    "p_prefix".to_owned(),
+                    CellValue::from_raw_string(like_prefix_pattern(p)),
+                );
+                match tag_type {
+                    TagType::Text | TagType::Link | TagType::Enum => {
+                        " AND unaccent_lower((tv.value_string)::text) LIKE unaccent_lower(:p_prefix) "
+                            .to_owned()
+                    }
+                    _ => format!(" AND {} LIKE :p_prefix ", &value_text),
+                }
+            }
+        };
+
+        let sql_query = format!(
+            r"SELECT {1} AS value, COUNT(DISTINCT tv.item_id) AS item_count
+                    FROM cs_{0}.tag_value tv
+                    WHERE tv.tag_id = :p_tag_id
+                    AND {2} IS NOT NULL {3}
+                    GROUP BY 1
+                    ORDER BY item_count DESC, value ",
+            customer_code, &value_text, column, &prefix_condition
+        );
+
+        let query = SQLQueryBlockAsync {
+            sql_query,
+            start: start_page.unwrap_or(0) * page_size.unwrap_or(0),
+            length: page_size,
+            params,
+        };
+
+        let mut sql_result: SQLDataSet = query.execute(trans).await.map_err(err_fwd!(
+            "Query failed, sql=[{}], follower=[{}]",
+            &query.sql_query,
+            &self.follower
+        ))?;
+
+        let mut values = vec![];
+        while sql_result.next() {
+            let value: String = sql_result
+                .get_string("value")
+                .ok_or(anyhow!("Wrong value"))?;
+            let item_count = sql_result.get_int("item_count").unwrap_or(0);
+            values.push(TagValueCount { value, item_count });
+        }
+
+        Ok(values)
+    }
+
+    /// The tag_value column holding the values of the tag type
+    fn value_column(tag_type: &TagType) -> &'static str {
+        match tag_type {
+            TagType::Text | TagType::Link | TagType::Enum => "tv.value_string",
+            TagType::Bool => "tv.value_boolean",
+            TagType::Int => "tv.value_integer",
+            TagType::Double => "tv.value_double",
+            TagType::Date => "tv.value_date",
+            TagType::DateTime => "tv.value_datetime",
+        }
+    }
+
+    /// Sql expression of a value as text, in the same format as the tag values of the api
+    fn value_as_text(tag_type: &TagType, expression: &str) -> String {
+        match tag_type {
+            TagType::Text | TagType::Link | TagType::Enum => expression.to_owned(),
+            TagType::DateTime => {
+                format!(r#"to_char({}, 'YYYY-MM-DD"T"HH24:MI:SS"Z"')"#, expression)
+            }
+            _ => format!("CAST({} AS VARCHAR)", expression),
+        }
+    }
+
+    ///
+    /// 🌟 Change the allowed values of an enum tag
+    ///     Renamed values are propagated to the items, a removed value must not be used anymore.
+    ///
+    pub async fn update_enum_values(
+        mut self,
+        tag_id: i64,
+        update_request: Json<UpdateEnumValuesRequest>,
+    ) -> WebType<SimpleMessage> {
+        log_info!(
+            "🚀 Start update_enum_values api, tag_id=[{}], update_request=[{:?}], follower=[{}]",
+            tag_id,
+            &update_request,
+            &self.follower
+        );
+
+        let entry_session = try_or_return!(
+            valid_sid_get_session(&self.session_token, &mut self.follower).await,
+            Self::web_type_error()
+        );
+
+        let customer_code = entry_session.customer_code.as_str();
+
+        if let Err(e) = Self::check_enum_values(&update_request.allowed_values) {
+            log_error!(
+                "💣 The allowed values are not correct, err message=[{}], follower=[{}]",
+                e.err_message,
+                &self.follower
+            );
+            return WebType::from_errorset(e);
+        }
+
+        // Open Db connection
+        let Ok(mut cnx) = SQLConnectionAsync::from_pool().await.map_err(err_fwd!(
+            "💣 New Db connection failed, follower=[{}]",
+            &self.follower
+        )) else {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        };
+
+        let Ok(mut trans) = cnx.begin().await.map_err(err_fwd!(
+            "💣 Transaction issue, follower=[{}]",
+            &self.follower
+        )) else {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        };
+
+        let tag = try_or_return!(
+            self.find_enum_tag(&mut trans, tag_id, customer_code).await,
+            Self::web_type_error()
+        );
+
+        let current_values = tag.allowed_values.unwrap_or_default();
+
+        // | Propagate the renamed values to the items
+        // The renames are applied all at once, so a swap (A->B, B->A) or a chain (A->B, B->C)
+        // reads the values from before the update
+        let mut renames: HashMap<String, String> = HashMap::new();
+        for rename in update_request.renames.iter().flatten() {
+            if !current_values.contains(&rename.old_value)
+                || !update_request.allowed_values.contains(&rename.new_value)
+                || renames
+                    .insert(rename.old_value.clone(), rename.new_value.clone())
+                    .is_some()
+            {
+                log_error!(
+                    "💣 Incorrect rename, old_value=[{}], new_value=[{}], follower=[{}]",
+                    &rename.old_value,
+                    &rename.new_value,
+                    &self.follower
+                );
+                return WebType::from_errorset(&INCORRECT_ENUM_ALLOWED_VALUES);
+            }
+        }
+
+        if !renames.is_empty() {
+            if self
+                .rename_enum_tag_values(
+                    &mut trans,
+                    tag_id,
+                    &renames,
+                    entry_session.user_id,
+                    customer_code,
+                )
+                .await
+                .is_err()
+            {
+                return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+            }
+
+            log_info!(
+                "😎 Renamed the enum values, renames=[{:?}], follower=[{}]",
+                &renames,
+                &self.follower
+            );
+        }
+
+        let default_value = tag
+            .default_value
+            .clone()
+            .map(|d| renames.get(&d).cloned().unwrap_or(d));
+
+        if let Some(d) = &default_value {
+            if !update_request.allowed_values.contains(d) {
+                log_error!(
+                    "💣 The default value is not allowed anymore, default_value=[{}], follower=[{}]",
+                    d,
+                    &self.follower
+                );
+                return WebType::from_errorset(&INCORRECT_DEFAULT_ENUM_VALUE);
+            }
+        }
+
+        if default_value != tag.default_value
+            && self
+                .update_default_value(&mut trans, tag_id, default_value.as_deref(), customer_code)
+                .await
+                .is_err()
+        {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        }
+
+        // | Replace the list of allowed values
+        if self
+            .delete_enum_values(&mut trans, tag_id, customer_code)
+            .await
+            .map_err(err_fwd!(
+                "💣 Enum values delete failed, tag_id=[{}], follower=[{}]",
+                tag_id,
+                &self.follower
+            ))
+            .is_err()
+        {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        }
+
+        if self
+            .insert_enum_values(
+                &mut trans,
+                tag_id,
+                &update_request.allowed_values,
+                customer_code,
+            )
+            .await
+            .is_err()
+        {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        }
+
+        // | A value removed from the list must not be used by any item
+        if self
+            .check_enum_value_usage(&mut trans, tag_id, customer_code)
+            .await
+            .is_err()
+        {
+            log_error!(
+                "💣 A removed enum value is still in use, tag id=[{}], follower=[{}]",
+                tag_id,
+                &self.follower
+            );
+            return WebType::from_errorset(&STILL_IN_USE);
+        }
+
+        if trans
+            .commit()
+            .await
+            .map_err(err_fwd!("💣 Commit failed, follower=[{}]", &self.follower))
+            .is_err()
+        {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        }
+
+        log_info!(
+            "🏁 End update_enum_values api, follower=[{}]",
+            &self.follower
+        );
+
+        WebType::from_item(
+            StatusCode::OK.as_u16(),
+            SimpleMessage {
+                message: "Ok".to_string(),
+            },
+        )
+    }
+
+    /// Find the tag and ensure it's an enum tag
+    async fn find_enum_tag(
+        &self,
+        trans: &mut SQLTransactionAsync<'_>,
+        tag_id: i64,
+        customer_code: &str,
+    ) -> Result<TagElement, &'static ErrorSet<'static>> {
+        let tag = self.find_tag(trans, tag_id, customer_code).await?;
+        if tag.tag_type != TagType::Enum.as_str() {
+            log_error!(
+                "💣 The tag is not an enum, tag_id=[{}], tag_type=[{}], follower=[{}]",
+                tag_id,
+                &tag.tag_type,
+                &self.follower
+            );
+            return Err(&NOT_AN_ENUM_TAG);
+        }
+
+        Ok(tag)
+    }
+
+    /// Read the tag definition, MISSING_TAG if it does not exist
+    async fn find_tag(
+        &self,
+        trans: &mut SQLTransactionAsync<'_>,
+        tag_id: i64,
+        customer_code: &str,
+    ) -> Result<TagElement, &'static ErrorSet<'static>> {
+        let Ok(mut tags) = self
+            .search_tag_by_id(trans, Some(tag_id), None, None, customer_code)
+            .await
+            .map_err(err_fwd!(
+                "💣 Cannot find the tag by id, follower=[{}]",
+                &self.follower
+            ))
+        else {
+            return Err(&INTERNAL_DATABASE_ERROR);
+        };
+
+        if tags.is_empty() {
+            log_error!(
+                "💣 Missing tag, tag_id=[{}], follower=[{}]",
+                tag_id,
+                &self.follower
+            );
+            return Err(&MISSING_TAG);
+        }
+
+        Ok(tags.remove(0))
+    }
+
+    /// Rename the values of the items in one statement, [renames] : old value -> new value
+    async fn rename_enum_tag_values(
+        &self,
+        trans: &mut SQLTransactionAsync<'_>,
+        tag_id: i64,
+        renames: &HashMap<String, String>,
+        user_id: i64,
+        customer_code: &str,
+    ) -> anyhow::Result<()> {
+        let json_renames = serde_json::to_string(renames)?;
+
+        // Keep a trace of the change on each item before renaming
+        let sql_history = format!(
+            r"INSERT INTO cs_{0}.tag_value_history (tag_value_id, item_id, tag_id, operation, old_value, new_value, user_id, changed_gmt)
+                    SELECT tv.id, tv.item_id, tv.tag_id, 'update', r.old_value, r.new_value, :p_user_id, :p_changed
+                    FROM cs_{0}.tag_value tv
+                    JOIN jsonb_each_text( CAST( :p_renames AS jsonb ) ) AS r ( old_value, new_value )
+                        ON tv.value_string = r.old_value
+                    WHERE tv.tag_id = :p_tag_id",
+            customer_code
+        );
+
+        let mut history_params = HashMap::new();
+        history_params.insert("p_tag_id".to_string(), CellValue::from_raw_int(tag_id));
+        history_params.insert(
+            "p_renames".to_string(),
+            CellValue::from_raw_str(&json_renames),
+        );
+        history_params.insert("p_user_id".to_string(), CellValue::from_raw_int(user_id));
+        history_params.insert(
+            "p_changed".to_string(),
+            CellValue::from_raw_systemtime(SystemTime::now()),
+        );
+
+        let history_insert = SQLChangeAsync {
+            sql_query: sql_history,
+            params: history_params,
+            sequence_name: "".to_string(),
+        };
+
+        history_insert.update(trans).await.map_err(err_fwd!(
+            "💣 Cannot record the tag value history, renames=[{}], follower=[{}]",
+            &json_renames,
+            &self.follower
+        ))?;
+
+        // Each row is matched with its value before the update
+        let sql_query = format!(
+            r"UPDATE cs_{}.tag_value tv
+                    SET value_string = r.new_value
+                    FROM jsonb_each_text( CAST( :p_renames AS jsonb ) ) AS r ( old_value, new_value )
+                    WHERE tv.tag_id = :p_tag_id AND tv.value_string = r.old_value",
+            customer_code
+        );
+
+        let mut params = HashMap::new();
+        params.insert("p_tag_id".to_string(), CellValue::from_raw_int(tag_id));
+        params.insert(
+            "p_renames".to_string(),
+            CellValue::from_raw_str(&json_renames),
+        );
+
+        let sql_update = SQLChangeAsync {
+            sql_query,
+            params,
+            sequence_name: "".to_string(),
+        };
+
+        sql_update.update(trans).await.map_err(err_fwd!(
+            "💣 Rename of the enum values failed, renames=[{}], follower=[{}]",
+            &json_renames,
+            &self.follower
+        ))?;
+        Ok(())
+    }
+
+    async fn update_default_value(
+        &self,
+        trans: &mut SQLTransactionAsync<'_>,
+        tag_id: i64,
+        default_value: Option<&str>,
+        customer_code: &str,
+    ) -> anyhow::Result<()> {
+        let sql_query = format!(
+            r"UPDATE cs_{}.tag_definition
+                    SET default_value = :p_default_value
+                    WHERE id = :p_tag_id",
+            customer_code
+        );
+
+        let mut params = HashMap::new();
+        params.insert("p_tag_id".to_string(), CellValue::from_raw_int(tag_id));
+        params.insert(
+            "p_default_value".to_string(),
+            CellValue::from_opt_str(default_value),
+        );
+
+        let sql_update = SQLChangeAsync {
+            sql_query,
+            params,
+            sequence_name: "".to_string(),
+        };
+
+        let _ = sql_update.update(trans).await.map_err(err_fwd!(
+            "💣 Update of the default value failed, tag_id=[{}], follower=[{}]",
+            tag_id,
+            &self.follower
+        ))?;
+        Ok(())
+    }
+
+    /// Fail if an item holds a value which is not in the allowed values of the tag
+    async fn check_enum_value_usage(
+        &self,
+        trans: &mut SQLTransactionAsync<'_>,
+        tag_id: i64,
+        customer_code: &str,
+    ) -> anyhow::Result<()> {
+        let sql_query = format!(
+            r"SELECT 1 FROM cs_{0}.tag_value tv
+                    WHERE tv.tag_id = :p_tag_id
+                    AND NOT EXISTS ( SELECT 1 FROM cs_{0}.tag_enum_value ev
+                                        WHERE ev.tag_id = tv.tag_id AND ev.value = tv.value_string )",
+            customer_code
+        );
+
+        let mut params = HashMap::new();
+        params.insert("p_tag_id".to_owned(), CellValue::from_raw_int(tag_id));
+
+        let sql = SQLQueryBlockAsync {
+            sql_query,
+            start: 0,
+            length: Some(1),
+            params,
+        };
+
+        let dataset = sql.execute(trans).await.map_err(tr_fwd!())?;
+
+        if dataset.len() > 0 {
+            return Err(anyhow::anyhow!(
+                "Enum value still in use, follower=[{}]",
+                &self.follower
+            ));
+        }
+
+        Ok(())
+    }
+
+    ///
+    /// Return a None if the tag definition is correct
+    ///
+    pub(crate) fn check_input_values(
+        &self,
+        add_tag_request: &AddTagRequest,
+    ) -> Result<(), &ErrorSet<'static>> {
+        log_info!(
+            "Check the tag definition, add_tag_request=[{:?}], follower=[{}]",
+            add_tag_request,
+            &self.follower
+        );
+
+        // Check the tag name
+        if has_not_printable_char(&add_tag_request.name) {
+            return Err(&INCORRECT_CHAR_TAG_NAME);
+        }
+
+        if add_tag_request.name.len() > 50 {
+            return Err(&INCORRECT_LENGTH_TAG_NAME);
+        }
+
+        let tag_type = match TagType::from_str(add_tag_request.tag_type.to_lowercase().as_str()) {
+            Ok(v) => v,
+            Err(_) => {
+                return Err(&INCORRECT_TAG_TYPE);
+            }
+        };
+
+        if tag_type != TagType::Enum && add_tag_request.allowed_values.is_some() {
+            return Err(&INCORRECT_ENUM_ALLOWED_VALUES);
+        }
+
+        // Check the input values ( ie tag_type, length limit, default_value type, etc )
+        match tag_type {
+            TagType::Text => {
+                if let Some(default_string) = &add_tag_request.default_value {
+                    if default_string.len() > MAX_STRING_LENGTH as usize {
+                        return Err(&INCORRECT_DEFAULT_STRING_LENGTH);
+                    }
+                }
+            }
+            TagType::Link => {
+                // A Link is like a string
+                const MAX_LINK_LENGTH: usize = 400;
+                if let Some(default_string) = &add_tag_request.default_value {
+                    if default_string.len() > MAX_LINK_LENGTH as usize {
+                        return Err(&INCORRECT_DEFAULT_LINK_LENGTH);
+                    }
+                }
+            }
+            TagType::Bool => {
+                if let Some(v) = &add_tag_request.default_value {
+                    if v != "true" && v != "false" {
+                        return Err(&INCORRECT_DEFAULT_BOOLEAN_VALUE);
+                    }
+                }
+            }
+            TagType::Int => {
+                if let Some(v) = &add_tag_request.default_value {
+                    if v.parse::<i64>().is_err() {
+                        return Err(&INCORRECT_DEFAULT_INTEGER_VALUE);
+                    }
+                }
+            }
+            TagType::Double => {
+                if let Some(d) = &add_tag_request.default_value {
+                    if d.parse::<f64>().is_err() {
+                        return Err(&INCORRECT_DEFAULT_DOUBLE_VALUE);
+                    }
+                }
+            }
+            TagType::Date => {
+                if let Some(d_str) = &add_tag_request.default_value {
+                    // Check if the default is a valid date  ISO8601 1977-04-22
+                    if iso_to_naivedate(d_str).is_err() {
+                        return Err(&INCORRECT_DEFAULT_DATE_VALUE);
+                    }
+                }
+            }
+            TagType::DateTime => {
+                if let Some(dt_str) = &add_tag_request.default_value {
+                    // Check if the default is a valid datetime ISO8601 "1977-04-22T06:00:00Z"
+                    if iso_to_datetime(dt_str).is_err() {
+                        return Err(&INCORRECT_DEFAULT_DATETIME_VALUE);
+                    }
+                }
+            }
+            TagType::Enum => {
+                let Some(allowed_values) = &add_tag_request.allowed_values else {
+                    return Err(&INCORRECT_ENUM_ALLOWED_VALUES);
+                };
+                Self::check_enum_values(allowed_values)?;
+                if let Some(v) = &add_tag_request.default_value {
+                    if !allowed_values.contains(v) {
+                        return Err(&INCORRECT_DEFAULT_ENUM_VALUE);
+                    }
+                }
+            }
+        };
+
+        if let Some(constraints) = &add_tag_request.constraints {
+            Self::check_constraints_definition(&tag_type, constraints)?;
+            // The default value must respect the constraints too
+            if let Some(default_value) = &add_tag_request.default_value {
+                let value = EnumTagValue::from_string(default_value, tag_type.as_str())
+                    .map_err(|_| &*INCORRECT_TAG_CONSTRAINTS)?;
+                if Self::check_value_constraints(&tag_type, constraints, &value).is_err() {
+                    return Err(&INCORRECT_TAG_CONSTRAINTS);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    ///
+    /// Min and max are only for the integer, double and date tags, pattern and max length only for the text tags
+    ///
+    fn check_constraints_definition(
+        tag_type: &TagType,
+        constraints: &TagConstraints,
+    ) -> Result<(), &'static ErrorSet<'static>> {
+        match tag_type {
+            TagType::Int => {
+                Self::check_bounds(constraints, |v| v.parse::<i64>().ok())?;
+            }
+            TagType::Double => {
+                Self::check_bounds(constraints, |v| {
+                    v.parse::<f64>().ok().filter(|d| d.is_finite())
+                })?;
+            }
+            TagType::Date => {
+                Self::check_bounds(constraints, |v| iso_to_naivedate(v).ok())?;
+            }
+            _ => {
+                if constraints.min_value.is_some() || constraints.max_value.is_some() {
+                    return Err(&INCORRECT_TAG_CONSTRAINTS);
+                }
+            }
+        }
+
+        if *tag_type == TagType::Text {
+            if let Some(max_length) = constraints.max_length {
+                if max_length <= 0 || max_length as usize > MAX_STRING_LENGTH {
+                    return Err(&INCORRECT_TAG_CONSTRAINTS);
+                }
+            }
+            if let Some(pattern) = &constraints.pattern {
+                if pattern.is_empty() || pattern.len() > 255 || Regex::new(pattern).is_err() {
+                    return Err(&INCORRECT_TAG_CONSTRAINTS);
+                }
+            }
+        } else if constraints.max_length.is_some() || constraints.pattern.is_some() {
+            return Err(&INCORRECT_TAG_CONSTRAINTS);
+        }
+
+        Ok(())
+    }
+
+    /// Both bounds must be parsable for the tag type and the min must not be greater than the max
+    fn check_bounds<T: PartialOrd>(
+        constraints: &TagConstraints,
+        parse: impl Fn(&str) -> Option<T>,
+    ) -> Result<(), &'static ErrorSet<'static>> {
+        let min = match &constraints.min_value {
+            Some(v) => Some(parse(v).ok_or(&*INCORRECT_TAG_CONSTRAINTS)?),
+            None => None,
+        };
+        let max = match &constraints.max_value {
+            Some(v) => Some(parse(v).ok_or(&*INCORRECT_TAG_CONSTRAINTS)?),
+            None => None,
+        };
+        if let (Some(min), Some(max)) = (min, max) {
+            if min > max {
+                return Err(&INCORRECT_TAG_CONSTRAINTS);
+            }
+        }
+        Ok(())
+    }
+
+    ///
+    /// Check a tag value against the constraints of its definition
+    /// Return the reason of the violation, to be reported with the tag name
+    ///
+    pub(crate) fn check_value_constraints(
+        tag_type: &TagType,
+        constraints: &TagConstraints,
+        value: &EnumTagValue,
+    ) -> Result<(), String> {
+        let out_of_bounds = |v: &str| match (&constraints.min_value, &constraints.max_value) {
+            (Some(min), Some(max)) => {
+                format!("value [{}] is not between [{}] and [{}]", v, min, max)
+            }
+            (Some(min), None) => format!("value [{}] is lower than the minimum [{}]", v, min),
+            (None, _) => format!(
+                "value [{}] is greater than the maximum [{}]",
+                v,
+                constraints.max_value.as_deref().unwrap_or_default()
+            ),
+        };
+
+        match value {
+            EnumTagValue::Integer(Some(i)) if *tag_type == TagType::Int => {
+                let below = Self::parse_bound(&constraints.min_value, |b| b.parse::<i64>().ok())
+                    .is_some_and(|min| *i < min);
+                let above = Self::parse_bound(&constraints.max_value, |b| b.parse::<i64>().ok())
+                    .is_some_and(|max| *i > max);
+                if below || above {
+                    return Err(out_of_bounds(&i.to_string()));
+                }
+            }
+            EnumTagValue::Double(Some(d)) if *tag_type == TagType::Double => {
+                let below = Self::parse_bound(&constraints.min_value, |b| b.parse::<f64>().ok())
+                    .is_some_and(|min| *d < min);
+                let above = Self::parse_bound(&constraints.max_value, |b| b.parse::<f64>().ok())
+                    .is_some_and(|max| *d > max);
+                if below || above {
+                    return Err(out_of_bounds(&d.to_string()));
+                }
+            }
+            EnumTagValue::SimpleDate(Some(s)) if *tag_type == TagType::Date => {
+                let date =
+                    iso_to_naivedate(s).map_err(|_| format!("value [{}] is not a date", s))?;
+                let below = Self::parse_bound(&constraints.min_value, |b| iso_to_naivedate(b).ok())
+                    .is_some_and(|min| date < min);
+                let above = Self::parse_bound(&constraints.max_value, |b| iso_to_naivedate(b).ok())
+                    .is_some_and(|max| date > max);
+                if below || above {
+                    return Err(out_of_bounds(s));
+                }
+            }
+            EnumTagValue::Text(Some(s)) if *tag_type == TagType::Text => {
+                let max_length = constraints.max_length.unwrap_or(MAX_STRING_LENGTH as i32);
+                if s.chars().count() > max_length as usize {
+                    return Err(format!("value is longer than [{}] characters", max_length));
+                }
+                if let Some(pattern) = &constraints.pattern {
+                    // The whole value must match, not only a part of it
+                    let re = Regex::new(&format!("^(?:{})$", pattern))
+                        .map_err(|_| format!("pattern [{}] is not valid", pattern))?;
+                    if !re.is_match(s) {
+                        return Err(format!(
+                            "value [{}] does not match the pattern [{}]",
+                            s, pattern
+                        ));
+                    }
+                }
+            }
+            EnumTagValue::Text(None)
+            | EnumTagValue::Boolean(None)
+            | EnumTagValue::Integer(None)
+            | EnumTagValue::Double(None)
+            | EnumTagValue::SimpleDate(None)
+            | EnumTagValue::DateTime(None)
+            | EnumTagValue::Link(None)
+            | EnumTagValue::Enumeration(None) => {
+                if constraints.required.unwrap_or(false) {
+                    return Err("value is required".to_string());
+                }
+            }
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    fn parse_bound<T>(bound: &Option<String>, parse: impl Fn(&str) -> Option<T>) -> Option<T> {
+        bound.as_deref().and_then(parse)
+    }
+
+    ///
+    /// The list of allowed values must be non empty, without duplicates and each value must fit in a tag value
+    ///
+    fn check_enum_values(allowed_values: &[String]) -> Result<(), &'static ErrorSet<'static>> {
+        const MAX_ENUM_VALUE_LENGTH: usize = 255;
+
+        if allowed_values.is_empty() {
+            return Err(&INCORRECT_ENUM_ALLOWED_VALUES);
+        }
+
+        for (i, value) in allowed_values.iter().enumerate() {
+            if value.is_empty()
+                || value.len() > MAX_ENUM_VALUE_LENGTH
+                || value.chars().any(|c| c.is_control())
+                || allowed_values[..i].contains(value)
+            {
+                return Err(&INCORRECT_ENUM_ALLOWED_VALUES);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn web_type_error<T>() -> impl Fn(&ErrorSet<'static>) -> WebType<T>
+    where
+        T: DeserializeOwned,
+    {
+        |e| {
+            log_error!("💣 Error after try {:?}", e);
+            WebType::from_errorset(e)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use chrono::{DateTime, Datelike, Timelike, Utc};
+
+    use commons_pg::sql_transaction::{iso_to_datetime, iso_to_naivedate};
+    use commons_pg::sql_transaction_async::{
+        SQLChangeAsync, SQLConnectionAsync, SQLQueryBlockAsync,
+    };
+    use commons_services::token_lib::SessionToken;
+    use commons_services::x_request_id::XRequestID;
+    use dkdto::{EnumTagValue, TagConstraints, TagType};
+
+    use crate::tag::{like_prefix_pattern, TagDelegate};
+    use crate::test_db::{create_customer_schema, TEST_DB_URL};
+
+    #[test]
+    fn like_prefix_pattern_test() {
+        assert_eq!("inv%", like_prefix_pattern("inv"));
+        assert_eq!("100\\%%", like_prefix_pattern("100%"));
+        assert_eq!("a\\_b%", like_prefix_pattern("a_b"));
+        assert_eq!("c:\\\\%", like_prefix_pattern("c:\\"));
+    }
+
+    #[test]
+    fn check_value_constraints_test() {
+        let bounds = |min: Option<&str>, max: Option<&str>| TagConstraints {
+            min_value: min.map(str::to_string),
+            max_value: max.map(str::to_string),
+            ..Default::default()
+        };
+
+        let score = bounds(Some("0"), Some("100"));
+        let check = |tag_type, constraints, value| {
+            TagDelegate::check_value_constraints(&tag_type, constraints, &value)
+        };
+        assert!(check(TagType::Int, &score, EnumTagValue::Integer(Some(50))).is_ok());
+        assert!(check(TagType::Int, &score, EnumTagValue::Integer(Some(101))).is_err());
+        assert!(check(TagType::Int, &score, EnumTagValue::Integer(Some(-1))).is_err());
+
+        let ratio = bounds(Some("0.5"), None);
+        assert!(check(TagType::Double, &ratio, EnumTagValue::Double(Some(0.5))).is_ok());
+        assert!(check(TagType::Double, &ratio, EnumTagValue::Double(Some(0.4))).is_err());
+
+        let period = bounds(Some("2020-01-01"), Some("2020-12-31"));
+        let date = |d: &str| EnumTagValue::SimpleDate(Some(d.to_string()));
+        assert!(check(TagType::Date, &period, date("2020-06-15")).is_ok());
+        assert!(check(TagType::Date, &period, date("2021-01-01")).is_err());
+
+        let code = TagConstraints {
+            pattern: Some("[A-Z]{3}-[0-9]+".to_string()),
+            max_length: Some(8),
+            required: Some(true),
+            ..Default::default()
+        };
+        let text = |t: &str| EnumTagValue::Text(Some(t.to_string()));
+        assert!(check(TagType::Text, &code, text("ABC-12")).is_ok());
+        assert!(check(TagType::Text, &code, text("xABC-12")).is_err());
+        assert!(check(TagType::Text, &code, text("ABC-123456")).is_err());
+        assert!(check(TagType::Text, &code, EnumTagValue::Text(None)).is_err());
+    }
+
+    #[test]
+    fn check_constraints_definition_test() {
+        let bounds = |min: &str, max: &str| TagConstraints {
+            min_value: Some(min.to_string()),
+            max_value: Some(max.to_string()),
+            ..Default::default()
+        };
+        assert!(
+            TagDelegate::check_constraints_definition(&TagType::Int, &bounds("1", "10")).is_ok()
+        );
+        assert!(
+            TagDelegate::check_constraints_definition(&TagType::Int, &bounds("10", "1")).is_err()
+        );
+        assert!(
+            TagDelegate::check_constraints_definition(&TagType::Int, &bounds("1", "x")).is_err()
+        );
+        assert!(
+            TagDelegate::check_constraints_definition(&TagType::Text, &bounds("1", "10")).is_err()
+        );
+
+        let pattern = |p: &str| TagConstraints {
+            pattern: Some(p.to_string()),
+            ..Default::default()
+        };
+        assert!(
+            TagDelegate::check_constraints_definition(&TagType::Text, &pattern("[a-z]+")).is_ok()
+        );
+        assert!(
+            TagDelegate::check_constraints_definition(&TagType::Text, &pattern("[a-z")).is_err()
+        );
+        assert!(
+            TagDelegate::check_constraints_definition(&TagType::Int, &pattern("[a-z]+")).is_err()
+        );
+    }
+
+    #[test]
+    fn check_enum_values_test() {
+        let values = |v: &[&str]| v.iter().map(|x| x.to_string()).collect::<Vec<String>>();
+
+        assert!(TagDelegate::check_enum_values(&values(&["draft", "review", "published"])).is_ok());
+        assert!(TagDelegate::check_enum_values(&values(&["In progress", "Done"])).is_ok());
+
+        assert!(TagDelegate::check_enum_values(&values(&[])).is_err());
+        assert!(TagDelegate::check_enum_values(&values(&["draft", ""])).is_err());
+        assert!(TagDelegate::check_enum_values(&values(&["draft", "review", "draft"])).is_err());
+        assert!(TagDelegate::check_enum_values(&values(&["dr\u{0007}aft"])).is_err());
+        assert!(TagDelegate::check_enum_values(&vec!["x".repeat(256)]).is_err());
+    }
+
+    #[test]
+    fn is_valid_datetime_test() {
+        assert!(iso_to_datetime("1977-04-22T06:12:04Z").is_ok());
+        assert!(iso_to_datetime("1977-04-22T23:12:04Z").is_ok());
+        assert!(iso_to_datetime("1977-04-22T23:12:04+01:00").is_ok());
+        assert!(iso_to_datetime("0000-12-04T22:12:04Z").is_ok());
+
+        assert!(iso_to_datetime("1977-04-22T26:12:04Z").is_err());
+        assert!(iso_to_datetime("1977-0422T22:12:04Z").is_err());
+        assert!(iso_to_datetime("1977-04T22:12:04Z").is_err());
         assert!(iso_to_datetime("1977-04-22T22:12:04+01").is_err());
         assert!(iso_to_datetime("1977-13-04T22:12:04Z").is_err());
     }
@@ -727,4 +2050,71 @@ mod test {
         let s = dt.to_rfc3339();
         dbg!(s);
     }
+
+    /// A swap (open <-> closed) and a chain (draft -> open -> closed) must not merge the values
+    /// Needs the database of the tests : cargo test -- --ignored
+    #[tokio::test]
+    #[ignore]
+    async fn rename_enum_tag_values_swap_test() -> anyhow::Result<()> {
+        let mut cnx = SQLConnectionAsync::new(TEST_DB_URL).await?;
+        let mut trans = cnx.begin().await?;
+        create_customer_schema(&mut trans, "renametest").await?;
+        SQLChangeAsync {
+            sql_query: r"
+                INSERT INTO cs_renametest.tag_definition (id, name, type) VALUES (1, 'status', 'enum');
+                INSERT INTO cs_renametest.item (id, name, created_gmt, last_modified_gmt)
+                    VALUES (1, 'a', now(), now()), (2, 'b', now(), now()), (3, 'c', now(), now());
+                INSERT INTO cs_renametest.tag_value (tag_id, item_id, value_string)
+                    VALUES (1, 1, 'open'), (1, 2, 'closed'), (1, 3, 'draft');"
+                .to_string(),
+            params: Default::default(),
+            sequence_name: "".to_string(),
+        }
+        .batch(&mut trans)
+        .await?;
+
+        let delegate = TagDelegate::new(
+            SessionToken("test".to_string()),
+            XRequestID::from_value(None),
+        );
+        let renames = HashMap::from([
+            ("open".to_string(), "closed".to_string()),
+            ("closed".to_string(), "open".to_string()),
+            ("draft".to_string(), "open".to_string()),
+        ]);
+        delegate
+            .rename_enum_tag_values(&mut trans, 1, &renames, 0, "renametest")
+            .await?;
+
+        let mut data = SQLQueryBlockAsync {
+            sql_query: r"SELECT tv.value_string,
+                    (SELECT COUNT(*) FROM cs_renametest.tag_value_history h
+                        WHERE h.item_id = tv.item_id) AS history_count
+                    FROM cs_renametest.tag_value tv ORDER BY tv.item_id"
+                .to_string(),
+            start: 0,
+            length: None,
+            params: Default::default(),
+        }
+        .execute(&mut trans)
+        .await?;
+        trans.rollback().await;
+
+        let mut values = vec![];
+        while data.next() {
+            values.push((
+                data.get_string("value_string").unwrap(),
+                data.get_int("history_count").unwrap(),
+            ));
+        }
+        assert_eq!(
+            vec![
+                ("closed".to_string(), 1),
+                ("open".to_string(), 1),
+                ("open".to_string(), 1)
+            ],
+            values
+        );
+        Ok(())
+    }
 }
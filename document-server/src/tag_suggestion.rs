@@ -0,0 +1,534 @@
+use std::collections::{HashMap, HashSet};
+
+use anyhow::anyhow;
+use axum::http::StatusCode;
+use log::*;
+use serde::de::DeserializeOwned;
+
+use commons_error::*;
+use commons_pg::sql_transaction::{CellValue, SQLDataSet};
+use commons_pg::sql_transaction_async::{
+    SQLConnectionAsync, SQLQueryBlockAsync, SQLTransactionAsync,
+};
+use commons_services::key_lib::fetch_customer_key;
+use commons_services::session_lib::valid_sid_get_session;
+use commons_services::token_lib::SessionToken;
+use commons_services::try_or_return;
+use commons_services::x_request_id::{Follower, XRequestID};
+use dkcrypto::dk_crypto::DkEncrypt;
+use dkdto::error_codes::{INTERNAL_DATABASE_ERROR, INTERNAL_TECHNICAL_ERROR};
+use dkdto::{ErrorSet, SuggestedTag, SuggestedTagsReply, TagType, WebType, WebTypeBuilder};
+use doka_cli::request_client::TokenType;
+
+use crate::ft_tokenizer::tsvector_lexemes;
+use crate::fulltext::FullTextDelegate;
+
+/// Number of suggested tags in the reply
+const MAX_SUGGESTED_TAGS: usize = 10;
+/// Number of the most used values of the text tags compared with the keywords
+const MAX_TEXT_TAG_VALUES: u32 = 1_000;
+
+/// A keyword of the document with the number of documents holding it
+struct KeywordStat {
+    lexeme: String,
+    term_count: i32,
+    doc_count: i64,
+}
+
+/// A value of an enumerated or text tag, with its encrypted lexemes in a language of the document
+struct CandidateValue {
+    tag_id: i64,
+    tag_name: String,
+    tag_type: String,
+    value: String,
+    lexemes: Vec<String>,
+}
+
+pub(crate) struct TagSuggestionDelegate {
+    pub session_token: SessionToken,
+    pub follower: Follower,
+}
+
+impl TagSuggestionDelegate {
+    pub fn new(session_token: SessionToken, x_request_id: XRequestID) -> Self {
+        Self {
+            session_token,
+            follower: Follower {
+                x_request_id: x_request_id.new_if_null(),
+                token_type: TokenType::None,
+            },
+        }
+    }
+
+    ///
+    /// 🌟 Values of the enumerated and text tags matching the TF-IDF keywords of the document of an item
+    ///     The values already set on the item are not suggested
+    ///
+    pub async fn get_suggested_tags(mut self, item_id: i64) -> WebType<SuggestedTagsReply> {
+        log_info!(
+            "🚀 Start get_suggested_tags api, item_id=[{}], follower=[{}]",
+            item_id,
+            &self.follower
+        );
+
+        let entry_session = try_or_return!(
+            valid_sid_get_session(&self.session_token, &mut self.follower).await,
+            Self::web_type_error()
+        );
+
+        let customer_code = entry_session.customer_code.as_str();
+
+        let Ok(customer_key) = fetch_customer_key(customer_code, &self.follower)
+            .await
+            .map_err(err_fwd!(
+                "💣 Cannot get the customer key, follower=[{}]",
+                &self.follower
+            ))
+        else {
+            return WebType::from_errorset(&INTERNAL_TECHNICAL_ERROR);
+        };
+
+        let Ok(mut cnx) = SQLConnectionAsync::from_pool().await.map_err(err_fwd!(
+            "💣 New Db connection failed, follower=[{}]",
+            &self.follower
+        )) else {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        };
+
+        let Ok(mut trans) = cnx.begin().await.map_err(err_fwd!(
+            "💣 Transaction issue, follower=[{}]",
+            &self.follower
+        )) else {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        };
+
+        let file_ref = try_or_return!(
+            self.fulltext_delegate()
+                .find_item_file_ref(&mut trans, item_id, customer_code)
+                .await,
+            Self::web_type_error()
+        );
+
+        let Ok(keyword_scores) = self
+            .search_keyword_scores(&mut trans, &file_ref, customer_code)
+            .await
+            .map_err(err_fwd!(
+                "💣 Cannot read the keywords, file_ref=[{}], follower=[{}]",
+                &file_ref,
+                &self.follower
+            ))
+        else {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        };
+
+        let tags = if keyword_scores.is_empty() {
+            log_info!(
+                "No keyword for the document, file_ref=[{}], follower=[{}]",
+                &file_ref,
+                &self.follower
+            );
+            vec![]
+        } else {
+            let Ok(candidates) = self
+                .search_candidate_values(&mut trans, &file_ref, &customer_key, customer_code)
+                .await
+                .map_err(err_fwd!(
+                    "💣 Cannot read the tag values, follower=[{}]",
+                    &self.follower
+                ))
+            else {
+                return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+            };
+
+            let Ok(item_values) = self
+                .search_item_values(&mut trans, item_id, customer_code)
+                .await
+                .map_err(err_fwd!(
+                    "💣 Cannot read the tags of the item, follower=[{}]",
+                    &self.follower
+                ))
+            else {
+                return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+            };
+
+            suggest_tags(&keyword_scores, candidates, &item_values)
+        };
+
+        if trans
+            .commit()
+            .await
+            .map_err(err_fwd!("💣 Commit failed, follower=[{}]", &self.follower))
+            .is_err()
+        {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        }
+
+        log_info!(
+            "😎 Suggested tags, item_id=[{}], number of tags=[{}], follower=[{}]",
+            item_id,
+            tags.len(),
+            &self.follower
+        );
+        log_info!(
+            "🏁 End get_suggested_tags api, follower=[{}]",
+            &self.follower
+        );
+
+        WebType::from_item(
+            StatusCode::OK.as_u16(),
+            SuggestedTagsReply { item_id, tags },
+        )
+    }
+
+    /// The TF-IDF score of each keyword of the document, against the documents of the customer
+    async fn search_keyword_scores(
+        &self,
+        trans: &mut SQLTransactionAsync<'_>,
+        file_ref: &str,
+        customer_code: &str,
+    ) -> anyhow::Result<HashMap<String, f64>> {
+        let mut params = HashMap::new();
+        params.insert("p_file_ref".to_string(), CellValue::from_raw_str(file_ref));
+
+        let query = SQLQueryBlockAsync {
+            sql_query: format!(
+                r"SELECT k.lexeme, k.term_count, COALESCE(s.doc_count, 1) AS doc_count,
+                        ( SELECT COUNT(DISTINCT file_ref) FROM cs_{0}.document_keyword ) AS total_count
+                    FROM cs_{0}.document_keyword k
+                    LEFT JOIN cs_{0}.fulltext_term_stat s ON s.lexeme = k.lexeme
+                    WHERE k.file_ref = :p_file_ref",
+                customer_code
+            ),
+            start: 0,
+            length: None,
+            params,
+        };
+
+        let mut sql_result: SQLDataSet = query.execute(trans).await.map_err(err_fwd!(
+            "💣 Query failed, [{}], follower=[{}]",
+            &query.sql_query,
+            &self.follower
+        ))?;
+
+        let mut keywords = vec![];
+        let mut total_count = 0;
+        while sql_result.next() {
+            let lexeme = sql_result
+                .get_string("lexeme")
+                .ok_or(anyhow!("Wrong lexeme col"))?;
+            let term_count = sql_result
+                .get_int_32("term_count")
+                .ok_or(anyhow!("Wrong term_count col"))?;
+            let doc_count = sql_result.get_int_32("doc_count").unwrap_or(1) as i64;
+            total_count = sql_result.get_int("total_count").unwrap_or(1);
+            keywords.push(KeywordStat {
+                lexeme,
+                term_count,
+                doc_count,
+            });
+        }
+
+        Ok(keyword_scores(&keywords, total_count))
+    }
+
+    /// The values of the enumerated tags and the most used values of the text tags,
+    /// with their encrypted lexemes in each language of the document
+    async fn search_candidate_values(
+        &self,
+        trans: &mut SQLTransactionAsync<'_>,
+        file_ref: &str,
+        customer_key: &str,
+        customer_code: &str,
+    ) -> anyhow::Result<Vec<CandidateValue>> {
+        let mut params = HashMap::new();
+        params.insert("p_file_ref".to_string(), CellValue::from_raw_str(file_ref));
+
+        let query = SQLQueryBlockAsync {
+            sql_query: format!(
+                r"SELECT c.tag_id, c.tag_name, c.tag_type, c.value,
+                        CAST( to_tsvector( CAST(l.lang AS regconfig), unaccent_lower(c.value) ) as VARCHAR ) as tsv
+                    FROM (
+                        SELECT t.id AS tag_id, t.name AS tag_name, t.type AS tag_type, e.value
+                            FROM cs_{0}.tag_enum_value e
+                            INNER JOIN cs_{0}.tag_definition t ON t.id = e.tag_id
+                        UNION
+                        SELECT tv.tag_id, tv.tag_name, tv.tag_type, tv.value FROM (
+                            SELECT t.id AS tag_id, t.name AS tag_name, t.type AS tag_type, v.value_string AS value
+                                FROM cs_{0}.tag_value v
+                                INNER JOIN cs_{0}.tag_definition t ON t.id = v.tag_id
+                                WHERE t.type = '{1}' AND v.value_string IS NOT NULL
+                                GROUP BY t.id, t.name, t.type, v.value_string
+                                ORDER BY COUNT(*) DESC
+                                LIMIT {2} ) tv
+                    ) c
+                    CROSS JOIN ( SELECT DISTINCT lang FROM cs_{0}.document WHERE file_ref = :p_file_ref ) l",
+                customer_code,
+                TagType::Text.as_str(),
+                MAX_TEXT_TAG_VALUES
+            ),
+            start: 0,
+            length: None,
+            params,
+        };
+
+        let mut sql_result: SQLDataSet = query.execute(trans).await.map_err(err_fwd!(
+            "💣 Query failed, [{}], follower=[{}]",
+            &query.sql_query,
+            &self.follower
+        ))?;
+
+        let mut candidates = vec![];
+        while sql_result.next() {
+            let tsv = sql_result.get_string("tsv").unwrap_or_default();
+            candidates.push(CandidateValue {
+                tag_id: sql_result
+                    .get_int("tag_id")
+                    .ok_or(anyhow!("Wrong tag_id col"))?,
+                tag_name: sql_result
+                    .get_string("tag_name")
+                    .ok_or(anyhow!("Wrong tag_name col"))?,
+                tag_type: sql_result
+                    .get_string("tag_type")
+                    .ok_or(anyhow!("Wrong tag_type col"))?,
+                value: sql_result
+                    .get_string("value")
+                    .ok_or(anyhow!("Wrong value col"))?,
+                lexemes: tsvector_lexemes(&tsv)
+                    .iter()
+                    .map(|lexeme| DkEncrypt::hmac_word(lexeme, customer_key))
+                    .collect(),
+            });
+        }
+        Ok(candidates)
+    }
+
+    /// The text values already set on the item, [ ( <tag_id>, <value> ) ]
+    async fn search_item_values(
+        &self,
+        trans: &mut SQLTransactionAsync<'_>,
+        item_id: i64,
+        customer_code: &str,
+    ) -> anyhow::Result<HashSet<(i64, String)>> {
+        let mut params = HashMap::new();
+        params.insert("p_item_id".to_string(), CellValue::from_raw_int(item_id));
+
+        let query = SQLQueryBlockAsync {
+            sql_query: format!(
+                r"SELECT tag_id, value_string FROM cs_{}.tag_value
+                    WHERE item_id = :p_item_id AND value_string IS NOT NULL",
+                customer_code
+            ),
+            start: 0,
+            length: None,
+            params,
+        };
+
+        let mut sql_result: SQLDataSet = query.execute(trans).await.map_err(err_fwd!(
+            "💣 Query failed, [{}], follower=[{}]",
+            &query.sql_query,
+            &self.follower
+        ))?;
+
+        let mut item_values = HashSet::new();
+        while sql_result.next() {
+            let tag_id = sql_result
+                .get_int("tag_id")
+                .ok_or(anyhow!("Wrong tag_id col"))?;
+            let value = sql_result
+                .get_string("value_string")
+                .ok_or(anyhow!("Wrong value_string col"))?;
+            item_values.insert((tag_id, value));
+        }
+        Ok(item_values)
+    }
+
+    fn fulltext_delegate(&self) -> FullTextDelegate {
+        FullTextDelegate::new(
+            self.session_token.clone(),
+            self.follower.x_request_id.clone(),
+        )
+    }
+
+    fn web_type_error<T>() -> impl Fn(&ErrorSet<'static>) -> WebType<T>
+    where
+        T: DeserializeOwned,
+    {
+        |e| {
+            log_error!("💣 Error after try {:?}", e);
+            WebType::from_errorset(e)
+        }
+    }
+}
+
+/// Term frequency times the smoothed inverse document frequency,
+/// so a keyword found in all the documents still has a weight
+pub(crate) fn tf_idf(term_count: i32, doc_count: i64, total_count: i64) -> f64 {
+    let idf = ((1 + total_count) as f64 / (1 + doc_count) as f64).ln() + 1.0;
+    term_count as f64 * idf
+}
+
+/// The score of each keyword, relative to the best keyword of the document
+fn keyword_scores(keywords: &[KeywordStat], total_count: i64) -> HashMap<String, f64> {
+    let scores: Vec<(&str, f64)> = keywords
+        .iter()
+        .map(|k| {
+            (
+                k.lexeme.as_str(),
+                tf_idf(k.term_count, k.doc_count, total_count),
+            )
+        })
+        .collect();
+    let max_score = scores.iter().map(|(_, s)| *s).fold(0.0, f64::max);
+    if max_score <= 0.0 {
+        return HashMap::new();
+    }
+    scores
+        .into_iter()
+        .map(|(lexeme, score)| (lexeme.to_string(), score / max_score))
+        .collect()
+}
+
+///
+/// The values whose lexemes are all keywords of the document, the best first
+/// The score of a value is the average score of its lexemes, the best language is kept
+///
+fn suggest_tags(
+    keyword_scores: &HashMap<String, f64>,
+    candidates: Vec<CandidateValue>,
+    item_values: &HashSet<(i64, String)>,
+) -> Vec<SuggestedTag> {
+    let mut best: HashMap<(i64, String), SuggestedTag> = HashMap::new();
+    for candidate in candidates {
+        // A value made of stop words only has no lexeme
+        if candidate.lexemes.is_empty()
+            || item_values.contains(&(candidate.tag_id, candidate.value.clone()))
+        {
+            continue;
+        }
+        let Some(scores) = candidate
+            .lexemes
+            .iter()
+            .map(|lexeme| keyword_scores.get(lexeme).copied())
+            .collect::<Option<Vec<f64>>>()
+        else {
+            continue;
+        };
+        let score = scores.iter().sum::<f64>() / scores.len() as f64;
+
+        let key = (candidate.tag_id, candidate.value.clone());
+        if best.get(&key).is_some_and(|tag| tag.score >= score) {
+            continue;
+        }
+        best.insert(
+            key,
+            SuggestedTag {
+                tag_id: candidate.tag_id,
+                tag_name: candidate.tag_name,
+                tag_type: candidate.tag_type,
+                value: candidate.value,
+                score,
+            },
+        );
+    }
+
+    let mut tags: Vec<SuggestedTag> = best.into_values().collect();
+    tags.sort_by(|a, b| {
+        b.score
+            .total_cmp(&a.score)
+            .then(a.tag_name.cmp(&b.tag_name))
+            .then(a.value.cmp(&b.value))
+    });
+    tags.truncate(MAX_SUGGESTED_TAGS);
+    tags
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::{HashMap, HashSet};
+
+    use crate::tag_suggestion::{
+        keyword_scores, suggest_tags, tf_idf, CandidateValue, KeywordStat, MAX_SUGGESTED_TAGS,
+    };
+
+    fn keyword(lexeme: &str, term_count: i32, doc_count: i64) -> KeywordStat {
+        KeywordStat {
+            lexeme: lexeme.to_string(),
+            term_count,
+            doc_count,
+        }
+    }
+
+    fn candidate(tag_id: i64, value: &str, lexemes: &[&str]) -> CandidateValue {
+        CandidateValue {
+            tag_id,
+            tag_name: format!("tag_{}", tag_id),
+            tag_type: "enum".to_string(),
+            value: value.to_string(),
+            lexemes: lexemes.iter().map(|l| l.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn tf_idf_test() {
+        // A rare keyword weighs more than a common one
+        assert!(tf_idf(3, 1, 100) > tf_idf(3, 90, 100));
+        // A keyword of all the documents still has a weight
+        assert!(tf_idf(3, 100, 100) > 0.0);
+        assert_eq!(tf_idf(2, 5, 100) * 2.0, tf_idf(4, 5, 100));
+    }
+
+    #[test]
+    fn keyword_scores_test() {
+        let scores = keyword_scores(
+            &[
+                keyword("invoic", 10, 2),
+                keyword("year", 10, 50),
+                keyword("total", 1, 2),
+            ],
+            100,
+        );
+        assert_eq!(1.0, scores["invoic"]);
+        assert!(scores["year"] < scores["invoic"]);
+        assert!(scores["total"] < scores["year"]);
+        assert!(keyword_scores(&[], 0).is_empty());
+    }
+
+    #[test]
+    fn suggest_tags_test() {
+        let keyword_scores = HashMap::from([
+            ("invoic".to_string(), 1.0),
+            ("acm".to_string(), 0.6),
+            ("corp".to_string(), 0.2),
+            ("contract".to_string(), 0.4),
+        ]);
+        let candidates = vec![
+            candidate(1, "Invoice", &["invoic"]),
+            candidate(1, "Contract", &["contract"]),
+            candidate(1, "Order", &["order"]),
+            // Only when all the lexemes are keywords
+            candidate(2, "Acme Corp", &["acm", "corp"]),
+            candidate(2, "Acme Limited", &["acm", "limit"]),
+            // Only stop words
+            candidate(2, "The", &[]),
+            // The value in another language, the best score is kept
+            candidate(1, "Invoice", &["invoice"]),
+        ];
+        let item_values = HashSet::from([(1, "Contract".to_string())]);
+
+        let tags = suggest_tags(&keyword_scores, candidates, &item_values);
+        let values: Vec<(&str, f64)> = tags.iter().map(|t| (t.value.as_str(), t.score)).collect();
+        assert_eq!(vec![("Invoice", 1.0), ("Acme Corp", 0.4)], values);
+        assert_eq!("tag_1", tags[0].tag_name);
+    }
+
+    #[test]
+    fn suggest_tags_limit_test() {
+        let keyword_scores = HashMap::from([("a".to_string(), 1.0)]);
+        let candidates = (0..MAX_SUGGESTED_TAGS + 5)
+            .map(|i| candidate(i as i64, "A", &["a"]))
+            .collect();
+        let tags = suggest_tags(&keyword_scores, candidates, &HashSet::new());
+        assert_eq!(MAX_SUGGESTED_TAGS, tags.len());
+        assert_eq!("tag_0", tags[0].tag_name);
+    }
+}
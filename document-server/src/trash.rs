@@ -0,0 +1,1039 @@
+use std::collections::HashMap;
+use std::time::{Duration, SystemTime};
+
+use anyhow::anyhow;
+use axum::http::StatusCode;
+use axum::Json;
+use chrono::{DateTime, Utc};
+use log::{error, info, warn};
+use serde::de::DeserializeOwned;
+
+use commons_error::*;
+use commons_pg::sql_transaction::{date_time_to_iso, CellValue, SQLDataSet};
+use commons_pg::sql_transaction_async::{
+    SQLChangeAsync, SQLConnectionAsync, SQLQueryBlockAsync, SQLTransactionAsync,
+};
+use commons_services::session_lib::valid_sid_get_session;
+use commons_services::token_lib::SessionToken;
+use commons_services::try_or_return;
+use commons_services::x_request_id::{Follower, XRequestID};
+use dkconfig::properties::get_prop_value;
+use dkconfig::property_name::{FILE_SERVER_HOSTNAME_PROPERTY, FILE_SERVER_PORT_PROPERTY};
+use dkdto::error_codes::{
+    FILE_RELEASE_FAILED, INCORRECT_RETENTION_PERIOD, INTERNAL_DATABASE_ERROR, ITEM_NOT_IN_TRASH,
+    ITEM_ON_LEGAL_HOLD, MISSING_ITEM,
+};
+use dkdto::{
+    ErrorSet, GetTrashReply, SimpleMessage, TrashElement, TrashRetentionReply,
+    TrashRetentionRequest, WebType, WebTypeBuilder,
+};
+use doka_cli::async_request_client::FileServerClientAsync;
+use doka_cli::request_client::TokenType;
+
+use crate::fulltext::FullTextDelegate;
+use crate::item_lock::ItemLockDelegate;
+use crate::item_version::ItemVersionDelegate;
+
+/// Number of days an item stays in the trash when the customer did not set it
+const DEFAULT_TRASH_RETENTION_DAYS: i32 = 30;
+
+/// Key of the trash retention period in the customer settings
+const TRASH_RETENTION_SETTING: &str = "trash_retention_days";
+
+/// Where an item stands regarding the trash
+struct ItemState {
+    file_ref: Option<String>,
+    trashed: bool,
+    legal_hold: bool,
+}
+
+///
+/// Soft deletion of the items
+/// The trashed items are purged for good once the retention period of the customer is over.
+/// The expired items are purged periodically by the scheduler of the document server.
+///
+pub(crate) struct TrashDelegate {
+    pub session_token: SessionToken,
+    pub follower: Follower,
+}
+
+impl TrashDelegate {
+    pub fn new(session_token: SessionToken, x_request_id: XRequestID) -> Self {
+        Self {
+            session_token,
+            follower: Follower {
+                x_request_id: x_request_id.new_if_null(),
+                token_type: TokenType::None,
+            },
+        }
+    }
+
+    ///
+    /// 🌟 Move an item to the trash
+    ///
+    pub async fn trash_item(mut self, item_id: i64) -> WebType<SimpleMessage> {
+        log_info!(
+            "🚀 Start trash_item api, item_id=[{}], follower=[{}]",
+            item_id,
+            &self.follower
+        );
+
+        let entry_session = try_or_return!(
+            valid_sid_get_session(&self.session_token, &mut self.follower).await,
+            Self::web_type_error()
+        );
+
+        let customer_code = entry_session.customer_code.as_str();
+
+        let Ok(mut cnx) = SQLConnectionAsync::from_pool().await.map_err(err_fwd!(
+            "💣 New Db connection failed, follower=[{}]",
+            &self.follower
+        )) else {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        };
+
+        let Ok(mut trans) = cnx.begin().await.map_err(err_fwd!(
+            "💣 Transaction issue, follower=[{}]",
+            &self.follower
+        )) else {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        };
+
+        let state = match self
+            .find_item_state(&mut trans, item_id, customer_code)
+            .await
+        {
+            Ok(Some(state)) if !state.trashed => state,
+            Ok(_) => {
+                log_error!(
+                    "💣 Missing item, item_id=[{}], follower=[{}]",
+                    item_id,
+                    &self.follower
+                );
+                return WebType::from_errorset(&MISSING_ITEM);
+            }
+            Err(e) => {
+                log_error!(
+                    "💣 Cannot read the item, item_id=[{}], error=[{}], follower=[{}]",
+                    item_id,
+                    e,
+                    &self.follower
+                );
+                return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+            }
+        };
+
+        if state.legal_hold {
+            log_error!(
+                "💣 The item is on legal hold, item_id=[{}], follower=[{}]",
+                item_id,
+                &self.follower
+            );
+            return WebType::from_errorset(&ITEM_ON_LEGAL_HOLD);
+        }
+
+        // An item checked out by another session cannot be trashed
+        let item_lock_delegate = ItemLockDelegate::new(
+            self.session_token.clone(),
+            self.follower.x_request_id.clone(),
+        );
+        if let Err(e) = item_lock_delegate
+            .prepare_write(&mut trans, item_id, entry_session.id, None, customer_code)
+            .await
+        {
+            return WebType::from_errorset(e);
+        }
+
+        if self
+            .set_trashed(&mut trans, item_id, Some(SystemTime::now()), customer_code)
+            .await
+            .is_err()
+        {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        }
+
+        if trans
+            .commit()
+            .await
+            .map_err(err_fwd!("💣 Commit failed, follower=[{}]", &self.follower))
+            .is_err()
+        {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        }
+
+        log_info!(
+            "😎 The item is in the trash, item_id=[{}], file_ref=[{:?}], follower=[{}]",
+            item_id,
+            &state.file_ref,
+            &self.follower
+        );
+
+        log_info!("🏁 End trash_item api, follower=[{}]", &self.follower);
+
+        WebType::from_item(
+            StatusCode::OK.as_u16(),
+            SimpleMessage {
+                message: "Ok".to_string(),
+            },
+        )
+    }
+
+    ///
+    /// 🌟 Find the items in the trash, most recently trashed first
+    ///
+    pub async fn get_trash(
+        mut self,
+        start_page: Option<u32>,
+        page_size: Option<u32>,
+    ) -> WebType<GetTrashReply> {
+        log_info!(
+            "🚀 Start get_trash api, start_page=[{:?}], page_size=[{:?}], follower=[{}]",
+            start_page,
+            page_size,
+            &self.follower
+        );
+
+        let entry_session = try_or_return!(
+            valid_sid_get_session(&self.session_token, &mut self.follower).await,
+            Self::web_type_error()
+        );
+
+        let customer_code = entry_session.customer_code.as_str();
+
+        let Ok(mut cnx) = SQLConnectionAsync::from_pool().await.map_err(err_fwd!(
+            "💣 New Db connection failed, follower=[{}]",
+            &self.follower
+        )) else {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        };
+
+        let Ok(mut trans) = cnx.begin().await.map_err(err_fwd!(
+            "💣 Transaction issue, follower=[{}]",
+            &self.follower
+        )) else {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        };
+
+        let Ok(retention_days) = self
+            .read_retention_days(&mut trans, customer_code)
+            .await
+            .map_err(err_fwd!(
+                "💣 Cannot read the trash retention period, follower=[{}]",
+                &self.follower
+            ))
+        else {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        };
+
+        let Ok(items) = self
+            .search_trashed_items(
+                &mut trans,
+                start_page,
+                page_size,
+                retention_days,
+                customer_code,
+            )
+            .await
+            .map_err(err_fwd!(
+                "💣 Cannot find the trashed items, follower=[{}]",
+                &self.follower
+            ))
+        else {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        };
+
+        if trans
+            .commit()
+            .await
+            .map_err(err_fwd!("💣 Commit failed, follower=[{}]", &self.follower))
+            .is_err()
+        {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        }
+
+        log_info!(
+            "😎 We found the trashed items, item count=[{}], follower=[{}]",
+            items.len(),
+            &self.follower
+        );
+
+        log_info!("🏁 End get_trash api, follower=[{}]", &self.follower);
+
+        WebType::from_item(
+            StatusCode::OK.as_u16(),
+            GetTrashReply {
+                retention_days,
+                items,
+            },
+        )
+    }
+
+    ///
+    /// 🌟 Bring an item back from the trash
+    ///
+    pub async fn restore_item(mut self, item_id: i64) -> WebType<SimpleMessage> {
+        log_info!(
+            "🚀 Start restore_item api, item_id=[{}], follower=[{}]",
+            item_id,
+            &self.follower
+        );
+
+        let entry_session = try_or_return!(
+            valid_sid_get_session(&self.session_token, &mut self.follower).await,
+            Self::web_type_error()
+        );
+
+        let customer_code = entry_session.customer_code.as_str();
+
+        let Ok(mut cnx) = SQLConnectionAsync::from_pool().await.map_err(err_fwd!(
+            "💣 New Db connection failed, follower=[{}]",
+            &self.follower
+        )) else {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        };
+
+        let Ok(mut trans) = cnx.begin().await.map_err(err_fwd!(
+            "💣 Transaction issue, follower=[{}]",
+            &self.follower
+        )) else {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        };
+
+        if let Err(e) = self
+            .check_in_trash(&mut trans, item_id, customer_code)
+            .await
+        {
+            return WebType::from_errorset(e);
+        }
+
+        if self
+            .set_trashed(&mut trans, item_id, None, customer_code)
+            .await
+            .is_err()
+        {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        }
+
+        if trans
+            .commit()
+            .await
+            .map_err(err_fwd!("💣 Commit failed, follower=[{}]", &self.follower))
+            .is_err()
+        {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        }
+
+        log_info!(
+            "😎 The item is restored, item_id=[{}], follower=[{}]",
+            item_id,
+            &self.follower
+        );
+
+        log_info!("🏁 End restore_item api, follower=[{}]", &self.follower);
+
+        WebType::from_item(
+            StatusCode::OK.as_u16(),
+            SimpleMessage {
+                message: "Ok".to_string(),
+            },
+        )
+    }
+
+    ///
+    /// 🌟 Delete for good an item from the trash, its file is released on the file server
+    ///
+    pub async fn purge_item(mut self, item_id: i64) -> WebType<SimpleMessage> {
+        log_info!(
+            "🚀 Start purge_item api, item_id=[{}], follower=[{}]",
+            item_id,
+            &self.follower
+        );
+
+        let entry_session = try_or_return!(
+            valid_sid_get_session(&self.session_token, &mut self.follower).await,
+            Self::web_type_error()
+        );
+
+        let customer_code = entry_session.customer_code.as_str();
+
+        let Ok(mut cnx) = SQLConnectionAsync::from_pool().await.map_err(err_fwd!(
+            "💣 New Db connection failed, follower=[{}]",
+            &self.follower
+        )) else {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        };
+
+        let Ok(mut trans) = cnx.begin().await.map_err(err_fwd!(
+            "💣 Transaction issue, follower=[{}]",
+            &self.follower
+        )) else {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        };
+
+        let file_ref = match self
+            .check_in_trash(&mut trans, item_id, customer_code)
+            .await
+        {
+            Ok(state) if state.legal_hold => {
+                log_error!(
+                    "💣 The item is on legal hold, item_id=[{}], follower=[{}]",
+                    item_id,
+                    &self.follower
+                );
+                return WebType::from_errorset(&ITEM_ON_LEGAL_HOLD);
+            }
+            Ok(state) => state.file_ref,
+            Err(e) => return WebType::from_errorset(e),
+        };
+
+        let file_refs = match self
+            .purge(&mut trans, item_id, file_ref.as_deref(), customer_code)
+            .await
+        {
+            Ok(file_refs) => file_refs,
+            Err(e) => return WebType::from_errorset(e),
+        };
+
+        if trans
+            .commit()
+            .await
+            .map_err(err_fwd!("💣 Commit failed, follower=[{}]", &self.follower))
+            .is_err()
+        {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        }
+
+        self.release_files(&file_refs).await;
+
+        log_info!(
+            "😎 The item is purged, item_id=[{}], file_ref=[{:?}], follower=[{}]",
+            item_id,
+            &file_ref,
+            &self.follower
+        );
+
+        log_info!("🏁 End purge_item api, follower=[{}]", &self.follower);
+
+        WebType::from_item(
+            StatusCode::OK.as_u16(),
+            SimpleMessage {
+                message: "Ok".to_string(),
+            },
+        )
+    }
+
+    ///
+    /// 🌟 Change the number of days the items stay in the trash before they are purged
+    ///
+    pub async fn set_trash_retention(
+        mut self,
+        retention_request: Json<TrashRetentionRequest>,
+    ) -> WebType<TrashRetentionReply> {
+        log_info!(
+            "🚀 Start set_trash_retention api, retention_request=[{:?}], follower=[{}]",
+            &retention_request,
+            &self.follower
+        );
+
+        let entry_session = try_or_return!(
+            valid_sid_get_session(&self.session_token, &mut self.follower).await,
+            Self::web_type_error()
+        );
+
+        let customer_code = entry_session.customer_code.as_str();
+
+        let retention_days = retention_request.retention_days;
+        if retention_days <= 0 {
+            log_error!(
+                "💣 Incorrect trash retention period, retention_days=[{}], follower=[{}]",
+                retention_days,
+                &self.follower
+            );
+            return WebType::from_errorset(&INCORRECT_RETENTION_PERIOD);
+        }
+
+        let Ok(mut cnx) = SQLConnectionAsync::from_pool().await.map_err(err_fwd!(
+            "💣 New Db connection failed, follower=[{}]",
+            &self.follower
+        )) else {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        };
+
+        let Ok(mut trans) = cnx.begin().await.map_err(err_fwd!(
+            "💣 Transaction issue, follower=[{}]",
+            &self.follower
+        )) else {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        };
+
+        let sql_query = format!(
+            r"INSERT INTO cs_{}.customer_setting (setting_key, setting_value)
+                    VALUES (:p_setting_key, :p_setting_value)
+                    ON CONFLICT (setting_key) DO UPDATE SET setting_value = EXCLUDED.setting_value",
+            customer_code
+        );
+
+        let mut params = HashMap::new();
+        params.insert(
+            "p_setting_key".to_string(),
+            CellValue::from_raw_str(TRASH_RETENTION_SETTING),
+        );
+        params.insert(
+            "p_setting_value".to_string(),
+            CellValue::from_raw_string(retention_days.to_string()),
+        );
+
+        let sql_upsert = SQLChangeAsync {
+            sql_query,
+            params,
+            sequence_name: "".to_string(),
+        };
+
+        if sql_upsert
+            .update(&mut trans)
+            .await
+            .map_err(err_fwd!(
+                "💣 Cannot store the trash retention period, follower=[{}]",
+                &self.follower
+            ))
+            .is_err()
+        {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        }
+
+        if trans
+            .commit()
+            .await
+            .map_err(err_fwd!("💣 Commit failed, follower=[{}]", &self.follower))
+            .is_err()
+        {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        }
+
+        log_info!(
+            "🏁 End set_trash_retention api, follower=[{}]",
+            &self.follower
+        );
+
+        WebType::from_item(
+            StatusCode::OK.as_u16(),
+            TrashRetentionReply { retention_days },
+        )
+    }
+
+    /// Purge the items whose retention period is over, one transaction per item
+    /// A failure is only logged, the items will be purged on the next run of the scheduler
+    pub(crate) async fn purge_expired_items(&self, customer_code: &str) {
+        let Ok(mut cnx) = SQLConnectionAsync::from_pool().await.map_err(err_fwd!(
+            "💣 New Db connection failed, follower=[{}]",
+            &self.follower
+        )) else {
+            return;
+        };
+
+        let expired_items = {
+            let Ok(mut trans) = cnx.begin().await.map_err(err_fwd!(
+                "💣 Transaction issue, follower=[{}]",
+                &self.follower
+            )) else {
+                return;
+            };
+
+            let Ok(expired_items) = self
+                .find_expired_items(&mut trans, customer_code)
+                .await
+                .map_err(err_fwd!(
+                    "💣 Cannot find the expired items, follower=[{}]",
+                    &self.follower
+                ))
+            else {
+                return;
+            };
+            let _ = trans.commit().await;
+            expired_items
+        };
+
+        for (item_id, file_ref) in expired_items {
+            let Ok(mut trans) = cnx.begin().await.map_err(err_fwd!(
+                "💣 Transaction issue, follower=[{}]",
+                &self.follower
+            )) else {
+                return;
+            };
+
+            let Ok(file_refs) = self
+                .purge(&mut trans, item_id, file_ref.as_deref(), customer_code)
+                .await
+            else {
+                log_warn!(
+                    "⛔ The expired item is not purged, item_id=[{}], follower=[{}]",
+                    item_id,
+                    &self.follower
+                );
+                continue;
+            };
+
+            if trans
+                .commit()
+                .await
+                .map_err(err_fwd!("💣 Commit failed, follower=[{}]", &self.follower))
+                .is_ok()
+            {
+                log_info!(
+                    "😎 Purged the expired item, item_id=[{}], follower=[{}]",
+                    item_id,
+                    &self.follower
+                );
+                self.release_files(&file_refs).await;
+            }
+        }
+    }
+
+    /// Find the trashed items whose retention period is over, with their file reference
+    async fn find_expired_items(
+        &self,
+        trans: &mut SQLTransactionAsync<'_>,
+        customer_code: &str,
+    ) -> anyhow::Result<Vec<(i64, Option<String>)>> {
+        let retention_days = self.read_retention_days(trans, customer_code).await?;
+        let limit = SystemTime::now() - Duration::from_secs(retention_days as u64 * 24 * 3600);
+
+        let sql_query = format!(
+            r"SELECT it.id, it.file_ref FROM cs_{}.item it
+                    WHERE it.trashed_gmt IS NOT NULL
+                    AND it.trashed_gmt < :p_limit
+                    AND it.legal_hold = false
+                    ORDER BY it.trashed_gmt ",
+            customer_code
+        );
+
+        let mut params = HashMap::new();
+        params.insert("p_limit".to_string(), CellValue::from_raw_systemtime(limit));
+
+        let query = SQLQueryBlockAsync {
+            sql_query,
+            start: 0,
+            length: None,
+            params,
+        };
+
+        let mut sql_result: SQLDataSet = query
+            .execute(trans)
+            .await
+            .map_err(err_fwd!("Query failed, [{}]", &query.sql_query))?;
+
+        let mut expired_items = vec![];
+        while sql_result.next() {
+            let item_id = sql_result.get_int("id").ok_or(anyhow!("Wrong id"))?;
+            expired_items.push((item_id, sql_result.get_string("file_ref")));
+        }
+        Ok(expired_items)
+    }
+
+    /// Delete the item and everything linked to it, and give the files of all its versions
+    /// The caller releases the files with "release_files" once the transaction is committed
+    /// The caller ensures the item is not on legal hold
+    pub(crate) async fn purge(
+        &self,
+        trans: &mut SQLTransactionAsync<'_>,
+        item_id: i64,
+        file_ref: Option<&str>,
+        customer_code: &str,
+    ) -> Result<Vec<String>, &'static ErrorSet<'static>> {
+        let item_version_delegate = ItemVersionDelegate::new(
+            self.session_token.clone(),
+            self.follower.x_request_id.clone(),
+        );
+        let Ok(mut file_refs) = item_version_delegate
+            .find_version_file_refs(trans, item_id, customer_code)
+            .await
+            .map_err(err_fwd!(
+                "💣 Cannot find the versions, item_id=[{}], follower=[{}]",
+                item_id,
+                &self.follower
+            ))
+        else {
+            return Err(&INTERNAL_DATABASE_ERROR);
+        };
+        if let Some(file_ref) = file_ref {
+            if !file_refs.iter().any(|f| f == file_ref) {
+                file_refs.push(file_ref.to_string());
+            }
+        }
+
+        // | The parts of the files go with their keywords and their document frequencies
+        let fulltext_delegate = FullTextDelegate::new(
+            self.session_token.clone(),
+            self.follower.x_request_id.clone(),
+        );
+        for file_ref in &file_refs {
+            if fulltext_delegate
+                .delete_document_parts(trans, file_ref, customer_code)
+                .await
+                .is_err()
+            {
+                return Err(&INTERNAL_DATABASE_ERROR);
+            }
+        }
+
+        let sql_deletes = [
+            format!(
+                r"DELETE FROM cs_{0}.item_comment_mention WHERE comment_id IN
+                        (SELECT ic.id FROM cs_{0}.item_comment ic WHERE ic.item_id = :p_item_id)",
+                customer_code
+            ),
+            format!(
+                "DELETE FROM cs_{}.item_comment WHERE item_id = :p_item_id",
+                customer_code
+            ),
+            format!(
+                "DELETE FROM cs_{}.tag_value_history WHERE item_id = :p_item_id",
+                customer_code
+            ),
+            format!(
+                "DELETE FROM cs_{}.tag_value WHERE item_id = :p_item_id",
+                customer_code
+            ),
+            format!(
+                "DELETE FROM cs_{}.item_version WHERE item_id = :p_item_id",
+                customer_code
+            ),
+            format!(
+                "DELETE FROM cs_{}.item WHERE id = :p_item_id",
+                customer_code
+            ),
+        ];
+
+        for sql_delete in sql_deletes {
+            let mut params = HashMap::new();
+            params.insert("p_item_id".to_string(), CellValue::from_raw_int(item_id));
+
+            let query = SQLChangeAsync {
+                sql_query: sql_delete,
+                params,
+                sequence_name: "".to_string(),
+            };
+
+            if query
+                .delete(trans)
+                .await
+                .map_err(err_fwd!(
+                    "💣 Query failed, [{}], follower=[{}]",
+                    &query.sql_query,
+                    &self.follower
+                ))
+                .is_err()
+            {
+                return Err(&INTERNAL_DATABASE_ERROR);
+            }
+        }
+
+        Ok(file_refs)
+    }
+
+    /// Release the files of a purged item on the file server
+    /// A failure is only logged, the item is already gone
+    pub(crate) async fn release_files(&self, file_refs: &[String]) {
+        for file_ref in file_refs {
+            if self.release_file(file_ref).await.is_err() {
+                log_warn!(
+                    "⛔ The file of the purged item is not released, file_ref=[{}], follower=[{}]",
+                    file_ref,
+                    &self.follower
+                );
+            }
+        }
+    }
+
+    /// Ask the file server to remove the parts of the file
+    async fn release_file(&self, file_ref: &str) -> Result<(), &'static ErrorSet<'static>> {
+        let Ok(file_server_client) = Self::find_file_server_client().map_err(err_fwd!(
+            "💣 Cannot read the file server information, follower=[{}]",
+            &self.follower
+        )) else {
+            return Err(&FILE_RELEASE_FAILED);
+        };
+
+        match file_server_client
+            .release_file(file_ref, &self.session_token.0)
+            .await
+        {
+            Ok(_) => {
+                log_info!(
+                    "😎 The file is released, file_ref=[{}], follower=[{}]",
+                    file_ref,
+                    &self.follower
+                );
+                Ok(())
+            }
+            Err(e) => {
+                log_error!(
+                    "💣 The file server failed to release the file, file_ref=[{}], error=[{:?}], follower=[{}]",
+                    file_ref,
+                    e,
+                    &self.follower
+                );
+                Err(&FILE_RELEASE_FAILED)
+            }
+        }
+    }
+
+    fn find_file_server_client() -> anyhow::Result<FileServerClientAsync> {
+        let file_server_host = get_prop_value(FILE_SERVER_HOSTNAME_PROPERTY)?;
+        let file_server_port = get_prop_value(FILE_SERVER_PORT_PROPERTY)?.parse::<u16>()?;
+        Ok(FileServerClientAsync::new(
+            &file_server_host,
+            file_server_port,
+        ))
+    }
+
+    /// Ensure the item is in the trash
+    async fn check_in_trash(
+        &self,
+        trans: &mut SQLTransactionAsync<'_>,
+        item_id: i64,
+        customer_code: &str,
+    ) -> Result<ItemState, &'static ErrorSet<'static>> {
+        match self.find_item_state(trans, item_id, customer_code).await {
+            Ok(Some(state)) if state.trashed => Ok(state),
+            Ok(Some(_)) => {
+                log_error!(
+                    "💣 The item is not in the trash, item_id=[{}], follower=[{}]",
+                    item_id,
+                    &self.follower
+                );
+                Err(&ITEM_NOT_IN_TRASH)
+            }
+            Ok(None) => {
+                log_error!(
+                    "💣 Missing item, item_id=[{}], follower=[{}]",
+                    item_id,
+                    &self.follower
+                );
+                Err(&MISSING_ITEM)
+            }
+            Err(e) => {
+                log_error!(
+                    "💣 Cannot read the item, item_id=[{}], error=[{}], follower=[{}]",
+                    item_id,
+                    e,
+                    &self.follower
+                );
+                Err(&INTERNAL_DATABASE_ERROR)
+            }
+        }
+    }
+
+    async fn find_item_state(
+        &self,
+        trans: &mut SQLTransactionAsync<'_>,
+        item_id: i64,
+        customer_code: &str,
+    ) -> anyhow::Result<Option<ItemState>> {
+        let sql_query = format!(
+            r"SELECT it.file_ref, it.trashed_gmt, it.legal_hold FROM cs_{}.item it
+                    WHERE it.id = :p_item_id ",
+            customer_code
+        );
+
+        let mut params = HashMap::new();
+        params.insert("p_item_id".to_string(), CellValue::from_raw_int(item_id));
+
+        let query = SQLQueryBlockAsync {
+            sql_query,
+            start: 0,
+            length: Some(1),
+            params,
+        };
+
+        let mut sql_result: SQLDataSet = query
+            .execute(trans)
+            .await
+            .map_err(err_fwd!("Query failed, [{}]", &query.sql_query))?;
+
+        if !sql_result.next() {
+            return Ok(None);
+        }
+
+        Ok(Some(ItemState {
+            file_ref: sql_result.get_string("file_ref"),
+            trashed: sql_result.get_timestamp("trashed_gmt").is_some(),
+            legal_hold: sql_result.get_bool("legal_hold").unwrap_or(false),
+        }))
+    }
+
+    /// Set or clear the trash date of the item
+    async fn set_trashed(
+        &self,
+        trans: &mut SQLTransactionAsync<'_>,
+        item_id: i64,
+        trashed: Option<SystemTime>,
+        customer_code: &str,
+    ) -> anyhow::Result<()> {
+        let sql_query = format!(
+            r"UPDATE cs_{}.item SET trashed_gmt = :p_trashed WHERE id = :p_item_id",
+            customer_code
+        );
+
+        let mut params = HashMap::new();
+        params.insert(
+            "p_trashed".to_string(),
+            CellValue::from_opt_systemtime(trashed),
+        );
+        params.insert("p_item_id".to_string(), CellValue::from_raw_int(item_id));
+
+        let sql_update = SQLChangeAsync {
+            sql_query,
+            params,
+            sequence_name: "".to_string(),
+        };
+
+        sql_update.update(trans).await.map_err(err_fwd!(
+            "💣 Cannot change the trash state of the item, item_id=[{}], follower=[{}]",
+            item_id,
+            &self.follower
+        ))
+    }
+
+    async fn search_trashed_items(
+        &self,
+        trans: &mut SQLTransactionAsync<'_>,
+        start_page: Option<u32>,
+        page_size: Option<u32>,
+        retention_days: i32,
+        customer_code: &str,
+    ) -> anyhow::Result<Vec<TrashElement>> {
+        let sql_query = format!(
+            r"SELECT it.id, it.name, it.file_ref, it.trashed_gmt, dt.name AS document_type
+                    FROM cs_{0}.item it
+                    LEFT JOIN cs_{0}.document_type dt ON dt.id = it.document_type_id
+                    WHERE it.trashed_gmt IS NOT NULL
+                    ORDER BY it.trashed_gmt DESC, it.id ",
+            customer_code
+        );
+
+        let query = SQLQueryBlockAsync {
+            sql_query,
+            start: start_page.unwrap_or(0) * page_size.unwrap_or(0),
+            length: page_size,
+            params: HashMap::new(),
+        };
+
+        let mut sql_result: SQLDataSet = query
+            .execute(trans)
+            .await
+            .map_err(err_fwd!("Query failed, [{}]", &query.sql_query))?;
+
+        let mut items = vec![];
+        while sql_result.next() {
+            let trashed = sql_result
+                .get_timestamp_as_datetime("trashed_gmt")
+                .ok_or(anyhow!("Wrong trashed gmt"))?;
+            items.push(TrashElement {
+                item_id: sql_result.get_int("id").ok_or(anyhow!("Wrong id"))?,
+                name: sql_result.get_string("name").unwrap_or_default(),
+                file_ref: sql_result.get_string("file_ref"),
+                document_type: sql_result.get_string("document_type"),
+                trashed: date_time_to_iso(&trashed),
+                purge_after: date_time_to_iso(&purge_after(&trashed, retention_days)),
+            });
+        }
+        Ok(items)
+    }
+
+    /// Trash retention period of the customer, in days
+    async fn read_retention_days(
+        &self,
+        trans: &mut SQLTransactionAsync<'_>,
+        customer_code: &str,
+    ) -> anyhow::Result<i32> {
+        let sql_query = format!(
+            r"SELECT cs.setting_value FROM cs_{}.customer_setting cs
+                    WHERE cs.setting_key = :p_setting_key ",
+            customer_code
+        );
+
+        let mut params = HashMap::new();
+        params.insert(
+            "p_setting_key".to_string(),
+            CellValue::from_raw_str(TRASH_RETENTION_SETTING),
+        );
+
+        let query = SQLQueryBlockAsync {
+            sql_query,
+            start: 0,
+            length: Some(1),
+            params,
+        };
+
+        let mut sql_result: SQLDataSet = query
+            .execute(trans)
+            .await
+            .map_err(err_fwd!("Query failed, [{}]", &query.sql_query))?;
+
+        let setting_value = if sql_result.next() {
+            sql_result.get_string("setting_value")
+        } else {
+            None
+        };
+
+        Ok(retention_days_from_setting(setting_value.as_deref()))
+    }
+
+    fn web_type_error<T>() -> impl Fn(&ErrorSet<'static>) -> WebType<T>
+    where
+        T: DeserializeOwned,
+    {
+        |e| {
+            log_error!("💣 Error after try {:?}", e);
+            WebType::from_errorset(e)
+        }
+    }
+}
+
+/// Retention period from the customer setting, the default one when it is missing or wrong
+fn retention_days_from_setting(setting_value: Option<&str>) -> i32 {
+    setting_value
+        .and_then(|v| v.trim().parse::<i32>().ok())
+        .filter(|days| *days > 0)
+        .unwrap_or(DEFAULT_TRASH_RETENTION_DAYS)
+}
+
+/// Date after which a trashed item is purged
+fn purge_after(trashed: &DateTime<Utc>, retention_days: i32) -> DateTime<Utc> {
+    *trashed + chrono::Duration::days(retention_days as i64)
+}
+
+#[cfg(test)]
+mod test {
+    use commons_pg::sql_transaction::{date_time_to_iso, iso_to_datetime};
+
+    use crate::trash::{purge_after, retention_days_from_setting, DEFAULT_TRASH_RETENTION_DAYS};
+
+    #[test]
+    fn retention_days_from_setting_test() {
+        assert_eq!(90, retention_days_from_setting(Some("90")));
+        assert_eq!(7, retention_days_from_setting(Some(" 7 ")));
+        assert_eq!(
+            DEFAULT_TRASH_RETENTION_DAYS,
+            retention_days_from_setting(None)
+        );
+        assert_eq!(
+            DEFAULT_TRASH_RETENTION_DAYS,
+            retention_days_from_setting(Some("0"))
+        );
+        assert_eq!(
+            DEFAULT_TRASH_RETENTION_DAYS,
+            retention_days_from_setting(Some("a month"))
+        );
+    }
+
+    #[test]
+    fn purge_after_test() {
+        let trashed = iso_to_datetime("2024-01-20T10:30:00Z").unwrap();
+        assert_eq!(
+            "2024-02-19T10:30:00+00:00",
+            date_time_to_iso(&purge_after(&trashed, 30))
+        );
+    }
+}
@@ -30,6 +30,7 @@ mod api_document_tests {
             name: "A truck".to_string(),
             file_ref: None,
             properties: None,
+            document_type: None,
         };
 
         let document_server = DocumentServerClient::new("localhost", 30070);
@@ -74,6 +75,7 @@ mod api_document_tests {
             name: "A truck".to_string(),
             file_ref: None,
             properties: Some(vec![p1,p2]),
+            document_type: None,
         };
 
         let document_server = DocumentServerClient::new("localhost", 30070);
@@ -112,6 +114,7 @@ mod api_document_tests {
             name: "A truck".to_string(),
             file_ref: None,
             properties: None,
+            document_type: None,
         };
 
         let document_server = DocumentServerClient::new("localhost", 30070);
@@ -192,6 +195,7 @@ mod api_document_tests {
             name: "A truck 2".to_string(),
             file_ref: None,
             properties: Some(vec![p1,p2]),
+            document_type: None,
         };
 
         let document_server = DocumentServerClient::new("localhost", 30070);
@@ -1,30 +1,45 @@
 use std::fmt::Display;
+use std::sync::Arc;
 use std::time::Duration;
 
 use anyhow::anyhow;
 use commons_error::*;
 use log::*;
 use percent_encoding::{utf8_percent_encode, NON_ALPHANUMERIC};
-use reqwest::{multipart, Client, RequestBuilder};
+use reqwest::{multipart, Body, Client, RequestBuilder};
 use serde::de::DeserializeOwned;
 use serde::{de, Serialize};
 use url::Url;
 
 use dkdto::error_codes::{HTTP_CLIENT_ERROR, INTERNAL_TECHNICAL_ERROR, URL_PARSING_ERROR};
 use dkdto::{
-    AddItemReply, AddItemRequest, AddItemTagReply, AddItemTagRequest, AddKeyReply, AddKeyRequest,
-    AddTagReply, AddTagRequest, CustomerKeyReply, DeleteFullTextRequest, FullTextReply,
-    FullTextRequest, GetFileInfoReply, GetFileInfoShortReply, GetItemReply, GetTagReply,
-    ListOfFileInfoReply, ListOfUploadInfoReply, MediaBytes, OpenSessionReply, OpenSessionRequest,
-    SessionReply, SimpleMessage, TikaMeta, TikaParsing, UploadReply, WebResponse, WebTypeBuilder,
+    AddClassificationRuleReply, AddClassificationRuleRequest, AddDocumentTypeReply,
+    AddDocumentTypeRequest, AddItemCommentReply, AddItemCommentRequest, AddItemReply,
+    AddItemRequest, AddItemTagReply, AddItemTagRequest, AddItemVersionReply, AddItemVersionRequest,
+    AddKeyReply, AddKeyRequest, AddRetentionRuleReply, AddRetentionRuleRequest, AddTagReply,
+    AddTagRequest, ApplyRetentionReply, ApplyRetentionRequest, BulkTagReply, BulkTagRequest,
+    CheckOutReply, CheckOutRequest, ClassifyFileReply, ClassifyFileRequest, CustomerKeyReply,
+    DeleteFullTextRequest, DocumentTextReply, DryRunClassificationReply,
+    DryRunClassificationRequest, ExportCsvReply, FileMetadataReply, FullTextChunk,
+    FullTextDictionaryReply, FullTextReply, FullTextSearchReply, FullTextStreamHeader,
+    GetClassificationRuleReply, GetDestructionCertificateReply, GetDocumentTypeReply,
+    GetEnumValuesReply, GetFileInfoReply, GetFileInfoShortReply, GetItemCommentReply,
+    GetItemHistoryReply, GetItemReply, GetItemVersionReply, GetRetentionRuleReply, GetTagReply,
+    GetTagValuesReply, GetTrashReply, GetUserReply, ImportCsvReply, ImportCsvRequest,
+    ItemFileRefsReply, LegalHoldRequest, ListOfFileInfoReply, ListOfUploadInfoReply, MediaBytes,
+    OpenSessionReply, OpenSessionRequest, ReindexJobReply, ReindexRequest, SessionReply,
+    SimpleMessage, SuggestedTagsReply, TagStatsReply, TikaMeta, TikaParsing, TrashRetentionReply,
+    TrashRetentionRequest, UpdateEnumValuesRequest, UpdateFullTextDictionaryReply,
+    UpdateFullTextDictionaryRequest, UpdateItemCommentRequest, UploadReply, WebResponse,
+    WebTypeBuilder,
 };
 
 use crate::request_client::TokenType::{Sid, Token};
-use crate::request_client::{CustomHeaders, TokenType};
+use crate::request_client::{ndjson_line, text_chunks, CustomHeaders, TokenType, MAX_TEXT_CHUNK};
 
 const TIMEOUT: Duration = Duration::from_secs(60 * 60);
-// const MAX_HTTP_RETRY: u32 = 5;
-// const LAPS: u32 = 2_000;
+const MAX_HTTP_RETRY: u32 = 5;
+const LAPS: u32 = 2_000;
 
 pub struct KeyManagerClientAsync {
     server: WebServerAsync,
@@ -109,6 +124,32 @@ impl SessionManagerClientAsync {
     }
 }
 
+///
+/// Admin Server
+///
+pub struct AdminServerClientAsync {
+    server: WebServerAsync,
+}
+
+impl AdminServerClientAsync {
+    pub fn new(server_name: &str, port: u16) -> Self {
+        Self {
+            server: WebServerAsync::new(server_name, port, "admin-server"),
+        }
+    }
+
+    ///
+    /// The users of the customer of the session
+    ///
+    pub async fn get_users(&self, sid: &str) -> WebResponse<GetUserReply> {
+        // http://{}:{}/admin-server/user
+        let url = self.server.build_url("user");
+        self.server
+            .get_data_retry(&url, &Sid(sid.to_string()))
+            .await
+    }
+}
+
 ///
 /// Document Server
 ///
@@ -116,27 +157,627 @@ pub struct DocumentServerClientAsync {
     server: WebServerAsync,
 }
 
-impl DocumentServerClientAsync {
-    pub fn new(server_name: &str, port: u16) -> Self {
-        Self {
-            server: WebServerAsync::new(server_name, port, "document-server"),
-        }
+impl DocumentServerClientAsync {
+    pub fn new(server_name: &str, port: u16) -> Self {
+        Self {
+            server: WebServerAsync::new(server_name, port, "document-server"),
+        }
+    }
+
+    pub fn read_info(&self) -> (String, u16) {
+        (self.server.server_name.clone(), self.server.port)
+    }
+
+    pub async fn create_item(
+        &self,
+        request: &AddItemRequest,
+        sid: &str,
+    ) -> WebResponse<AddItemReply> {
+        // let url = format!("http://{}:{}/document-server/item", &self.server.server_name, self.server.port);
+        let url = self.server.build_url("item");
+
+        let headers = CustomHeaders {
+            token_type: TokenType::Sid(sid.to_string()),
+            x_request_id: None,
+            cek: None,
+        };
+
+        self.server.post_data_retry(&url, request, &headers).await
+    }
+
+    ///
+    ///
+    ///
+    pub async fn get_item(&self, item_id: i64, sid: &str) -> WebResponse<GetItemReply> {
+        // let url = format!("http://{}:{}/document-server/item/{}", &self.server.server_name, self.server.port,
+        //                   item_id );
+        let url = self.server.build_url_with_refcode("item", item_id);
+        let reply: WebResponse<GetItemReply> = self
+            .server
+            .get_data_retry(&url, &Sid(sid.to_string()))
+            .await;
+        reply
+    }
+
+    ///
+    ///
+    ///
+    pub async fn update_item_tag(
+        &self,
+        item_id: i64,
+        request: &AddItemTagRequest,
+        sid: &str,
+    ) -> WebResponse<AddItemTagReply> {
+        // http://{}:{}/document-server/item/<item_id>/tags
+
+        let end_point = format!("item/{0}/tags", item_id);
+        let url = self.server.build_url(&end_point);
+
+        let headers = CustomHeaders {
+            token_type: Sid(sid.to_string()),
+            x_request_id: None,
+            cek: None,
+        };
+
+        self.server.post_data_retry(&url, request, &headers).await
+    }
+
+    ///
+    /// Tag changes on all the items matching the filter
+    ///
+    pub async fn bulk_tag(&self, request: &BulkTagRequest, sid: &str) -> WebResponse<BulkTagReply> {
+        // http://{}:{}/document-server/item/bulk_tag
+        let url = self.server.build_url("item/bulk_tag");
+
+        let headers = CustomHeaders {
+            token_type: Sid(sid.to_string()),
+            x_request_id: None,
+            cek: None,
+        };
+
+        self.server.post_data_retry(&url, request, &headers).await
+    }
+
+    ///
+    /// Set the tag values of the items from a csv sheet
+    ///
+    pub async fn import_csv(
+        &self,
+        request: &ImportCsvRequest,
+        sid: &str,
+    ) -> WebResponse<ImportCsvReply> {
+        // http://{}:{}/document-server/item/import_csv
+        let url = self.server.build_url("item/import_csv");
+
+        let headers = CustomHeaders {
+            token_type: Sid(sid.to_string()),
+            x_request_id: None,
+            cek: None,
+        };
+
+        self.server.post_data_retry(&url, request, &headers).await
+    }
+
+    ///
+    /// Items matching the filter as csv
+    ///
+    pub async fn export_csv(&self, filters: &str, sid: &str) -> WebResponse<ExportCsvReply> {
+        // http://{}:{}/document-server/item/export_csv?filters=<filters>
+        let end_point = format!(
+            "item/export_csv?filters={0}",
+            utf8_percent_encode(filters, NON_ALPHANUMERIC)
+        );
+        let url = self.server.build_url(&end_point);
+        self.server
+            .get_data_retry(&url, &Sid(sid.to_string()))
+            .await
+    }
+
+    ///
+    /// File references of the items matching the filter
+    ///
+    pub async fn search_file_refs(
+        &self,
+        filters: &str,
+        sid: &str,
+    ) -> WebResponse<ItemFileRefsReply> {
+        // http://{}:{}/document-server/item/file_refs?filters=<filters>
+        let end_point = format!(
+            "item/file_refs?filters={0}",
+            utf8_percent_encode(filters, NON_ALPHANUMERIC)
+        );
+        let url = self.server.build_url(&end_point);
+        self.server
+            .get_data_retry(&url, &Sid(sid.to_string()))
+            .await
+    }
+
+    ///
+    /// TODO perform URL escaping
+    ///
+    pub async fn delete_item_tag(
+        &self,
+        item_id: i64,
+        tag_names: &[String],
+        value_ids: &[i64],
+        sid: &str,
+    ) -> WebResponse<SimpleMessage> {
+        // http://{}:{}/document-server/item/<item_id>/tags?tag_names=<tag_names>&value_ids=<value_ids>
+        let value_ids: Vec<String> = value_ids.iter().map(|id| id.to_string()).collect();
+        let end_point = format!(
+            "item/{0}/tags?tag_names={1}&value_ids={2}",
+            item_id,
+            tag_names.join(","),
+            value_ids.join(",")
+        );
+        let url = self.server.build_url(&end_point);
+        self.server
+            .delete_data_retry(&url, &Sid(sid.to_owned()))
+            .await
+    }
+
+    ///
+    /// TODO might be merged with get_item
+    ///
+    pub async fn search_item(&self, sid: &str) -> WebResponse<GetItemReply> {
+        // let url = format!("http://{}:{}/document-server/item/", &self.server.server_name, self.server.port,
+        //                   item_id );
+        let url = self.server.build_url("item");
+        self.server
+            .get_data_retry(&url, &Sid(sid.to_string()))
+            .await
+    }
+
+    ///
+    ///
+    ///
+    pub async fn create_tag(&self, request: &AddTagRequest, sid: &str) -> WebResponse<AddTagReply> {
+        // let url = format!("http://{}:{}/document-server/tag", &self.server.server_name, self.server.port);
+        let url = self.server.build_url("tag");
+
+        let headers = CustomHeaders {
+            token_type: TokenType::Sid(sid.to_string()),
+            x_request_id: None,
+            cek: None,
+        };
+
+        self.server.post_data_retry(&url, request, &headers).await
+    }
+
+    ///
+    ///
+    ///
+    pub async fn get_all_tag(&self, sid: &str) -> WebResponse<GetTagReply> {
+        //let url = format!("http://{}:{}/document-server/tag", &self.server.server_name, self.server.port);
+        let url = self.server.build_url("tag");
+        self.server
+            .get_data_retry(&url, &Sid(sid.to_string()))
+            .await
+    }
+
+    ///
+    ///
+    ///
+    pub async fn delete_tag(&self, tag_id: i64, sid: &str) -> WebResponse<SimpleMessage> {
+        self.server
+            .delete_for_url(tag_id, "tag", &Sid(sid.to_owned()))
+            .await
+    }
+
+    ///
+    /// Allowed values of an enum tag, possibly restricted to a prefix
+    ///
+    pub async fn get_enum_values(
+        &self,
+        tag_id: i64,
+        prefix: Option<&str>,
+        sid: &str,
+    ) -> WebResponse<GetEnumValuesReply> {
+        // http://{}:{}/document-server/tag/<tag_id>/enum?prefix=<prefix>
+        let end_point = match prefix {
+            None => format!("tag/{0}/enum", tag_id),
+            Some(p) => format!(
+                "tag/{0}/enum?prefix={1}",
+                tag_id,
+                utf8_percent_encode(p, NON_ALPHANUMERIC)
+            ),
+        };
+        let url = self.server.build_url(&end_point);
+        self.server
+            .get_data_retry(&url, &Sid(sid.to_string()))
+            .await
+    }
+
+    ///
+    /// Changes of the tag values of an item, oldest first
+    ///
+    pub async fn get_item_history(
+        &self,
+        item_id: i64,
+        sid: &str,
+    ) -> WebResponse<GetItemHistoryReply> {
+        // http://{}:{}/document-server/item/<item_id>/history
+        let end_point = format!("item/{0}/history", item_id);
+        let url = self.server.build_url(&end_point);
+        self.server
+            .get_data_retry(&url, &Sid(sid.to_string()))
+            .await
+    }
+
+    ///
+    /// Move an item to the trash
+    ///
+    pub async fn trash_item(&self, item_id: i64, sid: &str) -> WebResponse<SimpleMessage> {
+        // http://{}:{}/document-server/item/<item_id>
+        let end_point = format!("item/{0}", item_id);
+        let url = self.server.build_url(&end_point);
+        self.server
+            .delete_data_retry(&url, &Sid(sid.to_owned()))
+            .await
+    }
+
+    ///
+    /// Items in the trash, most recently trashed first
+    ///
+    pub async fn get_trash(&self, sid: &str) -> WebResponse<GetTrashReply> {
+        // http://{}:{}/document-server/trash
+        let url = self.server.build_url("trash");
+        self.server
+            .get_data_retry(&url, &Sid(sid.to_string()))
+            .await
+    }
+
+    ///
+    /// Bring an item back from the trash
+    ///
+    pub async fn restore_item(&self, item_id: i64, sid: &str) -> WebResponse<SimpleMessage> {
+        // http://{}:{}/document-server/trash/<item_id>/restore
+        let end_point = format!("trash/{0}/restore", item_id);
+        let url = self.server.build_url(&end_point);
+
+        let headers = CustomHeaders {
+            token_type: Sid(sid.to_string()),
+            x_request_id: None,
+            cek: None,
+        };
+
+        self.server.post_data_retry(&url, &(), &headers).await
+    }
+
+    ///
+    /// Delete for good an item from the trash
+    ///
+    pub async fn purge_item(&self, item_id: i64, sid: &str) -> WebResponse<SimpleMessage> {
+        // http://{}:{}/document-server/trash/<item_id>
+        let end_point = format!("trash/{0}", item_id);
+        let url = self.server.build_url(&end_point);
+        self.server
+            .delete_data_retry(&url, &Sid(sid.to_owned()))
+            .await
+    }
+
+    ///
+    /// Change the number of days the items stay in the trash
+    ///
+    pub async fn set_trash_retention(
+        &self,
+        request: &TrashRetentionRequest,
+        sid: &str,
+    ) -> WebResponse<TrashRetentionReply> {
+        // http://{}:{}/document-server/trash/retention
+        let url = self.server.build_url("trash/retention");
+
+        let headers = CustomHeaders {
+            token_type: Sid(sid.to_string()),
+            x_request_id: None,
+            cek: None,
+        };
+
+        self.server.post_data_retry(&url, request, &headers).await
+    }
+
+    ///
+    /// Check out an item, the other sessions cannot change it until the check-in
+    ///
+    pub async fn check_out_item(
+        &self,
+        item_id: i64,
+        request: &CheckOutRequest,
+        sid: &str,
+    ) -> WebResponse<CheckOutReply> {
+        // http://{}:{}/document-server/item/<item_id>/checkout
+        let end_point = format!("item/{0}/checkout", item_id);
+        let url = self.server.build_url(&end_point);
+
+        let headers = CustomHeaders {
+            token_type: Sid(sid.to_string()),
+            x_request_id: None,
+            cek: None,
+        };
+
+        self.server.post_data_retry(&url, request, &headers).await
+    }
+
+    ///
+    /// Check in an item
+    ///
+    pub async fn check_in_item(&self, item_id: i64, sid: &str) -> WebResponse<SimpleMessage> {
+        // http://{}:{}/document-server/item/<item_id>/checkin
+        let end_point = format!("item/{0}/checkin", item_id);
+        let url = self.server.build_url(&end_point);
+
+        let headers = CustomHeaders {
+            token_type: Sid(sid.to_string()),
+            x_request_id: None,
+            cek: None,
+        };
+
+        self.server.post_data_retry(&url, &(), &headers).await
+    }
+
+    ///
+    /// All the versions of an item, oldest first
+    ///
+    pub async fn get_item_versions(
+        &self,
+        item_id: i64,
+        sid: &str,
+    ) -> WebResponse<GetItemVersionReply> {
+        // http://{}:{}/document-server/item/<item_id>/version
+        let end_point = format!("item/{0}/version", item_id);
+        let url = self.server.build_url(&end_point);
+        self.server
+            .get_data_retry(&url, &Sid(sid.to_string()))
+            .await
+    }
+
+    ///
+    /// Add a version to an item, the file must be uploaded first
+    ///
+    pub async fn add_item_version(
+        &self,
+        item_id: i64,
+        request: &AddItemVersionRequest,
+        sid: &str,
+    ) -> WebResponse<AddItemVersionReply> {
+        // http://{}:{}/document-server/item/<item_id>/version
+        let end_point = format!("item/{0}/version", item_id);
+        let url = self.server.build_url(&end_point);
+
+        let headers = CustomHeaders {
+            token_type: Sid(sid.to_string()),
+            x_request_id: None,
+            cek: None,
+        };
+
+        self.server.post_data_retry(&url, request, &headers).await
+    }
+
+    ///
+    /// Make a previous version the current version of the item
+    ///
+    pub async fn rollback_item_version(
+        &self,
+        item_id: i64,
+        version_no: i32,
+        sid: &str,
+    ) -> WebResponse<SimpleMessage> {
+        // http://{}:{}/document-server/item/<item_id>/version/<version_no>/rollback
+        let end_point = format!("item/{0}/version/{1}/rollback", item_id, version_no);
+        let url = self.server.build_url(&end_point);
+
+        let headers = CustomHeaders {
+            token_type: Sid(sid.to_string()),
+            x_request_id: None,
+            cek: None,
+        };
+
+        self.server.post_data_retry(&url, &(), &headers).await
+    }
+
+    ///
+    /// Items whose file contains all the words of the query, with snippets of their text
+    ///
+    pub async fn search_fulltext(
+        &self,
+        query: &str,
+        start_page: Option<u32>,
+        page_size: Option<u32>,
+        sid: &str,
+    ) -> WebResponse<FullTextSearchReply> {
+        // http://{}:{}/document-server/search/fulltext?query=<query>&start_page=<start_page>&page_size=<page_size>
+        let mut end_point = format!(
+            "search/fulltext?query={0}",
+            utf8_percent_encode(query, NON_ALPHANUMERIC)
+        );
+        if let Some(p) = start_page {
+            end_point.push_str(&format!("&start_page={}", p));
+        }
+        if let Some(s) = page_size {
+            end_point.push_str(&format!("&page_size={}", s));
+        }
+        let url = self.server.build_url(&end_point);
+        self.server
+            .get_data_retry(&url, &Sid(sid.to_string()))
+            .await
+    }
+
+    ///
+    /// Stop words and synonym groups of the full text search
+    ///
+    pub async fn get_fulltext_dictionary(&self, sid: &str) -> WebResponse<FullTextDictionaryReply> {
+        // http://{}:{}/document-server/search/fulltext/dictionary
+        let url = self.server.build_url("search/fulltext/dictionary");
+        self.server
+            .get_data_retry(&url, &Sid(sid.to_string()))
+            .await
+    }
+
+    ///
+    /// Replace the stop words and synonym groups of the full text search
+    ///
+    pub async fn update_fulltext_dictionary(
+        &self,
+        request: &UpdateFullTextDictionaryRequest,
+        sid: &str,
+    ) -> WebResponse<UpdateFullTextDictionaryReply> {
+        // http://{}:{}/document-server/search/fulltext/dictionary
+        let url = self.server.build_url("search/fulltext/dictionary");
+
+        let headers = CustomHeaders {
+            token_type: Sid(sid.to_string()),
+            x_request_id: None,
+            cek: None,
+        };
+
+        self.server.post_data_retry(&url, request, &headers).await
+    }
+
+    ///
+    /// Tag values matching the keywords of the document of an item
+    ///
+    pub async fn get_suggested_tags(
+        &self,
+        item_id: i64,
+        sid: &str,
+    ) -> WebResponse<SuggestedTagsReply> {
+        // http://{}:{}/document-server/item/<item_id>/suggested-tags
+        let end_point = format!("item/{0}/suggested-tags", item_id);
+        let url = self.server.build_url(&end_point);
+        self.server
+            .get_data_retry(&url, &Sid(sid.to_string()))
+            .await
+    }
+
+    ///
+    /// Clear text extracted from the current file of an item
+    ///
+    pub async fn get_item_text(&self, item_id: i64, sid: &str) -> WebResponse<DocumentTextReply> {
+        // http://{}:{}/document-server/item/<item_id>/text
+        let end_point = format!("item/{0}/text", item_id);
+        let url = self.server.build_url(&end_point);
+        self.server
+            .get_data_retry(&url, &Sid(sid.to_string()))
+            .await
+    }
+
+    ///
+    /// Clear text extracted from a file
+    ///
+    pub async fn get_file_text(&self, file_ref: &str, sid: &str) -> WebResponse<DocumentTextReply> {
+        // http://{}:{}/document-server/file/<file_ref>/text
+        let end_point = format!(
+            "file/{0}/text",
+            utf8_percent_encode(file_ref, NON_ALPHANUMERIC)
+        );
+        let url = self.server.build_url(&end_point);
+        self.server
+            .get_data_retry(&url, &Sid(sid.to_string()))
+            .await
+    }
+
+    ///
+    /// All the comments of an item, oldest first
+    ///
+    pub async fn get_item_comments(
+        &self,
+        item_id: i64,
+        sid: &str,
+    ) -> WebResponse<GetItemCommentReply> {
+        // http://{}:{}/document-server/item/<item_id>/comment
+        let end_point = format!("item/{0}/comment", item_id);
+        let url = self.server.build_url(&end_point);
+        self.server
+            .get_data_retry(&url, &Sid(sid.to_string()))
+            .await
+    }
+
+    ///
+    /// Comment an item or reply to a comment
+    ///
+    pub async fn add_item_comment(
+        &self,
+        item_id: i64,
+        request: &AddItemCommentRequest,
+        sid: &str,
+    ) -> WebResponse<AddItemCommentReply> {
+        // http://{}:{}/document-server/item/<item_id>/comment
+        let end_point = format!("item/{0}/comment", item_id);
+        let url = self.server.build_url(&end_point);
+
+        let headers = CustomHeaders {
+            token_type: Sid(sid.to_string()),
+            x_request_id: None,
+            cek: None,
+        };
+
+        self.server.post_data_retry(&url, request, &headers).await
+    }
+
+    ///
+    /// Change the text of a comment, only for its author
+    ///
+    pub async fn update_item_comment(
+        &self,
+        item_id: i64,
+        comment_id: i64,
+        request: &UpdateItemCommentRequest,
+        sid: &str,
+    ) -> WebResponse<SimpleMessage> {
+        // http://{}:{}/document-server/item/<item_id>/comment/<comment_id>
+        let end_point = format!("item/{0}/comment/{1}", item_id, comment_id);
+        let url = self.server.build_url(&end_point);
+
+        let headers = CustomHeaders {
+            token_type: Sid(sid.to_string()),
+            x_request_id: None,
+            cek: None,
+        };
+
+        self.server.post_data_retry(&url, request, &headers).await
+    }
+
+    ///
+    /// Delete a comment, only for its author
+    ///
+    pub async fn delete_item_comment(
+        &self,
+        item_id: i64,
+        comment_id: i64,
+        sid: &str,
+    ) -> WebResponse<SimpleMessage> {
+        // http://{}:{}/document-server/item/<item_id>/comment/<comment_id>
+        let end_point = format!("item/{0}/comment/{1}", item_id, comment_id);
+        let url = self.server.build_url(&end_point);
+        self.server
+            .delete_data_retry(&url, &Sid(sid.to_owned()))
+            .await
     }
 
-    pub fn read_info(&self) -> (String, u16) {
-        (self.server.server_name.clone(), self.server.port)
+    ///
+    /// All the retention rules
+    ///
+    pub async fn get_all_retention_rule(&self, sid: &str) -> WebResponse<GetRetentionRuleReply> {
+        // http://{}:{}/document-server/retention_rule
+        let url = self.server.build_url("retention_rule");
+        self.server
+            .get_data_retry(&url, &Sid(sid.to_string()))
+            .await
     }
 
-    pub async fn create_item(
+    ///
+    /// Create a retention rule
+    ///
+    pub async fn add_retention_rule(
         &self,
-        request: &AddItemRequest,
+        request: &AddRetentionRuleRequest,
         sid: &str,
-    ) -> WebResponse<AddItemReply> {
-        // let url = format!("http://{}:{}/document-server/item", &self.server.server_name, self.server.port);
-        let url = self.server.build_url("item");
+    ) -> WebResponse<AddRetentionRuleReply> {
+        // http://{}:{}/document-server/retention_rule
+        let url = self.server.build_url("retention_rule");
 
         let headers = CustomHeaders {
-            token_type: TokenType::Sid(sid.to_string()),
+            token_type: Sid(sid.to_string()),
             x_request_id: None,
             cek: None,
         };
@@ -145,32 +786,101 @@ impl DocumentServerClientAsync {
     }
 
     ///
+    /// Delete a retention rule
     ///
+    pub async fn delete_retention_rule(
+        &self,
+        rule_id: i64,
+        sid: &str,
+    ) -> WebResponse<SimpleMessage> {
+        // http://{}:{}/document-server/retention_rule/<rule_id>
+        let end_point = format!("retention_rule/{0}", rule_id);
+        let url = self.server.build_url(&end_point);
+        self.server
+            .delete_data_retry(&url, &Sid(sid.to_owned()))
+            .await
+    }
+
     ///
-    pub async fn get_item(&self, item_id: i64, sid: &str) -> WebResponse<GetItemReply> {
-        // let url = format!("http://{}:{}/document-server/item/{}", &self.server.server_name, self.server.port,
-        //                   item_id );
-        let url = self.server.build_url_with_refcode("item", item_id);
-        let reply: WebResponse<GetItemReply> = self
-            .server
-            .get_data_retry(&url, &Sid(sid.to_string()))
-            .await;
-        reply
+    /// Put an item on legal hold or release it
+    ///
+    pub async fn set_legal_hold(
+        &self,
+        item_id: i64,
+        request: &LegalHoldRequest,
+        sid: &str,
+    ) -> WebResponse<SimpleMessage> {
+        // http://{}:{}/document-server/item/<item_id>/legal_hold
+        let end_point = format!("item/{0}/legal_hold", item_id);
+        let url = self.server.build_url(&end_point);
+
+        let headers = CustomHeaders {
+            token_type: Sid(sid.to_string()),
+            x_request_id: None,
+            cek: None,
+        };
+
+        self.server.post_data_retry(&url, request, &headers).await
     }
 
     ///
+    /// Report or destroy the items whose retention period is over
+    ///
+    pub async fn apply_retention(
+        &self,
+        request: &ApplyRetentionRequest,
+        sid: &str,
+    ) -> WebResponse<ApplyRetentionReply> {
+        // http://{}:{}/document-server/retention/apply
+        let url = self.server.build_url("retention/apply");
+
+        let headers = CustomHeaders {
+            token_type: Sid(sid.to_string()),
+            x_request_id: None,
+            cek: None,
+        };
+
+        self.server.post_data_retry(&url, request, &headers).await
+    }
+
     ///
+    /// Destruction certificates, most recent first
     ///
-    pub async fn update_item_tag(
+    pub async fn get_destruction_certificates(
         &self,
-        item_id: i64,
-        request: &AddItemTagRequest,
         sid: &str,
-    ) -> WebResponse<AddItemTagReply> {
-        // http://{}:{}/document-server/item/<item_id>/tags
+    ) -> WebResponse<GetDestructionCertificateReply> {
+        // http://{}:{}/document-server/retention/certificate
+        let url = self.server.build_url("retention/certificate");
+        self.server
+            .get_data_retry(&url, &Sid(sid.to_string()))
+            .await
+    }
 
-        let end_point = format!("item/{0}/tags", item_id);
-        let url = self.server.build_url(&end_point);
+    ///
+    /// All the classification rules
+    ///
+    pub async fn get_all_classification_rule(
+        &self,
+        sid: &str,
+    ) -> WebResponse<GetClassificationRuleReply> {
+        // http://{}:{}/document-server/classification_rule
+        let url = self.server.build_url("classification_rule");
+        self.server
+            .get_data_retry(&url, &Sid(sid.to_string()))
+            .await
+    }
+
+    ///
+    /// Create a classification rule
+    ///
+    pub async fn add_classification_rule(
+        &self,
+        request: &AddClassificationRuleRequest,
+        sid: &str,
+    ) -> WebResponse<AddClassificationRuleReply> {
+        // http://{}:{}/document-server/classification_rule
+        let url = self.server.build_url("classification_rule");
 
         let headers = CustomHeaders {
             token_type: Sid(sid.to_string()),
@@ -182,16 +892,15 @@ impl DocumentServerClientAsync {
     }
 
     ///
-    /// TODO perform URL escaping
+    /// Delete a classification rule
     ///
-    pub async fn delete_item_tag(
+    pub async fn delete_classification_rule(
         &self,
-        item_id: i64,
-        tag_names: &[String],
+        rule_id: i64,
         sid: &str,
     ) -> WebResponse<SimpleMessage> {
-        // http://{}:{}/document-server/item/<item_id>/tags?tag_names=<tag_names>
-        let end_point = format!("item/{0}/tags?tag_names={1}", item_id, tag_names.join(","));
+        // http://{}:{}/document-server/classification_rule/<rule_id>
+        let end_point = format!("classification_rule/{0}", rule_id);
         let url = self.server.build_url(&end_point);
         self.server
             .delete_data_retry(&url, &Sid(sid.to_owned()))
@@ -199,12 +908,56 @@ impl DocumentServerClientAsync {
     }
 
     ///
-    /// TODO might be merged with get_item
+    /// Test a classification condition on the existing documents
     ///
-    pub async fn search_item(&self, sid: &str) -> WebResponse<GetItemReply> {
-        // let url = format!("http://{}:{}/document-server/item/", &self.server.server_name, self.server.port,
-        //                   item_id );
-        let url = self.server.build_url("item");
+    pub async fn dry_run_classification(
+        &self,
+        request: &DryRunClassificationRequest,
+        sid: &str,
+    ) -> WebResponse<DryRunClassificationReply> {
+        // http://{}:{}/document-server/classification_rule/dry_run
+        let url = self.server.build_url("classification_rule/dry_run");
+
+        let headers = CustomHeaders {
+            token_type: Sid(sid.to_string()),
+            x_request_id: None,
+            cek: None,
+        };
+
+        self.server.post_data_retry(&url, request, &headers).await
+    }
+
+    ///
+    /// Usage statistics of a tag
+    ///
+    pub async fn get_tag_stats(&self, tag_id: i64, sid: &str) -> WebResponse<TagStatsReply> {
+        // http://{}:{}/document-server/tag/<tag_id>/stats
+        let end_point = format!("tag/{0}/stats", tag_id);
+        let url = self.server.build_url(&end_point);
+        self.server
+            .get_data_retry(&url, &Sid(sid.to_string()))
+            .await
+    }
+
+    ///
+    /// Distinct values of a tag with their item count, possibly restricted to a prefix
+    ///
+    pub async fn get_tag_values(
+        &self,
+        tag_id: i64,
+        prefix: Option<&str>,
+        sid: &str,
+    ) -> WebResponse<GetTagValuesReply> {
+        // http://{}:{}/document-server/tag/<tag_id>/values?prefix=<prefix>
+        let end_point = match prefix {
+            None => format!("tag/{0}/values", tag_id),
+            Some(p) => format!(
+                "tag/{0}/values?prefix={1}",
+                tag_id,
+                utf8_percent_encode(p, NON_ALPHANUMERIC)
+            ),
+        };
+        let url = self.server.build_url(&end_point);
         self.server
             .get_data_retry(&url, &Sid(sid.to_string()))
             .await
@@ -213,12 +966,18 @@ impl DocumentServerClientAsync {
     ///
     ///
     ///
-    pub async fn create_tag(&self, request: &AddTagRequest, sid: &str) -> WebResponse<AddTagReply> {
-        // let url = format!("http://{}:{}/document-server/tag", &self.server.server_name, self.server.port);
-        let url = self.server.build_url("tag");
+    pub async fn update_enum_values(
+        &self,
+        tag_id: i64,
+        request: &UpdateEnumValuesRequest,
+        sid: &str,
+    ) -> WebResponse<SimpleMessage> {
+        // http://{}:{}/document-server/tag/<tag_id>/enum
+        let end_point = format!("tag/{0}/enum", tag_id);
+        let url = self.server.build_url(&end_point);
 
         let headers = CustomHeaders {
-            token_type: TokenType::Sid(sid.to_string()),
+            token_type: Sid(sid.to_string()),
             x_request_id: None,
             cek: None,
         };
@@ -229,9 +988,8 @@ impl DocumentServerClientAsync {
     ///
     ///
     ///
-    pub async fn get_all_tag(&self, sid: &str) -> WebResponse<GetTagReply> {
-        //let url = format!("http://{}:{}/document-server/tag", &self.server.server_name, self.server.port);
-        let url = self.server.build_url("tag");
+    pub async fn get_all_document_type(&self, sid: &str) -> WebResponse<GetDocumentTypeReply> {
+        let url = self.server.build_url("document_type");
         self.server
             .get_data_retry(&url, &Sid(sid.to_string()))
             .await
@@ -240,35 +998,80 @@ impl DocumentServerClientAsync {
     ///
     ///
     ///
-    pub async fn delete_tag(&self, tag_id: i64, sid: &str) -> WebResponse<SimpleMessage> {
+    pub async fn create_document_type(
+        &self,
+        request: &AddDocumentTypeRequest,
+        sid: &str,
+    ) -> WebResponse<AddDocumentTypeReply> {
+        let url = self.server.build_url("document_type");
+
+        let headers = CustomHeaders {
+            token_type: Sid(sid.to_string()),
+            x_request_id: None,
+            cek: None,
+        };
+
+        self.server.post_data_retry(&url, request, &headers).await
+    }
+
+    ///
+    ///
+    ///
+    pub async fn delete_document_type(
+        &self,
+        document_type_id: i64,
+        sid: &str,
+    ) -> WebResponse<SimpleMessage> {
         self.server
-            .delete_for_url(tag_id, "tag", &Sid(sid.to_owned()))
+            .delete_for_url(document_type_id, "document_type", &Sid(sid.to_owned()))
             .await
     }
 
     ///
-    ///
+    /// Send the text as a NDJSON stream of chunks
+    /// The caller gives the text away, it is the only copy and it is kept until the last attempt,
+    /// to replay the stream on a retry. The text extraction returns the whole text,
+    /// so the memory of the caller beyond that copy is out of the scope of this stream
     ///
     pub async fn fulltext_indexing(
         &self,
-        raw_text: &str,
+        raw_text: String,
         file_name: &str,
         file_ref: &str,
         sid: &str,
     ) -> WebResponse<FullTextReply> {
-        let request = FullTextRequest {
+        let header = FullTextStreamHeader {
             file_name: file_name.to_owned(),
             file_ref: file_ref.to_owned(),
-            raw_text: raw_text.to_owned(),
         };
-        // dbg!(&request);
+        let Ok(header_line) = ndjson_line(&header) else {
+            return WebResponse::from_errorset(&HTTP_CLIENT_ERROR);
+        };
+
+        // The text is shared by the attempts, the chunk lines are only built when the body is sent
+        let text = Arc::new(raw_text);
+        let ndjson_body = move || {
+            let text = Arc::clone(&text);
+            let chunk_lines = text_chunks(&text, MAX_TEXT_CHUNK)
+                .into_iter()
+                .map(move |(start, end)| {
+                    ndjson_line(&FullTextChunk {
+                        text: text[start..end].to_owned(),
+                    })
+                });
+            let lines = std::iter::once(Ok(header_line.clone())).chain(chunk_lines);
+            Body::wrap_stream(futures::stream::iter(lines))
+        };
+
         let url = self.server.build_url("fulltext_indexing");
         let headers = CustomHeaders {
             token_type: TokenType::Sid(sid.to_string()),
             x_request_id: None,
             cek: None,
         };
-        self.server.post_data_retry(&url, &request, &headers).await
+        self.server
+            .post_ndjson_retry(&url, ndjson_body, &headers)
+            .await
     }
 
     ///
@@ -290,6 +1093,23 @@ impl DocumentServerClientAsync {
         };
         self.server.post_data_retry(&url, &request, &headers).await
     }
+
+    ///
+    /// Apply the classification rules to a new file
+    ///
+    pub async fn classify_file(
+        &self,
+        request: &ClassifyFileRequest,
+        sid: &str,
+    ) -> WebResponse<ClassifyFileReply> {
+        let url = self.server.build_url("classify_file");
+        let headers = CustomHeaders {
+            token_type: TokenType::Sid(sid.to_string()),
+            x_request_id: None,
+            cek: None,
+        };
+        self.server.post_data_retry(&url, request, &headers).await
+    }
 }
 
 /// File Server
@@ -354,6 +1174,14 @@ impl FileServerClientAsync {
             .await
     }
 
+    pub async fn metadata(&self, file_ref: &str, sid: &str) -> WebResponse<FileMetadataReply> {
+        // http://{}:{}/file-server/metadata/<file_ref>
+        let url = self.server.build_url_with_refcode("metadata", &file_ref);
+        self.server
+            .get_data_retry(&url, &Sid(sid.to_string()))
+            .await
+    }
+
     pub async fn stats(&self, file_ref: &str, sid: &str) -> WebResponse<GetFileInfoShortReply> {
         // let url = format!("http://{}:{}/file-server/stats/{}", &self.server.server_name, self.server.port);
         let url = self.server.build_url_with_refcode("stats", &file_ref);
@@ -379,6 +1207,56 @@ impl FileServerClientAsync {
             .get_data_retry(&url, &Sid(sid.to_string()))
             .await
     }
+
+    pub async fn release_file(&self, file_ref: &str, sid: &str) -> WebResponse<SimpleMessage> {
+        // http://{}:{}/file-server/release/<file_ref>
+        let url = self.server.build_url_with_refcode("release", &file_ref);
+        self.server
+            .delete_data_retry(&url, &Sid(sid.to_string()))
+            .await
+    }
+
+    ///
+    /// Start a job to rebuild the full text index of the files matching the pattern
+    ///
+    pub async fn start_reindex(
+        &self,
+        request: &ReindexRequest,
+        sid: &str,
+    ) -> WebResponse<ReindexJobReply> {
+        // http://{}:{}/file-server/reindex
+        let url = self.server.build_url("reindex");
+
+        let headers = CustomHeaders {
+            token_type: Sid(sid.to_string()),
+            x_request_id: None,
+            cek: None,
+        };
+
+        self.server.post_data_retry(&url, request, &headers).await
+    }
+
+    pub async fn get_reindex_job(&self, job_id: i64, sid: &str) -> WebResponse<ReindexJobReply> {
+        // http://{}:{}/file-server/reindex/<job_id>
+        let url = self.server.build_url_with_refcode("reindex", job_id);
+        self.server
+            .get_data_retry(&url, &Sid(sid.to_string()))
+            .await
+    }
+
+    pub async fn resume_reindex(&self, job_id: i64, sid: &str) -> WebResponse<ReindexJobReply> {
+        // http://{}:{}/file-server/reindex/<job_id>/resume
+        let end_point = format!("reindex/{0}/resume", job_id);
+        let url = self.server.build_url(&end_point);
+
+        let headers = CustomHeaders {
+            token_type: Sid(sid.to_string()),
+            x_request_id: None,
+            cek: None,
+        };
+
+        self.server.post_data_retry(&url, &(), &headers).await
+    }
 }
 
 ///
@@ -520,6 +1398,55 @@ impl WebServerAsync {
         }
     }
 
+    /// Post a NDJSON body, the body is built again for each attempt since a stream is consumed by the send
+    async fn post_ndjson_retry<V: de::DeserializeOwned, F: Fn() -> Body>(
+        &self,
+        url: &str,
+        ndjson_body: F,
+        headers: &CustomHeaders,
+    ) -> WebResponse<V> {
+        let mut count: u32 = 0;
+        loop {
+            match self.post_ndjson(url, ndjson_body(), headers).await {
+                Ok(response) => return response,
+                Err(e) if count < MAX_HTTP_RETRY => {
+                    log_warn!("Operation failed, attempt=[{}], e=[{}]", count, e);
+                    tokio::time::sleep(Duration::from_millis(LAPS as u64)).await;
+                    count += 1;
+                }
+                Err(_) => return WebResponse::from_errorset(&HTTP_CLIENT_ERROR),
+            }
+        }
+    }
+
+    /// Post a NDJSON body
+    async fn post_ndjson<V: de::DeserializeOwned>(
+        &self,
+        url: &str,
+        body: Body,
+        headers: &CustomHeaders,
+    ) -> anyhow::Result<WebResponse<V>> {
+        let client = Client::new();
+        let url = Url::parse(url)?;
+        let request_builder = client
+            .post(url)
+            .timeout(TIMEOUT)
+            .header("Content-Type", "application/x-ndjson");
+
+        let request_builder = match &headers.token_type {
+            Token(token_value) => request_builder.header("token", token_value.clone()),
+            Sid(sid_value) => request_builder.header("sid", sid_value.clone()),
+            TokenType::None => request_builder,
+        };
+
+        let request_builder = match headers.x_request_id {
+            None => request_builder,
+            Some(x_request_id) => request_builder.header("X-Request-ID", x_request_id),
+        };
+
+        Self::send_request_builder(request_builder.body(body)).await
+    }
+
     async fn post_data<U: Serialize, V: de::DeserializeOwned>(
         &self,
         url: &str,
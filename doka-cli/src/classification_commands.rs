@@ -0,0 +1,147 @@
+use anyhow::anyhow;
+
+use dkconfig::properties::get_prop_value;
+use dkdto::{AddClassificationRuleRequest, ClassificationAction, DryRunClassificationRequest};
+use doka_cli::request_client::DocumentServerClient;
+
+use crate::session_commands::read_session_id;
+
+fn document_server_client() -> anyhow::Result<DocumentServerClient> {
+    let server_host = get_prop_value("server.host")?;
+    let document_server_port: u16 = get_prop_value("ds.port")?.parse()?;
+    Ok(DocumentServerClient::new(
+        &server_host,
+        document_server_port,
+    ))
+}
+
+///
+/// The actions are of the form "doc_category:invoice,reviewed:false"
+///
+fn parse_actions(actions: &str) -> anyhow::Result<Vec<ClassificationAction>> {
+    actions
+        .split(',')
+        .map(|action| {
+            let (tag_name, value) = action
+                .split_once(':')
+                .ok_or_else(|| anyhow!("💣 Wrong action, action=[{}]", action))?;
+            Ok(ClassificationAction {
+                tag_name: tag_name.trim().to_owned(),
+                value: value.trim().trim_matches('\'').to_owned(),
+            })
+        })
+        .collect()
+}
+
+///
+pub(crate) fn classification_list() -> anyhow::Result<()> {
+    println!("👶 Getting the classification rules...");
+
+    let client = document_server_client()?;
+    let sid = read_session_id()?;
+
+    match client.get_all_classification_rule(&sid) {
+        Ok(reply) => {
+            println!("😎 Classification rules : {}", reply.rules.len());
+            println!("id\tname\tcondition\tactions");
+            for rule in reply.rules {
+                let actions: Vec<String> = rule
+                    .actions
+                    .iter()
+                    .map(|action| format!("{}:{}", &action.tag_name, &action.value))
+                    .collect();
+                println!(
+                    "{}\t{}\t{}\t{}",
+                    rule.rule_id,
+                    &rule.name,
+                    &rule.condition,
+                    actions.join(",")
+                );
+            }
+            Ok(())
+        }
+        Err(e) => Err(anyhow!("{}", e.message)),
+    }
+}
+
+///
+pub(crate) fn classification_create(
+    name: &str,
+    condition: &str,
+    actions: &str,
+) -> anyhow::Result<()> {
+    println!("👶 Create the classification rule...");
+
+    let actions = parse_actions(actions)?;
+    let client = document_server_client()?;
+    let sid = read_session_id()?;
+
+    let request = AddClassificationRuleRequest {
+        name: name.to_owned(),
+        condition: condition.to_owned(),
+        actions,
+    };
+
+    match client.add_classification_rule(&request, &sid) {
+        Ok(reply) => {
+            println!(
+                "😎 Classification rule successfully created, rule id : {} ",
+                reply.rule_id
+            );
+            Ok(())
+        }
+        Err(e) => Err(anyhow!("{}", e.message)),
+    }
+}
+
+///
+pub(crate) fn classification_delete(id: &str) -> anyhow::Result<()> {
+    println!("👶 Delete the classification rule...");
+
+    let rule_id: i64 = id.parse()?;
+    let client = document_server_client()?;
+    let sid = read_session_id()?;
+
+    match client.delete_classification_rule(rule_id, &sid) {
+        Ok(_reply) => {
+            println!(
+                "😎 Classification rule successfully deleted, rule id : {} ",
+                rule_id
+            );
+            Ok(())
+        }
+        Err(e) => Err(anyhow!("{}", e.message)),
+    }
+}
+
+///
+/// Test a condition on the most recent documents before creating the rule
+///
+pub(crate) fn classification_dry_run(condition: &str, o_limit: Option<&str>) -> anyhow::Result<()> {
+    println!("👶 Test the classification condition...");
+
+    let limit = o_limit.map(|limit| limit.parse::<u32>()).transpose()?;
+    let client = document_server_client()?;
+    let sid = read_session_id()?;
+
+    let request = DryRunClassificationRequest {
+        condition: condition.to_owned(),
+        limit,
+    };
+
+    match client.dry_run_classification(&request, &sid) {
+        Ok(reply) => {
+            println!(
+                "😎 Checked documents : {}, matching documents : {}",
+                reply.checked_count,
+                reply.items.len()
+            );
+            println!("id\tname\tfile ref");
+            for item in reply.items {
+                println!("{}\t{}\t{}", item.item_id, &item.name, &item.file_ref);
+            }
+            Ok(())
+        }
+        Err(e) => Err(anyhow!("{}", e.message)),
+    }
+}
@@ -0,0 +1,157 @@
+use anyhow::anyhow;
+
+use dkconfig::properties::get_prop_value;
+use dkdto::{AddItemCommentRequest, UpdateItemCommentRequest};
+use doka_cli::request_client::DocumentServerClient;
+
+use crate::session_commands::read_session_id;
+
+fn document_server_client() -> anyhow::Result<DocumentServerClient> {
+    let server_host = get_prop_value("server.host")?;
+    let document_server_port: u16 = get_prop_value("ds.port")?.parse()?;
+    Ok(DocumentServerClient::new(
+        &server_host,
+        document_server_port,
+    ))
+}
+
+///
+/// Read the user ids of the mentions, ex: "12,15"
+///
+fn parse_mentions(o_mentions: Option<&str>) -> anyhow::Result<Vec<i64>> {
+    match o_mentions {
+        None => Ok(vec![]),
+        Some(mentions) => mentions
+            .split(',')
+            .map(|m| m.trim().parse::<i64>().map_err(|e| anyhow!("{}", e)))
+            .collect(),
+    }
+}
+
+///
+pub(crate) fn comment_list(id: &str) -> anyhow::Result<()> {
+    println!("👶 Getting the comments of the item...");
+
+    let item_id: i64 = id.parse()?;
+    let client = document_server_client()?;
+    let sid = read_session_id()?;
+
+    match client.get_item_comments(item_id, &sid) {
+        Ok(reply) => {
+            println!("😎 Comments of the item : {}", reply.comments.len());
+            println!("id\treply to\tuser id\tcreated\tedited\tpage\toffset\tmentions\ttext");
+            for comment in reply.comments {
+                let mentions: Vec<String> =
+                    comment.mentions.iter().map(|m| m.to_string()).collect();
+                println!(
+                    "{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}",
+                    comment.comment_id,
+                    comment.parent_id.map(|p| p.to_string()).unwrap_or_default(),
+                    comment.user_id,
+                    &comment.created,
+                    comment.edited.unwrap_or_default(),
+                    comment.page.map(|p| p.to_string()).unwrap_or_default(),
+                    comment
+                        .text_offset
+                        .map(|o| o.to_string())
+                        .unwrap_or_default(),
+                    mentions.join(","),
+                    if comment.deleted {
+                        "<deleted>"
+                    } else {
+                        &comment.text
+                    }
+                );
+            }
+            Ok(())
+        }
+        Err(e) => Err(anyhow!("{}", e.message)),
+    }
+}
+
+///
+pub(crate) fn comment_add(
+    id: &str,
+    text: &str,
+    o_parent: Option<&str>,
+    o_mentions: Option<&str>,
+    o_page: Option<&str>,
+    o_offset: Option<&str>,
+) -> anyhow::Result<()> {
+    println!("👶 Comment the item...");
+
+    let item_id: i64 = id.parse()?;
+    let request = AddItemCommentRequest {
+        text: text.to_owned(),
+        parent_id: o_parent.map(str::parse::<i64>).transpose()?,
+        mentions: parse_mentions(o_mentions)?,
+        page: o_page.map(str::parse::<i32>).transpose()?,
+        text_offset: o_offset.map(str::parse::<i32>).transpose()?,
+    };
+
+    let client = document_server_client()?;
+    let sid = read_session_id()?;
+
+    match client.add_item_comment(item_id, &request, &sid) {
+        Ok(reply) => {
+            println!(
+                "😎 Comment successfully added, item id : {}, comment id : {} ",
+                item_id, reply.comment_id
+            );
+            Ok(())
+        }
+        Err(e) => Err(anyhow!("{}", e.message)),
+    }
+}
+
+///
+pub(crate) fn comment_edit(
+    id: &str,
+    comment: &str,
+    text: &str,
+    o_mentions: Option<&str>,
+) -> anyhow::Result<()> {
+    println!("👶 Change the comment...");
+
+    let item_id: i64 = id.parse()?;
+    let comment_id: i64 = comment.parse()?;
+    let request = UpdateItemCommentRequest {
+        text: text.to_owned(),
+        mentions: parse_mentions(o_mentions)?,
+    };
+
+    let client = document_server_client()?;
+    let sid = read_session_id()?;
+
+    match client.update_item_comment(item_id, comment_id, &request, &sid) {
+        Ok(_reply) => {
+            println!(
+                "😎 Comment successfully changed, comment id : {} ",
+                comment_id
+            );
+            Ok(())
+        }
+        Err(e) => Err(anyhow!("{}", e.message)),
+    }
+}
+
+///
+pub(crate) fn comment_delete(id: &str, comment: &str) -> anyhow::Result<()> {
+    println!("👶 Delete the comment...");
+
+    let item_id: i64 = id.parse()?;
+    let comment_id: i64 = comment.parse()?;
+    let client = document_server_client()?;
+    let sid = read_session_id()?;
+
+    match client.delete_item_comment(item_id, comment_id, &sid) {
+        Ok(_reply) => {
+            println!(
+                "😎 Comment successfully deleted, comment id : {} ",
+                comment_id
+            );
+            Ok(())
+        }
+        Err(e) => Err(anyhow!("{}", e.message)),
+    }
+}
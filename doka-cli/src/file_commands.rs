@@ -5,7 +5,8 @@ use std::path::Path;
 use anyhow::anyhow;
 
 use dkconfig::properties::get_prop_value;
-use doka_cli::request_client::FileServerClient;
+use dkdto::{ReindexJobReply, ReindexRequest};
+use doka_cli::request_client::{DocumentServerClient, FileServerClient};
 
 use crate::session_commands::read_session_id;
 
@@ -101,6 +102,32 @@ pub(crate) fn file_info(file_ref: &str) -> anyhow::Result<()> {
     Ok(())
 }
 
+///
+/// Print the text the document server extracted from the file
+///
+pub(crate) fn file_text(file_ref: &str) -> anyhow::Result<()> {
+    println!("👶 Getting the text of the file...");
+
+    let server_host = get_prop_value("server.host")?;
+    let document_server_port: u16 = get_prop_value("ds.port")?.parse()?;
+    let client = DocumentServerClient::new(&server_host, document_server_port);
+    let sid = read_session_id()?;
+
+    match client.get_file_text(file_ref, &sid) {
+        Ok(reply) => {
+            println!(
+                "😎 Text of the file : {}, parts : {}, languages : {}",
+                &reply.file_ref,
+                reply.part_count,
+                reply.languages.join(", ")
+            );
+            println!("{}", &reply.text);
+            Ok(())
+        }
+        Err(e) => Err(anyhow!("{}", e.message)),
+    }
+}
+
 pub(crate) fn file_list(pattern: &str) -> anyhow::Result<()> {
     println!("👶 Getting the file information...");
 
@@ -154,4 +181,90 @@ pub(crate) fn file_loading() -> anyhow::Result<()> {
         }
     }
     Ok(())
-}
\ No newline at end of file
+}
+
+fn file_server_client() -> anyhow::Result<FileServerClient> {
+    let server_host = get_prop_value("server.host")?;
+    let file_server_port: u16 = get_prop_value("fs.port")?.parse()?;
+    Ok(FileServerClient::new(&server_host, file_server_port))
+}
+
+fn print_reindex_job(job: &ReindexJobReply) {
+    println!("id\tpattern\tfilters\tstatus\tfiles\tdone\tfailed\tlast file\tstart\tend");
+    println!(
+        "{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}",
+        job.job_id,
+        &job.pattern,
+        job.filters.clone().unwrap_or_default(),
+        &job.status,
+        job.file_count,
+        job.done_count,
+        job.failed_count,
+        job.last_file_ref.clone().unwrap_or_default(),
+        &job.start_date_time,
+        job.end_date_time.map(|d| d.to_string()).unwrap_or_default()
+    );
+}
+
+///
+/// Start a job to rebuild the full text index of the files, all the files when there is no pattern
+/// With a search filter, only the files of the items matching it
+///
+pub(crate) fn file_reindex(o_pattern: Option<&str>, o_filters: Option<&str>) -> anyhow::Result<()> {
+    println!("👶 Start the reindex job...");
+
+    let client = file_server_client()?;
+    let sid = read_session_id()?;
+    let request = ReindexRequest {
+        pattern: o_pattern.map(str::to_owned),
+        filters: o_filters.map(str::to_owned),
+    };
+
+    match client.start_reindex(&request, &sid) {
+        Ok(job) => {
+            println!("😎 Reindex job started, job id : {}", job.job_id);
+            print_reindex_job(&job);
+            Ok(())
+        }
+        Err(e) => Err(anyhow!("{}", e.message)),
+    }
+}
+
+///
+/// Print the progress of a reindex job
+///
+pub(crate) fn file_reindex_status(job: &str) -> anyhow::Result<()> {
+    println!("👶 Getting the progress of the reindex job...");
+
+    let job_id: i64 = job.parse()?;
+    let client = file_server_client()?;
+    let sid = read_session_id()?;
+
+    match client.get_reindex_job(job_id, &sid) {
+        Ok(job) => {
+            print_reindex_job(&job);
+            Ok(())
+        }
+        Err(e) => Err(anyhow!("{}", e.message)),
+    }
+}
+
+///
+/// Resume a reindex job after the last file it processed
+///
+pub(crate) fn file_reindex_resume(job: &str) -> anyhow::Result<()> {
+    println!("👶 Resume the reindex job...");
+
+    let job_id: i64 = job.parse()?;
+    let client = file_server_client()?;
+    let sid = read_session_id()?;
+
+    match client.resume_reindex(job_id, &sid) {
+        Ok(job) => {
+            println!("😎 Reindex job resumed, job id : {}", job.job_id);
+            print_reindex_job(&job);
+            Ok(())
+        }
+        Err(e) => Err(anyhow!("{}", e.message)),
+    }
+}
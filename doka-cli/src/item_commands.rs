@@ -7,9 +7,14 @@ use regex::Regex;
 
 use commons_error::*;
 use dkconfig::properties::get_prop_value;
-use dkdto::{AddItemRequest, AddItemTagRequest, AddTagValue, EnumTagValue, GetItemReply};
+use dkdto::{
+    AddItemRequest, AddItemTagRequest, AddItemVersionRequest, AddTagValue, BulkTagRequest,
+    CheckOutRequest, DeletedTagValue, EnumTagValue, GetItemReply, ImportCsvRequest,
+    LegalHoldRequest, UpdateFullTextDictionaryRequest,
+};
 use doka_cli::request_client::{DocumentServerClient, FileServerClient};
 
+use crate::file_commands::file_download;
 use crate::item_commands::DisplayFormat::{INLINE, JSON};
 use crate::session_commands::read_session_id;
 
@@ -60,6 +65,109 @@ pub(crate) fn search_item() -> anyhow::Result<()> {
     }
 }
 
+///
+pub(crate) fn item_fulltext(
+    query: &str,
+    o_start_page: Option<&str>,
+    o_page_size: Option<&str>,
+) -> anyhow::Result<()> {
+    println!("👶 Searching the text of the items...");
+
+    let start_page = o_start_page.map(str::parse::<u32>).transpose()?;
+    let page_size = o_page_size.map(str::parse::<u32>).transpose()?;
+
+    let server_host = get_prop_value("server.host")?;
+    let document_server_port: u16 = get_prop_value("ds.port")?.parse()?;
+    let client = DocumentServerClient::new(&server_host, document_server_port);
+    let sid = read_session_id()?;
+
+    match client.search_fulltext(query, start_page, page_size, &sid) {
+        Ok(reply) => {
+            println!("😎 Items successfully found, count : {} ", reply.hits.len());
+            for hit in &reply.hits {
+                println!(
+                    "{}\t{}\t{}",
+                    hit.item_id,
+                    &hit.name,
+                    hit.file_ref.as_deref().unwrap_or_default()
+                );
+                for snippet in &hit.snippets {
+                    println!("\t{}", snippet);
+                }
+            }
+            Ok(())
+        }
+        Err(e) => Err(anyhow!("{}", e.message)),
+    }
+}
+
+///
+pub(crate) fn item_dictionary() -> anyhow::Result<()> {
+    println!("👶 Getting the full text dictionary...");
+
+    let server_host = get_prop_value("server.host")?;
+    let document_server_port: u16 = get_prop_value("ds.port")?.parse()?;
+    let client = DocumentServerClient::new(&server_host, document_server_port);
+    let sid = read_session_id()?;
+
+    match client.get_fulltext_dictionary(&sid) {
+        Ok(reply) => {
+            println!("😎 Dictionary successfully found");
+            println!("stop words:\t{}", reply.stop_words.join(","));
+            for group in &reply.synonyms {
+                println!("synonyms:\t{}", group.join(","));
+            }
+            Ok(())
+        }
+        Err(e) => Err(anyhow!("{}", e.message)),
+    }
+}
+
+/// Replace the stop words "art,sa" and/or the synonym groups "llc,limited company;invoice,bill"
+/// The part not given is kept
+pub(crate) fn item_dictionary_set(
+    o_stop_words: Option<&str>,
+    o_synonyms: Option<&str>,
+) -> anyhow::Result<()> {
+    println!("👶 Changing the full text dictionary...");
+
+    let split_words = |words: &str| -> Vec<String> {
+        words
+            .split(',')
+            .map(|w| w.trim().to_string())
+            .filter(|w| !w.is_empty())
+            .collect()
+    };
+
+    let server_host = get_prop_value("server.host")?;
+    let document_server_port: u16 = get_prop_value("ds.port")?.parse()?;
+    let client = DocumentServerClient::new(&server_host, document_server_port);
+    let sid = read_session_id()?;
+
+    let current = client
+        .get_fulltext_dictionary(&sid)
+        .map_err(|e| anyhow!("{}", e.message))?;
+
+    let request = UpdateFullTextDictionaryRequest {
+        stop_words: o_stop_words.map_or(current.stop_words, split_words),
+        synonyms: match o_synonyms {
+            None => current.synonyms,
+            Some(groups) => groups.split(';').map(split_words).collect(),
+        },
+    };
+
+    match client.update_fulltext_dictionary(&request, &sid) {
+        Ok(reply) => {
+            println!("😎 Dictionary successfully changed");
+            if let Some(job_id) = reply.reindex_job_id {
+                println!("😎 Reindex job started, job id : {}", job_id);
+            }
+            Ok(())
+        }
+        Err(e) => Err(anyhow!("{}", e.message)),
+    }
+}
+
 //
 fn show_items(items: &GetItemReply, display_format: DisplayFormat) -> anyhow::Result<()> {
     match &display_format {
@@ -180,6 +288,7 @@ pub(crate) fn create_item(
     o_file_ref: Option<&str>,
     o_path: Option<&str>,
     o_properties: Option<&str>,
+    o_document_type: Option<&str>,
 ) -> anyhow::Result<()> {
     println!("👶 Creating the item...");
 
@@ -243,6 +352,7 @@ pub(crate) fn create_item(
         name: item_name.to_owned(),
         file_ref,
         properties: Some(properties),
+        document_type: o_document_type.map(str::to_string),
     };
 
     // dbg!(&add_item_request);
@@ -286,7 +396,11 @@ pub fn item_tag_update(id: &str, o_add_props: Option<&str>) -> anyhow::Result<()
     }
 }
 
-pub fn item_tag_delete(id: &str, o_delete_props: Option<&str>) -> anyhow::Result<()> {
+pub fn item_tag_delete(
+    id: &str,
+    o_delete_props: Option<&str>,
+    o_delete_values: Option<&str>,
+) -> anyhow::Result<()> {
     println!("👶 Delete the item tags...");
 
     let item_id: i64 = id.parse()?;
@@ -298,6 +412,16 @@ pub fn item_tag_delete(id: &str, o_delete_props: Option<&str>) -> anyhow::Result
         .map(|tag| tag.to_string())
         .collect();
 
+    // The single values are given by their tag value id, as shown by the item
+    let value_ids = o_delete_values
+        .map(|ids| {
+            ids.split(',')
+                .map(|id| id.trim().parse::<i64>())
+                .collect::<Result<Vec<i64>, _>>()
+        })
+        .transpose()?
+        .unwrap_or_default();
+
     dbg!(&tag_names);
 
     let sid = read_session_id()?;
@@ -306,7 +430,8 @@ pub fn item_tag_delete(id: &str, o_delete_props: Option<&str>) -> anyhow::Result
 
     let document_server_client = DocumentServerClient::new(&server_host, document_server_port);
     // let add_item_tag_request = AddItemTagRequest { item_id, properties };
-    let r_add_item_tag = document_server_client.delete_item_tag(item_id, &tag_names, &sid);
+    let r_add_item_tag =
+        document_server_client.delete_item_tag(item_id, &tag_names, &value_ids, &sid);
 
     match r_add_item_tag {
         Ok(_reply) => {
@@ -317,6 +442,396 @@ pub fn item_tag_delete(id: &str, o_delete_props: Option<&str>) -> anyhow::Result
     }
 }
 
+pub fn item_delete(id: &str) -> anyhow::Result<()> {
+    println!("👶 Move the item to the trash...");
+
+    let item_id: i64 = id.parse()?;
+
+    let sid = read_session_id()?;
+    let server_host = get_prop_value("server.host")?;
+    let document_server_port: u16 = get_prop_value("ds.port")?.parse()?;
+
+    let document_server_client = DocumentServerClient::new(&server_host, document_server_port);
+    match document_server_client.trash_item(item_id, &sid) {
+        Ok(_reply) => {
+            println!(
+                "😎 Item successfully moved to the trash, item id : {} ",
+                item_id
+            );
+            Ok(())
+        }
+        Err(e) => Err(anyhow!("{}", e.message)),
+    }
+}
+
+pub fn item_hold(id: &str, legal_hold: bool) -> anyhow::Result<()> {
+    println!("👶 Change the legal hold of the item...");
+
+    let item_id: i64 = id.parse()?;
+
+    let sid = read_session_id()?;
+    let server_host = get_prop_value("server.host")?;
+    let document_server_port: u16 = get_prop_value("ds.port")?.parse()?;
+
+    let document_server_client = DocumentServerClient::new(&server_host, document_server_port);
+    match document_server_client.set_legal_hold(item_id, &LegalHoldRequest { legal_hold }, &sid) {
+        Ok(_reply) => {
+            println!(
+                "😎 Legal hold successfully changed, item id : {}, legal hold : {} ",
+                item_id, legal_hold
+            );
+            Ok(())
+        }
+        Err(e) => Err(anyhow!("{}", e.message)),
+    }
+}
+
+pub fn item_checkout(id: &str, o_minutes: Option<&str>) -> anyhow::Result<()> {
+    println!("👶 Check out the item...");
+
+    let item_id: i64 = id.parse()?;
+    let duration_minutes = match o_minutes {
+        Some(minutes) => Some(minutes.parse::<i32>()?),
+        None => None,
+    };
+
+    let sid = read_session_id()?;
+    let server_host = get_prop_value("server.host")?;
+    let document_server_port: u16 = get_prop_value("ds.port")?.parse()?;
+
+    let document_server_client = DocumentServerClient::new(&server_host, document_server_port);
+    match document_server_client.check_out_item(
+        item_id,
+        &CheckOutRequest { duration_minutes },
+        &sid,
+    ) {
+        Ok(reply) => {
+            println!(
+                "😎 Item successfully checked out, item id : {}, version : {}, until : {} ",
+                item_id, reply.version, &reply.expiry
+            );
+            Ok(())
+        }
+        Err(e) => Err(anyhow!("{}", e.message)),
+    }
+}
+
+pub fn item_checkin(id: &str) -> anyhow::Result<()> {
+    println!("👶 Check in the item...");
+
+    let item_id: i64 = id.parse()?;
+
+    let sid = read_session_id()?;
+    let server_host = get_prop_value("server.host")?;
+    let document_server_port: u16 = get_prop_value("ds.port")?.parse()?;
+
+    let document_server_client = DocumentServerClient::new(&server_host, document_server_port);
+    match document_server_client.check_in_item(item_id, &sid) {
+        Ok(_reply) => {
+            println!("😎 Item successfully checked in, item id : {} ", item_id);
+            Ok(())
+        }
+        Err(e) => Err(anyhow!("{}", e.message)),
+    }
+}
+
+pub fn item_versions(id: &str) -> anyhow::Result<()> {
+    println!("👶 Getting the versions of the item...");
+
+    let item_id: i64 = id.parse()?;
+
+    let sid = read_session_id()?;
+    let server_host = get_prop_value("server.host")?;
+    let document_server_port: u16 = get_prop_value("ds.port")?.parse()?;
+
+    let document_server_client = DocumentServerClient::new(&server_host, document_server_port);
+    match document_server_client.get_item_versions(item_id, &sid) {
+        Ok(reply) => {
+            println!("😎 Versions of the item : {}", reply.versions.len());
+            println!("version\tcurrent\tfile ref\tcreated\tuser id\tcomment");
+            for version in reply.versions {
+                println!(
+                    "{}\t{}\t{}\t{}\t{}\t{}",
+                    version.version_no,
+                    if version.current { "*" } else { "" },
+                    &version.file_ref,
+                    &version.created,
+                    version.user_id.map(|u| u.to_string()).unwrap_or_default(),
+                    version.comment.unwrap_or_default()
+                );
+            }
+            Ok(())
+        }
+        Err(e) => Err(anyhow!("{}", e.message)),
+    }
+}
+
+///
+/// Print the text extracted from the current file of the item
+///
+pub fn item_text(id: &str) -> anyhow::Result<()> {
+    println!("👶 Getting the text of the item...");
+
+    let item_id: i64 = id.parse()?;
+
+    let sid = read_session_id()?;
+    let server_host = get_prop_value("server.host")?;
+    let document_server_port: u16 = get_prop_value("ds.port")?.parse()?;
+
+    let document_server_client = DocumentServerClient::new(&server_host, document_server_port);
+    match document_server_client.get_item_text(item_id, &sid) {
+        Ok(reply) => {
+            println!(
+                "😎 Text of the file : {}, parts : {}, languages : {}",
+                &reply.file_ref,
+                reply.part_count,
+                reply.languages.join(", ")
+            );
+            println!("{}", &reply.text);
+            Ok(())
+        }
+        Err(e) => Err(anyhow!("{}", e.message)),
+    }
+}
+
+///
+/// Print the tag values matching the keywords of the document of the item
+///
+pub fn item_suggested_tags(id: &str) -> anyhow::Result<()> {
+    println!("👶 Getting the suggested tags of the item...");
+
+    let item_id: i64 = id.parse()?;
+
+    let sid = read_session_id()?;
+    let server_host = get_prop_value("server.host")?;
+    let document_server_port: u16 = get_prop_value("ds.port")?.parse()?;
+
+    let document_server_client = DocumentServerClient::new(&server_host, document_server_port);
+    match document_server_client.get_suggested_tags(item_id, &sid) {
+        Ok(reply) => {
+            println!(
+                "😎 Suggested tags of the item : {}, number of tags : {}",
+                reply.item_id,
+                reply.tags.len()
+            );
+            for tag in &reply.tags {
+                println!(
+                    "{} ({}) = {}, score : {:.2}",
+                    &tag.tag_name, &tag.tag_type, &tag.value, tag.score
+                );
+            }
+            Ok(())
+        }
+        Err(e) => Err(anyhow!("{}", e.message)),
+    }
+}
+
+pub fn item_version_add(id: &str, file_ref: &str, o_comment: Option<&str>) -> anyhow::Result<()> {
+    println!("👶 Add a version to the item...");
+
+    let item_id: i64 = id.parse()?;
+
+    let sid = read_session_id()?;
+    let server_host = get_prop_value("server.host")?;
+    let document_server_port: u16 = get_prop_value("ds.port")?.parse()?;
+
+    let request = AddItemVersionRequest {
+        file_ref: file_ref.to_owned(),
+        comment: o_comment.map(str::to_owned),
+    };
+
+    let document_server_client = DocumentServerClient::new(&server_host, document_server_port);
+    match document_server_client.add_item_version(item_id, &request, &sid) {
+        Ok(reply) => {
+            println!(
+                "😎 Version successfully added, item id : {}, version : {} ",
+                item_id, reply.version_no
+            );
+            Ok(())
+        }
+        Err(e) => Err(anyhow!("{}", e.message)),
+    }
+}
+
+///
+/// Download the file of a version of the item from the file server
+///
+pub fn item_version_download(id: &str, version: &str, path: &str) -> anyhow::Result<()> {
+    let item_id: i64 = id.parse()?;
+    let version_no: i32 = version.parse()?;
+
+    let sid = read_session_id()?;
+    let server_host = get_prop_value("server.host")?;
+    let document_server_port: u16 = get_prop_value("ds.port")?.parse()?;
+
+    let document_server_client = DocumentServerClient::new(&server_host, document_server_port);
+    let reply = document_server_client
+        .get_item_versions(item_id, &sid)
+        .map_err(|e| anyhow!("{}", e.message))?;
+    let version = reply
+        .versions
+        .iter()
+        .find(|v| v.version_no == version_no)
+        .ok_or_else(|| anyhow!("💣 Missing version, version=[{}]", version_no))?;
+
+    file_download(path, &version.file_ref)
+}
+
+pub fn item_rollback(id: &str, version: &str) -> anyhow::Result<()> {
+    println!("👶 Roll back the item...");
+
+    let item_id: i64 = id.parse()?;
+    let version_no: i32 = version.parse()?;
+
+    let sid = read_session_id()?;
+    let server_host = get_prop_value("server.host")?;
+    let document_server_port: u16 = get_prop_value("ds.port")?.parse()?;
+
+    let document_server_client = DocumentServerClient::new(&server_host, document_server_port);
+    match document_server_client.rollback_item_version(item_id, version_no, &sid) {
+        Ok(_reply) => {
+            println!(
+                "😎 Item successfully rolled back, item id : {}, version : {} ",
+                item_id, version_no
+            );
+            Ok(())
+        }
+        Err(e) => Err(anyhow!("{}", e.message)),
+    }
+}
+
+pub fn item_bulk_tag(
+    filters: &str,
+    o_add_props: Option<&str>,
+    o_delete_props: Option<&str>,
+    o_delete_values: Option<&str>,
+    dry_run: bool,
+) -> anyhow::Result<()> {
+    println!("👶 Change the tags of the items matching the filter...");
+
+    // Fill the properties vector from the "(tag[:value[:link|date|text|number]])()..."
+    let properties = build_properties_from_string(o_add_props)?;
+
+    let deleted_tags: Vec<String> = o_delete_props
+        .map(|props| props.split(',').map(|tag| tag.to_string()).collect())
+        .unwrap_or_default();
+
+    // The single values are "tag:value", separated by commas
+    let deleted_values = o_delete_values
+        .map(|values| {
+            values
+                .split(',')
+                .map(|value| {
+                    value
+                        .split_once(':')
+                        .map(|(tag_name, value)| DeletedTagValue {
+                            tag_name: tag_name.to_string(),
+                            value: value.to_string(),
+                        })
+                        .ok_or(anyhow!("Wrong tag value, expected tag:value, [{}]", value))
+                })
+                .collect::<anyhow::Result<Vec<DeletedTagValue>>>()
+        })
+        .transpose()?
+        .unwrap_or_default();
+
+    let sid = read_session_id()?;
+    let server_host = get_prop_value("server.host")?;
+    let document_server_port: u16 = get_prop_value("ds.port")?.parse()?;
+
+    let document_server_client = DocumentServerClient::new(&server_host, document_server_port);
+    let bulk_tag_request = BulkTagRequest {
+        filters: filters.to_string(),
+        properties: Some(properties),
+        deleted_tags: Some(deleted_tags),
+        deleted_values: Some(deleted_values),
+        dry_run: Some(dry_run),
+    };
+    let r_bulk_tag = document_server_client.bulk_tag(&bulk_tag_request, &sid);
+
+    match r_bulk_tag {
+        Ok(reply) => {
+            if reply.dry_run {
+                println!("😎 Items to be changed : {} ", reply.item_count);
+            } else {
+                println!(
+                    "😎 Tags successfully changed, item count : {} ",
+                    reply.item_count
+                );
+            }
+            Ok(())
+        }
+        Err(e) => Err(anyhow!("{}", e.message)),
+    }
+}
+
+pub fn item_import_csv(path: &str, o_match_by: Option<&str>, dry_run: bool) -> anyhow::Result<()> {
+    println!("👶 Import the tag values from the csv file...");
+
+    let csv = std::fs::read_to_string(path)
+        .map_err(eprint_fwd!("Cannot read the csv file, path=[{}]", path))?;
+
+    let sid = read_session_id()?;
+    let server_host = get_prop_value("server.host")?;
+    let document_server_port: u16 = get_prop_value("ds.port")?.parse()?;
+
+    let document_server_client = DocumentServerClient::new(&server_host, document_server_port);
+    let import_request = ImportCsvRequest {
+        csv,
+        match_by: o_match_by.map(|m| m.to_string()),
+        dry_run: Some(dry_run),
+    };
+
+    match document_server_client.import_csv(&import_request, &sid) {
+        Ok(reply) => {
+            for error in &reply.errors {
+                println!("💣 Line {} : {}", error.line, &error.message);
+            }
+            if !reply.errors.is_empty() {
+                return Err(anyhow!(
+                    "The csv has {} error(s), no item changed",
+                    reply.errors.len()
+                ));
+            }
+            if reply.dry_run {
+                println!(
+                    "😎 The csv is valid, rows : {}, items to be changed : {} ",
+                    reply.row_count, reply.item_count
+                );
+            } else {
+                println!(
+                    "😎 Tags successfully imported, item count : {} ",
+                    reply.item_count
+                );
+            }
+            Ok(())
+        }
+        Err(e) => Err(anyhow!("{}", e.message)),
+    }
+}
+
+pub fn item_export_csv(o_filters: Option<&str>, path: &str) -> anyhow::Result<()> {
+    println!("👶 Export the items to the csv file...");
+
+    let sid = read_session_id()?;
+    let server_host = get_prop_value("server.host")?;
+    let document_server_port: u16 = get_prop_value("ds.port")?.parse()?;
+
+    let document_server_client = DocumentServerClient::new(&server_host, document_server_port);
+    match document_server_client.export_csv(o_filters.unwrap_or(""), &sid) {
+        Ok(reply) => {
+            std::fs::write(path, reply.csv)
+                .map_err(eprint_fwd!("Cannot write the csv file, path=[{}]", path))?;
+            println!(
+                "😎 Items successfully exported, item count : {} ",
+                reply.item_count
+            );
+            Ok(())
+        }
+        Err(e) => Err(anyhow!("{}", e.message)),
+    }
+}
+
 fn build_properties_from_string(o_props: Option<&str>) -> anyhow::Result<Vec<AddTagValue>> {
     let properties = if let Some(props_str) = o_props {
         let re = Regex::new(r"\((.*?)\)").unwrap();
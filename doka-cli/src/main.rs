@@ -8,19 +8,39 @@ use commons_error::*;
 use dkconfig::conf_reader::{read_config, read_config_from_path, read_doka_env};
 use dkconfig::properties::{get_prop_value, set_prop_values};
 
+use crate::classification_commands::{
+    classification_create, classification_delete, classification_dry_run, classification_list,
+};
 use crate::command_options::{display_commands, load_commands, parse_args, Command, Params};
+use crate::comment_commands::{comment_add, comment_delete, comment_edit, comment_list};
 use crate::customer_commands::{create_customer, delete_customer, disable_customer};
-use crate::file_commands::{file_download, file_info, file_list, file_loading, file_upload};
-use crate::item_commands::{create_item, get_item, item_tag_delete, item_tag_update, search_item};
+use crate::file_commands::{
+    file_download, file_info, file_list, file_loading, file_reindex, file_reindex_resume,
+    file_reindex_status, file_text, file_upload,
+};
+use crate::item_commands::{
+    create_item, get_item, item_bulk_tag, item_checkin, item_checkout, item_delete,
+    item_dictionary, item_dictionary_set, item_export_csv, item_fulltext, item_hold,
+    item_import_csv, item_rollback, item_suggested_tags, item_tag_delete, item_tag_update,
+    item_text, item_version_add, item_version_download, item_versions, search_item,
+};
+use crate::retention_commands::{
+    retention_apply, retention_certificates, retention_create, retention_delete, retention_list,
+};
 use crate::session_commands::session_login;
 use crate::token_commands::{get_target_file, token_generate};
+use crate::trash_commands::{trash_list, trash_purge, trash_restore, trash_retention};
 
+mod classification_commands;
 mod command_options;
+mod comment_commands;
 mod customer_commands;
 mod file_commands;
 mod item_commands;
+mod retention_commands;
 mod session_commands;
 mod token_commands;
+mod trash_commands;
 
 const PARAMETER_ERROR: u16 = 10;
 const LOGIN_SESSION_FAILED: u16 = 30;
@@ -31,8 +51,13 @@ const GENERATE_TOKEN_FAILED: u16 = 80;
 const CREATE_ITEM_FAILED: u16 = 90;
 const GET_ITEM_FAILED: u16 = 100;
 const PROP_ITEM_FAILED: u16 = 101;
+const DELETE_ITEM_FAILED: u16 = 102;
+const RETENTION_FAILED: u16 = 103;
+const COMMENT_FAILED: u16 = 104;
+const CLASSIFICATION_FAILED: u16 = 105;
 const FILE_UPLOAD_FAILED: u16 = 110;
 const FILE_DOWNLOAD_FAILED: u16 = 120;
+const FILE_REINDEX_FAILED: u16 = 130;
 const SUCCESS: u16 = 0;
 
 fn read_configuration_file() -> anyhow::Result<()> {
@@ -140,13 +165,20 @@ fn dispatch(params: &Params, commands: &[Command]) -> u16 {
             success_or_err(err, LOGIN_SESSION_FAILED)
         }
         ("item", "create") => {
-            let Ok((item_name, o_file_ref, o_path, o_properties)) =
-                (|| -> anyhow::Result<(String, Option<String>, Option<String>, Option<String>)> {
+            let Ok((item_name, o_file_ref, o_path, o_properties, o_document_type)) =
+                (|| -> anyhow::Result<(
+                    String,
+                    Option<String>,
+                    Option<String>,
+                    Option<String>,
+                    Option<String>,
+                )> {
                     Ok((
                         extract_mandatory_option(&params.options, "-n")?,
                         extract_option(&params.options, "-fr")?,
                         extract_option(&params.options, "-pt")?,
                         extract_option(&params.options, "-p")?,
+                        extract_option(&params.options, "-dt")?,
                     ))
                 })()
                 .map_err(eprint_fwd!("Error"))
@@ -158,6 +190,7 @@ fn dispatch(params: &Params, commands: &[Command]) -> u16 {
                 o_file_ref.as_deref(),
                 o_path.as_deref(),
                 o_properties.as_deref(),
+                o_document_type.as_deref(),
             );
             success_or_err(err, CREATE_ITEM_FAILED)
         }
@@ -165,6 +198,41 @@ fn dispatch(params: &Params, commands: &[Command]) -> u16 {
             let _err = search_item();
             0
         }
+        ("item", "fulltext") => {
+            let Ok((query, o_start_page, o_page_size)) =
+                (|| -> anyhow::Result<(String, Option<String>, Option<String>)> {
+                    Ok((
+                        extract_mandatory_option(&params.options, "-q")?,
+                        extract_option(&params.options, "-sp")?,
+                        extract_option(&params.options, "-ps")?,
+                    ))
+                })()
+                .map_err(eprint_fwd!("Error"))
+            else {
+                return PARAMETER_ERROR;
+            };
+            let err = item_fulltext(&query, o_start_page.as_deref(), o_page_size.as_deref());
+            success_or_err(err, GET_ITEM_FAILED)
+        }
+        ("item", "dictionary") => {
+            let err = item_dictionary();
+            success_or_err(err, GET_ITEM_FAILED)
+        }
+        ("item", "dictionary-set") => {
+            let Ok((o_stop_words, o_synonyms)) =
+                (|| -> anyhow::Result<(Option<String>, Option<String>)> {
+                    Ok((
+                        extract_option(&params.options, "-s")?,
+                        extract_option(&params.options, "-y")?,
+                    ))
+                })()
+                .map_err(eprint_fwd!("Error"))
+            else {
+                return PARAMETER_ERROR;
+            };
+            let err = item_dictionary_set(o_stop_words.as_deref(), o_synonyms.as_deref());
+            success_or_err(err, PROP_ITEM_FAILED)
+        }
         ("item", "get") => {
             let Ok(id) =
                 extract_mandatory_option(&params.options, "-id").map_err(eprint_fwd!("Error"))
@@ -175,11 +243,12 @@ fn dispatch(params: &Params, commands: &[Command]) -> u16 {
             success_or_err(err, GET_ITEM_FAILED)
         }
         ("item", "tag") => {
-            let Ok((id, o_delete_prop, o_add_props)) =
-                (|| -> anyhow::Result<(String, Option<String>, Option<String>)> {
+            let Ok((id, o_delete_prop, o_delete_values, o_add_props)) =
+                (|| -> anyhow::Result<(String, Option<String>, Option<String>, Option<String>)> {
                     Ok((
                         extract_mandatory_option(&params.options, "-id")?,
                         extract_option(&params.options, "-d")?,
+                        extract_option(&params.options, "-dv")?,
                         extract_option(&params.options, "-u")?,
                     ))
                 })()
@@ -191,10 +260,359 @@ fn dispatch(params: &Params, commands: &[Command]) -> u16 {
             let err = if o_add_props.is_some() {
                 item_tag_update(&id, o_add_props.as_deref())
             } else {
-                item_tag_delete(&id, o_delete_prop.as_deref())
+                item_tag_delete(&id, o_delete_prop.as_deref(), o_delete_values.as_deref())
+            };
+            success_or_err(err, PROP_ITEM_FAILED)
+        }
+        ("item", "bulk-tag") => {
+            let Ok((filters, o_delete_props, o_delete_values, o_add_props)) =
+                (|| -> anyhow::Result<(String, Option<String>, Option<String>, Option<String>)> {
+                    Ok((
+                        extract_mandatory_option(&params.options, "-f")?,
+                        extract_option(&params.options, "-d")?,
+                        extract_option(&params.options, "-dv")?,
+                        extract_option(&params.options, "-u")?,
+                    ))
+                })()
+                .map_err(eprint_fwd!("Error"))
+            else {
+                return PARAMETER_ERROR;
+            };
+            let dry_run = params.options.contains_key("-dry");
+
+            let err = item_bulk_tag(
+                &filters,
+                o_add_props.as_deref(),
+                o_delete_props.as_deref(),
+                o_delete_values.as_deref(),
+                dry_run,
+            );
+            success_or_err(err, PROP_ITEM_FAILED)
+        }
+        ("item", "import-csv") => {
+            let Ok((path, o_match_by)) = (|| -> anyhow::Result<(String, Option<String>)> {
+                Ok((
+                    extract_mandatory_option(&params.options, "-pt")?,
+                    extract_option(&params.options, "-m")?,
+                ))
+            })()
+            .map_err(eprint_fwd!("Error")) else {
+                return PARAMETER_ERROR;
+            };
+            let dry_run = params.options.contains_key("-dry");
+
+            let err = item_import_csv(&path, o_match_by.as_deref(), dry_run);
+            success_or_err(err, PROP_ITEM_FAILED)
+        }
+        ("item", "export-csv") => {
+            let Ok((path, o_filters)) = (|| -> anyhow::Result<(String, Option<String>)> {
+                Ok((
+                    extract_mandatory_option(&params.options, "-pt")?,
+                    extract_option(&params.options, "-f")?,
+                ))
+            })()
+            .map_err(eprint_fwd!("Error")) else {
+                return PARAMETER_ERROR;
+            };
+
+            let err = item_export_csv(o_filters.as_deref(), &path);
+            success_or_err(err, GET_ITEM_FAILED)
+        }
+        ("item", "delete") => {
+            let Ok(id) =
+                extract_mandatory_option(&params.options, "-id").map_err(eprint_fwd!("Error"))
+            else {
+                return PARAMETER_ERROR;
+            };
+            let err = item_delete(&id);
+            success_or_err(err, DELETE_ITEM_FAILED)
+        }
+        ("trash", "list") => {
+            let err = trash_list();
+            success_or_err(err, GET_ITEM_FAILED)
+        }
+        ("trash", "restore") => {
+            let Ok(id) =
+                extract_mandatory_option(&params.options, "-id").map_err(eprint_fwd!("Error"))
+            else {
+                return PARAMETER_ERROR;
+            };
+            let err = trash_restore(&id);
+            success_or_err(err, DELETE_ITEM_FAILED)
+        }
+        ("trash", "purge") => {
+            let Ok(id) =
+                extract_mandatory_option(&params.options, "-id").map_err(eprint_fwd!("Error"))
+            else {
+                return PARAMETER_ERROR;
+            };
+            let err = trash_purge(&id);
+            success_or_err(err, DELETE_ITEM_FAILED)
+        }
+        ("trash", "retention") => {
+            let Ok(days) =
+                extract_mandatory_option(&params.options, "-d").map_err(eprint_fwd!("Error"))
+            else {
+                return PARAMETER_ERROR;
+            };
+            let err = trash_retention(&days);
+            success_or_err(err, DELETE_ITEM_FAILED)
+        }
+        ("item", "hold") => {
+            let Ok(id) =
+                extract_mandatory_option(&params.options, "-id").map_err(eprint_fwd!("Error"))
+            else {
+                return PARAMETER_ERROR;
+            };
+            let legal_hold = !params.options.contains_key("-off");
+            let err = item_hold(&id, legal_hold);
+            success_or_err(err, PROP_ITEM_FAILED)
+        }
+        ("item", "checkout") => {
+            let Ok((id, o_minutes)) = (|| -> anyhow::Result<(String, Option<String>)> {
+                Ok((
+                    extract_mandatory_option(&params.options, "-id")?,
+                    extract_option(&params.options, "-m")?,
+                ))
+            })()
+            .map_err(eprint_fwd!("Error")) else {
+                return PARAMETER_ERROR;
+            };
+            let err = item_checkout(&id, o_minutes.as_deref());
+            success_or_err(err, PROP_ITEM_FAILED)
+        }
+        ("item", "checkin") => {
+            let Ok(id) =
+                extract_mandatory_option(&params.options, "-id").map_err(eprint_fwd!("Error"))
+            else {
+                return PARAMETER_ERROR;
+            };
+            let err = item_checkin(&id);
+            success_or_err(err, PROP_ITEM_FAILED)
+        }
+        ("item", "text") => {
+            let Ok(id) =
+                extract_mandatory_option(&params.options, "-id").map_err(eprint_fwd!("Error"))
+            else {
+                return PARAMETER_ERROR;
+            };
+            let err = item_text(&id);
+            success_or_err(err, GET_ITEM_FAILED)
+        }
+        ("item", "suggested-tags") => {
+            let Ok(id) =
+                extract_mandatory_option(&params.options, "-id").map_err(eprint_fwd!("Error"))
+            else {
+                return PARAMETER_ERROR;
+            };
+            let err = item_suggested_tags(&id);
+            success_or_err(err, GET_ITEM_FAILED)
+        }
+        ("item", "versions") => {
+            let Ok(id) =
+                extract_mandatory_option(&params.options, "-id").map_err(eprint_fwd!("Error"))
+            else {
+                return PARAMETER_ERROR;
+            };
+            let err = item_versions(&id);
+            success_or_err(err, GET_ITEM_FAILED)
+        }
+        ("item", "version-add") => {
+            let Ok((id, file_ref, o_comment)) =
+                (|| -> anyhow::Result<(String, String, Option<String>)> {
+                    Ok((
+                        extract_mandatory_option(&params.options, "-id")?,
+                        extract_mandatory_option(&params.options, "-fr")?,
+                        extract_option(&params.options, "-c")?,
+                    ))
+                })()
+                .map_err(eprint_fwd!("Error"))
+            else {
+                return PARAMETER_ERROR;
             };
+            let err = item_version_add(&id, &file_ref, o_comment.as_deref());
             success_or_err(err, PROP_ITEM_FAILED)
         }
+        ("item", "version-download") => {
+            let Ok((id, version, path)) = (|| -> anyhow::Result<(String, String, String)> {
+                Ok((
+                    extract_mandatory_option(&params.options, "-id")?,
+                    extract_mandatory_option(&params.options, "-v")?,
+                    extract_mandatory_option(&params.options, "-pt")?,
+                ))
+            })()
+            .map_err(eprint_fwd!("Error")) else {
+                return PARAMETER_ERROR;
+            };
+            let err = item_version_download(&id, &version, &path);
+            success_or_err(err, FILE_DOWNLOAD_FAILED)
+        }
+        ("item", "rollback") => {
+            let Ok((id, version)) = (|| -> anyhow::Result<(String, String)> {
+                Ok((
+                    extract_mandatory_option(&params.options, "-id")?,
+                    extract_mandatory_option(&params.options, "-v")?,
+                ))
+            })()
+            .map_err(eprint_fwd!("Error")) else {
+                return PARAMETER_ERROR;
+            };
+            let err = item_rollback(&id, &version);
+            success_or_err(err, PROP_ITEM_FAILED)
+        }
+        ("comment", "list") => {
+            let Ok(id) =
+                extract_mandatory_option(&params.options, "-id").map_err(eprint_fwd!("Error"))
+            else {
+                return PARAMETER_ERROR;
+            };
+            let err = comment_list(&id);
+            success_or_err(err, COMMENT_FAILED)
+        }
+        ("comment", "add") => {
+            let Ok((id, text, o_parent, o_mentions, o_page, o_offset)) =
+                (|| -> anyhow::Result<(
+                    String,
+                    String,
+                    Option<String>,
+                    Option<String>,
+                    Option<String>,
+                    Option<String>,
+                )> {
+                    Ok((
+                        extract_mandatory_option(&params.options, "-id")?,
+                        extract_mandatory_option(&params.options, "-t")?,
+                        extract_option(&params.options, "-r")?,
+                        extract_option(&params.options, "-m")?,
+                        extract_option(&params.options, "-pg")?,
+                        extract_option(&params.options, "-o")?,
+                    ))
+                })()
+                .map_err(eprint_fwd!("Error"))
+            else {
+                return PARAMETER_ERROR;
+            };
+            let err = comment_add(
+                &id,
+                &text,
+                o_parent.as_deref(),
+                o_mentions.as_deref(),
+                o_page.as_deref(),
+                o_offset.as_deref(),
+            );
+            success_or_err(err, COMMENT_FAILED)
+        }
+        ("comment", "edit") => {
+            let Ok((id, comment_id, text, o_mentions)) =
+                (|| -> anyhow::Result<(String, String, String, Option<String>)> {
+                    Ok((
+                        extract_mandatory_option(&params.options, "-id")?,
+                        extract_mandatory_option(&params.options, "-cid")?,
+                        extract_mandatory_option(&params.options, "-t")?,
+                        extract_option(&params.options, "-m")?,
+                    ))
+                })()
+                .map_err(eprint_fwd!("Error"))
+            else {
+                return PARAMETER_ERROR;
+            };
+            let err = comment_edit(&id, &comment_id, &text, o_mentions.as_deref());
+            success_or_err(err, COMMENT_FAILED)
+        }
+        ("comment", "delete") => {
+            let Ok((id, comment_id)) = (|| -> anyhow::Result<(String, String)> {
+                Ok((
+                    extract_mandatory_option(&params.options, "-id")?,
+                    extract_mandatory_option(&params.options, "-cid")?,
+                ))
+            })()
+            .map_err(eprint_fwd!("Error")) else {
+                return PARAMETER_ERROR;
+            };
+            let err = comment_delete(&id, &comment_id);
+            success_or_err(err, COMMENT_FAILED)
+        }
+        ("retention", "list") => {
+            let err = retention_list();
+            success_or_err(err, RETENTION_FAILED)
+        }
+        ("retention", "create") => {
+            let Ok((name, filters, start_from, period)) =
+                (|| -> anyhow::Result<(String, String, String, String)> {
+                    Ok((
+                        extract_mandatory_option(&params.options, "-n")?,
+                        extract_mandatory_option(&params.options, "-f")?,
+                        extract_mandatory_option(&params.options, "-s")?,
+                        extract_mandatory_option(&params.options, "-p")?,
+                    ))
+                })()
+                .map_err(eprint_fwd!("Error"))
+            else {
+                return PARAMETER_ERROR;
+            };
+            let err = retention_create(&name, &filters, &start_from, &period);
+            success_or_err(err, RETENTION_FAILED)
+        }
+        ("retention", "delete") => {
+            let Ok(id) =
+                extract_mandatory_option(&params.options, "-id").map_err(eprint_fwd!("Error"))
+            else {
+                return PARAMETER_ERROR;
+            };
+            let err = retention_delete(&id);
+            success_or_err(err, RETENTION_FAILED)
+        }
+        ("retention", "apply") => {
+            let dry_run = params.options.contains_key("-dry");
+            let err = retention_apply(dry_run);
+            success_or_err(err, RETENTION_FAILED)
+        }
+        ("retention", "certificates") => {
+            let err = retention_certificates();
+            success_or_err(err, RETENTION_FAILED)
+        }
+        ("classification", "list") => {
+            let err = classification_list();
+            success_or_err(err, CLASSIFICATION_FAILED)
+        }
+        ("classification", "create") => {
+            let Ok((name, condition, actions)) =
+                (|| -> anyhow::Result<(String, String, String)> {
+                    Ok((
+                        extract_mandatory_option(&params.options, "-n")?,
+                        extract_mandatory_option(&params.options, "-c")?,
+                        extract_mandatory_option(&params.options, "-a")?,
+                    ))
+                })()
+                .map_err(eprint_fwd!("Error"))
+            else {
+                return PARAMETER_ERROR;
+            };
+            let err = classification_create(&name, &condition, &actions);
+            success_or_err(err, CLASSIFICATION_FAILED)
+        }
+        ("classification", "delete") => {
+            let Ok(id) =
+                extract_mandatory_option(&params.options, "-id").map_err(eprint_fwd!("Error"))
+            else {
+                return PARAMETER_ERROR;
+            };
+            let err = classification_delete(&id);
+            success_or_err(err, CLASSIFICATION_FAILED)
+        }
+        ("classification", "dry-run") => {
+            let Ok((condition, o_limit)) = (|| -> anyhow::Result<(String, Option<String>)> {
+                Ok((
+                    extract_mandatory_option(&params.options, "-c")?,
+                    extract_option(&params.options, "-l")?,
+                ))
+            })()
+            .map_err(eprint_fwd!("Error")) else {
+                return PARAMETER_ERROR;
+            };
+            let err = classification_dry_run(&condition, o_limit.as_deref());
+            success_or_err(err, CLASSIFICATION_FAILED)
+        }
         ("file", "upload") => {
             let Ok((item_info, path)) = (|| -> anyhow::Result<(String, String)> {
                 Ok((
@@ -231,6 +649,15 @@ fn dispatch(params: &Params, commands: &[Command]) -> u16 {
             let err = file_info(&file_ref);
             success_or_err(err, FILE_DOWNLOAD_FAILED)
         }
+        ("file", "text") => {
+            let Ok(file_ref) =
+                extract_mandatory_option(&params.options, "-fr").map_err(eprint_fwd!("Error"))
+            else {
+                return PARAMETER_ERROR;
+            };
+            let err = file_text(&file_ref);
+            success_or_err(err, FILE_DOWNLOAD_FAILED)
+        }
         ("file", "list") => {
             let Ok(pattern) = (|| -> anyhow::Result<String> {
                 Ok(extract_mandatory_option(&params.options, "-m")?)
@@ -245,6 +672,38 @@ fn dispatch(params: &Params, commands: &[Command]) -> u16 {
             let err = file_loading();
             success_or_err(err, FILE_DOWNLOAD_FAILED)
         }
+        ("file", "reindex") => {
+            let Ok(o_pattern) =
+                extract_option(&params.options, "-m").map_err(eprint_fwd!("Error"))
+            else {
+                return PARAMETER_ERROR;
+            };
+            let Ok(o_filters) =
+                extract_option(&params.options, "-f").map_err(eprint_fwd!("Error"))
+            else {
+                return PARAMETER_ERROR;
+            };
+            let err = file_reindex(o_pattern.as_deref(), o_filters.as_deref());
+            success_or_err(err, FILE_REINDEX_FAILED)
+        }
+        ("file", "reindex-status") => {
+            let Ok(job) =
+                extract_mandatory_option(&params.options, "-j").map_err(eprint_fwd!("Error"))
+            else {
+                return PARAMETER_ERROR;
+            };
+            let err = file_reindex_status(&job);
+            success_or_err(err, FILE_REINDEX_FAILED)
+        }
+        ("file", "reindex-resume") => {
+            let Ok(job) =
+                extract_mandatory_option(&params.options, "-j").map_err(eprint_fwd!("Error"))
+            else {
+                return PARAMETER_ERROR;
+            };
+            let err = file_reindex_resume(&job);
+            success_or_err(err, FILE_REINDEX_FAILED)
+        }
         (_, _) => SUCCESS,
     }
 }
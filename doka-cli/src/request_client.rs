@@ -13,12 +13,25 @@ use url::Url;
 use commons_error::*;
 use dkdto::error_codes::HTTP_CLIENT_ERROR;
 use dkdto::{
-    AddItemReply, AddItemRequest, AddItemTagReply, AddItemTagRequest, AddKeyReply, AddKeyRequest,
-    AddTagReply, AddTagRequest, CreateCustomerReply, CreateCustomerRequest, CustomerKeyReply,
-    DeleteFullTextRequest, ErrorMessage, FullTextReply, FullTextRequest, GetFileInfoReply,
-    GetFileInfoShortReply, GetItemReply, GetTagReply, ListOfFileInfoReply, ListOfUploadInfoReply,
-    LoginReply, LoginRequest, MediaBytes, OpenSessionReply, OpenSessionRequest, SessionReply,
-    SimpleMessage, TikaMeta, TikaParsing, UploadReply, WebResponse, WebTypeBuilder,
+    AddClassificationRuleReply, AddClassificationRuleRequest, AddDocumentTypeReply,
+    AddDocumentTypeRequest, AddItemCommentReply, AddItemCommentRequest, AddItemReply,
+    AddItemRequest, AddItemTagReply, AddItemTagRequest, AddItemVersionReply, AddItemVersionRequest,
+    AddKeyReply, AddKeyRequest, AddRetentionRuleReply, AddRetentionRuleRequest, AddTagReply,
+    AddTagRequest, ApplyRetentionReply, ApplyRetentionRequest, BulkTagReply, BulkTagRequest,
+    CheckOutReply, CheckOutRequest, ClassifyFileReply, ClassifyFileRequest, CreateCustomerReply,
+    CreateCustomerRequest, CustomerKeyReply, DeleteFullTextRequest, DocumentTextReply,
+    DryRunClassificationReply, DryRunClassificationRequest, ErrorMessage, ExportCsvReply,
+    FileMetadataReply, FullTextChunk, FullTextDictionaryReply, FullTextReply, FullTextSearchReply,
+    FullTextStreamHeader, GetClassificationRuleReply, GetDestructionCertificateReply,
+    GetDocumentTypeReply, GetEnumValuesReply, GetFileInfoReply, GetFileInfoShortReply,
+    GetItemCommentReply, GetItemHistoryReply, GetItemReply, GetItemVersionReply,
+    GetRetentionRuleReply, GetTagReply, GetTagValuesReply, GetTrashReply, GetUserReply,
+    ImportCsvReply, ImportCsvRequest, LegalHoldRequest, ListOfFileInfoReply, ListOfUploadInfoReply,
+    LoginReply, LoginRequest, MediaBytes, OpenSessionReply, OpenSessionRequest, ReindexJobReply,
+    ReindexRequest, SessionReply, SimpleMessage, SuggestedTagsReply, TagStatsReply, TikaMeta,
+    TikaParsing, TrashRetentionReply, TrashRetentionRequest, UpdateEnumValuesRequest,
+    UpdateFullTextDictionaryReply, UpdateFullTextDictionaryRequest, UpdateItemCommentRequest,
+    UploadReply, WebResponse, WebTypeBuilder,
 };
 
 use crate::request_client::TokenType::{Sid, Token};
@@ -51,6 +64,48 @@ impl TokenType {
 }
 const LAPS: u32 = 2_000;
 
+/// Max size of a chunk of text in the body of the full text indexing
+pub(crate) const MAX_TEXT_CHUNK: usize = 65_536;
+
+///
+/// Cut the text in chunks of max_len bytes at most, after a whitespace when possible,
+/// so the words are not split between 2 chunks
+/// [ ( <start>, <end> ) ]
+///
+pub(crate) fn text_chunks(text: &str, max_len: usize) -> Vec<(usize, usize)> {
+    let mut chunks = vec![];
+    let mut start = 0;
+    while start < text.len() {
+        let mut end = usize::min(start + max_len, text.len());
+        if end < text.len() {
+            while !text.is_char_boundary(end) {
+                end -= 1;
+            }
+            match text[start..end].rfind(char::is_whitespace) {
+                Some(pos) if pos > 0 => {
+                    let space = text[start + pos..].chars().next().unwrap_or(' ');
+                    end = start + pos + space.len_utf8();
+                }
+                _ => {}
+            }
+            if end == start {
+                // A char longer than max_len
+                end += text[start..].chars().next().map_or(1, |c| c.len_utf8());
+            }
+        }
+        chunks.push((start, end));
+        start = end;
+    }
+    chunks
+}
+
+/// A line of a NDJSON body
+pub(crate) fn ndjson_line<U: Serialize>(value: &U) -> anyhow::Result<Vec<u8>> {
+    let mut line = serde_json::to_vec(value)?;
+    line.push(b'\n');
+    Ok(line)
+}
+
 #[derive(Clone)]
 struct WebServer {
     server_name: String,
@@ -203,6 +258,26 @@ impl WebServer {
         Self::send_request_builder(request_builder_4)
     }
 
+    /// Post a NDJSON body
+    fn post_ndjson<V: de::DeserializeOwned>(
+        &self,
+        url: &str,
+        body: Vec<u8>,
+        headers: &CustomHeaders,
+    ) -> anyhow::Result<WebResponse<V>> {
+        let request_builder = reqwest::blocking::Client::new()
+            .post(Url::parse(url)?)
+            .timeout(TIMEOUT)
+            .header("Content-Type", "application/x-ndjson");
+        let request_builder_2 = match &headers.token_type {
+            Token(token_value) => request_builder.header("token", token_value.clone()),
+            Sid(sid_value) => request_builder.header("sid", sid_value.clone()),
+            TokenType::None => request_builder,
+        };
+
+        Self::send_request_builder(request_builder_2.body(body))
+    }
+
     fn post_data_retry<U: Serialize, V: de::DeserializeOwned>(
         &self,
         url: &str,
@@ -524,6 +599,15 @@ impl AdminServerClient {
 
         self.server.post_data_retry(&url, request, &headers)
     }
+
+    ///
+    /// The users of the customer of the session
+    ///
+    pub fn get_users(&self, sid: &str) -> WebResponse<GetUserReply> {
+        // http://{}:{}/admin-server/user
+        let url = self.server.build_url("user");
+        self.server.get_data_retry(&url, &Sid(sid.to_string()))
+    }
 }
 
 ///
@@ -588,6 +672,51 @@ impl DocumentServerClient {
         self.server.post_data_retry(&url, request, &headers)
     }
 
+    ///
+    /// Tag changes on all the items matching the filter
+    ///
+    pub fn bulk_tag(&self, request: &BulkTagRequest, sid: &str) -> WebResponse<BulkTagReply> {
+        // http://{}:{}/document-server/item/bulk_tag
+        let url = self.server.build_url("item/bulk_tag");
+
+        let headers = CustomHeaders {
+            token_type: Sid(sid.to_string()),
+            x_request_id: None,
+            cek: None,
+        };
+
+        self.server.post_data_retry(&url, request, &headers)
+    }
+
+    ///
+    /// Set the tag values of the items from a csv sheet
+    ///
+    pub fn import_csv(&self, request: &ImportCsvRequest, sid: &str) -> WebResponse<ImportCsvReply> {
+        // http://{}:{}/document-server/item/import_csv
+        let url = self.server.build_url("item/import_csv");
+
+        let headers = CustomHeaders {
+            token_type: Sid(sid.to_string()),
+            x_request_id: None,
+            cek: None,
+        };
+
+        self.server.post_data_retry(&url, request, &headers)
+    }
+
+    ///
+    /// Items matching the filter as csv
+    ///
+    pub fn export_csv(&self, filters: &str, sid: &str) -> WebResponse<ExportCsvReply> {
+        // http://{}:{}/document-server/item/export_csv?filters=<filters>
+        let end_point = format!(
+            "item/export_csv?filters={0}",
+            utf8_percent_encode(filters, NON_ALPHANUMERIC)
+        );
+        let url = self.server.build_url(&end_point);
+        self.server.get_data_retry(&url, &Sid(sid.to_string()))
+    }
+
     ///
     /// TODO perform URL escaping
     ///
@@ -595,10 +724,17 @@ impl DocumentServerClient {
         &self,
         item_id: i64,
         tag_names: &[String],
+        value_ids: &[i64],
         sid: &str,
     ) -> WebResponse<SimpleMessage> {
-        // http://{}:{}/document-server/item/<item_id>/tags?tag_names=<tag_names>
-        let end_point = format!("item/{0}/tags?tag_names={1}", item_id, tag_names.join(","));
+        // http://{}:{}/document-server/item/<item_id>/tags?tag_names=<tag_names>&value_ids=<value_ids>
+        let value_ids: Vec<String> = value_ids.iter().map(|id| id.to_string()).collect();
+        let end_point = format!(
+            "item/{0}/tags?tag_names={1}&value_ids={2}",
+            item_id,
+            tag_names.join(","),
+            value_ids.join(",")
+        );
         let url = self.server.build_url(&end_point);
         self.server.delete_data_retry(&url, &Sid(sid.to_owned()))
     }
@@ -647,7 +783,607 @@ impl DocumentServerClient {
     }
 
     ///
+    /// Allowed values of an enum tag, possibly restricted to a prefix
+    ///
+    pub fn get_enum_values(
+        &self,
+        tag_id: i64,
+        prefix: Option<&str>,
+        sid: &str,
+    ) -> WebResponse<GetEnumValuesReply> {
+        // http://{}:{}/document-server/tag/<tag_id>/enum?prefix=<prefix>
+        let end_point = match prefix {
+            None => format!("tag/{0}/enum", tag_id),
+            Some(p) => format!(
+                "tag/{0}/enum?prefix={1}",
+                tag_id,
+                utf8_percent_encode(p, NON_ALPHANUMERIC)
+            ),
+        };
+        let url = self.server.build_url(&end_point);
+        self.server.get_data_retry(&url, &Sid(sid.to_string()))
+    }
+
+    ///
+    /// Changes of the tag values of an item, oldest first
+    ///
+    pub fn get_item_history(&self, item_id: i64, sid: &str) -> WebResponse<GetItemHistoryReply> {
+        // http://{}:{}/document-server/item/<item_id>/history
+        let end_point = format!("item/{0}/history", item_id);
+        let url = self.server.build_url(&end_point);
+        self.server.get_data_retry(&url, &Sid(sid.to_string()))
+    }
+
+    ///
+    /// Move an item to the trash
+    ///
+    pub fn trash_item(&self, item_id: i64, sid: &str) -> WebResponse<SimpleMessage> {
+        // http://{}:{}/document-server/item/<item_id>
+        let end_point = format!("item/{0}", item_id);
+        let url = self.server.build_url(&end_point);
+        self.server.delete_data_retry(&url, &Sid(sid.to_owned()))
+    }
+
+    ///
+    /// Items in the trash, most recently trashed first
+    ///
+    pub fn get_trash(&self, sid: &str) -> WebResponse<GetTrashReply> {
+        // http://{}:{}/document-server/trash
+        let url = self.server.build_url("trash");
+        self.server.get_data_retry(&url, &Sid(sid.to_string()))
+    }
+
+    ///
+    /// Bring an item back from the trash
+    ///
+    pub fn restore_item(&self, item_id: i64, sid: &str) -> WebResponse<SimpleMessage> {
+        // http://{}:{}/document-server/trash/<item_id>/restore
+        let end_point = format!("trash/{0}/restore", item_id);
+        let url = self.server.build_url(&end_point);
+
+        let headers = CustomHeaders {
+            token_type: Sid(sid.to_string()),
+            x_request_id: None,
+            cek: None,
+        };
+
+        self.server.post_data_retry(&url, &(), &headers)
+    }
+
+    ///
+    /// Delete for good an item from the trash
+    ///
+    pub fn purge_item(&self, item_id: i64, sid: &str) -> WebResponse<SimpleMessage> {
+        // http://{}:{}/document-server/trash/<item_id>
+        let end_point = format!("trash/{0}", item_id);
+        let url = self.server.build_url(&end_point);
+        self.server.delete_data_retry(&url, &Sid(sid.to_owned()))
+    }
+
+    ///
+    /// Change the number of days the items stay in the trash
+    ///
+    pub fn set_trash_retention(
+        &self,
+        request: &TrashRetentionRequest,
+        sid: &str,
+    ) -> WebResponse<TrashRetentionReply> {
+        // http://{}:{}/document-server/trash/retention
+        let url = self.server.build_url("trash/retention");
+
+        let headers = CustomHeaders {
+            token_type: Sid(sid.to_string()),
+            x_request_id: None,
+            cek: None,
+        };
+
+        self.server.post_data_retry(&url, request, &headers)
+    }
+
+    ///
+    /// Check out an item, the other sessions cannot change it until the check-in
+    ///
+    pub fn check_out_item(
+        &self,
+        item_id: i64,
+        request: &CheckOutRequest,
+        sid: &str,
+    ) -> WebResponse<CheckOutReply> {
+        // http://{}:{}/document-server/item/<item_id>/checkout
+        let end_point = format!("item/{0}/checkout", item_id);
+        let url = self.server.build_url(&end_point);
+
+        let headers = CustomHeaders {
+            token_type: Sid(sid.to_string()),
+            x_request_id: None,
+            cek: None,
+        };
+
+        self.server.post_data_retry(&url, request, &headers)
+    }
+
+    ///
+    /// Check in an item
+    ///
+    pub fn check_in_item(&self, item_id: i64, sid: &str) -> WebResponse<SimpleMessage> {
+        // http://{}:{}/document-server/item/<item_id>/checkin
+        let end_point = format!("item/{0}/checkin", item_id);
+        let url = self.server.build_url(&end_point);
+
+        let headers = CustomHeaders {
+            token_type: Sid(sid.to_string()),
+            x_request_id: None,
+            cek: None,
+        };
+
+        self.server.post_data_retry(&url, &(), &headers)
+    }
+
+    ///
+    /// All the versions of an item, oldest first
+    ///
+    pub fn get_item_versions(&self, item_id: i64, sid: &str) -> WebResponse<GetItemVersionReply> {
+        // http://{}:{}/document-server/item/<item_id>/version
+        let end_point = format!("item/{0}/version", item_id);
+        let url = self.server.build_url(&end_point);
+        self.server.get_data_retry(&url, &Sid(sid.to_string()))
+    }
+
+    ///
+    /// Add a version to an item, the file must be uploaded first
+    ///
+    pub fn add_item_version(
+        &self,
+        item_id: i64,
+        request: &AddItemVersionRequest,
+        sid: &str,
+    ) -> WebResponse<AddItemVersionReply> {
+        // http://{}:{}/document-server/item/<item_id>/version
+        let end_point = format!("item/{0}/version", item_id);
+        let url = self.server.build_url(&end_point);
+
+        let headers = CustomHeaders {
+            token_type: Sid(sid.to_string()),
+            x_request_id: None,
+            cek: None,
+        };
+
+        self.server.post_data_retry(&url, request, &headers)
+    }
+
+    ///
+    /// Make a previous version the current version of the item
+    ///
+    pub fn rollback_item_version(
+        &self,
+        item_id: i64,
+        version_no: i32,
+        sid: &str,
+    ) -> WebResponse<SimpleMessage> {
+        // http://{}:{}/document-server/item/<item_id>/version/<version_no>/rollback
+        let end_point = format!("item/{0}/version/{1}/rollback", item_id, version_no);
+        let url = self.server.build_url(&end_point);
+
+        let headers = CustomHeaders {
+            token_type: Sid(sid.to_string()),
+            x_request_id: None,
+            cek: None,
+        };
+
+        self.server.post_data_retry(&url, &(), &headers)
+    }
+
     ///
+    /// Items whose file contains all the words of the query, with snippets of their text
+    ///
+    pub fn search_fulltext(
+        &self,
+        query: &str,
+        start_page: Option<u32>,
+        page_size: Option<u32>,
+        sid: &str,
+    ) -> WebResponse<FullTextSearchReply> {
+        // http://{}:{}/document-server/search/fulltext?query=<query>&start_page=<start_page>&page_size=<page_size>
+        let mut end_point = format!(
+            "search/fulltext?query={0}",
+            utf8_percent_encode(query, NON_ALPHANUMERIC)
+        );
+        if let Some(p) = start_page {
+            end_point.push_str(&format!("&start_page={}", p));
+        }
+        if let Some(s) = page_size {
+            end_point.push_str(&format!("&page_size={}", s));
+        }
+        let url = self.server.build_url(&end_point);
+        self.server.get_data_retry(&url, &Sid(sid.to_string()))
+    }
+
+    ///
+    /// Stop words and synonym groups of the full text search
+    ///
+    pub fn get_fulltext_dictionary(&self, sid: &str) -> WebResponse<FullTextDictionaryReply> {
+        // http://{}:{}/document-server/search/fulltext/dictionary
+        let url = self.server.build_url("search/fulltext/dictionary");
+        self.server.get_data_retry(&url, &Sid(sid.to_string()))
+    }
+
+    ///
+    /// Replace the stop words and synonym groups of the full text search
+    ///
+    pub fn update_fulltext_dictionary(
+        &self,
+        request: &UpdateFullTextDictionaryRequest,
+        sid: &str,
+    ) -> WebResponse<UpdateFullTextDictionaryReply> {
+        // http://{}:{}/document-server/search/fulltext/dictionary
+        let url = self.server.build_url("search/fulltext/dictionary");
+
+        let headers = CustomHeaders {
+            token_type: Sid(sid.to_string()),
+            x_request_id: None,
+            cek: None,
+        };
+
+        self.server.post_data_retry(&url, request, &headers)
+    }
+
+    ///
+    /// Tag values matching the keywords of the document of an item
+    ///
+    pub fn get_suggested_tags(&self, item_id: i64, sid: &str) -> WebResponse<SuggestedTagsReply> {
+        // http://{}:{}/document-server/item/<item_id>/suggested-tags
+        let end_point = format!("item/{0}/suggested-tags", item_id);
+        let url = self.server.build_url(&end_point);
+        self.server.get_data_retry(&url, &Sid(sid.to_string()))
+    }
+
+    ///
+    /// Clear text extracted from the current file of an item
+    ///
+    pub fn get_item_text(&self, item_id: i64, sid: &str) -> WebResponse<DocumentTextReply> {
+        // http://{}:{}/document-server/item/<item_id>/text
+        let end_point = format!("item/{0}/text", item_id);
+        let url = self.server.build_url(&end_point);
+        self.server.get_data_retry(&url, &Sid(sid.to_string()))
+    }
+
+    ///
+    /// Clear text extracted from a file
+    ///
+    pub fn get_file_text(&self, file_ref: &str, sid: &str) -> WebResponse<DocumentTextReply> {
+        // http://{}:{}/document-server/file/<file_ref>/text
+        let end_point = format!(
+            "file/{0}/text",
+            utf8_percent_encode(file_ref, NON_ALPHANUMERIC)
+        );
+        let url = self.server.build_url(&end_point);
+        self.server.get_data_retry(&url, &Sid(sid.to_string()))
+    }
+
+    ///
+    /// All the comments of an item, oldest first
+    ///
+    pub fn get_item_comments(&self, item_id: i64, sid: &str) -> WebResponse<GetItemCommentReply> {
+        // http://{}:{}/document-server/item/<item_id>/comment
+        let end_point = format!("item/{0}/comment", item_id);
+        let url = self.server.build_url(&end_point);
+        self.server.get_data_retry(&url, &Sid(sid.to_string()))
+    }
+
+    ///
+    /// Comment an item or reply to a comment
+    ///
+    pub fn add_item_comment(
+        &self,
+        item_id: i64,
+        request: &AddItemCommentRequest,
+        sid: &str,
+    ) -> WebResponse<AddItemCommentReply> {
+        // http://{}:{}/document-server/item/<item_id>/comment
+        let end_point = format!("item/{0}/comment", item_id);
+        let url = self.server.build_url(&end_point);
+
+        let headers = CustomHeaders {
+            token_type: Sid(sid.to_string()),
+            x_request_id: None,
+            cek: None,
+        };
+
+        self.server.post_data_retry(&url, request, &headers)
+    }
+
+    ///
+    /// Change the text of a comment, only for its author
+    ///
+    pub fn update_item_comment(
+        &self,
+        item_id: i64,
+        comment_id: i64,
+        request: &UpdateItemCommentRequest,
+        sid: &str,
+    ) -> WebResponse<SimpleMessage> {
+        // http://{}:{}/document-server/item/<item_id>/comment/<comment_id>
+        let end_point = format!("item/{0}/comment/{1}", item_id, comment_id);
+        let url = self.server.build_url(&end_point);
+
+        let headers = CustomHeaders {
+            token_type: Sid(sid.to_string()),
+            x_request_id: None,
+            cek: None,
+        };
+
+        self.server.post_data_retry(&url, request, &headers)
+    }
+
+    ///
+    /// Delete a comment, only for its author
+    ///
+    pub fn delete_item_comment(
+        &self,
+        item_id: i64,
+        comment_id: i64,
+        sid: &str,
+    ) -> WebResponse<SimpleMessage> {
+        // http://{}:{}/document-server/item/<item_id>/comment/<comment_id>
+        let end_point = format!("item/{0}/comment/{1}", item_id, comment_id);
+        let url = self.server.build_url(&end_point);
+        self.server.delete_data_retry(&url, &Sid(sid.to_owned()))
+    }
+
+    ///
+    /// All the retention rules
+    ///
+    pub fn get_all_retention_rule(&self, sid: &str) -> WebResponse<GetRetentionRuleReply> {
+        // http://{}:{}/document-server/retention_rule
+        let url = self.server.build_url("retention_rule");
+        self.server.get_data_retry(&url, &Sid(sid.to_string()))
+    }
+
+    ///
+    /// Create a retention rule
+    ///
+    pub fn add_retention_rule(
+        &self,
+        request: &AddRetentionRuleRequest,
+        sid: &str,
+    ) -> WebResponse<AddRetentionRuleReply> {
+        // http://{}:{}/document-server/retention_rule
+        let url = self.server.build_url("retention_rule");
+
+        let headers = CustomHeaders {
+            token_type: Sid(sid.to_string()),
+            x_request_id: None,
+            cek: None,
+        };
+
+        self.server.post_data_retry(&url, request, &headers)
+    }
+
+    ///
+    /// Delete a retention rule
+    ///
+    pub fn delete_retention_rule(&self, rule_id: i64, sid: &str) -> WebResponse<SimpleMessage> {
+        // http://{}:{}/document-server/retention_rule/<rule_id>
+        let end_point = format!("retention_rule/{0}", rule_id);
+        let url = self.server.build_url(&end_point);
+        self.server.delete_data_retry(&url, &Sid(sid.to_owned()))
+    }
+
+    ///
+    /// Put an item on legal hold or release it
+    ///
+    pub fn set_legal_hold(
+        &self,
+        item_id: i64,
+        request: &LegalHoldRequest,
+        sid: &str,
+    ) -> WebResponse<SimpleMessage> {
+        // http://{}:{}/document-server/item/<item_id>/legal_hold
+        let end_point = format!("item/{0}/legal_hold", item_id);
+        let url = self.server.build_url(&end_point);
+
+        let headers = CustomHeaders {
+            token_type: Sid(sid.to_string()),
+            x_request_id: None,
+            cek: None,
+        };
+
+        self.server.post_data_retry(&url, request, &headers)
+    }
+
+    ///
+    /// Report or destroy the items whose retention period is over
+    ///
+    pub fn apply_retention(
+        &self,
+        request: &ApplyRetentionRequest,
+        sid: &str,
+    ) -> WebResponse<ApplyRetentionReply> {
+        // http://{}:{}/document-server/retention/apply
+        let url = self.server.build_url("retention/apply");
+
+        let headers = CustomHeaders {
+            token_type: Sid(sid.to_string()),
+            x_request_id: None,
+            cek: None,
+        };
+
+        self.server.post_data_retry(&url, request, &headers)
+    }
+
+    ///
+    /// Destruction certificates, most recent first
+    ///
+    pub fn get_destruction_certificates(
+        &self,
+        sid: &str,
+    ) -> WebResponse<GetDestructionCertificateReply> {
+        // http://{}:{}/document-server/retention/certificate
+        let url = self.server.build_url("retention/certificate");
+        self.server.get_data_retry(&url, &Sid(sid.to_string()))
+    }
+
+    ///
+    /// All the classification rules
+    ///
+    pub fn get_all_classification_rule(
+        &self,
+        sid: &str,
+    ) -> WebResponse<GetClassificationRuleReply> {
+        // http://{}:{}/document-server/classification_rule
+        let url = self.server.build_url("classification_rule");
+        self.server.get_data_retry(&url, &Sid(sid.to_string()))
+    }
+
+    ///
+    /// Create a classification rule
+    ///
+    pub fn add_classification_rule(
+        &self,
+        request: &AddClassificationRuleRequest,
+        sid: &str,
+    ) -> WebResponse<AddClassificationRuleReply> {
+        // http://{}:{}/document-server/classification_rule
+        let url = self.server.build_url("classification_rule");
+
+        let headers = CustomHeaders {
+            token_type: Sid(sid.to_string()),
+            x_request_id: None,
+            cek: None,
+        };
+
+        self.server.post_data_retry(&url, request, &headers)
+    }
+
+    ///
+    /// Delete a classification rule
+    ///
+    pub fn delete_classification_rule(
+        &self,
+        rule_id: i64,
+        sid: &str,
+    ) -> WebResponse<SimpleMessage> {
+        // http://{}:{}/document-server/classification_rule/<rule_id>
+        let end_point = format!("classification_rule/{0}", rule_id);
+        let url = self.server.build_url(&end_point);
+        self.server.delete_data_retry(&url, &Sid(sid.to_owned()))
+    }
+
+    ///
+    /// Test a classification condition on the existing documents
+    ///
+    pub fn dry_run_classification(
+        &self,
+        request: &DryRunClassificationRequest,
+        sid: &str,
+    ) -> WebResponse<DryRunClassificationReply> {
+        // http://{}:{}/document-server/classification_rule/dry_run
+        let url = self.server.build_url("classification_rule/dry_run");
+
+        let headers = CustomHeaders {
+            token_type: Sid(sid.to_string()),
+            x_request_id: None,
+            cek: None,
+        };
+
+        self.server.post_data_retry(&url, request, &headers)
+    }
+
+    ///
+    /// Usage statistics of a tag
+    ///
+    pub fn get_tag_stats(&self, tag_id: i64, sid: &str) -> WebResponse<TagStatsReply> {
+        // http://{}:{}/document-server/tag/<tag_id>/stats
+        let end_point = format!("tag/{0}/stats", tag_id);
+        let url = self.server.build_url(&end_point);
+        self.server.get_data_retry(&url, &Sid(sid.to_string()))
+    }
+
+    ///
+    /// Distinct values of a tag with their item count, possibly restricted to a prefix
+    ///
+    pub fn get_tag_values(
+        &self,
+        tag_id: i64,
+        prefix: Option<&str>,
+        sid: &str,
+    ) -> WebResponse<GetTagValuesReply> {
+        // http://{}:{}/document-server/tag/<tag_id>/values?prefix=<prefix>
+        let end_point = match prefix {
+            None => format!("tag/{0}/values", tag_id),
+            Some(p) => format!(
+                "tag/{0}/values?prefix={1}",
+                tag_id,
+                utf8_percent_encode(p, NON_ALPHANUMERIC)
+            ),
+        };
+        let url = self.server.build_url(&end_point);
+        self.server.get_data_retry(&url, &Sid(sid.to_string()))
+    }
+
+    ///
+    ///
+    ///
+    pub fn update_enum_values(
+        &self,
+        tag_id: i64,
+        request: &UpdateEnumValuesRequest,
+        sid: &str,
+    ) -> WebResponse<SimpleMessage> {
+        // http://{}:{}/document-server/tag/<tag_id>/enum
+        let end_point = format!("tag/{0}/enum", tag_id);
+        let url = self.server.build_url(&end_point);
+
+        let headers = CustomHeaders {
+            token_type: Sid(sid.to_string()),
+            x_request_id: None,
+            cek: None,
+        };
+
+        self.server.post_data_retry(&url, request, &headers)
+    }
+
+    ///
+    ///
+    ///
+    pub fn get_all_document_type(&self, sid: &str) -> WebResponse<GetDocumentTypeReply> {
+        let url = self.server.build_url("document_type");
+        self.server.get_data_retry(&url, &Sid(sid.to_string()))
+    }
+
+    ///
+    ///
+    ///
+    pub fn create_document_type(
+        &self,
+        request: &AddDocumentTypeRequest,
+        sid: &str,
+    ) -> WebResponse<AddDocumentTypeReply> {
+        let url = self.server.build_url("document_type");
+
+        let headers = CustomHeaders {
+            token_type: Sid(sid.to_string()),
+            x_request_id: None,
+            cek: None,
+        };
+
+        self.server.post_data_retry(&url, request, &headers)
+    }
+
+    ///
+    ///
+    ///
+    pub fn delete_document_type(
+        &self,
+        document_type_id: i64,
+        sid: &str,
+    ) -> WebResponse<SimpleMessage> {
+        self.server
+            .delete_for_url(document_type_id, "document_type", &Sid(sid.to_owned()))
+    }
+
+    ///
+    /// Send the raw text to index, as a header line followed by the chunks of the text
     ///
     pub fn fulltext_indexing(
         &self,
@@ -656,10 +1392,21 @@ impl DocumentServerClient {
         file_ref: &str,
         sid: &str,
     ) -> WebResponse<FullTextReply> {
-        let request = FullTextRequest {
+        let header = FullTextStreamHeader {
             file_name: file_name.to_owned(),
             file_ref: file_ref.to_owned(),
-            raw_text: raw_text.to_owned(),
+        };
+        let body = (|| -> anyhow::Result<Vec<u8>> {
+            let mut body = ndjson_line(&header)?;
+            for (start, end) in text_chunks(raw_text, MAX_TEXT_CHUNK) {
+                body.extend(ndjson_line(&FullTextChunk {
+                    text: raw_text[start..end].to_owned(),
+                })?);
+            }
+            Ok(body)
+        })();
+        let Ok(body) = body else {
+            return WebResponse::from_errorset(&HTTP_CLIENT_ERROR);
         };
         let url = self.server.build_url("fulltext_indexing");
         let headers = CustomHeaders {
@@ -667,7 +1414,13 @@ impl DocumentServerClient {
             x_request_id: None,
             cek: None,
         };
-        self.server.post_data_retry(&url, &request, &headers)
+        let post_ndjson =
+            || -> anyhow::Result<WebResponse<FullTextReply>> {
+                self.server.post_ndjson(&url, body.clone(), &headers)
+            };
+        self.server
+            .retry(post_ndjson)
+            .unwrap_or_else(|_| WebResponse::from_errorset(&HTTP_CLIENT_ERROR))
     }
 
     ///
@@ -685,6 +1438,23 @@ impl DocumentServerClient {
         };
         self.server.post_data_retry(&url, &request, &headers)
     }
+
+    ///
+    /// Apply the classification rules to a new file
+    ///
+    pub fn classify_file(
+        &self,
+        request: &ClassifyFileRequest,
+        sid: &str,
+    ) -> WebResponse<ClassifyFileReply> {
+        let url = self.server.build_url("classify_file");
+        let headers = CustomHeaders {
+            token_type: TokenType::Sid(sid.to_string()),
+            x_request_id: None,
+            cek: None,
+        };
+        self.server.post_data_retry(&url, request, &headers)
+    }
 }
 
 ///
@@ -779,6 +1549,12 @@ impl FileServerClient {
         self.server.get_data_retry(&url, &Sid(sid.to_string()))
     }
 
+    pub fn metadata(&self, file_ref: &str, sid: &str) -> WebResponse<FileMetadataReply> {
+        // http://{}:{}/file-server/metadata/<file_ref>
+        let url = self.server.build_url_with_refcode("metadata", &file_ref);
+        self.server.get_data_retry(&url, &Sid(sid.to_string()))
+    }
+
     pub fn stats(&self, file_ref: &str, sid: &str) -> WebResponse<GetFileInfoShortReply> {
         // let url = format!("http://{}:{}/file-server/stats/{}", &self.server.server_name, self.server.port);
         let url = self.server.build_url_with_refcode("stats", &file_ref);
@@ -798,6 +1574,52 @@ impl FileServerClient {
         // let url = self.server.build_url("stats/1ABH234");
         self.server.get_data_retry(&url, &Sid(sid.to_string()))
     }
+
+    pub fn release_file(&self, file_ref: &str, sid: &str) -> WebResponse<SimpleMessage> {
+        // http://{}:{}/file-server/release/<file_ref>
+        let url = self.server.build_url_with_refcode("release", &file_ref);
+        self.server.delete_data_retry(&url, &Sid(sid.to_string()))
+    }
+
+    ///
+    /// Start a job to rebuild the full text index of the files matching the pattern
+    ///
+    pub fn start_reindex(
+        &self,
+        request: &ReindexRequest,
+        sid: &str,
+    ) -> WebResponse<ReindexJobReply> {
+        // http://{}:{}/file-server/reindex
+        let url = self.server.build_url("reindex");
+
+        let headers = CustomHeaders {
+            token_type: Sid(sid.to_string()),
+            x_request_id: None,
+            cek: None,
+        };
+
+        self.server.post_data_retry(&url, request, &headers)
+    }
+
+    pub fn get_reindex_job(&self, job_id: i64, sid: &str) -> WebResponse<ReindexJobReply> {
+        // http://{}:{}/file-server/reindex/<job_id>
+        let url = self.server.build_url_with_refcode("reindex", job_id);
+        self.server.get_data_retry(&url, &Sid(sid.to_string()))
+    }
+
+    pub fn resume_reindex(&self, job_id: i64, sid: &str) -> WebResponse<ReindexJobReply> {
+        // http://{}:{}/file-server/reindex/<job_id>/resume
+        let end_point = format!("reindex/{0}/resume", job_id);
+        let url = self.server.build_url(&end_point);
+
+        let headers = CustomHeaders {
+            token_type: Sid(sid.to_string()),
+            x_request_id: None,
+            cek: None,
+        };
+
+        self.server.post_data_retry(&url, &(), &headers)
+    }
 }
 
 #[cfg(test)]
@@ -806,7 +1628,7 @@ mod test {
 
     use dkdto::TikaParsing;
 
-    use crate::request_client::{DocumentServerClient, TikaServerClient};
+    use crate::request_client::{text_chunks, DocumentServerClient, TikaServerClient};
 
     fn put_data(url: &str, request: Vec<u8>) -> anyhow::Result<TikaParsing> {
         let request_builder = reqwest::blocking::Client::new().put(Url::parse(url)?);
@@ -930,4 +1752,25 @@ mod test {
         println!("Original URL: {}", original_url);
         println!("Encoded URL: {}", encoded_url);
     }
+
+    #[test]
+    fn text_chunks_test() {
+        let text = "Le petit chat dort";
+        let chunks: Vec<&str> = text_chunks(text, 8)
+            .into_iter()
+            .map(|(start, end)| &text[start..end])
+            .collect();
+        assert_eq!(vec!["Le ", "petit ", "chat ", "dort"], chunks);
+
+        // No whitespace, the word is cut on a char boundary
+        let text = "éléphant";
+        let chunks: Vec<&str> = text_chunks(text, 3)
+            .into_iter()
+            .map(|(start, end)| &text[start..end])
+            .collect();
+        assert_eq!(text, chunks.concat());
+        assert!(chunks.iter().all(|c| c.len() <= 3));
+
+        assert!(text_chunks("", 8).is_empty());
+    }
 }
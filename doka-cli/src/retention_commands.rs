@@ -0,0 +1,159 @@
+use anyhow::anyhow;
+
+use dkconfig::properties::get_prop_value;
+use dkdto::{AddRetentionRuleRequest, ApplyRetentionRequest};
+use doka_cli::request_client::DocumentServerClient;
+
+use crate::session_commands::read_session_id;
+
+fn document_server_client() -> anyhow::Result<DocumentServerClient> {
+    let server_host = get_prop_value("server.host")?;
+    let document_server_port: u16 = get_prop_value("ds.port")?.parse()?;
+    Ok(DocumentServerClient::new(
+        &server_host,
+        document_server_port,
+    ))
+}
+
+///
+pub(crate) fn retention_list() -> anyhow::Result<()> {
+    println!("👶 Getting the retention rules...");
+
+    let client = document_server_client()?;
+    let sid = read_session_id()?;
+
+    match client.get_all_retention_rule(&sid) {
+        Ok(reply) => {
+            println!("😎 Retention rules : {}", reply.rules.len());
+            println!("id\tname\tstart from\tperiod\tfilters");
+            for rule in reply.rules {
+                println!(
+                    "{}\t{}\t{}\t{}\t{}",
+                    rule.rule_id, &rule.name, &rule.start_from, &rule.period, &rule.filters
+                );
+            }
+            Ok(())
+        }
+        Err(e) => Err(anyhow!("{}", e.message)),
+    }
+}
+
+///
+pub(crate) fn retention_create(
+    name: &str,
+    filters: &str,
+    start_from: &str,
+    period: &str,
+) -> anyhow::Result<()> {
+    println!("👶 Create the retention rule...");
+
+    let client = document_server_client()?;
+    let sid = read_session_id()?;
+
+    let request = AddRetentionRuleRequest {
+        name: name.to_owned(),
+        filters: filters.to_owned(),
+        start_from: start_from.to_owned(),
+        period: period.to_owned(),
+    };
+
+    match client.add_retention_rule(&request, &sid) {
+        Ok(reply) => {
+            println!(
+                "😎 Retention rule successfully created, rule id : {} ",
+                reply.rule_id
+            );
+            Ok(())
+        }
+        Err(e) => Err(anyhow!("{}", e.message)),
+    }
+}
+
+///
+pub(crate) fn retention_delete(id: &str) -> anyhow::Result<()> {
+    println!("👶 Delete the retention rule...");
+
+    let rule_id: i64 = id.parse()?;
+    let client = document_server_client()?;
+    let sid = read_session_id()?;
+
+    match client.delete_retention_rule(rule_id, &sid) {
+        Ok(_reply) => {
+            println!(
+                "😎 Retention rule successfully deleted, rule id : {} ",
+                rule_id
+            );
+            Ok(())
+        }
+        Err(e) => Err(anyhow!("{}", e.message)),
+    }
+}
+
+///
+/// Apply the retention now, the document server also applies it periodically
+///
+pub(crate) fn retention_apply(dry_run: bool) -> anyhow::Result<()> {
+    println!("👶 Apply the retention rules...");
+
+    let client = document_server_client()?;
+    let sid = read_session_id()?;
+
+    match client.apply_retention(
+        &ApplyRetentionRequest {
+            dry_run: Some(dry_run),
+        },
+        &sid,
+    ) {
+        Ok(reply) => {
+            println!(
+                "😎 Expired items : {}, destroyed items : {}",
+                reply.items.len(),
+                reply.destroyed_count
+            );
+            println!("id\tname\trule\texpired\tlegal hold\tdestroyed\terror");
+            for item in reply.items {
+                println!(
+                    "{}\t{}\t{}\t{}\t{}\t{}\t{}",
+                    item.item_id,
+                    &item.name,
+                    &item.rule_name,
+                    &item.expired,
+                    item.legal_hold,
+                    item.destroyed,
+                    item.error.unwrap_or_default()
+                );
+            }
+            Ok(())
+        }
+        Err(e) => Err(anyhow!("{}", e.message)),
+    }
+}
+
+///
+pub(crate) fn retention_certificates() -> anyhow::Result<()> {
+    println!("👶 Getting the destruction certificates...");
+
+    let client = document_server_client()?;
+    let sid = read_session_id()?;
+
+    match client.get_destruction_certificates(&sid) {
+        Ok(reply) => {
+            println!("😎 Destruction certificates : {}", reply.certificates.len());
+            println!("id\titem id\titem name\trule\texpired\tdestroyed\tuser id");
+            for certificate in reply.certificates {
+                println!(
+                    "{}\t{}\t{}\t{}\t{}\t{}\t{}",
+                    certificate.certificate_id,
+                    certificate.item_id,
+                    &certificate.item_name,
+                    &certificate.rule_name,
+                    &certificate.expired,
+                    &certificate.destroyed,
+                    certificate.user_id
+                );
+            }
+            Ok(())
+        }
+        Err(e) => Err(anyhow!("{}", e.message)),
+    }
+}
@@ -0,0 +1,97 @@
+use anyhow::anyhow;
+
+use dkconfig::properties::get_prop_value;
+use dkdto::TrashRetentionRequest;
+use doka_cli::request_client::DocumentServerClient;
+
+use crate::session_commands::read_session_id;
+
+fn document_server_client() -> anyhow::Result<DocumentServerClient> {
+    let server_host = get_prop_value("server.host")?;
+    let document_server_port: u16 = get_prop_value("ds.port")?.parse()?;
+    Ok(DocumentServerClient::new(
+        &server_host,
+        document_server_port,
+    ))
+}
+
+///
+pub(crate) fn trash_list() -> anyhow::Result<()> {
+    println!("👶 Getting the items in the trash...");
+
+    let client = document_server_client()?;
+    let sid = read_session_id()?;
+
+    match client.get_trash(&sid) {
+        Ok(reply) => {
+            println!(
+                "😎 Items in the trash : {}, retention : {} days",
+                reply.items.len(),
+                reply.retention_days
+            );
+            println!("id\tname\ttrashed\tpurge after");
+            for item in reply.items {
+                println!(
+                    "{}\t{}\t{}\t{}",
+                    item.item_id, &item.name, &item.trashed, &item.purge_after
+                );
+            }
+            Ok(())
+        }
+        Err(e) => Err(anyhow!("{}", e.message)),
+    }
+}
+
+///
+pub(crate) fn trash_restore(id: &str) -> anyhow::Result<()> {
+    println!("👶 Restore the item...");
+
+    let item_id: i64 = id.parse()?;
+    let client = document_server_client()?;
+    let sid = read_session_id()?;
+
+    match client.restore_item(item_id, &sid) {
+        Ok(_reply) => {
+            println!("😎 Item successfully restored, item id : {} ", item_id);
+            Ok(())
+        }
+        Err(e) => Err(anyhow!("{}", e.message)),
+    }
+}
+
+///
+pub(crate) fn trash_purge(id: &str) -> anyhow::Result<()> {
+    println!("👶 Purge the item...");
+
+    let item_id: i64 = id.parse()?;
+    let client = document_server_client()?;
+    let sid = read_session_id()?;
+
+    match client.purge_item(item_id, &sid) {
+        Ok(_reply) => {
+            println!("😎 Item successfully purged, item id : {} ", item_id);
+            Ok(())
+        }
+        Err(e) => Err(anyhow!("{}", e.message)),
+    }
+}
+
+///
+pub(crate) fn trash_retention(days: &str) -> anyhow::Result<()> {
+    println!("👶 Set the trash retention period...");
+
+    let retention_days: i32 = days.parse()?;
+    let client = document_server_client()?;
+    let sid = read_session_id()?;
+
+    match client.set_trash_retention(&TrashRetentionRequest { retention_days }, &sid) {
+        Ok(reply) => {
+            println!(
+                "😎 Trash retention successfully set, days : {} ",
+                reply.retention_days
+            );
+            Ok(())
+        }
+        Err(e) => Err(anyhow!("{}", e.message)),
+    }
+}
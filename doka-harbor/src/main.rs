@@ -1,7 +1,7 @@
 use std::net::SocketAddr;
 use std::process::exit;
 
-use axum::extract::Path;
+use axum::extract::{Path, Query};
 use axum::http::Method;
 use axum::response::Html;
 use axum::{routing::get, Router};
@@ -15,6 +15,7 @@ use dkconfig::properties::{get_prop_value, set_prop_values};
 use dkconfig::property_name::{COMMON_EDIBLE_KEY_PROPERTY, LOG_CONFIG_FILE_PROPERTY};
 use dkdto::cbor_type::CborBytes;
 use log::*;
+use serde_derive::Deserialize;
 use tower_http::cors::{Any, CorsLayer};
 use tower_http::services::ServeDir;
 
@@ -55,6 +56,26 @@ async fn search_result() -> CborBytes {
     delegate.search_result().await.into()
 }
 
+#[derive(Deserialize)]
+struct TagValuesQuery {
+    prefix: Option<String>,
+}
+
+/// 🌟 Allowed values of an enum tag, for the filter autocompletion
+///
+/// GET /cbor/tag_values/:tag_id?prefix=
+async fn tag_values(
+    Path(tag_id): Path<i64>,
+    Query(tag_values_query): Query<TagValuesQuery>,
+) -> CborBytes {
+    let session_token = SessionToken { 0: "".to_string() };
+    let delegate = SearchResultComponent::new(session_token, XRequestID::from_value(None));
+    delegate
+        .tag_values(tag_id, tag_values_query.prefix)
+        .await
+        .into()
+}
+
 /// Handler to serve HTML
 async fn serve_html() -> Html<&'static str> {
     Html(
@@ -154,6 +175,7 @@ async fn main() {
         .route("/cbor/get_file/:file_ref", get(get_file))
         .route("/cbor/view_file/:file_ref", get(view_file))
         .route("/cbor/search_result", get(search_result))
+        .route("/cbor/tag_values/:tag_id", get(tag_values))
         // TODO below is a test page to serve a static content
         .route("/index2", get(serve_html))
         .nest_service("/static", ServeDir::new("static"))
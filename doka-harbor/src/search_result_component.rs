@@ -24,7 +24,7 @@ use commons_services::try_or_return;
 use commons_services::x_request_id::{Follower, XRequestID};
 use dkdto::cbor_type::CborType;
 use dkdto::error_codes::INTERNAL_TECHNICAL_ERROR;
-use dkdto::{ErrorSet, GetItemReply, WebType, WebTypeBuilder};
+use dkdto::{ErrorSet, GetEnumValuesReply, GetItemReply, WebType, WebTypeBuilder};
 use doka_cli::async_request_client::{DocumentServerClientAsync, FileServerClientAsync};
 use doka_cli::request_client::TokenType;
 
@@ -231,6 +231,36 @@ impl SearchResultComponent {
         ret
     }
 
+    /// 🌟 Allowed values of an enum tag, to feed the filter autocompletion
+    pub async fn tag_values(
+        &self,
+        tag_id: i64,
+        prefix: Option<String>,
+    ) -> CborType<GetEnumValuesReply> {
+        log_info!("🚀 Start the tag_values API");
+
+        let sid = "no7sunaJVabyGe3-_LkD9inQmrlQYaKhl3v3JCaK4zFiweZSK_YisP6SKEtj3UaIBjO8y1yvOyHFJwHZFRi3EndsOorrVgfENrJu8g";
+        let server_host = "localhost"; // get_prop_value("server.host")?;
+        let document_server_port: u16 = 30070; // get_prop_value("ds.port")?.parse()?;
+
+        let client = DocumentServerClientAsync::new(&server_host, document_server_port);
+        let Ok(enum_values) = client
+            .get_enum_values(tag_id, prefix.as_deref(), &sid)
+            .await
+            .map_err(err_fwd!(
+                "💣 Cannot fetch the tag values, tag_id=[{}], follower=[{}]",
+                tag_id,
+                &self.follower
+            ))
+        else {
+            return CborType::from_errorset(&INTERNAL_TECHNICAL_ERROR);
+        };
+
+        log_info!("🏁 End the tag_values API");
+
+        CborType::from_item(StatusCode::OK.as_u16(), enum_values)
+    }
+
     async fn smart_fetch_original_file(
         &self,
         micro_trans: &str,
@@ -110,6 +110,7 @@ impl MapToHarbor<Option<String>> for EnumTagValue {
             EnumTagValue::SimpleDate(v) => v.as_ref().map(|vv| date_format_fn(&vv)), // Format date using context
             EnumTagValue::DateTime(v) => v.as_ref().map(|vv| date_format_fn(&vv)), // Format datetime using context
             EnumTagValue::Link(v) => v.as_ref().map(|vv| vv.to_string()), // Convert link to string
+            EnumTagValue::Enumeration(v) => v.clone(), // One of the allowed values of the tag
         }
     }
 }
@@ -106,6 +106,8 @@ fn generate_document_server_app_properties(config: &Config, ports: &Ports) -> an
         .replace("{KM_PORT}", &ports.key_manager.to_string())
         .replace("{SM_HOST}", "localhost")
         .replace("{SM_PORT}", &ports.session_manager.to_string())
+        .replace("{FS_HOST}", "localhost")
+        .replace("{FS_PORT}", &ports.file_server.to_string())
         .replace("{TKS_HOST}", "localhost") // TKS is for TIKA Server
         .replace("{TKS_PORT}", &ports.tika_server.to_string())
     };
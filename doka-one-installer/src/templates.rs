@@ -98,10 +98,16 @@ km.port={KM_PORT}
 #Session Manager service
 sm.host={SM_HOST}
 sm.port={SM_PORT}
+#File Server
+fs.host={FS_HOST}
+fs.port={FS_PORT}
 #tika
 tks.host={TKS_HOST}
 tks.port={TKS_PORT}
 
+#Period of the scheduled tasks (trash purge, retention), in minutes
+scheduler.period_minutes=60
+
 #Normalize log configuration path.
 log4rs.config={SERVICE_LOG4RS}
 "#;
@@ -21,7 +21,9 @@ use tokio::task;
 
 use commons_error::*;
 use commons_pg::sql_transaction::{CellValue, SQLDataSet};
-use commons_pg::sql_transaction_async::{SQLChangeAsync, SQLConnectionAsync, SQLQueryBlockAsync};
+use commons_pg::sql_transaction_async::{
+    SQLChangeAsync, SQLConnectionAsync, SQLQueryBlockAsync, SQLTransactionAsync,
+};
 use commons_services::key_lib::fetch_customer_key;
 use commons_services::session_lib::valid_sid_get_session;
 use commons_services::token_lib::SessionToken;
@@ -30,23 +32,21 @@ use commons_services::x_request_id::{Follower, XRequestID};
 use dkconfig::properties::get_prop_value;
 use dkconfig::property_name::{
     DOCUMENT_SERVER_HOSTNAME_PROPERTY, DOCUMENT_SERVER_PORT_PROPERTY,
-    TIKA_SERVER_HOSTNAME_PROPERTY, TIKA_SERVER_PORT_PROPERTY,
 };
 use dkcrypto::dk_crypto::CypherMode::CC20;
 use dkcrypto::dk_crypto::DkEncrypt;
 use dkdto::error_codes::{FILE_INFO_NOT_FOUND, INTERNAL_DATABASE_ERROR, INTERNAL_TECHNICAL_ERROR};
 use dkdto::{
-    DownloadReply, EntrySession, ErrorSet, GetFileInfoReply, GetFileInfoShortReply,
-    ListOfFileInfoReply, ListOfUploadInfoReply, UploadInfoReply, UploadReply, WebType,
-    WebTypeBuilder,
+    ClassifyFileRequest, DownloadReply, EntrySession, ErrorSet, FileMetadataReply,
+    GetFileInfoReply, GetFileInfoShortReply, ListOfFileInfoReply, ListOfUploadInfoReply,
+    SimpleMessage, UploadInfoReply, UploadReply, WebType, WebTypeBuilder,
 };
-use doka_cli::async_request_client::{DocumentServerClientAsync, TikaServerClientAsync};
+use doka_cli::async_request_client::DocumentServerClientAsync;
 use doka_cli::request_client::TokenType;
 
-// use tokio::stream;
+use crate::text_extractor::{extract_text, metadata_value_text, TIKA_CONTENT_META};
 
-const TIKA_CONTENT_META: &str = "X-TIKA:content";
-const CONTENT_TYPE_META: &str = "Content-Type";
+// use tokio::stream;
 
 pub type IndexedParts = HashMap<u32, Vec<u8>>;
 
@@ -487,7 +487,7 @@ impl FileDelegate {
         &self,
         file_id: i64,
         file_ref: &str,
-        _item_info_str: &str,
+        item_info_str: &str,
         block_count: u32,
         customer_code: &str,
         customer_key: &str,
@@ -502,9 +502,15 @@ impl FileDelegate {
             .serial_encrypt(file_id, file_ref, block_count, customer_code, customer_key)
             .await?;
 
-        // Parse the file (Tika)
+        // Parse the file (native extractor or Tika)
         let _r = self
-            .serial_parse_content(file_id, &file_ref, block_count, customer_code)
+            .serial_parse_content(
+                file_id,
+                &file_ref,
+                item_info_str,
+                block_count,
+                customer_code,
+            )
             .await?;
         log_info!(
             "😎 Successful process file for file_ref=[{}], file_id=[{}], follower=[{}]",
@@ -558,13 +564,13 @@ impl FileDelegate {
         };
 
         // Create an entry in file_reference
-        let Ok((file_id, file_ref)) =
-            self.create_file_reference(customer_code)
-                .await
-                .map_err(err_fwd!(
-                    "💣 Cannot create an entry in the file reference table, follower=[{}]",
-                    &self.follower
-                ))
+        let Ok((file_id, file_ref)) = self
+            .create_file_reference(item_info, customer_code)
+            .await
+            .map_err(err_fwd!(
+                "💣 Cannot create an entry in the file reference table, follower=[{}]",
+                &self.follower
+            ))
         else {
             return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
         };
@@ -714,6 +720,7 @@ impl FileDelegate {
         &self,
         file_id: i64,
         file_ref: &str,
+        item_info_str: &str,
         block_count: u32,
         customer_code: &str,
     ) -> anyhow::Result<()> {
@@ -741,7 +748,7 @@ impl FileDelegate {
         let total_size = mem_file.len();
         // Read the metadata and the raw text of the file
         let media_type = self
-            .analyse_entire_content(&file_ref, mem_file, &customer_code)
+            .analyse_entire_content(&file_ref, item_info_str, mem_file, &customer_code)
             .await
             .map_err(tr_fwd!())?;
         // Update the file_reference table : checksum, original_file_size, total_part, media_type
@@ -959,7 +966,7 @@ impl FileDelegate {
     //     Ok(())
     // }
 
-    fn find_document_server_client() -> anyhow::Result<DocumentServerClientAsync> {
+    pub(crate) fn find_document_server_client() -> anyhow::Result<DocumentServerClientAsync> {
         let document_server_host = get_prop_value(DOCUMENT_SERVER_HOSTNAME_PROPERTY)?;
         let document_server_port = get_prop_value(DOCUMENT_SERVER_PORT_PROPERTY)?.parse::<u16>()?;
         Ok(DocumentServerClientAsync::new(
@@ -968,13 +975,14 @@ impl FileDelegate {
         ))
     }
 
-    /// Call the tika server to parse the file and get the text data
+    /// Extract the text data of the file, natively or with the tika server
     /// Insert the metadata
-    /// Call the document server to fulltext parse the text data
+    /// Call the document server to fulltext parse the text data, then to classify the file
     /// return the media type
     async fn analyse_entire_content(
         &self,
         file_ref: &str,
+        item_info: &str,
         mem_file: Vec<u8>,
         customer_code: &str,
     ) -> anyhow::Result<String> {
@@ -984,23 +992,15 @@ impl FileDelegate {
             &self.follower
         );
 
-        let tika_server_host = get_prop_value(TIKA_SERVER_HOSTNAME_PROPERTY)?;
-        let tika_server_port = get_prop_value(TIKA_SERVER_PORT_PROPERTY)?.parse::<u16>()?;
-
         // Get the raw text from the original file
-        let tsc = TikaServerClientAsync::new(&tika_server_host, tika_server_port);
-        let raw_json = tsc
-            .parse_data_json(&mem_file)
+        let mut extracted = extract_text(&mem_file)
             .await
             .map_err(err_fwd!("Cannot parse the original file"))?;
-        let x_tika_content = raw_json[TIKA_CONTENT_META]
-            .as_str()
-            .ok_or(anyhow!("Bad tika content"))?;
-        let content_type = raw_json[CONTENT_TYPE_META]
-            .as_str()
-            .ok_or(anyhow!("Bad content type"))?;
-
-        let metadata = raw_json.as_object().unwrap();
+        // Only the text is needed from now on
+        drop(mem_file);
+        let x_tika_content = &extracted.text;
+        let content_type = &extracted.content_type;
+        let metadata = &extracted.metadata;
         log_info!(
             "Parsing done for file_ref=[{}], content size=[{}], content type=[{}], follower=[{}]",
             file_ref,
@@ -1013,7 +1013,6 @@ impl FileDelegate {
             .insert_metadata(&customer_code, file_ref, &metadata)
             .await?;
 
-        // TODO TikaParsing can contain all the metadata, so keep them and return then instead of getting only the content-type.
         log_info!(
             "Metadata done for file_ref=[{}], follower=[{}]",
             file_ref,
@@ -1025,11 +1024,12 @@ impl FileDelegate {
 
         dbg!(&document_server.read_info());
 
-        // TODO we must also pass the  self.follower.x_request_id + handle the file name
+        // TODO we must also pass the  self.follower.x_request_id
         let wr_reply = document_server
             .fulltext_indexing(
-                &x_tika_content,
-                "no_filename_for_now",
+                // The text is sent as a stream and not kept here
+                std::mem::take(&mut extracted.text),
+                &decode_item_info(item_info),
                 file_ref,
                 &self.follower.token_type.value(),
             )
@@ -1054,6 +1054,36 @@ impl FileDelegate {
             }
         }
 
+        // The classification rules read the text from the document server, the file is kept when they fail
+        let classify_request = ClassifyFileRequest {
+            file_ref: file_ref.to_owned(),
+            // The rules see the same file name as in the dry run
+            file_name: decode_item_info(item_info),
+            media_type: content_type.to_owned(),
+            metadata: extracted.metadata_values(),
+        };
+        match document_server
+            .classify_file(&classify_request, &self.follower.token_type.value())
+            .await
+        {
+            Ok(reply) => {
+                log_info!(
+                    "Classification done, rule_names=[{:?}], item_count=[{}], follower=[{}]",
+                    &reply.rule_names,
+                    reply.item_count,
+                    &self.follower
+                );
+            }
+            Err(e) => {
+                log_warn!(
+                    "⛔ The file cannot be classified, file_ref=[{}], reply=[{:?}], follower=[{}]",
+                    file_ref,
+                    e,
+                    &self.follower
+                );
+            }
+        }
+
         log_info!(
             "... End of parse file content processing, file_ref=[{}], follower=[{}]",
             file_ref,
@@ -1112,7 +1142,7 @@ impl FileDelegate {
     }
 
     //
-    async fn set_file_reference_fulltext_indicator(
+    pub(crate) async fn set_file_reference_fulltext_indicator(
         &self,
         file_ref: &str,
         customer_code: &str,
@@ -1196,6 +1226,122 @@ impl FileDelegate {
         web_type
     }
 
+    ///
+    /// 🌟 Get the metadata of a file [file_ref], as extracted along with its text
+    ///
+    pub async fn file_metadata(&mut self, file_ref: &str) -> WebType<FileMetadataReply> {
+        log_info!(
+            "🚀 Start file_metadata api, file_ref=[{}], follower=[{}]",
+            file_ref,
+            &self.follower
+        );
+
+        let entry_session = try_or_return!(
+            valid_sid_get_session(&self.session_token, &mut self.follower).await,
+            Self::web_type_error()
+        );
+
+        let customer_code = entry_session.customer_code.as_str();
+
+        let Ok(mut cnx) = SQLConnectionAsync::from_pool().await.map_err(err_fwd!(
+            "💣 New Db connection failed, follower=[{}]",
+            &self.follower
+        )) else {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        };
+
+        let Ok(mut trans) = cnx.begin().await.map_err(err_fwd!(
+            "💣 Transaction issue, follower=[{}]",
+            &self.follower
+        )) else {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        };
+
+        let Ok(o_file_metadata) = self
+            .search_file_metadata(&mut trans, file_ref, customer_code)
+            .await
+            .map_err(err_fwd!(
+                "💣 Cannot read the metadata, file_ref=[{}], follower=[{}]",
+                file_ref,
+                &self.follower
+            ))
+        else {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        };
+
+        if trans
+            .commit()
+            .await
+            .map_err(err_fwd!("💣 Commit failed, follower=[{}]", &self.follower))
+            .is_err()
+        {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        }
+
+        let Some(file_metadata) = o_file_metadata else {
+            log_error!(
+                "💣 Missing file, file_ref=[{}], follower=[{}]",
+                file_ref,
+                &self.follower
+            );
+            return WebType::from_errorset(&FILE_INFO_NOT_FOUND);
+        };
+
+        log_info!("🏁 End file_metadata api, follower=[{}]", &self.follower);
+
+        WebType::from_item(StatusCode::OK.as_u16(), file_metadata)
+    }
+
+    /// Media type and metadata of the file, None if the file does not exist
+    async fn search_file_metadata(
+        &self,
+        trans: &mut SQLTransactionAsync<'_>,
+        file_ref: &str,
+        customer_code: &str,
+    ) -> anyhow::Result<Option<FileMetadataReply>> {
+        let mut params = HashMap::new();
+        params.insert("p_file_ref".to_string(), CellValue::from_raw_str(file_ref));
+
+        let query = SQLQueryBlockAsync {
+            sql_query: format!(
+                r"SELECT fr.mime_type, fm.meta_key, fm.value
+                    FROM fs_{0}.file_reference fr
+                    LEFT JOIN fs_{0}.file_metadata fm ON fm.file_reference_id = fr.id
+                    WHERE fr.file_ref = :p_file_ref ",
+                customer_code
+            ),
+            start: 0,
+            length: None,
+            params,
+        };
+
+        let mut sql_result: SQLDataSet = query
+            .execute(trans)
+            .await
+            .map_err(err_fwd!("Query failed, [{}]", &query.sql_query))?;
+
+        let mut o_file_metadata: Option<FileMetadataReply> = None;
+        while sql_result.next() {
+            let file_metadata = o_file_metadata.get_or_insert_with(|| FileMetadataReply {
+                file_ref: file_ref.to_owned(),
+                media_type: sql_result.get_string("mime_type"),
+                metadata: HashMap::new(),
+            });
+            if let (Some(key), Some(value)) = (
+                sql_result.get_string("meta_key"),
+                sql_result.get_string("value"),
+            ) {
+                // The values are stored in json
+                let text = match serde_json::from_str::<Value>(&value) {
+                    Ok(json_value) => metadata_value_text(&json_value),
+                    Err(_) => value,
+                };
+                file_metadata.metadata.insert(key, text);
+            }
+        }
+        Ok(o_file_metadata)
+    }
+
     /// 🌟 Find the files in the system
     pub async fn file_list(&mut self, match_expression: &str) -> WebType<ListOfFileInfoReply> {
         log_info!("🚀 Start file_list api, follower=[{}]", &self.follower);
@@ -1231,7 +1377,7 @@ impl FileDelegate {
         }
     }
 
-    fn is_valid_pattern(s: &str) -> bool {
+    pub(crate) fn is_valid_pattern(s: &str) -> bool {
         s.chars()
             .all(|c| c.is_alphanumeric() || c == '-' || c == '*')
     }
@@ -1630,6 +1776,109 @@ impl FileDelegate {
         wt_stats
     }
 
+    ///
+    /// 🌟 Release the file [file_ref], its parts and metadata are removed for good
+    ///     Releasing an unknown file is not an error
+    ///
+    pub async fn release_file(&mut self, file_ref: &str) -> WebType<SimpleMessage> {
+        log_info!(
+            "🚀 Start release_file api, file_ref=[{}], follower=[{}]",
+            file_ref,
+            &self.follower
+        );
+
+        let entry_session = try_or_return!(
+            valid_sid_get_session(&self.session_token, &mut self.follower).await,
+            Self::web_type_error()
+        );
+
+        let customer_code = entry_session.customer_code.as_str();
+
+        let Ok(mut cnx) = SQLConnectionAsync::from_pool().await.map_err(err_fwd!(
+            "💣 New Db connection failed, follower=[{}]",
+            &self.follower
+        )) else {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        };
+
+        let Ok(mut trans) = cnx.begin().await.map_err(err_fwd!(
+            "💣 Transaction issue, follower=[{}]",
+            &self.follower
+        )) else {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        };
+
+        // The dependent tables first, the file reference last
+        let sql_deletes = [
+            format!(
+                r"DELETE FROM fs_{0}.file_parts WHERE file_reference_id IN
+                        (SELECT id FROM fs_{0}.file_reference WHERE file_ref = :p_file_ref)",
+                customer_code
+            ),
+            format!(
+                r"DELETE FROM fs_{0}.file_metadata WHERE file_reference_id IN
+                        (SELECT id FROM fs_{0}.file_reference WHERE file_ref = :p_file_ref)",
+                customer_code
+            ),
+            format!(
+                "DELETE FROM fs_{}.file_uploads WHERE file_ref = :p_file_ref",
+                customer_code
+            ),
+            format!(
+                "DELETE FROM fs_{}.file_reference WHERE file_ref = :p_file_ref",
+                customer_code
+            ),
+        ];
+
+        for sql_delete in sql_deletes {
+            let mut params = HashMap::new();
+            params.insert("p_file_ref".to_string(), CellValue::from_raw_str(file_ref));
+
+            let query = SQLChangeAsync {
+                sql_query: sql_delete,
+                params,
+                sequence_name: "".to_string(),
+            };
+
+            if query
+                .delete(&mut trans)
+                .await
+                .map_err(err_fwd!(
+                    "💣 Query failed, [{}], follower=[{}]",
+                    &query.sql_query,
+                    &self.follower
+                ))
+                .is_err()
+            {
+                return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+            }
+        }
+
+        if trans
+            .commit()
+            .await
+            .map_err(err_fwd!("💣 Commit failed, follower=[{}]", &self.follower))
+            .is_err()
+        {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        }
+
+        log_info!(
+            "😎 Committed. The file is released, file_ref=[{}], follower=[{}]",
+            file_ref,
+            &self.follower
+        );
+
+        log_info!("🏁 End release_file api, follower=[{}]", &self.follower);
+
+        WebType::from_item(
+            StatusCode::OK.as_u16(),
+            SimpleMessage {
+                message: "Ok".to_string(),
+            },
+        )
+    }
+
     fn download_reply_error() -> impl Fn(&ErrorSet<'static>) -> DownloadReply {
         |e| {
             log_error!("💣 Error after try {:?}", e);
@@ -1733,7 +1982,7 @@ impl FileDelegate {
 
     /// Get all the encrypted parts of the file
     /// ( "application/pdf", {0 : "...", 1: "...", ...} )
-    async fn search_parts(
+    pub(crate) async fn search_parts(
         &self,
         file_ref: &str,
         customer_code: &str,
@@ -2067,7 +2316,12 @@ impl FileDelegate {
         Ok(clear_slides)
     }
 
-    async fn create_file_reference(&self, customer_code: &str) -> anyhow::Result<(i64, String)> {
+    /// The item_info is kept with the file, the reindex gives it again as the file name
+    async fn create_file_reference(
+        &self,
+        item_info: &str,
+        customer_code: &str,
+    ) -> anyhow::Result<(i64, String)> {
         let mut cnx = SQLConnectionAsync::from_pool().await.map_err(tr_fwd!())?;
         let mut trans = cnx.begin().await.map_err(tr_fwd!())?;
 
@@ -2075,8 +2329,8 @@ impl FileDelegate {
 
         let sql_query = format!(
             r"INSERT INTO fs_{}.file_reference
-            ( file_ref, mime_type,  checksum, original_file_size,  encrypted_file_size,  total_part, is_encrypted )
-            VALUES ( :p_file_ref, :p_mime_type, :p_checksum, :p_original_file_size, :p_encrypted_file_size, :p_total_part, false)",
+            ( file_ref, mime_type,  checksum, original_file_size,  encrypted_file_size,  total_part, is_encrypted, item_info )
+            VALUES ( :p_file_ref, :p_mime_type, :p_checksum, :p_original_file_size, :p_encrypted_file_size, :p_total_part, false, :p_item_info)",
             customer_code
         );
 
@@ -2095,6 +2349,10 @@ impl FileDelegate {
         params.insert("p_original_file_size".to_string(), CellValue::Int(None));
         params.insert("p_encrypted_file_size".to_string(), CellValue::Int(None));
         params.insert("p_total_part".to_string(), CellValue::Int32(None));
+        params.insert(
+            "p_item_info".to_string(),
+            CellValue::from_raw_str(item_info),
+        );
 
         let sql_insert = SQLChangeAsync {
             sql_query,
@@ -2259,6 +2517,16 @@ impl FileDelegate {
     }
 }
 
+/// The value of the item_info path segment, ex : the file name
+/// It's Base64Url encoded, the clients sending the plain value are still accepted
+pub(crate) fn decode_item_info(item_info: &str) -> String {
+    base64::engine::general_purpose::URL_SAFE_NO_PAD
+        .decode(item_info)
+        .ok()
+        .and_then(|bytes| String::from_utf8(bytes).ok())
+        .unwrap_or_else(|| item_info.to_owned())
+}
+
 //
 // cargo test file_server_tests  -- --nocapture
 //
@@ -2268,6 +2536,8 @@ mod file_server_tests {
     use std::process::exit;
     use std::sync::Once;
 
+    use crate::file_delegate::decode_item_info;
+
     static INIT: Once = Once::new();
 
     fn init_log() {
@@ -2325,4 +2595,13 @@ mod file_server_tests {
     //     let code = calculate_code(input_text);
     //     println!("{}", code);
     // }
+
+    #[test]
+    fn decode_item_info_test() {
+        // "invoice 2024.pdf"
+        assert_eq!("invoice 2024.pdf", decode_item_info("aW52b2ljZSAyMDI0LnBkZg"));
+        // "facture été.pdf"
+        assert_eq!("facture été.pdf", decode_item_info("ZmFjdHVyZSDDqXTDqS5wZGY"));
+        assert_eq!("bright snow", decode_item_info("bright snow"));
+    }
 }
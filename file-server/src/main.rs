@@ -3,8 +3,8 @@ use std::process::exit;
 
 use axum::extract::{DefaultBodyLimit, Multipart, Path};
 use axum::http::Method;
-use axum::routing::{get, post};
-use axum::Router;
+use axum::routing::{delete, get, post};
+use axum::{Json, Router};
 use log::*;
 use tower_http::cors::{Any, CorsLayer};
 
@@ -17,13 +17,16 @@ use dkconfig::conf_reader::{read_config, read_doka_env};
 use dkconfig::properties::{get_prop_pg_connect_string, get_prop_value, set_prop_values};
 use dkconfig::property_name::{LOG_CONFIG_FILE_PROPERTY, SERVER_PORT_PROPERTY};
 use dkdto::{
-    DownloadReply, GetFileInfoReply, GetFileInfoShortReply, ListOfFileInfoReply,
-    ListOfUploadInfoReply, UploadReply, WebType,
+    DownloadReply, FileMetadataReply, GetFileInfoReply, GetFileInfoShortReply, ListOfFileInfoReply,
+    ListOfUploadInfoReply, ReindexJobReply, ReindexRequest, SimpleMessage, UploadReply, WebType,
 };
 
 use crate::file_delegate::FileDelegate;
+use crate::reindex::ReindexDelegate;
 
 mod file_delegate;
+mod reindex;
+mod text_extractor;
 
 ///
 /// 🌟  Upload the binary content of a file v2
@@ -57,6 +60,19 @@ pub async fn file_info(
     delegate.file_info(&file_ref).await
 }
 
+///
+/// 🌟 Get the metadata of a file [file_ref]
+/// Used from document-server to test the classification rules on the existing files
+///
+// #[get("/metadata/<file_ref>")]
+pub async fn file_metadata(
+    session_token: SessionToken,
+    Path(file_ref): Path<String>,
+) -> WebType<FileMetadataReply> {
+    let mut delegate = FileDelegate::new(session_token, XRequestID::from_value(None));
+    delegate.file_metadata(&file_ref).await
+}
+
 ///
 /// 🌟 Get the information about the loading status of a file [file_ref]
 ///
@@ -89,6 +105,56 @@ pub async fn download(session_token: SessionToken, Path(file_ref): Path<String>)
     delegate.download(&file_ref).await
 }
 
+///
+/// 🌟 Release the file, its parts are removed from the storage
+/// Used from document-server when an item is purged
+///
+// #[delete("/release/<file_ref>")]
+pub async fn release_file(
+    session_token: SessionToken,
+    Path(file_ref): Path<String>,
+) -> WebType<SimpleMessage> {
+    let mut delegate = FileDelegate::new(session_token, XRequestID::from_value(None));
+    delegate.release_file(&file_ref).await
+}
+
+///
+/// 🌟 Start a job to extract again the text of the files and rebuild their full text index
+/// The files are a file reference, a pattern of file references or all the files of the customer
+///
+// #[post("/reindex", format = "application/json", data = "<reindex_request>")]
+pub async fn start_reindex(
+    session_token: SessionToken,
+    reindex_request: Json<ReindexRequest>,
+) -> WebType<ReindexJobReply> {
+    let mut delegate = ReindexDelegate::new(session_token, XRequestID::from_value(None));
+    delegate.start_reindex(reindex_request).await
+}
+
+///
+/// 🌟 Get the progress of a reindex job
+///
+// #[get("/reindex/<job_id>")]
+pub async fn get_reindex_job(
+    session_token: SessionToken,
+    Path(job_id): Path<i64>,
+) -> WebType<ReindexJobReply> {
+    let mut delegate = ReindexDelegate::new(session_token, XRequestID::from_value(None));
+    delegate.get_reindex_job(job_id).await
+}
+
+///
+/// 🌟 Resume a reindex job after the last file it processed
+///
+// #[post("/reindex/<job_id>/resume")]
+pub async fn resume_reindex(
+    session_token: SessionToken,
+    Path(job_id): Path<i64>,
+) -> WebType<ReindexJobReply> {
+    let mut delegate = ReindexDelegate::new(session_token, XRequestID::from_value(None));
+    delegate.resume_reindex(job_id).await
+}
+
 #[derive(Debug)]
 pub struct CORS;
 
@@ -156,6 +222,10 @@ async fn main() {
 
     let _ = init_db_pool_async(&connect_string, db_pool_size).await;
 
+    if let Err(e) = reindex::stop_interrupted_jobs().await {
+        log_error!("💣 Cannot stop the interrupted reindex jobs, e=[{:?}]", e);
+    }
+
     log_info!("🚀 Start {} on port {}", PROGRAM_NAME, port);
 
     let cors = CorsLayer::new()
@@ -175,10 +245,15 @@ async fn main() {
         .route("/upload2/:item_info", post(upload))
         .route("/loading", get(file_loading))
         .route("/info/:file_ref", get(file_info))
+        .route("/metadata/:file_ref", get(file_metadata))
         .route("/stats/:file_ref", get(file_stats))
         .route("/list/:pattern", get(file_list))
         // .route("/raw_download/:file_ref", get(raw_download))
         .route("/download/:file_ref", get(download))
+        .route("/release/:file_ref", delete(release_file))
+        .route("/reindex", post(start_reindex))
+        .route("/reindex/:job_id", get(get_reindex_job))
+        .route("/reindex/:job_id/resume", post(resume_reindex))
         .layer(cors)
         .layer(DefaultBodyLimit::max(usize::MAX));
 
@@ -0,0 +1,974 @@
+use std::collections::HashMap;
+use std::time::SystemTime;
+
+use anyhow::anyhow;
+use axum::http::StatusCode;
+use axum::Json;
+use log::*;
+use serde::de::DeserializeOwned;
+
+use commons_error::*;
+use commons_pg::sql_transaction::{CellValue, SQLDataSet};
+use commons_pg::sql_transaction_async::{SQLChangeAsync, SQLConnectionAsync, SQLQueryBlockAsync};
+use commons_services::key_lib::fetch_customer_key;
+use commons_services::session_lib::valid_sid_get_session;
+use commons_services::token_lib::SessionToken;
+use commons_services::try_or_return;
+use commons_services::x_request_id::{Follower, XRequestID};
+use dkdto::error_codes::{
+    INCORRECT_REINDEX_PATTERN, INTERNAL_DATABASE_ERROR, INTERNAL_TECHNICAL_ERROR,
+    REINDEX_JOB_NOT_FOUND, REINDEX_JOB_RUNNING,
+};
+use dkdto::{
+    ErrorMessage, ErrorSet, ReindexJobReply, ReindexRequest, SimpleMessage, WebResponse, WebType,
+    WebTypeBuilder,
+};
+use doka_cli::request_client::TokenType;
+
+use crate::file_delegate::{decode_item_info, FileDelegate};
+use crate::text_extractor::extract_text;
+
+const STATUS_RUNNING: &str = "RUNNING";
+const STATUS_STOPPED: &str = "STOPPED";
+const STATUS_DONE: &str = "DONE";
+
+/// Number of file references read at once by the job
+const REINDEX_BATCH_SIZE: u32 = 50;
+
+#[derive(Debug, Clone)]
+pub(crate) struct ReindexDelegate {
+    pub session_token: SessionToken,
+    pub follower: Follower,
+}
+
+impl ReindexDelegate {
+    pub fn new(session_token: SessionToken, x_request_id: XRequestID) -> Self {
+        Self {
+            session_token,
+            follower: Follower {
+                x_request_id: x_request_id.new_if_null(),
+                token_type: TokenType::None,
+            },
+        }
+    }
+
+    fn web_type_error<T>() -> impl Fn(&ErrorSet<'static>) -> WebType<T>
+    where
+        T: DeserializeOwned,
+    {
+        |e| {
+            log_error!("💣 Error after try {:?}", e);
+            WebType::from_errorset(e)
+        }
+    }
+
+    fn file_delegate(&self) -> FileDelegate {
+        FileDelegate {
+            session_token: self.session_token.clone(),
+            follower: self.follower.clone(),
+        }
+    }
+
+    ///
+    /// 🌟 Start a job to extract again the text of the files and rebuild their full text index
+    ///
+    /// The job runs in the background, its progress is read with get_reindex_job
+    /// The files are selected by a pattern on their reference and, with a search filter,
+    /// among the files of the items found by the document server when the job starts
+    ///
+    pub async fn start_reindex(
+        &mut self,
+        reindex_request: Json<ReindexRequest>,
+    ) -> WebType<ReindexJobReply> {
+        log_info!("🚀 Start start_reindex api, follower=[{}]", &self.follower);
+
+        let entry_session = try_or_return!(
+            valid_sid_get_session(&self.session_token, &mut self.follower).await,
+            Self::web_type_error()
+        );
+        let customer_code = entry_session.customer_code.as_str();
+
+        let pattern = reindex_request.pattern.clone().unwrap_or("*".to_string());
+        if pattern.is_empty() || !FileDelegate::is_valid_pattern(&pattern) {
+            log_error!(
+                "💣 Incorrect pattern, pattern=[{}], follower=[{}]",
+                &pattern,
+                &self.follower
+            );
+            return WebType::from_errorset(&INCORRECT_REINDEX_PATTERN);
+        }
+
+        let filters = reindex_request
+            .filters
+            .as_deref()
+            .map(str::trim)
+            .filter(|f| !f.is_empty())
+            .map(str::to_owned);
+
+        // The document server knows the items, it gives the files of the filter
+        let file_refs = match &filters {
+            None => vec![],
+            Some(filters) => match self.search_file_refs(filters).await {
+                Ok(file_refs) => file_refs,
+                Err(e) => {
+                    log_error!(
+                        "💣 Cannot find the files of the filter, filters=[{}], message=[{}], follower=[{}]",
+                        filters,
+                        &e.message,
+                        &self.follower
+                    );
+                    return WebType::from_simple(
+                        e.http_error_code,
+                        SimpleMessage { message: e.message },
+                    );
+                }
+            },
+        };
+
+        let Ok(customer_key) = fetch_customer_key(customer_code, &self.follower)
+            .await
+            .map_err(err_fwd!(
+                "💣 Cannot get the customer key, follower=[{}]",
+                &self.follower
+            ))
+        else {
+            return WebType::from_errorset(&INTERNAL_TECHNICAL_ERROR);
+        };
+
+        let Ok(job_id) = self
+            .create_reindex_job(&pattern, filters.as_deref(), &file_refs, customer_code)
+            .await
+            .map_err(err_fwd!(
+                "💣 Cannot create the reindex job, follower=[{}]",
+                &self.follower
+            ))
+        else {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        };
+
+        let job = try_or_return!(
+            self.find_reindex_job(job_id, customer_code).await,
+            Self::web_type_error()
+        );
+
+        self.thread_reindex(job_id, customer_code, &customer_key);
+
+        log_info!(
+            "😎 Reindex job started, job_id=[{}], pattern=[{}], filters=[{:?}], file_count=[{}], follower=[{}]",
+            job_id,
+            &pattern,
+            &filters,
+            job.file_count,
+            &self.follower
+        );
+        log_info!("🏁 End start_reindex api, follower=[{}]", &self.follower);
+
+        WebType::from_item(StatusCode::OK.as_u16(), job)
+    }
+
+    ///
+    /// 🌟 Resume a stopped reindex job after the last file it processed
+    ///
+    /// The job is claimed under a row lock, a RUNNING job is refused
+    /// The jobs interrupted by a restart of the server are STOPPED at startup
+    ///
+    pub async fn resume_reindex(&mut self, job_id: i64) -> WebType<ReindexJobReply> {
+        log_info!(
+            "🚀 Start resume_reindex api, job_id=[{}], follower=[{}]",
+            job_id,
+            &self.follower
+        );
+
+        let entry_session = try_or_return!(
+            valid_sid_get_session(&self.session_token, &mut self.follower).await,
+            Self::web_type_error()
+        );
+        let customer_code = entry_session.customer_code.as_str();
+
+        let Ok(customer_key) = fetch_customer_key(customer_code, &self.follower)
+            .await
+            .map_err(err_fwd!(
+                "💣 Cannot get the customer key, follower=[{}]",
+                &self.follower
+            ))
+        else {
+            return WebType::from_errorset(&INTERNAL_TECHNICAL_ERROR);
+        };
+
+        let Ok(previous_status) =
+            self.claim_reindex_job(job_id, customer_code)
+                .await
+                .map_err(err_fwd!(
+                    "💣 Cannot claim the reindex job, job_id=[{}], follower=[{}]",
+                    job_id,
+                    &self.follower
+                ))
+        else {
+            return WebType::from_errorset(&INTERNAL_DATABASE_ERROR);
+        };
+
+        match previous_status.as_deref() {
+            None => {
+                log_error!(
+                    "💣 Reindex job not found, job_id=[{}], follower=[{}]",
+                    job_id,
+                    &self.follower
+                );
+                return WebType::from_errorset(&REINDEX_JOB_NOT_FOUND);
+            }
+            Some(STATUS_RUNNING) => {
+                log_error!(
+                    "💣 The reindex job is already running, job_id=[{}], follower=[{}]",
+                    job_id,
+                    &self.follower
+                );
+                return WebType::from_errorset(&REINDEX_JOB_RUNNING);
+            }
+            Some(STATUS_STOPPED) => {
+                self.thread_reindex(job_id, customer_code, &customer_key);
+            }
+            Some(_) => {
+                log_info!(
+                    "The job is already done, job_id=[{}], follower=[{}]",
+                    job_id,
+                    &self.follower
+                );
+            }
+        }
+
+        let job = try_or_return!(
+            self.find_reindex_job(job_id, customer_code).await,
+            Self::web_type_error()
+        );
+
+        log_info!("🏁 End resume_reindex api, follower=[{}]", &self.follower);
+
+        WebType::from_item(StatusCode::OK.as_u16(), job)
+    }
+
+    ///
+    /// 🌟 Progress of a reindex job
+    ///
+    pub async fn get_reindex_job(&mut self, job_id: i64) -> WebType<ReindexJobReply> {
+        log_info!(
+            "🚀 Start get_reindex_job api, job_id=[{}], follower=[{}]",
+            job_id,
+            &self.follower
+        );
+
+        let entry_session = try_or_return!(
+            valid_sid_get_session(&self.session_token, &mut self.follower).await,
+            Self::web_type_error()
+        );
+
+        let job = try_or_return!(
+            self.find_reindex_job(job_id, &entry_session.customer_code)
+                .await,
+            Self::web_type_error()
+        );
+
+        log_info!("🏁 End get_reindex_job api, follower=[{}]", &self.follower);
+
+        WebType::from_item(StatusCode::OK.as_u16(), job)
+    }
+
+    fn thread_reindex(&self, job_id: i64, customer_code: &str, customer_key: &str) {
+        let local_self = self.clone();
+        let local_customer_code = String::from(customer_code);
+        let local_customer_key = String::from(customer_key);
+        let _th = tokio::spawn(async move {
+            if let Err(e) = local_self
+                .run_reindex_job(job_id, &local_customer_code, &local_customer_key)
+                .await
+            {
+                log_error!(
+                    "💣 The reindex job stopped, it can be resumed, job_id=[{}], e=[{:?}], follower=[{}]",
+                    job_id,
+                    e,
+                    &local_self.follower
+                );
+                let _ = local_self
+                    .set_job_status(job_id, STATUS_STOPPED, &local_customer_code)
+                    .await
+                    .map_err(err_fwd!(
+                        "💣 Cannot stop the reindex job, job_id=[{}], follower=[{}]",
+                        job_id,
+                        &local_self.follower
+                    ));
+            }
+        });
+    }
+
+    /// Reindex the files of the job, from the last file processed, by batch of file references
+    /// The job row is updated after each file, so the job can be resumed at any time
+    async fn run_reindex_job(
+        &self,
+        job_id: i64,
+        customer_code: &str,
+        customer_key: &str,
+    ) -> anyhow::Result<()> {
+        loop {
+            let (pattern, filtered, last_file_id) =
+                self.read_job_cursor(job_id, customer_code).await?;
+            let files = self
+                .next_file_references(job_id, &pattern, filtered, last_file_id, customer_code)
+                .await?;
+            if files.is_empty() {
+                break;
+            }
+
+            for (file_id, file_ref, item_info) in files {
+                // The item_info of the upload, the files uploaded before it was kept have none
+                let file_name = item_info
+                    .as_deref()
+                    .map(decode_item_info)
+                    .unwrap_or_default();
+                let succeeded = self
+                    .reindex_file(&file_ref, &file_name, customer_code, customer_key)
+                    .await
+                    .map_err(err_fwd!(
+                        "💣 Cannot reindex the file, file_ref=[{}], follower=[{}]",
+                        &file_ref,
+                        &self.follower
+                    ))
+                    .is_ok();
+                self.update_job_progress(job_id, file_id, &file_ref, succeeded, customer_code)
+                    .await?;
+            }
+        }
+
+        self.end_reindex_job(job_id, customer_code).await?;
+        log_info!(
+            "😎 Reindex job done, job_id=[{}], follower=[{}]",
+            job_id,
+            &self.follower
+        );
+        Ok(())
+    }
+
+    /// Decrypt the parts of the file, extract its text again and replace its full text index
+    async fn reindex_file(
+        &self,
+        file_ref: &str,
+        file_name: &str,
+        customer_code: &str,
+        customer_key: &str,
+    ) -> anyhow::Result<()> {
+        log_info!(
+            "Reindex the file, file_ref=[{}], follower=[{}]",
+            file_ref,
+            &self.follower
+        );
+
+        let file_delegate = self.file_delegate();
+        let (_media_type, enc_parts) = file_delegate.search_parts(file_ref, customer_code).await?;
+        let number_of_parts = enc_parts.len();
+        let clear_parts = file_delegate
+            .parallel_decrypt(enc_parts, customer_key)
+            .await?;
+        if clear_parts.len() != number_of_parts {
+            return Err(anyhow!(
+                "Some parts cannot be decrypted, file_ref=[{}]",
+                file_ref
+            ));
+        }
+
+        // The parts are consumed while the file is rebuilt, to hold the content only once
+        let mut clear_parts: Vec<(u32, Vec<u8>)> = clear_parts.into_iter().collect();
+        clear_parts.sort_by_key(|(part_number, _)| *part_number);
+        let mut mem_file: Vec<u8> = vec![];
+        for (_, part) in clear_parts {
+            mem_file.extend(part);
+        }
+
+        // Get the raw text from the original file
+        let extracted = extract_text(&mem_file)
+            .await
+            .map_err(err_fwd!("Cannot parse the original file"))?;
+        drop(mem_file);
+
+        let document_server = FileDelegate::find_document_server_client()
+            .map_err(err_fwd!("Cannot find the document server"))?;
+        let reply = document_server
+            .fulltext_indexing(
+                extracted.text,
+                file_name,
+                file_ref,
+                &self.follower.token_type.value(),
+            )
+            .await
+            .map_err(|e| anyhow!(e.message))?;
+
+        file_delegate
+            .set_file_reference_fulltext_indicator(file_ref, customer_code)
+            .await?;
+
+        log_info!(
+            "😎 File reindexed, file_ref=[{}], number of text parts=[{}], follower=[{}]",
+            file_ref,
+            reply.part_count,
+            &self.follower
+        );
+        Ok(())
+    }
+
+    /// The file references of the items matching the filter, from the document server
+    async fn search_file_refs(&self, filters: &str) -> WebResponse<Vec<String>> {
+        let document_server = FileDelegate::find_document_server_client()
+            .map_err(err_fwd!("Cannot find the document server"))
+            .map_err(ErrorMessage::from)?;
+        let reply = document_server
+            .search_file_refs(filters, &self.follower.token_type.value())
+            .await?;
+        Ok(reply.file_refs)
+    }
+
+    /// With [filters], the job keeps the files found for the filter, it reads them until its end
+    async fn create_reindex_job(
+        &self,
+        pattern: &str,
+        filters: Option<&str>,
+        file_refs: &[String],
+        customer_code: &str,
+    ) -> anyhow::Result<i64> {
+        let mut cnx = SQLConnectionAsync::from_pool().await.map_err(tr_fwd!())?;
+        let mut trans = cnx.begin().await.map_err(tr_fwd!())?;
+
+        let sql_query = format!(
+            r"INSERT INTO fs_{0}.reindex_job
+            ( pattern, filters, status, file_count, done_count, failed_count, last_file_id, start_gmt )
+            VALUES ( :p_pattern, :p_filters, :p_status, 0, 0, 0, 0, :p_start_gmt )",
+            customer_code
+        );
+
+        let sequence_name = format!("fs_{}.reindex_job_id_seq", customer_code);
+
+        let mut params = HashMap::new();
+        params.insert("p_pattern".to_string(), CellValue::from_raw_str(pattern));
+        params.insert(
+            "p_filters".to_string(),
+            CellValue::String(filters.map(str::to_owned)),
+        );
+        params.insert(
+            "p_status".to_string(),
+            CellValue::from_raw_str(STATUS_RUNNING),
+        );
+        params.insert(
+            "p_start_gmt".to_string(),
+            CellValue::from_raw_systemtime(SystemTime::now()),
+        );
+
+        let sql_insert = SQLChangeAsync {
+            sql_query,
+            params,
+            sequence_name,
+        };
+
+        let job_id = sql_insert
+            .insert(&mut trans)
+            .await
+            .map_err(err_fwd!("Insertion failed, follower=[{}]", &self.follower))?;
+
+        if filters.is_some() {
+            let mut params = HashMap::new();
+            params.insert("p_job_id".to_string(), CellValue::from_raw_int(job_id));
+            params.insert(
+                "p_json_file_refs".to_string(),
+                CellValue::from_raw_string(serde_json::to_string(file_refs)?),
+            );
+
+            let sql_insert = SQLChangeAsync {
+                sql_query: format!(
+                    r"INSERT INTO fs_{}.reindex_job_file ( job_id, file_ref )
+                    SELECT DISTINCT :p_job_id, jsonb_array_elements_text( CAST( :p_json_file_refs AS jsonb ) )",
+                    customer_code
+                ),
+                params,
+                sequence_name: "".to_string(),
+            };
+
+            sql_insert
+                .insert_no_pk(&mut trans)
+                .await
+                .map_err(err_fwd!("Insertion failed, follower=[{}]", &self.follower))?;
+        }
+
+        let mut params = HashMap::new();
+        params.insert(
+            "p_sql_pattern".to_string(),
+            CellValue::from_raw_string(pattern.replace('*', "%")),
+        );
+        params.insert("p_job_id".to_string(), CellValue::from_raw_int(job_id));
+
+        let sql_update = SQLChangeAsync {
+            sql_query: format!(
+                r"UPDATE fs_{0}.reindex_job
+                SET file_count = ( SELECT COUNT(*) FROM fs_{0}.file_reference WHERE {1} )
+                WHERE id = :p_job_id",
+                customer_code,
+                job_files_condition(customer_code, filters.is_some())
+            ),
+            params,
+            sequence_name: "".to_string(),
+        };
+
+        sql_update
+            .update(&mut trans)
+            .await
+            .map_err(err_fwd!("Update failed, follower=[{}]", &self.follower))?;
+
+        trans
+            .commit()
+            .await
+            .map_err(err_fwd!("💣 Commit failed, follower=[{}]", &self.follower))?;
+
+        Ok(job_id)
+    }
+
+    async fn find_reindex_job(
+        &self,
+        job_id: i64,
+        customer_code: &str,
+    ) -> Result<ReindexJobReply, &'static ErrorSet<'static>> {
+        let Ok(mut data_set) =
+            self.query_reindex_job(job_id, customer_code)
+                .await
+                .map_err(err_fwd!(
+                    "💣 Cannot read the reindex job, job_id=[{}], follower=[{}]",
+                    job_id,
+                    &self.follower
+                ))
+        else {
+            return Err(&INTERNAL_DATABASE_ERROR);
+        };
+
+        if !data_set.next() {
+            log_error!(
+                "💣 Reindex job not found, job_id=[{}], follower=[{}]",
+                job_id,
+                &self.follower
+            );
+            return Err(&REINDEX_JOB_NOT_FOUND);
+        }
+
+        let Ok(job) = Self::build_reindex_job(&mut data_set).map_err(err_fwd!(
+            "💣 Cannot build the reindex job, follower=[{}]",
+            &self.follower
+        )) else {
+            return Err(&INTERNAL_DATABASE_ERROR);
+        };
+        Ok(job)
+    }
+
+    async fn query_reindex_job(
+        &self,
+        job_id: i64,
+        customer_code: &str,
+    ) -> anyhow::Result<SQLDataSet> {
+        let mut cnx = SQLConnectionAsync::from_pool().await.map_err(tr_fwd!())?;
+        let mut trans = cnx.begin().await.map_err(tr_fwd!())?;
+
+        let sql_query = format!(
+            r"SELECT id, pattern, filters, status, file_count, done_count, failed_count,
+                    last_file_id, last_file_ref, start_gmt, end_gmt
+                FROM fs_{}.reindex_job
+                WHERE id = :p_job_id",
+            customer_code
+        );
+
+        let mut params = HashMap::new();
+        params.insert("p_job_id".to_string(), CellValue::from_raw_int(job_id));
+
+        let query = SQLQueryBlockAsync {
+            sql_query,
+            start: 0,
+            length: Some(1),
+            params,
+        };
+
+        let data_set = query
+            .execute(&mut trans)
+            .await
+            .map_err(err_fwd!("💣 Query failed, follower=[{}]", &self.follower))?;
+
+        trans
+            .commit()
+            .await
+            .map_err(err_fwd!("💣 Commit failed, follower=[{}]", &self.follower))?;
+
+        Ok(data_set)
+    }
+
+    fn build_reindex_job(data_set: &mut SQLDataSet) -> anyhow::Result<ReindexJobReply> {
+        Ok(ReindexJobReply {
+            job_id: data_set.get_int("id").ok_or(anyhow!("Wrong id"))?,
+            pattern: data_set
+                .get_string("pattern")
+                .ok_or(anyhow!("Wrong pattern"))?,
+            filters: data_set.get_string("filters"),
+            status: data_set
+                .get_string("status")
+                .ok_or(anyhow!("Wrong status"))?,
+            file_count: data_set
+                .get_int("file_count")
+                .ok_or(anyhow!("Wrong file_count"))?,
+            done_count: data_set
+                .get_int("done_count")
+                .ok_or(anyhow!("Wrong done_count"))?,
+            failed_count: data_set
+                .get_int("failed_count")
+                .ok_or(anyhow!("Wrong failed_count"))?,
+            last_file_ref: data_set.get_string("last_file_ref"),
+            start_date_time: data_set
+                .get_timestamp_as_datetime("start_gmt")
+                .ok_or(anyhow!("Wrong start_gmt"))?,
+            end_date_time: data_set.get_timestamp_as_datetime("end_gmt"),
+        })
+    }
+
+    /// Lock the job row and move a STOPPED job back to RUNNING
+    /// Give the status of the job before the claim, None if there is no job
+    async fn claim_reindex_job(
+        &self,
+        job_id: i64,
+        customer_code: &str,
+    ) -> anyhow::Result<Option<String>> {
+        let mut cnx = SQLConnectionAsync::from_pool().await.map_err(tr_fwd!())?;
+        let mut trans = cnx.begin().await.map_err(tr_fwd!())?;
+
+        let mut params = HashMap::new();
+        params.insert("p_job_id".to_string(), CellValue::from_raw_int(job_id));
+
+        // The query block appends an OFFSET, the row lock goes in a sub-select
+        let query = SQLQueryBlockAsync {
+            sql_query: format!(
+                r"SELECT status FROM ( SELECT status FROM fs_{}.reindex_job
+                    WHERE id = :p_job_id FOR UPDATE ) j ",
+                customer_code
+            ),
+            start: 0,
+            length: Some(1),
+            params,
+        };
+
+        let mut data_set = query
+            .execute(&mut trans)
+            .await
+            .map_err(err_fwd!("💣 Query failed, follower=[{}]", &self.follower))?;
+
+        if !data_set.next() {
+            return Ok(None);
+        }
+        let status = data_set
+            .get_string("status")
+            .ok_or(anyhow!("Wrong status"))?;
+
+        if status == STATUS_STOPPED {
+            let mut params = HashMap::new();
+            params.insert(
+                "p_status".to_string(),
+                CellValue::from_raw_str(STATUS_RUNNING),
+            );
+            params.insert("p_job_id".to_string(), CellValue::from_raw_int(job_id));
+
+            let sql_update = SQLChangeAsync {
+                sql_query: format!(
+                    r"UPDATE fs_{}.reindex_job SET status = :p_status WHERE id = :p_job_id",
+                    customer_code
+                ),
+                params,
+                sequence_name: "".to_string(),
+            };
+
+            sql_update
+                .update(&mut trans)
+                .await
+                .map_err(err_fwd!("Update failed, follower=[{}]", &self.follower))?;
+        }
+
+        trans
+            .commit()
+            .await
+            .map_err(err_fwd!("💣 Commit failed, follower=[{}]", &self.follower))?;
+
+        Ok(Some(status))
+    }
+
+    async fn set_job_status(
+        &self,
+        job_id: i64,
+        status: &str,
+        customer_code: &str,
+    ) -> anyhow::Result<()> {
+        let mut cnx = SQLConnectionAsync::from_pool().await.map_err(tr_fwd!())?;
+        let mut trans = cnx.begin().await.map_err(tr_fwd!())?;
+
+        let mut params = HashMap::new();
+        params.insert("p_status".to_string(), CellValue::from_raw_str(status));
+        params.insert("p_job_id".to_string(), CellValue::from_raw_int(job_id));
+
+        let sql_update = SQLChangeAsync {
+            sql_query: format!(
+                r"UPDATE fs_{}.reindex_job SET status = :p_status WHERE id = :p_job_id",
+                customer_code
+            ),
+            params,
+            sequence_name: "".to_string(),
+        };
+
+        sql_update
+            .update(&mut trans)
+            .await
+            .map_err(err_fwd!("Update failed, follower=[{}]", &self.follower))?;
+
+        trans
+            .commit()
+            .await
+            .map_err(err_fwd!("💣 Commit failed, follower=[{}]", &self.follower))?;
+
+        Ok(())
+    }
+
+    /// ( <pattern>, <the job has a filter>, <last_file_id> )
+    async fn read_job_cursor(
+        &self,
+        job_id: i64,
+        customer_code: &str,
+    ) -> anyhow::Result<(String, bool, i64)> {
+        let mut data_set = self.query_reindex_job(job_id, customer_code).await?;
+        if !data_set.next() {
+            return Err(anyhow!("Reindex job not found, job_id=[{}]", job_id));
+        }
+        let pattern = data_set
+            .get_string("pattern")
+            .ok_or(anyhow!("Wrong pattern"))?;
+        let filtered = data_set.get_string("filters").is_some();
+        let last_file_id = data_set
+            .get_int("last_file_id")
+            .ok_or(anyhow!("Wrong last_file_id"))?;
+        Ok((pattern, filtered, last_file_id))
+    }
+
+    /// The next files of the job, after the last file processed
+    /// [ ( <file_id>, <file_ref>, <item_info> ) ]
+    async fn next_file_references(
+        &self,
+        job_id: i64,
+        pattern: &str,
+        filtered: bool,
+        last_file_id: i64,
+        customer_code: &str,
+    ) -> anyhow::Result<Vec<(i64, String, Option<String>)>> {
+        let mut cnx = SQLConnectionAsync::from_pool().await.map_err(tr_fwd!())?;
+        let mut trans = cnx.begin().await.map_err(tr_fwd!())?;
+
+        let sql_query = format!(
+            r"SELECT id, file_ref, item_info
+                FROM fs_{}.file_reference
+                WHERE {} AND id > :p_last_file_id
+                ORDER BY id",
+            customer_code,
+            job_files_condition(customer_code, filtered)
+        );
+
+        let mut params = HashMap::new();
+        params.insert(
+            "p_sql_pattern".to_string(),
+            CellValue::from_raw_string(pattern.replace('*', "%")),
+        );
+        params.insert(
+            "p_last_file_id".to_string(),
+            CellValue::from_raw_int(last_file_id),
+        );
+        if filtered {
+            params.insert("p_job_id".to_string(), CellValue::from_raw_int(job_id));
+        }
+
+        let query = SQLQueryBlockAsync {
+            sql_query,
+            start: 0,
+            length: Some(REINDEX_BATCH_SIZE),
+            params,
+        };
+
+        let mut data_set = query
+            .execute(&mut trans)
+            .await
+            .map_err(err_fwd!("💣 Query failed, follower=[{}]", &self.follower))?;
+
+        trans
+            .commit()
+            .await
+            .map_err(err_fwd!("💣 Commit failed, follower=[{}]", &self.follower))?;
+
+        let mut files = vec![];
+        while data_set.next() {
+            let file_id = data_set.get_int("id").ok_or(anyhow!("Wrong id"))?;
+            let file_ref = data_set
+                .get_string("file_ref")
+                .ok_or(anyhow!("Wrong file_ref"))?;
+            files.push((file_id, file_ref, data_set.get_string("item_info")));
+        }
+        Ok(files)
+    }
+
+    /// Move the cursor of the job after the file and count it
+    async fn update_job_progress(
+        &self,
+        job_id: i64,
+        file_id: i64,
+        file_ref: &str,
+        succeeded: bool,
+        customer_code: &str,
+    ) -> anyhow::Result<()> {
+        let mut cnx = SQLConnectionAsync::from_pool().await.map_err(tr_fwd!())?;
+        let mut trans = cnx.begin().await.map_err(tr_fwd!())?;
+
+        let counter = if succeeded {
+            "done_count"
+        } else {
+            "failed_count"
+        };
+        let sql_query = format!(
+            r"UPDATE fs_{0}.reindex_job
+                SET {1} = {1} + 1,
+                    last_file_id = :p_file_id,
+                    last_file_ref = :p_file_ref
+                WHERE id = :p_job_id",
+            customer_code, counter
+        );
+
+        let mut params = HashMap::new();
+        params.insert("p_file_id".to_string(), CellValue::from_raw_int(file_id));
+        params.insert("p_file_ref".to_string(), CellValue::from_raw_str(file_ref));
+        params.insert("p_job_id".to_string(), CellValue::from_raw_int(job_id));
+
+        let sql_update = SQLChangeAsync {
+            sql_query,
+            params,
+            sequence_name: "".to_string(),
+        };
+
+        let _ = sql_update
+            .update(&mut trans)
+            .await
+            .map_err(err_fwd!("Update failed, follower=[{}]", &self.follower))?;
+
+        trans
+            .commit()
+            .await
+            .map_err(err_fwd!("💣 Commit failed, follower=[{}]", &self.follower))?;
+
+        Ok(())
+    }
+
+    async fn end_reindex_job(&self, job_id: i64, customer_code: &str) -> anyhow::Result<()> {
+        let mut cnx = SQLConnectionAsync::from_pool().await.map_err(tr_fwd!())?;
+        let mut trans = cnx.begin().await.map_err(tr_fwd!())?;
+
+        let sql_query = format!(
+            r"UPDATE fs_{}.reindex_job
+                SET status = :p_status, end_gmt = :p_end_gmt
+                WHERE id = :p_job_id",
+            customer_code
+        );
+
+        let mut params = HashMap::new();
+        params.insert("p_status".to_string(), CellValue::from_raw_str(STATUS_DONE));
+        params.insert(
+            "p_end_gmt".to_string(),
+            CellValue::from_raw_systemtime(SystemTime::now()),
+        );
+        params.insert("p_job_id".to_string(), CellValue::from_raw_int(job_id));
+
+        let sql_update = SQLChangeAsync {
+            sql_query,
+            params,
+            sequence_name: "".to_string(),
+        };
+
+        let _ = sql_update
+            .update(&mut trans)
+            .await
+            .map_err(err_fwd!("Update failed, follower=[{}]", &self.follower))?;
+
+        trans
+            .commit()
+            .await
+            .map_err(err_fwd!("💣 Commit failed, follower=[{}]", &self.follower))?;
+
+        Ok(())
+    }
+}
+
+/// The files of a job, the encrypted files of the pattern, since only they have parts to read
+/// With a filter, only the files found for the filter when the job started (:p_job_id)
+fn job_files_condition(customer_code: &str, filtered: bool) -> String {
+    let job_files = if filtered {
+        format!(
+            " AND file_ref IN ( SELECT file_ref FROM fs_{}.reindex_job_file WHERE job_id = :p_job_id )",
+            customer_code
+        )
+    } else {
+        String::new()
+    };
+    format!(
+        "file_ref LIKE :p_sql_pattern AND is_encrypted = true{}",
+        job_files
+    )
+}
+
+/// The jobs run in the server process, so the RUNNING jobs found at startup were interrupted
+/// They are STOPPED, to be resumed on demand
+pub(crate) async fn stop_interrupted_jobs() -> anyhow::Result<()> {
+    let mut cnx = SQLConnectionAsync::from_pool().await.map_err(tr_fwd!())?;
+    let mut trans = cnx.begin().await.map_err(tr_fwd!())?;
+
+    let query = SQLQueryBlockAsync {
+        sql_query: r"SELECT table_schema FROM information_schema.tables
+                    WHERE table_name = 'reindex_job' AND table_schema LIKE 'fs\_%'"
+            .to_string(),
+        start: 0,
+        length: None,
+        params: HashMap::new(),
+    };
+
+    let mut data_set = query
+        .execute(&mut trans)
+        .await
+        .map_err(err_fwd!("💣 Query failed, [{}]", &query.sql_query))?;
+
+    while data_set.next() {
+        let schema = data_set
+            .get_string("table_schema")
+            .ok_or(anyhow!("Wrong schema"))?;
+
+        let mut params = HashMap::new();
+        params.insert(
+            "p_stopped".to_string(),
+            CellValue::from_raw_str(STATUS_STOPPED),
+        );
+        params.insert(
+            "p_running".to_string(),
+            CellValue::from_raw_str(STATUS_RUNNING),
+        );
+
+        let sql_update = SQLChangeAsync {
+            sql_query: format!(
+                r"UPDATE {}.reindex_job SET status = :p_stopped WHERE status = :p_running",
+                schema
+            ),
+            params,
+            sequence_name: "".to_string(),
+        };
+
+        sql_update
+            .update(&mut trans)
+            .await
+            .map_err(err_fwd!("💣 Update failed, schema=[{}]", &schema))?;
+    }
+
+    trans.commit().await.map_err(err_fwd!("💣 Commit failed"))?;
+
+    Ok(())
+}
@@ -0,0 +1,1057 @@
+use std::cmp::min;
+use std::collections::HashMap;
+
+use anyhow::anyhow;
+use axum::async_trait;
+use base64::Engine;
+use log::*;
+use serde_json::{Map, Value};
+
+use commons_error::*;
+use dkconfig::properties::get_prop_value;
+use dkconfig::property_name::{TIKA_SERVER_HOSTNAME_PROPERTY, TIKA_SERVER_PORT_PROPERTY};
+use doka_cli::async_request_client::TikaServerClientAsync;
+
+pub(crate) const TIKA_CONTENT_META: &str = "X-TIKA:content";
+pub(crate) const CONTENT_TYPE_META: &str = "Content-Type";
+
+const TEXT_PLAIN: &str = "text/plain";
+const TEXT_MARKDOWN: &str = "text/markdown";
+const TEXT_HTML: &str = "text/html";
+const TEXT_CSV: &str = "text/csv";
+const TEXT_TSV: &str = "text/tab-separated-values";
+const APPLICATION_JSON: &str = "application/json";
+const APPLICATION_XML: &str = "application/xml";
+const MESSAGE_RFC822: &str = "message/rfc822";
+const APPLICATION_PDF: &str = "application/pdf";
+const APPLICATION_ZIP: &str = "application/zip";
+const APPLICATION_MS_OFFICE: &str = "application/x-tika-msoffice";
+const APPLICATION_OCTET_STREAM: &str = "application/octet-stream";
+
+/// Number of bytes read to sniff the media type
+const SNIFF_LENGTH: usize = 8 * 1024;
+/// Number of lines read to find the delimiter of a csv file
+const SNIFF_CSV_LINES: usize = 10;
+
+const UTF8_BOM: &[u8] = b"\xEF\xBB\xBF";
+
+/// The text of a file and its metadata, the content type is also in the metadata
+pub(crate) struct ExtractedText {
+    pub content_type: String,
+    pub text: String,
+    pub metadata: Map<String, Value>,
+}
+
+impl ExtractedText {
+    fn new(content_type: &str, text: String) -> Self {
+        let mut metadata = Map::new();
+        metadata.insert(
+            CONTENT_TYPE_META.to_string(),
+            Value::String(content_type.to_string()),
+        );
+        Self {
+            content_type: content_type.to_string(),
+            text,
+            metadata,
+        }
+    }
+
+    /// The metadata as plain text, without the content of the file
+    pub(crate) fn metadata_values(&self) -> HashMap<String, String> {
+        self.metadata
+            .iter()
+            .filter(|(key, _)| key.as_str() != TIKA_CONTENT_META)
+            .map(|(key, value)| (key.clone(), metadata_value_text(value)))
+            .collect()
+    }
+}
+
+/// Text of a metadata value, the values of a list are separated by commas
+pub(crate) fn metadata_value_text(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        Value::Array(values) => values
+            .iter()
+            .map(metadata_value_text)
+            .collect::<Vec<_>>()
+            .join(", "),
+        _ => value.to_string(),
+    }
+}
+
+/// Read the text of the files of some media types
+#[async_trait]
+pub(crate) trait TextExtractor: Send + Sync {
+    fn name(&self) -> &'static str;
+    fn accepts(&self, media_type: &str) -> bool;
+    async fn extract(&self, data: &[u8], media_type: &str) -> anyhow::Result<ExtractedText>;
+}
+
+/// The native extractors, Tika reads all the other media types
+fn extractors() -> Vec<Box<dyn TextExtractor>> {
+    vec![
+        Box::new(PlainTextExtractor),
+        Box::new(MarkdownExtractor),
+        Box::new(HtmlExtractor),
+        Box::new(CsvExtractor),
+        Box::new(JsonExtractor),
+        Box::new(XmlExtractor),
+        Box::new(EmlExtractor),
+    ]
+}
+
+///
+/// Text and metadata of the file, the extractor is chosen by the sniffed media type
+/// Tika is used for the office and pdf formats, and when a native extractor fails
+///
+pub(crate) async fn extract_text(data: &[u8]) -> anyhow::Result<ExtractedText> {
+    let media_type = sniff_media_type(data);
+
+    if let Some(extractor) = extractors().iter().find(|e| e.accepts(media_type)) {
+        log_info!(
+            "Native text extraction, media_type=[{}], extractor=[{}]",
+            media_type,
+            extractor.name()
+        );
+        match extractor.extract(data, media_type).await {
+            Ok(extracted) => return Ok(extracted),
+            Err(e) => {
+                log_warn!(
+                    "Native text extraction failed, fallback to Tika, extractor=[{}], e=[{}]",
+                    extractor.name(),
+                    e
+                );
+            }
+        }
+    }
+
+    log_info!("Tika text extraction, media_type=[{}]", media_type);
+    TikaExtractor.extract(data, media_type).await
+}
+
+struct TikaExtractor;
+
+#[async_trait]
+impl TextExtractor for TikaExtractor {
+    fn name(&self) -> &'static str {
+        "tika"
+    }
+
+    fn accepts(&self, _media_type: &str) -> bool {
+        true
+    }
+
+    async fn extract(&self, data: &[u8], _media_type: &str) -> anyhow::Result<ExtractedText> {
+        let tika_server_host = get_prop_value(TIKA_SERVER_HOSTNAME_PROPERTY)?;
+        let tika_server_port = get_prop_value(TIKA_SERVER_PORT_PROPERTY)?.parse::<u16>()?;
+
+        let tsc = TikaServerClientAsync::new(&tika_server_host, tika_server_port);
+        let mut raw_json = tsc
+            .parse_data_json(&data.to_vec())
+            .await
+            .map_err(err_fwd!("Cannot parse the original file"))?;
+        let text = match raw_json[TIKA_CONTENT_META].take() {
+            Value::String(text) => text,
+            _ => return Err(anyhow!("Bad tika content")),
+        };
+        let content_type = raw_json[CONTENT_TYPE_META]
+            .as_str()
+            .ok_or(anyhow!("Bad content type"))?
+            .to_string();
+        let mut metadata = raw_json
+            .as_object()
+            .cloned()
+            .ok_or(anyhow!("Bad tika metadata"))?;
+        metadata.remove(TIKA_CONTENT_META);
+
+        Ok(ExtractedText {
+            content_type,
+            text,
+            metadata,
+        })
+    }
+}
+
+struct PlainTextExtractor;
+
+#[async_trait]
+impl TextExtractor for PlainTextExtractor {
+    fn name(&self) -> &'static str {
+        "plain text"
+    }
+
+    fn accepts(&self, media_type: &str) -> bool {
+        media_type == TEXT_PLAIN
+    }
+
+    async fn extract(&self, data: &[u8], media_type: &str) -> anyhow::Result<ExtractedText> {
+        Ok(ExtractedText::new(media_type, decode_text(data)))
+    }
+}
+
+struct MarkdownExtractor;
+
+#[async_trait]
+impl TextExtractor for MarkdownExtractor {
+    fn name(&self) -> &'static str {
+        "markdown"
+    }
+
+    fn accepts(&self, media_type: &str) -> bool {
+        media_type == TEXT_MARKDOWN
+    }
+
+    async fn extract(&self, data: &[u8], media_type: &str) -> anyhow::Result<ExtractedText> {
+        Ok(ExtractedText::new(
+            media_type,
+            markdown_text(&decode_text(data)),
+        ))
+    }
+}
+
+struct HtmlExtractor;
+
+#[async_trait]
+impl TextExtractor for HtmlExtractor {
+    fn name(&self) -> &'static str {
+        "html"
+    }
+
+    fn accepts(&self, media_type: &str) -> bool {
+        media_type == TEXT_HTML
+    }
+
+    async fn extract(&self, data: &[u8], media_type: &str) -> anyhow::Result<ExtractedText> {
+        Ok(ExtractedText::new(
+            media_type,
+            markup_text(&decode_text(data), true),
+        ))
+    }
+}
+
+struct CsvExtractor;
+
+#[async_trait]
+impl TextExtractor for CsvExtractor {
+    fn name(&self) -> &'static str {
+        "csv"
+    }
+
+    fn accepts(&self, media_type: &str) -> bool {
+        media_type == TEXT_CSV || media_type == TEXT_TSV
+    }
+
+    async fn extract(&self, data: &[u8], media_type: &str) -> anyhow::Result<ExtractedText> {
+        let text = decode_text(data);
+        let delimiter = if media_type == TEXT_TSV {
+            b'\t'
+        } else {
+            sniff_delimiter(&text).unwrap_or(b',')
+        };
+        Ok(ExtractedText::new(media_type, csv_text(&text, delimiter)?))
+    }
+}
+
+struct JsonExtractor;
+
+#[async_trait]
+impl TextExtractor for JsonExtractor {
+    fn name(&self) -> &'static str {
+        "json"
+    }
+
+    fn accepts(&self, media_type: &str) -> bool {
+        media_type == APPLICATION_JSON
+    }
+
+    async fn extract(&self, data: &[u8], media_type: &str) -> anyhow::Result<ExtractedText> {
+        let value: Value = serde_json::from_str(&decode_text(data))?;
+        let mut lines = vec![];
+        json_values(&value, &mut lines);
+        Ok(ExtractedText::new(media_type, lines.join("\n")))
+    }
+}
+
+struct XmlExtractor;
+
+#[async_trait]
+impl TextExtractor for XmlExtractor {
+    fn name(&self) -> &'static str {
+        "xml"
+    }
+
+    fn accepts(&self, media_type: &str) -> bool {
+        media_type == APPLICATION_XML
+    }
+
+    async fn extract(&self, data: &[u8], media_type: &str) -> anyhow::Result<ExtractedText> {
+        Ok(ExtractedText::new(
+            media_type,
+            markup_text(&decode_text(data), false),
+        ))
+    }
+}
+
+struct EmlExtractor;
+
+#[async_trait]
+impl TextExtractor for EmlExtractor {
+    fn name(&self) -> &'static str {
+        "eml"
+    }
+
+    fn accepts(&self, media_type: &str) -> bool {
+        media_type == MESSAGE_RFC822
+    }
+
+    async fn extract(&self, data: &[u8], _media_type: &str) -> anyhow::Result<ExtractedText> {
+        Ok(eml_text(data))
+    }
+}
+
+///
+/// Media type from the first bytes of the file
+/// The binary formats are only told apart to be logged, Tika reads them all
+///
+pub(crate) fn sniff_media_type(data: &[u8]) -> &'static str {
+    let head = &data[..min(data.len(), SNIFF_LENGTH)];
+    if head.starts_with(b"%PDF") {
+        return APPLICATION_PDF;
+    }
+    if head.starts_with(b"PK\x03\x04") {
+        return APPLICATION_ZIP;
+    }
+    if head.starts_with(b"\xD0\xCF\x11\xE0") {
+        return APPLICATION_MS_OFFICE;
+    }
+
+    let Some(text) = sniff_text(head) else {
+        return APPLICATION_OCTET_STREAM;
+    };
+    let start = text.trim_start();
+    let lower_start = start[..min(start.len(), 512)].to_ascii_lowercase();
+
+    if lower_start.starts_with("<!doctype html")
+        || lower_start.starts_with("<html")
+        || (lower_start.starts_with("<?xml") && lower_start.contains("<html"))
+    {
+        return TEXT_HTML;
+    }
+    if lower_start.starts_with("<?xml") || (start.starts_with('<') && start.contains("</")) {
+        return APPLICATION_XML;
+    }
+    if (start.starts_with('{') || start.starts_with('['))
+        && serde_json::from_str::<Value>(&decode_text(data)).is_ok()
+    {
+        return APPLICATION_JSON;
+    }
+    if is_email(text) {
+        return MESSAGE_RFC822;
+    }
+    match sniff_delimiter(text) {
+        Some(b'\t') => return TEXT_TSV,
+        Some(_) => return TEXT_CSV,
+        None => {}
+    }
+    if is_markdown(text) {
+        return TEXT_MARKDOWN;
+    }
+    TEXT_PLAIN
+}
+
+/// The text of the first bytes, None for binary data
+/// The last char may be cut by the sniff length
+fn sniff_text(head: &[u8]) -> Option<&str> {
+    let head = head.strip_prefix(UTF8_BOM).unwrap_or(head);
+    let text = match std::str::from_utf8(head) {
+        Ok(text) => text,
+        Err(e) if e.error_len().is_none() => std::str::from_utf8(&head[..e.valid_up_to()]).ok()?,
+        Err(_) => return None,
+    };
+    let is_binary = text
+        .chars()
+        .any(|c| c.is_control() && !matches!(c, '\t' | '\n' | '\r' | '\x0C'));
+    if is_binary {
+        None
+    } else {
+        Some(text)
+    }
+}
+
+fn decode_text(data: &[u8]) -> String {
+    String::from_utf8_lossy(data.strip_prefix(UTF8_BOM).unwrap_or(data)).into_owned()
+}
+
+/// A mail starts with its headers, some of them are always there
+fn is_email(text: &str) -> bool {
+    const MAIL_HEADERS: [&str; 8] = [
+        "from",
+        "to",
+        "subject",
+        "date",
+        "received",
+        "message-id",
+        "mime-version",
+        "return-path",
+    ];
+    let mut known_headers = 0;
+    for line in text.lines() {
+        if line.is_empty() {
+            break;
+        }
+        if line.starts_with([' ', '\t']) {
+            continue;
+        }
+        let Some((name, _)) = line.split_once(':') else {
+            return false;
+        };
+        if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+            return false;
+        }
+        if MAIL_HEADERS.contains(&name.to_ascii_lowercase().as_str()) {
+            known_headers += 1;
+        }
+    }
+    known_headers >= 2
+}
+
+/// The delimiter found the same number of times, out of the quotes, on each of the first lines
+fn sniff_delimiter(text: &str) -> Option<u8> {
+    let lines: Vec<&str> = text
+        .lines()
+        .filter(|l| !l.trim().is_empty())
+        .take(SNIFF_CSV_LINES + 1)
+        .collect();
+    // The last line may be cut by the sniff length
+    let lines = if lines.len() > SNIFF_CSV_LINES {
+        &lines[..SNIFF_CSV_LINES]
+    } else {
+        &lines[..]
+    };
+    if lines.len() < 2 {
+        return None;
+    }
+
+    [b'\t', b',', b';'].into_iter().find(|&delimiter| {
+        let counts: Vec<usize> = lines
+            .iter()
+            .map(|line| {
+                let mut in_quotes = false;
+                line.bytes()
+                    .filter(|&b| {
+                        if b == b'"' {
+                            in_quotes = !in_quotes;
+                        }
+                        b == delimiter && !in_quotes
+                    })
+                    .count()
+            })
+            .collect();
+        counts[0] > 0 && counts.iter().all(|&c| c == counts[0])
+    })
+}
+
+fn is_markdown(text: &str) -> bool {
+    text.lines().any(|line| {
+        let line = line.trim_start();
+        let heading = line.trim_start_matches('#');
+        (heading.len() < line.len() && line.len() - heading.len() <= 6 && heading.starts_with(' '))
+            || line.starts_with("```")
+            || line.contains("](")
+    })
+}
+
+/// The text of a markdown document, without the marks
+fn markdown_text(markdown: &str) -> String {
+    let mut lines = vec![];
+    for line in markdown.lines() {
+        let mut line = line.trim();
+        if line.starts_with("```") || line.starts_with("~~~") {
+            continue;
+        }
+        // Horizontal rules and the separators of the tables
+        if !line.is_empty()
+            && line
+                .chars()
+                .all(|c| matches!(c, '-' | '*' | '_' | '|' | ':' | ' '))
+        {
+            continue;
+        }
+        while let Some(quoted) = line.strip_prefix('>') {
+            line = quoted.trim_start();
+        }
+        let heading = line.trim_start_matches('#');
+        if heading.len() < line.len() && heading.starts_with(' ') {
+            line = heading.trim_start();
+        }
+        for mark in ["- [ ] ", "- [x] ", "- ", "* ", "+ "] {
+            if let Some(item) = line.strip_prefix(mark) {
+                line = item;
+                break;
+            }
+        }
+        lines.push(markdown_inline(line));
+    }
+    tidy_lines(&lines.join("\n"))
+}
+
+/// Remove the emphasis marks, the links and images keep their text only
+fn markdown_inline(line: &str) -> String {
+    let chars: Vec<char> = line.chars().collect();
+    let mut text = String::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        match c {
+            '!' if chars.get(i + 1) == Some(&'[') => {}
+            '[' => {
+                if let Some((text_end, link_end)) = find_link(&chars, i) {
+                    let link_text: String = chars[i + 1..text_end].iter().collect();
+                    text.push_str(&markdown_inline(&link_text));
+                    i = link_end + 1;
+                    continue;
+                }
+                text.push(c);
+            }
+            '*' | '`' => {}
+            '~' if chars.get(i + 1) == Some(&'~') => {
+                i += 1;
+            }
+            '_' => {
+                // Keep the underscores inside the words
+                let is_inner = i > 0
+                    && chars[i - 1].is_alphanumeric()
+                    && chars.get(i + 1).is_some_and(|n| n.is_alphanumeric());
+                if is_inner {
+                    text.push(c);
+                }
+            }
+            '|' => text.push(' '),
+            _ => text.push(c),
+        }
+        i += 1;
+    }
+    text
+}
+
+/// Position of the ] and the ) of a link [text](url) starting at [start]
+fn find_link(chars: &[char], start: usize) -> Option<(usize, usize)> {
+    let text_end = start + chars[start..].iter().position(|&c| c == ']')?;
+    if chars.get(text_end + 1) != Some(&'(') {
+        return None;
+    }
+    let link_end = text_end + chars[text_end..].iter().position(|&c| c == ')')?;
+    Some((text_end, link_end))
+}
+
+///
+/// The text of a html or xml document
+/// For html, the scripts and styles are left out and the blocks are on their own lines
+///
+fn markup_text(markup: &str, is_html: bool) -> String {
+    const HTML_BLOCKS: [&str; 22] = [
+        "p",
+        "div",
+        "br",
+        "li",
+        "tr",
+        "td",
+        "th",
+        "h1",
+        "h2",
+        "h3",
+        "h4",
+        "h5",
+        "h6",
+        "title",
+        "table",
+        "ul",
+        "ol",
+        "section",
+        "article",
+        "header",
+        "footer",
+        "blockquote",
+    ];
+
+    let mut text = String::new();
+    let mut rest = markup;
+    while let Some(start) = rest.find('<') {
+        text.push_str(&decode_entities(&rest[..start]));
+        rest = &rest[start..];
+
+        if let Some(comment) = rest.strip_prefix("<!--") {
+            rest = comment.find("-->").map_or("", |end| &comment[end + 3..]);
+            continue;
+        }
+        if let Some(cdata) = rest.strip_prefix("<![CDATA[") {
+            let end = cdata.find("]]>").unwrap_or(cdata.len());
+            text.push_str(&cdata[..end]);
+            rest = cdata.get(end + 3..).unwrap_or("");
+            continue;
+        }
+
+        let Some(end) = rest.find('>') else {
+            rest = "";
+            break;
+        };
+        let tag = &rest[1..end];
+        rest = &rest[end + 1..];
+
+        let is_closing = tag.starts_with('/');
+        let name: String = tag
+            .trim_start_matches('/')
+            .chars()
+            .take_while(|c| c.is_ascii_alphanumeric())
+            .collect::<String>()
+            .to_ascii_lowercase();
+
+        if !is_html {
+            // Each element is a field of its own
+            text.push(' ');
+            continue;
+        }
+        if !is_closing && (name == "script" || name == "style") {
+            let closing_tag = format!("</{}", name);
+            rest = match rest.to_ascii_lowercase().find(&closing_tag) {
+                None => "",
+                Some(pos) => rest[pos..].find('>').map_or("", |e| &rest[pos + e + 1..]),
+            };
+            continue;
+        }
+        if HTML_BLOCKS.contains(&name.as_str()) {
+            text.push('\n');
+        }
+    }
+    text.push_str(&decode_entities(rest));
+    tidy_lines(&text)
+}
+
+/// Replace the character references, the unknown entities are kept as they are
+fn decode_entities(text: &str) -> String {
+    let mut decoded = String::new();
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        decoded.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let entity = rest[1..]
+            .find(';')
+            .filter(|&end| end <= 10)
+            .and_then(|end| {
+                let name = &rest[1..end + 1];
+                let c = match name {
+                    "amp" => Some('&'),
+                    "lt" => Some('<'),
+                    "gt" => Some('>'),
+                    "quot" => Some('"'),
+                    "apos" => Some('\''),
+                    "nbsp" => Some(' '),
+                    _ => name
+                        .strip_prefix("#x")
+                        .or_else(|| name.strip_prefix("#X"))
+                        .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+                        .or_else(|| name.strip_prefix('#').and_then(|d| d.parse::<u32>().ok()))
+                        .and_then(char::from_u32),
+                };
+                c.map(|c| (c, end + 2))
+            });
+        match entity {
+            Some((c, len)) => {
+                decoded.push(c);
+                rest = &rest[len..];
+            }
+            None => {
+                decoded.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    decoded.push_str(rest);
+    decoded
+}
+
+/// One line per record, the fields separated by a space
+fn csv_text(text: &str, delimiter: u8) -> anyhow::Result<String> {
+    let mut reader = csv::ReaderBuilder::new()
+        .delimiter(delimiter)
+        .has_headers(false)
+        .flexible(true)
+        .from_reader(text.as_bytes());
+
+    let mut lines = vec![];
+    for record in reader.records() {
+        let record = record?;
+        let fields: Vec<&str> = record
+            .iter()
+            .map(|f| f.trim())
+            .filter(|f| !f.is_empty())
+            .collect();
+        if !fields.is_empty() {
+            lines.push(fields.join(" "));
+        }
+    }
+    Ok(lines.join("\n"))
+}
+
+/// The values of the json document, the keys are left out
+fn json_values(value: &Value, lines: &mut Vec<String>) {
+    match value {
+        Value::Null => {}
+        Value::String(s) => lines.push(s.clone()),
+        Value::Bool(_) | Value::Number(_) => lines.push(value.to_string()),
+        Value::Array(values) => values.iter().for_each(|v| json_values(v, lines)),
+        Value::Object(map) => map.values().for_each(|v| json_values(v, lines)),
+    }
+}
+
+///
+/// The subject and the text of the mail, the attachments are left out
+/// The html body is read only when there is no plain text body
+///
+fn eml_text(data: &[u8]) -> ExtractedText {
+    let message = decode_text(data).replace("\r\n", "\n");
+    let (headers, body) = split_headers(&message);
+
+    let header = |name: &str| -> Option<String> {
+        headers
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, v)| decode_encoded_words(v))
+    };
+
+    let mut bodies = vec![];
+    mime_part_text(&headers, body, &mut bodies);
+    let plain_bodies: Vec<&str> = bodies
+        .iter()
+        .filter(|(is_plain, _)| *is_plain)
+        .map(|(_, t)| t.as_str())
+        .collect();
+    let body_text = if plain_bodies.is_empty() {
+        bodies
+            .iter()
+            .map(|(_, t)| t.as_str())
+            .collect::<Vec<&str>>()
+            .join("\n")
+    } else {
+        plain_bodies.join("\n")
+    };
+
+    let subject = header("subject").unwrap_or_default();
+    let mut extracted = ExtractedText::new(
+        MESSAGE_RFC822,
+        tidy_lines(&format!("{}\n{}", subject, body_text)),
+    );
+    for (meta_key, name) in [
+        ("dc:title", "subject"),
+        ("Message-From", "from"),
+        ("Message-To", "to"),
+        ("dcterms:created", "date"),
+    ] {
+        if let Some(value) = header(name) {
+            extracted
+                .metadata
+                .insert(meta_key.to_string(), Value::String(value));
+        }
+    }
+    extracted
+}
+
+/// The headers with a lower case name, the folded lines are joined, and the rest of the part
+fn split_headers(part: &str) -> (Vec<(String, String)>, &str) {
+    let (head, body) = part.split_once("\n\n").unwrap_or((part, ""));
+    let mut headers: Vec<(String, String)> = vec![];
+    for line in head.lines() {
+        if line.starts_with([' ', '\t']) {
+            if let Some((_, value)) = headers.last_mut() {
+                value.push(' ');
+                value.push_str(line.trim());
+            }
+        } else if let Some((name, value)) = line.split_once(':') {
+            headers.push((name.trim().to_ascii_lowercase(), value.trim().to_string()));
+        }
+    }
+    (headers, body)
+}
+
+/// A parameter of a header value, ex : boundary in multipart/mixed; boundary="xyz"
+fn header_param(value: &str, param: &str) -> Option<String> {
+    value.split(';').skip(1).find_map(|p| {
+        let (name, v) = p.split_once('=')?;
+        (name.trim().eq_ignore_ascii_case(param)).then(|| v.trim().trim_matches('"').to_string())
+    })
+}
+
+/// The text bodies of a mime part, [true] for the plain text ones
+fn mime_part_text(headers: &[(String, String)], body: &str, bodies: &mut Vec<(bool, String)>) {
+    let header = |name: &str| {
+        headers
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, v)| v.as_str())
+    };
+    let content_type = header("content-type").unwrap_or(TEXT_PLAIN);
+    let media_type = content_type
+        .split(';')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_ascii_lowercase();
+
+    if media_type.starts_with("multipart/") {
+        let Some(boundary) = header_param(content_type, "boundary") else {
+            return;
+        };
+        let delimiter = format!("--{}", boundary);
+        // The preamble before the first delimiter is not a part
+        for part in body.split(delimiter.as_str()).skip(1) {
+            if part.starts_with("--") {
+                break;
+            }
+            let (part_headers, part_body) = split_headers(part.trim_start_matches('\n'));
+            mime_part_text(&part_headers, part_body, bodies);
+        }
+        return;
+    }
+
+    let is_attachment = header("content-disposition")
+        .is_some_and(|d| d.to_ascii_lowercase().starts_with("attachment"));
+    if is_attachment || (media_type != TEXT_PLAIN && media_type != TEXT_HTML) {
+        return;
+    }
+
+    let raw = match header("content-transfer-encoding")
+        .map(|e| e.to_ascii_lowercase())
+        .as_deref()
+    {
+        Some("base64") => {
+            let compact: String = body.chars().filter(|c| !c.is_whitespace()).collect();
+            base64::engine::general_purpose::STANDARD
+                .decode(compact)
+                .unwrap_or_else(|_| body.as_bytes().to_vec())
+        }
+        Some("quoted-printable") => decode_quoted_printable(body, false),
+        _ => body.as_bytes().to_vec(),
+    };
+    let text = decode_charset(&raw, header_param(content_type, "charset").as_deref());
+
+    if media_type == TEXT_HTML {
+        bodies.push((false, markup_text(&text, true)));
+    } else {
+        bodies.push((true, text));
+    }
+}
+
+/// [is_header] : the underscores of the encoded words are spaces
+fn decode_quoted_printable(text: &str, is_header: bool) -> Vec<u8> {
+    let bytes = text.as_bytes();
+    let mut decoded = vec![];
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'=' if bytes.get(i + 1) == Some(&b'\n') => i += 1,
+            b'=' => match text
+                .get(i + 1..i + 3)
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+            {
+                Some(b) => {
+                    decoded.push(b);
+                    i += 2;
+                }
+                None => decoded.push(b'='),
+            },
+            b'_' if is_header => decoded.push(b' '),
+            b => decoded.push(b),
+        }
+        i += 1;
+    }
+    decoded
+}
+
+/// The latin charsets map a byte to a char, all the other ones are read as utf-8
+fn decode_charset(bytes: &[u8], charset: Option<&str>) -> String {
+    match charset.map(|c| c.to_ascii_lowercase()).as_deref() {
+        Some("iso-8859-1") | Some("latin1") | Some("windows-1252") | Some("us-ascii") => {
+            bytes.iter().map(|&b| b as char).collect()
+        }
+        _ => String::from_utf8_lossy(bytes).into_owned(),
+    }
+}
+
+/// Decode the encoded words of a header, ex : =?UTF-8?B?w6l0w6k=?=
+fn decode_encoded_words(value: &str) -> String {
+    let mut decoded = String::new();
+    let mut rest = value;
+    while let Some(start) = rest.find("=?") {
+        let word = rest[start + 2..].splitn(3, '?').collect::<Vec<&str>>();
+        let (charset, encoding, encoded_end) = match word[..] {
+            [charset, encoding, tail] if tail.contains("?=") => {
+                (charset, encoding, tail.find("?=").unwrap_or_default())
+            }
+            _ => break,
+        };
+        let encoded = &word[2][..encoded_end];
+        let bytes = match encoding.to_ascii_uppercase().as_str() {
+            "B" => base64::engine::general_purpose::STANDARD
+                .decode(encoded)
+                .ok(),
+            "Q" => Some(decode_quoted_printable(encoded, true)),
+            _ => None,
+        };
+        let Some(bytes) = bytes else {
+            break;
+        };
+        let before = &rest[..start];
+        // The spaces between 2 encoded words are not part of the text
+        if decoded.is_empty() || !before.trim().is_empty() {
+            decoded.push_str(before);
+        }
+        decoded.push_str(&decode_charset(&bytes, Some(charset)));
+        let word_len = 2 + charset.len() + 1 + encoding.len() + 1 + encoded_end + 2;
+        rest = &rest[start + word_len..];
+    }
+    decoded.push_str(rest);
+    decoded
+}
+
+/// The lines without their extra spaces, the blank lines are removed
+fn tidy_lines(text: &str) -> String {
+    text.lines()
+        .map(|line| line.split_whitespace().collect::<Vec<&str>>().join(" "))
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod test {
+    use crate::text_extractor::{
+        csv_text, decode_encoded_words, decode_entities, eml_text, markdown_text, markup_text,
+        metadata_value_text, sniff_media_type,
+    };
+    use serde_json::json;
+
+    #[test]
+    fn sniff_media_type_test() {
+        assert_eq!("application/pdf", sniff_media_type(b"%PDF-1.7 ..."));
+        assert_eq!("application/zip", sniff_media_type(b"PK\x03\x04 ..."));
+        assert_eq!(
+            "application/octet-stream",
+            sniff_media_type(b"\x00\x01\x02")
+        );
+        assert_eq!(
+            "text/plain",
+            sniff_media_type("Le petit chat dort.".as_bytes())
+        );
+        assert_eq!(
+            "text/html",
+            sniff_media_type(b"<!DOCTYPE html><html><body>Hi</body></html>")
+        );
+        assert_eq!(
+            "application/xml",
+            sniff_media_type(b"<?xml version=\"1.0\"?><note>Hi</note>")
+        );
+        assert_eq!("application/json", sniff_media_type(b"{\"title\": \"Hi\"}"));
+        assert_eq!("text/plain", sniff_media_type(b"{ not json"));
+        assert_eq!("text/csv", sniff_media_type(b"name,city\nDenis,Paris\n"));
+        assert_eq!("text/csv", sniff_media_type(b"name;city\nDenis;Paris\n"));
+        assert_eq!(
+            "text/tab-separated-values",
+            sniff_media_type(b"name\tcity\nDenis\tParis\n")
+        );
+        assert_eq!(
+            "message/rfc822",
+            sniff_media_type(b"From: a@doka.one\nSubject: Hi\n\nHello")
+        );
+        assert_eq!("text/markdown", sniff_media_type(b"# Title\n\nSome text"));
+    }
+
+    #[test]
+    fn markdown_text_test() {
+        let markdown = "# The title\n\n> A **bold** quote\n\n- item one\n- see [the doc](http://doka.one)\n\n```\nlet snake_case = 1;\n```\n---\n![logo](logo.png)";
+        assert_eq!(
+            "The title\nA bold quote\nitem one\nsee the doc\nlet snake_case = 1;\nlogo",
+            markdown_text(markdown)
+        );
+    }
+
+    #[test]
+    fn markup_text_test() {
+        let html = "<html><head><title>Report</title><style>p { color: red; }</style></head>\
+            <body><p>Net <b>income</b> &amp; loss</p><script>alert('x')</script>\
+            <!-- comment --><div>L&#233;t&eacute;</div></body></html>";
+        assert_eq!(
+            "Report\nNet income & loss\nLét&eacute;",
+            markup_text(html, true)
+        );
+
+        let xml =
+            "<?xml version=\"1.0\"?><invoice><id>42</id><![CDATA[a < b]]><to>Doka</to></invoice>";
+        assert_eq!("42 a < b Doka", markup_text(xml, false));
+    }
+
+    #[test]
+    fn decode_entities_test() {
+        assert_eq!("a < b & c", decode_entities("a &lt; b &amp; c"));
+        assert_eq!("été", decode_entities("&#xE9;t&#233;"));
+        assert_eq!("R&D &unknown;", decode_entities("R&D &unknown;"));
+    }
+
+    #[test]
+    fn csv_text_test() -> anyhow::Result<()> {
+        assert_eq!(
+            "name city\nDenis Paris, France",
+            csv_text("name,city\nDenis,\"Paris, France\"\n", b',')?
+        );
+        assert_eq!("a b\nc", csv_text("a\tb\nc\t\n", b'\t')?);
+        Ok(())
+    }
+
+    #[test]
+    fn decode_encoded_words_test() {
+        assert_eq!("été", decode_encoded_words("=?UTF-8?B?w6l0w6k=?="));
+        assert_eq!(
+            "Le café",
+            decode_encoded_words("=?ISO-8859-1?Q?Le_caf=E9?=")
+        );
+        assert_eq!(
+            "Re: été chaud",
+            decode_encoded_words("Re: =?UTF-8?Q?=C3=A9t=C3=A9?= =?UTF-8?Q?_chaud?=")
+        );
+        assert_eq!("plain", decode_encoded_words("plain"));
+    }
+
+    #[test]
+    fn eml_text_test() {
+        let eml = "From: Denis <denis@doka.one>\r\n\
+            To: team@doka.one\r\n\
+            Subject: =?UTF-8?B?w6l0w6k=?= report\r\n\
+            Content-Type: multipart/alternative;\r\n boundary=\"b1\"\r\n\
+            \r\n\
+            preamble\r\n\
+            --b1\r\n\
+            Content-Type: text/plain; charset=utf-8\r\n\
+            Content-Transfer-Encoding: quoted-printable\r\n\
+            \r\n\
+            Net income is up by 5=25 =\r\nthis year\r\n\
+            --b1\r\n\
+            Content-Type: text/html\r\n\
+            \r\n\
+            <p>Net income</p>\r\n\
+            --b1--\r\n";
+        let extracted = eml_text(eml.as_bytes());
+        assert_eq!("message/rfc822", extracted.content_type);
+        assert_eq!(
+            "été report\nNet income is up by 5% this year",
+            extracted.text
+        );
+        assert_eq!("été report", extracted.metadata["dc:title"]);
+        assert_eq!("Denis <denis@doka.one>", extracted.metadata["Message-From"]);
+    }
+
+    #[test]
+    fn metadata_value_text_test() {
+        assert_eq!("Denis", metadata_value_text(&json!("Denis")));
+        assert_eq!("12", metadata_value_text(&json!(12)));
+        assert_eq!("en, fr", metadata_value_text(&json!(["en", "fr"])));
+    }
+}